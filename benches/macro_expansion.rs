@@ -0,0 +1,72 @@
+//! Benchmark for expanding user macros.
+//!
+//! Every expansion used to copy the macro body, substitute the arguments
+//! into the copy and push it back through the engine token by token. Bodies
+//! and arguments are now read in place. This expands 10k invocations of a
+//! 3-argument macro and compares the time with tokenizing the same input,
+//! which is unaffected by the change: expansion took about 16x as long as
+//! tokenizing before, and fails here if it is not at least 3x faster now.
+//!
+//! Run with `cargo bench --bench macro_expansion`.
+
+use std::time::{Duration, Instant};
+
+use tylax::core::latex2typst::engine::{tokenize, Engine};
+
+const DEFINITION: &str =
+    r"\newcommand{\score}[3]{\textbf{#1} scored \emph{#2} out of $#3$ points in the final exam}";
+
+const INVOCATIONS: usize = 10_000;
+
+/// Expansion time over tokenizing time before bodies were read in place.
+const RATIO_BEFORE: f64 = 16.0;
+
+/// Required speed-up over `RATIO_BEFORE`.
+const MIN_SPEEDUP: f64 = 3.0;
+
+fn document() -> String {
+    let mut document = String::from(DEFINITION);
+    for i in 0..INVOCATIONS {
+        document.push_str(&format!(r"\score{{Student {}}}{{{}}}{{100}} ", i, i % 100));
+    }
+    document
+}
+
+/// Best of five runs, to keep noise from skewing the ratio.
+fn best_of(mut run: impl FnMut() -> Duration) -> Duration {
+    (0..5).map(|_| run()).min().expect("at least one run")
+}
+
+fn main() {
+    let input = document();
+
+    let tokenize_time = best_of(|| {
+        let start = Instant::now();
+        let tokens = tokenize(&input);
+        let elapsed = start.elapsed();
+        assert!(!tokens.is_empty());
+        elapsed
+    });
+    let expand_time = best_of(|| {
+        let tokens = tokenize(&input);
+        let mut engine = Engine::new().with_max_tokens(usize::MAX / 2);
+        let start = Instant::now();
+        let output = engine.process(tokens);
+        let elapsed = start.elapsed();
+        assert!(!output.is_empty());
+        assert!(engine.take_structured_warnings().is_empty());
+        elapsed
+    });
+    let ratio = expand_time.as_secs_f64() / tokenize_time.as_secs_f64();
+
+    println!(
+        "{} invocations of a 3-argument macro: tokenized in {:?}, expanded in {:?} (ratio {:.2})",
+        INVOCATIONS, tokenize_time, expand_time, ratio
+    );
+    assert!(
+        ratio * MIN_SPEEDUP <= RATIO_BEFORE,
+        "expansion took {:.2}x as long as tokenizing, expected at most {:.2}x",
+        ratio,
+        RATIO_BEFORE / MIN_SPEEDUP
+    );
+}
@@ -0,0 +1,62 @@
+//! Scaling benchmark for very long single-line input.
+//!
+//! Minified LaTeX (no newlines, many macro uses) used to hit quadratic
+//! passes in preprocessing and macro expansion. This converts the same
+//! pathological document at two sizes and fails if a 4x larger input takes
+//! far more than 4x as long.
+//!
+//! Run with `cargo bench --bench preprocessing`.
+
+use std::time::{Duration, Instant};
+
+/// One chunk of the pathological document: macro uses, inline math with
+/// symbols that post-processing touches, protected commands, and a table.
+const CHUNK: &str = r"Set $\R$ and $2\pi + \mathbb{q} + \operatorname{ker} x$ by \LaTeX{} on \today. \begin{tabular}{cc} a & b \\ c & d \end{tabular} ";
+
+/// Allowed growth of the run time for a 4x larger input (linear is 4x).
+const MAX_RATIO: f64 = 8.0;
+
+fn document(chunks: usize) -> String {
+    format!(
+        r"\documentclass{{article}}\newcommand{{\R}}{{\mathbb{{R}}}}\begin{{document}}{}\end{{document}}",
+        CHUNK.repeat(chunks)
+    )
+}
+
+/// Best of three runs, to keep noise from skewing the ratio.
+fn time_conversion(chunks: usize) -> Duration {
+    let input = document(chunks);
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            let output = tylax::latex_document_to_typst(&input);
+            let elapsed = start.elapsed();
+            assert!(!output.is_empty());
+            elapsed
+        })
+        .min()
+        .expect("at least one run")
+}
+
+fn main() {
+    let small = 500;
+    let large = small * 4;
+
+    let small_time = time_conversion(small);
+    let large_time = time_conversion(large);
+    let ratio = large_time.as_secs_f64() / small_time.as_secs_f64();
+
+    println!(
+        "single-line document: {} bytes in {:?}, {} bytes in {:?} (ratio {:.2})",
+        document(small).len(),
+        small_time,
+        document(large).len(),
+        large_time,
+        ratio
+    );
+    assert!(
+        ratio < MAX_RATIO,
+        "conversion time grew {:.2}x for a 4x larger input",
+        ratio
+    );
+}
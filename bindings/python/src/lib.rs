@@ -137,6 +137,26 @@ impl TryFrom<&L2TOptions> for tylax::L2TOptions {
             optimize: py.optimize,
             expand_macros: py.expand_macros,
             preamble,
+            unknown_environment_policy: tylax::UnknownEnvironmentPolicy::ConvertBody,
+            image_extensions: tylax::L2TOptions::default().image_extensions,
+            enable_wrap_package: false,
+            float_barrier_pagebreak: false,
+            double_rule: tylax::DoubleRulePolicy::Thick,
+            todo_style: tylax::TodoStyle::Inline,
+            sideways_style: tylax::SidewaysStyle::Rotate,
+            math_passthrough: false,
+            line_ending: tylax::LineEnding::Lf,
+            emit_metadata: false,
+            metadata_commands: tylax::L2TOptions::default().metadata_commands,
+            data_tables: tylax::DataTableMode::Materialize,
+            heading_offset: 0,
+            auto_normalize_headings: false,
+            max_heading_level: 4,
+            cetz_version: tylax::tikz::CetzVersion::V0_3,
+            keep_trailing_content: false,
+            hayagriva_bibliography: false,
+            preserve_macros_as_let: false,
+            progress: None,
         })
     }
 }
@@ -221,6 +241,22 @@ fn build_t2l_options(document: bool, py: Option<&T2LOptions>) -> PyResult<tylax:
         author: py.and_then(|o| o.author.clone()),
         block_math_mode: py.map(|o| o.block_math_mode).unwrap_or(true),
         wrapper,
+        eval_inline_code: true,
+        use_autoref: false,
+        use_enumitem: false,
+        ascii_accents: false,
+        keep_unicode: false,
+        use_physics_package: false,
+        tcolorbox_blocks: false,
+        code_backend: tylax::CodeBackend::Listings,
+        unknown_rule_policy: tylax::UnknownEnvironmentPolicy::ConvertBody,
+        line_ending: tylax::LineEnding::Lf,
+        materialize_data: false,
+        place_backend: tylax::PlaceBackend::Textpos,
+        heading_offset: 0,
+        inline_math_delim: tylax::InlineMathDelim::Dollar,
+        display_math_delim: tylax::DisplayMathDelim::Brackets,
+        progress: None,
     })
 }
 
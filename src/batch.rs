@@ -393,24 +393,33 @@ fn check_collisions(plans: &[PlannedFile]) -> Result<(), BatchError> {
 
 fn convert_one(plan: &PlannedFile, options: &BatchOptions) -> io::Result<()> {
     let content = fs::read_to_string(&plan.input_path)?;
-    let converted = match plan.direction {
+    let converted = convert_source(&content, plan.direction, options);
+
+    if let Some(parent) = plan.output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&plan.output_path, converted)
+}
+
+/// Convert one source in a resolved direction with the options of a batch
+pub(crate) fn convert_source(
+    content: &str,
+    direction: BatchDirection,
+    options: &BatchOptions,
+) -> String {
+    match direction {
         BatchDirection::LatexToTypst => {
             if options.full_document {
-                latex_document_to_typst_with_options(&content, &options.l2t_options)
+                latex_document_to_typst_with_options(content, &options.l2t_options)
             } else {
-                latex_to_typst_with_options(&content, &options.l2t_options)
+                latex_to_typst_with_options(content, &options.l2t_options)
             }
         }
         BatchDirection::TypstToLatex => {
             let mut t2l_options = options.t2l_options.clone();
             t2l_options.full_document = options.full_document;
-            typst_to_latex_with_options(&content, &t2l_options)
+            typst_to_latex_with_options(content, &t2l_options)
         }
         BatchDirection::Auto => unreachable!("auto direction must be resolved before conversion"),
-    };
-
-    if let Some(parent) = plan.output_path.parent() {
-        fs::create_dir_all(parent)?;
     }
-    fs::write(&plan.output_path, converted)
 }
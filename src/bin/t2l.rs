@@ -7,14 +7,19 @@ use std::fs;
 #[cfg(feature = "cli")]
 use std::io::{self, Read, Write};
 #[cfg(feature = "cli")]
+use std::path::Path;
+#[cfg(feature = "cli")]
+use std::sync::Arc;
+#[cfg(feature = "cli")]
 use tylax::{
     batch::{convert_batch, BatchDirection, BatchFileStatus, BatchOptions},
     convert_auto, convert_auto_document, detect_format,
     diagnostics::{check_latex, format_diagnostics},
-    latex_document_to_typst, latex_to_typst, latex_to_typst_with_diagnostics_options,
+    diff_outputs,
+    files::StdFileResolver,
     tikz::{convert_cetz_to_tikz, convert_tikz_to_cetz, is_cetz_code},
     typst_document_to_latex, typst_to_latex, typst_to_latex_with_diagnostics, CliDiagnostic,
-    DocumentWrapperMode, L2TOptions, PreambleMode, T2LOptions,
+    DocumentWrapperMode, L2TOptions, LatexConverter, OutputFormat, PreambleMode, T2LOptions,
 };
 
 #[cfg(feature = "cli")]
@@ -169,10 +174,36 @@ enum Commands {
         exclude: Vec<String>,
     },
 
+    /// Compare two converted outputs block by block, ignoring whitespace and comments
+    Diff {
+        /// Previous output
+        old: String,
+
+        /// New output
+        new: String,
+
+        /// Format of both outputs (detected from extension or content by default)
+        #[arg(long, value_enum)]
+        format: Option<DiffFormat>,
+
+        /// Only report blocks whose similarity is at or below this value
+        #[arg(long, default_value_t = 1.0)]
+        threshold: f64,
+    },
+
     /// Show version and feature info
     Info,
 }
 
+#[cfg(feature = "cli")]
+#[derive(Clone, ValueEnum)]
+enum DiffFormat {
+    /// LaTeX output
+    Latex,
+    /// Typst output
+    Typst,
+}
+
 #[cfg(feature = "cli")]
 #[derive(Clone, ValueEnum)]
 enum TikzDirection {
@@ -278,7 +309,9 @@ fn main() -> io::Result<()> {
                 preamble: preamble_mode,
                 ..Default::default()
             };
-            let conv_result = latex_to_typst_with_diagnostics_options(&input, l2t_options);
+            let conv_result = LatexConverter::with_options(l2t_options)
+                .with_file_resolver(input_file_resolver(filename.as_deref()))
+                .convert_document_with_diagnostics(&input);
             let diags = conv_result
                 .warnings
                 .into_iter()
@@ -436,13 +469,17 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
 
             let result = if full_document {
                 match direction {
-                    Direction::L2t => latex_document_to_typst(&content),
+                    Direction::L2t => LatexConverter::new()
+                        .with_file_resolver(input_file_resolver(filename.as_deref()))
+                        .convert_document(&content),
                     Direction::T2l => typst_document_to_latex(&content),
                     Direction::Auto => convert_auto_document(&content).0,
                 }
             } else {
                 match direction {
-                    Direction::L2t => latex_to_typst(&content),
+                    Direction::L2t => LatexConverter::new()
+                        .with_file_resolver(input_file_resolver(filename.as_deref()))
+                        .convert_document(&content),
                     Direction::T2l => typst_to_latex(&content),
                     Direction::Auto => convert_auto(&content).0,
                 }
@@ -549,6 +586,52 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             }
         }
 
+        Commands::Diff {
+            old,
+            new,
+            format,
+            threshold,
+        } => {
+            let old_content = fs::read_to_string(&old)?;
+            let new_content = fs::read_to_string(&new)?;
+
+            let format = match format {
+                Some(DiffFormat::Latex) => OutputFormat::Latex,
+                Some(DiffFormat::Typst) => OutputFormat::Typst,
+                None => detect_diff_format(&new, &new_content),
+            };
+
+            let hunks: Vec<_> = diff_outputs(&old_content, &new_content, format)
+                .into_iter()
+                .filter(|hunk| hunk.similarity <= threshold)
+                .collect();
+
+            for hunk in &hunks {
+                println!(
+                    "@@ {} {}:{}..{} {}:{}..{} similarity {:.2} @@",
+                    hunk.kind,
+                    old,
+                    hunk.old.start,
+                    hunk.old.end,
+                    new,
+                    hunk.new.start,
+                    hunk.new.end,
+                    hunk.similarity
+                );
+                for line in old_content[hunk.old.clone()].lines() {
+                    println!("-{}", line);
+                }
+                for line in new_content[hunk.new.clone()].lines() {
+                    println!("+{}", line);
+                }
+            }
+
+            // Same convention as diff(1): 1 when differences were found
+            if !hunks.is_empty() {
+                std::process::exit(1);
+            }
+        }
+
         Commands::Info => {
             println!("Tylax - High-performance bidirectional LaTeX <-> Typst converter");
             println!("Version: {}", env!("CARGO_PKG_VERSION"));
@@ -558,6 +641,7 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             println!("  - Typst <-> LaTeX conversion (math + documents)");
             println!("  - TikZ <-> CeTZ graphics conversion");
             println!("  - Batch file processing");
+            println!("  - Block-level diff of converted outputs");
             println!("  - LaTeX diagnostics and checking");
             println!("  - Auto-detection of input format");
             println!();
@@ -575,7 +659,34 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
     Ok(())
 }
 
+/// Pick the parser for `t2l diff` from the file extension, falling back to content
+#[cfg(feature = "cli")]
+fn detect_diff_format(path: &str, content: &str) -> OutputFormat {
+    if path.ends_with(".typ") {
+        OutputFormat::Typst
+    } else if path.ends_with(".tex") || path.ends_with(".ltx") {
+        OutputFormat::Latex
+    } else if detect_format(content) == "typst" {
+        OutputFormat::Typst
+    } else {
+        OutputFormat::Latex
+    }
+}
+
 /// Detect if input is a full LaTeX document (vs math snippet)
+/// Resolver reading images and `\input` files next to the input file, or
+/// in the working directory when reading stdin
+#[cfg(feature = "cli")]
+fn input_file_resolver(filename: Option<&str>) -> Arc<StdFileResolver> {
+    let base_dir = filename
+        .and_then(|name| Path::new(name).parent())
+        .filter(|dir| !dir.as_os_str().is_empty());
+    Arc::new(match base_dir {
+        Some(dir) => StdFileResolver::with_base_dir(dir),
+        None => StdFileResolver::new(),
+    })
+}
+
 #[cfg(feature = "cli")]
 fn is_latex_document(input: &str) -> bool {
     // Check for document structure indicators
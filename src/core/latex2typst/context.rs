@@ -2,25 +2,52 @@
 //!
 //! This module contains the main converter struct and conversion state.
 
-use mitex_parser::syntax::{CmdItem, SyntaxElement, SyntaxKind, SyntaxNode};
+use mitex_parser::syntax::{CmdItem, EnvItem, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
 use mitex_parser::CommandSpec;
+use mitex_spec::{ArgPattern, ArgShape, CmdShape, CommandSpecItem, GlobStr};
 use mitex_spec_gen::DEFAULT_SPEC;
 use rowan::ast::AstNode;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::sync::Arc;
 
-use crate::data::constants::{AcronymDef, GlossaryDef};
+use crate::data::accents::{is_text_accent, LETTER_COMMANDS};
+use crate::data::constants::{font_size_pt, AcronymDef, GlossaryDef, THEOREM_TYPES};
+use crate::data::dates::{parse_date, DateLanguage};
 use crate::data::maps::TEX_COMMAND_SPEC;
-use crate::features::refs::{CitationMode, ReferenceType};
+use crate::data::siunitx::SiOptions;
+use crate::features::images::{
+    image_path_candidates, needs_format_conversion, ImageAsset, DEFAULT_IMAGE_EXTENSIONS,
+};
+use crate::features::refs::{CitationMode, Label, LabelType, ReferenceType};
+use crate::features::tikz::CetzVersion;
+use crate::utils::limits::{latex_tree_depth, MAX_NESTING_DEPTH};
+use crate::utils::line_endings::{finish_lines, normalize_line_endings, LineEnding};
+use crate::utils::progress::{Phase, Progress, ProgressCallback};
+use crate::utils::{CompanionFile, FileResolver};
 use fxhash::FxHashMap;
 use lazy_static::lazy_static;
 
 use super::engine::{ArgumentErrorType, EngineWarning};
+use super::environment::theorem_display_name;
+use super::framed::report_dropped_framed_options;
+use super::macros::{
+    find_definitions, is_typst_builtin, param_placeholder, restore_params, LetMacro,
+};
+use super::markup::{input_candidates, resolve_dimension, section_base_level};
+use super::math::mitex_call;
+use super::table::TableSpacing;
+use super::typography::report_typography_notes;
 use super::{ConversionResult, ConversionWarning, WarningKind};
 
 use super::utils::{
-    clean_whitespace, convert_caption_text, extract_arg_content, extract_arg_content_with_braces,
-    extract_curly_inner_content, protect_zero_arg_commands, restore_protected_commands,
+    balance_left_right, clean_whitespace, close_wrapped_paragraphs, convert_caption_text,
+    convert_caption_text_with, extract_arg_content, extract_arg_content_with_braces,
+    extract_curly_inner_content, extract_filecontents, has_verbatim_chars, inline_display_math,
+    inline_input_files, line_spacing_rule, page_slots_expr, protect_verbatim_arguments,
+    protect_zero_arg_commands, restore_protected_commands, verbatim_markup, LEFT_MARK, PAGE_NUMBER,
+    RIGHT_MARK, TODAY_PLACEHOLDER,
 };
 
 // =============================================================================
@@ -32,8 +59,8 @@ use super::utils::{
 /// converted document.
 ///
 /// This does **not** affect document metadata (`#set document(title:..)`)
-/// or the rendered title block — those are derived from the LaTeX source
-/// (`\title`, `\author`) and stay regardless of this setting.
+/// or the title block rendered at `\maketitle` — those are derived from the
+/// LaTeX source (`\title`, `\author`) and stay regardless of this setting.
 #[derive(Debug, Clone, Default)]
 pub enum PreambleMode {
     /// Emit the default style preamble for the detected document class.
@@ -47,6 +74,111 @@ pub enum PreambleMode {
     Custom(String),
 }
 
+/// What to do with a construct the converter has no mapping for: an unknown
+/// LaTeX environment, or an unknown Typst set/show rule going the other way.
+/// Every choice records a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownEnvironmentPolicy {
+    /// Strip the environment (or rule) itself and convert its body
+    #[default]
+    ConvertBody,
+    /// Keep the whole construct verbatim, fenced for manual fixing
+    RawBlock,
+    /// Remove the construct and its body
+    Drop,
+}
+
+/// How a double rule (`\hline\hline`) is drawn; Typst strokes are single
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoubleRulePolicy {
+    /// One line twice as thick
+    #[default]
+    Thick,
+    /// Two lines separated by a thin empty row
+    Spaced,
+}
+
+/// How todonotes' `\todo` is converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TodoStyle {
+    /// A highlighted note in the text
+    #[default]
+    Inline,
+    /// A margin note from the `@preview/drafting` package
+    Margin,
+}
+
+/// How rotating's `sidewaysfigure` and `sidewaystable` are converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SidewaysStyle {
+    /// The figure turned a quarter with `#rotate(90deg, reflow: true)`, its
+    /// caption turning with it
+    #[default]
+    Rotate,
+    /// The figure on a landscape page of its own, `#page(flipped: true)`
+    FlippedPage,
+}
+
+/// How `\csvautotabular` and `\pgfplotstabletypeset` data tables are converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataTableMode {
+    /// Read the data file through the file resolver and write its cells into
+    /// a literal `#table(..)`
+    #[default]
+    Materialize,
+    /// Load the file when the Typst document is compiled, with `csv(..)`
+    Load,
+}
+
+/// A semantic LaTeX command or environment kept as a Typst
+/// `#metadata(..) <kind>` marker when [`L2TOptions::emit_metadata`] is set,
+/// so that `query(<kind>)` finds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataCommand {
+    /// Command or environment name, without the backslash
+    pub name: String,
+    /// Key of the metadata dictionary and label of the marker
+    pub kind: String,
+    /// Split the content at `,`, `;`, `\sep` and `\and` into an array of
+    /// content blocks, as for keyword lists
+    pub list: bool,
+}
+
+impl MetadataCommand {
+    /// A command whose converted content is kept as one value
+    pub fn new(name: &str, kind: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            list: false,
+        }
+    }
+
+    /// A command whose content is a list of items
+    pub fn list(name: &str, kind: &str) -> Self {
+        Self {
+            list: true,
+            ..Self::new(name, kind)
+        }
+    }
+}
+
+/// Semantic commands of common classes recognized by default
+pub fn default_metadata_commands() -> Vec<MetadataCommand> {
+    vec![
+        MetadataCommand::list("keywords", "keywords"),
+        MetadataCommand::list("IEEEkeywords", "keywords"),
+        MetadataCommand::list("subjclass", "subjclass"),
+        MetadataCommand::new("ccsdesc", "ccs"),
+        MetadataCommand::new("acks", "acks"),
+        MetadataCommand::new("acknowledgments", "acks"),
+        MetadataCommand::new("fundingstatement", "funding"),
+        // Reviewer- and editor-facing parts of journal classes
+        MetadataCommand::new("highlights", "highlights"),
+        MetadataCommand::new("editor", "editor"),
+    ]
+}
+
 /// Options for LaTeX to Typst conversion
 #[derive(Debug, Clone)]
 pub struct L2TOptions {
@@ -83,6 +215,103 @@ pub struct L2TOptions {
     /// Controls emission of the style preamble in document mode.
     /// Default: [`PreambleMode::Default`]
     pub preamble: PreambleMode,
+
+    /// How environments without a Typst mapping are converted
+    /// Default: [`UnknownEnvironmentPolicy::ConvertBody`]
+    pub unknown_environment_policy: UnknownEnvironmentPolicy,
+
+    /// Extensions tried, in order, for `\includegraphics` paths without one
+    /// Default: `pdf`, `png`, `jpg`, `svg`
+    pub image_extensions: Vec<String>,
+
+    /// Convert `wrapfigure` to `wrap-content` from the `@preview/wrap-it`
+    /// package so text flows around it; otherwise it becomes an aligned figure
+    /// Default: false
+    pub enable_wrap_package: bool,
+
+    /// Turn placeins' `\FloatBarrier` into `#pagebreak(weak: true)`;
+    /// otherwise it is kept as a comment, as Typst has no float barrier
+    /// Default: false
+    pub float_barrier_pagebreak: bool,
+
+    /// How `\hline\hline` double rules are drawn in tables
+    /// Default: DoubleRulePolicy::Thick
+    pub double_rule: DoubleRulePolicy,
+
+    /// How `\todo` notes are converted
+    /// Default: TodoStyle::Inline
+    pub todo_style: TodoStyle,
+
+    /// How `sidewaysfigure` and `sidewaystable` floats are converted
+    /// Default: SidewaysStyle::Rotate
+    pub sideways_style: SidewaysStyle,
+
+    /// Keep the math of a document as LaTeX, rendered by the `@preview/mitex`
+    /// package: inline math becomes `#mi(`..`)` and display math
+    /// `#mitex(`..`)`. Macros are still expanded first.
+    /// Default: false
+    pub math_passthrough: bool,
+
+    /// Line ending of the output. Input is always read with `\n` endings.
+    /// Default: [`LineEnding::Lf`]
+    pub line_ending: LineEnding,
+
+    /// Keep the content of the semantic commands in `metadata_commands` as
+    /// `#metadata((kind: ..)) <kind>` markers for Typst queries
+    /// Default: false
+    pub emit_metadata: bool,
+
+    /// Commands and environments turned into metadata markers; push to it to
+    /// recognize class-specific commands
+    /// Default: [`default_metadata_commands`]
+    pub metadata_commands: Vec<MetadataCommand>,
+
+    /// How `\csvautotabular` and `\pgfplotstabletypeset` tables are converted;
+    /// materializing needs a file resolver
+    /// Default: [`DataTableMode::Materialize`]
+    pub data_tables: DataTableMode,
+
+    /// Added to the level of every heading; the result is clamped to levels
+    /// 1 to 6
+    /// Default: 0
+    pub heading_offset: i8,
+
+    /// Shift headings so the topmost sectioning command used in the document
+    /// becomes `=` (a `\subsection`-only fragment starts at `=`), before
+    /// `heading_offset` is applied
+    /// Default: false
+    pub auto_normalize_headings: bool,
+
+    /// Deepest heading level kept as a heading; sectioning that ends up
+    /// deeper, such as `\paragraph` and `\subparagraph`, becomes bold
+    /// run-in text
+    /// Default: 4
+    pub max_heading_level: u8,
+
+    /// CeTZ release the TikZ pictures are converted for: sets the version
+    /// imported and the coordinate arithmetic used
+    /// Default: [`CetzVersion::V0_3`]
+    pub cetz_version: CetzVersion,
+
+    /// Keep what follows `\end{document}` as a comment block at the end of
+    /// the output; otherwise it is dropped
+    /// Default: false
+    pub keep_trailing_content: bool,
+
+    /// Convert a bibliography embedded with `filecontents` to a Hayagriva
+    /// YAML companion file; otherwise it is kept as BibTeX
+    /// Default: false
+    pub hayagriva_bibliography: bool,
+
+    /// Keep `\newcommand` macros whose bodies are plain math as `#let`
+    /// definitions at the top of the output, and call them where they are
+    /// used; other macros are still expanded
+    /// Default: false
+    pub preserve_macros_as_let: bool,
+    /// Told the phase, bytes done and blocks converted while a document
+    /// is converted
+    /// Default: None
+    pub progress: Option<ProgressCallback>,
 }
 
 impl Default for L2TOptions {
@@ -96,6 +325,29 @@ impl Default for L2TOptions {
             optimize: true,
             expand_macros: true,
             preamble: PreambleMode::Default,
+            unknown_environment_policy: UnknownEnvironmentPolicy::ConvertBody,
+            image_extensions: DEFAULT_IMAGE_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            enable_wrap_package: false,
+            float_barrier_pagebreak: false,
+            double_rule: DoubleRulePolicy::Thick,
+            todo_style: TodoStyle::Inline,
+            sideways_style: SidewaysStyle::Rotate,
+            math_passthrough: false,
+            line_ending: LineEnding::Lf,
+            emit_metadata: false,
+            metadata_commands: default_metadata_commands(),
+            data_tables: DataTableMode::Materialize,
+            heading_offset: 0,
+            auto_normalize_headings: false,
+            max_heading_level: 4,
+            cetz_version: CetzVersion::V0_3,
+            keep_trailing_content: false,
+            hayagriva_bibliography: false,
+            preserve_macros_as_let: false,
+            progress: None,
         }
     }
 }
@@ -107,6 +359,8 @@ fn default_style_preamble(document_class: Option<&str>) -> String {
              #set heading(numbering: \"1.1\")\n\
              #set math.equation(numbering: \"(1)\")\n\n"
             .to_string(),
+        // A standalone document is a single picture or snippet
+        "standalone" => String::new(),
         "beamer" => "#import \"@preview/polylux:0.3.1\": *\n\
              #set page(paper: \"presentation-16-9\")\n\n"
             .to_string(),
@@ -156,6 +410,53 @@ impl L2TOptions {
             ..Self::default()
         }
     }
+
+    /// The metadata command registered for `name`, when metadata is emitted
+    pub fn metadata_command(&self, name: &str) -> Option<&MetadataCommand> {
+        if !self.emit_metadata {
+            return None;
+        }
+        self.metadata_commands
+            .iter()
+            .find(|command| command.name == name)
+    }
+}
+
+/// What a snippet converted on its own may assume about its surroundings.
+///
+/// Used by [`LatexConverter::convert_fragment`] and
+/// `typst2latex::convert_fragment`, which convert a single environment or
+/// command without document scaffolding.
+#[derive(Debug, Clone, Default)]
+pub struct FragmentContext {
+    /// The snippet is math content (the body of `$...$`)
+    pub math: bool,
+    /// The snippet sits inside a float (`figure` / `table`)
+    pub in_float: bool,
+    /// Macro definitions in the source language, as if they were in the
+    /// preamble (`\newcommand..` for LaTeX, `#let ..` for Typst)
+    pub macros: String,
+}
+
+impl FragmentContext {
+    /// A text-mode fragment
+    pub fn text() -> Self {
+        Self::default()
+    }
+
+    /// A math-mode fragment
+    pub fn math() -> Self {
+        Self {
+            math: true,
+            ..Self::default()
+        }
+    }
+
+    /// Set macro definitions the fragment relies on
+    pub fn with_macros(mut self, macros: impl Into<String>) -> Self {
+        self.macros = macros.into();
+        self
+    }
 }
 
 lazy_static! {
@@ -192,6 +493,7 @@ pub enum EnvironmentContext {
     Figure,
     Table,
     Tabular,
+    Minipage,
     Itemize,
     Enumerate,
     Description,
@@ -234,6 +536,88 @@ pub struct PendingReference {
     pub ref_type: ReferenceType,
 }
 
+/// fancyhdr header and footer, as `[left, center, right]` Typst slots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FancyHeaders {
+    pub head: [String; 3],
+    pub foot: [String; 3],
+}
+
+impl Default for FancyHeaders {
+    /// The package defaults for one-sided documents
+    fn default() -> Self {
+        Self {
+            head: [LEFT_MARK.to_string(), String::new(), RIGHT_MARK.to_string()],
+            foot: [String::new(), PAGE_NUMBER.to_string(), String::new()],
+        }
+    }
+}
+
+/// Page style from `\pagestyle`, `\thispagestyle` and the fancyhdr commands
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageStyle {
+    /// Style selected by `\pagestyle{..}`
+    pub name: Option<String>,
+    /// Style selected by `\thispagestyle{..}` on the first page
+    pub first_page: Option<String>,
+    /// Headers and footers for the `fancy` style
+    pub fancy: FancyHeaders,
+    /// Whether an explicit page break has been converted
+    pub page_broken: bool,
+}
+
+impl PageStyle {
+    /// Header and footer slots of a named page style
+    fn slots(&self, name: &str) -> Option<([String; 3], [String; 3])> {
+        let empty = || [String::new(), String::new(), String::new()];
+        match name {
+            "empty" => Some((empty(), empty())),
+            "plain" => Some((
+                empty(),
+                [String::new(), PAGE_NUMBER.to_string(), String::new()],
+            )),
+            "headings" | "myheadings" => Some((
+                [
+                    RIGHT_MARK.to_string(),
+                    String::new(),
+                    PAGE_NUMBER.to_string(),
+                ],
+                empty(),
+            )),
+            "fancy" => Some((self.fancy.head.clone(), self.fancy.foot.clone())),
+            _ => None,
+        }
+    }
+
+    /// Render the `#set page(header: .., footer: ..)` rule, if any style was selected.
+    /// A different first-page style is applied through a `here().page()` check.
+    pub fn set_rule(&self) -> Option<String> {
+        let (head, foot) = self.slots(self.name.as_deref()?)?;
+        let mut header = page_slots_expr(&head);
+        let mut footer = page_slots_expr(&foot);
+        if let Some((first_head, first_foot)) =
+            self.first_page.as_deref().and_then(|n| self.slots(n))
+        {
+            let first_page = |first: String, rest: String| {
+                if first == rest {
+                    rest
+                } else {
+                    format!(
+                        "context if here().page() == 1 {{ {} }} else {{ {} }}",
+                        first, rest
+                    )
+                }
+            };
+            header = first_page(page_slots_expr(&first_head), header);
+            footer = first_page(page_slots_expr(&first_foot), footer);
+        }
+        Some(format!(
+            "#set page(\n  header: {},\n  footer: {},\n)",
+            header, footer
+        ))
+    }
+}
+
 /// Conversion state maintained during AST traversal
 #[derive(Debug, Default)]
 pub struct ConversionState {
@@ -251,15 +635,63 @@ pub struct ConversionState {
     pub pending_citation: Option<PendingCitation>,
     /// Pending reference state
     pub pending_reference: Option<PendingReference>,
+    /// End of the `[..]` spacing of a `\\` already converted with it; the
+    /// elements up to there are skipped
+    pub consumed_until: Option<rowan::TextSize>,
+    /// `\bigr`-style commands that close an open `lr(size: ..,` call, and
+    /// whether their delimiters group with the opening one
+    pub sized_delimiter_closers: Vec<(rowan::TextRange, bool)>,
+    /// Marker shared by every `\item[..]` of each open itemize (raw LaTeX)
+    pub list_markers: Vec<Option<String>>,
     /// User-defined macros
     pub macros: HashMap<String, MacroDef>,
+    /// Custom units from \DeclareSIUnit (command -> unit text)
+    pub si_units: HashMap<String, String>,
+    /// Global siunitx options from \sisetup
+    pub si_options: SiOptions,
+    /// Length registers from \newlength/\setlength (name -> Typst length)
+    pub lengths: HashMap<String, String>,
+    /// Column types from \newcolumntype: letter -> (parameter count, spec)
+    pub column_types: HashMap<String, (usize, String)>,
+    /// Global `\baselinestretch` from \linespread/setspace in the preamble
+    pub line_stretch: Option<f64>,
+    /// Page headers and footers from \pagestyle and fancyhdr
+    pub page_style: PageStyle,
+    /// Rules of the typography toggles of the preamble, such as `\sloppy`
+    pub typography_rules: Vec<String>,
+    /// Typography toggles without a Typst equivalent, with why, reported
+    /// together at the end
+    pub typography_notes: Vec<(String, &'static str)>,
+    /// Options set for every `tcolorbox` or `mdframed` by `\tcbset` and
+    /// `\mdfsetup`, by environment
+    pub framed_defaults: HashMap<String, String>,
+    /// Options of framed boxes left out, reported together at the end
+    pub framed_options_dropped: Vec<String>,
+    /// Nesting depth of `{..}` groups, arguments and environment bodies
+    pub group_depth: usize,
+    /// Group depths of open `#text(size: ..)[` scopes from size switches
+    pub size_scopes: Vec<usize>,
+    /// `\arraystretch`, `\tabcolsep` and `\rowcolors` for the tables that follow
+    pub table_spacing: TableSpacing,
+    /// Table spacing to restore when leaving the group at each depth
+    pub saved_table_spacing: Vec<(usize, TableSpacing)>,
     /// Whether we're in preamble
     pub in_preamble: bool,
     /// Document metadata
     pub title: Option<String>,
     pub author: Option<String>,
     pub date: Option<String>,
+    /// Footnotes from `\thanks{..}` in `\title`, shown in the title block
+    pub title_notes: Vec<String>,
+    /// Individual authors from `\author{A \and B}`
+    pub authors: Vec<String>,
+    /// PDF metadata from `\hypersetup{pdftitle=.., pdfauthor=.., pdfkeywords=..}`
+    pub pdf_title: Option<String>,
+    pub pdf_authors: Vec<String>,
+    pub pdf_keywords: Vec<String>,
     pub document_class: Option<String>,
+    /// Levels added to every heading by `auto_normalize_headings`
+    pub heading_shift: i8,
     /// Collected structured warnings
     pub structured_warnings: Vec<ConversionWarning>,
     /// Legacy string warnings (for compatibility)
@@ -272,6 +704,45 @@ pub struct ConversionState {
     pub glossary: HashMap<String, GlossaryDef>,
     /// Set of acronyms that have been used (for first-use tracking)
     pub used_acronyms: HashSet<String>,
+    /// Keys defined by `\bibitem` in `thebibliography`; citations to them
+    /// become references to the converted entries
+    pub manual_bib_keys: HashSet<String>,
+    /// Labels of the document and what they point at, collected before
+    /// conversion so references may precede their targets
+    pub labels: Vec<Label>,
+    /// Original document input, for locating warnings in the source
+    pub source: String,
+    /// Directories from `\graphicspath`
+    pub graphics_paths: Vec<String>,
+    /// Images referenced by `\includegraphics`, in order of first use
+    pub images: Vec<ImageAsset>,
+    /// Files embedded with `filecontents`, by name
+    pub embedded_files: HashMap<String, String>,
+    /// Files the output needs next to it, such as an embedded bibliography
+    pub companion_files: Vec<CompanionFile>,
+    /// Macros kept as `#let` definitions, in order of definition
+    pub(crate) let_macros: Vec<LetMacro>,
+    /// Kind and range of the node whose children are the blocks reported
+    /// to the progress callback: the `document` environment, or the tree
+    pub(crate) progress_root: Option<(SyntaxKind, rowan::TextRange)>,
+    /// Top-level blocks converted so far
+    pub(crate) blocks_converted: usize,
+    /// Whether a `wrapfigure` became a `wrap-content` call
+    pub uses_wrap_package: bool,
+    /// Whether a margin note became a `@preview/drafting` call
+    pub uses_drafting_package: bool,
+    /// Whether math was passed through to a `@preview/mitex` call
+    pub uses_mitex_package: bool,
+    /// PDF forms of `\texorpdfstring` in the heading being converted
+    pub pdf_strings: Vec<String>,
+    /// Labels inside the title of the heading being converted, which go
+    /// after the whole heading
+    pub heading_labels: Option<Vec<String>>,
+    /// Main document language from babel or polyglossia
+    pub language: Option<DateLanguage>,
+    /// Whether the text of each enclosing `\emph` or `\textit` is italic,
+    /// innermost last
+    pub italic: Vec<bool>,
     /// Conversion options
     pub options: L2TOptions,
 }
@@ -304,6 +775,15 @@ impl ConversionState {
         self.env_stack.push(env);
     }
 
+    /// Indentation of a new line: the lines of a list item, including
+    /// further paragraphs, are indented past its marker
+    pub fn line_indent(&self) -> usize {
+        match self.indent {
+            0 => 0,
+            marker => marker + 2,
+        }
+    }
+
     /// Pop the current environment from the stack
     pub fn pop_env(&mut self) -> Option<EnvironmentContext> {
         let env = self.env_stack.pop();
@@ -390,6 +870,134 @@ impl ConversionState {
 pub struct LatexConverter {
     pub(crate) state: ConversionState,
     pub(crate) spec: CommandSpec,
+    /// Looks up image files for `\graphicspath` and extension probing
+    pub(crate) file_resolver: Option<Arc<dyn FileResolver>>,
+}
+
+/// Spell the LaTeX input conventions of a text word the Typst way: quotes
+/// opened with ``` `` ``` and `` ` `` become smart quotes, `--` and `---` the
+/// dashes they stand for
+fn text_typography(word: &str) -> Cow<'_, str> {
+    if !word.contains(['`', '-']) {
+        return Cow::Borrowed(word);
+    }
+    Cow::Owned(
+        word.replace("``", "\"")
+            .replace('`', "'")
+            .replace("---", "—")
+            .replace("--", "–"),
+    )
+}
+
+/// Whether a line of a document without `\begin{document}` starts its
+/// body: text, math or a sectioning command rather than preamble setup
+fn is_body_line(line: &str) -> bool {
+    match line.strip_prefix('\\') {
+        Some(rest) => {
+            let name: String = rest.chars().take_while(char::is_ascii_alphabetic).collect();
+            matches!(
+                name.as_str(),
+                "part"
+                    | "chapter"
+                    | "section"
+                    | "subsection"
+                    | "subsubsection"
+                    | "paragraph"
+                    | "maketitle"
+                    | "tableofcontents"
+            ) || (name == "begin" && !rest.starts_with("begin{filecontents"))
+        }
+        None => line
+            .chars()
+            .next()
+            .is_some_and(|c| c != '%' && c != '}' && c != ']'),
+    }
+}
+
+/// Whether a space token directly follows a control word that swallows it as
+/// TeX does: a size switch like `\small`, a letter like `\ss`, or an accent
+/// over one such as `\"\i`
+fn follows_control_word(token: &SyntaxToken) -> bool {
+    let Some(cmd) = token
+        .prev_sibling_or_token()
+        .and_then(|prev| prev.into_node())
+        .and_then(CmdItem::cast)
+    else {
+        return false;
+    };
+    let Some(name) = cmd.name_tok() else {
+        return false;
+    };
+    let name = name.text().trim_start_matches('\\');
+    font_size_pt(name).is_some()
+        || LETTER_COMMANDS.iter().any(|(letter, _)| *letter == name)
+        || (is_text_accent(name)
+            && cmd
+                .syntax()
+                .last_token()
+                .is_some_and(|t| t.kind() == SyntaxKind::ClauseCommandName))
+}
+
+/// The length of the `[..]` extra space after a text `\\`, which may follow
+/// it after spaces, and where the brackets end
+fn line_break_spacing(elem: &SyntaxElement) -> Option<(String, rowan::TextSize)> {
+    let mut next = elem.next_sibling_or_token();
+    while next
+        .as_ref()
+        .is_some_and(|e| e.kind() == SyntaxKind::TokenWhiteSpace)
+    {
+        next = next.and_then(|e| e.next_sibling_or_token());
+    }
+    let mut current = next
+        .filter(|e| e.kind() == SyntaxKind::TokenLBracket)?
+        .next_sibling_or_token();
+    let mut spacing = String::new();
+    while let Some(e) = current {
+        if e.kind() == SyntaxKind::TokenRBracket {
+            let spacing = spacing.trim();
+            // Anything else is text in brackets that starts the next line
+            let is_length = spacing
+                .starts_with(|c: char| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | '\\'));
+            return is_length.then(|| (spacing.to_string(), e.text_range().end()));
+        }
+        match &e {
+            SyntaxElement::Node(node) => spacing.push_str(&node.text().to_string()),
+            SyntaxElement::Token(token) => spacing.push_str(token.text()),
+        }
+        current = e.next_sibling_or_token();
+    }
+    None
+}
+
+/// Whether a `&` or `\\` separates cells of the enclosing table, rather than
+/// sitting inside a group, formula or other environment within one cell
+fn is_table_separator(elem: &SyntaxElement) -> bool {
+    let mut ancestor = elem.parent();
+    while let Some(node) = ancestor {
+        match node.kind() {
+            SyntaxKind::ItemCurly | SyntaxKind::ClauseArgument | SyntaxKind::ItemFormula => {
+                return false
+            }
+            SyntaxKind::ItemEnv => {
+                let name = EnvItem::cast(node)
+                    .and_then(|env| env.name_tok())
+                    .map(|tok| tok.text().to_string())
+                    .unwrap_or_default();
+                return matches!(
+                    name.as_str(),
+                    "tabular"
+                        | "tabular*"
+                        | "tabularx"
+                        | "longtable"
+                        | "longtabu"
+                        | "table"
+                        | "table*"
+                );
+            }
+            _ => ancestor = node.parent(),
+        }
+    }
+    true
 }
 
 /// A `ClauseArgument` is a *required* argument iff it does not carry an
@@ -406,7 +1014,7 @@ pub struct LatexConverter {
 ///     We must reject this case, or downstream code mistakes `"["` for the
 ///     real required argument and the citation handler in `markup.rs` is
 ///     bypassed.
-fn is_required_clause(child: &SyntaxNode) -> bool {
+pub(crate) fn is_required_clause(child: &SyntaxNode) -> bool {
     child.kind() == SyntaxKind::ClauseArgument
         && !child
             .children()
@@ -417,12 +1025,24 @@ fn is_required_clause(child: &SyntaxNode) -> bool {
         )
 }
 
+/// Collapse every run of spaces into a single space
+fn collapse_spaces(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    for c in input.chars() {
+        if !(c == ' ' && result.ends_with(' ')) {
+            result.push(c);
+        }
+    }
+    result
+}
+
 impl LatexConverter {
     /// Create a new converter with default options
     pub fn new() -> Self {
         Self {
             state: ConversionState::new(),
             spec: MERGED_SPEC.clone(),
+            file_resolver: None,
         }
     }
 
@@ -433,9 +1053,71 @@ impl LatexConverter {
         Self {
             state,
             spec: MERGED_SPEC.clone(),
+            file_resolver: None,
         }
     }
 
+    /// Resolve image paths through `resolver`, so that extension-less
+    /// `\includegraphics{plot}` and `\graphicspath` directories pick an
+    /// existing file, and read the files `\input` and `\include` put in
+    /// the document
+    pub fn with_file_resolver(mut self, resolver: Arc<dyn FileResolver>) -> Self {
+        self.file_resolver = Some(resolver);
+        self
+    }
+
+    /// Contents of the first of `candidates` embedded in the document or,
+    /// failing that, the resolver can read
+    pub(crate) fn read_resolved_file(&self, candidates: &[String]) -> Option<String> {
+        if let Some(embedded) = candidates
+            .iter()
+            .find_map(|candidate| self.state.embedded_files.get(candidate))
+        {
+            return Some(embedded.clone());
+        }
+        let resolver = self.file_resolver.as_ref()?;
+        candidates
+            .iter()
+            .find_map(|candidate| resolver.read_file(candidate).ok())
+    }
+
+    /// Output path for an `\includegraphics` path.
+    ///
+    /// Candidates follow graphicx: the path itself, then each `\graphicspath`
+    /// directory, trying [`L2TOptions::image_extensions`] when the path has no
+    /// extension. Without a resolver, or when no candidate exists, the path is
+    /// kept as written. The image is added to the asset manifest, with a
+    /// warning for formats Typst cannot embed.
+    pub(crate) fn resolve_image_path(&mut self, source: &str) -> String {
+        let source = source.trim();
+        let dirs = &self.state.graphics_paths;
+        let extensions = &self.state.options.image_extensions;
+        let found = self.file_resolver.as_ref().and_then(|resolver| {
+            image_path_candidates(source, dirs, extensions)
+                .into_iter()
+                .find(|candidate| resolver.file_exists(candidate))
+        });
+        let path = found.clone().unwrap_or_else(|| source.to_string());
+
+        if !self.state.images.iter().any(|image| image.path == path) {
+            if needs_format_conversion(&path) {
+                self.state.add_warning(
+                    ConversionWarning::new(
+                        WarningKind::UnsupportedImageFormat,
+                        format!("'{}' must be converted to PDF, PNG or SVG for Typst", path),
+                    )
+                    .with_location(format!("\\includegraphics{{{}}}", source)),
+                );
+            }
+            self.state.images.push(ImageAsset {
+                source: source.to_string(),
+                path: path.clone(),
+                found: found.is_some(),
+            });
+        }
+        path
+    }
+
     /// Get a reference to the current options
     pub fn options(&self) -> &L2TOptions {
         &self.state.options
@@ -541,79 +1223,608 @@ impl LatexConverter {
     }
 
     /// Check if input contains a real `\begin{document}` that is not commented out.
+    fn has_real_begin_document(input: &str) -> bool {
+        Self::find_uncommented(input, "\\begin{document}")
+            .next()
+            .is_some()
+    }
+
+    /// Byte offsets of the lines' first `needle` in `input` that are not
+    /// commented out.
     ///
-    /// This function scans line-by-line, ignoring lines where `\begin{document}`
+    /// This function scans line-by-line, ignoring lines where `needle`
     /// appears after a `%` comment marker.
-    fn has_real_begin_document(input: &str) -> bool {
-        for line in input.lines() {
-            // Find position of \begin{document} in this line
-            if let Some(doc_pos) = line.find("\\begin{document}") {
-                // Check if there's a % comment before it
-                let before_doc = &line[..doc_pos];
-                // If % exists before \begin{document}, this line is commented
-                if !before_doc.contains('%') {
-                    return true;
+    fn find_uncommented<'a>(input: &'a str, needle: &'a str) -> impl Iterator<Item = usize> + 'a {
+        input
+            .split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some((start, line))
+            })
+            .filter_map(move |(start, line)| {
+                let pos = line.find(needle)?;
+                // If % exists before the needle, this line is commented
+                (!line[..pos].contains('%')).then_some(start + pos)
+            })
+    }
+
+    /// Split off what follows `\end{document}`, noting it unless it is
+    /// only blank lines and comments
+    fn split_trailing_content<'a>(&mut self, input: &'a str) -> (&'a str, Option<&'a str>) {
+        const END: &str = "\\end{document}";
+        // The last one, as a tutorial may show a whole document verbatim
+        let Some(pos) = Self::find_uncommented(input, END).last() else {
+            return (input, None);
+        };
+        let (body, trailing) = input.split_at(pos + END.len());
+        let has_content = trailing
+            .lines()
+            .any(|line| !line.trim().is_empty() && !line.trim_start().starts_with('%'));
+        if !has_content {
+            return (body, None);
+        }
+        let keep = self.state.options.keep_trailing_content;
+        self.state
+            .add_warning(ConversionWarning::document_structure(format!(
+                "content after \\end{{document}} was {}",
+                if keep { "kept as a comment" } else { "dropped" }
+            )));
+        (body, keep.then_some(trailing))
+    }
+
+    /// A document with a `\documentclass` but no `\begin{document}`, with
+    /// one inserted before the first line that reads like the body: text or
+    /// sectioning outside any group of the preamble. Without such a line the
+    /// whole input is the preamble of an empty body.
+    fn assume_begin_document(&mut self, input: &str) -> Option<String> {
+        if Self::has_real_begin_document(input)
+            || Self::find_uncommented(input, "\\documentclass")
+                .next()
+                .is_none()
+        {
+            return None;
+        }
+        let mut depth = 0i32;
+        let mut offset = 0;
+        for (number, line) in input.split_inclusive('\n').enumerate() {
+            let trimmed = line.trim_start();
+            if depth == 0 && is_body_line(trimmed) {
+                self.state
+                    .add_warning(ConversionWarning::document_structure(format!(
+                        "no \\begin{{document}}: line {} onwards converted as the body",
+                        number + 1
+                    )));
+                return Some(format!(
+                    "{}\\begin{{document}}\n{}\n\\end{{document}}\n",
+                    &input[..offset],
+                    &input[offset..]
+                ));
+            }
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '%' => break,
+                    '{' | '[' => depth += 1,
+                    '}' | ']' => depth -= 1,
+                    _ => {}
                 }
             }
+            offset += line.len();
         }
-        false
+        // Only a preamble: its settings are kept for an empty body
+        Some(format!(
+            "{}\n\\begin{{document}}\n\\end{{document}}\n",
+            input
+        ))
+    }
+
+    /// Keys of all `\bibitem` entries in the document
+    fn collect_bibitem_keys(&self, tree: &SyntaxNode) -> HashSet<String> {
+        tree.descendants()
+            .filter_map(CmdItem::cast)
+            .filter(|cmd| {
+                cmd.name_tok()
+                    .is_some_and(|name| name.text() == "\\bibitem")
+            })
+            .filter_map(|cmd| self.get_required_arg(&cmd, 0))
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect()
+    }
+
+    /// Shift that makes the topmost sectioning command of the document a
+    /// level-1 heading, if `auto_normalize_headings` is set
+    fn heading_shift(&self, tree: &SyntaxNode) -> i8 {
+        if !self.state.options.auto_normalize_headings {
+            return 0;
+        }
+        let commands: Vec<CmdItem> = tree.descendants().filter_map(CmdItem::cast).collect();
+        let class = commands
+            .iter()
+            .find(|cmd| {
+                cmd.name_tok()
+                    .is_some_and(|name| name.text() == "\\documentclass")
+            })
+            .and_then(|cmd| self.get_required_arg(cmd, 0));
+        commands
+            .iter()
+            .filter_map(|cmd| {
+                let name = cmd.name_tok()?;
+                let name = name.text().trim_start_matches('\\').trim_end_matches('*');
+                section_base_level(name, class.as_deref())
+            })
+            .min()
+            .map_or(0, |level| -(level as i8))
+    }
+
+    /// Every `\label` of the document with the kind of element it numbers:
+    /// the innermost float, equation, theorem or list around it, otherwise
+    /// the last heading before it
+    fn collect_labels(&self, tree: &SyntaxNode) -> Vec<Label> {
+        let theorems: HashSet<String> = tree
+            .descendants()
+            .filter_map(CmdItem::cast)
+            .filter(|cmd| {
+                cmd.name_tok()
+                    .is_some_and(|name| name.text() == "\\newtheorem")
+            })
+            .filter_map(|cmd| self.get_required_arg(&cmd, 0))
+            .map(|name| name.trim().to_string())
+            .collect();
+        let mut theorem_numbers: HashMap<String, u32> = HashMap::new();
+        let mut heading: Option<String> = None;
+        let mut labels = Vec::new();
+
+        for node in tree.descendants() {
+            if let Some(env) = EnvItem::cast(node.clone()) {
+                let name = env
+                    .name_tok()
+                    .map(|t| t.text().to_string())
+                    .unwrap_or_default();
+                if name != "proof"
+                    && (THEOREM_TYPES.contains_key(name.as_str()) || theorems.contains(&name))
+                {
+                    *theorem_numbers.entry(name).or_insert(0) += 1;
+                }
+                continue;
+            }
+            let Some(cmd) = CmdItem::cast(node.clone()) else {
+                continue;
+            };
+            let name = cmd
+                .name_tok()
+                .map(|t| t.text().to_string())
+                .unwrap_or_default();
+            match name.trim_start_matches('\\').trim_end_matches('*') {
+                "part" | "chapter" | "section" | "subsection" | "subsubsection" | "paragraph"
+                | "subparagraph" => {
+                    heading = self
+                        .get_required_arg_with_braces(&cmd, 0)
+                        .map(|title| convert_caption_text(&title).trim().to_string());
+                }
+                "label" => {
+                    let Some(id) = self.get_required_arg(&cmd, 0) else {
+                        continue;
+                    };
+                    let mut label = Label::new(id.trim().to_string(), LabelType::Section);
+                    let element = node.ancestors().find_map(|ancestor| {
+                        let env = EnvItem::cast(ancestor)?;
+                        let name = env.name_tok()?.text().to_string();
+                        let label_type = match name.as_str() {
+                            "figure" | "figure*" | "wrapfigure" | "subfigure" => LabelType::Figure,
+                            "table" | "table*" | "longtable" => LabelType::Table,
+                            "equation" | "align" | "gather" | "multline" | "flalign"
+                            | "alignat" | "eqnarray" => LabelType::Equation,
+                            "enumerate" => LabelType::Item,
+                            _ if theorem_numbers.contains_key(&name) => LabelType::Theorem,
+                            _ => return None,
+                        };
+                        Some((label_type, name))
+                    });
+                    match element {
+                        Some((LabelType::Theorem, name)) => {
+                            label.label_type = LabelType::Theorem;
+                            label.number = theorem_numbers.get(&name).map(u32::to_string);
+                            label.supplement = Some(theorem_display_name(&name));
+                        }
+                        Some((label_type, _)) => label.label_type = label_type,
+                        None if heading.is_some() => label.title = heading.clone(),
+                        None => label.label_type = LabelType::Item,
+                    }
+                    labels.push(label);
+                }
+                _ => {}
+            }
+        }
+        labels
     }
 
     /// Convert a complete LaTeX document to Typst
     pub fn convert_document(&mut self, input: &str) -> String {
+        let original = input;
+        let input = &*normalize_line_endings(input);
+        let (input, embedded) = extract_filecontents(input);
+        self.state.embedded_files = embedded.into_iter().collect();
+        let input = &*inline_input_files(&input, &|path| {
+            self.read_resolved_file(&input_candidates(path))
+        });
+        let (input, trailing) = self.split_trailing_content(input);
+        let assumed = self.assume_begin_document(input);
+        let input = assumed.as_deref().unwrap_or(input);
+        let kept = self.extract_let_macros(input);
+        let input = kept.as_deref().unwrap_or(input);
+
         // Only enter preamble mode if there's actually a \begin{document}
         // that is NOT inside a comment. This avoids false positives from:
         //   % \begin{document}  (commented out)
         //   \begin{verbatim}\begin{document}\end{verbatim}  (inside verbatim - rare edge case)
         self.state.in_preamble = Self::has_real_begin_document(input);
+        self.state.source = input.to_string();
 
         // Preprocess: protect zero-argument commands that MiTeX would otherwise lose
-        let protected_input = protect_zero_arg_commands(input);
+        let protected_input = protect_zero_arg_commands(&protect_verbatim_arguments(input));
 
         // Optionally expand macros using the SOTA token-based engine
         // This correctly handles nested braces and complex macro arguments
-        let expanded_input = self.preprocess_expansion(&protected_input, false);
+        self.report_progress(Phase::MacroExpansion, 0, protected_input.len());
+        let expanded_input =
+            balance_left_right(&self.preprocess_expansion(&protected_input, false));
+        let size = protected_input.len();
+        self.report_progress(Phase::MacroExpansion, size, size);
 
         // Parse with mitex-parser
-        let tree = mitex_parser::parse(&expanded_input, self.spec.clone());
+        let size = expanded_input.len();
+        self.report_progress(Phase::Parsing, 0, size);
+        let tree = mitex_parser::parse(&expanded_input, self.parse_spec());
+        self.report_progress(Phase::Parsing, size, size);
+        if let Some(kept) = self.keep_too_deep(&tree, input, false) {
+            return self.finish_lines(&kept, original);
+        }
+
+        // Citations may precede the manual bibliography they point to
+        self.state.manual_bib_keys = self.collect_bibitem_keys(&tree);
+        self.state.labels = self.collect_labels(&tree);
+        self.state.heading_shift = self.heading_shift(&tree);
 
         // Convert AST to Typst with pre-allocated buffer
         let estimated_size = (expanded_input.len() as f64 * 1.5) as usize;
         let mut output = String::with_capacity(estimated_size.max(1024));
 
         // Walk the tree
+        if self.state.options.progress.is_some() {
+            let root = Self::document_node(&tree);
+            self.state.progress_root = Some((root.kind(), root.text_range()));
+            self.report_progress(Phase::Conversion, 0, size);
+        }
         self.visit_node(&tree, &mut output);
+        while self.state.size_scopes.pop().is_some() {
+            output.push(']');
+        }
+        report_typography_notes(self);
+        report_dropped_framed_options(self);
+        self.report_progress(Phase::Conversion, size, size);
 
         // Build final document with preamble
+        let size = output.len();
+        self.report_progress(Phase::PostProcessing, 0, size);
         let result = self.build_document(output);
 
         // Restore protected commands
-        restore_protected_commands(&result)
+        let mut result = restore_protected_commands(&self.restore_today(&result));
+        if let Some(trailing) = trailing {
+            // Block comments nest in Typst
+            let trailing = trailing.trim().replace("/*", "/ *").replace("*/", "* /");
+            result.truncate(result.trim_end().len());
+            let _ = write!(
+                result,
+                "\n\n/* After \\end{{document}}:\n{}\n*/\n",
+                trailing
+            );
+        }
+        let result = self.finish_lines(&result, original);
+        self.report_progress(Phase::PostProcessing, size, size);
+        result
+    }
+
+    /// The `document` environment of `tree`, or `tree` itself
+    fn document_node(tree: &SyntaxNode) -> SyntaxNode {
+        tree.children()
+            .find(|node| {
+                node.kind() == SyntaxKind::ItemEnv
+                    && EnvItem::cast(node.clone())
+                        .and_then(|env| env.name_tok())
+                        .is_some_and(|tok| tok.text() == "document")
+            })
+            .unwrap_or_else(|| tree.clone())
+    }
+
+    /// Tell the progress callback, if any, where the conversion is at
+    fn report_progress(&self, phase: Phase, bytes_processed: usize, bytes_total: usize) {
+        if let Some(progress) = &self.state.options.progress {
+            progress.report(Progress {
+                phase,
+                bytes_processed,
+                bytes_total,
+                current_block: self.state.blocks_converted,
+            });
+        }
+    }
+
+    /// Count `child` of `parent` as converted if it is a top-level block
+    fn block_converted(&mut self, parent: &SyntaxNode, child: &SyntaxElement) {
+        if child.as_node().is_none()
+            || self.state.progress_root != Some((parent.kind(), parent.text_range()))
+        {
+            return;
+        }
+        self.state.blocks_converted += 1;
+        let total = parent
+            .ancestors()
+            .last()
+            .map_or(0, |root| root.text_range().end().into());
+        self.report_progress(Phase::Conversion, child.text_range().end().into(), total);
+    }
+
+    /// With `preserve_macros_as_let`, keep the macros whose bodies are
+    /// plain math as `#let` definitions, and take their definitions out of
+    /// `input` so the macro engine leaves their uses alone
+    fn extract_let_macros(&mut self, input: &str) -> Option<String> {
+        if !self.state.options.preserve_macros_as_let {
+            return None;
+        }
+        let definitions = find_definitions(input);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for definition in &definitions {
+            *counts.entry(&definition.name).or_default() += 1;
+        }
+        let defined: HashSet<String> = counts.keys().map(|name| name.to_string()).collect();
+        let mut kept = HashSet::new();
+        let mut ranges = Vec::new();
+        for definition in &definitions {
+            // A single letter is used from code in math, which cannot take
+            // math arguments after it
+            // Text is kept as content; text mixed with math is expanded
+            let text = !definition.has_default && definition.is_text();
+            if counts[definition.name.as_str()] > 1
+                || definition.params > 9
+                || (definition.params > 0 && definition.name.len() == 1)
+                || definition.is_mixed()
+                || !(text || definition.is_clean_math(&defined, &kept))
+            {
+                continue;
+            }
+            let mut body = definition.body.clone();
+            for index in 0..definition.params {
+                body = body.replace(
+                    &format!("#{}", index + 1),
+                    &param_placeholder(index).to_string(),
+                );
+            }
+            let body = if text {
+                body.split_whitespace().collect::<Vec<_>>().join(" ")
+            } else {
+                let mut options = self.state.options.clone();
+                options.preserve_macros_as_let = false;
+                let mut converter = LatexConverter::with_options(options);
+                converter.state.let_macros = self.state.let_macros.clone();
+                let result = converter.convert_math_with_diagnostics(&body);
+                let body = result.output.trim();
+                if !result.warnings.is_empty() || body.is_empty() || body.contains("/*") {
+                    continue;
+                }
+                body.to_string()
+            };
+
+            let mut name = definition.name.clone();
+            while is_typst_builtin(&name) || self.state.let_macros.iter().any(|m| m.name == name) {
+                name.push('x');
+            }
+            if name != definition.name {
+                self.state
+                    .add_warning(ConversionWarning::macro_renamed(&definition.name, &name));
+            }
+            self.state.let_macros.push(LetMacro {
+                command: definition.name.clone(),
+                name,
+                params: definition.params,
+                body: restore_params(&body, definition.params),
+                text,
+            });
+            kept.insert(definition.name.clone());
+            ranges.push(definition.range.clone());
+        }
+        if ranges.is_empty() {
+            return None;
+        }
+
+        // Lines stay where they were
+        let mut output = String::with_capacity(input.len());
+        let mut last = 0;
+        for range in ranges {
+            output.push_str(&input[last..range.start]);
+            output.extend(input[range.clone()].matches('\n'));
+            last = range.end;
+        }
+        output.push_str(&input[last..]);
+        Some(output)
+    }
+
+    /// Command specification for parsing: metadata commands unknown to it
+    /// take an optional and a required argument, and macros kept as `#let`
+    /// definitions take their own
+    fn parse_spec(&self) -> CommandSpec {
+        let options = &self.state.options;
+        if (!options.emit_metadata
+            || options
+                .metadata_commands
+                .iter()
+                .all(|command| self.spec.get(&command.name).is_some()))
+            && self.state.let_macros.is_empty()
+        {
+            return self.spec.clone();
+        }
+        let mut commands: FxHashMap<String, _> = self
+            .spec
+            .items()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        if options.emit_metadata {
+            for command in &options.metadata_commands {
+                commands.entry(command.name.clone()).or_insert_with(|| {
+                    CommandSpecItem::Cmd(CmdShape {
+                        args: ArgShape::Right {
+                            pattern: ArgPattern::Glob {
+                                pattern: GlobStr::from("{,b}t"),
+                            },
+                        },
+                        alias: None,
+                    })
+                });
+            }
+        }
+        for let_macro in &self.state.let_macros {
+            let pattern = match let_macro.params {
+                0 => ArgPattern::None,
+                len => ArgPattern::FixedLenTerm { len: len as u8 },
+            };
+            commands.insert(
+                let_macro.command.clone(),
+                CommandSpecItem::Cmd(CmdShape {
+                    args: ArgShape::Right { pattern },
+                    alias: None,
+                }),
+            );
+        }
+        CommandSpec::new(commands)
+    }
+
+    /// Give the output the configured line ending, without trailing
+    /// whitespace
+    fn finish_lines(&self, output: &str, input: &str) -> String {
+        finish_lines(output, self.state.options.line_ending, input)
+    }
+
+    /// Convert a single environment or command, e.g. a `tabular`, the way it
+    /// would be converted inside a document, but without the preamble, set
+    /// rules or imports of [`convert_document`](Self::convert_document).
+    pub fn convert_fragment(&mut self, input: &str, context: FragmentContext) -> String {
+        self.state.in_preamble = false;
+        self.state.mode = if context.math {
+            ConversionMode::Math
+        } else {
+            ConversionMode::Text
+        };
+        if context.in_float {
+            self.state.push_env(EnvironmentContext::Figure);
+        }
+
+        let original = input;
+        let input = &*normalize_line_endings(input);
+
+        // Definitions come first so they apply to the fragment; on their own
+        // they convert to nothing
+        let source = if context.macros.is_empty() {
+            input.to_string()
+        } else {
+            format!("{}\n{}", context.macros, input)
+        };
+        self.state.source = source.clone();
+        let protected_input = protect_zero_arg_commands(&protect_verbatim_arguments(&source));
+        let expanded_input =
+            balance_left_right(&self.preprocess_expansion(&protected_input, context.math));
+        let tree = mitex_parser::parse(&expanded_input, self.parse_spec());
+        if let Some(kept) = self.keep_too_deep(&tree, input, context.math) {
+            return self.finish_lines(&kept, original);
+        }
+        self.state.manual_bib_keys = self.collect_bibitem_keys(&tree);
+        self.state.labels = self.collect_labels(&tree);
+        self.state.heading_shift = self.heading_shift(&tree);
+
+        let mut output = String::with_capacity(expanded_input.len().max(256));
+        self.visit_node(&tree, &mut output);
+        while self.state.size_scopes.pop().is_some() {
+            output.push(']');
+        }
+        report_typography_notes(self);
+        report_dropped_framed_options(self);
+        if context.in_float {
+            self.state.pop_env();
+        }
+
+        let output = if context.math {
+            self.postprocess_math(output)
+        } else {
+            clean_whitespace(&close_wrapped_paragraphs(&output))
+        };
+        let output = restore_protected_commands(&self.restore_today(&output));
+        self.finish_lines(output.trim(), original)
+    }
+
+    /// Source whose tree is deeper than [`MAX_NESTING_DEPTH`], kept
+    /// unconverted with a warning: as a string in math, otherwise as a raw
+    /// block
+    fn keep_too_deep(&mut self, tree: &SyntaxNode, input: &str, math: bool) -> Option<String> {
+        let depth = latex_tree_depth(tree);
+        if depth <= MAX_NESTING_DEPTH {
+            return None;
+        }
+        self.state.add_warning(ConversionWarning::new(
+            WarningKind::ParseError,
+            format!(
+                "input nests {} levels deep, more than the {} that are converted; kept as is",
+                depth, MAX_NESTING_DEPTH
+            ),
+        ));
+        Some(if math {
+            format!("\"{}\"", input.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            format!("```latex\n{}\n```", input.trim())
+        })
+    }
+
+    /// Put today's date, in the document language, where `\today` stood
+    fn restore_today(&self, text: &str) -> String {
+        if !text.contains(TODAY_PLACEHOLDER) {
+            return text.to_string();
+        }
+        let language = self.state.language.clone().unwrap_or_default();
+        text.replace(TODAY_PLACEHOLDER, &format!("#{}", language.typst_today()))
     }
 
     /// Convert math-only LaTeX to Typst
     pub fn convert_math(&mut self, input: &str) -> String {
         self.state.mode = ConversionMode::Math;
         self.state.in_preamble = false;
+        let original = input;
+        let input = &*normalize_line_endings(input);
 
         // Optionally expand macros with math mode enabled
-        let expanded_input = self.preprocess_expansion(input, true);
+        let expanded_input = balance_left_right(&self.preprocess_expansion(input, true));
 
         // Parse
-        let tree = mitex_parser::parse(&expanded_input, self.spec.clone());
+        let tree = mitex_parser::parse(&expanded_input, self.parse_spec());
+        if let Some(kept) = self.keep_too_deep(&tree, input, true) {
+            return self.finish_lines(&kept, original);
+        }
 
         // Convert with pre-allocated buffer
         let mut output = String::with_capacity(expanded_input.len().max(256));
         self.visit_node(&tree, &mut output);
 
         // Post-process
-        self.postprocess_math(output)
+        let output = self.postprocess_math(output);
+        self.finish_lines(&output, original)
     }
 
     /// Visit a syntax node and convert it
     pub fn visit_node(&mut self, node: &SyntaxNode, output: &mut String) {
         for child in node.children_with_tokens() {
-            self.visit_element(child, output);
+            self.visit_element(child.clone(), output);
+            self.block_converted(node, &child);
         }
     }
 
@@ -657,7 +1868,12 @@ impl LatexConverter {
             }
             SyntaxKind::ItemCurly if !pending.collecting_optional => {
                 if let SyntaxElement::Node(node) = elem {
-                    super::markup::emit_pending_citation_from_curly(&node, pending, output);
+                    super::markup::emit_pending_citation_from_curly(
+                        &node,
+                        pending,
+                        &self.state.manual_bib_keys,
+                        output,
+                    );
                     return true;
                 }
                 self.state.pending_citation = Some(pending);
@@ -719,6 +1935,16 @@ impl LatexConverter {
         if self.handle_pending_reference(elem.clone(), output) {
             return;
         }
+        if let Some(end) = self.state.consumed_until {
+            let range = elem.text_range();
+            if range.end() <= end {
+                return;
+            }
+            // A node partly consumed is visited child by child
+            if range.start() >= end {
+                self.state.consumed_until = None;
+            }
+        }
 
         match elem.kind() {
             // Handle errors gracefully
@@ -795,24 +2021,53 @@ impl LatexConverter {
                             output.push(c);
                             output.push(' ');
                         }
+                    } else if has_verbatim_chars(text) {
+                        // Text from `\string` or `\detokenize` prints as written
+                        output.push_str(&verbatim_markup(text));
                     } else {
-                        output.push_str(text);
+                        output.push_str(&text_typography(text));
                     }
                 }
             }
 
             // Whitespace
             TokenWhiteSpace => {
+                if self.state.in_preamble {
+                    return;
+                }
                 if let SyntaxElement::Token(t) = elem {
-                    output.push_str(t.text());
+                    if !follows_control_word(&t) {
+                        output.push_str(t.text());
+                    }
                 }
             }
 
             // Line break
             TokenLineBreak => {
+                if self.state.in_preamble {
+                    return;
+                }
                 if let SyntaxElement::Token(t) = elem {
-                    output.push_str(t.text());
-                    for _ in 0..self.state.indent {
+                    let mut text = t.text();
+                    // A comment swallows its own line ending: `foo%\nbar` is `foobar`,
+                    // while any further line ending is an empty line
+                    if t.prev_sibling_or_token().map(|p| p.kind()) == Some(TokenComment) {
+                        let rest = text.strip_prefix("\r\n").unwrap_or(text);
+                        let rest = rest.strip_prefix('\n').unwrap_or(rest);
+                        if rest.is_empty() {
+                            return;
+                        }
+                        text = "\n\n";
+                    }
+                    // A single line ending after block output that already ended
+                    // its line must not turn into an empty line (paragraph break)
+                    if text.matches('\n').count() == 1
+                        && (output.ends_with('\n') || follows_control_word(&t))
+                    {
+                        return;
+                    }
+                    output.push_str(text);
+                    for _ in 0..self.state.line_indent() {
                         output.push(' ');
                     }
                 } else {
@@ -827,8 +2082,20 @@ impl LatexConverter {
                 EnvironmentContext::Align | EnvironmentContext::Equation => {
                     output.push_str(" \\ ");
                 }
-                EnvironmentContext::Tabular => output.push_str("|||ROW|||"),
-                _ => output.push_str("\\ "),
+                EnvironmentContext::Tabular if is_table_separator(&elem) => {
+                    output.push_str("|||ROW|||")
+                }
+                _ => {
+                    output.push_str("\\ ");
+                    if let Some((spacing, end)) = line_break_spacing(&elem) {
+                        let _ = write!(
+                            output,
+                            "#v({}, weak: true) ",
+                            resolve_dimension(self, &spacing)
+                        );
+                        self.state.consumed_until = Some(end);
+                    }
+                }
             },
 
             // Ampersand (column separator)
@@ -836,7 +2103,9 @@ impl LatexConverter {
                 EnvironmentContext::Matrix => output.push_str("zws, "),
                 EnvironmentContext::Cases => output.push_str("& "),
                 EnvironmentContext::Align => output.push_str("& "),
-                EnvironmentContext::Tabular | EnvironmentContext::Table => {
+                EnvironmentContext::Tabular | EnvironmentContext::Table
+                    if is_table_separator(&elem) =>
+                {
                     output.push_str("|||CELL|||")
                 }
                 _ => output.push('&'),
@@ -847,7 +2116,7 @@ impl LatexConverter {
                 if matches!(self.state.mode, ConversionMode::Math) {
                     output.push_str("space.nobreak ");
                 } else {
-                    output.push(' ');
+                    output.push('~');
                 }
             }
             TokenHash => output.push_str("\\#"),
@@ -865,7 +2134,23 @@ impl LatexConverter {
                     output.push_str("\\^");
                 }
             }
-            TokenApostrophe => output.push('\''),
+            TokenApostrophe => {
+                // The second `'` of a `''` that closes a double quotation,
+                // parsed as a prime on the first
+                let doubled = elem
+                    .prev_sibling_or_token()
+                    .and_then(|prev| prev.into_node()?.last_token())
+                    .is_some_and(|prev| prev.kind() == TokenApostrophe);
+                if doubled
+                    && output.ends_with('\'')
+                    && !matches!(self.state.mode, ConversionMode::Math)
+                {
+                    output.pop();
+                    output.push('"');
+                } else {
+                    output.push('\'');
+                }
+            }
             TokenComma => {
                 if matches!(self.state.current_env(), EnvironmentContext::Cases) {
                     while output.ends_with(char::is_whitespace) {
@@ -974,6 +2259,25 @@ impl LatexConverter {
         None
     }
 
+    /// Get an optional argument preserving inner braces
+    pub fn get_optional_arg_with_braces(&self, cmd: &CmdItem, index: usize) -> Option<String> {
+        let mut optional_count = 0;
+        for child in cmd.syntax().children() {
+            if child.kind() == SyntaxKind::ClauseArgument {
+                let is_bracket = child
+                    .children()
+                    .any(|c| c.kind() == SyntaxKind::ItemBracket);
+                if is_bracket {
+                    if optional_count == index {
+                        return Some(extract_arg_content_with_braces(&child));
+                    }
+                    optional_count += 1;
+                }
+            }
+        }
+        None
+    }
+
     /// Convert a required argument - recursively processes the content.
     ///
     /// Handles both braced (`{...}`) and unbraced single-token arguments. Empty
@@ -985,17 +2289,7 @@ impl LatexConverter {
         for child in cmd.syntax().children() {
             if is_required_clause(&child) {
                 if required_count == index {
-                    let mut output = String::new();
-                    for content in child.children_with_tokens() {
-                        match content.kind() {
-                            SyntaxKind::TokenLBrace
-                            | SyntaxKind::TokenRBrace
-                            | SyntaxKind::TokenLBracket
-                            | SyntaxKind::TokenRBracket => continue,
-                            _ => self.visit_element(content, &mut output),
-                        }
-                    }
-                    return Some(output.trim().to_string());
+                    return Some(self.convert_group(&child));
                 }
                 required_count += 1;
             }
@@ -1003,6 +2297,48 @@ impl LatexConverter {
         None
     }
 
+    /// Convert the contents of an argument or a `{..}` group, without its
+    /// braces or brackets
+    pub fn convert_group(&mut self, group: &SyntaxNode) -> String {
+        let mut output = String::new();
+        self.enter_group();
+        for content in group.children_with_tokens() {
+            match content.kind() {
+                SyntaxKind::TokenLBrace
+                | SyntaxKind::TokenRBrace
+                | SyntaxKind::TokenLBracket
+                | SyntaxKind::TokenRBracket => continue,
+                _ => self.visit_element(content, &mut output),
+            }
+        }
+        self.leave_group(&mut output);
+        output.trim().to_string()
+    }
+
+    /// Convert the `{..}` groups right after `cmd`, at most `max`: arguments
+    /// the command takes only when they are braced, so that `\braket{a} + 1`
+    /// keeps its `+`. The groups are consumed.
+    pub fn convert_trailing_groups(&mut self, cmd: &CmdItem, max: usize) -> Vec<String> {
+        let mut groups = Vec::new();
+        let mut next = cmd.syntax().next_sibling_or_token();
+        while let Some(elem) = next.filter(|_| groups.len() < max) {
+            match elem.as_node() {
+                Some(node) if node.kind() == SyntaxKind::ItemCurly => groups.push(node.clone()),
+                _ if elem.kind() == SyntaxKind::TokenWhiteSpace => {}
+                _ => break,
+            }
+            next = elem.next_sibling_or_token();
+        }
+        let args = groups
+            .iter()
+            .map(|group| self.convert_group(group))
+            .collect();
+        if let Some(group) = groups.last() {
+            self.state.consumed_until = Some(group.text_range().end());
+        }
+        args
+    }
+
     /// Convert a required *term* argument such as `b` in `\frac{a}b` or `\sim`
     /// in `\overset{p}\sim`. Thin alias of [`Self::convert_required_arg`], which
     /// already handles unbraced single-token terms (and pads empty groups).
@@ -1010,16 +2346,42 @@ impl LatexConverter {
         self.convert_required_arg(cmd, index)
     }
 
+    /// Get an optional argument from a command and convert it to Typst
+    pub fn get_converted_optional_arg(&mut self, cmd: &CmdItem, index: usize) -> Option<String> {
+        let raw_text = self.get_optional_arg_with_braces(cmd, index)?;
+        Some(self.convert_argument_text(&raw_text).trim().to_string())
+    }
+
     /// Get a required argument from a command and convert it to Typst
     pub fn get_converted_required_arg(&mut self, cmd: &CmdItem, index: usize) -> Option<String> {
         let raw_text = self.get_required_arg_with_braces(cmd, index)?;
         if raw_text.contains('$') || raw_text.contains('\\') {
-            Some(convert_caption_text(&raw_text))
+            Some(self.convert_argument_text(&raw_text))
         } else {
             Some(raw_text)
         }
     }
 
+    /// [`convert_caption_text`], passing the math through to mitex when
+    /// `L2TOptions::math_passthrough` is set. Display math cannot stand in
+    /// the text, so it becomes inline math.
+    pub(super) fn convert_argument_text(&mut self, raw_text: &str) -> String {
+        let (inlined, displays) = inline_display_math(raw_text);
+        for delimiter in displays {
+            self.state.add_warning(ConversionWarning::approximation(
+                &delimiter,
+                "Display math in a caption or other inline argument is set as inline math",
+            ));
+        }
+        let raw_text = inlined.as_str();
+        if self.state.options.math_passthrough && raw_text.contains('$') {
+            self.state.uses_mitex_package = true;
+            convert_caption_text_with(raw_text, &|math| mitex_call(false, false, None, math))
+        } else {
+            convert_caption_text(raw_text)
+        }
+    }
+
     /// Get optional argument from an environment
     pub fn get_env_optional_arg(&self, node: &SyntaxNode) -> Option<String> {
         for child in node.children() {
@@ -1042,7 +2404,12 @@ impl LatexConverter {
     /// Get a required argument from an environment
     pub fn get_env_required_arg(&self, node: &SyntaxNode, index: usize) -> Option<String> {
         let mut required_count = 0;
-        for child in node.children() {
+        // Arguments declared in the env spec are nested under ItemBegin
+        let begin_args = node
+            .children()
+            .filter(|c| c.kind() == SyntaxKind::ItemBegin)
+            .flat_map(|begin| begin.children());
+        for child in begin_args.chain(node.children()) {
             if child.kind() == SyntaxKind::ClauseArgument {
                 let is_curly = child.children().any(|c| c.kind() == SyntaxKind::ItemCurly);
                 if is_curly {
@@ -1080,10 +2447,7 @@ impl LatexConverter {
         result = self.fix_empty_accent_args(&result);
 
         result = self.fix_symbol_spacing(&result);
-
-        while result.contains("  ") {
-            result = result.replace("  ", " ");
-        }
+        result = collapse_spaces(&result);
 
         result = result.replace(" ,", ",");
         result = result.replace("( ", "(");
@@ -1096,11 +2460,7 @@ impl LatexConverter {
 
     /// Clean up math spacing
     pub fn cleanup_math_spacing(&self, input: &str) -> String {
-        let mut result = input.to_string();
-
-        while result.contains("  ") {
-            result = result.replace("  ", " ");
-        }
+        let mut result = collapse_spaces(input);
 
         result = result.replace(" ,", ",");
         result = result.replace("( ", "(");
@@ -1194,45 +2554,27 @@ impl LatexConverter {
             "ddots",
         ];
 
-        let mut result = input.to_string();
-
-        for symbol in SYMBOL_PREFIXES {
-            // Pattern: non-letter/non-space followed by symbol
-            // We need to find cases like "2angle.r" or ")pi"
-            let mut i = 0;
-            while i < result.len() {
-                if let Some(pos) = result[i..].find(symbol) {
-                    let abs_pos = i + pos;
-                    if abs_pos > 0 {
-                        let prev_char = result.chars().nth(abs_pos - 1).unwrap_or(' ');
-                        // Insert space if previous char is not a letter, space, or opening paren/bracket
-                        if !prev_char.is_alphabetic()
-                            && prev_char != ' '
-                            && prev_char != '('
-                            && prev_char != '['
-                            && prev_char != '{'
-                            && prev_char != '\n'
-                            && prev_char != '\t'
-                        {
-                            // Check that we're not in the middle of a word
-                            // e.g., don't change "tangent" when looking for "angle"
-                            let after_symbol = abs_pos + symbol.len();
-                            let next_char = result.chars().nth(after_symbol);
-                            let is_word_boundary =
-                                next_char.is_none_or(|c| !c.is_alphanumeric() && c != '.');
-
-                            if is_word_boundary {
-                                result.insert(abs_pos, ' ');
-                                i = abs_pos + symbol.len() + 2; // Skip past inserted space and symbol
-                                continue;
-                            }
-                        }
-                    }
-                    i = abs_pos + 1;
-                } else {
-                    break;
-                }
+        // Single forward pass: a symbol gets a space when it starts right after
+        // a character other than a letter, space or opening bracket and ends
+        // at a word boundary.
+        let mut result = String::with_capacity(input.len() + input.len() / 16);
+        let mut prev_char: Option<char> = None;
+        for (pos, c) in input.char_indices() {
+            let needs_space = prev_char.is_some_and(|p| {
+                !p.is_alphabetic() && !matches!(p, ' ' | '(' | '[' | '{' | '\n' | '\t')
+            }) && c.is_alphabetic()
+                && SYMBOL_PREFIXES.iter().any(|symbol| {
+                    input[pos..].starts_with(symbol)
+                        && input[pos + symbol.len()..]
+                            .chars()
+                            .next()
+                            .is_none_or(|n| !n.is_alphanumeric() && n != '.')
+                });
+            if needs_space {
+                result.push(' ');
             }
+            result.push(c);
+            prev_char = Some(c);
         }
 
         result
@@ -1240,64 +2582,60 @@ impl LatexConverter {
 
     /// Fix operatorname() patterns
     pub fn fix_operatorname(&self, input: &str) -> String {
-        let mut result = input.to_string();
+        const PREFIX: &str = "operatorname(";
+        let mut result = String::with_capacity(input.len());
+        let mut rest = input;
 
-        while let Some(start) = result.find("operatorname(") {
-            let after = &result[start + 13..];
-            if let Some(end) = self.find_matching_paren(after) {
-                let content = &after[..end];
-                let clean_content: String =
-                    content.chars().filter(|c| !c.is_whitespace()).collect();
-                let replacement = format!("op(\"{}\")", clean_content);
-                let total_end = start + 13 + end + 1;
-                result = format!(
-                    "{}{}{}",
-                    &result[..start],
-                    replacement,
-                    &result[total_end..]
-                );
-            } else {
+        while let Some(start) = rest.find(PREFIX) {
+            let after = &rest[start + PREFIX.len()..];
+            let Some(end) = self.find_matching_paren(after) else {
                 break;
-            }
+            };
+            let clean_content: String = after[..end]
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            result.push_str(&rest[..start]);
+            let _ = write!(result, "op(\"{}\")", self.fix_operatorname(&clean_content));
+            rest = &after[end + 1..];
         }
 
+        result.push_str(rest);
         result
     }
 
     /// Fix bb() (blackboard bold)
     pub fn fix_blackboard_bold(&self, input: &str) -> String {
-        let mut result = input.to_string();
+        const PREFIX: &str = "bb(";
+        let mut result = String::with_capacity(input.len());
+        let mut rest = input;
 
-        while let Some(start) = result.find("bb(") {
-            let after = &result[start + 3..];
-            if let Some(end) = self.find_matching_paren(after) {
-                let content = &after[..end];
-                let clean_content: String =
-                    content.chars().filter(|c| !c.is_whitespace()).collect();
-
-                let replacement = match clean_content.as_str() {
-                    "E" => "EE".to_string(),
-                    "P" => "PP".to_string(),
-                    "R" => "RR".to_string(),
-                    "N" => "NN".to_string(),
-                    "Z" => "ZZ".to_string(),
-                    "Q" => "QQ".to_string(),
-                    "C" => "CC".to_string(),
-                    _ => format!("bb({})", clean_content),
-                };
-
-                let total_end = start + 3 + end + 1;
-                result = format!(
-                    "{}{}{}",
-                    &result[..start],
-                    replacement,
-                    &result[total_end..]
-                );
-            } else {
+        while let Some(start) = rest.find(PREFIX) {
+            let after = &rest[start + PREFIX.len()..];
+            let Some(end) = self.find_matching_paren(after) else {
                 break;
+            };
+            let clean_content: String = after[..end]
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            result.push_str(&rest[..start]);
+            match clean_content.as_str() {
+                "E" => result.push_str("EE"),
+                "P" => result.push_str("PP"),
+                "R" => result.push_str("RR"),
+                "N" => result.push_str("NN"),
+                "Z" => result.push_str("ZZ"),
+                "Q" => result.push_str("QQ"),
+                "C" => result.push_str("CC"),
+                _ => {
+                    let _ = write!(result, "bb({})", self.fix_blackboard_bold(&clean_content));
+                }
             }
+            rest = &after[end + 1..];
         }
 
+        result.push_str(rest);
         result
     }
 
@@ -1333,20 +2671,20 @@ impl LatexConverter {
 
         for accent in accents {
             let pattern = format!("{}()", accent);
-            while let Some(pos) = result.find(&pattern) {
-                let after = &result[pos + pattern.len()..];
-                if let Some(first_char) = after.chars().next() {
-                    if first_char.is_alphanumeric() {
-                        let arg_end = self.find_simple_arg_end(after);
-                        let arg = &after[..arg_end];
-                        let replacement = format!("{}({})", accent, arg.trim());
-                        let total = pos + pattern.len() + arg_end;
-                        result = format!("{}{}{}", &result[..pos], replacement, &result[total..]);
-                        continue;
-                    }
+            let mut fixed = String::with_capacity(result.len());
+            let mut rest = result.as_str();
+            while let Some(pos) = rest.find(&pattern) {
+                let after = &rest[pos + pattern.len()..];
+                if !after.chars().next().is_some_and(|c| c.is_alphanumeric()) {
+                    break;
                 }
-                break;
+                let arg_end = self.find_simple_arg_end(after);
+                fixed.push_str(&rest[..pos]);
+                let _ = write!(fixed, "{}({})", accent, after[..arg_end].trim());
+                rest = &after[arg_end..];
             }
+            fixed.push_str(rest);
+            result = fixed;
         }
 
         result
@@ -1437,20 +2775,11 @@ impl LatexConverter {
     // ============================================================
 
     /// Build the final Typst document
-    pub fn build_document(&self, content: String) -> String {
+    pub fn build_document(&mut self, content: String) -> String {
         let mut doc = String::new();
 
         // Document metadata
-        if self.state.title.is_some() || self.state.author.is_some() {
-            doc.push_str("#set document(\n");
-            if let Some(ref title) = self.state.title {
-                let _ = writeln!(doc, "  title: \"{}\",", title.replace('"', "\\\""));
-            }
-            if let Some(ref author) = self.state.author {
-                let _ = writeln!(doc, "  author: \"{}\",", author.replace('"', "\\\""));
-            }
-            doc.push_str(")\n\n");
-        }
+        doc.push_str(&self.document_metadata());
 
         // Style preamble (page / heading / math.equation, plus
         // class-specific imports). Controlled by L2TOptions.preamble.
@@ -1469,27 +2798,53 @@ impl LatexConverter {
             }
         }
 
-        // Title block
-        if self.state.title.is_some() || self.state.author.is_some() {
-            doc.push_str("#align(center)[\n");
-            if let Some(ref title) = self.state.title {
-                let _ = writeln!(doc, "  #text(size: 2em, weight: \"bold\")[{}]", title);
-            }
-            if let Some(ref author) = self.state.author {
-                let _ = write!(doc, "  \n  #text(size: 1.2em)[{}]\n", author);
+        if self.state.uses_wrap_package {
+            doc.push_str("#import \"@preview/wrap-it:0.1.1\": wrap-content\n\n");
+        }
+        if self.state.uses_drafting_package {
+            doc.push_str("#import \"@preview/drafting:0.2.2\": inline-note, margin-note\n\n");
+        }
+        if self.state.uses_mitex_package {
+            doc.push_str("#import \"@preview/mitex:0.2.5\": mi, mitex\n\n");
+        }
+
+        // Line spacing from \linespread / setspace
+        if let Some(stretch) = self.state.line_stretch {
+            let _ = writeln!(doc, "{}\n", line_spacing_rule(stretch));
+        }
+
+        // Typography toggles such as \sloppy
+        for rule in &self.state.typography_rules {
+            let _ = writeln!(doc, "{}\n", rule);
+        }
+
+        // Document language from babel / polyglossia
+        if let Some(language) = &self.state.language {
+            if *language != DateLanguage::default() {
+                let region = language
+                    .region
+                    .as_ref()
+                    .map(|region| format!(", region: \"{}\"", region))
+                    .unwrap_or_default();
+                let _ = writeln!(doc, "#set text(lang: \"{}\"{})\n", language.lang, region);
             }
-            if let Some(ref date) = self.state.date {
-                if date == "\\today" {
-                    doc.push_str("  \n  #datetime.today().display()\n");
-                } else {
-                    let _ = write!(doc, "  \n  {}\n", date);
-                }
+        }
+
+        // Headers and footers from \pagestyle / fancyhdr
+        if let Some(rule) = self.state.page_style.set_rule() {
+            let _ = writeln!(doc, "{}\n", rule);
+        }
+
+        // Macros kept from \newcommand
+        if !self.state.let_macros.is_empty() {
+            for let_macro in &self.state.let_macros {
+                let _ = writeln!(doc, "{}", let_macro.definition());
             }
-            doc.push_str("]\n\n");
+            doc.push('\n');
         }
 
         // Clean up content
-        let cleaned_content = clean_whitespace(&content);
+        let cleaned_content = clean_whitespace(&close_wrapped_paragraphs(&content));
         doc.push_str(&cleaned_content);
 
         // Add warnings as comments
@@ -1503,28 +2858,123 @@ impl LatexConverter {
         clean_whitespace(&doc)
     }
 
-    // ============================================================
-    // Helper methods for submodules
-    // ============================================================
+    /// Render the visible title block for `\maketitle`
+    pub fn title_block(&self) -> String {
+        let mut block = String::new();
+        if self.state.title.is_none() && self.state.author.is_none() {
+            return block;
+        }
+        block.push_str("\n#align(center)[\n");
+        if let Some(ref title) = self.state.title {
+            let notes: String = self.state.title_notes.concat();
+            let _ = writeln!(
+                block,
+                "  #text(size: 2em, weight: \"bold\")[{}{}]",
+                title, notes
+            );
+        }
+        if let Some(ref author) = self.state.author {
+            let _ = write!(block, "  \n  #text(size: 1.2em)[{}]\n", author);
+        }
+        // `\date{}` leaves the date line out
+        match self.state.date.as_deref() {
+            Some("") | None => {}
+            Some("\\today") => {
+                let _ = write!(block, "  \n  {}\n", TODAY_PLACEHOLDER);
+            }
+            Some(date) => {
+                let _ = write!(block, "  \n  {}\n", date);
+            }
+        }
+        block.push_str("]\n\n");
+        block
+    }
 
-    /// Process SI unit string
-    pub fn process_si_unit(&self, input: &str) -> String {
-        let mut result = input.to_string();
+    /// Render `#set document(..)` from `\title`, `\author`, `\date` and
+    /// `\hypersetup`. The hyperref values win when both are given.
+    fn document_metadata(&mut self) -> String {
+        let title = match (self.state.pdf_title.clone(), self.state.title.clone()) {
+            (Some(pdf), Some(title)) if pdf != title => {
+                self.state
+                    .add_warning(ConversionWarning::metadata_conflict("title", &pdf, &title));
+                Some(pdf)
+            }
+            (pdf, title) => pdf.or(title),
+        };
+        let authors = if self.state.pdf_authors.is_empty() {
+            self.state.authors.clone()
+        } else {
+            let pdf = self.state.pdf_authors.clone();
+            if !self.state.authors.is_empty() && self.state.authors != pdf {
+                self.state.add_warning(ConversionWarning::metadata_conflict(
+                    "author",
+                    &pdf.join(", "),
+                    &self.state.authors.join(", "),
+                ));
+            }
+            pdf
+        };
+        // A written date without a day is dated to the first of the month
+        let date = self.state.date.as_deref().and_then(|date| match date {
+            "" => Some("none".to_string()),
+            "\\today" | TODAY_PLACEHOLDER => Some("auto".to_string()),
+            _ => parse_date(date).map(|(year, month, day)| {
+                format!(
+                    "datetime(year: {}, month: {}, day: {})",
+                    year,
+                    month,
+                    day.unwrap_or(1)
+                )
+            }),
+        });
+
+        if title.is_none() && authors.is_empty() && self.state.pdf_keywords.is_empty() {
+            return String::new();
+        }
 
-        for (cmd, val) in crate::siunitx::SI_UNITS.iter() {
-            result = result.replace(cmd, val);
+        // Metadata is a plain string: line breaks and ties become spaces
+        let quote = |s: &str| {
+            let s = s
+                .split("\\ ")
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace('~', " ");
+            let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        let mut doc = String::from("#set document(\n");
+        if let Some(title) = title {
+            let _ = writeln!(doc, "  title: {},", quote(&title));
         }
-        for (cmd, val) in crate::siunitx::SI_PREFIXES.iter() {
-            result = result.replace(cmd, val);
+        match authors.as_slice() {
+            [] => {}
+            [author] => {
+                let _ = writeln!(doc, "  author: {},", quote(author));
+            }
+            _ => {
+                let list: Vec<String> = authors.iter().map(|a| quote(a)).collect();
+                let _ = writeln!(doc, "  author: ({}),", list.join(", "));
+            }
+        }
+        if !self.state.pdf_keywords.is_empty() {
+            let list: Vec<String> = self.state.pdf_keywords.iter().map(|k| quote(k)).collect();
+            let trailing = if list.len() == 1 { "," } else { "" };
+            let _ = writeln!(doc, "  keywords: ({}{}),", list.join(", "), trailing);
         }
+        if let Some(date) = date {
+            let _ = writeln!(doc, "  date: {},", date);
+        }
+        doc.push_str(")\n\n");
+        doc
+    }
 
-        result = result
-            .replace("\\per", "/")
-            .replace("\\squared", "²")
-            .replace("\\cubed", "³")
-            .replace(" ", "");
+    // ============================================================
+    // Helper methods for submodules
+    // ============================================================
 
-        result
+    /// Process SI unit string
+    pub fn process_si_unit(&self, input: &str, options: &SiOptions) -> String {
+        crate::siunitx::unit_to_text(input, &self.state.si_units, options)
     }
 
     /// Extract raw content from a verbatim-like environment
@@ -1549,12 +2999,67 @@ impl LatexConverter {
 
     /// Visit environment content (excluding begin/end)
     pub fn visit_env_content(&mut self, node: &SyntaxNode, output: &mut String) {
+        let start = output.len();
+        self.enter_group();
+        let mut captions = super::markup::CaptionOf::default();
         for child in node.children_with_tokens() {
             match child.kind() {
                 SyntaxKind::ItemBegin | SyntaxKind::ItemEnd => continue,
-                _ => self.visit_element(child, output),
+                _ if captions.consume(self, &child, output) => {}
+                _ => {
+                    let before = output.len();
+                    self.visit_element(child.clone(), output);
+                    captions.visited(self, &child, before, output);
+                    self.block_converted(node, &child);
+                }
             }
         }
+        captions.finish(self, output);
+        self.leave_group(output);
+        // The line break before \end{...} belongs to the closing delimiter
+        let trimmed = output[start..].trim_end_matches([' ', '\n']).len();
+        output.truncate(start + trimmed);
+    }
+
+    /// Enter a TeX group (`{..}`, a command argument or an environment body)
+    pub fn enter_group(&mut self) {
+        self.state.group_depth += 1;
+    }
+
+    /// Leave a TeX group, closing the size switches declared inside it
+    pub fn leave_group(&mut self, output: &mut String) {
+        while self.state.size_scopes.last() == Some(&self.state.group_depth) {
+            self.state.size_scopes.pop();
+            let trimmed = output.trim_end_matches([' ', '\n']).len();
+            output.truncate(trimmed);
+            output.push(']');
+        }
+        let depth = self.state.group_depth;
+        if let Some(pos) = self
+            .state
+            .saved_table_spacing
+            .iter()
+            .position(|(d, _)| *d == depth)
+        {
+            self.state.table_spacing = self.state.saved_table_spacing.remove(pos).1;
+        }
+        self.state.group_depth = self.state.group_depth.saturating_sub(1);
+    }
+
+    /// Table spacing for changes local to the current group
+    pub fn scoped_table_spacing(&mut self) -> &mut TableSpacing {
+        let depth = self.state.group_depth;
+        if depth > 0 && self.state.saved_table_spacing.last().map(|(d, _)| *d) != Some(depth) {
+            let saved = self.state.table_spacing.clone();
+            self.state.saved_table_spacing.push((depth, saved));
+        }
+        &mut self.state.table_spacing
+    }
+
+    /// Apply a size switch such as `\small` to the rest of the current group
+    pub fn open_size_scope(&mut self, pt: f64, output: &mut String) {
+        let _ = write!(output, "#text(size: {}pt)[", pt);
+        self.state.size_scopes.push(self.state.group_depth);
     }
 
     // ============================================================
@@ -1567,7 +3072,11 @@ impl LatexConverter {
     pub fn convert_document_with_diagnostics(&mut self, input: &str) -> ConversionResult {
         let output = self.convert_document(input);
         let warnings = self.state.take_structured_warnings();
-        ConversionResult::with_warnings(output, warnings)
+        let mut result = ConversionResult::with_warnings(output, warnings);
+        result.assets = std::mem::take(&mut self.state.images);
+        result.companion_files = std::mem::take(&mut self.state.companion_files);
+        result.labels = std::mem::take(&mut self.state.labels);
+        result
     }
 
     /// Convert math-only LaTeX to Typst with full diagnostics
@@ -8,7 +8,12 @@ use super::token::{TexToken, TokenList};
 use super::utils;
 use super::ArgumentErrorType;
 use super::EngineWarning;
-use std::collections::HashMap;
+use crate::core::latex2typst::utils::protect_verbatim_chars;
+use fxhash::FxHashMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::iter::Peekable;
+use std::rc::Rc;
 
 /// Errors that can occur during macro argument parsing
 #[derive(Debug, Clone)]
@@ -80,12 +85,60 @@ impl MacroDef {
     }
 }
 
+/// How a macro patching command (`\appto`, `\preto`, ...) modifies its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MacroPatch {
+    /// Insert the code before the existing body instead of after it
+    prepend: bool,
+    /// Define the patched macro globally
+    global: bool,
+    /// Expand the code before inserting it (`\eappto`, `\xappto`)
+    expand: bool,
+    /// The target is given by name (`\csappto{name}`)
+    by_name: bool,
+}
+
+impl MacroPatch {
+    /// Recognize etoolbox patching commands and the kernel's `\g@addto@macro`
+    fn parse(name: &str) -> Option<Self> {
+        if name == "g@addto@macro" {
+            return Some(MacroPatch {
+                prepend: false,
+                global: true,
+                expand: false,
+                by_name: false,
+            });
+        }
+        let (by_name, rest) = match name.strip_prefix("cs") {
+            Some(rest) => (true, rest),
+            None => (false, name),
+        };
+        let (global, expand, rest) = match rest.as_bytes().first() {
+            Some(b'g') => (true, false, &rest[1..]),
+            Some(b'e') => (false, true, &rest[1..]),
+            Some(b'x') => (true, true, &rest[1..]),
+            _ => (false, false, rest),
+        };
+        let prepend = match rest {
+            "appto" => false,
+            "preto" => true,
+            _ => return None,
+        };
+        Some(MacroPatch {
+            prepend,
+            global,
+            expand,
+            by_name,
+        })
+    }
+}
+
 /// Database of macro definitions with scope support
 #[derive(Debug, Clone)]
 pub struct MacroDb {
     // Stack of scopes. The last element is the current (innermost) scope.
     // The first element is the global scope.
-    scopes: Vec<HashMap<String, MacroDef>>,
+    scopes: Vec<FxHashMap<String, MacroDef>>,
 }
 
 impl Default for MacroDb {
@@ -98,13 +151,13 @@ impl MacroDb {
     /// Create a new empty macro database with a global scope
     pub fn new() -> Self {
         MacroDb {
-            scopes: vec![HashMap::new()],
+            scopes: vec![FxHashMap::default()],
         }
     }
 
     /// Push a new local scope
     pub fn push_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(FxHashMap::default());
     }
 
     /// Pop the current local scope
@@ -130,7 +183,7 @@ impl MacroDb {
 
     /// Look up a macro definition, searching from innermost to outermost scope
     pub fn get(&self, name: &str) -> Option<&MacroDef> {
-        for scope in self.scopes.iter().rev() {
+        for scope in self.scopes.iter().rev().filter(|scope| !scope.is_empty()) {
             if let Some(def) = scope.get(name) {
                 return Some(def);
             }
@@ -151,6 +204,295 @@ impl MacroDb {
     }
 }
 
+// ============================================================================
+// Token Stream
+// ============================================================================
+
+/// Input of a single `expand()` run.
+///
+/// The input is a stack of token runs: the tokens passed to `expand()` at
+/// the bottom, and tokens pushed back onto the front of the input above
+/// them, so pushing never copies the rest of the document. A macro body is
+/// read from its shared definition, with each parameter replaced by its
+/// argument when it is reached, so an invocation copies neither the body nor
+/// the arguments. Each run remembers the expansion depth that produced it.
+/// Clones share the same runs, which lets the expansion loop push tokens
+/// while a `Peekable` reads.
+#[derive(Debug, Clone)]
+struct TokenStream {
+    inner: Rc<RefCell<StreamState>>,
+}
+
+#[derive(Debug)]
+struct StreamState {
+    /// Runs still to be read, the next one last
+    runs: Vec<Run>,
+    /// Depth of the most recently yielded token
+    last_depth: usize,
+}
+
+#[derive(Debug)]
+struct Run {
+    source: RunSource,
+    depth: usize,
+}
+
+#[derive(Debug)]
+enum RunSource {
+    /// Tokens owned by the stream
+    Owned(std::vec::IntoIter<TexToken>),
+    /// A single token handed back to the stream
+    Single(Option<TexToken>),
+    /// A shared token list, read from `pos` on as it is
+    Shared { tokens: TokenList, pos: usize },
+    /// A macro body, read from `pos` on with `#n` replaced by argument `n`
+    /// and `##n` by `#n`
+    Body {
+        body: TokenList,
+        pos: usize,
+        args: Vec<TokenList>,
+    },
+}
+
+impl Run {
+    fn is_exhausted(&self) -> bool {
+        match &self.source {
+            RunSource::Owned(tokens) => tokens.len() == 0,
+            RunSource::Single(token) => token.is_none(),
+            RunSource::Shared { tokens, pos } => *pos >= tokens.len(),
+            RunSource::Body { body, pos, .. } => *pos >= body.len(),
+        }
+    }
+}
+
+/// What reading one token of a run gave
+enum Read {
+    Token(TexToken),
+    Argument(TokenList),
+    Exhausted,
+}
+
+impl TokenStream {
+    fn new(tokens: TokenList, depth: usize) -> Self {
+        let stream = TokenStream {
+            inner: Rc::new(RefCell::new(StreamState {
+                runs: Vec::new(),
+                last_depth: depth,
+            })),
+        };
+        stream.push_run(RunSource::Owned(tokens.into_inner().into_iter()), depth);
+        stream
+    }
+
+    /// Depth of the token most recently read from the stream
+    fn last_depth(&self) -> usize {
+        self.inner.borrow().last_depth
+    }
+
+    /// Push tokens onto the front of the stream, ahead of anything
+    /// `iter` has already peeked.
+    fn push_front(&self, iter: &mut Peekable<TokenStream>, tokens: Vec<TexToken>, depth: usize) {
+        self.unpeek(iter);
+        self.push_run(RunSource::Owned(tokens.into_iter()), depth);
+    }
+
+    /// Push a macro body onto the front of the stream, to be read with its
+    /// parameters replaced by `args`
+    fn push_body(
+        &self,
+        iter: &mut Peekable<TokenStream>,
+        body: TokenList,
+        args: Vec<TokenList>,
+        depth: usize,
+    ) {
+        self.unpeek(iter);
+        self.push_run(RunSource::Body { body, pos: 0, args }, depth);
+    }
+
+    /// A peeked token was the last one read, so hand it back first
+    fn unpeek(&self, iter: &mut Peekable<TokenStream>) {
+        if let Some(next) = iter.next() {
+            let next_depth = self.last_depth();
+            self.push_run(RunSource::Single(Some(next)), next_depth);
+        }
+    }
+
+    /// Move up to `limit` tokens that expansion leaves alone (characters,
+    /// spaces, braces, ...) from the front of the stream to `output`,
+    /// stopping at the first token that needs the expansion loop. Braces
+    /// open and close scopes of `db` as they pass. Only valid while no token
+    /// is peeked. Returns the number of tokens moved.
+    fn drain_inert(&self, output: &mut Vec<TexToken>, limit: usize, db: &mut MacroDb) -> usize {
+        // Number of leading tokens of `tokens` that can be moved
+        fn inert_prefix(tokens: &[TexToken], limit: usize, db: &mut MacroDb, body: bool) -> usize {
+            let mut count = 0;
+            for token in tokens.iter().take(limit) {
+                match token {
+                    TexToken::MathShift | TexToken::ControlSeq(_) => break,
+                    TexToken::Param(_) | TexToken::DeferredParam(_) if body => break,
+                    TexToken::BeginGroup => db.push_scope(),
+                    TexToken::EndGroup => db.pop_scope(),
+                    _ => {}
+                }
+                count += 1;
+            }
+            count
+        }
+
+        let mut state = self.inner.borrow_mut();
+        let mut moved = 0;
+        while let Some(run) = state.runs.last_mut() {
+            let depth = run.depth;
+            let available = limit - moved;
+            let (count, exhausted) = match &mut run.source {
+                RunSource::Owned(tokens) => {
+                    let count = inert_prefix(tokens.as_slice(), available, db, false);
+                    output.extend(tokens.by_ref().take(count));
+                    (count, tokens.len() == 0)
+                }
+                RunSource::Single(token) => {
+                    let count = inert_prefix(token.as_slice(), available, db, false);
+                    if count > 0 {
+                        output.extend(token.take());
+                    }
+                    (count, token.is_none())
+                }
+                RunSource::Shared { tokens, pos } => {
+                    let rest = &tokens.as_slice()[*pos..];
+                    let count = inert_prefix(rest, available, db, false);
+                    output.extend_from_slice(&rest[..count]);
+                    *pos += count;
+                    (count, *pos >= tokens.len())
+                }
+                RunSource::Body { body, pos, .. } => {
+                    let rest = &body.as_slice()[*pos..];
+                    let count = inert_prefix(rest, available, db, true);
+                    output.extend_from_slice(&rest[..count]);
+                    *pos += count;
+                    (count, *pos >= body.len())
+                }
+            };
+            if count > 0 {
+                state.last_depth = depth;
+            }
+            moved += count;
+            if !exhausted || moved == limit {
+                break;
+            }
+            state.runs.pop();
+        }
+        moved
+    }
+
+    /// Read the rest of a braced group whose `{` was just read, provided it
+    /// closes within the run at the front of the stream; the `}` is
+    /// consumed. Only valid while no token is peeked.
+    fn read_group(&self) -> Option<TokenList> {
+        fn group_length(tokens: &[TexToken]) -> Option<usize> {
+            let mut depth = 0usize;
+            for (i, token) in tokens.iter().enumerate().take(utils::MAX_ARG_TOKENS) {
+                match token {
+                    TexToken::BeginGroup => depth += 1,
+                    TexToken::EndGroup if depth == 0 => return Some(i),
+                    TexToken::EndGroup => depth -= 1,
+                    _ => {}
+                }
+            }
+            None
+        }
+
+        let mut state = self.inner.borrow_mut();
+        let run = state.runs.last_mut()?;
+        let depth = run.depth;
+        let group = match &mut run.source {
+            RunSource::Owned(tokens) => {
+                let len = group_length(tokens.as_slice())?;
+                let group: Vec<TexToken> = tokens.by_ref().take(len).collect();
+                tokens.next();
+                group
+            }
+            RunSource::Shared { tokens, pos } => {
+                let rest = &tokens.as_slice()[*pos..];
+                let len = group_length(rest)?;
+                let group = rest[..len].to_vec();
+                *pos += len + 1;
+                group
+            }
+            RunSource::Single(_) | RunSource::Body { .. } => return None,
+        };
+        state.last_depth = depth;
+        Some(TokenList::from_vec(group))
+    }
+
+    fn push_run(&self, source: RunSource, depth: usize) {
+        let mut state = self.inner.borrow_mut();
+        // Runs that were read to the end are dropped, so tail-recursive
+        // macros do not pile them up
+        while state.runs.last().is_some_and(Run::is_exhausted) {
+            state.runs.pop();
+        }
+        state.runs.push(Run { source, depth });
+    }
+}
+
+impl Iterator for TokenStream {
+    type Item = TexToken;
+
+    fn next(&mut self) -> Option<TexToken> {
+        let mut state = self.inner.borrow_mut();
+        loop {
+            let run = state.runs.last_mut()?;
+            let depth = run.depth;
+            let read = match &mut run.source {
+                RunSource::Owned(tokens) => tokens.next().map_or(Read::Exhausted, Read::Token),
+                RunSource::Single(token) => token.take().map_or(Read::Exhausted, Read::Token),
+                RunSource::Shared { tokens, pos } => match tokens.as_slice().get(*pos) {
+                    Some(token) => {
+                        *pos += 1;
+                        Read::Token(token.clone())
+                    }
+                    None => Read::Exhausted,
+                },
+                RunSource::Body { body, pos, args } => match body.as_slice().get(*pos) {
+                    Some(token) => {
+                        *pos += 1;
+                        match token {
+                            TexToken::Param(n) => (*n as usize)
+                                .checked_sub(1)
+                                .and_then(|i| args.get(i))
+                                .map_or(Read::Argument(TokenList::new()), |arg| {
+                                    Read::Argument(arg.clone())
+                                }),
+                            // Nested definitions: `##1` becomes `#1` for the
+                            // inner macro
+                            TexToken::DeferredParam(n) => Read::Token(TexToken::Param(*n)),
+                            token => Read::Token(token.clone()),
+                        }
+                    }
+                    None => Read::Exhausted,
+                },
+            };
+            match read {
+                Read::Token(token) => {
+                    state.last_depth = depth;
+                    return Some(token);
+                }
+                Read::Argument(tokens) => {
+                    if !tokens.is_empty() {
+                        state.runs.push(Run {
+                            source: RunSource::Shared { tokens, pos: 0 },
+                            depth,
+                        });
+                    }
+                }
+                Read::Exhausted => {
+                    state.runs.pop();
+                }
+            }
+        }
+    }
+}
+
 // ============================================================================
 // Engine Constants
 // ============================================================================
@@ -208,6 +550,12 @@ pub struct ExpansionState {
     pub db: MacroDb,
     /// Whether `@` is treated as a letter (for `\makeatletter`).
     pub at_is_letter: bool,
+    /// Whether `\begin{document}` has been seen.
+    pub document_begun: bool,
+    /// Tokens deferred by `\AtBeginDocument`, expanded at `\begin{document}`.
+    pub begin_document_hook: Vec<TexToken>,
+    /// Tokens deferred by `\AtEndDocument`, expanded at `\end{document}`.
+    pub end_document_hook: Vec<TexToken>,
 }
 
 impl ExpansionState {
@@ -224,6 +572,8 @@ impl ExpansionState {
 pub struct ExpansionContext {
     /// Current token count during expansion (for limit checking).
     pub current_token_count: usize,
+    /// Number of tokens in the top-level input, which the limit is added to.
+    pub input_token_count: usize,
     /// Collected structured warnings during expansion.
     pub structured_warnings: Vec<EngineWarning>,
 }
@@ -356,6 +706,7 @@ impl Engine {
     pub fn process(&mut self, tokens: TokenList) -> TokenList {
         // Reset token count for each top-level process call
         self.context.reset();
+        self.context.input_token_count = tokens.len();
         self.expand(tokens, 0)
     }
 
@@ -369,12 +720,17 @@ impl Engine {
         }
 
         let mut result = Vec::new();
-        let mut iter = tokens.into_inner().into_iter().peekable();
+        let stream = TokenStream::new(tokens, depth);
+        let mut iter = stream.clone().peekable();
+        let token_limit = self.config.max_tokens + self.context.input_token_count;
 
         while let Some(token) = iter.next() {
+            // Expansion depth of this token; tokens from macro bodies are one deeper
+            let depth = stream.last_depth();
+
             // Check token limit to prevent exponential expansion
             self.context.current_token_count += 1;
-            if self.context.current_token_count > self.config.max_tokens {
+            if self.context.current_token_count > token_limit {
                 self.push_warning(EngineWarning::TokenLimitExceeded {
                     max_tokens: self.config.max_tokens,
                 });
@@ -427,9 +783,15 @@ impl Engine {
                     if let Some(TexToken::ControlSeq(next_name)) = iter.peek() {
                         if primitives::is_definition_command(next_name) {
                             if let Some(TexToken::ControlSeq(cmd_name)) = iter.next() {
-                                let rest =
-                                    self.handle_definition(&cmd_name, &mut iter, true, depth);
-                                result.extend(rest.into_inner());
+                                if let Some(rest) = self.handle_definition(
+                                    &cmd_name,
+                                    &mut iter,
+                                    true,
+                                    depth,
+                                    &mut result,
+                                ) {
+                                    stream.push_front(&mut iter, rest.into_inner(), depth);
+                                }
                             }
                         } else {
                             result.push(token.clone());
@@ -439,20 +801,32 @@ impl Engine {
                     }
                 }
                 TexToken::ControlSeq(name) if primitives::is_definition_command(name) => {
-                    let rest = self.handle_definition(name, &mut iter, false, depth);
-                    result.extend(rest.into_inner());
+                    if let Some(rest) =
+                        self.handle_definition(name, &mut iter, false, depth, &mut result)
+                    {
+                        stream.push_front(&mut iter, rest.into_inner(), depth);
+                    }
                 }
                 TexToken::ControlSeq(name) if name == "begin" => {
                     if let Some(env_name) = self.read_env_name(&mut iter) {
                         let (tokens, _scope_pushed) =
                             self.handle_begin_environment(&env_name, &mut iter, depth);
                         result.extend(tokens);
+                        if env_name == "document" && !self.state.document_begun {
+                            self.state.document_begun = true;
+                            let hook = std::mem::take(&mut self.state.begin_document_hook);
+                            result.extend(self.expand(TokenList::from_vec(hook), depth + 1));
+                        }
                     } else {
                         result.push(token);
                     }
                 }
                 TexToken::ControlSeq(name) if name == "end" => {
                     if let Some(env_name) = self.read_env_name(&mut iter) {
+                        if env_name == "document" {
+                            let hook = std::mem::take(&mut self.state.end_document_hook);
+                            result.extend(self.expand(TokenList::from_vec(hook), depth + 1));
+                        }
                         let tokens = self.handle_end_environment(&env_name, &mut iter, depth);
                         result.extend(tokens);
                     } else {
@@ -474,10 +848,7 @@ impl Engine {
                             // Reconstruct stream: t1 + expanded_t2 + Rest
                             let mut new_tokens = vec![t1];
                             new_tokens.extend(expanded_t2.into_inner());
-                            new_tokens.extend(iter); // Consumes the rest
-
-                            // Continue expansion
-                            return self.expand(TokenList::from_vec(new_tokens), depth);
+                            stream.push_front(&mut iter, new_tokens, depth);
                         } else {
                             result.push(t1);
                         }
@@ -486,13 +857,35 @@ impl Engine {
                 TexToken::ControlSeq(name) if name == "csname" => {
                     if let Some(expanded_cs) = self.process_csname(&mut iter, depth) {
                         // Reconstruct stream: [expanded_cs] + Rest
-                        let mut new_tokens = vec![expanded_cs];
-                        new_tokens.extend(iter);
-                        return self.expand(TokenList::from_vec(new_tokens), depth);
+                        stream.push_front(&mut iter, vec![expanded_cs], depth);
                     } else {
                         result.push(token.clone());
                     }
                 }
+                TexToken::ControlSeq(name) if name == "ifdefined" => {
+                    utils::skip_spaces(&mut iter);
+                    let defined = match iter.next() {
+                        Some(TexToken::ControlSeq(cs)) => {
+                            let cs = if self.state.at_is_letter {
+                                self.merge_at_letters(cs, &mut iter)
+                            } else {
+                                cs
+                            };
+                            self.is_defined(&cs)
+                        }
+                        _ => false,
+                    };
+                    let branch = self.handle_if_conditional(defined, &mut iter);
+                    stream.push_front(&mut iter, branch.into_inner(), depth);
+                }
+                TexToken::ControlSeq(name) if name == "ifcsname" => {
+                    let defined = matches!(
+                        self.process_csname(&mut iter, depth),
+                        Some(TexToken::ControlSeq(cs)) if self.is_defined(&cs)
+                    );
+                    let branch = self.handle_if_conditional(defined, &mut iter);
+                    stream.push_front(&mut iter, branch.into_inner(), depth);
+                }
                 TexToken::ControlSeq(name) if name == "makeatletter" => {
                     // Enable @ as a letter in control sequence names
                     self.state.at_is_letter = true;
@@ -522,14 +915,14 @@ impl Engine {
                 }
                 TexToken::ControlSeq(name) => {
                     // If at_is_letter is true, try to merge @ and subsequent letters
-                    let merged_name = if self.state.at_is_letter {
-                        self.merge_at_letters(name.clone(), &mut iter)
+                    let merged_name: Cow<str> = if self.state.at_is_letter {
+                        Cow::Owned(self.merge_at_letters(name.clone(), &mut iter))
                     } else {
-                        name.clone()
+                        Cow::Borrowed(name)
                     };
 
                     if let Some(macro_def) = self.state.db.get(&merged_name).cloned() {
-                        match self.parse_arguments(&mut iter, &macro_def) {
+                        match self.parse_arguments(&mut iter, &macro_def, Some(&stream)) {
                             Ok(args) => {
                                 // Check depth limit BEFORE recursing - output args directly if exceeded
                                 if depth + 1 > self.config.max_depth {
@@ -543,16 +936,10 @@ impl Engine {
                                     continue;
                                 }
 
-                                let expanded_body = self.substitute_args(&macro_def.body, &args);
-                                // TeX semantics: insert expanded tokens at front of input stream
-                                // and continue processing. This is crucial for macros that expand
-                                // to special commands like \iftrue, \iffalse, etc.
-                                let mut new_tokens = expanded_body.into_inner();
-                                new_tokens.extend(iter);
-                                let fully_expanded =
-                                    self.expand(TokenList::from_vec(new_tokens), depth + 1);
-                                result.extend(fully_expanded.into_inner());
-                                return TokenList::from_vec(result);
+                                // TeX semantics: insert the body at the front of the input
+                                // stream and continue processing. This is crucial for macros
+                                // that expand to special commands like \iftrue, \iffalse, etc.
+                                stream.push_body(&mut iter, macro_def.body, args, depth + 1);
                             }
                             Err((err, consumed)) => {
                                 // Push warning and rollback: output macro name and consumed tokens as raw text
@@ -560,17 +947,29 @@ impl Engine {
                                 // Continue processing remaining tokens
                             }
                         }
+                    } else if let Some(tokens) =
+                        self.handle_hook_macro(&merged_name, &mut iter, depth)
+                    {
+                        result.extend(tokens);
                     } else if let Some(special_result) =
                         self.handle_special_macro(&merged_name, &mut iter)
                     {
                         let fully_expanded = self.expand(special_result, depth + 1);
                         result.extend(fully_expanded.into_inner());
                     } else {
-                        result.push(TexToken::ControlSeq(merged_name));
+                        match merged_name {
+                            Cow::Borrowed(_) => result.push(token),
+                            Cow::Owned(name) => result.push(TexToken::ControlSeq(name)),
+                        }
                     }
                 }
                 _ => {
-                    result.push(token.clone());
+                    result.push(token);
+                    // Nothing is peeked after a plain token, so the text
+                    // that follows can be copied without the loop
+                    let room = token_limit.saturating_sub(self.context.current_token_count);
+                    self.context.current_token_count +=
+                        stream.drain_inert(&mut result, room, &mut self.state.db);
                 }
             }
         }
@@ -614,7 +1013,7 @@ impl Engine {
                 .map(|cs| TokenList::from_vec(vec![cs])),
             TexToken::ControlSeq(ref name) => {
                 if let Some(macro_def) = self.state.db.get(name).cloned() {
-                    match self.parse_arguments(iter, &macro_def) {
+                    match self.parse_arguments(iter, &macro_def, None) {
                         Ok(args) => Some(self.substitute_args(&macro_def.body, &args)),
                         Err((err, consumed)) => {
                             // Push warning and return original token + consumed as unexpanded
@@ -714,241 +1113,260 @@ impl Engine {
         if found_end {
             // Expand the content
             let expanded_cs = self.expand(TokenList::from_vec(cs_tokens), depth + 1);
-            let mut cs_name = String::new();
-            for t in expanded_cs.into_inner() {
-                match t {
-                    TexToken::Char(c) => cs_name.push(c),
-                    TexToken::Space => cs_name.push(' '),
-                    TexToken::ControlSeq(n) => cs_name.push_str(&n),
-                    _ => {}
-                }
-            }
-            Some(TexToken::ControlSeq(cs_name))
+            Some(TexToken::ControlSeq(Self::cs_name_from_tokens(
+                expanded_cs.into_inner(),
+            )))
         } else {
             None
         }
     }
 
-    /// Helper to handle definition commands
+    /// Build a control sequence name from already expanded tokens
+    fn cs_name_from_tokens(tokens: Vec<TexToken>) -> String {
+        let mut cs_name = String::new();
+        for t in tokens {
+            match t {
+                TexToken::Char(c) => cs_name.push(c),
+                TexToken::Space => cs_name.push(' '),
+                TexToken::ControlSeq(n) => cs_name.push_str(&n),
+                _ => {}
+            }
+        }
+        cs_name
+    }
+
+    /// Helper to handle definition commands.
+    ///
+    /// Reads the definition from `iter`. While `@` is a letter the rest of
+    /// the input is consumed to merge `@` into names, and the tokens that
+    /// follow the definition are returned for the caller to push back onto
+    /// its stream.
     fn handle_definition<I>(
         &mut self,
         name: &str,
         iter: &mut std::iter::Peekable<I>,
         is_global: bool,
         depth: usize,
-    ) -> TokenList
+        result: &mut Vec<TexToken>,
+    ) -> Option<TokenList>
     where
         I: Iterator<Item = TexToken>,
     {
-        let remaining: Vec<TexToken> = iter.collect();
-
-        // If at_is_letter is true, pre-process tokens to merge @ into control sequences
-        let remaining_list = if self.state.at_is_letter {
-            self.merge_at_in_tokens(remaining)
+        // If at_is_letter is true, pre-process tokens to merge @ into control
+        // sequences; the definition is then read from the merged tokens and
+        // the rest of them handed back
+        let (definition, rest) = if self.state.at_is_letter {
+            let merged = self.merge_at_in_tokens(iter.collect());
+            let mut merged = merged.into_inner().into_iter().peekable();
+            let definition = primitives::read_definition(name, &mut merged);
+            (definition, Some(TokenList::from_vec(merged.collect())))
         } else {
-            TokenList::from_vec(remaining)
+            (primitives::read_definition(name, iter), None)
         };
 
-        match primitives::parse_definition(name, remaining_list) {
-            Ok((def_kind, rest)) => {
-                match def_kind {
-                    DefinitionKind::NewCommand {
-                        name,
-                        num_args,
-                        default,
-                        body,
+        if let Some(def_kind) = definition {
+            match def_kind {
+                DefinitionKind::NewCommand {
+                    name,
+                    num_args,
+                    default,
+                    body,
+                }
+                | DefinitionKind::RenewCommand {
+                    name,
+                    num_args,
+                    default,
+                    body,
+                }
+                | DefinitionKind::ProvideCommand {
+                    name,
+                    num_args,
+                    default,
+                    body,
+                } => {
+                    if num_args == 0 {
+                        Self::keep_parameter_definition(&name, &body, result);
                     }
-                    | DefinitionKind::RenewCommand {
-                        name,
-                        num_args,
-                        default,
-                        body,
+                    let macro_def = if let Some(def) = default {
+                        MacroDef::with_default(num_args, def, body)
+                    } else {
+                        MacroDef::new(num_args, body)
+                    };
+                    if is_global {
+                        self.state.db.define_global(name, macro_def);
+                    } else {
+                        self.state.db.define(name, macro_def);
                     }
-                    | DefinitionKind::ProvideCommand {
-                        name,
-                        num_args,
-                        default,
-                        body,
-                    } => {
-                        let macro_def = if let Some(def) = default {
-                            MacroDef::with_default(num_args, def, body)
-                        } else {
-                            MacroDef::new(num_args, body)
-                        };
-                        if is_global {
-                            self.state.db.define_global(name, macro_def);
-                        } else {
-                            self.state.db.define(name, macro_def);
-                        }
+                }
+                DefinitionKind::Def {
+                    name,
+                    signature,
+                    body,
+                } => {
+                    if matches!(signature, MacroSignature::Simple(0)) {
+                        Self::keep_parameter_definition(&name, &body, result);
                     }
-                    DefinitionKind::Def {
-                        name,
-                        signature,
-                        body,
-                    } => {
-                        let def = MacroDef::from_signature(signature, body);
-                        if is_global {
-                            self.state.db.define_global(name, def);
-                        } else {
-                            self.state.db.define(name, def);
-                        }
+                    let def = MacroDef::from_signature(signature, body);
+                    if is_global {
+                        self.state.db.define_global(name, def);
+                    } else {
+                        self.state.db.define(name, def);
                     }
-                    DefinitionKind::Edef {
-                        name,
-                        signature,
-                        body,
-                    } => {
-                        let expanded_body = self.expand(body, depth + 1);
-                        let def = MacroDef::from_signature(signature, expanded_body);
-                        if is_global {
-                            self.state.db.define_global(name, def);
-                        } else {
-                            self.state.db.define(name, def);
-                        }
+                }
+                DefinitionKind::Edef {
+                    name,
+                    signature,
+                    body,
+                } => {
+                    let expanded_body = self.expand(body, depth + 1);
+                    let def = MacroDef::from_signature(signature, expanded_body);
+                    if is_global {
+                        self.state.db.define_global(name, def);
+                    } else {
+                        self.state.db.define(name, def);
                     }
-                    DefinitionKind::Let { name, target } => {
-                        if let Some(def) = self.state.db.get(&target).cloned() {
-                            if is_global {
-                                self.state.db.define_global(name.clone(), def);
-                            } else {
-                                self.state.db.define(name.clone(), def);
-                            }
+                }
+                DefinitionKind::Let { name, target } => {
+                    if let Some(def) = self.state.db.get(&target).cloned() {
+                        if is_global {
+                            self.state.db.define_global(name.clone(), def);
                         } else {
-                            // Target not found - warn user that \let to built-in commands doesn't work
-                            self.push_warning(EngineWarning::LetTargetNotFound {
-                                name: name.clone(),
-                                target: target.clone(),
-                            });
+                            self.state.db.define(name.clone(), def);
                         }
+                    } else {
+                        // Target not found - warn user that \let to built-in commands doesn't work
+                        self.push_warning(EngineWarning::LetTargetNotFound {
+                            name: name.clone(),
+                            target: target.clone(),
+                        });
                     }
-                    DefinitionKind::NewEnvironment {
-                        name,
-                        num_args,
-                        default,
-                        begin_body,
-                        end_body,
+                }
+                DefinitionKind::NewEnvironment {
+                    name,
+                    num_args,
+                    default,
+                    begin_body,
+                    end_body,
+                }
+                | DefinitionKind::RenewEnvironment {
+                    name,
+                    num_args,
+                    default,
+                    begin_body,
+                    end_body,
+                } => {
+                    let begin_def = if let Some(def) = default {
+                        MacroDef::with_default(num_args, def, begin_body)
+                    } else {
+                        MacroDef::new(num_args, begin_body)
+                    };
+                    if is_global {
+                        self.state.db.define_global(name.clone(), begin_def);
+                    } else {
+                        self.state.db.define(name.clone(), begin_def);
                     }
-                    | DefinitionKind::RenewEnvironment {
-                        name,
-                        num_args,
-                        default,
-                        begin_body,
-                        end_body,
-                    } => {
-                        let begin_def = if let Some(def) = default {
-                            MacroDef::with_default(num_args, def, begin_body)
-                        } else {
-                            MacroDef::new(num_args, begin_body)
-                        };
-                        if is_global {
-                            self.state.db.define_global(name.clone(), begin_def);
-                        } else {
-                            self.state.db.define(name.clone(), begin_def);
-                        }
 
-                        let end_def = MacroDef::new(0, end_body);
-                        if is_global {
-                            self.state.db.define_global(format!("end{}", name), end_def);
-                        } else {
-                            self.state.db.define(format!("end{}", name), end_def);
-                        }
+                    let end_def = MacroDef::new(0, end_body);
+                    if is_global {
+                        self.state.db.define_global(format!("end{}", name), end_def);
+                    } else {
+                        self.state.db.define(format!("end{}", name), end_def);
                     }
-                    DefinitionKind::NewIf { base_name } => {
-                        // \newif\iffoo creates:
-                        // 1. \iffoo -> \iffalse (initial state)
-                        // 2. \footrue -> \def\iffoo{\iftrue}
-                        // 3. \foofalse -> \def\iffoo{\iffalse}
-
-                        let if_name = format!("if{}", base_name);
-
-                        // \iffoo initially expands to \iffalse
-                        let initial_def = MacroDef::new(
-                            0,
-                            TokenList::from_vec(vec![TexToken::ControlSeq("iffalse".to_string())]),
-                        );
-
-                        // \footrue defines \iffoo as \iftrue
-                        let true_body = TokenList::from_vec(vec![
-                            TexToken::ControlSeq("def".to_string()),
-                            TexToken::ControlSeq(if_name.clone()),
-                            TexToken::BeginGroup,
-                            TexToken::ControlSeq("iftrue".to_string()),
-                            TexToken::EndGroup,
-                        ]);
-                        let true_def = MacroDef::new(0, true_body);
-
-                        // \foofalse defines \iffoo as \iffalse
-                        let false_body = TokenList::from_vec(vec![
-                            TexToken::ControlSeq("def".to_string()),
-                            TexToken::ControlSeq(if_name.clone()),
-                            TexToken::BeginGroup,
-                            TexToken::ControlSeq("iffalse".to_string()),
-                            TexToken::EndGroup,
-                        ]);
-                        let false_def = MacroDef::new(0, false_body);
-
-                        if is_global {
-                            self.state.db.define_global(if_name, initial_def);
-                            self.state
-                                .db
-                                .define_global(format!("{}true", base_name), true_def);
-                            self.state
-                                .db
-                                .define_global(format!("{}false", base_name), false_def);
-                        } else {
-                            self.state.db.define(if_name, initial_def);
-                            self.state.db.define(format!("{}true", base_name), true_def);
-                            self.state
-                                .db
-                                .define(format!("{}false", base_name), false_def);
-                        }
+                }
+                DefinitionKind::NewIf { base_name } => {
+                    // \newif\iffoo creates:
+                    // 1. \iffoo -> \iffalse (initial state)
+                    // 2. \footrue -> \def\iffoo{\iftrue}
+                    // 3. \foofalse -> \def\iffoo{\iffalse}
+
+                    let if_name = format!("if{}", base_name);
+
+                    // \iffoo initially expands to \iffalse
+                    let initial_def = MacroDef::new(
+                        0,
+                        TokenList::from_vec(vec![TexToken::ControlSeq("iffalse".to_string())]),
+                    );
+
+                    // \footrue defines \iffoo as \iftrue
+                    let true_body = TokenList::from_vec(vec![
+                        TexToken::ControlSeq("def".to_string()),
+                        TexToken::ControlSeq(if_name.clone()),
+                        TexToken::BeginGroup,
+                        TexToken::ControlSeq("iftrue".to_string()),
+                        TexToken::EndGroup,
+                    ]);
+                    let true_def = MacroDef::new(0, true_body);
+
+                    // \foofalse defines \iffoo as \iffalse
+                    let false_body = TokenList::from_vec(vec![
+                        TexToken::ControlSeq("def".to_string()),
+                        TexToken::ControlSeq(if_name.clone()),
+                        TexToken::BeginGroup,
+                        TexToken::ControlSeq("iffalse".to_string()),
+                        TexToken::EndGroup,
+                    ]);
+                    let false_def = MacroDef::new(0, false_body);
+
+                    if is_global {
+                        self.state.db.define_global(if_name, initial_def);
+                        self.state
+                            .db
+                            .define_global(format!("{}true", base_name), true_def);
+                        self.state
+                            .db
+                            .define_global(format!("{}false", base_name), false_def);
+                    } else {
+                        self.state.db.define(if_name, initial_def);
+                        self.state.db.define(format!("{}true", base_name), true_def);
+                        self.state
+                            .db
+                            .define(format!("{}false", base_name), false_def);
                     }
-                    DefinitionKind::DeclareMathOperator {
-                        name,
-                        body,
-                        is_starred,
-                    } => {
-                        // \DeclareMathOperator{\name}{text} expands to:
-                        // \newcommand{\name}{\operatorname{text}}
-                        // This produces op("text") in Typst, which is the correct representation.
-                        // \DeclareMathOperator*{\name}{text} expands to:
-                        // \newcommand{\name}{\operatorname*{text}}
-                        // This produces limits(op("text")) in Typst.
-                        let op_cmd = if is_starred {
-                            "operatorname*"
-                        } else {
-                            "operatorname"
-                        };
-
-                        let mut expanded_body = vec![
-                            TexToken::ControlSeq(op_cmd.to_string()),
-                            TexToken::BeginGroup,
-                        ];
-                        // Filter out spacing commands like \, from the operator name
-                        for token in body.into_inner() {
-                            match &token {
-                                TexToken::ControlSeq(cs)
-                                    if cs == "," || cs == ";" || cs == "!" || cs == " " =>
-                                {
-                                    // Skip thin/medium/negative spaces in operator names
-                                }
-                                _ => expanded_body.push(token),
+                }
+                DefinitionKind::DeclareMathOperator {
+                    name,
+                    body,
+                    is_starred,
+                } => {
+                    // \DeclareMathOperator{\name}{text} expands to:
+                    // \newcommand{\name}{\operatorname{text}}
+                    // This produces op("text") in Typst, which is the correct representation.
+                    // \DeclareMathOperator*{\name}{text} expands to:
+                    // \newcommand{\name}{\operatorname*{text}}
+                    // This produces limits(op("text")) in Typst.
+                    let op_cmd = if is_starred {
+                        "operatorname*"
+                    } else {
+                        "operatorname"
+                    };
+
+                    let mut expanded_body = vec![
+                        TexToken::ControlSeq(op_cmd.to_string()),
+                        TexToken::BeginGroup,
+                    ];
+                    // Filter out spacing commands like \, from the operator name
+                    for token in body.into_inner() {
+                        match &token {
+                            TexToken::ControlSeq(cs)
+                                if cs == "," || cs == ";" || cs == "!" || cs == " " =>
+                            {
+                                // Skip thin/medium/negative spaces in operator names
                             }
+                            _ => expanded_body.push(token),
                         }
-                        expanded_body.push(TexToken::EndGroup);
+                    }
+                    expanded_body.push(TexToken::EndGroup);
 
-                        let def = MacroDef::new(0, TokenList::from_vec(expanded_body));
-                        if is_global {
-                            self.state.db.define_global(name, def);
-                        } else {
-                            self.state.db.define(name, def);
-                        }
+                    let def = MacroDef::new(0, TokenList::from_vec(expanded_body));
+                    if is_global {
+                        self.state.db.define_global(name, def);
+                    } else {
+                        self.state.db.define(name, def);
                     }
                 }
-                self.expand(rest, depth)
             }
-            Err(remaining) => self.expand(remaining, depth),
         }
+        rest
     }
 
     /// Read an environment name from {envname}
@@ -1009,7 +1427,7 @@ impl Engine {
         I: Iterator<Item = TexToken>,
     {
         if let Some(macro_def) = self.state.db.get(env_name).cloned() {
-            match self.parse_arguments(iter, &macro_def) {
+            match self.parse_arguments(iter, &macro_def, None) {
                 Ok(args) => {
                     let expanded_body = self.substitute_args(&macro_def.body, &args);
                     let fully_expanded = self.expand(expanded_body, depth + 1);
@@ -1084,6 +1502,120 @@ impl Engine {
         tokens
     }
 
+    // ========================================================================
+    // Hook and Patch Handling
+    // ========================================================================
+
+    /// Handle document hooks (`\AtBeginDocument`, `\AtEndDocument`) and
+    /// macro patching commands (`\appto`, `\preto`, `\g@addto@macro`, ...).
+    ///
+    /// Returns `None` if `name` is not one of these commands, otherwise the
+    /// tokens to emit in its place.
+    fn handle_hook_macro<I>(
+        &mut self,
+        name: &str,
+        iter: &mut std::iter::Peekable<I>,
+        depth: usize,
+    ) -> Option<Vec<TexToken>>
+    where
+        I: Iterator<Item = TexToken>,
+    {
+        match name {
+            "AtBeginDocument" | "AtEndDocument" => {
+                let (content, _) = self.read_argument_tracked(iter);
+                let content = self.merge_at_in_tokens(content).into_inner();
+                if name == "AtBeginDocument" && self.state.document_begun {
+                    // The document has already started, so the hook runs in place
+                    return Some(
+                        self.expand(TokenList::from_vec(content), depth + 1)
+                            .into_inner(),
+                    );
+                }
+                if name == "AtBeginDocument" {
+                    self.state.begin_document_hook.extend(content);
+                } else {
+                    self.state.end_document_hook.extend(content);
+                }
+                Some(Vec::new())
+            }
+            _ => {
+                let patch = MacroPatch::parse(name)?;
+                self.apply_macro_patch(name, &patch, iter, depth);
+                Some(Vec::new())
+            }
+        }
+    }
+
+    /// Read the target and code of a patching command and update the macro.
+    ///
+    /// Undefined targets are defined with the code as their body, as etoolbox does.
+    fn apply_macro_patch<I>(
+        &mut self,
+        name: &str,
+        patch: &MacroPatch,
+        iter: &mut std::iter::Peekable<I>,
+        depth: usize,
+    ) where
+        I: Iterator<Item = TexToken>,
+    {
+        let target = if patch.by_name {
+            let (content, _) = self.read_argument_tracked(iter);
+            let expanded = self.expand(TokenList::from_vec(content), depth + 1);
+            Some(Self::cs_name_from_tokens(expanded.into_inner()))
+        } else {
+            let (content, _) = self.read_argument_tracked(iter);
+            match content.as_slice() {
+                // `\appto\my@macro{..}`: the argument stops at `\my`, so merge the rest
+                [TexToken::ControlSeq(cs)] if self.state.at_is_letter => {
+                    Some(self.merge_at_letters(cs.clone(), iter))
+                }
+                [TexToken::ControlSeq(cs)] => Some(cs.clone()),
+                _ => match self.merge_at_in_tokens(content).as_slice() {
+                    [TexToken::ControlSeq(cs)] => Some(cs.clone()),
+                    _ => None,
+                },
+            }
+        };
+
+        let (code, _) = self.read_argument_tracked(iter);
+
+        let Some(target) = target.filter(|t| !t.is_empty()) else {
+            // Drop the command rather than leaking it into the body
+            self.push_warning(EngineWarning::ArgumentParsingFailed {
+                macro_name: name.to_string(),
+                error_kind: ArgumentErrorType::Other(
+                    "expected a control sequence to patch".to_string(),
+                ),
+            });
+            return;
+        };
+
+        let mut code = self.merge_at_in_tokens(code);
+        if patch.expand {
+            code = self.expand(code, depth + 1);
+        }
+
+        let def = match self.state.db.get(&target).cloned() {
+            Some(mut def) => {
+                let mut body = def.body.into_inner();
+                if patch.prepend {
+                    body.splice(0..0, code.into_inner());
+                } else {
+                    body.extend(code.into_inner());
+                }
+                def.body = TokenList::from_vec(body);
+                def
+            }
+            None => MacroDef::new(0, code),
+        };
+
+        if patch.global {
+            self.state.db.define_global(target, def);
+        } else {
+            self.state.db.define(target, def);
+        }
+    }
+
     // ========================================================================
     // Special Macro Handling
     // ========================================================================
@@ -1098,10 +1630,29 @@ impl Engine {
         I: Iterator<Item = TexToken>,
     {
         match name {
+            // The next token, or the argument, as the characters it was written with
+            "string" => iter.next().map(|token| literal_tokens(&[token])),
+            "detokenize" => Some(literal_tokens(utils::read_argument(iter).as_slice())),
             "xspace" => {
-                // xspace inserts a space if the next token is alphanumeric
-                let insert_space =
-                    matches!(iter.peek(), Some(TexToken::Char(c)) if c.is_alphanumeric());
+                // xspace inserts a space unless the next token is punctuation,
+                // a group, a space or a command that starts no word
+                let insert_space = match iter.peek() {
+                    Some(TexToken::Char(c)) => !",.'/?;:!-)]".contains(*c),
+                    Some(TexToken::ControlSeq(name)) => !matches!(
+                        name.as_str(),
+                        " " | "/"
+                            | "\\"
+                            | "footnote"
+                            | "footnotemark"
+                            | "space"
+                            | "xspace"
+                            | "par"
+                            | "begin"
+                            | "end"
+                    ),
+                    Some(TexToken::MathShift) => true,
+                    _ => false,
+                };
                 if insert_space {
                     Some(TokenList::from_vec(vec![TexToken::Space]))
                 } else {
@@ -1113,6 +1664,7 @@ impl Engine {
             "ifmmode" => Some(self.handle_if_conditional(self.config.math_mode, iter)),
             "ifx" => self.handle_ifx(iter),
             "ifstrequal" => self.handle_ifstrequal(iter),
+            "@ifundefined" => Some(self.handle_ifundefined(iter)),
             "else" | "fi" => Some(TokenList::new()),
             _ => None,
         }
@@ -1131,8 +1683,7 @@ impl Engine {
         for token in iter.by_ref() {
             match &token {
                 TexToken::ControlSeq(cmd) => match cmd.as_str() {
-                    "iftrue" | "iffalse" | "ifmmode" | "ifstrequal" | "ifx" | "if" | "ifnum"
-                    | "ifdim" | "ifcat" | "ifvoid" | "ifhbox" | "ifvbox" | "ifinner" | "ifcase" => {
+                    cmd if self.is_conditional(cmd) => {
                         depth += 1;
                         if in_else {
                             false_branch.push(token);
@@ -1186,6 +1737,68 @@ impl Engine {
         }
     }
 
+    /// Whether a control sequence opens an `\if...\fi` block: a TeX primitive
+    /// conditional or one declared with `\newif`. Skipped branches count
+    /// these so that a nested `\fi` does not close the outer conditional.
+    fn is_conditional(&self, name: &str) -> bool {
+        let primitive = matches!(
+            name,
+            "if" | "ifcat"
+                | "ifnum"
+                | "ifdim"
+                | "ifodd"
+                | "ifvmode"
+                | "ifhmode"
+                | "ifmmode"
+                | "ifinner"
+                | "ifvoid"
+                | "ifhbox"
+                | "ifvbox"
+                | "ifx"
+                | "ifeof"
+                | "iftrue"
+                | "iffalse"
+                | "ifcase"
+                | "ifdefined"
+                | "ifcsname"
+                | "iffontchar"
+        );
+        primitive
+            || self.state.db.get(name).is_some_and(|def| {
+                matches!(
+                    def.body.as_slice(),
+                    [TexToken::ControlSeq(body)] if body == "iftrue" || body == "iffalse"
+                )
+            })
+    }
+
+    /// Whether a control sequence counts as defined for `\ifdefined`,
+    /// `\ifcsname` and `\@ifundefined`. Only macros in the database are
+    /// known: built-in commands such as `\chapter` or `\pdfoutput` count as
+    /// undefined, so guarded fallback definitions are kept.
+    fn is_defined(&self, name: &str) -> bool {
+        self.state.db.get(name).is_some()
+    }
+
+    /// Handle \@ifundefined{name}{true}{false}
+    fn handle_ifundefined<I>(&self, iter: &mut std::iter::Peekable<I>) -> TokenList
+    where
+        I: Iterator<Item = TexToken>,
+    {
+        utils::skip_spaces(iter);
+        let name = Self::cs_name_from_tokens(utils::read_argument(iter).into_inner());
+        utils::skip_spaces(iter);
+        let true_branch = utils::read_argument(iter);
+        utils::skip_spaces(iter);
+        let false_branch = utils::read_argument(iter);
+
+        if self.is_defined(name.trim()) {
+            false_branch
+        } else {
+            true_branch
+        }
+    }
+
     /// Handle \ifstrequal{str1}{str2}{true}{false}
     fn handle_ifstrequal<I>(&self, iter: &mut std::iter::Peekable<I>) -> Option<TokenList>
     where
@@ -1297,6 +1910,24 @@ impl Engine {
         skipped
     }
 
+    /// Layout parameters such as `\arraystretch` are read by the converter.
+    /// The parser drops macro definitions, so their values are passed on as
+    /// `\setlength{\arraystretch}{..}`.
+    fn keep_parameter_definition(name: &str, body: &TokenList, result: &mut Vec<TexToken>) {
+        if !matches!(name, "arraystretch") {
+            return;
+        }
+        result.extend([
+            TexToken::ControlSeq("setlength".to_string()),
+            TexToken::BeginGroup,
+            TexToken::ControlSeq(name.to_string()),
+            TexToken::EndGroup,
+            TexToken::BeginGroup,
+        ]);
+        result.extend(body.as_slice().iter().cloned());
+        result.push(TexToken::EndGroup);
+    }
+
     /// Check if a control sequence name is an unsupported primitive
     fn is_unsupported_primitive(name: &str) -> bool {
         matches!(
@@ -1368,18 +1999,21 @@ impl Engine {
     }
 
     /// Parse macro arguments from the token stream
-    /// Returns Ok(args) on success, or Err((error, consumed_tokens)) on failure
+    /// Returns Ok(args) on success, or Err((error, consumed_tokens)) on failure.
+    /// `stream` is the stream `iter` reads, if any; braced arguments are then
+    /// copied from it in one go.
     fn parse_arguments<I>(
         &self,
         iter: &mut std::iter::Peekable<I>,
         macro_def: &MacroDef,
+        stream: Option<&TokenStream>,
     ) -> Result<Vec<TokenList>, (MacroError, Vec<TexToken>)>
     where
         I: Iterator<Item = TexToken>,
     {
         match &macro_def.signature {
             MacroSignature::Simple(num_args) => {
-                let mut args = Vec::new();
+                let mut args = Vec::with_capacity(*num_args as usize);
 
                 let start_idx = if macro_def.default_arg.is_some() {
                     utils::skip_spaces(iter);
@@ -1396,7 +2030,13 @@ impl Engine {
 
                 for _ in start_idx..*num_args {
                     utils::skip_spaces(iter);
-                    args.push(utils::read_argument(iter));
+                    let arg = match stream {
+                        Some(stream) if iter.next_if_eq(&TexToken::BeginGroup).is_some() => stream
+                            .read_group()
+                            .unwrap_or_else(|| utils::read_balanced_group(iter)),
+                        _ => utils::read_argument(iter),
+                    };
+                    args.push(arg);
                 }
 
                 Ok(args)
@@ -1443,14 +2083,19 @@ impl Engine {
                         }
                     }
 
-                    // Match exact tokens
-                    for exp_token in expected {
+                    // Match exact tokens; spaces after a control word are skipped
+                    for exp_token in utils::significant_tokens(expected) {
                         match iter.next() {
                             Some(tok) => {
                                 consumed.push(tok.clone());
                                 if tok != *exp_token {
                                     return Err((MacroError::PatternMismatch, consumed));
                                 }
+                                if utils::ends_control_word(&tok) {
+                                    while iter.peek() == Some(&TexToken::Space) {
+                                        consumed.push(iter.next().expect("peek succeeded"));
+                                    }
+                                }
                             }
                             None => return Err((MacroError::RunawayArgument, consumed)),
                         }
@@ -1563,7 +2208,12 @@ impl Engine {
         (content, consumed)
     }
 
-    /// Read tokens until delimiter is found (respecting brace depth)
+    /// Read tokens until the delimiter appears at brace depth 0.
+    ///
+    /// The delimiter is matched against the tail of what has been read, so a
+    /// failed partial match (`aab` against `ab`) cannot swallow the start of
+    /// the real delimiter. Spaces after a control word take no part in the
+    /// match, and an argument that is a single brace group loses its braces.
     fn read_delimited_argument<I>(
         &self,
         iter: &mut std::iter::Peekable<I>,
@@ -1573,81 +2223,44 @@ impl Engine {
     where
         I: Iterator<Item = TexToken>,
     {
-        let mut arg_content = Vec::new();
+        let delimiter = utils::significant_tokens(delimiter);
+        let mut arg_content: Vec<TexToken> = Vec::new();
+        // Indices into `arg_content` of the tokens that take part in matching
+        let mut significant: Vec<usize> = Vec::new();
         let mut depth: i32 = 0;
-        let mut count = 0;
         let max_arg_tokens = 10000; // Safety limit
 
         loop {
-            if count > max_arg_tokens {
+            if arg_content.len() > max_arg_tokens {
                 return Err(MacroError::RunawayArgument);
             }
-
-            // At depth 0, try to match delimiter
-            if depth == 0 && !delimiter.is_empty() {
-                // Check if first delimiter token matches
-                if let Some(first_delim) = delimiter.first() {
-                    if let Some(next) = iter.peek() {
-                        if next == first_delim {
-                            // Potential match - try to match entire delimiter
-                            let mut match_buffer = Vec::new();
-                            let mut matched = true;
-
-                            for expected in delimiter {
-                                if let Some(tok) = iter.next() {
-                                    match_buffer.push(tok.clone());
-                                    if tok != *expected {
-                                        matched = false;
-                                        break;
-                                    }
-                                } else {
-                                    matched = false;
-                                    break;
-                                }
-                            }
-
-                            if matched {
-                                // Full match - delimiter found
-                                consumed.extend(match_buffer);
-                                return Ok(arg_content);
-                            } else {
-                                // Partial match - these tokens are part of argument
-                                for tok in match_buffer {
-                                    consumed.push(tok.clone());
-                                    match &tok {
-                                        TexToken::BeginGroup => depth += 1,
-                                        TexToken::EndGroup => depth = (depth - 1).max(0),
-                                        _ => {}
-                                    }
-                                    arg_content.push(tok);
-                                }
-                                count += 1;
-                                continue;
-                            }
-                        }
-                    }
-                }
+            let Some(token) = iter.next() else {
+                return Err(MacroError::RunawayArgument);
+            };
+            consumed.push(token.clone());
+            match &token {
+                TexToken::BeginGroup => depth += 1,
+                TexToken::EndGroup => depth = (depth - 1).max(0),
+                _ => {}
             }
-
-            // Normal token reading
-            if let Some(token) = iter.next() {
-                consumed.push(token.clone());
-                match &token {
-                    TexToken::BeginGroup => {
-                        depth += 1;
-                        arg_content.push(token);
-                    }
-                    TexToken::EndGroup => {
-                        depth = (depth - 1).max(0);
-                        arg_content.push(token);
-                    }
-                    _ => {
-                        arg_content.push(token);
-                    }
+            if !(token == TexToken::Space
+                && arg_content.last().is_some_and(utils::ends_control_word))
+            {
+                significant.push(arg_content.len());
+            }
+            arg_content.push(token);
+
+            if depth == 0 && significant.len() >= delimiter.len() {
+                let tail = &significant[significant.len() - delimiter.len()..];
+                if tail
+                    .iter()
+                    .zip(&delimiter)
+                    .all(|(&i, expected)| arg_content[i] == **expected)
+                {
+                    let start = tail.first().copied().unwrap_or(arg_content.len());
+                    arg_content.truncate(start);
+                    return Ok(utils::strip_outer_group(arg_content));
                 }
-                count += 1;
-            } else {
-                return Err(MacroError::RunawayArgument);
             }
         }
     }
@@ -1687,6 +2300,21 @@ impl Default for Engine {
     }
 }
 
+/// Tokens that print `tokens` as written, for `\string` and `\detokenize`:
+/// special characters become the placeholders of verbatim text, so neither
+/// the parser nor the converter acts on them
+fn literal_tokens(tokens: &[TexToken]) -> TokenList {
+    let text = super::lexer::detokenize(&TokenList::from_vec(tokens.to_vec()));
+    let literal = protect_verbatim_chars(&text)
+        .chars()
+        .map(|c| match c {
+            ' ' => TexToken::Space,
+            c => TexToken::Char(c),
+        })
+        .collect();
+    TokenList::from_vec(literal)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2317,6 +2945,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_and_detokenize_print_as_written() {
+        use crate::core::latex2typst::utils::restore_verbatim_chars;
+
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\def\cs#1{\texttt{\string#1}} \cs\section, \string{ and \detokenize{\emph{x} $y$}",
+        );
+        let output = detokenize(&engine.process(input));
+        // Nothing is left for the parser to act on until the text is restored
+        assert!(!output.contains("\\section"), "{}", output);
+        assert_eq!(
+            restore_verbatim_chars(&output).trim(),
+            r"\texttt{\section}, { and \emph{x} $y$"
+        );
+    }
+
     #[test]
     fn test_xspace_before_punctuation() {
         let mut engine = Engine::new();
@@ -2332,6 +2977,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_xspace_follows_xspace_package_rules() {
+        let expand = |text: &str| {
+            let mut engine = Engine::new();
+            let input = tokenize(&format!(r"\def\foo{{bar\xspace}}{}", text));
+            detokenize(&engine.process(input))
+        };
+        assert_eq!(expand(r"\foo \emph{x}"), r"bar \emph{x}");
+        assert_eq!(expand("\\foo\n  baz"), "bar baz");
+        assert_eq!(
+            expand(r"\foo, \foo) \foo's \foo-x"),
+            "bar, bar) bar's bar-x"
+        );
+        assert_eq!(expand(r"\foo\footnote{y}"), r"bar\footnote{y}");
+        assert_eq!(expand(r"\foo{} x"), "bar{} x");
+    }
+
     #[test]
     fn test_two_delimiters() {
         let mut engine = Engine::new();
@@ -2346,6 +3008,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_delimited_arguments_with_commands_and_groups() {
+        let mut engine = Engine::new();
+        let input =
+            tokenize(r"\def\pair(#1,#2){\langle #1; #2\rangle}\pair(\frac{1}{2},{b,c}) \pair(,x)");
+        let result = detokenize(&engine.process(input));
+        assert!(
+            result.contains(r"\langle \frac{1}{2}; b,c\rangle"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains(r"\langle; x\rangle"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_control_sequence_delimiter_ignores_spaces() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\until#1\stop {[#1]}\until a {b} c\stop d \until e\stop");
+        let result = detokenize(&engine.process(input));
+        assert!(result.contains("[a {b} c]d"), "got: {}", result);
+        assert!(result.contains("[e]"), "got: {}", result);
+
+        // A space token after `\stop` (as left by an expansion) still matches
+        let mut tokens = tokenize(r"\def\upto#1\stop.{[#1]}\upto x").into_inner();
+        tokens.extend([
+            TexToken::ControlSeq("stop".into()),
+            TexToken::Space,
+            TexToken::Char('.'),
+        ]);
+        let result = detokenize(&engine.process(TokenList::from_vec(tokens)));
+        assert_eq!(result, "[x]");
+    }
+
+    #[test]
+    fn test_delimiter_after_partial_match() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\foo#1ab{<#1>}\foo xaab");
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result, "<xa>");
+    }
+
+    #[test]
+    fn test_picture_put_definition() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\put(#1,#2)#3{[#3 at #1/#2]}\put(10, 0){\line(1,0){5}}");
+        let result = detokenize(&engine.process(input));
+        assert!(
+            result.contains(r"[\line(1,0){5} at 10/ 0]"),
+            "got: {}",
+            result
+        );
+    }
+
     // =====================================================================
     // Robustness Tests - DeferredParam, makeatletter, ifx
     // =====================================================================
@@ -2427,6 +3142,71 @@ mod tests {
         assert!(result.contains("AT"), "Expected AT in: {}", result);
     }
 
+    #[test]
+    fn test_ifdefined_guards() {
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\newcommand{\R}{\mathbb{R}}\ifdefined\R YES\else NO\fi \ifdefined\pdfoutput PDF\else DVI\fi",
+        );
+        let result = detokenize(&engine.process(input));
+        assert!(
+            result.contains("YES") && !result.contains("NO"),
+            "{}",
+            result
+        );
+        // Built-ins unknown to the macro database count as undefined
+        assert!(
+            result.contains("DVI") && !result.contains("PDF"),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_skipped_branch_with_nested_conditionals() {
+        let mut engine = Engine::new();
+        // hyperref-style guard: the inner \ifnum...\fi must not close \ifdefined
+        let input = tokenize(
+            r"\newif\ifdraft \ifdefined\pdfoutput\ifnum\pdfoutput>0 PDF\fi\ifdraft D\fi\else DVI\fi AFTER",
+        );
+        let result = detokenize(&engine.process(input));
+        assert!(result.contains("DVI"), "{}", result);
+        assert!(result.contains("AFTER"), "{}", result);
+        assert!(!result.contains("PDF"), "{}", result);
+    }
+
+    #[test]
+    fn test_ifcsname() {
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\def\mytitle{T}\ifcsname mytitle\endcsname YES\else NO\fi \ifcsname c@chapter\endcsname CH\else ART\fi",
+        );
+        let result = detokenize(&engine.process(input));
+        assert!(
+            result.contains("YES") && !result.contains("NO"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("ART") && !result.contains("CH"),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_at_ifundefined() {
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\makeatletter\@ifundefined{chapter}{\newcommand{\chapter}{\section}}{}\makeatother\chapter",
+        );
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result.trim(), r"\section");
+
+        let input = tokenize(r"\makeatletter\@ifundefined{chapter}{A}{B}\makeatother");
+        assert_eq!(detokenize(&engine.process(input)).trim(), "B");
+    }
+
     #[test]
     fn test_ifx_same_undefined() {
         let mut engine = Engine::new();
@@ -2759,4 +3539,81 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn test_appto_and_preto() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\newcommand{\x}{B}\appto\x{C}\preto{\x}{A}\x");
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result.trim(), "ABC");
+
+        // Undefined targets are defined by the patch
+        let mut engine = Engine::new();
+        let input = tokenize(r"\appto\y{new}\y");
+        assert_eq!(detokenize(&engine.process(input)).trim(), "new");
+    }
+
+    #[test]
+    fn test_gappto_survives_group() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\x{a}{\gappto\x{b}\appto\x{c}}\x");
+        let result = detokenize(&engine.process(input));
+        assert!(result.ends_with("ab"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_csappto_and_g_addto_macro() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\x{a}\csappto{x}{b}\x");
+        assert!(detokenize(&engine.process(input)).ends_with("ab"));
+
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\makeatletter\def\my@list{a}\g@addto@macro\my@list{b}\def\show{\my@list}\makeatother \show",
+        );
+        let result = detokenize(&engine.process(input));
+        assert!(!result.contains("addto"), "Got: {}", result);
+        assert!(result.ends_with("ab"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_patch_without_target_warns() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\appto{ab}{c}Body");
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result, "Body");
+        assert!(engine.take_structured_warnings().iter().any(|w| matches!(
+            w,
+            EngineWarning::ArgumentParsingFailed { macro_name, .. } if macro_name == "appto"
+        )));
+    }
+
+    #[test]
+    fn test_at_begin_document_deferred() {
+        let mut engine = Engine::new();
+        let input = tokenize(
+            r"\newcommand{\name}{Abstract}\AtBeginDocument{\renewcommand{\name}{Summary}Hello}\AtEndDocument{Bye}\begin{document}\name\end{document}",
+        );
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result, r"\begin{document}HelloSummaryBye\end{document}");
+    }
+
+    #[test]
+    fn test_at_begin_document_in_body_runs_in_place() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\begin{document}A\AtBeginDocument{B}C\end{document}");
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result, r"\begin{document}ABC\end{document}");
+    }
+
+    #[test]
+    fn test_sequential_macro_uses_do_not_accumulate_depth() {
+        // Each use expands at the depth of its own call site, so a long
+        // document can use a macro far more often than the depth limit.
+        let mut engine = Engine::new().with_max_depth(10);
+        let input = tokenize(&format!(r"\newcommand{{\R}}{{x}}{}", r"\R ".repeat(50)));
+        let result = detokenize(&engine.process(input));
+        assert_eq!(result.matches('x').count(), 50);
+        assert!(engine.take_structured_warnings().is_empty());
+    }
 }
@@ -6,50 +6,140 @@
 //! - Comment handling
 //! - Space normalization after control sequences
 //! - Parameter token parsing
+//! - `^^` notation: `^^M` is the character 64 places away from `M` (a
+//!   carriage return, so a line ending), `^^5c` the character with hex
+//!   code `5c`, and the result is read as if it had been typed
+//!
+//! ## Round trips
+//!
+//! Tokens survive a round trip exactly: `tokenize(&detokenize(&tokens))`
+//! gives `tokens` back for any list `tokenize` produced. Text survives
+//! [`detokenize`]`(&`[`tokenize`]`(text))` unchanged up to these
+//! normalizations:
+//! - a run of spaces, tabs and one line ending is a single space, and a
+//!   blank line is `\par` (a space in math mode)
+//! - blanks and one line ending after a control word are dropped, and a
+//!   space is put back where the next token is a letter
+//! - a comment takes the line ending and the next line's indentation with
+//!   it, and ends with `\n`
+//! - `^^` notation is decoded
+//! - `##` not followed by a parameter number is `#`
 
 use super::token::{TexToken, TokenList};
 
+/// How the lexer reads its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LexerConfig {
+    /// Whether `@` is a letter in control words, as after `\makeatletter`.
+    /// `\makeatletter` and `\makeatother` in the input switch it as well.
+    pub at_letter: bool,
+    /// Whether the input is math, as for the engine's math mode: a blank
+    /// line is a space there, since math cannot end a paragraph
+    pub math_mode: bool,
+}
+
 /// The TeX Lexer that converts source text to tokens
 pub struct Lexer<'a> {
-    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    input: &'a str,
+    /// Byte offset of the next character
+    pos: usize,
+    config: LexerConfig,
     /// Track if we just emitted a control sequence (for space swallowing)
     after_cs: bool,
-    /// Phantom data to hold lifetime
-    _marker: std::marker::PhantomData<&'a str>,
+    /// Set when a line ending was already consumed, so that an immediately
+    /// following newline marks an empty line (paragraph break)
+    at_line_start: bool,
 }
 
 impl<'a> Lexer<'a> {
     /// Create a new lexer for the given input
     pub fn new(input: &'a str) -> Self {
+        Self::with_config(input, LexerConfig::default())
+    }
+
+    /// Create a lexer that reads the input as `config` says
+    pub fn with_config(input: &'a str, config: LexerConfig) -> Self {
         Lexer {
-            chars: input.char_indices().peekable(),
+            input,
+            pos: 0,
+            config,
             after_cs: false,
-            _marker: std::marker::PhantomData,
+            at_line_start: false,
+        }
+    }
+
+    /// The character at the current position and the bytes it takes up,
+    /// with `^^` notation decoded
+    fn decode(&self) -> Option<(char, usize)> {
+        let rest = &self.input[self.pos..];
+        let c = rest.chars().next()?;
+        let Some(after) = rest.strip_prefix("^^") else {
+            return Some((c, c.len_utf8()));
+        };
+        let mut chars = after.chars();
+        let (first, second) = (chars.next(), chars.next());
+        let is_hex = |c: Option<char>| c.is_some_and(|c| matches!(c, '0'..='9' | 'a'..='f'));
+        if is_hex(first) && is_hex(second) {
+            let code = u8::from_str_radix(&after[..2], 16).ok()?;
+            return Some((char::from(code), 4));
+        }
+        match first {
+            Some(first) if first.is_ascii() => Some((char::from(first as u8 ^ 0x40), 3)),
+            _ => Some((c, 1)),
         }
     }
 
     /// Peek at the next character without consuming it
     fn peek_char(&mut self) -> Option<char> {
-        self.chars.peek().map(|(_, c)| *c)
+        self.decode().map(|(c, _)| c)
     }
 
     /// Consume and return the next character
     fn next_char(&mut self) -> Option<char> {
-        self.chars.next().map(|(_, c)| c)
+        let (c, len) = self.decode()?;
+        self.pos += len;
+        Some(c)
     }
 
-    /// Skip whitespace, returns true if any was skipped
-    fn skip_whitespace(&mut self) -> bool {
-        let mut skipped = false;
-        while let Some(c) = self.peek_char() {
-            if c.is_ascii_whitespace() {
+    /// Whether `c` may be part of a control word
+    fn is_letter(&self, c: char) -> bool {
+        c.is_ascii_alphabetic() || (c == '@' && self.config.at_letter)
+    }
+
+    /// Skip spaces and tabs on the current line
+    fn skip_blanks(&mut self) {
+        while let Some(' ' | '\t') = self.peek_char() {
+            self.next_char();
+        }
+    }
+
+    /// Consume a single line ending (`\n`, `\r\n` or `\r`), returns true if one was found
+    fn eat_line_ending(&mut self) -> bool {
+        match self.peek_char() {
+            Some('\r') => {
                 self.next_char();
-                skipped = true;
-            } else {
-                break;
+                if self.peek_char() == Some('\n') {
+                    self.next_char();
+                }
+                true
+            }
+            Some('\n') => {
+                self.next_char();
+                true
             }
+            _ => false,
+        }
+    }
+
+    /// Skip the rest of the current line like TeX does after a control word:
+    /// trailing blanks and the line ending are ignored, as is the next line's
+    /// indentation. An empty next line still produces a paragraph break.
+    fn skip_line_end(&mut self) {
+        self.skip_blanks();
+        if self.eat_line_ending() {
+            self.skip_blanks();
+            self.at_line_start = true;
         }
-        skipped
     }
 
     /// Read a control sequence name (letters only, or single non-letter)
@@ -58,10 +148,10 @@ impl<'a> Lexer<'a> {
 
         // Check first character
         if let Some(c) = self.peek_char() {
-            if c.is_ascii_alphabetic() {
+            if self.is_letter(c) {
                 // Multi-letter control sequence
                 while let Some(c) = self.peek_char() {
-                    if c.is_ascii_alphabetic() {
+                    if self.is_letter(c) {
                         name.push(c);
                         self.next_char();
                     } else {
@@ -70,6 +160,11 @@ impl<'a> Lexer<'a> {
                 }
                 // TeX swallows spaces after alphabetic control sequences
                 self.after_cs = true;
+                match name.as_str() {
+                    "makeatletter" => self.config.at_letter = true,
+                    "makeatother" => self.config.at_letter = false,
+                    _ => {}
+                }
             } else {
                 // Single non-letter control sequence like \% \{ \}
                 name.push(c);
@@ -91,16 +186,10 @@ impl<'a> Lexer<'a> {
             comment.push(c);
             self.next_char();
         }
-        // Consume the newline
-        if let Some(c) = self.peek_char() {
-            if c == '\r' {
-                self.next_char();
-            }
-        }
-        if let Some(c) = self.peek_char() {
-            if c == '\n' {
-                self.next_char();
-            }
+        // The comment swallows its line ending and the next line's indentation
+        if self.eat_line_ending() {
+            self.skip_blanks();
+            self.at_line_start = true;
         }
         comment
     }
@@ -109,9 +198,10 @@ impl<'a> Lexer<'a> {
     fn next_token(&mut self) -> Option<TexToken> {
         // Handle space swallowing after control sequences
         if self.after_cs {
-            self.skip_whitespace();
             self.after_cs = false;
+            self.skip_line_end();
         }
+        let at_line_start = std::mem::take(&mut self.at_line_start);
 
         let c = self.next_char()?;
 
@@ -199,19 +289,19 @@ impl<'a> Lexer<'a> {
 
             // Newlines - can be significant in TeX
             '\n' | '\r' => {
+                if c == '\r' && self.peek_char() == Some('\n') {
+                    self.next_char();
+                }
                 // Check for paragraph break (blank line)
-                let mut blank_line = false;
-                while let Some(next) = self.peek_char() {
-                    if next == ' ' || next == '\t' {
-                        self.next_char();
-                    } else if next == '\n' || next == '\r' {
-                        self.next_char();
-                        blank_line = true;
-                    } else {
+                let mut blank_line = at_line_start;
+                loop {
+                    self.skip_blanks();
+                    if !self.eat_line_ending() {
                         break;
                     }
+                    blank_line = true;
                 }
-                if blank_line {
+                if blank_line && !self.config.math_mode {
                     // Paragraph break becomes \par
                     Some(TexToken::ControlSeq("par".into()))
                 } else {
@@ -245,7 +335,22 @@ pub fn tokenize(input: &str) -> TokenList {
     Lexer::new(input).tokenize()
 }
 
+/// Tokenize a string as `config` says, e.g. a package preamble with `@`
+/// as a letter, or math the way the engine's math mode reads it
+pub fn tokenize_with_config(input: &str, config: LexerConfig) -> TokenList {
+    Lexer::with_config(input, config).tokenize()
+}
+
+/// Whether the control sequence `name` is a control word, which a letter
+/// after it would run into
+fn is_control_word(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphabetic())
+        || (name.len() > 1 && name.chars().all(|c| c.is_ascii_alphabetic() || c == '@'))
+}
+
 /// Convert a token list back to a string (detokenize)
+///
+/// See the [module documentation](self) for what a round trip keeps.
 pub fn detokenize(tokens: &TokenList) -> String {
     let mut result = String::new();
     let slice = tokens.as_slice();
@@ -258,10 +363,10 @@ pub fn detokenize(tokens: &TokenList) -> String {
 
                 // Add space after alphabetic control sequences if next token
                 // is a letter or another control sequence
-                if name.chars().all(|c| c.is_ascii_alphabetic()) {
+                if is_control_word(name) {
                     if let Some(next) = slice.get(i + 1) {
                         match next {
-                            TexToken::Char(c) if c.is_ascii_alphabetic() => {
+                            TexToken::Char(c) if c.is_ascii_alphabetic() || *c == '@' => {
                                 result.push(' ');
                             }
                             TexToken::ControlSeq(_) => {
@@ -364,6 +469,24 @@ mod tests {
         assert_eq!(tokens.as_slice()[2], TexToken::Char('b'));
     }
 
+    #[test]
+    fn test_comment_line_before_blank_line_is_par() {
+        let tokens = tokenize("a\n%\n\nb");
+        assert_eq!(tokens.as_slice()[1], TexToken::Space);
+        assert_eq!(tokens.as_slice()[2], TexToken::Comment("".into()));
+        assert_eq!(tokens.as_slice()[3], TexToken::ControlSeq("par".into()));
+    }
+
+    #[test]
+    fn test_blank_line_after_control_word_is_par() {
+        let tokens = tokenize("\\foo\n\nb");
+        assert_eq!(tokens.as_slice()[0], TexToken::ControlSeq("foo".into()));
+        assert_eq!(tokens.as_slice()[1], TexToken::ControlSeq("par".into()));
+
+        let tokens = tokenize("\\foo\n  b");
+        assert_eq!(tokens.as_slice()[1], TexToken::Char('b'));
+    }
+
     #[test]
     fn test_escaped_chars() {
         let tokens = tokenize("\\% \\{");
@@ -394,6 +517,88 @@ mod tests {
         assert_eq!(output, input);
     }
 
+    #[test]
+    fn test_caret_notation() {
+        let tokens = tokenize("a^^M b^^5cfoo ^^41^^I^^7b");
+        assert_eq!(
+            tokens.as_slice(),
+            [
+                TexToken::Char('a'),
+                TexToken::Space,
+                TexToken::Char('b'),
+                TexToken::ControlSeq("foo".into()),
+                TexToken::Char('A'),
+                TexToken::Space,
+                TexToken::BeginGroup,
+            ]
+        );
+        // A lone pair and an upper-case hex pair are not notation for a code
+        assert_eq!(detokenize(&tokenize("x^^")), "x^^");
+        assert_eq!(detokenize(&tokenize("^^4A")), "tA");
+    }
+
+    #[test]
+    fn test_tilde_is_active() {
+        let tokens = tokenize("a~b \\~{o}");
+        assert_eq!(tokens.as_slice()[1], TexToken::ActiveChar('~'));
+        assert_eq!(tokens.as_slice()[4], TexToken::ControlSeq("~".into()));
+        assert_eq!(detokenize(&tokens), "a~b \\~{o}");
+    }
+
+    #[test]
+    fn test_at_letter() {
+        let config = LexerConfig {
+            at_letter: true,
+            ..Default::default()
+        };
+        let tokens = tokenize_with_config("\\foo@bar x", config);
+        assert_eq!(tokens.as_slice()[0], TexToken::ControlSeq("foo@bar".into()));
+        assert_eq!(tokens.as_slice()[1], TexToken::Char('x'));
+        assert_eq!(detokenize(&tokens), "\\foo@bar x");
+
+        let tokens = tokenize("\\a@b\\makeatletter\\a@b\\makeatother\\a@b");
+        let names: Vec<_> = tokens
+            .as_slice()
+            .iter()
+            .filter_map(TexToken::as_control_seq)
+            .collect();
+        assert_eq!(names, ["a", "makeatletter", "a@b", "makeatother", "a"]);
+    }
+
+    #[test]
+    fn test_math_mode_blank_line_is_space() {
+        let config = LexerConfig {
+            math_mode: true,
+            ..Default::default()
+        };
+        let tokens = tokenize_with_config("a\n\nb", config);
+        assert_eq!(tokens.as_slice()[1], TexToken::Space);
+        assert_eq!(
+            tokenize("a\n\nb").as_slice()[1],
+            TexToken::ControlSeq("par".into())
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_guarantees() {
+        let inputs = [
+            "\\section{Intro}  Text,\ttabbed\nnext line\n\n\\par new % note\n  indented",
+            "\\def\\x#1##2{#1}\\x{a} ## #0 \\foo\\bar baz\\\\ \\@ \\, ~ $x^2_i$ & \\%",
+            "\\makeatletter\\c@page@x y\\makeatother \\@gobble{a}",
+            "^^M^^5cemph{^^41} ^^ ^^é",
+            "end \\",
+        ];
+        for input in inputs {
+            let tokens = tokenize(input);
+            let text = detokenize(&tokens);
+            assert_eq!(tokenize(&text), tokens, "{:?} -> {:?}", input, text);
+            assert_eq!(detokenize(&tokenize(&text)), text);
+        }
+        // Text already in normal form is kept as is
+        let normal = "\\frac{a}{b} \\alpha x%c\ny\\par z";
+        assert_eq!(detokenize(&tokenize(normal)), normal);
+    }
+
     #[test]
     fn test_newcommand_body() {
         let tokens = tokenize("\\langle #1, #2\\rangle");
@@ -25,9 +25,9 @@ pub mod token;
 pub mod utils;
 
 pub use engine::{Engine, MacroDb, MacroDef};
-pub use lexer::{detokenize, tokenize, Lexer};
+pub use lexer::{detokenize, tokenize, tokenize_with_config, Lexer, LexerConfig};
 pub use primitives::{parse_definitions, DefinitionKind};
-pub use token::{TexToken, TokenList};
+pub use token::{Groups, TexToken, TokenList};
 
 // =============================================================================
 // Structured Engine Warnings
@@ -195,7 +195,13 @@ pub fn expand_latex_with_warnings(input: &str, math_mode: bool) -> ExpandResult
     };
 
     // Tokenize
-    let tokens = tokenize(input);
+    let tokens = tokenize_with_config(
+        input,
+        LexerConfig {
+            math_mode,
+            ..Default::default()
+        },
+    );
 
     // Parse definitions and expand
     let expanded = engine.process(tokens);
@@ -4,6 +4,9 @@
 //! - `\newcommand`, `\renewcommand`, `\providecommand`
 //! - `\def`
 //! - `\let`
+//! - `\newtcolorbox`, `\newmdenv`, as environments around the box
+
+use std::iter::Peekable;
 
 use super::token::{TexToken, TokenList};
 use super::utils;
@@ -129,6 +132,10 @@ pub fn is_definition_command(name: &str) -> bool {
             | "DeclareRobustCommand"
             | "newenvironment"
             | "renewenvironment"
+            | "newtcolorbox"
+            | "renewtcolorbox"
+            | "newmdenv"
+            | "renewmdenv"
             | "def"
             | "edef"
             | "gdef"
@@ -145,18 +152,38 @@ pub fn parse_definition(
     cmd_name: &str,
     tokens: TokenList,
 ) -> Result<(DefinitionKind, TokenList), TokenList> {
+    let mut iter = tokens.into_inner().into_iter().peekable();
+    let definition = read_definition(cmd_name, &mut iter);
+    let rest = TokenList::from_vec(iter.collect());
+    match definition {
+        Some(def) => Ok((def, rest)),
+        None => Err(rest),
+    }
+}
+
+/// Read a definition from the front of a token stream, consuming only the
+/// tokens that belong to it
+///
+/// Returns `None` if the definition is malformed; the tokens read up to that
+/// point are dropped.
+pub fn read_definition<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
     match cmd_name {
         "newcommand" | "renewcommand" | "providecommand" | "DeclareRobustCommand" => {
-            parse_newcommand_style(cmd_name, tokens)
+            parse_newcommand_style(cmd_name, iter)
         }
-        "NewDocumentCommand" | "RenewDocumentCommand" => parse_xparse_style(cmd_name, tokens),
-        "DeclareMathOperator" => parse_declare_math_operator(tokens),
-        "newenvironment" | "renewenvironment" => parse_newenvironment(cmd_name, tokens),
-        "def" | "gdef" => parse_def(tokens, false),
-        "edef" | "xdef" => parse_def(tokens, true),
-        "let" => parse_let(tokens),
-        "newif" => parse_newif(tokens),
-        _ => Err(tokens),
+        "NewDocumentCommand" | "RenewDocumentCommand" => parse_xparse_style(cmd_name, iter),
+        "DeclareMathOperator" => parse_declare_math_operator(iter),
+        "newenvironment" | "renewenvironment" => parse_newenvironment(cmd_name, iter),
+        "newtcolorbox" | "renewtcolorbox" => parse_newtcolorbox(cmd_name, iter),
+        "newmdenv" | "renewmdenv" => parse_newmdenv(cmd_name, iter),
+        "def" | "gdef" => parse_def(iter, false),
+        "edef" | "xdef" => parse_def(iter, true),
+        "let" => parse_let(iter),
+        "newif" => parse_newif(iter),
+        _ => None,
     }
 }
 
@@ -169,18 +196,9 @@ pub fn parse_definitions(tokens: TokenList) -> (Vec<DefinitionKind>, TokenList)
     while let Some(token) = iter.next() {
         match &token {
             TexToken::ControlSeq(name) if is_definition_command(name) => {
-                let remaining: Vec<TexToken> = iter.collect();
-                let remaining_list = TokenList::from_vec(remaining);
-
-                match parse_definition(name, remaining_list) {
-                    Ok((def, rest)) => {
-                        definitions.push(def);
-                        iter = rest.into_inner().into_iter().peekable();
-                    }
-                    Err(rest) => {
-                        result.push(token);
-                        iter = rest.into_inner().into_iter().peekable();
-                    }
+                match read_definition(name, &mut iter) {
+                    Some(def) => definitions.push(def),
+                    None => result.push(token),
                 }
             }
             _ => {
@@ -193,33 +211,31 @@ pub fn parse_definitions(tokens: TokenList) -> (Vec<DefinitionKind>, TokenList)
 }
 
 /// Parse \newcommand, \renewcommand, \providecommand
-fn parse_newcommand_style(
-    cmd_name: &str,
-    tokens: TokenList,
-) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
+fn parse_newcommand_style<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
     // Skip spaces
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Check for star variant (ignored for now)
     if matches!(iter.peek(), Some(TexToken::Char('*'))) {
         iter.next();
-        utils::skip_spaces(&mut iter);
+        utils::skip_spaces(iter);
     }
 
     // Parse command name: either {\cmd} or \cmd
     let macro_name = match iter.peek() {
         Some(TexToken::BeginGroup) => {
             iter.next();
-            let name = match utils::read_control_seq_name(&mut iter) {
+            let name = match utils::read_control_seq_name(iter) {
                 Ok(n) => n,
-                Err(_) => return Err(TokenList::from_vec(iter.collect())),
+                Err(_) => return None,
             };
             // Consume closing brace
             match iter.next() {
                 Some(TexToken::EndGroup) => {}
-                _ => return Err(TokenList::from_vec(iter.collect())),
+                _ => return None,
             }
             name
         }
@@ -227,44 +243,44 @@ fn parse_newcommand_style(
             if let Some(TexToken::ControlSeq(name)) = iter.next() {
                 name
             } else {
-                return Err(TokenList::from_vec(iter.collect()));
+                return None;
             }
         }
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse optional argument count [n]
     let num_args = if matches!(iter.peek(), Some(TexToken::Char('['))) {
         iter.next();
-        let num = utils::read_number(&mut iter).unwrap_or_default();
+        let num = utils::read_number(iter).unwrap_or_default();
         // Consume closing bracket
-        utils::skip_until_char(&mut iter, ']');
+        utils::skip_until_char(iter, ']');
         num
     } else {
         0
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse optional default value [default]
     let default = if matches!(iter.peek(), Some(TexToken::Char('['))) {
         iter.next();
-        let def_tokens = utils::read_until_char(&mut iter, ']');
+        let def_tokens = utils::read_until_char(iter, ']');
         Some(def_tokens)
     } else {
         None
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse body {body}
     let body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
     let def = match cmd_name {
@@ -295,29 +311,27 @@ fn parse_newcommand_style(
         _ => unreachable!(),
     };
 
-    Ok((def, TokenList::from_vec(iter.collect())))
+    Some(def)
 }
 
 /// Parse xparse-style commands (simplified)
-fn parse_xparse_style(
-    cmd_name: &str,
-    tokens: TokenList,
-) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_xparse_style<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Parse command name
     let macro_name = match iter.peek() {
         Some(TexToken::BeginGroup) => {
             iter.next();
-            let name = match utils::read_control_seq_name(&mut iter) {
+            let name = match utils::read_control_seq_name(iter) {
                 Ok(n) => n,
-                Err(_) => return Err(TokenList::from_vec(iter.collect())),
+                Err(_) => return None,
             };
             match iter.next() {
                 Some(TexToken::EndGroup) => {}
-                _ => return Err(TokenList::from_vec(iter.collect())),
+                _ => return None,
             }
             name
         }
@@ -325,31 +339,31 @@ fn parse_xparse_style(
             if let Some(TexToken::ControlSeq(name)) = iter.next() {
                 name
             } else {
-                return Err(TokenList::from_vec(iter.collect()));
+                return None;
             }
         }
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse argument specification {mmm} - count 'm' for mandatory args
     let num_args = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        let spec = utils::read_balanced_group(&mut iter);
+        let spec = utils::read_balanced_group(iter);
         count_mandatory_args(&spec)
     } else {
         0
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse body
     let body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
     let def = match cmd_name {
@@ -368,19 +382,20 @@ fn parse_xparse_style(
         _ => unreachable!(),
     };
 
-    Ok((def, TokenList::from_vec(iter.collect())))
+    Some(def)
 }
 
 /// Parse \def\name#1#2{body} or \edef\name#1#2{body}
-fn parse_def(tokens: TokenList, is_edef: bool) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_def<I>(iter: &mut Peekable<I>, is_edef: bool) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Parse macro name
     let macro_name = match iter.next() {
         Some(TexToken::ControlSeq(name)) => name,
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
     // Parse parameter text until '{'
@@ -433,9 +448,9 @@ fn parse_def(tokens: TokenList, is_edef: bool) -> Result<(DefinitionKind, TokenL
     // Parse body
     let body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
     let def = if is_edef {
@@ -452,42 +467,40 @@ fn parse_def(tokens: TokenList, is_edef: bool) -> Result<(DefinitionKind, TokenL
         }
     };
 
-    Ok((def, TokenList::from_vec(iter.collect())))
+    Some(def)
 }
 
 /// Parse \let\name=\target or \let\name\target
-fn parse_let(tokens: TokenList) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_let<I>(iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Parse the new name
     let new_name = match iter.next() {
         Some(TexToken::ControlSeq(name)) => name,
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Optional equals sign
     if matches!(iter.peek(), Some(TexToken::Char('='))) {
         iter.next();
-        utils::skip_spaces(&mut iter);
+        utils::skip_spaces(iter);
     }
 
     // Parse the target
     let target = match iter.next() {
         Some(TexToken::ControlSeq(name)) => name,
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
-    Ok((
-        DefinitionKind::Let {
-            name: new_name,
-            target,
-        },
-        TokenList::from_vec(iter.collect()),
-    ))
+    Some(DefinitionKind::Let {
+        name: new_name,
+        target,
+    })
 }
 
 /// Parse \newif\iffoo
@@ -495,20 +508,21 @@ fn parse_let(tokens: TokenList) -> Result<(DefinitionKind, TokenList), TokenList
 /// - \iffoo -> \iffalse (initial state)
 /// - \footrue -> \def\iffoo{\iftrue}
 /// - \foofalse -> \def\iffoo{\iffalse}
-fn parse_newif(tokens: TokenList) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_newif<I>(iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Parse \iffoo - the name must start with "if"
     let full_name = match iter.next() {
         Some(TexToken::ControlSeq(name)) => name,
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
     // Validate that name starts with "if"
     if !full_name.starts_with("if") {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     }
 
     // Extract base name (e.g., "foo" from "iffoo")
@@ -517,24 +531,20 @@ fn parse_newif(tokens: TokenList) -> Result<(DefinitionKind, TokenList), TokenLi
         .unwrap_or(&full_name)
         .to_string();
 
-    Ok((
-        DefinitionKind::NewIf { base_name },
-        TokenList::from_vec(iter.collect()),
-    ))
+    Some(DefinitionKind::NewIf { base_name })
 }
 
 /// Parse \DeclareMathOperator{\name}{text}
-fn parse_declare_math_operator(
-    tokens: TokenList,
-) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_declare_math_operator<I>(iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Check for star variant
     let is_starred = if matches!(iter.peek(), Some(TexToken::Char('*'))) {
         iter.next();
-        utils::skip_spaces(&mut iter);
+        utils::skip_spaces(iter);
         true
     } else {
         false
@@ -544,13 +554,13 @@ fn parse_declare_math_operator(
     let macro_name = match iter.peek() {
         Some(TexToken::BeginGroup) => {
             iter.next();
-            let name = match utils::read_control_seq_name(&mut iter) {
+            let name = match utils::read_control_seq_name(iter) {
                 Ok(n) => n,
-                Err(_) => return Err(TokenList::from_vec(iter.collect())),
+                Err(_) => return None,
             };
             match iter.next() {
                 Some(TexToken::EndGroup) => {}
-                _ => return Err(TokenList::from_vec(iter.collect())),
+                _ => return None,
             }
             name
         }
@@ -558,51 +568,46 @@ fn parse_declare_math_operator(
             if let Some(TexToken::ControlSeq(name)) = iter.next() {
                 name
             } else {
-                return Err(TokenList::from_vec(iter.collect()));
+                return None;
             }
         }
-        _ => return Err(TokenList::from_vec(iter.collect())),
+        _ => return None,
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse body {text}
     let body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
-    Ok((
-        DefinitionKind::DeclareMathOperator {
-            name: macro_name,
-            body,
-            is_starred,
-        },
-        TokenList::from_vec(iter.collect()),
-    ))
+    Some(DefinitionKind::DeclareMathOperator {
+        name: macro_name,
+        body,
+        is_starred,
+    })
 }
 
 /// Parse \newenvironment{name}[n][default]{begin}{end}
-fn parse_newenvironment(
-    cmd_name: &str,
-    tokens: TokenList,
-) -> Result<(DefinitionKind, TokenList), TokenList> {
-    let mut iter = tokens.into_inner().into_iter().peekable();
-
-    utils::skip_spaces(&mut iter);
+fn parse_newenvironment<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
 
     // Check for star variant (ignored)
     if matches!(iter.peek(), Some(TexToken::Char('*'))) {
         iter.next();
-        utils::skip_spaces(&mut iter);
+        utils::skip_spaces(iter);
     }
 
     // Parse environment name: {envname}
     let env_name = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        let name_tokens = utils::read_balanced_group(&mut iter);
+        let name_tokens = utils::read_balanced_group(iter);
         // Extract name from tokens (should be plain text)
         name_tokens
             .as_slice()
@@ -613,54 +618,54 @@ fn parse_newenvironment(
             })
             .collect::<String>()
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
     if env_name.is_empty() {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     }
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse optional argument count [n]
     let num_args = if matches!(iter.peek(), Some(TexToken::Char('['))) {
         iter.next();
-        let num = utils::read_number(&mut iter).unwrap_or_default();
-        utils::skip_until_char(&mut iter, ']');
+        let num = utils::read_number(iter).unwrap_or_default();
+        utils::skip_until_char(iter, ']');
         num
     } else {
         0
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse optional default value [default]
     let default = if matches!(iter.peek(), Some(TexToken::Char('['))) {
         iter.next();
-        let def_tokens = utils::read_until_char(&mut iter, ']');
+        let def_tokens = utils::read_until_char(iter, ']');
         Some(def_tokens)
     } else {
         None
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse begin body {begin}
     let begin_body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
-    utils::skip_spaces(&mut iter);
+    utils::skip_spaces(iter);
 
     // Parse end body {end}
     let end_body = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
         iter.next();
-        utils::read_balanced_group(&mut iter)
+        utils::read_balanced_group(iter)
     } else {
-        return Err(TokenList::from_vec(iter.collect()));
+        return None;
     };
 
     let def = match cmd_name {
@@ -681,7 +686,160 @@ fn parse_newenvironment(
         _ => unreachable!(),
     };
 
-    Ok((def, TokenList::from_vec(iter.collect())))
+    Some(def)
+}
+
+/// Parse \newtcolorbox[init]{name}[n][default]{options} as an environment
+/// around a `tcolorbox` with those options. Without arguments, the box
+/// takes more options in brackets, as tcolorbox's boxes do.
+fn parse_newtcolorbox<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
+
+    // Init options (label numbering and such) have no effect on the box
+    if matches!(iter.peek(), Some(TexToken::Char('['))) {
+        iter.next();
+        utils::read_until_char(iter, ']');
+        utils::skip_spaces(iter);
+    }
+
+    let name = read_group_name(iter)?;
+    utils::skip_spaces(iter);
+
+    let mut num_args = if matches!(iter.peek(), Some(TexToken::Char('['))) {
+        iter.next();
+        let num = utils::read_number(iter).unwrap_or_default();
+        utils::skip_until_char(iter, ']');
+        num
+    } else {
+        0
+    };
+    utils::skip_spaces(iter);
+
+    let mut default = if matches!(iter.peek(), Some(TexToken::Char('['))) {
+        iter.next();
+        Some(utils::read_until_char(iter, ']'))
+    } else {
+        None
+    };
+    utils::skip_spaces(iter);
+
+    let mut options = if matches!(iter.peek(), Some(TexToken::BeginGroup)) {
+        iter.next();
+        utils::read_balanced_group(iter)
+    } else {
+        return None;
+    };
+
+    if num_args == 0 {
+        num_args = 1;
+        default = Some(TokenList::new());
+        options.push(TexToken::Char(','));
+        options.push(TexToken::Param(1));
+    }
+
+    Some(box_environment(
+        cmd_name == "renewtcolorbox",
+        name,
+        num_args,
+        default,
+        "tcolorbox",
+        options,
+    ))
+}
+
+/// Parse \newmdenv[options]{name} as an environment around an `mdframed`
+/// with those options
+fn parse_newmdenv<I>(cmd_name: &str, iter: &mut Peekable<I>) -> Option<DefinitionKind>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
+
+    let options = if matches!(iter.peek(), Some(TexToken::Char('['))) {
+        iter.next();
+        utils::read_until_char(iter, ']')
+    } else {
+        TokenList::new()
+    };
+    utils::skip_spaces(iter);
+
+    let name = read_group_name(iter)?;
+
+    Some(box_environment(
+        cmd_name == "renewmdenv",
+        name,
+        0,
+        None,
+        "mdframed",
+        options,
+    ))
+}
+
+/// Read a braced name such as `{mybox}`
+fn read_group_name<I>(iter: &mut Peekable<I>) -> Option<String>
+where
+    I: Iterator<Item = TexToken>,
+{
+    if !matches!(iter.peek(), Some(TexToken::BeginGroup)) {
+        return None;
+    }
+    iter.next();
+    let name: String = utils::read_balanced_group(iter)
+        .as_slice()
+        .iter()
+        .filter_map(|t| match t {
+            TexToken::Char(c) => Some(*c),
+            _ => None,
+        })
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// An environment that opens the box environment `inner` with `options`
+fn box_environment(
+    renew: bool,
+    name: String,
+    num_args: u8,
+    default: Option<TokenList>,
+    inner: &str,
+    options: TokenList,
+) -> DefinitionKind {
+    let environment = |command: &str| {
+        let mut tokens = vec![
+            TexToken::ControlSeq(command.to_string()),
+            TexToken::BeginGroup,
+        ];
+        tokens.extend(inner.chars().map(TexToken::Char));
+        tokens.push(TexToken::EndGroup);
+        tokens
+    };
+    let mut begin_body = environment("begin");
+    begin_body.push(TexToken::Char('['));
+    begin_body.extend(options);
+    begin_body.push(TexToken::Char(']'));
+    let begin_body = TokenList::from_vec(begin_body);
+    let end_body = TokenList::from_vec(environment("end"));
+
+    if renew {
+        DefinitionKind::RenewEnvironment {
+            name,
+            num_args,
+            default,
+            begin_body,
+            end_body,
+        }
+    } else {
+        DefinitionKind::NewEnvironment {
+            name,
+            num_args,
+            default,
+            begin_body,
+            end_body,
+        }
+    }
 }
 
 // Helper functions removed - using utils module
@@ -701,7 +859,7 @@ mod tests {
     #[test]
     fn test_parse_newcommand_simple() {
         let tokens = tokenize("{\\foo}{bar}");
-        let result = parse_newcommand_style("newcommand", tokens);
+        let result = parse_definition("newcommand", tokens);
         assert!(result.is_ok());
 
         let (def, _rest) = result.unwrap();
@@ -723,7 +881,7 @@ mod tests {
     #[test]
     fn test_parse_newcommand_with_args() {
         let tokens = tokenize("{\\pair}[2]{\\langle #1, #2\\rangle}");
-        let result = parse_newcommand_style("newcommand", tokens);
+        let result = parse_definition("newcommand", tokens);
         assert!(result.is_ok());
 
         let (def, _rest) = result.unwrap();
@@ -746,7 +904,7 @@ mod tests {
     #[test]
     fn test_parse_def() {
         let tokens = tokenize("\\foo#1#2{#1 and #2}");
-        let result = parse_def(tokens, false);
+        let result = parse_definition("def", tokens);
         assert!(result.is_ok());
 
         let (def, _rest) = result.unwrap();
@@ -767,7 +925,7 @@ mod tests {
     #[test]
     fn test_parse_edef() {
         let tokens = tokenize("\\bar{expanded content}");
-        let result = parse_def(tokens, true);
+        let result = parse_definition("edef", tokens);
         assert!(result.is_ok());
 
         let (def, _rest) = result.unwrap();
@@ -788,7 +946,7 @@ mod tests {
     #[test]
     fn test_parse_let() {
         let tokens = tokenize("\\foo=\\bar");
-        let result = parse_let(tokens);
+        let result = parse_definition("let", tokens);
         assert!(result.is_ok());
 
         let (def, _rest) = result.unwrap();
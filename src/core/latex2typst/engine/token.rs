@@ -4,6 +4,7 @@
 //! actually processes input - as a stream of categorized tokens.
 
 use std::fmt;
+use std::sync::Arc;
 
 /// A TeX token representing the smallest unit of TeX processing.
 ///
@@ -115,29 +116,33 @@ impl fmt::Display for TexToken {
     }
 }
 
-/// A list of tokens, used for macro bodies and arguments
+/// A list of tokens, used for macro bodies and arguments.
+///
+/// The tokens are shared: cloning a list (as every macro invocation does
+/// with the body it looks up) is a reference count increment, and the first
+/// modification of a shared list copies it.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub struct TokenList(pub Vec<TexToken>);
+pub struct TokenList(Arc<Vec<TexToken>>);
 
 impl TokenList {
     /// Create a new empty token list
     pub fn new() -> Self {
-        TokenList(Vec::new())
+        TokenList::default()
     }
 
     /// Create from a vector of tokens
     pub fn from_vec(tokens: Vec<TexToken>) -> Self {
-        TokenList(tokens)
+        TokenList(Arc::new(tokens))
     }
 
     /// Push a token to the list
     pub fn push(&mut self, token: TexToken) {
-        self.0.push(token);
+        Arc::make_mut(&mut self.0).push(token);
     }
 
-    /// Get the inner vector
+    /// Get the inner vector, copying it only if the list is shared
     pub fn into_inner(self) -> Vec<TexToken> {
-        self.0
+        Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
     }
 
     /// Get a reference to the inner vector
@@ -154,14 +159,52 @@ impl TokenList {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+
+    /// Iterate over the items at brace depth zero: a `{..}` group with its
+    /// braces, or a single token
+    pub fn groups(&self) -> Groups<'_> {
+        Groups { rest: &self.0 }
+    }
+}
+
+/// Iterator over the items of a token list, see [`TokenList::groups`].
+///
+/// An unmatched `}` is an item of its own, and an unclosed `{` takes the
+/// rest of the list.
+#[derive(Debug, Clone)]
+pub struct Groups<'a> {
+    rest: &'a [TexToken],
+}
+
+impl<'a> Iterator for Groups<'a> {
+    type Item = &'a [TexToken];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.rest.first()?;
+        let mut end = 1;
+        if first.is_begin_group() {
+            let mut depth = 1;
+            while end < self.rest.len() && depth > 0 {
+                match self.rest[end] {
+                    TexToken::BeginGroup => depth += 1,
+                    TexToken::EndGroup => depth -= 1,
+                    _ => {}
+                }
+                end += 1;
+            }
+        }
+        let (item, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(item)
+    }
 }
 
+/// The text of the list, the same as [`detokenize`] gives
+///
+/// [`detokenize`]: super::lexer::detokenize
 impl fmt::Display for TokenList {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for token in &self.0 {
-            write!(f, "{}", token)?;
-        }
-        Ok(())
+        f.write_str(&super::lexer::detokenize(self))
     }
 }
 
@@ -170,7 +213,7 @@ impl IntoIterator for TokenList {
     type IntoIter = std::vec::IntoIter<TexToken>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.into_inner().into_iter()
     }
 }
 
@@ -185,6 +228,7 @@ impl<'a> IntoIterator for &'a TokenList {
 
 #[cfg(test)]
 mod tests {
+    use super::super::lexer::{detokenize, tokenize};
     use super::*;
 
     #[test]
@@ -210,4 +254,24 @@ mod tests {
         ]);
         assert_eq!(format!("{}", tokens), "\\frac{a}{b}");
     }
+
+    #[test]
+    fn test_token_list_display_matches_detokenize() {
+        let tokens = tokenize("\\alpha x%note\ny\\beta\\gamma");
+        assert_eq!(tokens.to_string(), detokenize(&tokens));
+        assert_eq!(tokens.to_string(), "\\alpha x%note\ny\\beta \\gamma");
+    }
+
+    #[test]
+    fn test_groups() {
+        let tokens = tokenize("\\frac{a{b}}x{c");
+        let groups: Vec<String> = tokens
+            .groups()
+            .map(|group| TokenList::from_vec(group.to_vec()).to_string())
+            .collect();
+        assert_eq!(groups, ["\\frac", "{a{b}}", "x", "{c"]);
+
+        let tokens = tokenize("}a");
+        assert_eq!(tokens.groups().count(), 2);
+    }
 }
@@ -4,7 +4,11 @@ use super::token::{TexToken, TokenList};
 
 /// Maximum number of tokens to read in a single argument.
 /// This prevents runaway parsing when input contains unclosed braces.
-const MAX_ARG_TOKENS: usize = 10000;
+pub(super) const MAX_ARG_TOKENS: usize = 10000;
+
+/// Room reserved for a braced argument up front; most are short enough
+/// that reading one allocates only once
+const ARG_CAPACITY: usize = 16;
 
 /// Skip space and comment tokens
 pub fn skip_spaces<I>(iter: &mut std::iter::Peekable<I>)
@@ -19,6 +23,47 @@ where
     }
 }
 
+/// Whether a token is a control word (`\stop`), after which TeX's tokenizer
+/// skips spaces
+pub fn ends_control_word(token: &TexToken) -> bool {
+    matches!(token, TexToken::ControlSeq(name) if name.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Tokens of a delimiter that take part in matching: spaces after a control
+/// word are dropped
+pub fn significant_tokens(tokens: &[TexToken]) -> Vec<&TexToken> {
+    let mut result: Vec<&TexToken> = Vec::new();
+    for token in tokens {
+        let after_word = result.last().is_some_and(|prev| ends_control_word(prev));
+        if !(*token == TexToken::Space && after_word) {
+            result.push(token);
+        }
+    }
+    result
+}
+
+/// Strip the braces from an argument that is a single brace group, as TeX does
+/// for delimited arguments (`{a,b}` → `a,b`, but `{a}{b}` is kept)
+pub fn strip_outer_group(tokens: Vec<TexToken>) -> Vec<TexToken> {
+    if tokens.first() != Some(&TexToken::BeginGroup) || tokens.last() != Some(&TexToken::EndGroup) {
+        return tokens;
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TexToken::BeginGroup => depth += 1,
+            TexToken::EndGroup => {
+                depth -= 1;
+                if depth == 0 && i + 1 < tokens.len() {
+                    return tokens;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens[1..tokens.len() - 1].to_vec()
+}
+
 /// Read a control sequence name from the iterator
 #[allow(clippy::result_unit_err)]
 pub fn read_control_seq_name<I>(iter: &mut std::iter::Peekable<I>) -> Result<String, ()>
@@ -109,7 +154,7 @@ pub fn read_balanced_group<I>(iter: &mut I) -> TokenList
 where
     I: Iterator<Item = TexToken>,
 {
-    let mut result = Vec::new();
+    let mut result = Vec::with_capacity(ARG_CAPACITY);
     let mut depth = 1;
 
     for token in iter {
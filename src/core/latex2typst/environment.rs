@@ -4,12 +4,25 @@
 
 use mitex_parser::syntax::{CmdItem, EnvItem, SyntaxElement, SyntaxKind, SyntaxNode};
 use rowan::ast::AstNode;
+use std::collections::HashMap;
 use std::fmt::Write;
 
-use super::context::{ConversionMode, EnvironmentContext, LatexConverter};
-use super::table::{parse_with_grid_parser, CellAlign};
-use super::utils::sanitize_label;
-use crate::data::constants::{CodeBlockOptions, TheoremStyle, LANGUAGE_MAP, THEOREM_TYPES};
+use super::context::{
+    is_required_clause, ConversionMode, EnvironmentContext, LatexConverter, SidewaysStyle,
+    UnknownEnvironmentPolicy,
+};
+use super::framed::convert_framed_box;
+use super::markup::{built_tikz_picture, cetz_canvas, input_tikz_picture, metadata_marker};
+use super::math::{mitex_call, separate_display_math, sized_matrix_delimiter};
+use super::table::{parse_with_grid_parser, CellAlign, TableRules};
+use super::utils::{
+    line_spacing_rule, sanitize_label, APPENDIX_RULES, SUBAPPENDIX_RULES, WRAP_BODY_MARKER,
+};
+use super::{ConversionWarning, WarningKind};
+use crate::data::constants::{
+    CodeBlockOptions, TheoremStyle, DOUBLE_SPACING_STRETCH, LANGUAGE_MAP, ONEHALF_SPACING_STRETCH,
+    THEOREM_TYPES,
+};
 
 /// Convert a LaTeX environment
 pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, output: &mut String) {
@@ -26,6 +39,19 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
     let env_name = env.name_tok().map(|t| t.text().to_string());
     let env_str = env_name.as_deref().unwrap_or("");
 
+    // Semantic environments keep their content for Typst queries
+    if let Some(command) = conv.state.options.metadata_command(env_str).cloned() {
+        let body = conv.extract_env_raw_content(&node);
+        output.push_str(&metadata_marker(&command, &body));
+    }
+
+    if conv.state.options.math_passthrough && conv.state.mode != ConversionMode::Math {
+        if let Some(wrapper) = passthrough_math_wrapper(env_str) {
+            convert_passthrough_math(conv, &node, env_str, wrapper, output);
+            return;
+        }
+    }
+
     match env_str {
         // Document environment - marks end of preamble
         "document" => {
@@ -37,12 +63,23 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
         "figure" | "figure*" => {
             convert_figure(conv, &node, output);
         }
+        "wrapfigure" => {
+            convert_wrapfigure(conv, &node, output);
+        }
 
         // Table environment
         "table" | "table*" => {
             convert_table(conv, &node, output);
         }
 
+        // rotating's floats set a quarter turn
+        "sidewaysfigure" | "sidewaysfigure*" => {
+            convert_sideways(conv, &node, false, output);
+        }
+        "sidewaystable" | "sidewaystable*" => {
+            convert_sideways(conv, &node, true, output);
+        }
+
         // Tabular environment
         "tabular" | "tabular*" | "tabularx" | "longtable" | "longtabu" => {
             convert_tabular(conv, &node, output);
@@ -55,25 +92,30 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
 
         // List environments
         "itemize" => {
+            // A marker shared by all items becomes a scoped list marker
+            let marker = shared_item_marker(conv, &node);
+            if let Some(raw) = &marker {
+                let converted = conv.convert_argument_text(raw);
+                let _ = write!(output, "\n#[\n#set list(marker: [{}])", converted.trim());
+            }
+            conv.state.list_markers.push(marker.clone());
             conv.state.push_env(EnvironmentContext::Itemize);
-            output.push('\n');
-            conv.visit_env_content(&node, output);
+            convert_list_items(conv, &node, output);
             conv.state.pop_env();
-            output.push('\n');
+            conv.state.list_markers.pop();
+            if marker.is_some() {
+                output.push_str("]\n");
+            }
         }
         "enumerate" => {
             conv.state.push_env(EnvironmentContext::Enumerate);
-            output.push('\n');
-            conv.visit_env_content(&node, output);
+            convert_list_items(conv, &node, output);
             conv.state.pop_env();
-            output.push('\n');
         }
         "description" => {
             conv.state.push_env(EnvironmentContext::Description);
-            output.push('\n');
-            conv.visit_env_content(&node, output);
+            convert_list_items(conv, &node, output);
             conv.state.pop_env();
-            output.push('\n');
         }
 
         // Math environments
@@ -100,7 +142,9 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
         }
 
         // Matrix environments
-        "matrix" | "pmatrix" | "bmatrix" | "Bmatrix" | "vmatrix" | "Vmatrix" | "smallmatrix" => {
+        "matrix" | "pmatrix" | "bmatrix" | "Bmatrix" | "vmatrix" | "Vmatrix" | "matrix*"
+        | "pmatrix*" | "bmatrix*" | "Bmatrix*" | "vmatrix*" | "Vmatrix*" | "smallmatrix"
+        | "psmallmatrix" | "bsmallmatrix" | "Bsmallmatrix" | "vsmallmatrix" | "Vsmallmatrix" => {
             convert_matrix(conv, &node, env_str, output);
         }
 
@@ -125,6 +169,13 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
             convert_tikz(conv, &node, output);
         }
 
+        // picture / PSTricks graphics are kept as source behind a placeholder
+        "picture" | "pspicture" | "pspicture*" => {
+            if let Some(body) = picture_placeholder(conv, &node, env_str) {
+                let _ = write!(output, "\n#figure(\n  {},\n  kind: image,\n)\n", body);
+            }
+        }
+
         // Theorem-like environments
         "theorem" | "lemma" | "proposition" | "corollary" | "definition" | "example" | "remark"
         | "proof" | "conjecture" | "claim" | "fact" | "observation" | "property" | "question"
@@ -169,6 +220,29 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
             conv.visit_env_content(&node, output);
             output.push_str("\n]\n");
         }
+        "samepage" => {
+            output.push_str("#block(breakable: false)[\n");
+            conv.visit_env_content(&node, output);
+            output.push_str("\n]\n");
+        }
+
+        // setspace environments: scoped line spacing
+        "spacing" | "onehalfspace" | "doublespace" | "singlespace" => {
+            let stretch = match env_str {
+                "onehalfspace" => Some(ONEHALF_SPACING_STRETCH),
+                "doublespace" => Some(DOUBLE_SPACING_STRETCH),
+                "singlespace" => Some(1.0),
+                _ => conv
+                    .get_env_required_arg(&node, 0)
+                    .and_then(|arg| arg.trim().parse::<f64>().ok()),
+            };
+            output.push_str("#[\n");
+            if let Some(stretch) = stretch {
+                let _ = writeln!(output, "{}", line_spacing_rule(stretch));
+            }
+            conv.visit_env_content(&node, output);
+            output.push_str("\n]\n");
+        }
 
         // Minipage
         "minipage" => {
@@ -176,10 +250,24 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
                 .get_env_required_arg(&node, 0)
                 .unwrap_or("100%".to_string());
             let _ = writeln!(output, "#block(width: {})[", convert_dimension(&width));
+            conv.state.push_env(EnvironmentContext::Minipage);
             conv.visit_env_content(&node, output);
+            conv.state.pop_env();
             output.push_str("\n]\n");
         }
 
+        // pdflscape/lscape pages turned to landscape
+        "landscape" => {
+            output.push_str("\n#page(flipped: true)[\n");
+            conv.visit_env_content(&node, output);
+            output.push_str("\n]\n");
+        }
+
+        // textpos blocks at an absolute page position
+        "textblock" | "textblock*" => {
+            convert_textblock(conv, &node, output);
+        }
+
         // Bibliography
         "thebibliography" => {
             convert_bibliography(conv, &node, output);
@@ -187,9 +275,14 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
 
         // Appendix
         "appendix" | "appendices" => {
-            output.push_str("\n// Appendix\n");
+            output.push_str(APPENDIX_RULES);
             conv.visit_env_content(&node, output);
         }
+        "subappendices" => {
+            output.push_str(SUBAPPENDIX_RULES);
+            conv.visit_env_content(&node, output);
+            output.push_str("\n]\n");
+        }
 
         // Frame (beamer)
         "frame" => {
@@ -212,38 +305,294 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
             convert_subfigure(conv, &node, output);
         }
 
+        // tcolorbox and mdframed boxes
+        "tcolorbox" | "mdframed" => {
+            convert_framed_box(conv, &node, env_str, output);
+        }
+
         // Algorithm
         "algorithm" | "algorithmic" | "algorithm2e" => {
             convert_algorithm(conv, &node, output);
         }
 
-        // Unknown environments - pass through content
+        // Unknown environments
         _ => {
             // Check if it's a theorem-like environment defined by user
             if conv.state.counters.contains_key(env_str) {
                 convert_theorem(conv, &node, env_str, output);
             } else {
-                // Just process content
-                let _ = writeln!(output, "/* Begin {} */", env_str);
-                conv.visit_env_content(&node, output);
-                let _ = write!(output, "\n/* End {} */\n", env_str);
+                convert_unknown_environment(conv, &node, env_str, output);
             }
         }
     }
 }
 
+/// Convert an environment without a Typst mapping as
+/// `L2TOptions::unknown_environment_policy` says, warning with its line
+fn convert_unknown_environment(
+    conv: &mut LatexConverter,
+    node: &SyntaxNode,
+    name: &str,
+    output: &mut String,
+) {
+    let position = environment_position(conv, node, name);
+
+    let message = match conv.state.options.unknown_environment_policy {
+        UnknownEnvironmentPolicy::ConvertBody => {
+            conv.visit_env_content(node, output);
+            output.push('\n');
+            format!("unknown environment '{}': converted its content only", name)
+        }
+        UnknownEnvironmentPolicy::RawBlock => {
+            let source = original_environment_source(conv, node, name, position);
+            let _ = write!(
+                output,
+                "\n// Unconverted environment '{}'\n{}\n",
+                name,
+                latex_raw_block(&source)
+            );
+            format!("unknown environment '{}' kept as raw LaTeX", name)
+        }
+        UnknownEnvironmentPolicy::Drop => format!("unknown environment '{}' dropped", name),
+    };
+
+    let location = environment_location(conv, name, position);
+    conv.state.add_warning(
+        ConversionWarning::new(WarningKind::UnknownEnvironment, message).with_location(location),
+    );
+}
+
+/// Byte offset of the `\begin{name}` of `node` in the original source.
+///
+/// The tree holds the macro-expanded input, so the same occurrence is found
+/// by counting the environments of that name before it.
+fn environment_position(conv: &LatexConverter, node: &SyntaxNode, name: &str) -> Option<usize> {
+    let begin = format!("\\begin{{{}}}", name);
+    let offset = node.text_range().start();
+    let root = node.ancestors().last().unwrap_or_else(|| node.clone());
+    let nth = root
+        .descendants()
+        .filter(|n| n.text_range().start() < offset)
+        .filter_map(EnvItem::cast)
+        .filter(|env| env.name_tok().is_some_and(|t| t.text() == name))
+        .count();
+    conv.state
+        .source
+        .match_indices(&begin)
+        .nth(nth)
+        .map(|(pos, _)| pos)
+}
+
+/// `\begin{name} (line N)`, for warnings
+fn environment_location(conv: &LatexConverter, name: &str, position: Option<usize>) -> String {
+    let begin = format!("\\begin{{{}}}", name);
+    match position {
+        Some(pos) => {
+            let line = conv.state.source[..pos].matches('\n').count() + 1;
+            format!("{} (line {})", begin, line)
+        }
+        None => begin,
+    }
+}
+
+/// The original text of an environment, falling back to the parsed tree
+fn original_environment_source(
+    conv: &LatexConverter,
+    node: &SyntaxNode,
+    name: &str,
+    position: Option<usize>,
+) -> String {
+    position
+        .and_then(|pos| environment_source(&conv.state.source[pos..], name))
+        .map(str::to_string)
+        .unwrap_or_else(|| node.text().to_string())
+}
+
+/// LaTeX source as a Typst raw block, fenced longer than any backtick run in it
+fn latex_raw_block(source: &str) -> String {
+    let longest_run = source.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}latex\n{}\n{}", fence, source, fence)
+}
+
+/// Figure body standing in for a `picture`/`pspicture`: a dashed box the
+/// size of the picture over its original source. Warns once per picture;
+/// returns None when the unknown environment policy drops it.
+fn picture_placeholder(conv: &mut LatexConverter, node: &SyntaxNode, name: &str) -> Option<String> {
+    let position = environment_position(conv, node, name);
+    let source = original_environment_source(conv, node, name, position);
+    let location = environment_location(conv, name, position);
+
+    let begin = format!("\\begin{{{}}}", name);
+    let coordinates = picture_coordinates(source.strip_prefix(&begin).unwrap_or_default());
+    let size = if name == "picture" {
+        // (width,height)(x0,y0) in \unitlength
+        let unit = conv
+            .state
+            .lengths
+            .get("unitlength")
+            .cloned()
+            .unwrap_or_else(|| "1pt".to_string());
+        coordinates
+            .first()
+            .map(|&(w, h)| (scale_length(w, &unit), scale_length(h, &unit)))
+    } else {
+        // (x0,y0)(x1,y1) or (x1,y1) in PSTricks' default unit of 1cm
+        let ((x0, y0), (x1, y1)) = match coordinates.as_slice() {
+            [lower, upper, ..] => (*lower, *upper),
+            [upper] => ((0.0, 0.0), *upper),
+            [] => ((0.0, 0.0), (0.0, 0.0)),
+        };
+        (!coordinates.is_empty())
+            .then(|| (scale_length(x1 - x0, "1cm"), scale_length(y1 - y0, "1cm")))
+    };
+
+    if conv.state.options.unknown_environment_policy == UnknownEnvironmentPolicy::Drop {
+        conv.state.add_warning(
+            ConversionWarning::new(WarningKind::UnknownEnvironment, format!("{} dropped", name))
+                .with_location(location),
+        );
+        return None;
+    }
+
+    let (rect, size_hint) = match size {
+        Some((width, height)) => (
+            format!(
+                "#rect(width: {}, height: {}, stroke: (dash: \"dashed\"))",
+                width, height
+            ),
+            format!(" ({} x {})", width, height),
+        ),
+        None => (
+            "#rect(stroke: (dash: \"dashed\"))".to_string(),
+            String::new(),
+        ),
+    };
+    conv.state.add_warning(
+        ConversionWarning::new(
+            WarningKind::UnknownEnvironment,
+            format!(
+                "{}{} kept as a placeholder with its source",
+                name, size_hint
+            ),
+        )
+        .with_location(location),
+    );
+    Some(format!(
+        "[\n{}[Unconverted picture]\n{}\n]",
+        rect,
+        latex_raw_block(&source)
+    ))
+}
+
+/// The leading `(x,y)` pairs of a picture environment, after an optional
+/// `*` and `[options]`
+fn picture_coordinates(args: &str) -> Vec<(f64, f64)> {
+    let mut rest = args.trim_start().trim_start_matches('*').trim_start();
+    if rest.starts_with('[') {
+        rest = rest.find(']').map_or("", |end| &rest[end + 1..]);
+    }
+    let mut coordinates = Vec::new();
+    while let Some(inner) = rest.trim_start().strip_prefix('(') {
+        let Some(end) = inner.find(')') else {
+            break;
+        };
+        let mut parts = inner[..end]
+            .split(',')
+            .map(|part| part.trim().parse::<f64>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(x)), Some(Ok(y))) => coordinates.push((x, y)),
+            _ => break,
+        }
+        rest = &inner[end + 1..];
+    }
+    coordinates
+}
+
+/// `value` times a length such as `1mm` or `0.5cm`
+fn scale_length(value: f64, unit: &str) -> String {
+    let unit = unit.trim();
+    let split = unit
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(unit.len());
+    let factor = if split == 0 {
+        Ok(1.0)
+    } else {
+        unit[..split].parse::<f64>()
+    };
+    match factor {
+        Ok(factor) => format!("{}{}", value * factor, &unit[split..]),
+        Err(_) => format!("{} * {}", value, unit),
+    }
+}
+
+/// The text of `\begin{name} .. \end{name}` at the start of `source`,
+/// skipping nested environments of the same name
+fn environment_source<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let begin = format!("\\begin{{{}}}", name);
+    let end = format!("\\end{{{}}}", name);
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while pos < source.len() {
+        let rest = &source[pos..];
+        if rest.starts_with(&begin) {
+            depth += 1;
+            pos += begin.len();
+        } else if rest.starts_with(&end) {
+            depth -= 1;
+            pos += end.len();
+            if depth == 0 {
+                return Some(&source[..pos]);
+            }
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
 // =============================================================================
 // Environment conversion functions
 // =============================================================================
 
+/// Convert the body of a list. The items are converted into a buffer of
+/// their own, where each `\item` can drop the blank lines before it; a
+/// nested list starts right below the line of its parent item.
+fn convert_list_items(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
+    let mut items = String::new();
+    conv.visit_env_content(node, &mut items);
+    if conv.state.indent > 2 {
+        output.truncate(output.trim_end().len());
+    } else {
+        output.push('\n');
+    }
+    output.push_str(&items);
+    output.push('\n');
+}
+
+/// The optional `\item[..]` label shared by every item of a list, if any
+fn shared_item_marker(conv: &LatexConverter, node: &SyntaxNode) -> Option<String> {
+    let mut markers = node
+        .children()
+        .filter_map(CmdItem::cast)
+        .filter(|cmd| cmd.name_tok().is_some_and(|t| t.text() == "\\item"))
+        .map(|cmd| conv.get_optional_arg_with_braces(&cmd, 0));
+    let first = markers.next()??;
+    markers
+        .all(|marker| marker.as_deref() == Some(first.as_str()))
+        .then_some(first)
+}
+
 /// Convert a figure environment
 fn convert_figure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     conv.state.push_env(EnvironmentContext::Figure);
 
+    let start = output.len();
     output.push_str("\n#figure(\n");
 
     // Find image and caption using AST
     let mut has_image = false;
+    let mut cetz_import = None;
     let mut caption_cmd: Option<CmdItem> = None;
     let mut label_text = String::new();
 
@@ -252,15 +601,32 @@ fn convert_figure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Str
             if let Some(cmd) = CmdItem::cast(n.clone()) {
                 if let Some(name_tok) = cmd.name_tok() {
                     let name = name_tok.text();
-                    if name == "\\includegraphics" {
+                    let path = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                    // Figure files with a TikZ source become CeTZ canvases
+                    let picture = match name {
+                        "\\includegraphics" => built_tikz_picture(conv, &path),
+                        "\\input" => input_tikz_picture(conv, &path),
+                        _ => None,
+                    };
+                    if let Some(picture) = picture {
+                        has_image = true;
+                        let (import, canvas) = cetz_canvas(conv, &picture);
+                        let canvas = canvas
+                            .trim_start_matches('#')
+                            .trim_end()
+                            .replace('\n', "\n  ");
+                        let _ = writeln!(output, "  {},", canvas);
+                        cetz_import = Some(import);
+                    } else if name == "\\includegraphics" {
                         has_image = true;
                         output.push_str("  image(\"");
-                        if let Some(path) = conv.get_required_arg(&cmd, 0) {
-                            output.push_str(&path);
-                        }
+                        output.push_str(&conv.resolve_image_path(&path));
                         output.push_str("\"),\n");
                     } else if name == "\\caption" {
                         // Store the command for later conversion
+                        if let Some(lbl) = caption_label(conv, &cmd) {
+                            label_text = lbl;
+                        }
                         caption_cmd = Some(cmd.clone());
                     } else if name == "\\label" {
                         if let Some(lbl) = conv.get_required_arg(&cmd, 0) {
@@ -273,7 +639,26 @@ fn convert_figure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Str
     }
 
     if !has_image {
-        output.push_str("  [],\n"); // Placeholder
+        let picture = node.children().filter_map(EnvItem::cast).find_map(|env| {
+            let name = env.name_tok()?.text().to_string();
+            matches!(name.as_str(), "picture" | "pspicture" | "pspicture*")
+                .then(|| (env.syntax().clone(), name))
+        });
+        match picture.and_then(|(env, name)| picture_placeholder(conv, &env, &name)) {
+            Some(body) => {
+                let _ = writeln!(output, "  {},\n  kind: image,", body);
+            }
+            None => match figure_body(conv, node) {
+                Some(body) => {
+                    let _ = writeln!(output, "  [\n{}\n  ],", body);
+                }
+                None => output.push_str("  [],\n"), // Placeholder
+            },
+        }
+    }
+
+    if let Some(placement) = float_placement(conv, node) {
+        let _ = writeln!(output, "  placement: {},", placement);
     }
 
     // Convert caption content (may contain math like $\downarrow$)
@@ -290,10 +675,279 @@ fn convert_figure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Str
     }
 
     output.push('\n');
+    // Imports cannot appear inside the figure call
+    if let Some(import) = cetz_import {
+        output.insert_str(start, &format!("\n{}\n", import));
+    }
 
     conv.state.pop_env();
 }
 
+/// The content of a figure without an image, such as minipages set side by
+/// side, converted to stand in for the image
+fn figure_body(conv: &mut LatexConverter, node: &SyntaxNode) -> Option<String> {
+    let mut body = String::new();
+    conv.enter_group();
+    for child in node.children_with_tokens() {
+        if matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd) {
+            continue;
+        }
+        let name = child
+            .as_node()
+            .and_then(|n| CmdItem::cast(n.clone()))
+            .and_then(|cmd| cmd.name_tok());
+        if name.is_some_and(|name| matches!(name.text(), "\\caption" | "\\label")) {
+            continue;
+        }
+        conv.visit_element(child, &mut body);
+    }
+    conv.leave_group(&mut body);
+    let body = body.trim();
+    (!body.is_empty()).then(|| body.to_string())
+}
+
+/// The key of a `\label` inside a caption, which Typst attaches after the
+/// figure instead
+fn caption_label(conv: &LatexConverter, caption: &CmdItem) -> Option<String> {
+    caption
+        .syntax()
+        .descendants()
+        .filter_map(CmdItem::cast)
+        .find(|cmd| cmd.name_tok().is_some_and(|name| name.text() == "\\label"))
+        .and_then(|cmd| conv.get_required_arg(&cmd, 0))
+}
+
+/// Typst `placement` for the `[htbp!H]` specifier of a figure or table
+/// float. `H` pins the float in place, a lone `t` or `b` keeps its side and
+/// any other combination floats automatically. Specifiers Typst cannot honour
+/// (`p`, `H` mixed with other letters, unknown letters) get an info diagnostic.
+fn float_placement(conv: &mut LatexConverter, node: &SyntaxNode) -> Option<&'static str> {
+    let env = EnvItem::cast(node.clone())?.name_tok()?.text().to_string();
+    if !matches!(env.as_str(), "figure" | "figure*" | "table" | "table*") {
+        return None;
+    }
+    let spec = conv.get_env_optional_arg(node)?;
+    let letters: String = spec
+        .chars()
+        .filter(|c| *c != '!' && !c.is_whitespace())
+        .collect();
+    let (placement, exact) = match letters.as_str() {
+        "" => return None,
+        "H" => ("none", true),
+        "t" => ("top", true),
+        "b" => ("bottom", true),
+        _ if letters.contains('H') => ("none", false),
+        _ => (
+            "auto",
+            letters.chars().all(|c| matches!(c, 'h' | 't' | 'b')),
+        ),
+    };
+    if !exact {
+        conv.state.add_warning(
+            ConversionWarning::new(
+                WarningKind::PageFlow,
+                format!(
+                    "Float specifier `{}` has no Typst equivalent; using `placement: {}`",
+                    spec.trim(),
+                    placement
+                ),
+            )
+            .with_location(format!("\\begin{{{}}}[{}]", env, spec.trim())),
+        );
+    }
+    Some(placement)
+}
+
+/// Convert a `wrapfigure`. With [`L2TOptions::enable_wrap_package`] the
+/// figure and the paragraph after it go into `wrap-content`; otherwise the
+/// figure is only aligned to its side, as Typst cannot flow text around it.
+///
+/// [`L2TOptions::enable_wrap_package`]: super::L2TOptions::enable_wrap_package
+fn convert_wrapfigure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
+    let placement = conv.get_env_required_arg(node, 0).unwrap_or_default();
+    let side = match placement.trim() {
+        "l" | "L" | "i" | "I" => "left",
+        _ => "right",
+    };
+    let width = conv
+        .get_env_required_arg(node, 1)
+        .map(|width| convert_dimension(&width))
+        .filter(|width| !width.is_empty())
+        .unwrap_or_else(|| "50%".to_string());
+
+    let mut figure = String::new();
+    convert_figure(conv, node, &mut figure);
+    let figure = figure.trim();
+
+    if conv.state.options.enable_wrap_package {
+        conv.state.uses_wrap_package = true;
+        let _ = write!(
+            output,
+            "\n#wrap-content(\n  block(width: {})[{}],\n  align: {},\n)[{}",
+            width, figure, side, WRAP_BODY_MARKER
+        );
+    } else {
+        conv.state.add_warning(ConversionWarning::approximation(
+            "\\begin{wrapfigure}",
+            format!(
+                "Text does not wrap around the figure; it is {}-aligned instead \
+                 (enable `enable_wrap_package` to use wrap-it)",
+                side
+            ),
+        ));
+        let _ = writeln!(
+            output,
+            "\n#align({}, block(width: {})[{}])",
+            side, width, figure
+        );
+    }
+}
+
+/// Convert a textpos `textblock*` to a `place()` call. The anchor `[ax,ay]`
+/// gives the alignment; positions in page coordinates such as
+/// `\dimexpr\paperwidth-1cm\relax` become `dx`/`dy` offsets from it.
+fn convert_textblock(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
+    let anchor = conv.get_env_optional_arg(node).unwrap_or_default();
+    let mut fractions = anchor
+        .split(',')
+        .map(|f| f.trim().parse::<f64>().unwrap_or(0.0));
+    let (ax, ay) = (
+        fractions.next().unwrap_or(0.0),
+        fractions.next().unwrap_or(0.0),
+    );
+    let (horizontal, ax) = match ax {
+        f if f < 0.25 => ("left", 0.0),
+        f if f > 0.75 => ("right", 1.0),
+        _ => ("center", 0.5),
+    };
+    let (vertical, ay) = match ay {
+        f if f < 0.25 => ("top", 0.0),
+        f if f > 0.75 => ("bottom", 1.0),
+        _ => ("horizon", 0.5),
+    };
+
+    // The position `(x,y)` is not an argument to the parser: it leads the body
+    let mut position = String::new();
+    let mut body = String::new();
+    let mut depth = 0;
+    let mut closed = false;
+    for child in node.children_with_tokens() {
+        match child.kind() {
+            SyntaxKind::ItemBegin | SyntaxKind::ItemEnd => continue,
+            _ if closed => conv.visit_element(child, &mut body),
+            _ => {
+                let text = match &child {
+                    SyntaxElement::Token(t) => t.text().to_string(),
+                    SyntaxElement::Node(n) => n.text().to_string(),
+                };
+                if position.is_empty() && text.trim().is_empty() {
+                    continue;
+                }
+                if position.is_empty() && !text.trim_start().starts_with('(') {
+                    closed = true;
+                    conv.visit_element(child, &mut body);
+                    continue;
+                }
+                for (i, c) in text.char_indices() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 && c == ')' {
+                        closed = true;
+                        body.push_str(&text[i + 1..]);
+                        break;
+                    }
+                    position.push(c);
+                }
+            }
+        }
+    }
+    let position = position.trim().trim_start_matches('(');
+    let (x, y) = split_top_level_comma(position);
+
+    let mut args = format!("{} + {}", vertical, horizontal);
+    for (name, coordinate, page, fraction) in [
+        ("dx", x, "\\paperwidth", ax),
+        ("dy", y, "\\paperheight", ay),
+    ] {
+        if let Some(offset) = page_offset(coordinate, page, fraction) {
+            let _ = write!(args, ", {}: {}", name, offset);
+        }
+    }
+    let _ = write!(output, "\n#place({})[\n{}\n]\n", args, body.trim());
+}
+
+/// Split `x,y` at its first comma outside braces
+fn split_top_level_comma(s: &str) -> (&str, &str) {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => return (&s[..i], &s[i + 1..]),
+            _ => {}
+        }
+    }
+    (s, "")
+}
+
+/// Offset of a page coordinate such as `0.5\paperwidth` or
+/// `\dimexpr\paperheight-1cm\relax` from the fraction of the page the
+/// alignment puts a block at; `None` when there is none
+fn page_offset(coordinate: &str, page: &str, fraction: f64) -> Option<String> {
+    let coordinate: String = coordinate
+        .trim()
+        .trim_start_matches("\\dimexpr")
+        .trim_end_matches("\\relax")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    // Sum the terms: fractions of the page and other lengths
+    let mut base = 0.0;
+    let mut lengths = String::new();
+    let mut term_start = 0;
+    for (i, c) in coordinate.char_indices().chain([(coordinate.len(), '+')]) {
+        if (c != '+' && c != '-') || i == term_start {
+            continue;
+        }
+        let term = &coordinate[term_start..i];
+        term_start = i;
+        let (sign, magnitude) = match term.strip_prefix('-') {
+            Some(magnitude) => (-1.0, magnitude),
+            None => (1.0, term.trim_start_matches('+')),
+        };
+        if let Some(factor) = magnitude.strip_suffix(page) {
+            base += sign * factor.parse::<f64>().unwrap_or(1.0);
+        } else if magnitude
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .parse::<f64>()
+            != Ok(0.0)
+        {
+            let _ = write!(
+                lengths,
+                " {} {}",
+                if sign < 0.0 { '-' } else { '+' },
+                magnitude
+            );
+        }
+    }
+    let length = lengths.trim_start();
+
+    let percent = ((base - fraction) * 10000.0).round() / 100.0;
+    match (percent == 0.0, length) {
+        (true, "") => None,
+        (true, length) => Some(
+            length
+                .strip_prefix("+ ")
+                .map_or_else(|| length.replacen("- ", "-", 1), str::to_string),
+        ),
+        (false, "") => Some(format!("{}%", percent)),
+        (false, length) => Some(format!("{}% {}", percent, length)),
+    }
+}
+
 /// Convert a table environment
 fn convert_table(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     conv.state.push_env(EnvironmentContext::Table);
@@ -301,19 +955,31 @@ fn convert_table(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Stri
     let mut caption_cmd: Option<CmdItem> = None;
     let mut label_text = String::new();
     let mut table_content = String::new();
+    let mut flipped = false;
 
     // First pass: extract caption, label, and tabular content using AST
+    conv.enter_group();
     for child in node.children_with_tokens() {
         if let SyntaxElement::Node(n) = &child {
             if let Some(cmd) = CmdItem::cast(n.clone()) {
                 if let Some(name_tok) = cmd.name_tok() {
                     let name = name_tok.text();
                     if name == "\\caption" {
+                        if let Some(lbl) = caption_label(conv, &cmd) {
+                            label_text = lbl;
+                        }
                         caption_cmd = Some(cmd.clone());
                     } else if name == "\\label" {
                         if let Some(lbl) = conv.get_required_arg(&cmd, 0) {
                             label_text = lbl;
                         }
+                    } else if name == "\\setlength" {
+                        // Spacing parameters for the tables of this float
+                        conv.visit_element(child.clone(), &mut table_content);
+                    } else if let Some(tabular) = rotated_tabular(conv, &cmd) {
+                        // A tabular turned sideways: the float goes on a landscape page
+                        convert_tabular(conv, &tabular, &mut table_content);
+                        flipped = true;
                     }
                 }
             }
@@ -331,10 +997,18 @@ fn convert_table(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Stri
             }
         }
     }
+    conv.leave_group(&mut table_content);
 
     // Build properly formatted figure
+    if flipped {
+        output.push_str("\n#page(flipped: true)[");
+    }
     output.push_str("\n#figure(");
 
+    if let Some(placement) = float_placement(conv, node) {
+        let _ = write!(output, "\n  placement: {},", placement);
+    }
+
     // Convert caption content (may contain math)
     if let Some(ref cmd) = caption_cmd {
         if let Some(cap) = conv.get_converted_required_arg(cmd, 0) {
@@ -351,10 +1025,56 @@ fn convert_table(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Stri
     }
 
     output.push('\n');
+    if flipped {
+        output.push_str("]\n");
+    }
 
     conv.state.pop_env();
 }
 
+/// Convert a `sidewaysfigure` or `sidewaystable` as a figure or table turned
+/// with the whole figure, caption included, or set on a landscape page
+fn convert_sideways(
+    conv: &mut LatexConverter,
+    node: &SyntaxNode,
+    table: bool,
+    output: &mut String,
+) {
+    let mut figure = String::new();
+    if table {
+        convert_table(conv, node, &mut figure);
+    } else {
+        convert_figure(conv, node, &mut figure);
+    }
+    let open = match conv.state.options.sideways_style {
+        SidewaysStyle::Rotate => "#rotate(90deg, reflow: true)[",
+        SidewaysStyle::FlippedPage => "#page(flipped: true)[",
+    };
+    let _ = write!(output, "\n{}\n{}\n]\n", open, figure.trim());
+}
+
+/// The tabular of `\rotatebox{90}{\begin{tabular}..}` or another quarter
+/// turn, the usual way to set a table sideways without pdflscape
+fn rotated_tabular(conv: &LatexConverter, cmd: &CmdItem) -> Option<SyntaxNode> {
+    if cmd.name_tok()?.text() != "\\rotatebox" {
+        return None;
+    }
+    let angle: f64 = conv.get_required_arg(cmd, 0)?.trim().parse().ok()?;
+    if angle % 180.0 == 0.0 || angle % 90.0 != 0.0 {
+        return None;
+    }
+    cmd.syntax()
+        .children()
+        .filter(is_required_clause)
+        .nth(1)?
+        .descendants()
+        .find(|n| {
+            EnvItem::cast(n.clone())
+                .and_then(|env| env.name_tok())
+                .is_some_and(|name| name.text().starts_with("tabular"))
+        })
+}
+
 /// Convert a tabular environment using the state-aware grid parser
 fn convert_tabular(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     conv.state.push_env(EnvironmentContext::Tabular);
@@ -365,18 +1085,15 @@ fn convert_tabular(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut St
 
     // Get column specification from the environment's first required argument
     let col_spec = get_tabular_col_spec(node).unwrap_or_default();
-    let columns = parse_column_spec(&col_spec);
-
-    // Convert column specs to CellAlign
-    let alignments: Vec<CellAlign> = columns
-        .iter()
-        .map(|c| match c.as_str() {
-            "l" => CellAlign::Left,
-            "r" => CellAlign::Right,
-            "c" => CellAlign::Center,
-            _ => CellAlign::Auto,
-        })
-        .collect();
+    let col_spec = expand_column_types(&col_spec, &conv.state.column_types);
+    let (alignments, cell_formats): (Vec<CellAlign>, Vec<(String, String)>) =
+        parse_column_spec(&col_spec)
+            .iter()
+            .map(|column| {
+                let (align, before, after) = column_format(conv, column);
+                (align, (before, after))
+            })
+            .unzip();
 
     // Collect table content
     let mut content = String::new();
@@ -385,8 +1102,20 @@ fn convert_tabular(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut St
     // Restore previous mode
     conv.state.mode = prev_mode;
 
+    let rules = TableRules {
+        width: conv.state.lengths.get("arrayrulewidth").cloned(),
+        double: conv.state.options.double_rule,
+        vlines: parse_vrule_separators(&col_spec),
+    };
+
     // Use the new grid parser
-    let typst_output = parse_with_grid_parser(&content, alignments);
+    let typst_output = parse_with_grid_parser(
+        &content,
+        alignments,
+        rules,
+        cell_formats,
+        &conv.state.table_spacing,
+    );
     output.push_str(&typst_output);
 
     conv.state.pop_env();
@@ -429,6 +1158,7 @@ fn convert_equation(
     // Apply math cleanup
     let cleaned = conv.cleanup_math_spacing(&math_content);
 
+    separate_display_math(conv, output);
     // For starred equations (equation*), disable numbering
     if is_starred {
         output.push_str("#math.equation(block: true, numbering: none)[\n$ ");
@@ -450,6 +1180,52 @@ fn convert_equation(
     conv.state.pop_env();
 }
 
+/// For display math environments, the environment their body is set in when
+/// passed through to mitex (empty for a plain body)
+fn passthrough_math_wrapper(env_name: &str) -> Option<&'static str> {
+    match env_name.trim_end_matches('*') {
+        "equation" | "displaymath" | "math" => Some(""),
+        "align" | "flalign" | "alignat" | "eqnarray" => Some("aligned"),
+        "gather" | "multline" => Some("gathered"),
+        _ => None,
+    }
+}
+
+/// Keep a display math environment as LaTeX in a mitex call
+fn convert_passthrough_math(
+    conv: &mut LatexConverter,
+    node: &SyntaxNode,
+    env_name: &str,
+    wrapper: &str,
+    output: &mut String,
+) {
+    let mut body = String::new();
+    let mut label = None;
+    for child in node.children_with_tokens() {
+        if matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd) {
+            continue;
+        }
+        if let Some(cmd) = child.as_node().and_then(|n| CmdItem::cast(n.clone())) {
+            match cmd.name_tok().as_ref().map(|t| t.text()) {
+                Some("\\label") => {
+                    label = conv.get_required_arg(&cmd, 0).map(|l| sanitize_label(&l));
+                    continue;
+                }
+                Some("\\nonumber" | "\\notag") => continue,
+                _ => {}
+            }
+        }
+        body.push_str(&child.to_string());
+    }
+    if !wrapper.is_empty() {
+        body = format!("\\begin{{{0}}}\n{1}\n\\end{{{0}}}", wrapper, body.trim());
+    }
+    let numbered = !env_name.ends_with('*') && !matches!(env_name, "displaymath" | "math");
+    conv.state.uses_mitex_package = true;
+    output.push_str(&mitex_call(true, numbered, label.as_deref(), &body));
+    output.push('\n');
+}
+
 /// Convert an align environment
 fn convert_align(
     conv: &mut LatexConverter,
@@ -491,6 +1267,7 @@ fn convert_align(
     let cleaned = conv.cleanup_math_spacing(&math_content);
 
     if !is_inner {
+        separate_display_math(conv, output);
         // For starred environments (align*, eqnarray*, etc.), disable numbering
         if is_starred {
             output.push_str("#math.equation(block: true, numbering: none)[\n$ ");
@@ -585,7 +1362,26 @@ fn convert_matrix(
     env_name: &str,
     output: &mut String,
 ) {
-    convert_matrix_with_delim(conv, node, env_name, None, output);
+    // \bigl( \begin{smallmatrix}..\end{smallmatrix} \bigr) delimits the matrix
+    let delim = sized_matrix_delimiter(node);
+    convert_matrix_with_delim(conv, node, env_name, delim, output);
+}
+
+/// The `mat(delim: ..)` a matrix environment brings itself, by its prefix
+fn matrix_delimiter(env_name: &str) -> Option<&'static str> {
+    let base = env_name.trim_end_matches('*');
+    let prefix = base
+        .strip_suffix("smallmatrix")
+        .or_else(|| base.strip_suffix("matrix"))
+        .unwrap_or(base);
+    match prefix {
+        "p" => Some("("),
+        "b" => Some("["),
+        "B" => Some("{"),
+        "v" => Some("|"),
+        "V" => Some("‖"), // Use double bar Unicode character for Typst
+        _ => None,
+    }
 }
 
 pub(crate) fn convert_matrix_with_delim(
@@ -595,6 +1391,18 @@ pub(crate) fn convert_matrix_with_delim(
     delim_override: Option<&str>,
     output: &mut String,
 ) {
+    // The mathtools starred variants take the column alignment: pmatrix*[r]
+    let align = if env_name.ends_with('*') {
+        conv.get_env_optional_arg(node)
+            .and_then(|spec| match spec.trim() {
+                "l" => Some("left"),
+                "r" => Some("right"),
+                _ => None,
+            })
+    } else {
+        None
+    };
+
     conv.state.push_env(EnvironmentContext::Matrix);
     let prev_mode = conv.state.mode;
     conv.state.mode = ConversionMode::Math;
@@ -605,18 +1413,8 @@ pub(crate) fn convert_matrix_with_delim(
     conv.state.mode = prev_mode;
     conv.state.pop_env();
 
-    // Determine delimiter type
-    // For plain "matrix" environment, use delim: #none
-    // For others, use the appropriate delimiter string
-    let delim = delim_override.or(match env_name {
-        "pmatrix" => Some("("),
-        "bmatrix" => Some("["),
-        "Bmatrix" => Some("{"),
-        "vmatrix" => Some("|"),
-        "Vmatrix" => Some("‖"), // Use double bar Unicode character for Typst
-        "smallmatrix" | "matrix" => None,
-        _ => None,
-    });
+    // Plain "matrix" gets delim: #none, the others their delimiter string
+    let delim = delim_override.or_else(|| matrix_delimiter(env_name));
 
     // Clean up content - remove zws markers and format
     let content = content
@@ -625,13 +1423,18 @@ pub(crate) fn convert_matrix_with_delim(
         .trim()
         .to_string();
 
-    match delim {
-        Some(d) => {
-            let _ = write!(output, "mat(delim: \"{}\", {}) ", d, content);
-        }
-        None => {
-            let _ = write!(output, "mat(delim: #none, {}) ", content);
-        }
+    let mut args = match delim {
+        Some(d) => format!("delim: \"{}\", ", d),
+        None => "delim: #none, ".to_string(),
+    };
+    if let Some(align) = align {
+        let _ = write!(args, "align: {}, ", align);
+    }
+    // smallmatrix sets its entries in the smaller inline style
+    if env_name.contains("smallmatrix") {
+        let _ = write!(output, "inline(mat({}{})) ", args, content);
+    } else {
+        let _ = write!(output, "mat({}{}) ", args, content);
     }
 }
 
@@ -778,9 +1581,16 @@ fn convert_minted(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Str
 fn convert_tikz(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     conv.state.push_env(EnvironmentContext::TikZ);
 
-    // Use the TikZ to CeTZ transpiler
-    let tikz_source = node.text().to_string();
-    let cetz_code = crate::tikz::convert_tikz_to_cetz(&tikz_source);
+    // Use the TikZ to CeTZ transpiler. The node text holds the environment
+    // name instead of `\begin{..}`/`\end{..}`, so the source is rebuilt.
+    let body: String = node
+        .children_with_tokens()
+        .filter(|child| !matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd))
+        .map(|child| child.to_string())
+        .collect();
+    let tikz_source = format!("\\begin{{tikzpicture}}{}\\end{{tikzpicture}}", body);
+    let cetz_code =
+        crate::tikz::convert_tikz_to_cetz_for(&tikz_source, conv.state.options.cetz_version);
 
     output.push_str("\n// TikZ converted to CeTZ\n");
     output.push_str(&cetz_code);
@@ -789,6 +1599,20 @@ fn convert_tikz(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Strin
     conv.state.pop_env();
 }
 
+/// Name a theorem-like environment is shown with: from the mapping table,
+/// or the environment name capitalized
+pub(crate) fn theorem_display_name(env_name: &str) -> String {
+    if let Some(info) = THEOREM_TYPES.get(env_name) {
+        return info.display_name.to_string();
+    }
+    env_name
+        .chars()
+        .next()
+        .map(|c| c.to_uppercase().to_string())
+        .unwrap_or_default()
+        + &env_name[1..]
+}
+
 /// Convert a theorem-like environment
 fn convert_theorem(
     conv: &mut LatexConverter,
@@ -799,19 +1623,10 @@ fn convert_theorem(
     let env_ctx = EnvironmentContext::Theorem(env_name.to_string());
     conv.state.push_env(env_ctx);
 
-    // Get theorem info from mapping table, or use defaults
-    let (display_name, style) = if let Some(info) = THEOREM_TYPES.get(env_name) {
-        (info.display_name.to_string(), info.style)
-    } else {
-        // Fallback: capitalize first letter
-        let name = env_name
-            .chars()
-            .next()
-            .map(|c| c.to_uppercase().to_string())
-            .unwrap_or_default()
-            + &env_name[1..];
-        (name, TheoremStyle::Plain)
-    };
+    let display_name = theorem_display_name(env_name);
+    let style = THEOREM_TYPES
+        .get(env_name)
+        .map_or(TheoremStyle::Plain, |info| info.style);
 
     // Proof doesn't get numbered
     let is_proof = env_name == "proof";
@@ -879,11 +1694,18 @@ fn convert_theorem(
 }
 
 /// Convert a bibliography environment
+///
+/// Each `\bibitem` becomes a numbered figure of kind `"bib"` carrying the
+/// item's key as label, so citations can reference it (plain list items
+/// cannot be referenced in Typst). The show rule lays the figures out as a
+/// `[1] entry` list.
 fn convert_bibliography(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     conv.state.push_env(EnvironmentContext::Bibliography);
 
-    output.push_str("\n= References\n\n");
-    output.push_str("#show figure.where(kind: \"bib\"): it => block[#it.caption #it.body]\n");
+    output.push_str("\n#heading(numbering: none)[References]\n\n");
+    output.push_str(
+        "#show figure.where(kind: \"bib\"): it => block(width: 100%, align(left)[\\[#it.counter.display(it.numbering)\\] #it.body])\n",
+    );
 
     // Process bibitem commands using the dedicated function
     convert_thebibliography_content(conv, node, output);
@@ -897,75 +1719,64 @@ fn convert_thebibliography_content(
     node: &SyntaxNode,
     output: &mut String,
 ) {
-    let mut bib_counter = 1;
-    let mut current_label = String::new();
-    let mut in_item = false;
+    // (custom label, key) of the entry being collected
+    let mut current: Option<(Option<String>, String)> = None;
+    let mut body = String::new();
 
     for child in node.children_with_tokens() {
-        // Check if current child is a \bibitem command
-        let is_bibitem = if let SyntaxElement::Node(n) = &child {
-            if let Some(cmd) = CmdItem::cast(n.clone()) {
-                if let Some(name) = cmd.name_tok() {
-                    name.text() == "\\bibitem"
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        } else {
-            false
-        };
+        if matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd) {
+            continue;
+        }
 
-        if is_bibitem {
-            // Close previous item
-            if in_item {
-                output.push_str("] ");
-                if !current_label.is_empty() {
-                    let _ = write!(output, "<{}>", sanitize_label(&current_label));
-                }
-                output.push('\n');
-            }
-
-            // Start new item
-            if let SyntaxElement::Node(n) = &child {
-                if let Some(cmd) = CmdItem::cast(n.clone()) {
-                    // Get label from arg - use get_required_arg for simple labels
-                    if let Some(arg) = conv.get_required_arg(&cmd, 0) {
-                        current_label = arg;
-                    } else {
-                        current_label = String::new();
-                    }
-
-                    let _ = write!(
-                        output,
-                        "#figure(kind: \"bib\", supplement: none, caption: [{}])[",
-                        bib_counter
-                    );
-                    bib_counter += 1;
-                    in_item = true;
-                }
-            }
-        } else {
-            // If in item, output content
-            if in_item {
-                // Skip begin/end tokens
-                match child.kind() {
-                    SyntaxKind::ItemBegin | SyntaxKind::ItemEnd => continue,
-                    _ => conv.visit_element(child, output),
-                }
+        let bibitem = child
+            .as_node()
+            .cloned()
+            .and_then(CmdItem::cast)
+            .filter(|cmd| {
+                cmd.name_tok()
+                    .is_some_and(|name| name.text() == "\\bibitem")
+            });
+
+        if let Some(cmd) = bibitem {
+            if let Some((label, key)) = current.take() {
+                write_bibitem(output, label.as_deref(), &key, &body);
             }
+            body.clear();
+            let label = conv
+                .get_optional_arg(&cmd, 0)
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty());
+            let key = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            current = Some((label, key.trim().to_string()));
+        } else if current.is_some() {
+            conv.visit_element(child, &mut body);
         }
     }
 
-    // Close last item
-    if in_item {
-        output.push_str("] ");
-        if !current_label.is_empty() {
-            let _ = write!(output, "<{}>", sanitize_label(&current_label));
-        }
-        output.push('\n');
+    if let Some((label, key)) = current {
+        write_bibitem(output, label.as_deref(), &key, &body);
+    }
+}
+
+fn write_bibitem(output: &mut String, label: Option<&str>, key: &str, body: &str) {
+    // `\bibitem[Lam94]{key}` shows (and is cited as) its own label
+    let numbering = match label {
+        Some(label) => format!(
+            "_ => \"{}\"",
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => "\"1\"".to_string(),
+    };
+    let _ = write!(
+        output,
+        "#figure(kind: \"bib\", supplement: none, numbering: {})[{}]",
+        numbering,
+        body.trim()
+    );
+    if !key.is_empty() {
+        let _ = write!(output, " <{}>", sanitize_label(key));
     }
+    output.push('\n');
 }
 
 /// Convert a beamer frame
@@ -988,12 +1799,14 @@ fn convert_frame(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut Stri
 /// Convert a subfigure
 fn convert_subfigure(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
     let width = conv
-        .get_env_optional_arg(node)
+        .get_env_required_arg(node, 0)
         .unwrap_or("0.5\\linewidth".to_string());
     let width_typst = convert_dimension(&width);
 
     let _ = writeln!(output, "#box(width: {})[", width_typst);
+    conv.state.push_env(EnvironmentContext::Minipage);
     conv.visit_env_content(node, output);
+    conv.state.pop_env();
     output.push_str("\n]\n");
 }
 
@@ -1123,22 +1936,44 @@ fn extract_braced_group(chars: &mut std::iter::Peekable<std::str::Chars>) -> Opt
     Some(content)
 }
 
-/// Parse column specification from LaTeX format (e.g., "l|ccc" -> ["l", "c", "c", "c"])
-fn parse_column_spec(spec: &str) -> Vec<String> {
-    let mut columns = Vec::new();
+/// A column of a tabular spec
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnSpec {
+    /// `l`, `c` or `r`; paragraph columns align left
+    align: char,
+    /// Declarations from `>{..}`, applied to each cell of the column
+    before: String,
+    /// Material from `<{..}`, appended to each cell of the column
+    after: String,
+}
+
+impl ColumnSpec {
+    fn new(align: char) -> Self {
+        ColumnSpec {
+            align,
+            before: String::new(),
+            after: String::new(),
+        }
+    }
+}
+
+/// Parse column specification from LaTeX format (e.g., "l|ccc" -> l, c, c, c)
+fn parse_column_spec(spec: &str) -> Vec<ColumnSpec> {
+    let mut columns: Vec<ColumnSpec> = Vec::new();
+    let mut before = String::new();
     let mut chars = spec.chars().peekable();
 
     while let Some(c) = chars.next() {
-        match c {
-            'l' | 'c' | 'r' => columns.push(c.to_string()),
+        let column = match c {
+            'l' | 'c' | 'r' => ColumnSpec::new(c),
             'p' | 'm' | 'b' | 'X' => {
                 skip_braced_group(&mut chars); // Skip width specification
-                columns.push("l".to_string()); // Default to left
+                ColumnSpec::new('l') // Default to left
             }
             '*' => {
                 // Repeat specification *{n}{spec}
                 if let Some(count_str) = extract_braced_group(&mut chars) {
-                    let count: usize = count_str.parse().unwrap_or(1);
+                    let count: usize = count_str.trim().parse().unwrap_or(1);
                     if let Some(spec_str) = extract_braced_group(&mut chars) {
                         let inner_cols = parse_column_spec(&spec_str);
                         for _ in 0..count {
@@ -1146,21 +1981,224 @@ fn parse_column_spec(spec: &str) -> Vec<String> {
                         }
                     }
                 }
+                continue;
             }
-            '|' => {}                                   // Skip vertical separators
-            '@' | '!' => skip_braced_group(&mut chars), // Skip @{} and !{} expressions
-            '>' | '<' => skip_braced_group(&mut chars), // Skip column modifiers
-            _ => {}
-        }
+            '>' => {
+                before.push_str(&extract_braced_group(&mut chars).unwrap_or_default());
+                continue;
+            }
+            '<' => {
+                let after = extract_braced_group(&mut chars).unwrap_or_default();
+                if let Some(last) = columns.last_mut() {
+                    last.after.push_str(&after);
+                }
+                continue;
+            }
+            '@' | '!' => {
+                skip_braced_group(&mut chars); // Skip @{} and !{} expressions
+                continue;
+            }
+            _ => continue, // Vertical separators and spaces
+        };
+        columns.push(ColumnSpec {
+            before: std::mem::take(&mut before),
+            ..column
+        });
     }
 
     if columns.is_empty() {
-        columns.push("l".to_string());
+        columns.push(ColumnSpec::new('l'));
     }
 
     columns
 }
 
+/// Alignment of the column described by the spec of a `\multicolumn`
+pub(crate) fn multicolumn_align(spec: &str) -> Option<CellAlign> {
+    match parse_column_spec(spec).first()?.align {
+        'l' => Some(CellAlign::Left),
+        'c' => Some(CellAlign::Center),
+        'r' => Some(CellAlign::Right),
+        _ => None,
+    }
+}
+
+/// Replace the column types defined with `\newcolumntype` in a tabular spec
+/// by their definitions, filling in their parameters
+fn expand_column_types(spec: &str, types: &HashMap<String, (usize, String)>) -> String {
+    if types.is_empty() {
+        return spec.to_string();
+    }
+    // Definitions may use each other, but not forever
+    let mut expanded = spec.to_string();
+    for _ in 0..16 {
+        let mut result = String::with_capacity(expanded.len());
+        let mut changed = false;
+        let mut chars = expanded.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // Arguments are copied as they are, except what `*` repeats
+                '>' | '<' | '@' | '!' | 'p' | 'm' | 'b' | 'w' | 'W' | '*' => {
+                    result.push(c);
+                    if let Some(group) = extract_braced_group(&mut chars) {
+                        let _ = write!(result, "{{{}}}", group);
+                    }
+                    if matches!(c, 'w' | 'W') {
+                        if let Some(group) = extract_braced_group(&mut chars) {
+                            let _ = write!(result, "{{{}}}", group);
+                        }
+                    }
+                }
+                // The spec repeated by `*`
+                '{' => {
+                    let mut inner = String::new();
+                    let mut depth = 1;
+                    for ch in chars.by_ref() {
+                        match ch {
+                            '{' => depth += 1,
+                            '}' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                        inner.push(ch);
+                    }
+                    let expanded_inner = expand_column_types(&inner, types);
+                    changed |= expanded_inner != inner;
+                    let _ = write!(result, "{{{}}}", expanded_inner);
+                }
+                _ => match types.get(c.encode_utf8(&mut [0; 4]) as &str) {
+                    Some((params, definition)) => {
+                        let mut definition = definition.clone();
+                        for i in 1..=*params {
+                            let arg = extract_braced_group(&mut chars).unwrap_or_default();
+                            definition = definition.replace(&format!("#{}", i), &arg);
+                        }
+                        result.push_str(&definition);
+                        changed = true;
+                    }
+                    None => result.push(c),
+                },
+            }
+        }
+        expanded = result;
+        if !changed {
+            break;
+        }
+    }
+    expanded
+}
+
+/// Turn the `>{..}` and `<{..}` of a column into its alignment and the Typst
+/// placed around each cell. `<{..}` material is converted like cell text;
+/// declarations with no Typst counterpart are skipped with a warning.
+fn column_format(conv: &mut LatexConverter, column: &ColumnSpec) -> (CellAlign, String, String) {
+    let mut align = match column.align {
+        'l' => CellAlign::Left,
+        'r' => CellAlign::Right,
+        'c' => CellAlign::Center,
+        _ => CellAlign::Auto,
+    };
+    let mut wrappers = Vec::new();
+    let mut rest = column.before.trim();
+    while !rest.is_empty() {
+        let Some(declaration) = rest.strip_prefix('\\') else {
+            // Text such as the `$` of a math column
+            let end = rest.find('\\').unwrap_or(rest.len());
+            unknown_column_declaration(conv, rest[..end].trim());
+            rest = &rest[end..];
+            continue;
+        };
+        let name_len = declaration
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(declaration.len());
+        let name = &declaration[..name_len];
+        // Arguments, as of `\hspace{0pt}` or `\color{red}`
+        let mut chars = declaration[name_len..].trim_start().chars().peekable();
+        while chars.peek() == Some(&'{') {
+            skip_braced_group(&mut chars);
+        }
+        let args_len = declaration.len() - name_len - chars.map(char::len_utf8).sum::<usize>();
+        rest = declaration[name_len + args_len..].trim_start();
+        match name {
+            "raggedleft" | "RaggedLeft" => align = CellAlign::Right,
+            "raggedright" | "RaggedRight" => align = CellAlign::Left,
+            "centering" | "Centering" => align = CellAlign::Center,
+            "bfseries" | "bf" => wrappers.push("#strong["),
+            "itshape" | "it" | "em" => wrappers.push("#emph["),
+            "scshape" | "sc" => wrappers.push("#smallcaps["),
+            "arraybackslash" | "hspace" | "normalfont" | "mdseries" | "upshape" => {}
+            _ => unknown_column_declaration(conv, &format!("\\{}", name)),
+        }
+    }
+
+    // The closing `$` of a math column goes with the skipped opening one
+    let material = if column.before.contains('$') {
+        column.after.replace('$', "")
+    } else {
+        column.after.clone()
+    };
+    let mut after = String::new();
+    if !material.trim().is_empty() {
+        let tree = mitex_parser::parse(&material, conv.spec.clone());
+        conv.visit_node(&tree, &mut after);
+    }
+    let before = wrappers.concat();
+    let after = format!("{}{}", after.trim_end(), "]".repeat(wrappers.len()));
+    (align, before, after)
+}
+
+fn unknown_column_declaration(conv: &mut LatexConverter, declaration: &str) {
+    conv.state.add_warning(ConversionWarning::new(
+        WarningKind::UnsupportedMacro,
+        format!(
+            "`>{{{}}}` in a column spec has no Typst counterpart, skipped",
+            declaration
+        ),
+    ));
+}
+
+/// Find `!{\vrule ..}` separators in a column specification: the column
+/// boundary each one sits on and its `width`, if given
+fn parse_vrule_separators(spec: &str) -> Vec<(usize, Option<String>)> {
+    let mut vlines = Vec::new();
+    let mut column = 0;
+    let mut chars = spec.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            'l' | 'c' | 'r' => column += 1,
+            'p' | 'm' | 'b' | 'X' => {
+                skip_braced_group(&mut chars);
+                column += 1;
+            }
+            '*' => {
+                let count = extract_braced_group(&mut chars)
+                    .and_then(|count| count.trim().parse::<usize>().ok())
+                    .unwrap_or(1);
+                let inner = extract_braced_group(&mut chars).unwrap_or_default();
+                column += count * parse_column_spec(&inner).len();
+            }
+            '!' => {
+                let separator = extract_braced_group(&mut chars).unwrap_or_default();
+                if let Some(rest) = separator.trim().strip_prefix("\\vrule") {
+                    let width = rest
+                        .trim()
+                        .strip_prefix("width")
+                        .and_then(|width| width.split_whitespace().next())
+                        .map(convert_dimension);
+                    vlines.push((column, width));
+                }
+            }
+            '@' | '>' | '<' => skip_braced_group(&mut chars),
+            _ => {}
+        }
+    }
+
+    vlines
+}
+
 /// Convert a LaTeX dimension to Typst
 fn convert_dimension(dim: &str) -> String {
     let dim = dim.trim();
@@ -0,0 +1,225 @@
+//! Framed boxes: tcolorbox and mdframed
+//!
+//! `\begin{tcolorbox}[..]` and `\begin{mdframed}[..]` become a full-width
+//! `#block` with the fill, frame and corners their options give, and the
+//! title as a bold first line. Boxes defined with `\newtcolorbox` and
+//! `\newmdenv` reach here as these environments, expanded by the engine.
+
+use mitex_parser::syntax::{SyntaxKind, SyntaxNode};
+use std::fmt::Write;
+
+use super::context::LatexConverter;
+use super::markup::resolve_dimension;
+use super::utils::extract_arg_content_with_braces;
+use super::{ConversionWarning, WarningKind};
+use crate::data::colors::parse_color_expression;
+
+/// Style of a framed box
+struct FramedStyle {
+    fill: Option<String>,
+    frame_color: String,
+    frame_width: String,
+    radius: Option<String>,
+    breakable: bool,
+    title: Option<String>,
+}
+
+impl FramedStyle {
+    /// The look of a box without options
+    fn new(env: &str) -> Self {
+        match env {
+            "tcolorbox" => FramedStyle {
+                fill: Some(parse_color_expression("black!5!white")),
+                frame_color: parse_color_expression("black!75!white"),
+                frame_width: "0.5mm".to_string(),
+                radius: Some("4pt".to_string()),
+                breakable: true,
+                title: None,
+            },
+            _ => FramedStyle {
+                fill: None,
+                frame_color: "black".to_string(),
+                frame_width: "0.4pt".to_string(),
+                radius: None,
+                breakable: true,
+                title: None,
+            },
+        }
+    }
+
+    /// Apply one option, false if it has no Typst counterpart
+    fn apply(&mut self, conv: &mut LatexConverter, key: &str, value: Option<&str>) -> bool {
+        match (key, value) {
+            ("colback" | "backgroundcolor", Some(color)) => {
+                self.fill = Some(parse_color_expression(color));
+            }
+            ("colframe" | "linecolor", Some(color)) => {
+                self.frame_color = parse_color_expression(color);
+            }
+            ("boxrule" | "linewidth", Some(width)) => {
+                self.frame_width = resolve_dimension(conv, width);
+            }
+            ("arc" | "roundcorner", Some(radius)) => {
+                self.radius = Some(resolve_dimension(conv, radius));
+            }
+            ("title" | "frametitle", Some(title)) => {
+                let title = conv.convert_argument_text(title).trim().to_string();
+                self.title = (!title.is_empty()).then_some(title);
+            }
+            ("sharp corners", _) => self.radius = None,
+            ("unbreakable" | "nobreak", _) => self.breakable = false,
+            // Typst blocks break across pages and set their title bold already
+            ("rounded corners" | "breakable" | "enhanced" | "fonttitle", _) => {}
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Convert a `tcolorbox` or `mdframed` environment to a `#block`
+pub(super) fn convert_framed_box(
+    conv: &mut LatexConverter,
+    node: &SyntaxNode,
+    env: &str,
+    output: &mut String,
+) {
+    let defaults = conv
+        .state
+        .framed_defaults
+        .get(env)
+        .cloned()
+        .unwrap_or_default();
+    let options = framed_options(node).unwrap_or_default();
+
+    let mut style = FramedStyle::new(env);
+    for (key, value) in split_options(&defaults)
+        .into_iter()
+        .chain(split_options(&options))
+    {
+        if !style.apply(conv, &key, value.as_deref()) {
+            let dropped = &mut conv.state.framed_options_dropped;
+            if !dropped.contains(&key) {
+                dropped.push(key);
+            }
+        }
+    }
+
+    let mut args = vec!["width: 100%".to_string()];
+    if let Some(fill) = &style.fill {
+        args.push(format!("fill: {}", fill));
+    }
+    args.push(format!(
+        "stroke: {} + {}",
+        style.frame_width, style.frame_color
+    ));
+    args.push("inset: 8pt".to_string());
+    if let Some(radius) = &style.radius {
+        args.push(format!("radius: {}", radius));
+    }
+    if !style.breakable {
+        args.push("breakable: false".to_string());
+    }
+
+    let _ = writeln!(output, "\n#block({})[", args.join(", "));
+    if let Some(title) = &style.title {
+        let _ = writeln!(output, "*{}* \\", title);
+    }
+    let mut body = String::new();
+    conv.visit_env_content(node, &mut body);
+    output.push_str(body.trim_start());
+    output.push_str("\n]\n");
+}
+
+/// Remember the options of `\tcbset` or `\mdfsetup` for the boxes after it
+pub(super) fn set_framed_defaults(conv: &mut LatexConverter, env: &str, options: &str) {
+    let defaults = conv
+        .state
+        .framed_defaults
+        .entry(env.to_string())
+        .or_default();
+    if !defaults.is_empty() {
+        defaults.push(',');
+    }
+    defaults.push_str(options);
+}
+
+/// Report the options of framed boxes left out in a single diagnostic
+pub(super) fn report_dropped_framed_options(conv: &mut LatexConverter) {
+    let dropped = std::mem::take(&mut conv.state.framed_options_dropped);
+    if dropped.is_empty() {
+        return;
+    }
+    conv.state.add_warning(ConversionWarning::new(
+        WarningKind::Approximation,
+        format!(
+            "framed box options without a Typst equivalent were ignored: {}",
+            dropped.join(", ")
+        ),
+    ));
+}
+
+/// The `[..]` options of a box environment, braces kept
+fn framed_options(node: &SyntaxNode) -> Option<String> {
+    node.children()
+        .filter(|child| child.kind() == SyntaxKind::ItemBegin)
+        .flat_map(|begin| begin.children())
+        .find(|clause| {
+            clause.kind() == SyntaxKind::ClauseArgument
+                && clause
+                    .children()
+                    .any(|c| c.kind() == SyntaxKind::ItemBracket)
+        })
+        .map(|clause| extract_arg_content_with_braces(&clause))
+}
+
+/// Split `key=value` options at top-level commas; a key alone has no value
+fn split_options(options: &str) -> Vec<(String, Option<String>)> {
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in options.chars().chain(std::iter::once(',')) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                let entry = std::mem::take(&mut current);
+                let (key, value) = match entry.split_once('=') {
+                    Some((key, value)) => {
+                        let value = value.trim();
+                        let value = value
+                            .strip_prefix('{')
+                            .and_then(|v| v.strip_suffix('}'))
+                            .unwrap_or(value);
+                        (key, Some(value.to_string()))
+                    }
+                    None => (entry.as_str(), None),
+                };
+                let key = key.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !key.is_empty() {
+                    entries.push((key, value));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_options() {
+        assert_eq!(
+            split_options("title={A, B}, colback=blue!5,sharp  corners,"),
+            vec![
+                ("title".to_string(), Some("A, B".to_string())),
+                ("colback".to_string(), Some("blue!5".to_string())),
+                ("sharp corners".to_string(), None),
+            ]
+        );
+    }
+}
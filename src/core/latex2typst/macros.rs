@@ -0,0 +1,600 @@
+//! Macros kept as Typst `#let` definitions
+//!
+//! With [`L2TOptions::preserve_macros_as_let`](super::L2TOptions), a
+//! `\newcommand` whose body is math that converts on its own becomes
+//! `#let E = $bb(E)$` at the top of the output, and its uses call it instead
+//! of repeating the expansion. Its definition is taken out of the source
+//! before the macro engine runs, so the engine leaves the uses alone.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::data::typst_compat::{TYPST_MARKUP_HANDLERS, TYPST_MATH_HANDLERS};
+use crate::data::TYPST_TO_TEX;
+
+/// Names Typst code or math already gives a meaning that are not in the
+/// symbol and function tables
+const TYPST_RESERVED: &[&str] = &[
+    // Keywords
+    "let",
+    "set",
+    "show",
+    "if",
+    "else",
+    "for",
+    "while",
+    "in",
+    "not",
+    "and",
+    "or",
+    "none",
+    "auto",
+    "return",
+    "import",
+    "include",
+    "break",
+    "continue",
+    "context",
+    "true",
+    "false",
+    "as",
+    // Standard library
+    "align",
+    "array",
+    "block",
+    "box",
+    "calc",
+    "circle",
+    "color",
+    "content",
+    "counter",
+    "dict",
+    "document",
+    "ellipse",
+    "emoji",
+    "eval",
+    "figure",
+    "float",
+    "footnote",
+    "grid",
+    "h",
+    "heading",
+    "highlight",
+    "image",
+    "int",
+    "label",
+    "layout",
+    "line",
+    "link",
+    "list",
+    "lorem",
+    "luma",
+    "math",
+    "measure",
+    "metadata",
+    "numbering",
+    "outline",
+    "pad",
+    "page",
+    "par",
+    "path",
+    "place",
+    "polygon",
+    "query",
+    "quote",
+    "raw",
+    "rect",
+    "ref",
+    "repeat",
+    "rgb",
+    "square",
+    "stack",
+    "state",
+    "str",
+    "strong",
+    "sub",
+    "super",
+    "sym",
+    "table",
+    "terms",
+    "text",
+    "type",
+    "v",
+    // Math functions
+    "abs",
+    "accent",
+    "attach",
+    "bb",
+    "bold",
+    "cal",
+    "cancel",
+    "cases",
+    "ceil",
+    "class",
+    "display",
+    "equation",
+    "floor",
+    "frak",
+    "inline",
+    "italic",
+    "limits",
+    "lr",
+    "mat",
+    "mid",
+    "mono",
+    "norm",
+    "op",
+    "primes",
+    "round",
+    "sans",
+    "script",
+    "scripts",
+    "serif",
+    "sscript",
+    "stretch",
+    "upright",
+];
+
+/// Commands after which a body means different things in text and math, or
+/// is not math at all
+const MODE_DEPENDENT: &[&str] = &[
+    "ifmmode",
+    "ensuremath",
+    "text",
+    "mbox",
+    "hbox",
+    "textrm",
+    "textit",
+    "textbf",
+    "textsf",
+    "texttt",
+    "textup",
+    "textnormal",
+    "emph",
+    "xspace",
+    "label",
+    "begin",
+    "def",
+    "newcommand",
+    "renewcommand",
+    "(",
+    "[",
+];
+
+/// A macro kept as a Typst definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LetMacro {
+    /// Name of the LaTeX command, without the backslash
+    pub command: String,
+    /// Name of the Typst binding
+    pub name: String,
+    /// Number of positional arguments
+    pub params: usize,
+    /// Typst math of the body, or its markup when it is text; argument `n`
+    /// is `#a`, `#b`, ..
+    pub body: String,
+    /// Whether the body is text, kept as content instead of math
+    pub text: bool,
+}
+
+impl LetMacro {
+    /// The `#let` definition
+    pub fn definition(&self) -> String {
+        let body = if self.text {
+            format!("[{}]", self.body)
+        } else {
+            format!("${}$", self.body)
+        };
+        if self.params == 0 {
+            format!("#let {} = {}", self.name, body)
+        } else {
+            let params: Vec<String> = (0..self.params).map(param_name).collect();
+            format!("#let {}({}) = {}", self.name, params.join(", "), body)
+        }
+    }
+
+    /// A use of a text macro, with its arguments converted to Typst markup
+    /// as trailing content arguments
+    pub fn content_call(&self, args: &[String]) -> String {
+        let mut call = format!("#{}", self.name);
+        for arg in args {
+            call.push('[');
+            call.push_str(arg);
+            call.push(']');
+        }
+        call
+    }
+
+    /// A use in math, with its arguments converted to Typst math. A single
+    /// letter is read as the letter in math, so it is used from code, ended
+    /// with `;` so that a following `(..)` or `[..]` is not an argument.
+    pub fn call(&self, args: &[String]) -> String {
+        if self.name.chars().count() == 1 {
+            format!("#{}; ", self.name)
+        } else if self.params == 0 {
+            format!("{} ", self.name)
+        } else {
+            let args: Vec<String> = args.iter().map(|arg| escape_separators(arg)).collect();
+            format!("{}({}) ", self.name, args.join(", "))
+        }
+    }
+}
+
+/// Escape the commas and semicolons of `arg` that would otherwise split it
+/// into several arguments of a math call
+fn escape_separators(arg: &str) -> String {
+    let mut escaped = String::with_capacity(arg.len());
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                escaped.extend(chars.next());
+                continue;
+            }
+            '"' => quoted = !quoted,
+            '(' | '[' | '{' if !quoted => depth += 1,
+            ')' | ']' | '}' if !quoted => depth = depth.saturating_sub(1),
+            ',' | ';' if !quoted && depth == 0 => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Name of argument `index` in a definition
+fn param_name(index: usize) -> String {
+    char::from(b'a' + index as u8).to_string()
+}
+
+/// Placeholder for argument `index` while the body is converted: a
+/// private-use character, which math conversion passes through as it is
+pub(crate) fn param_placeholder(index: usize) -> char {
+    char::from_u32(0xE020 + index as u32).unwrap_or('\u{E020}')
+}
+
+/// Put the arguments back into a converted body as `#a`, `#b`, .., keeping
+/// what follows from being read as part of the name
+pub(crate) fn restore_params(body: &str, params: usize) -> String {
+    let mut restored = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (0..params).find(|&index| param_placeholder(index) == c) {
+            Some(index) => {
+                restored.push('#');
+                restored.push_str(&param_name(index));
+                if chars.peek().is_some_and(|&next| {
+                    next.is_alphanumeric() || matches!(next, '_' | '-' | '.' | '(' | '[')
+                }) {
+                    restored.push(' ');
+                }
+            }
+            None => restored.push(c),
+        }
+    }
+    restored
+}
+
+/// A `\newcommand`, `\renewcommand` or `\providecommand` in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MacroDefinition {
+    /// Byte range of the whole definition
+    pub range: Range<usize>,
+    /// Name without the backslash
+    pub name: String,
+    /// Number of arguments
+    pub params: usize,
+    /// Whether the first argument is optional, with a default
+    pub has_default: bool,
+    /// Replacement text
+    pub body: String,
+}
+
+impl MacroDefinition {
+    /// Whether the body is plain text: words outside any group, and nothing
+    /// but words, digits, spacing, punctuation and arguments
+    pub fn is_text(&self) -> bool {
+        let mut chars = self.body.chars().peekable();
+        while let Some(c) = chars.next() {
+            let allowed = c.is_alphanumeric()
+                || c.is_whitespace()
+                || ".,;:!?'-()/~".contains(c)
+                || (c == '#' && chars.next_if(char::is_ascii_digit).is_some());
+            if !allowed {
+                return false;
+            }
+        }
+        has_words(&self.body)
+    }
+
+    /// Whether the body mixes words with math, so that it is neither text
+    /// nor math on its own
+    pub fn is_mixed(&self) -> bool {
+        !self.is_text() && has_words(&self.body)
+    }
+
+    /// Whether the body can stand alone as Typst math: no text, no mode
+    /// tests, no definitions, and no other macro of the document except
+    /// those already kept as definitions
+    pub fn is_clean_math(&self, defined: &HashSet<String>, kept: &HashSet<String>) -> bool {
+        if self.has_default || self.body.trim().is_empty() {
+            return false;
+        }
+        if self.body.contains(['$', '&', '%']) || self.body.contains("##") {
+            return false;
+        }
+        command_names(&self.body).all(|name| {
+            !MODE_DEPENDENT.contains(&name)
+                && !name.starts_with("if")
+                && !name.starts_with("text")
+                && (!defined.contains(name) || kept.contains(name))
+        })
+    }
+}
+
+/// Whether `body` has a word of two letters or more outside any group and
+/// any command name
+fn has_words(body: &str) -> bool {
+    let mut depth = 0usize;
+    let mut letters = 0;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if chars.next_if(char::is_ascii_alphabetic).is_some() {
+                    while chars.next_if(char::is_ascii_alphabetic).is_some() {}
+                } else {
+                    chars.next();
+                }
+            }
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            c if c.is_alphabetic() && depth == 0 => {
+                letters += 1;
+                if letters == 2 {
+                    return true;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        letters = 0;
+    }
+    false
+}
+
+/// Names of the control sequences in `text`
+fn command_names(text: &str) -> impl Iterator<Item = &str> {
+    text.match_indices('\\').filter_map(move |(pos, _)| {
+        let after = &text[pos + 1..];
+        let len = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        match len {
+            0 => after.get(..after.chars().next()?.len_utf8()),
+            len => Some(&after[..len]),
+        }
+    })
+}
+
+/// The `\newcommand` family of definitions in `source`, in order. Those
+/// with a name that is not all letters are skipped.
+pub(crate) fn find_definitions(source: &str) -> Vec<MacroDefinition> {
+    const COMMANDS: &[&str] = &["\\newcommand", "\\renewcommand", "\\providecommand"];
+    let mut definitions = Vec::new();
+    for (start, _) in source.match_indices('\\') {
+        let Some(command) = COMMANDS
+            .iter()
+            .find(|command| source[start..].starts_with(**command))
+        else {
+            continue;
+        };
+        // Not commented out
+        let line_start = source[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        if source[line_start..start].contains('%') {
+            continue;
+        }
+        if let Some(definition) = parse_definition(source, start, start + command.len()) {
+            definitions.push(definition);
+        }
+    }
+    definitions
+}
+
+/// `*{\name}[n][default]{body}` or `\name[n]{body}` from `pos` on
+fn parse_definition(source: &str, start: usize, pos: usize) -> Option<MacroDefinition> {
+    let mut rest = &source[pos..];
+    rest = rest.strip_prefix('*').unwrap_or(rest).trim_start();
+    let name = match rest.strip_prefix('{') {
+        Some(braced) => {
+            let (name, after) = braced.split_once('}')?;
+            rest = after;
+            name.trim()
+        }
+        None => {
+            let len = 1 + rest[1..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len() - 1);
+            let name = rest.get(..len)?;
+            rest = &rest[len..];
+            name
+        }
+    };
+    let name = name.strip_prefix('\\')?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut params = 0;
+    let mut has_default = false;
+    rest = rest.trim_start();
+    if let Some(count) = rest.strip_prefix('[') {
+        let (count, after) = count.split_once(']')?;
+        params = count.trim().parse().ok()?;
+        rest = after.trim_start();
+        if rest.starts_with('[') {
+            has_default = true;
+            rest = &rest[balanced_end(rest, '[', ']')?..];
+            rest = rest.trim_start();
+        }
+    }
+    if !rest.starts_with('{') {
+        return None;
+    }
+    let end = balanced_end(rest, '{', '}')?;
+    let body = rest[1..end - 1].to_string();
+    let range_end = source.len() - rest.len() + end;
+    Some(MacroDefinition {
+        range: start..range_end,
+        name: name.to_string(),
+        params,
+        has_default,
+        body,
+    })
+}
+
+/// Byte offset just past the group `text` opens with `open`
+fn balanced_end(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + c.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether Typst already has a meaning for `name`
+pub(crate) fn is_typst_builtin(name: &str) -> bool {
+    TYPST_RESERVED.contains(&name)
+        || TYPST_MATH_HANDLERS.contains_key(name)
+        || TYPST_MARKUP_HANDLERS.contains_key(name)
+        || TYPST_TO_TEX.keys().any(|symbol| {
+            symbol
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_definitions() {
+        let source = "\\newcommand{\\E}{\\mathbb{E}}\n% \\newcommand{\\X}{x}\n\
+                      \\renewcommand*\\norm[1]{\\lVert #1 \\rVert}\n\
+                      \\newcommand{\\opt}[2][1]{#1^{#2}}\n\\newcommand{\\@x}{y}";
+        let definitions = find_definitions(source);
+        let summary: Vec<_> = definitions
+            .iter()
+            .map(|d| (d.name.as_str(), d.params, d.has_default, d.body.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("E", 0, false, "\\mathbb{E}"),
+                ("norm", 1, false, "\\lVert #1 \\rVert"),
+                ("opt", 2, true, "#1^{#2}"),
+            ]
+        );
+        assert_eq!(
+            &source[definitions[0].range.clone()],
+            "\\newcommand{\\E}{\\mathbb{E}}"
+        );
+    }
+
+    #[test]
+    fn test_clean_math_bodies() {
+        let defined: HashSet<String> = ["E", "R", "cond"].map(String::from).into();
+        let kept: HashSet<String> = ["E"].map(String::from).into();
+        let clean = |body: &str| {
+            MacroDefinition {
+                range: 0..0,
+                name: "m".to_string(),
+                params: 1,
+                has_default: false,
+                body: body.to_string(),
+            }
+            .is_clean_math(&defined, &kept)
+        };
+        assert!(clean("\\mathbb{E}"));
+        assert!(clean("\\E\\left[#1\\right]"));
+        assert!(!clean("\\R^{#1}"));
+        assert!(!clean("\\ifmmode x\\else y\\fi"));
+        assert!(!clean("\\ensuremath{x}"));
+        assert!(!clean("\\textbf{#1}"));
+        assert!(!clean("$x$"));
+    }
+
+    #[test]
+    fn test_text_and_mixed_bodies() {
+        let definition = |body: &str| MacroDefinition {
+            range: 0..0,
+            name: "m".to_string(),
+            params: 1,
+            has_default: false,
+            body: body.to_string(),
+        };
+        assert!(definition("TyLaX").is_text());
+        assert!(definition("Hello, #1!").is_text());
+        assert!(!definition("x").is_text());
+        assert!(!definition("\\operatorname{Var}").is_text());
+        assert!(!definition("\\operatorname{Var}").is_mixed());
+        assert!(definition("see \\alpha").is_mixed());
+        assert!(definition("\\,dx").is_mixed());
+        assert!(!definition("\\mathbb{R}^#1").is_mixed());
+    }
+
+    #[test]
+    fn test_call_escapes_separators() {
+        let norm = LetMacro {
+            command: "norm".to_string(),
+            name: "normx".to_string(),
+            params: 1,
+            body: "lr(|| #a ||)".to_string(),
+            text: false,
+        };
+        assert_eq!(
+            norm.call(&["x, f(a, b)".to_string()]),
+            "normx(x\\, f(a, b)) "
+        );
+        assert_eq!(norm.definition(), "#let normx(a) = $lr(|| #a ||)$");
+
+        let greet = LetMacro {
+            command: "greet".to_string(),
+            name: "greet".to_string(),
+            params: 1,
+            body: "Hello, #a!".to_string(),
+            text: true,
+        };
+        assert_eq!(greet.definition(), "#let greet(a) = [Hello, #a!]");
+        assert_eq!(greet.content_call(&["_you_".to_string()]), "#greet[_you_]");
+    }
+
+    #[test]
+    fn test_restore_params_and_builtins() {
+        assert_eq!(
+            restore_params("lr(|| \u{E020} ||) + \u{E021}_1", 2),
+            "lr(|| #a ||) + #b _1"
+        );
+        assert!(is_typst_builtin("norm"));
+        assert!(is_typst_builtin("arrow"));
+        assert!(is_typst_builtin("RR"));
+        assert!(!is_typst_builtin("E"));
+        assert!(!is_typst_builtin("Var"));
+    }
+}
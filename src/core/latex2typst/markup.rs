@@ -2,31 +2,58 @@
 //!
 //! This module handles LaTeX commands like \section, \textbf, \cite, etc.
 
-use mitex_parser::syntax::{CmdItem, SyntaxElement};
+use mitex_parser::syntax::{CmdItem, EnvItem, SyntaxElement, SyntaxKind, SyntaxNode};
 use rowan::ast::AstNode;
+use std::collections::HashSet;
 use std::fmt::Write;
 
-use crate::data::colors::parse_color_expression;
-use crate::data::constants::{CodeBlockOptions, LANGUAGE_MAP};
+use crate::data::accents::{compose_accent, is_text_accent, math_accent};
+use crate::data::colors::{parse_color_expression, parse_model_color};
+use crate::data::constants::{
+    font_size_pt, CodeBlockOptions, DOUBLE_SPACING_STRETCH, LANGUAGE_MAP, ONEHALF_SPACING_STRETCH,
+};
+use crate::data::dates::DateLanguage;
 use crate::data::extended_symbols::EXTENDED_SYMBOLS;
 use crate::data::maps::TEX_COMMAND_SPEC;
 use crate::data::shorthands::apply_shorthand;
+use crate::data::siunitx::{
+    angle_to_text, number_to_typst_math, split_key_values, RangeUnits, SiOptions,
+};
 use crate::data::symbols::{
-    BIBLATEX_COMMANDS, CHAR_COMMANDS, GREEK_LETTERS, LETTER_COMMANDS, MISC_SYMBOLS, NAME_COMMANDS,
-    TEXT_FORMAT_COMMANDS,
+    negate_relation, BIBLATEX_COMMANDS, CHAR_COMMANDS, GREEK_LETTERS, LETTER_COMMANDS,
+    MISC_SYMBOLS, NAME_COMMANDS, TEXT_FORMAT_COMMANDS,
 };
 use mitex_spec::CommandSpecItem;
 
 use super::context::{
-    ConversionMode, EnvironmentContext, LatexConverter, MacroDef, PendingCitation, PendingOperator,
-    PendingReference,
+    ConversionMode, DataTableMode, EnvironmentContext, LatexConverter, MacroDef, MetadataCommand,
+    PendingCitation, PendingOperator, PendingReference, TodoStyle,
+};
+use super::environment::multicolumn_align;
+use super::framed::set_framed_defaults;
+use super::macros::LetMacro;
+use super::math::{convert_sized_delimiter, delimits_sized_matrix};
+use super::table::{RowColors, CELL_FILL_MARKER, ROW_FILL_MARKER};
+use super::typography::{
+    apply_typography_action, convert_typography_command, typography_command, typography_package,
+};
+use super::utils::{
+    contains_top_level_separator, convert_caption_text, extract_arg_content, extract_thanks,
+    line_spacing_rule, monospace_text, optional_args_to_prefix_suffix, raw_inline,
+    restore_verbatim_chars, sanitize_label, source_document_class, split_inline_math,
+    standalone_tikz_picture, to_roman_numeral, APPENDIX_RULES, LEFT_MARK, PAGE_NUMBER, RIGHT_MARK,
+    TODAY_PLACEHOLDER,
 };
-use super::utils::{contains_top_level_separator, sanitize_label, to_roman_numeral};
-use crate::features::images::ImageAttributes;
+use super::{latex_math_to_typst, ConversionWarning, WarningKind};
+use crate::features::bibtex::{parse_bibtex, BibEntry};
+use crate::features::data_tables::{parse_delimited, DataTableOptions};
+use crate::features::images::{image_extension, parse_graphics_path, ImageAttributes};
 use crate::features::refs::{
-    citation_mode_from_latex_command, citation_to_typst, label_to_typst, reference_to_typst,
-    reference_type_from_latex_command, Citation, CitationMode, CiteGroup, Reference, ReferenceType,
+    citation_mode_from_latex_command, citation_to_typst, cleveref_to_typst, label_to_typst,
+    reference_to_typst, reference_type_from_latex_command, Citation, CitationMode, CiteGroup,
+    Label, LabelType, Reference, ReferenceType,
 };
+use crate::utils::CompanionFile;
 
 fn has_split_optional_citation_start(cmd: &CmdItem) -> bool {
     cmd.syntax().children().any(|child| {
@@ -38,27 +65,6 @@ fn has_split_optional_citation_start(cmd: &CmdItem) -> bool {
     })
 }
 
-fn optional_args_to_prefix_suffix(optional_args: &[String]) -> (Option<String>, Option<String>) {
-    let cleaned: Vec<String> = optional_args
-        .iter()
-        .map(|value| {
-            value
-                .trim()
-                .trim_start_matches('[')
-                .trim_end_matches(']')
-                .trim()
-                .to_string()
-        })
-        .filter(|value| !value.is_empty())
-        .collect();
-
-    match cleaned.as_slice() {
-        [] => (None, None),
-        [only] => (None, Some(only.clone())),
-        [prefix, suffix, ..] => (Some(prefix.clone()), Some(suffix.clone())),
-    }
-}
-
 fn normalize_operator_name_text(text: &str) -> Option<String> {
     let trimmed = text
         .trim()
@@ -158,8 +164,19 @@ fn emit_citation_group(
     mode: CitationMode,
     prefix: Option<String>,
     suffix: Option<String>,
+    manual_keys: &HashSet<String>,
     output: &mut String,
 ) {
+    let key_list: Vec<&str> = keys
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .collect();
+    if !key_list.is_empty() && key_list.iter().all(|key| manual_keys.contains(*key)) {
+        output.push_str(&manual_citation_to_typst(&key_list, prefix, suffix));
+        return;
+    }
+
     let mut group = CiteGroup::new();
     group.prefix = prefix.filter(|value| !value.trim().is_empty());
     group.suffix = suffix.filter(|value| !value.trim().is_empty());
@@ -174,14 +191,43 @@ fn emit_citation_group(
     }
 }
 
+/// Cite entries of a `thebibliography` list as `[1, 2, p. 5]`.
+///
+/// The entries are numbered figures (see `convert_bibliography`), so each key
+/// is a plain reference; the brackets and notes are added around them as
+/// LaTeX's numeric citations do.
+fn manual_citation_to_typst(
+    keys: &[&str],
+    prefix: Option<String>,
+    suffix: Option<String>,
+) -> String {
+    let refs: Vec<String> = keys
+        .iter()
+        .map(|key| format!("#ref(<{}>)", sanitize_label(key)))
+        .collect();
+    let mut result = String::from("\\[");
+    if let Some(prefix) = prefix.filter(|value| !value.trim().is_empty()) {
+        result.push_str(prefix.trim());
+        result.push(' ');
+    }
+    result.push_str(&refs.join(", "));
+    if let Some(suffix) = suffix.filter(|value| !value.trim().is_empty()) {
+        result.push_str(", ");
+        result.push_str(suffix.trim());
+    }
+    result.push_str("\\]");
+    result
+}
+
 pub fn emit_pending_citation_from_curly(
     node: &mitex_parser::syntax::SyntaxNode,
     pending: PendingCitation,
+    manual_keys: &HashSet<String>,
     output: &mut String,
 ) {
     let keys = crate::core::latex2typst::utils::extract_curly_inner_content(node);
     let (prefix, suffix) = optional_args_to_prefix_suffix(&pending.optional_args);
-    emit_citation_group(&keys, pending.mode, prefix, suffix, output);
+    emit_citation_group(&keys, pending.mode, prefix, suffix, manual_keys, output);
 }
 
 pub fn emit_pending_reference_from_curly(
@@ -235,11 +281,7 @@ pub fn convert_command_sym(conv: &mut LatexConverter, elem: SyntaxElement, outpu
                 return;
             }
             "_" => {
-                if matches!(conv.state.mode, ConversionMode::Math) {
-                    output.push('_');
-                } else {
-                    output.push_str("\\_"); // _ causes emphasis in text
-                }
+                output.push_str("\\_"); // _ causes emphasis in text, a subscript in math
                 return;
             }
             "*" => {
@@ -260,7 +302,11 @@ pub fn convert_command_sym(conv: &mut LatexConverter, elem: SyntaxElement, outpu
                 return;
             }
             "&" => {
-                output.push('&');
+                if matches!(conv.state.mode, ConversionMode::Math) {
+                    output.push_str("\\&"); // & is an alignment point in math
+                } else {
+                    output.push('&');
+                }
                 return;
             }
             "{" => {
@@ -365,6 +411,13 @@ fn protect_comma(content: &str) -> String {
     }
 }
 
+/// The quoted `#link(..)` target of a verbatim `\url`/`\href` argument,
+/// percent-encoded characters and all
+fn link_target(url: &str) -> String {
+    let url = restore_verbatim_chars(url.trim());
+    format!("\"{}\"", url.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 /// Convert a LaTeX command
 pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &mut String) {
     let node = match &elem {
@@ -388,6 +441,20 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
     // Remove leading backslash for matching
     let base_name = cmd_str.trim_start_matches('\\');
 
+    // Semantic commands keep their content for Typst queries
+    if let Some(command) = conv.state.options.metadata_command(base_name).cloned() {
+        let raw = conv
+            .get_required_arg_with_braces(&cmd, 0)
+            .unwrap_or_default();
+        output.push_str(&metadata_marker(&command, &raw));
+    }
+
+    // Global typography toggles, in the preamble or the body
+    if let Some(command) = typography_command(base_name) {
+        convert_typography_command(conv, &cmd, command, output);
+        return;
+    }
+
     // Handle preamble commands
     if conv.state.in_preamble {
         match base_name {
@@ -398,17 +465,57 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 return;
             }
             "title" => {
-                conv.state.title = conv.extract_metadata_arg(&cmd);
+                let raw = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+                let (title, notes) = extract_thanks(&raw);
+                let title = convert_caption_text(&title).trim().to_string();
+                conv.state.title = (!title.is_empty()).then_some(title);
+                conv.state.title_notes = notes.iter().map(|n| thanks_footnote(n)).collect();
                 return;
             }
             "author" => {
-                conv.state.author = conv.extract_metadata_arg(&cmd);
+                let raw = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+                let mut authors = Vec::new();
+                let mut visible = Vec::new();
+                for part in raw.split("\\and") {
+                    let (name, notes) = extract_thanks(part);
+                    let name = convert_caption_text(&name).trim().to_string();
+                    if name.is_empty() {
+                        continue;
+                    }
+                    let notes: String = notes.iter().map(|n| thanks_footnote(n)).collect();
+                    visible.push(format!("{}{}", name, notes));
+                    authors.push(name);
+                }
+                conv.state.author = (!visible.is_empty()).then(|| visible.join(", "));
+                conv.state.authors = authors;
+                return;
+            }
+            "hypersetup" => {
+                handle_hypersetup(conv, &cmd);
                 return;
             }
             "date" => {
                 conv.state.date = conv.extract_metadata_arg(&cmd);
                 return;
             }
+            "usepackage" | "RequirePackage"
+                if conv
+                    .get_required_arg(&cmd, 0)
+                    .is_some_and(|pkgs| pkgs.split(',').any(|p| p.trim() == "babel")) =>
+            {
+                let options = conv.get_optional_arg(&cmd, 0).unwrap_or_default();
+                if let Some(language) = babel_main_language(&options) {
+                    conv.state.language = Some(language);
+                }
+                return;
+            }
+            "setmainlanguage" | "setdefaultlanguage" => {
+                let name = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                if let Some(language) = DateLanguage::from_babel(&name) {
+                    conv.state.language = Some(language);
+                }
+                return;
+            }
             "newcommand" | "renewcommand" | "providecommand" => {
                 handle_newcommand(conv, &cmd);
                 return;
@@ -425,25 +532,52 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 handle_newglossaryentry(conv, &cmd);
                 return;
             }
+            "newlength" | "setlength" | "addtolength" | "settowidth" => {
+                handle_length_command(conv, &cmd, base_name);
+                return;
+            }
+            "linespread" | "setstretch" | "onehalfspacing" | "doublespacing"
+            | "singlespacing" => {
+                conv.state.line_stretch =
+                    line_stretch_for_command(conv, &cmd, base_name).filter(|s| *s != 1.0);
+                return;
+            }
+            "DeclareSIUnit" => {
+                handle_declare_si_unit(conv, &cmd);
+                return;
+            }
+            "sisetup" => {
+                let opts = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+                conv.state.si_options.apply(&opts);
+                return;
+            }
+            "usepackage" | "RequirePackage" => {
+                let packages = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                for package in packages.split(',').map(str::trim) {
+                    if let Some(action) = typography_package(package) {
+                        apply_typography_action(conv, package, action, &cmd, output);
+                    }
+                }
+                return;
+            }
             // Preamble/setup commands to ignore
-            "usepackage" | "RequirePackage" | "input" | "include" | "includeonly"
-            | "bibliography" | "bibliographystyle" | "maketitle" | "pagestyle" 
-            | "thispagestyle" | "pagenumbering" | "setcounter" | "addtocounter" 
-            | "setlength" | "addtolength" | "newtheorem" | "theoremstyle" 
+            "input" | "include" | "includeonly"
+            | "bibliography" | "bibliographystyle" | "maketitle" | "pagenumbering"
+            | "setcounter" | "addtocounter" 
+            | "newtheorem" | "theoremstyle" 
             | "allowdisplaybreaks" | "numberwithin" | "DeclareMathOperator"
-            | "DeclarePairedDelimiter" | "sisetup" | "NewDocumentCommand"
+            | "DeclarePairedDelimiter" | "NewDocumentCommand"
             | "RenewDocumentCommand" | "ProvideDocumentCommand" | "DeclareDocumentCommand"
             // Layout and spacing
-            | "geometry" | "onehalfspacing" | "doublespacing" | "singlespacing"
-            | "linespread" | "baselinestretch" | "parindent" | "parskip"
+            | "geometry" | "baselinestretch" | "parindent" | "parskip"
             // AtBegin/AtEnd hooks
             | "makeatletter" | "makeatother" | "AtBeginDocument" | "AtEndDocument"
             // Environment definitions
             | "newenvironment" | "renewenvironment"
             // Hyperref and colors
-            | "hypersetup" | "definecolor" | "colorlet"
+            | "definecolor" | "colorlet"
             // Graphics
-            | "graphicspath" | "DeclareGraphicsExtensions"
+            | "DeclareGraphicsExtensions"
             // Captions and floats
             | "captionsetup" | "floatsetup"
             // Lists
@@ -469,6 +603,8 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             // Listings and minted setup
             | "lstset" | "lstdefinestyle" | "lstdefinelanguage"
             | "usemintedstyle" | "setminted"
+            // Framed box libraries
+            | "tcbuselibrary"
             // Additional formatting commands
             | "protect" | "unexpanded" | "expandafter" | "csname" | "endcsname"
             | "let" | "gdef" | "edef" | "xdef" | "futurelet"
@@ -480,6 +616,18 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
     }
 
+    // Macros kept as `#let` definitions
+    if let Some(let_macro) = conv
+        .state
+        .let_macros
+        .iter()
+        .find(|m| m.command == base_name)
+        .cloned()
+    {
+        convert_let_macro_call(conv, &cmd, &let_macro, output);
+        return;
+    }
+
     // Check for user-defined macros
     if let Some(macro_def) = conv.state.macros.get(base_name).cloned() {
         let expanded = expand_user_macro(conv, &cmd, &macro_def);
@@ -489,6 +637,19 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
 
     // Handle document commands
     match base_name {
+        // Size switches scope to the rest of the enclosing group
+        "tiny" | "scriptsize" | "footnotesize" | "small" | "normalsize" | "large" | "Large"
+        | "LARGE" | "huge" | "Huge" => {
+            if !matches!(conv.state.mode, ConversionMode::Math) {
+                if let Some(pt) = font_size_pt(base_name) {
+                    conv.open_size_scope(pt, output);
+                }
+            }
+        }
+        // Title block, emitted where the source asks for it
+        "maketitle" => {
+            output.push_str(&conv.title_block());
+        }
         // Section commands - Part gets special formatting with Roman numerals
         "part" => {
             let title = conv
@@ -506,50 +667,41 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             output.push_str("]\n");
             output.push_str("#v(2em)\n\n");
         }
-        "chapter" => {
-            let title = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push('\n');
-            output.push_str("= ");
-            output.push_str(&title);
-            output.push('\n');
-        }
         // Sectioning - adjust level based on documentclass
-        "section" => {
-            // article: section = level 1 (=), report/book: section = level 2 (==)
-            let base_level = if conv.state.document_class.as_deref() == Some("article") {
-                0
-            } else {
-                1
-            };
-            convert_section(conv, &cmd, base_level, output);
-        }
-        "subsection" => {
-            let base_level = if conv.state.document_class.as_deref() == Some("article") {
-                1
-            } else {
-                2
-            };
-            convert_section(conv, &cmd, base_level, output);
-        }
-        "subsubsection" => {
-            let base_level = if conv.state.document_class.as_deref() == Some("article") {
-                2
-            } else {
-                3
-            };
-            convert_section(conv, &cmd, base_level, output);
+        "chapter" | "section" | "subsection" | "subsubsection" | "paragraph"
+        | "subparagraph" => {
+            let level = section_base_level(base_name, conv.state.document_class.as_deref())
+                .unwrap_or_default();
+            convert_section(conv, &cmd, level, None, output);
+        }
+        // Unnumbered, and only in the outline with an \addcontentsline next to it
+        "chapter*" | "section*" | "subsection*" | "subsubsection*" | "paragraph*"
+        | "subparagraph*" => {
+            let name = base_name.trim_end_matches('*');
+            let level = section_base_level(name, conv.state.document_class.as_deref())
+                .unwrap_or_default();
+            let outlined = adjacent_command(cmd.syntax(), is_contents_line).is_some();
+            convert_section(conv, &cmd, level, Some(outlined), output);
+        }
+        "addcontentsline" => convert_contents_line(conv, &cmd, output),
+        "phantomsection" => {
+            // An anchor for the \label that follows
+            if adjacent_command(cmd.syntax(), |next| command_is(next, "\\label"))
+                .is_some_and(|label| label.syntax().text_range().start() > cmd.syntax().text_range().start())
+            {
+                output.push_str("#metadata(none)");
+            }
         }
-        "paragraph" => {
-            let base_level = if conv.state.document_class.as_deref() == Some("article") {
-                3
-            } else {
-                4
-            };
-            convert_section(conv, &cmd, base_level, output);
+        "pdfbookmark" => convert_pdf_bookmark(conv, &cmd, output),
+        "hypertarget" => {
+            let name = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let text = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
+            let _ = write!(output, "#metadata(none) <{}>{}", sanitize_label(&name), text);
         }
-        "subparagraph" => {
-            let title = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "\n_{}_\n", title);
+        "hyperlink" => {
+            let name = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let text = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
+            let _ = write!(output, "#link(<{}>)[{}]", sanitize_label(&name), text);
         }
 
         // Text formatting
@@ -558,16 +710,45 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let _ = write!(output, "*{}*", content);
         }
         "textit" | "it" | "emph" => {
+            // `\emph` toggles italics, like Typst's emphasis; `\textit`
+            // only ever turns them on
+            let enclosing = conv.state.italic.last().copied();
+            let italic = base_name != "emph" || !enclosing.unwrap_or(false);
+            conv.state.italic.push(italic);
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "_{}_", content);
+            conv.state.italic.pop();
+            match enclosing {
+                None => {
+                    let _ = write!(output, "_{}_", content);
+                }
+                Some(enclosing) if enclosing == italic => output.push_str(&content),
+                // Underscores cannot nest, but `emph` can
+                Some(_) => {
+                    let _ = write!(output, "#emph[{}]", content);
+                }
+            }
         }
         "texttt" | "tt" => {
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "`{}`", content);
+            let content = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+            for (part, math) in split_inline_math(&content) {
+                if math {
+                    let _ = write!(output, "${}$", latex_math_to_typst(part));
+                } else {
+                    output.push_str(&raw_inline(&monospace_text(part)));
+                }
+            }
+        }
+        "underline" | "uline" | "uuline" | "uwave" | "ul" => {
+            let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+            if matches!(conv.state.mode, ConversionMode::Math) {
+                let _ = write!(output, "underline({}) ", content);
+            } else {
+                let _ = write!(output, "#underline[{}]", content);
+            }
         }
-        "underline" => {
+        "sout" | "xout" | "st" => {
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "#underline[{}]", content);
+            let _ = write!(output, "#strike[{}]", content);
         }
         "textsc" | "sc" => {
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
@@ -583,8 +764,13 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         // Text in math - these commands output text in math mode
         "text" | "textrm" | "textup" | "textnormal" => {
-            if let Some(arg) = conv.get_required_arg(&cmd, 0) {
-                let _ = write!(output, "\"{}\" ", arg);
+            if matches!(conv.state.mode, ConversionMode::Math) {
+                if let Some(arg) = conv.get_required_arg(&cmd, 0) {
+                    output.push_str(&math_text(&arg));
+                }
+            } else {
+                let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+                output.push_str(&content);
             }
         }
 
@@ -597,9 +783,12 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             }
             let label = conv.get_required_arg(&cmd, 0).unwrap_or_default();
             let clean_label = sanitize_label(&label);
-            output.push_str(&label_to_typst(&clean_label));
+            match conv.state.heading_labels.as_mut() {
+                Some(labels) => labels.push(clean_label),
+                None => output.push_str(&label_to_typst(&clean_label)),
+            }
         }
-        "ref" | "autoref" | "cref" | "Cref" | "eqref" | "pageref" | "nameref" => {
+        "ref" | "eqref" | "pageref" => {
             let ref_type = reference_type_from_latex_command(base_name).unwrap_or(ReferenceType::Basic);
             if let Some(label) = conv.get_required_arg(&cmd, 0) {
                 let clean_label = sanitize_label(&label);
@@ -612,6 +801,41 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 conv.state.pending_reference = Some(PendingReference { ref_type });
             }
         }
+        // hyperref: the kind or the title of the labeled element
+        "autoref" | "nameref" => {
+            if let Some(key) = conv.get_required_arg(&cmd, 0) {
+                let reference = labeled_reference(conv, base_name, key.trim());
+                output.push_str(&reference);
+            } else {
+                conv.state.pending_reference = Some(PendingReference {
+                    ref_type: ReferenceType::Named,
+                });
+            }
+        }
+
+        // cleveref: comma-separated keys, supplements from label prefixes
+        "cref" | "Cref" | "labelcref" | "namecref" | "nameCref" => {
+            if let Some(arg) = conv.get_required_arg(&cmd, 0) {
+                let keys: Vec<String> = arg
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(sanitize_label)
+                    .collect();
+                output.push_str(&cleveref_to_typst(base_name, &keys));
+            } else {
+                conv.state.pending_reference = Some(PendingReference {
+                    ref_type: ReferenceType::Named,
+                });
+            }
+        }
+        "crefrange" | "Crefrange" => {
+            let keys: Vec<String> = (0..2)
+                .filter_map(|i| conv.get_required_arg(&cmd, i))
+                .map(|key| sanitize_label(key.trim()))
+                .collect();
+            output.push_str(&cleveref_to_typst(base_name, &keys));
+        }
 
         // Citations - routed through shared citation semantics
         "cite" | "Cite" | "citep" | "citep*" | "citet" | "citet*" | "citeal"
@@ -629,7 +853,14 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 .collect::<Vec<_>>();
             if let Some(keys) = conv.get_required_arg(&cmd, 0) {
                 let (prefix, suffix) = optional_args_to_prefix_suffix(&optional_args);
-                emit_citation_group(&keys, mode, prefix, suffix, output);
+                emit_citation_group(
+                    &keys,
+                    mode,
+                    prefix,
+                    suffix,
+                    &conv.state.manual_bib_keys,
+                    output,
+                );
             } else {
                 conv.state.pending_citation = Some(PendingCitation {
                     mode,
@@ -643,13 +874,20 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         // URLs and hyperlinks
         "url" => {
             if let Some(url) = conv.get_required_arg(&cmd, 0) {
-                let _ = write!(output, "#link(\"{}\")", url);
+                let _ = write!(output, "#link({})", link_target(&url));
             }
         }
         "href" => {
             let url = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let text = conv.get_required_arg(&cmd, 1).unwrap_or_else(|| url.clone());
-            let _ = write!(output, "#link(\"{}\")[{}]", url, text);
+            let text = conv
+                .convert_required_arg(&cmd, 1)
+                .unwrap_or_else(|| restore_verbatim_chars(&url));
+            let _ = write!(output, "#link({})[{}]", link_target(&url), text);
+        }
+        "path" => {
+            if let Some(path) = conv.get_required_arg(&cmd, 0) {
+                let _ = write!(output, "`{}`", restore_verbatim_chars(&path));
+            }
         }
         "hyperref" => {
             let previous_mode = conv.state.mode;
@@ -678,10 +916,52 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             output.push_str("#super[]");
         }
 
+        // todonotes and margin notes
+        "todo" => convert_todo(conv, &cmd, output),
+        "marginnote" | "marginpar" => {
+            let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+            conv.state.uses_drafting_package = true;
+            let _ = write!(output, "#margin-note(stroke: none)[{}]", content.trim());
+        }
+        "missingfigure" => {
+            let options = conv.get_optional_arg(&cmd, 0).unwrap_or_default();
+            let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+            let mut width = "100%".to_string();
+            let mut height = "4cm".to_string();
+            let mut fill = "luma(230)".to_string();
+            for (key, value) in split_key_values(&options) {
+                match key.as_str() {
+                    "figwidth" => width = resolve_dimension(conv, &value),
+                    "figheight" => height = resolve_dimension(conv, &value),
+                    "figcolor" => fill = parse_color_expression(&value),
+                    _ => {}
+                }
+            }
+            let _ = write!(
+                output,
+                "#figure(rect(width: {}, height: {}, fill: {})[#align(center + horizon)[Missing figure \\ {}]])",
+                width,
+                height,
+                fill,
+                content.trim()
+            );
+        }
+
         // Graphics - use images module for proper parsing
+        "graphicspath" => {
+            let dirs = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+            conv.state.graphics_paths = parse_graphics_path(&dirs);
+        }
         "includegraphics" => {
             let options = conv.get_optional_arg(&cmd, 0).unwrap_or_default();
             let path = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            // A figure PDF built from a TikZ source next to it
+            if let Some(picture) = built_tikz_picture(conv, &path) {
+                let (import, canvas) = cetz_canvas(conv, &picture);
+                let _ = write!(output, "\n{}\n{}", import, canvas);
+                return;
+            }
+            let path = conv.resolve_image_path(&path);
 
             // Use the images module for proper parsing
             let attrs = ImageAttributes::parse(&options);
@@ -694,6 +974,23 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             }
         }
 
+        "input" => {
+            handle_input(conv, &cmd, output);
+        }
+        "bibliography" => {
+            handle_bibliography(conv, &cmd, output);
+        }
+
+        "csvautotabular" | "pgfplotstabletypeset" => {
+            handle_data_table(conv, &cmd, base_name, output);
+        }
+
+        // A \captionof outside an environment body keeps its text
+        "captionof" => {
+            let content = conv.get_converted_required_arg(&cmd, 1).unwrap_or_default();
+            output.push_str(&content);
+        }
+
         // Caption
         "caption" => {
             let content = conv.get_converted_required_arg(&cmd, 0).unwrap_or_default();
@@ -704,6 +1001,10 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 EnvironmentContext::Table => {
                     let _ = write!(output, "  ), caption: [{}]", content);
                 }
+                // A panel of a figure: the caption goes under it
+                EnvironmentContext::Minipage => {
+                    let _ = write!(output, "\n#align(center)[{}]\n", content);
+                }
                 _ => {
                     let _ = write!(output, "[{}]", content);
                 }
@@ -712,26 +1013,41 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
 
         // List item
         "item" => {
+            // An empty line between items would split the list in two
+            output.truncate(output.trim_end().len());
             output.push('\n');
             for _ in 0..conv.state.indent {
                 output.push(' ');
             }
             match conv.state.current_env() {
                 EnvironmentContext::Enumerate => {
-                    // Check for optional label
-                    if let Some(label) = conv.get_optional_arg(&cmd, 0) {
-                        let _ = write!(output, "+ [{}] ", label);
+                    // A custom label replaces the number: a term item keeps
+                    // the label and still starts an item of its own
+                    if let Some(label) = conv.get_converted_optional_arg(&cmd, 0) {
+                        let _ = write!(output, "/ {}: ", label);
                     } else {
                         output.push_str("+ ");
                     }
                 }
                 EnvironmentContext::Description => {
-                    if let Some(term) = conv.get_optional_arg(&cmd, 0) {
+                    if let Some(term) = conv.get_converted_optional_arg(&cmd, 0) {
                         let _ = write!(output, "/ {}: ", term);
                     } else {
                         output.push_str("/ ");
                     }
                 }
+                EnvironmentContext::Itemize => {
+                    let raw = conv.get_optional_arg_with_braces(&cmd, 0);
+                    let shared = conv.state.list_markers.last().cloned().flatten();
+                    match raw {
+                        Some(raw) if Some(&raw) != shared.as_ref() => {
+                            // Per-item marker: a term item with the marker as its term
+                            let marker = conv.get_converted_optional_arg(&cmd, 0);
+                            let _ = write!(output, "/ {}: ", marker.unwrap_or_default());
+                        }
+                        _ => output.push_str("- "),
+                    }
+                }
                 _ => {
                     output.push_str("- ");
                 }
@@ -795,23 +1111,25 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let _ = write!(output, "display(frac({}, {}))", num.trim(), den.trim());
         }
         "tfrac" => {
-            // tfrac might use slash if enabled and simple
+            // A slash would lose the text-style size in display math
             let num = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
             let den = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
-
-            if conv.state.options.frac_to_slash
-                && conv.is_simple_term(&num)
-                && conv.is_simple_term(&den)
-            {
-                let _ = write!(output, "{}/{}", num.trim(), den.trim());
-            } else {
-                let _ = write!(output, "inline(frac({}, {}))", num.trim(), den.trim());
-            }
+            let _ = write!(output, "inline(frac({}, {}))", num.trim(), den.trim());
         }
         "cfrac" => {
+            // Continued fractions keep every level in display style; Typst
+            // has no numerator alignment for `\cfrac[l]` / `\cfrac[r]`
+            if let Some(align) = conv.get_optional_arg(&cmd, 0) {
+                if matches!(align.trim(), "l" | "r") {
+                    conv.state.add_warning(ConversionWarning::approximation(
+                        "\\cfrac",
+                        format!("Numerator alignment [{}] dropped", align.trim()),
+                    ));
+                }
+            }
             let num = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
             let den = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
-            let _ = write!(output, "frac({}, {})", num.trim(), den.trim());
+            let _ = write!(output, "display(frac({}, {}))", num.trim(), den.trim());
         }
 
         // Math roots
@@ -839,7 +1157,11 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "bar" | "overline" => {
             if let Some(arg) = conv.convert_required_arg(&cmd, 0) {
-                let _ = write!(output, "overline({}) ", arg);
+                if base_name == "overline" && !matches!(conv.state.mode, ConversionMode::Math) {
+                    let _ = write!(output, "#overline[{}]", arg);
+                } else {
+                    let _ = write!(output, "overline({}) ", arg);
+                }
             }
         }
         "vec" => {
@@ -973,39 +1295,72 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
 
         // siunitx commands
-        "SI" | "si" => {
-            let value = conv.get_required_arg(&cmd, 0);
-            let unit = conv.get_required_arg(&cmd, 1);
-            match (value, unit) {
-                (Some(v), Some(u)) => {
-                    let unit_str = conv.process_si_unit(&u);
-                    let _ = write!(output, "${} space {}$", v, unit_str);
-                }
-                (None, Some(u)) => {
-                    let unit_str = conv.process_si_unit(&u);
-                    let _ = write!(output, "${}$", unit_str);
-                }
-                _ => {}
-            }
-        }
-        "qty" => {
+        "SI" | "qty" => {
+            let opts = si_command_options(conv, &cmd);
             let value = conv.get_required_arg(&cmd, 0).unwrap_or_default();
             let unit = conv.get_required_arg(&cmd, 1).unwrap_or_default();
-            let unit_str = conv.process_si_unit(&unit);
-            let _ = write!(output, "${} space {}$", value, unit_str);
+            let unit_str = conv.process_si_unit(&unit, &opts);
+            let _ = write!(
+                output,
+                "${} space {}$",
+                number_to_typst_math(&value, &opts),
+                quote_si_unit(&unit_str)
+            );
+        }
+        "si" | "unit" => {
+            let opts = si_command_options(conv, &cmd);
+            let unit = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let unit_str = conv.process_si_unit(&unit, &opts);
+            let _ = write!(output, "${}$", quote_si_unit(&unit_str));
         }
         "num" => {
+            let opts = si_command_options(conv, &cmd);
             let value = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "${}$", value);
-        }
-        "unit" => {
-            let unit = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let unit_str = conv.process_si_unit(&unit);
-            let _ = write!(output, "${}$", unit_str);
+            let _ = write!(output, "${}$", number_to_typst_math(&value, &opts));
         }
         "ang" => {
             let angle = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "${}°$", angle);
+            let _ = write!(output, "${}$", angle_to_text(&angle));
+        }
+        "SIrange" | "qtyrange" => {
+            let opts = si_command_options(conv, &cmd);
+            let from = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let to = conv.get_required_arg(&cmd, 1).unwrap_or_default();
+            let unit = conv.get_required_arg(&cmd, 2).unwrap_or_default();
+            let unit = quote_si_unit(&conv.process_si_unit(&unit, &opts));
+            let from = number_to_typst_math(&from, &opts);
+            let to = number_to_typst_math(&to, &opts);
+            let _ = match opts.range_units {
+                RangeUnits::Repeat => write!(
+                    output,
+                    "${} space {} dash.en {} space {}$",
+                    from, unit, to, unit
+                ),
+                RangeUnits::Single => {
+                    write!(output, "${} dash.en {} space {}$", from, to, unit)
+                }
+                RangeUnits::Brackets => {
+                    write!(output, "$({} dash.en {}) space {}$", from, to, unit)
+                }
+            };
+        }
+        "numrange" => {
+            let opts = si_command_options(conv, &cmd);
+            let from = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let to = conv.get_required_arg(&cmd, 1).unwrap_or_default();
+            let _ = write!(
+                output,
+                "${} dash.en {}$",
+                number_to_typst_math(&from, &opts),
+                number_to_typst_math(&to, &opts)
+            );
+        }
+        "DeclareSIUnit" => {
+            handle_declare_si_unit(conv, &cmd);
+        }
+        "sisetup" => {
+            let opts = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+            conv.state.si_options.apply(&opts);
         }
 
         // =====================================================================
@@ -1184,7 +1539,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let is_starred = base_name.ends_with('*');
             let opt_n = conv.get_optional_arg(&cmd, 0);
             let arg1 = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let arg2 = conv.convert_required_arg(&cmd, 1);
+            let arg2 = conv.convert_trailing_groups(&cmd, 1).pop();
             if is_starred {
                 // Inline form: \dv*{f}{x} → dif f slash dif x
                 if let Some(n) = opt_n {
@@ -1249,8 +1604,8 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let is_starred = base_name.ends_with('*');
             let opt_n = conv.get_optional_arg(&cmd, 0);
             let arg1 = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let arg2 = conv.convert_required_arg(&cmd, 1);
-            let arg3 = conv.convert_required_arg(&cmd, 2);
+            let mut trailing = conv.convert_trailing_groups(&cmd, 2).into_iter();
+            let (arg2, arg3) = (trailing.next(), trailing.next());
             if is_starred {
                 // Inline form: \pdv*{f}{x} → diff f slash diff x
                 match (opt_n, arg2, arg3) {
@@ -1334,7 +1689,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let is_starred = base_name.ends_with('*');
             let opt_n = conv.get_optional_arg(&cmd, 0);
             let arg1 = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let arg2 = conv.convert_required_arg(&cmd, 1);
+            let arg2 = conv.convert_trailing_groups(&cmd, 1).pop();
             if is_starred {
                 if let Some(n) = opt_n {
                     match arg2 {
@@ -1430,10 +1785,21 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "braket" | "innerproduct" | "ip" | "braket*" => {
             // \braket{a}{b} → lr(⟨ a | b ⟩)
+            // \braket{a|b} (braket package) → lr(⟨ a | b ⟩)
             // \braket{a} → lr(⟨ a | a ⟩)
+            let has_bar = conv
+                .get_required_arg(&cmd, 0)
+                .is_some_and(|raw| raw.contains('|'));
             let a = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let b = conv.convert_required_arg(&cmd, 1);
+            let b = if has_bar {
+                None
+            } else {
+                conv.convert_trailing_groups(&cmd, 1).pop()
+            };
             match b {
+                None if has_bar => {
+                    let _ = write!(output, "lr(angle.l {} angle.r)", a.trim());
+                }
                 Some(b) => {
                     let _ = write!(
                         output,
@@ -1455,7 +1821,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             // \dyad{a}{b} → lr(| a ⟩) lr(⟨ b |)
             // \dyad{a} → lr(| a ⟩) lr(⟨ a |)
             let a = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let b = conv.convert_required_arg(&cmd, 1);
+            let b = conv.convert_trailing_groups(&cmd, 1).pop();
             let b_val = b.as_deref().unwrap_or(a.trim());
             let _ = write!(
                 output,
@@ -1467,7 +1833,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             // \expval{A} → lr(⟨ A ⟩)
             // \expval{A}{Ψ} → lr(⟨ Ψ | A | Ψ ⟩)
             let op = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let state = conv.convert_required_arg(&cmd, 1);
+            let state = conv.convert_trailing_groups(&cmd, 1).pop();
             match state {
                 Some(psi) => {
                     let _ = write!(
@@ -1849,11 +2215,52 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         // Spacing commands
         "hspace" | "hspace*" => {
             let dim = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "#h({})", convert_dimension(&dim));
+            let _ = write!(output, "#h({})", resolve_dimension(conv, &dim));
         }
         "vspace" | "vspace*" => {
             let dim = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "#v({})", convert_dimension(&dim));
+            let _ = write!(output, "#v({})", resolve_dimension(conv, &dim));
+        }
+        // Fills: each one takes an equal share of the free space, like 1fr
+        "hfill" | "hfil" | "vfill" | "vfil" | "dotfill" | "hrulefill" => {
+            if !matches!(conv.state.mode, ConversionMode::Math) {
+                output.push_str(match base_name {
+                    "hfill" | "hfil" => "#h(1fr)",
+                    "vfill" | "vfil" => "#v(1fr)",
+                    "dotfill" => "#box(width: 1fr, repeat[.])",
+                    _ => "#box(width: 1fr, line(length: 100%))",
+                });
+            }
+        }
+        "newlength" | "setlength" | "addtolength" | "settowidth" => {
+            handle_length_command(conv, &cmd, base_name);
+        }
+        "tcbset" | "mdfsetup" => {
+            let options = conv.get_required_arg_with_braces(&cmd, 0).unwrap_or_default();
+            let env = if base_name == "tcbset" { "tcolorbox" } else { "mdframed" };
+            set_framed_defaults(conv, env, &options);
+        }
+        "newcolumntype" => {
+            let name = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let params = conv
+                .get_optional_arg(&cmd, 0)
+                .and_then(|n| n.trim().parse().ok())
+                .unwrap_or(0);
+            let spec = conv.get_required_arg_with_braces(&cmd, 1).unwrap_or_default();
+            let name = name.trim();
+            if name.chars().count() == 1 {
+                conv.state
+                    .column_types
+                    .insert(name.to_string(), (params, spec));
+            }
+        }
+        "linespread" | "setstretch" | "onehalfspacing" | "doublespacing" | "singlespacing" => {
+            if let Some(stretch) = line_stretch_for_command(conv, &cmd, base_name) {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                let _ = writeln!(output, "{}", line_spacing_rule(stretch));
+            }
         }
         "quad" => {
             if matches!(conv.state.mode, ConversionMode::Math) {
@@ -1892,6 +2299,9 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "par" | "bigskip" | "medskip" | "smallskip" => {
             output.push_str("\n\n");
+            for _ in 0..conv.state.line_indent() {
+                output.push(' ');
+            }
         }
 
         // Special math symbols
@@ -1906,7 +2316,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         // Special characters
         "LaTeX" => output.push_str("LaTeX"),
         "TeX" => output.push_str("TeX"),
-        "today" => output.push_str("#datetime.today().display()"),
+        "today" => output.push_str(TODAY_PLACEHOLDER),
         "cdots" => output.push_str("dots.h.c"),
         "ldots" | "dots" => output.push_str("..."),
         "copyright" => output.push('©'),
@@ -1922,17 +2332,12 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "textasciitilde" => output.push('~'),
         "textasciicircum" => output.push('^'),
         "%" => output.push('%'),
+        "&" if matches!(conv.state.mode, ConversionMode::Math) => output.push_str("\\&"),
         "&" => output.push('&'),
         // Special characters that need escaping in Typst text mode
         "$" => output.push_str("\\$"),
         "#" => output.push_str("\\#"),
-        "_" => {
-            if matches!(conv.state.mode, ConversionMode::Math) {
-                output.push('_');
-            } else {
-                output.push_str("\\_");
-            }
-        }
+        "_" => output.push_str("\\_"),
         "*" => {
             if matches!(conv.state.mode, ConversionMode::Math) {
                 output.push('*');
@@ -1944,36 +2349,17 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "{" => output.push('{'),
         "}" => output.push('}'),
 
-        // Accents in text mode
-        "`" => {
-            // grave accent
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_text_accent(&content, '`'));
-        }
-        "'" => {
-            // acute accent
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_text_accent(&content, '\''));
-        }
-        "^" => {
-            // circumflex
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_text_accent(&content, '^'));
-        }
-        "~" => {
-            // tilde
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_text_accent(&content, '~'));
-        }
-        "\"" => {
-            // umlaut
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_text_accent(&content, '"'));
-        }
-        "c" => {
-            // cedilla
-            let content = conv.get_required_arg(&cmd, 0).unwrap_or_default();
-            output.push_str(&apply_cedilla(&content));
+        // Text accents: \"{o} -> ö, \v{s} -> š, \'\i -> í; in math they
+        // become the matching math accent, if there is one
+        name if is_text_accent(name) => {
+            let arg = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+            if !matches!(conv.state.mode, ConversionMode::Math) {
+                output.push_str(&compose_accent(name, &arg));
+            } else if let Some(accent) = math_accent(name) {
+                let _ = write!(output, "{}({}) ", accent, arg);
+            } else {
+                output.push_str(&arg);
+            }
         }
 
         // Color commands (using parse_color_expression for proper color mapping)
@@ -2001,6 +2387,17 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 typst_bg, typst_frame, content
             );
         }
+        "rotatebox" => {
+            let angle = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let content = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
+            // \rotatebox turns counterclockwise, Typst clockwise
+            match angle.trim().parse::<f64>() {
+                Ok(angle) if angle != 0.0 => {
+                    let _ = write!(output, "#rotate({}deg, reflow: true)[{}]", -angle, content);
+                }
+                _ => output.push_str(&content),
+            }
+        }
         "highlight" | "hl" => {
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
             let _ = write!(output, "#highlight[{}]", content);
@@ -2057,8 +2454,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "coprod" => output.push_str("product.co "),
 
         // Delimiters
-        "left" | "right" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl" | "biggr" | "Biggl"
-        | "Biggr" | "middle" => {
+        "left" | "right" | "middle" => {
             // These are handled by ItemLR
         }
 
@@ -2088,32 +2484,24 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             }
         }
 
-        // Stacking - tex2typst style with limits()
-        "overset" => {
-            // \overset{top}{base} -> limits(base)^(top)
+        // Stacking - attach() with limits() so the annotation sits above/below;
+        // the base keeps its math class, so relations keep relation spacing
+        "overset" | "stackrel" => {
+            // \overset{top}{base} -> attach(limits(base), t: top)
             // Special optimization: \overset{\text{def}}{=} -> eq.def
             let top = conv.convert_required_term_arg(&cmd, 0).unwrap_or_default();
             let base = conv.convert_required_term_arg(&cmd, 1).unwrap_or_default();
-            let top_trimmed = top.trim().replace("\"", "");
-            if (top_trimmed == "def" || top_trimmed.contains("def"))
-                && (base.trim() == "=" || base.trim() == "eq")
-            {
+            if top.trim().trim_matches('"') == "def" && matches!(base.trim(), "=" | "eq") {
                 output.push_str("eq.def ");
             } else {
-                let _ = write!(output, "limits({})^({}) ", base, top);
+                let _ = write!(output, "attach(limits({}), t: {}) ", base, top);
             }
         }
         "underset" => {
-            // \underset{bottom}{base} -> limits(base)_(bottom)
+            // \underset{bottom}{base} -> attach(limits(base), b: bottom)
             let bottom = conv.convert_required_term_arg(&cmd, 0).unwrap_or_default();
             let base = conv.convert_required_term_arg(&cmd, 1).unwrap_or_default();
-            let _ = write!(output, "limits({})_({}) ", base, bottom);
-        }
-        "stackrel" => {
-            // \stackrel{top}{relation} -> limits(relation)^(top)
-            let top = conv.convert_required_term_arg(&cmd, 0).unwrap_or_default();
-            let base = conv.convert_required_term_arg(&cmd, 1).unwrap_or_default();
-            let _ = write!(output, "limits({})^({}) ", base, top);
+            let _ = write!(output, "attach(limits({}), b: {}) ", base, bottom);
         }
         "substack" => {
             // \substack{a \\ b} -> directly output content
@@ -2121,14 +2509,50 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 output.push_str(&arg);
             }
         }
+        "limits" | "nolimits" => {
+            // Postfix modifiers wrap the operator before them:
+            // \int\limits -> limits(integral), \sum\nolimits -> scripts(sum)
+            let mut operator = String::new();
+            for child in cmd.syntax().children_with_tokens() {
+                if child.as_token().is_some_and(|t| t.text() == cmd_str) {
+                    continue;
+                }
+                conv.visit_element(child, &mut operator);
+            }
+            // The outermost modifier wins when stacked: \int\limits\nolimits
+            let operator = strip_limits_wrapper(operator.trim());
+            let wrapper = if base_name == "limits" { "limits" } else { "scripts" };
+            let _ = write!(output, "{}({}) ", wrapper, operator);
+        }
 
         // Protect / misc
         "protect" => {
             // ignore
         }
+        "texorpdfstring" => {
+            // The TeX form is shown; the PDF form only names the bookmark
+            let tex = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+            let pdf = conv.get_required_arg(&cmd, 1).unwrap_or_default();
+            output.push_str(&tex);
+            conv.state.pdf_strings.push(pdf);
+        }
         "mbox" | "makebox" | "hbox" => {
-            let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "\"{}\"", content);
+            if matches!(conv.state.mode, ConversionMode::Math) {
+                if let Some(arg) = conv.get_required_arg(&cmd, 0) {
+                    output.push_str(&math_text(&arg));
+                }
+            } else {
+                let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
+                match conv.get_optional_arg(&cmd, 0) {
+                    Some(width) if !width.trim().is_empty() => {
+                        let width = resolve_dimension(conv, &width);
+                        let _ = write!(output, "#box(width: {})[{}]", width, content);
+                    }
+                    _ => {
+                        let _ = write!(output, "#box[{}]", content);
+                    }
+                }
+            }
         }
         "raisebox" => {
             let _height = conv.get_required_arg(&cmd, 0);
@@ -2140,6 +2564,37 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             let content = conv.convert_required_arg(&cmd, 1).unwrap_or_default();
             output.push_str(&content);
         }
+        "shortstack" | "makecell" | "thead" | "pbox" => {
+            // \pbox{width}{text} carries its text in the second argument
+            let index = usize::from(base_name == "pbox");
+            let content = conv.convert_required_arg(&cmd, index).unwrap_or_default();
+            // The optional argument of \pbox is a vertical position, not an alignment
+            let align = conv
+                .get_optional_arg(&cmd, 0)
+                .filter(|_| base_name != "pbox")
+                .and_then(|spec| spec.trim().chars().find_map(stack_align));
+            let content = content.trim().replace("\\  ", "\\ ");
+            if conv.state.is_inside(&EnvironmentContext::Tabular) {
+                // The lines stay in one cell; an explicit alignment overrides the column's
+                match align {
+                    Some(align) => {
+                        let _ = write!(
+                            output,
+                            "___TYPST_CELL___:table.cell(align: {})[{}]",
+                            align, content
+                        );
+                    }
+                    None => output.push_str(&content),
+                }
+            } else {
+                let _ = write!(
+                    output,
+                    "#box(align({}, [{}]))",
+                    align.unwrap_or("center"),
+                    content
+                );
+            }
+        }
         "minipage" => {
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
             output.push_str(&content);
@@ -2154,9 +2609,49 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "multicolumn" => {
             let ncols = conv.get_required_arg(&cmd, 0).unwrap_or("1".to_string());
-            let _align = conv.get_required_arg(&cmd, 1);
+            let spec = conv.get_required_arg(&cmd, 1).unwrap_or_default();
             let content = conv.convert_required_arg(&cmd, 2).unwrap_or_default();
-            let _ = write!(output, "___TYPST_CELL___:table.cell(colspan: {})[{}]", ncols, content);
+            if spec.contains('|') {
+                conv.state.add_warning(ConversionWarning::approximation(
+                    &format!("\\multicolumn{{{}}}{{{}}}", ncols.trim(), spec),
+                    "Vertical rules of a \\multicolumn spec are not drawn",
+                ));
+            }
+            let mut attrs = vec![format!("colspan: {}", ncols.trim())];
+            if let Some(align) = multicolumn_align(&spec) {
+                attrs.push(format!("align: {}", align.to_typst()));
+            }
+            let _ = write!(
+                output,
+                "___TYPST_CELL___:table.cell({})[{}]",
+                attrs.join(", "),
+                content
+            );
+        }
+        // Table shading from xcolor's `table` option
+        "cellcolor" | "rowcolor" if conv.state.is_inside(&EnvironmentContext::Tabular) => {
+            let model = conv.get_optional_arg(&cmd, 0);
+            let color = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let marker = if base_name == "cellcolor" {
+                CELL_FILL_MARKER
+            } else {
+                ROW_FILL_MARKER
+            };
+            let fill = parse_model_color(&color, model.as_deref());
+            let _ = write!(output, "{}{}___", marker, fill);
+        }
+        "rowcolors" => {
+            let start = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            let color = |index| {
+                let color = conv.get_required_arg(&cmd, index).unwrap_or_default();
+                (!color.trim().is_empty()).then(|| parse_color_expression(&color))
+            };
+            let colors = RowColors {
+                start: start.trim().parse().unwrap_or(1),
+                odd: color(1),
+                even: color(2),
+            };
+            conv.scoped_table_spacing().row_colors = Some(colors);
         }
         "multirow" => {
             let nrows = conv.get_required_arg(&cmd, 0).unwrap_or("1".to_string());
@@ -2548,17 +3043,11 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
 
         // Big delimiters - handled via data module
         _ if crate::data::symbols::is_big_delimiter_command(base_name) => {
-            if let Some(delim) = conv.get_required_arg(&cmd, 0) {
-                if let Some(typst_delim) = crate::data::symbols::convert_delimiter(delim.trim()) {
-                    if !typst_delim.is_empty() {
-                        output.push_str(typst_delim);
-                        output.push(' ');
-                    }
-                } else {
-                    output.push_str(delim.trim());
-                    output.push(' ');
-                }
+            if delimits_sized_matrix(cmd.syntax()) {
+                // Becomes the matrix's delim
+                return;
             }
+            convert_sized_delimiter(conv, &cmd, output);
         }
 
         // Custom Operators with limits
@@ -2588,61 +3077,23 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "E" => output.push_str("bb(E) "),
         "iid" => output.push_str("\"i.i.d.\""),
 
-        // Negation command - \not followed by a symbol
+        // Negation command - \not followed by a relation
         "not" => {
-            // \not X -> X.not (for symbols that support it)
-            // or cancel(X) as fallback
+            // \not X -> X.not for relations with a negated symbol, cancel(X) otherwise
             if let Some(arg) = conv.convert_required_arg(&cmd, 0) {
                 let arg = arg.trim();
-                // Common negatable symbols
-                let negated = match arg {
-                    "=" | "eq" => "eq.not",
-                    "<" | "lt" => "lt.not",
-                    ">" | "gt" => "gt.not",
-                    "in" => "in.not",
-                    "subset" => "subset.not",
-                    "supset" => "supset.not",
-                    "equiv" => "equiv.not",
-                    "approx" => "approx.not",
-                    "sim" | "tilde.op" => "tilde.not",
-                    "parallel" => "parallel.not",
-                    "exists" => "exists.not",
-                    "ni" | "in.rev" => "in.rev.not",
-                    "mid" | "divides" => "divides.not",
-                    "prec" => "prec.not",
-                    "succ" => "succ.not",
-                    "subset.eq" => "subset.eq.not",
-                    "supset.eq" => "supset.eq.not",
-                    "lt.eq" => "lt.eq.not",
-                    "gt.eq" => "gt.eq.not",
-                    "arrow.l" => "arrow.l.not",
-                    "arrow.r" => "arrow.r.not",
-                    "arrow.l.double" => "arrow.l.double.not",
-                    "arrow.r.double" => "arrow.r.double.not",
-                    "tack.r" => "tack.r.not",
-                    "forces" => "forces.not",
-                    _ => {
-                        // Try appending .not for any symbol
-                        if arg.chars().all(|c| c.is_alphanumeric() || c == '.') {
-                            // Output as symbol.not
-                            let _ = write!(output, "{}.not ", arg);
-                            return;
-                        } else {
-                            // Fallback: use cancel
-                            let _ = write!(output, "cancel({}) ", arg);
-                            return;
-                        }
-                    }
-                };
-                output.push_str(negated);
-                output.push(' ');
+                if let Some(negated) = negate_relation(arg) {
+                    let _ = write!(output, "{} ", negated);
+                } else if !arg.is_empty() {
+                    let _ = write!(output, "cancel({}) ", arg);
+                }
             }
         }
 
         // Inline code
         "verb" => {
             if let Some(content) = conv.get_required_arg(&cmd, 0) {
-                let _ = write!(output, "`{}`", content);
+                let _ = write!(output, "`{}`", restore_verbatim_chars(&content));
             } else {
                 let text = cmd.syntax().text().to_string();
                 for delim in ['|', '!', '+', '@', '#', '"', '\''] {
@@ -2660,6 +3111,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "lstinline" => {
             if let Some(content) = conv.get_required_arg(&cmd, 0) {
+                let content = restore_verbatim_chars(&content);
                 let options_str = conv.get_optional_arg(&cmd, 0).unwrap_or_default();
                 let options = CodeBlockOptions::parse(&options_str);
                 let lang = options.get_typst_language();
@@ -2706,13 +3158,72 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         // Page breaks
         "newpage" | "clearpage" | "cleardoublepage" => {
             output.push_str("\n#pagebreak()\n");
+            conv.state.page_style.page_broken = true;
+        }
+        "pagebreak" => {
+            // Below priority 4 LaTeX only suggests a break
+            let priority = conv
+                .get_optional_arg(&cmd, 0)
+                .and_then(|n| n.trim().parse::<u8>().ok())
+                .unwrap_or(4);
+            if priority < 4 {
+                output.push_str("\n#pagebreak(weak: true)\n");
+            } else {
+                output.push_str("\n#pagebreak()\n");
+            }
+            conv.state.page_style.page_broken = true;
+        }
+        "nopagebreak" => {
+            conv.state.add_warning(
+                ConversionWarning::new(
+                    WarningKind::PageFlow,
+                    "Typst cannot forbid a page break here; dropped",
+                )
+                .with_location("\\nopagebreak"),
+            );
         }
+        "FloatBarrier" => {
+            if conv.state.options.float_barrier_pagebreak {
+                output.push_str("\n#pagebreak(weak: true)\n");
+            } else {
+                output.push_str("\n// \\FloatBarrier\n");
+            }
+        }
+
+        // Page styles and fancyhdr headers/footers, emitted as one page rule
+        "pagestyle" => {
+            let style = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            conv.state.page_style.name = Some(style.trim().to_string());
+        }
+        "thispagestyle" => {
+            let style = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+            if conv.state.page_style.page_broken {
+                conv.state.add_warning(
+                    ConversionWarning::new(
+                        WarningKind::UnsupportedMacro,
+                        format!(
+                            "\\thispagestyle{{{}}} after a page break cannot be scoped \
+                             to one page; ignored",
+                            style.trim()
+                        ),
+                    )
+                    .with_location("\\thispagestyle"),
+                );
+            } else {
+                conv.state.page_style.first_page = Some(style.trim().to_string());
+            }
+        }
+        "fancyhead" | "fancyfoot" | "fancyhf" | "lhead" | "chead" | "rhead" | "lfoot"
+        | "cfoot" | "rfoot" => {
+            handle_fancy_command(conv, &cmd, base_name);
+        }
+        "thepage" => output.push_str(PAGE_NUMBER),
+        "leftmark" => output.push_str(LEFT_MARK),
+        "rightmark" => output.push_str(RIGHT_MARK),
 
         // Appendix
         "appendix" => {
-            output.push_str("\n// Appendix\n");
-            output.push_str("#counter(heading).update(0)\n");
-            output.push_str("#set heading(numbering: \"A.\")\n\n");
+            output.push_str(APPENDIX_RULES);
         }
 
         // Color command (scope-based, hard to convert perfectly)
@@ -2726,32 +3237,31 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
 
         // Ignored commands - alignment and layout
         "centering" | "raggedright" | "raggedleft" | "noindent" | "indent"
-        | "pagebreak" | "nopagebreak" | "enlargethispage"
-        | "null" | "relax" | "ignorespaces" | "obeylines" | "obeyspaces" | "frenchspacing"
-        | "nonfrenchspacing" | "normalfont" | "rmfamily" | "sffamily" | "ttfamily" | "bfseries"
-        | "mdseries" | "itshape" | "scshape" | "upshape" | "slshape" | "normalsize" | "tiny"
-        | "scriptsize" | "footnotesize" | "small" | "large" | "Large" | "LARGE" | "huge"
-        | "Huge" | "nocite" | "printbibliography" | "printglossary" | "printacronyms"
+        | "enlargethispage" | "samepage"
+        | "null" | "relax" | "ignorespaces" | "obeylines" | "obeyspaces" | "normalfont" | "rmfamily" | "sffamily" | "ttfamily" | "bfseries"
+        | "mdseries" | "itshape" | "scshape" | "upshape" | "slshape" | "nocite" | "printbibliography" | "printglossary" | "printacronyms"
         | "glsresetall" | "tableofcontents" | "listoffigures" | "listoftables"
         | "frontmatter" | "mainmatter" | "backmatter"
         // IEEE and conference specific
         | "IEEEauthorblockN" | "IEEEauthorblockA" | "IEEEoverridecommandlockouts"
         | "IEEEaftertitletext" | "IEEEmembership" | "IEEEspecialpapernotice"
         | "markboth" | "markright" | "thanks" | "and"
+        // Manual bibliography: \newblock only separates parts of an entry
+        | "newblock"
         // Additional formatting switches (excluding already handled: it, bf, tt, sc, rm)
         | "em" | "sf" | "sl"
         // Floats and placement
-        | "suppressfloats" | "FloatBarrier" | "clearfloats"
+        | "suppressfloats" | "clearfloats"
         // Spacing (excluding already handled: smallskip, medskip, bigskip)
-        | "vfill" | "hfill" | "hfil" | "vfil" | "break" | "allowbreak" | "nobreak"
+        | "break" | "allowbreak" | "nobreak"
         | "goodbreak" | "penalty"
         // Margin and page setup
-        | "marginpar" | "marginparpush" | "reversemarginpar" | "normalmarginpar"
+        | "marginparpush" | "reversemarginpar" | "normalmarginpar" | "listoftodos"
         // Misc invisible commands (excluding already handled: protect)
         | "expandafter" | "global" | "long" | "outer" | "inner"
         | "noexpand" | "csname" | "endcsname" | "string" | "number" 
         // More bibliography
-        | "addbibresource" | "bibdata" | "bibstyle" 
+        | "addbibresource" | "bibdata" | "bibstyle" | "bibliographystyle" 
         // Index
         | "makeindex" | "printindex" | "index" | "glossary" => {
             // Ignore these
@@ -2769,7 +3279,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
             // Check text format commands (these return prefix/suffix pairs)
             let lookup_name = format!("\\{}", base_name);
             if let Some((prefix, suffix)) = TEXT_FORMAT_COMMANDS.get(lookup_name.as_str()) {
-                if let Some(content) = conv.get_required_arg(&cmd, 0) {
+                if let Some(content) = conv.convert_required_arg(&cmd, 0) {
                     output.push_str(prefix);
                     output.push_str(&content);
                     output.push_str(suffix);
@@ -2777,6 +3287,13 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 return;
             }
 
+            // Unknown preamble commands are setup the body must not show
+            if conv.state.in_preamble {
+                conv.state
+                    .add_warning(ConversionWarning::preamble_dropped(cmd_str));
+                return;
+            }
+
             // Pass through unknown commands using AST-based processing
             // This preserves the behavior of convert_default_command from old version
             if conv.state.options.non_strict {
@@ -2809,8 +3326,11 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                         output.push_str(base_name);
                     }
                 } else {
-                    // In text mode, output name as comment to avoid garbage text
-                    let _ = write!(output, "/* \\{} */", base_name);
+                    // In text mode, output name as comment to avoid garbage text;
+                    // a semantic command already left its metadata marker
+                    if conv.state.options.metadata_command(base_name).is_none() {
+                        let _ = write!(output, "/* \\{} */", base_name);
+                    }
                     for child in cmd.syntax().children_with_tokens() {
                         if child.kind() == SyntaxKind::ClauseArgument {
                             if let SyntaxElement::Node(n) = child {
@@ -2849,6 +3369,494 @@ fn convert_matrix_body(input: &str) -> String {
     result.trim().to_string()
 }
 
+/// Files `\input{path}` may refer to
+pub(crate) fn input_candidates(path: &str) -> Vec<String> {
+    if image_extension(path).is_some() {
+        vec![path.to_string()]
+    } else {
+        vec![format!("{}.tex", path), format!("{}.tikz", path)]
+    }
+}
+
+/// TikZ picture of an `\input` figure file, read through the file resolver
+pub(crate) fn input_tikz_picture(conv: &LatexConverter, path: &str) -> Option<String> {
+    let source = conv.read_resolved_file(&input_candidates(path.trim()))?;
+    standalone_tikz_picture(&source).map(str::to_string)
+}
+
+/// TikZ picture an `\includegraphics` PDF was built from, read from the
+/// `.tikz` or `.tex` source next to it
+pub(crate) fn built_tikz_picture(conv: &LatexConverter, path: &str) -> Option<String> {
+    let path = path.trim();
+    let stem = path.strip_suffix(".pdf").unwrap_or(path);
+    let source = conv.read_resolved_file(&[format!("{}.tikz", stem), format!("{}.tex", stem)])?;
+    standalone_tikz_picture(&source).map(str::to_string)
+}
+
+/// Convert a TikZ picture into the CeTZ import and the `#canvas(..)` call
+pub(crate) fn cetz_canvas(conv: &LatexConverter, picture: &str) -> (String, String) {
+    let cetz = crate::tikz::convert_tikz_to_cetz_for(picture, conv.state.options.cetz_version);
+    match cetz.split_once("\n\n") {
+        Some((import, canvas)) => (import.to_string(), canvas.to_string()),
+        None => (String::new(), cetz),
+    }
+}
+
+/// `#metadata((kind: ..)) <kind>` marker for a semantic command or
+/// environment, with its content converted: an array of content blocks or a
+/// content block
+pub(crate) fn metadata_marker(command: &MetadataCommand, raw: &str) -> String {
+    let value = if command.list {
+        let items: Vec<String> = split_metadata_list(raw)
+            .iter()
+            .map(|item| convert_caption_text(item).trim().to_string())
+            .filter(|item| !item.is_empty())
+            .map(|item| format!("[{}]", item))
+            .collect();
+        let trailing = if items.len() == 1 { "," } else { "" };
+        format!("({}{})", items.join(", "), trailing)
+    } else {
+        format!("[{}]", convert_caption_text(raw.trim()).trim())
+    };
+    format!(
+        "\n#metadata(({}: {})) <{}>\n",
+        command.kind, value, command.kind
+    )
+}
+
+/// Items of a keyword list, separated by `,`, `;`, `\sep` or `\and`
+/// outside braces
+fn split_metadata_list(raw: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut depth = 0;
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' | ';' if depth == 0 => {
+                items.push(String::new());
+                continue;
+            }
+            '\\' if depth == 0 => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                }
+                if name == "sep" || name == "and" {
+                    items.push(String::new());
+                } else {
+                    let item = items.last_mut().expect("items start non-empty");
+                    item.push('\\');
+                    item.push_str(&name);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        items.last_mut().expect("items start non-empty").push(c);
+    }
+    items
+}
+
+/// `\captionof` among the elements of one environment body. The caption
+/// wraps the content block just before it (an image, a tabular, a picture)
+/// in a `#figure`, or the next one when nothing captionable precedes it.
+#[derive(Default)]
+pub(crate) struct CaptionOf {
+    /// Output span of the last content block, while only spacing follows it
+    block: Option<(usize, usize)>,
+    /// A caption, and the label after it, waiting for the next block
+    pending: Option<(CmdItem, Option<String>)>,
+    /// End of the figure just built, for a `\label` that follows it
+    figure_end: Option<usize>,
+}
+
+impl CaptionOf {
+    /// Handle `elem` if it is a `\captionof` or the `\label` of one; returns
+    /// whether it was consumed
+    pub(crate) fn consume(
+        &mut self,
+        conv: &mut LatexConverter,
+        elem: &SyntaxElement,
+        output: &mut String,
+    ) -> bool {
+        let Some(cmd) = elem.as_node().cloned().and_then(CmdItem::cast) else {
+            return false;
+        };
+        match cmd.name_tok().map(|t| t.text().to_string()).as_deref() {
+            Some("\\captionof") => {
+                match self.block.take() {
+                    Some((start, end)) => {
+                        self.figure_end =
+                            Some(captioned_figure(conv, &cmd, None, start..end, output));
+                    }
+                    None => self.pending = Some((cmd, None)),
+                }
+                true
+            }
+            Some("\\label") => {
+                let label = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                if let Some((_, pending_label)) = &mut self.pending {
+                    *pending_label = Some(label);
+                    return true;
+                }
+                match self.figure_end.take() {
+                    Some(end) if output[end..].trim().is_empty() => {
+                        output.truncate(end);
+                        let _ = writeln!(output, " {}", label_to_typst(&sanitize_label(&label)));
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Note `elem`, just converted into `output[start..]`
+    pub(crate) fn visited(
+        &mut self,
+        conv: &mut LatexConverter,
+        elem: &SyntaxElement,
+        start: usize,
+        output: &mut String,
+    ) {
+        if is_caption_spacing(elem) {
+            return;
+        }
+        self.figure_end = None;
+        self.block = None;
+        if !is_captionable(elem) {
+            return;
+        }
+        match self.pending.take() {
+            Some((cmd, label)) => {
+                let end = output.len();
+                let figure_end = captioned_figure(conv, &cmd, label.clone(), start..end, output);
+                self.figure_end = label.is_none().then_some(figure_end);
+            }
+            None => self.block = Some((start, output.len())),
+        }
+    }
+
+    /// End of the body: a caption that found no block stays as text
+    pub(crate) fn finish(&mut self, conv: &mut LatexConverter, output: &mut String) {
+        if let Some((cmd, _)) = self.pending.take() {
+            output.push_str(&conv.get_converted_required_arg(&cmd, 1).unwrap_or_default());
+        }
+    }
+}
+
+/// Content a `\captionof` can caption
+fn is_captionable(elem: &SyntaxElement) -> bool {
+    let Some(node) = elem.as_node() else {
+        return false;
+    };
+    if let Some(env) = EnvItem::cast(node.clone()) {
+        return env.name_tok().is_some_and(|name| {
+            let name = name.text();
+            name.starts_with("tabular")
+                || matches!(
+                    name,
+                    "array" | "tikzpicture" | "picture" | "lstlisting" | "minted" | "verbatim"
+                )
+        });
+    }
+    CmdItem::cast(node.clone())
+        .and_then(|cmd| cmd.name_tok())
+        .is_some_and(|name| matches!(name.text(), "\\includegraphics" | "\\input"))
+}
+
+/// Elements allowed between a block and its `\captionof`
+fn is_caption_spacing(elem: &SyntaxElement) -> bool {
+    match elem {
+        SyntaxElement::Token(_) => {
+            elem.as_token().is_some_and(|t| t.text().trim().is_empty())
+                || elem.kind() == SyntaxKind::TokenComment
+        }
+        SyntaxElement::Node(node) => CmdItem::cast(node.clone())
+            .and_then(|cmd| cmd.name_tok())
+            .is_some_and(|name| {
+                matches!(
+                    name.text(),
+                    "\\centering"
+                        | "\\par"
+                        | "\\vspace"
+                        | "\\smallskip"
+                        | "\\medskip"
+                        | "\\bigskip"
+                        | "\\hfill"
+                )
+            }),
+    }
+}
+
+/// Replace `output[span]` by a `#figure` of the `\captionof` kind holding it;
+/// returns the output position just after the figure
+fn captioned_figure(
+    conv: &mut LatexConverter,
+    cmd: &CmdItem,
+    label: Option<String>,
+    span: std::ops::Range<usize>,
+    output: &mut String,
+) -> usize {
+    let kind = conv.get_required_arg(cmd, 0).unwrap_or_default();
+    let caption = conv.get_converted_required_arg(cmd, 1).unwrap_or_default();
+    let kind = match kind.trim() {
+        "figure" => "image".to_string(),
+        "table" => "table".to_string(),
+        other => {
+            let mut chars = other.chars();
+            let supplement: String = chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+            format!("\"{}\",\n  supplement: [{}]", other, supplement)
+        }
+    };
+    let mut figure = format!(
+        "\n#figure(\n  kind: {},\n  caption: [{}],\n)[\n{}\n]",
+        kind,
+        caption.trim(),
+        output[span.clone()].trim()
+    );
+    if let Some(label) = label {
+        let _ = write!(figure, " {}", label_to_typst(&sanitize_label(&label)));
+    }
+    let end = span.start + figure.len();
+    figure.push('\n');
+    output.replace_range(span, &figure);
+    end
+}
+
+/// `\csvautotabular` or `\pgfplotstabletypeset` of a data file, per
+/// [`L2TOptions::data_tables`]: its cells written into a `#table(..)`, or the
+/// file loaded with `csv(..)` when the Typst document is compiled. A file the
+/// resolver cannot read becomes a placeholder table.
+///
+/// [`L2TOptions::data_tables`]: super::L2TOptions::data_tables
+fn handle_data_table(conv: &mut LatexConverter, cmd: &CmdItem, name: &str, output: &mut String) {
+    let path = conv.get_required_arg(cmd, 0).unwrap_or_default();
+    let path = path.trim();
+    let options = conv.get_optional_arg(cmd, 0).unwrap_or_default();
+    let table = match name {
+        "csvautotabular" => DataTableOptions::csvsimple(&options),
+        _ => DataTableOptions::pgfplotstable(&options),
+    };
+    let align = |index: usize| match table.alignment(index) {
+        'c' => "center",
+        'r' => "right",
+        _ => "left",
+    };
+
+    if conv.state.options.data_tables == DataTableMode::Load {
+        let delimiter = match table.separator {
+            Some(',') => String::new(),
+            Some(separator) => format!(", delimiter: {:?}", separator.to_string()),
+            None => {
+                conv.state.add_warning(ConversionWarning::approximation(
+                    &format!("\\{}{{{}}}", name, path),
+                    "Whitespace-separated columns are loaded with a single space as delimiter",
+                ));
+                ", delimiter: \" \"".to_string()
+            }
+        };
+        let aligns: Vec<&str> = (0..table.alignments.len().max(1)).map(align).collect();
+        let align = match aligns.as_slice() {
+            [single] => single.to_string(),
+            many => format!("({})", many.join(", ")),
+        };
+        let rows = if table.header {
+            "    table.header(..data.first()),\n    ..data.slice(1).flatten(),"
+        } else {
+            "    ..data.flatten(),"
+        };
+        let _ = write!(
+            output,
+            "\n#{{\n  let data = csv(\"{}\"{})\n  table(\n    columns: data.first().len(),\n    align: {},\n{}\n  )\n}}\n",
+            path, delimiter, align, rows
+        );
+        return;
+    }
+
+    let Some(content) = conv.read_resolved_file(&[path.to_string()]) else {
+        conv.state.add_warning(
+            ConversionWarning::new(
+                WarningKind::FileNotFound,
+                format!(
+                    "Data file `{}` could not be read; a placeholder table is used",
+                    path
+                ),
+            )
+            .with_location(format!("\\{}{{{}}}", name, path)),
+        );
+        let _ = write!(
+            output,
+            "\n#table(columns: 1, [Missing data file `{}`])\n",
+            path
+        );
+        return;
+    };
+
+    let rows = parse_delimited(&content, table.separator);
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(1).max(1);
+    let mut typst = String::from("#table(\n");
+    let _ = writeln!(
+        typst,
+        "    columns: ({}),",
+        vec!["auto"; columns].join(", ")
+    );
+    let aligns: Vec<&str> = (0..columns).map(align).collect();
+    let _ = writeln!(typst, "    align: ({}),", aligns.join(", "));
+    for (index, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|column| {
+                let cell = row.get(column).map(String::as_str).unwrap_or_default();
+                let mut converted = String::new();
+                let tree = mitex_parser::parse(cell, conv.spec.clone());
+                conv.visit_node(&tree, &mut converted);
+                format!("[{}]", converted.trim())
+            })
+            .collect();
+        if index == 0 && table.header {
+            let _ = writeln!(typst, "    table.header({}),", cells.join(", "));
+        } else {
+            let _ = writeln!(typst, "    {},", cells.join(", "));
+        }
+    }
+    typst.push(')');
+    let _ = writeln!(output, "\n{}", typst);
+}
+
+/// `\input` of a figure file through the file resolver: a standalone TikZ
+/// picture becomes a CeTZ figure, any other standalone document or an
+/// unreadable `.tikz` file its pre-built PDF
+fn handle_input(conv: &mut LatexConverter, cmd: &CmdItem, output: &mut String) {
+    let path = conv.get_required_arg(cmd, 0).unwrap_or_default();
+    let path = path.trim();
+    if let Some(picture) = input_tikz_picture(conv, path) {
+        let (import, canvas) = cetz_canvas(conv, &picture);
+        let _ = write!(output, "\n{}\n#figure[\n{}]\n", import, canvas);
+        return;
+    }
+
+    let is_figure = path.ends_with(".tikz")
+        || conv
+            .read_resolved_file(&input_candidates(path))
+            .is_some_and(|source| source_document_class(&source) == Some("standalone"));
+    if !is_figure {
+        let _ = write!(output, "/* \\input{{{}}} */", path);
+        return;
+    }
+    let stem = path.strip_suffix(".tikz").or(path.strip_suffix(".tex"));
+    let image = conv.resolve_image_path(&format!("{}.pdf", stem.unwrap_or(path)));
+    conv.state.add_warning(ConversionWarning::approximation(
+        &format!("\\input{{{}}}", path),
+        format!(
+            "Not a TikZ picture that can be converted, '{}' is shown instead",
+            image
+        ),
+    ));
+    let _ = write!(output, "#image(\"{}\")", image);
+}
+
+/// `\bibliography{refs}` as `#bibliography("refs.bib")`. A bibliography
+/// embedded with `filecontents` becomes a companion file of the output,
+/// converted to Hayagriva YAML if `hayagriva_bibliography` is set.
+fn handle_bibliography(conv: &mut LatexConverter, cmd: &CmdItem, output: &mut String) {
+    let names = conv.get_required_arg(cmd, 0).unwrap_or_default();
+    let mut paths = Vec::new();
+    for name in names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let mut path = name.to_string();
+        if !path.ends_with(".bib") {
+            path.push_str(".bib");
+        }
+        if let Some(bib) = conv.state.embedded_files.get(&path).cloned() {
+            let contents = if conv.state.options.hayagriva_bibliography {
+                path = format!("{}.yml", path.trim_end_matches(".bib"));
+                parse_bibtex(&bib)
+                    .iter()
+                    .map(BibEntry::to_yaml)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                bib
+            };
+            if !conv
+                .state
+                .companion_files
+                .iter()
+                .any(|file| file.path == path)
+            {
+                conv.state.companion_files.push(CompanionFile {
+                    path: path.clone(),
+                    contents,
+                });
+            }
+        }
+        paths.push(format!("\"{}\"", path));
+    }
+    match paths.as_slice() {
+        [] => {}
+        [path] => {
+            let _ = write!(output, "\n#bibliography({})\n", path);
+        }
+        paths => {
+            let _ = write!(output, "\n#bibliography(({}))\n", paths.join(", "));
+        }
+    }
+}
+
+/// Render a `\thanks{..}` note as a Typst footnote
+fn thanks_footnote(note: &str) -> String {
+    format!("#footnote[{}]", convert_caption_text(note).trim())
+}
+
+/// Main language from babel's options: `main=..` if given, otherwise the
+/// last language loaded
+fn babel_main_language(options: &str) -> Option<DateLanguage> {
+    let options: Vec<&str> = options.split(',').map(str::trim).collect();
+    options
+        .iter()
+        .find_map(|opt| opt.strip_prefix("main=").map(str::trim))
+        .and_then(DateLanguage::from_babel)
+        .or_else(|| {
+            options
+                .iter()
+                .rev()
+                .find_map(|opt| DateLanguage::from_babel(opt))
+        })
+}
+
+/// Collect PDF metadata (`pdftitle`, `pdfauthor`, `pdfkeywords`) from \hypersetup
+fn handle_hypersetup(conv: &mut LatexConverter, cmd: &CmdItem) {
+    let Some(options) = conv.get_required_arg_with_braces(cmd, 0) else {
+        return;
+    };
+    let list = |value: &str| -> Vec<String> {
+        value
+            .split([',', ';'])
+            .map(|item| convert_caption_text(item).trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+    for (key, value) in split_key_values(&options) {
+        match key.as_str() {
+            "pdftitle" => {
+                conv.state.pdf_title = Some(convert_caption_text(&value).trim().to_string())
+            }
+            "pdfauthor" => conv.state.pdf_authors = list(&value),
+            "pdfkeywords" => conv.state.pdf_keywords = list(&value),
+            _ => {}
+        }
+    }
+}
+
 /// Handle \newcommand or \renewcommand
 fn handle_newcommand(conv: &mut LatexConverter, cmd: &CmdItem) {
     // \newcommand{\name}[nargs][default]{replacement}
@@ -2973,6 +3981,58 @@ fn handle_newglossaryentry(conv: &mut LatexConverter, cmd: &CmdItem) {
     }
 }
 
+/// Call a macro kept as a `#let` definition, with its arguments as math;
+/// in text the call is an inline equation
+fn convert_let_macro_call(
+    conv: &mut LatexConverter,
+    cmd: &CmdItem,
+    let_macro: &LetMacro,
+    output: &mut String,
+) {
+    let in_math = matches!(conv.state.mode, ConversionMode::Math);
+    let arg_mode = if let_macro.text {
+        ConversionMode::Text
+    } else {
+        ConversionMode::Math
+    };
+    let mode = std::mem::replace(&mut conv.state.mode, arg_mode);
+    let args: Vec<String> = (0..let_macro.params)
+        .map(|index| conv.convert_required_arg(cmd, index).unwrap_or_default())
+        .collect();
+    conv.state.mode = mode;
+    if let_macro.text {
+        // `;` ends the call where what follows would continue it
+        let call = let_macro.content_call(&args);
+        let following: String =
+            std::iter::successors(cmd.syntax().next_sibling_or_token(), |elem| {
+                elem.next_sibling_or_token()
+            })
+            .take(3)
+            .map(|elem| elem.to_string())
+            .collect();
+        let mut following = following.trim_start_matches("{}").chars();
+        let continues = following.next().is_some_and(|c| {
+            c.is_alphanumeric()
+                || matches!(c, '_' | '(' | '[')
+                || (c == '.' && following.next().is_some_and(char::is_alphanumeric))
+        });
+        if in_math {
+            let _ = write!(output, "{}; ", call);
+        } else if continues {
+            let _ = write!(output, "{};", call);
+        } else {
+            output.push_str(&call);
+        }
+        return;
+    }
+    let call = let_macro.call(&args);
+    if in_math {
+        output.push_str(&call);
+    } else {
+        let _ = write!(output, "${}$", call.trim_end().trim_end_matches(';'));
+    }
+}
+
 /// Expand a user-defined macro
 fn expand_user_macro(conv: &mut LatexConverter, cmd: &CmdItem, macro_def: &MacroDef) -> String {
     let mut result = macro_def.replacement.clone();
@@ -2995,6 +4055,331 @@ fn expand_user_macro(conv: &mut LatexConverter, cmd: &CmdItem, macro_def: &Macro
     output
 }
 
+/// Handle \DeclareSIUnit[options]{\name}{definition}
+fn handle_declare_si_unit(conv: &mut LatexConverter, cmd: &CmdItem) {
+    let name = conv.get_required_arg(cmd, 0).unwrap_or_default();
+    let name = name.trim();
+    if !name.starts_with('\\') || name.len() < 2 {
+        return;
+    }
+    let definition = conv.get_required_arg(cmd, 1).unwrap_or_default();
+    let text = conv.process_si_unit(&definition, &SiOptions::default());
+    conv.state.si_units.insert(name.to_string(), text);
+}
+
+/// Global siunitx options with the command's `[...]` overrides applied
+fn si_command_options(conv: &LatexConverter, cmd: &CmdItem) -> SiOptions {
+    let local = conv.get_optional_arg_with_braces(cmd, 0);
+    conv.state.si_options.with_overrides(local.as_deref())
+}
+
+/// Quote unit text so Typst math sets it upright
+fn quote_si_unit(unit: &str) -> String {
+    if unit.is_empty() {
+        String::new()
+    } else {
+        format!("\"{}\"", unit.replace('"', "\\\""))
+    }
+}
+
+/// Handle length register commands (\newlength, \setlength, \addtolength, \settowidth)
+fn handle_length_command(conv: &mut LatexConverter, cmd: &CmdItem, name: &str) {
+    let register = match conv.get_required_arg(cmd, 0) {
+        Some(r) => r.trim().trim_start_matches('\\').to_string(),
+        None => return,
+    };
+    if register.is_empty() {
+        return;
+    }
+
+    match name {
+        "newlength" => {
+            conv.state
+                .lengths
+                .entry(register)
+                .or_insert_with(|| "0pt".to_string());
+        }
+        "setlength" if register == "arraystretch" => {
+            // Passed on by the macro engine from `\renewcommand{\arraystretch}`
+            let value = conv.get_required_arg(cmd, 1).unwrap_or_default();
+            if let Ok(stretch) = value.trim().parse::<f64>() {
+                conv.scoped_table_spacing().array_stretch = Some(stretch);
+            }
+        }
+        "setlength" => {
+            let value = conv.get_required_arg(cmd, 1).unwrap_or_default();
+            let value = resolve_dimension(conv, &value);
+            if register == "tabcolsep" {
+                conv.scoped_table_spacing().column_sep = Some(value.clone());
+            }
+            conv.state.lengths.insert(register, value);
+        }
+        "addtolength" => {
+            let value = conv.get_required_arg(cmd, 1).unwrap_or_default();
+            let value = resolve_dimension(conv, &value);
+            let total = match conv.state.lengths.get(&register) {
+                Some(current) if current != "0pt" => format!("{} + {}", current, value),
+                _ => value,
+            };
+            conv.state.lengths.insert(register, total);
+        }
+        "settowidth" => {
+            // Text cannot be measured here, assume roughly half an em per character
+            let text = conv.get_required_arg(cmd, 1).unwrap_or_default();
+            let chars = text
+                .trim()
+                .chars()
+                .filter(|c| *c != '{' && *c != '}')
+                .count();
+            let width = format!("{}em", chars as f32 * 0.5);
+            conv.state.add_warning(ConversionWarning::approximation(
+                &format!("\\{}", register),
+                format!("Width of '{}' approximated as {}", text.trim(), width),
+            ));
+            conv.state.lengths.insert(register, width);
+        }
+        _ => {}
+    }
+}
+
+/// Fill fancyhdr header/footer slots from `\fancyhead[..]{..}`, `\lhead{..}` etc.
+///
+/// Selectors combine positions (`L`, `C`, `R`), pages (`E`, `O`) and, for
+/// `\fancyhf`, parts (`H`, `F`); a missing letter class means all of them.
+/// Only odd-page settings are kept since the output is one-sided.
+fn handle_fancy_command(conv: &mut LatexConverter, cmd: &CmdItem, name: &str) {
+    // The slots hold body text even when set from the preamble
+    let in_preamble = std::mem::replace(&mut conv.state.in_preamble, false);
+    let value = conv.convert_required_arg(cmd, 0).unwrap_or_default();
+    conv.state.in_preamble = in_preamble;
+    let selectors = match name {
+        "lhead" => "HL".to_string(),
+        "chead" => "HC".to_string(),
+        "rhead" => "HR".to_string(),
+        "lfoot" => "FL".to_string(),
+        "cfoot" => "FC".to_string(),
+        "rfoot" => "FR".to_string(),
+        _ => {
+            let part = match name {
+                "fancyhead" => "H",
+                "fancyfoot" => "F",
+                _ => "",
+            };
+            let spec = conv.get_optional_arg(cmd, 0).unwrap_or_default();
+            spec.split(',')
+                .map(|item| format!("{}{}", part, item.trim().to_ascii_uppercase()))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+    };
+
+    let fancy = &mut conv.state.page_style.fancy;
+    for item in selectors.split(',') {
+        if item.contains('E') && !item.contains('O') {
+            continue;
+        }
+        let pick = |letters: &str| -> Vec<usize> {
+            let found: Vec<usize> = item.chars().filter_map(|c| letters.find(c)).collect();
+            if found.is_empty() {
+                (0..letters.len()).collect()
+            } else {
+                found
+            }
+        };
+        for part in pick("HF") {
+            let slots = if part == 0 {
+                &mut fancy.head
+            } else {
+                &mut fancy.foot
+            };
+            for position in pick("LCR") {
+                slots[position] = value.clone();
+            }
+        }
+    }
+}
+
+/// Read the `\baselinestretch` factor set by \linespread or a setspace command
+fn line_stretch_for_command(conv: &LatexConverter, cmd: &CmdItem, name: &str) -> Option<f64> {
+    match name {
+        "onehalfspacing" => Some(ONEHALF_SPACING_STRETCH),
+        "doublespacing" => Some(DOUBLE_SPACING_STRETCH),
+        "singlespacing" => Some(1.0),
+        _ => conv
+            .get_required_arg(cmd, 0)
+            .and_then(|arg| arg.trim().parse::<f64>().ok())
+            .filter(|s| *s > 0.0),
+    }
+}
+
+/// Horizontal alignment of a `\shortstack`/`\makecell` position letter
+fn stack_align(letter: char) -> Option<&'static str> {
+    match letter {
+        'l' => Some("left"),
+        'c' => Some("center"),
+        'r' => Some("right"),
+        _ => None,
+    }
+}
+
+/// Convert `\todo[options]{text}`: a highlighted note in the text, or a
+/// drafting margin note with [`TodoStyle::Margin`]. `inline` notes become
+/// a block of their own; `color` sets the note fill.
+fn convert_todo(conv: &mut LatexConverter, cmd: &CmdItem, output: &mut String) {
+    let options = conv.get_optional_arg(cmd, 0).unwrap_or_default();
+    let content = conv.convert_required_arg(cmd, 0).unwrap_or_default();
+    let content = content.trim();
+    let inline = options.split(',').any(|option| option.trim() == "inline");
+    let color = split_key_values(&options)
+        .into_iter()
+        .find(|(key, _)| key == "color" || key == "backgroundcolor")
+        .map(|(_, value)| parse_color_expression(&value));
+
+    match conv.state.options.todo_style {
+        TodoStyle::Margin => {
+            conv.state.uses_drafting_package = true;
+            let (name, key) = if inline {
+                ("inline-note", "fill")
+            } else {
+                ("margin-note", "stroke")
+            };
+            match color {
+                Some(color) => {
+                    let _ = write!(output, "#{}({}: {})[{}]", name, key, color, content);
+                }
+                None => {
+                    let _ = write!(output, "#{}[{}]", name, content);
+                }
+            }
+        }
+        TodoStyle::Inline => {
+            let fill = color.unwrap_or_else(|| "orange".to_string());
+            if inline {
+                let _ = write!(
+                    output,
+                    "#block(fill: {}, inset: 6pt, width: 100%)[TODO: {}]",
+                    fill, content
+                );
+            } else {
+                let _ = write!(output, "#highlight(fill: {})[TODO: {}]", fill, content);
+            }
+        }
+    }
+}
+
+/// Text in math, such as the argument of `\text`, as Typst strings with
+/// the inline math in it converted between them
+fn math_text(text: &str) -> String {
+    let mut result = String::new();
+    for (part, math) in split_inline_math(text) {
+        if math {
+            let _ = write!(result, "{} ", latex_math_to_typst(part).trim());
+        } else {
+            // A tie is a non-breaking space in the string
+            let _ = write!(result, "\"{}\" ", part.replace('~', "\\u{a0}"));
+        }
+    }
+    result
+}
+
+/// Convert a LaTeX dimension to Typst, resolving user-defined length registers
+///
+/// Lengths that cannot be resolved become `1em` with a warning instead of
+/// leaking raw LaTeX into the output.
+pub(crate) fn resolve_dimension(conv: &mut LatexConverter, dim: &str) -> String {
+    let dim = dim.trim();
+
+    // Register reference with an optional factor: \mylen, 0.5\mylen, -\mylen
+    if let Some(pos) = dim.rfind('\\') {
+        let name = &dim[pos + 1..];
+        if let Some(value) = conv.state.lengths.get(name).cloned() {
+            let factor = dim[..pos].trim();
+            if factor.is_empty() {
+                return value;
+            }
+            let value = if value.contains(' ') {
+                format!("({})", value)
+            } else {
+                value
+            };
+            if factor == "-" {
+                return format!("-{}", value);
+            }
+            if factor.parse::<f32>().is_ok() {
+                return format!("{} * {}", factor, value);
+            }
+        }
+    }
+
+    let converted = convert_dimension(dim);
+    if converted.contains('\\') {
+        conv.state.add_warning(ConversionWarning::approximation(
+            dim,
+            "Unresolved length replaced by 1em",
+        ));
+        return "1em".to_string();
+    }
+    converted
+}
+
+/// `\autoref` or `\nameref` to `key`. `\nameref` links the title of a
+/// labeled heading; `\autoref` spells out the supplement Typst cannot
+/// supply. Anything else is a plain `@key`.
+fn labeled_reference(conv: &mut LatexConverter, command: &str, key: &str) -> String {
+    let target = sanitize_label(key);
+    let label = conv.state.labels.iter().find(|label| label.id == key);
+    match (command, label) {
+        (
+            "nameref",
+            Some(Label {
+                title: Some(title), ..
+            }),
+        ) => {
+            return format!("#link(<{}>)[{}]", target, title);
+        }
+        (
+            "autoref",
+            Some(Label {
+                supplement: Some(supplement),
+                number,
+                ..
+            }),
+        ) => {
+            let number = number
+                .as_deref()
+                .map(|n| format!(" {}", n))
+                .unwrap_or_default();
+            return format!("#link(<{}>)[{}{}]", target, supplement, number);
+        }
+        ("nameref", label) => {
+            let message = match label.map(|label| label.label_type) {
+                None => format!("\\nameref{{{}}}: no such label", key),
+                Some(label_type) => format!(
+                    "\\nameref{{{}}} points at a {}, which has no title",
+                    key,
+                    match label_type {
+                        LabelType::Figure => "figure",
+                        LabelType::Table => "table",
+                        LabelType::Equation => "equation",
+                        LabelType::Theorem => "theorem",
+                        LabelType::Section => "heading",
+                        LabelType::Item => "item",
+                    }
+                ),
+            };
+            conv.state.add_warning(ConversionWarning::approximation(
+                "\\nameref",
+                format!("{}; converted to a numbered reference", message),
+            ));
+        }
+        _ => {}
+    }
+    reference_to_typst(&Reference {
+        target,
+        ref_type: ReferenceType::Named,
+    })
+}
+
 /// Convert a LaTeX dimension to Typst
 fn convert_dimension(dim: &str) -> String {
     let dim = dim.trim();
@@ -3052,97 +4437,205 @@ fn convert_dimension(dim: &str) -> String {
     dim
 }
 
-/// Apply a text accent to a character
-fn apply_text_accent(content: &str, accent: char) -> String {
-    let c = content.chars().next().unwrap_or(' ');
-    match accent {
-        '`' => match c {
-            'a' => "à".to_string(),
-            'e' => "è".to_string(),
-            'i' => "ì".to_string(),
-            'o' => "ò".to_string(),
-            'u' => "ù".to_string(),
-            'A' => "À".to_string(),
-            'E' => "È".to_string(),
-            'I' => "Ì".to_string(),
-            'O' => "Ò".to_string(),
-            'U' => "Ù".to_string(),
-            _ => content.to_string(),
-        },
-        '\'' => match c {
-            'a' => "á".to_string(),
-            'e' => "é".to_string(),
-            'i' => "í".to_string(),
-            'o' => "ó".to_string(),
-            'u' => "ú".to_string(),
-            'y' => "ý".to_string(),
-            'A' => "Á".to_string(),
-            'E' => "É".to_string(),
-            'I' => "Í".to_string(),
-            'O' => "Ó".to_string(),
-            'U' => "Ú".to_string(),
-            'Y' => "Ý".to_string(),
-            _ => content.to_string(),
-        },
-        '^' => match c {
-            'a' => "â".to_string(),
-            'e' => "ê".to_string(),
-            'i' => "î".to_string(),
-            'o' => "ô".to_string(),
-            'u' => "û".to_string(),
-            'A' => "Â".to_string(),
-            'E' => "Ê".to_string(),
-            'I' => "Î".to_string(),
-            'O' => "Ô".to_string(),
-            'U' => "Û".to_string(),
-            _ => content.to_string(),
-        },
-        '~' => match c {
-            'a' => "ã".to_string(),
-            'n' => "ñ".to_string(),
-            'o' => "õ".to_string(),
-            'A' => "Ã".to_string(),
-            'N' => "Ñ".to_string(),
-            'O' => "Õ".to_string(),
-            _ => content.to_string(),
-        },
-        '"' => match c {
-            'a' => "ä".to_string(),
-            'e' => "ë".to_string(),
-            'i' => "ï".to_string(),
-            'o' => "ö".to_string(),
-            'u' => "ü".to_string(),
-            'y' => "ÿ".to_string(),
-            'A' => "Ä".to_string(),
-            'E' => "Ë".to_string(),
-            'I' => "Ï".to_string(),
-            'O' => "Ö".to_string(),
-            'U' => "Ü".to_string(),
-            _ => content.to_string(),
-        },
-        _ => content.to_string(),
+/// `operator` without the `limits(..)`/`scripts(..)` wrappers around it
+fn strip_limits_wrapper(operator: &str) -> &str {
+    for wrapper in ["limits(", "scripts("] {
+        if let Some(inner) = operator
+            .strip_prefix(wrapper)
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return strip_limits_wrapper(inner.trim());
+        }
     }
+    operator
+}
+
+/// Zero-based heading level of a sectioning command: article sections are
+/// level 0 (`=`), report and book sections level 1 below their chapters
+pub(crate) fn section_base_level(name: &str, document_class: Option<&str>) -> Option<u8> {
+    let level = match name {
+        "chapter" => return Some(0),
+        "section" => 0,
+        "subsection" => 1,
+        "subsubsection" => 2,
+        "paragraph" => 3,
+        "subparagraph" => 4,
+        _ => return None,
+    };
+    Some(if document_class == Some("article") {
+        level
+    } else {
+        level + 1
+    })
+}
+
+/// Typst level of a heading at zero-based `level`, shifted by the heading
+/// options
+fn heading_level(conv: &LatexConverter, level: u8) -> u8 {
+    let options = &conv.state.options;
+    (i16::from(level) + 1 + i16::from(options.heading_offset) + i16::from(conv.state.heading_shift))
+        .clamp(1, 6) as u8
 }
 
-/// Apply cedilla
-fn apply_cedilla(content: &str) -> String {
-    let c = content.chars().next().unwrap_or(' ');
-    match c {
-        'c' => "ç".to_string(),
-        'C' => "Ç".to_string(),
-        _ => content.to_string(),
+/// Whether `cmd` is the command `name`, e.g. `\label`
+fn command_is(cmd: &CmdItem, name: &str) -> bool {
+    cmd.name_tok().is_some_and(|tok| tok.text() == name)
+}
+
+/// Whether `cmd` adds an entry to the table of contents
+fn is_contents_line(cmd: &CmdItem) -> bool {
+    command_is(cmd, "\\addcontentsline")
+        && cmd
+            .syntax()
+            .children()
+            .find(|child| child.kind() == SyntaxKind::ClauseArgument)
+            .is_some_and(|file| extract_arg_content(&file).trim() == "toc")
+}
+
+/// Whether `cmd` is an unnumbered sectioning command such as `\section*`
+fn is_starred_heading(cmd: &CmdItem) -> bool {
+    cmd.name_tok().is_some_and(|tok| {
+        tok.text()
+            .strip_prefix('\\')
+            .and_then(|name| name.strip_suffix('*'))
+            .is_some_and(|name| section_base_level(name, None).is_some())
+    })
+}
+
+/// The command next to `node` that `matches`, before or after it with only
+/// spacing, comments, labels and `\phantomsection` in between
+fn adjacent_command(node: &SyntaxNode, matches: impl Fn(&CmdItem) -> bool) -> Option<CmdItem> {
+    let find = |mut next: Option<SyntaxElement>, forward: bool| {
+        while let Some(elem) = next {
+            let cmd = elem.as_node().and_then(|n| CmdItem::cast(n.clone()));
+            match &cmd {
+                Some(cmd) if matches(cmd) => return Some(cmd.clone()),
+                Some(cmd) if command_is(cmd, "\\label") || command_is(cmd, "\\phantomsection") => {}
+                Some(_) => return None,
+                None if elem.to_string().trim().is_empty()
+                    || elem.kind() == SyntaxKind::TokenComment => {}
+                None => return None,
+            }
+            next = if forward {
+                elem.next_sibling_or_token()
+            } else {
+                elem.prev_sibling_or_token()
+            };
+        }
+        None
+    };
+    find(node.next_sibling_or_token(), true).or_else(|| find(node.prev_sibling_or_token(), false))
+}
+
+/// `\addcontentsline{toc}{level}{title}`: next to an unnumbered heading it
+/// puts that heading in the outline; on its own it adds a hidden heading
+/// that only the outline shows. Entries of other lists are dropped.
+fn convert_contents_line(conv: &mut LatexConverter, cmd: &CmdItem, output: &mut String) {
+    if !is_contents_line(cmd) {
+        let list = conv.get_required_arg(cmd, 0).unwrap_or_default();
+        conv.state.add_warning(ConversionWarning::approximation(
+            "\\addcontentsline",
+            format!(
+                "Entries of the '{}' list have no Typst equivalent and were dropped",
+                list.trim()
+            ),
+        ));
+        return;
+    }
+    if adjacent_command(cmd.syntax(), is_starred_heading).is_some() {
+        return;
+    }
+    let name = conv.get_required_arg(cmd, 1).unwrap_or_default();
+    let level =
+        section_base_level(name.trim(), conv.state.document_class.as_deref()).unwrap_or_default();
+    let title = conv.get_converted_required_arg(cmd, 2).unwrap_or_default();
+    let _ = write!(
+        output,
+        "#place(hide[#heading(level: {}, numbering: none)[{}]])",
+        heading_level(conv, level),
+        title.trim()
+    );
+}
+
+/// `\pdfbookmark[level]{title}{anchor}`: a hidden heading that is a PDF
+/// bookmark but not in the outline, labelled with the anchor
+fn convert_pdf_bookmark(conv: &mut LatexConverter, cmd: &CmdItem, output: &mut String) {
+    // hyperref levels: 0 is a chapter, 1 a section, ..
+    let name = match conv
+        .get_optional_arg(cmd, 0)
+        .and_then(|l| l.trim().parse::<i8>().ok())
+    {
+        Some(1) => "section",
+        Some(2) => "subsection",
+        Some(3) => "subsubsection",
+        Some(4) => "paragraph",
+        Some(5..) => "subparagraph",
+        _ => "chapter",
+    };
+    let level = section_base_level(name, conv.state.document_class.as_deref()).unwrap_or_default();
+    let title = conv.get_converted_required_arg(cmd, 0).unwrap_or_default();
+    let _ = write!(
+        output,
+        "#place(hide[#heading(level: {}, numbering: none, outlined: false, bookmarked: true)[{}]])",
+        heading_level(conv, level),
+        title.trim()
+    );
+    if let Some(anchor) = conv.get_required_arg(cmd, 1) {
+        let _ = write!(output, " <{}>", sanitize_label(&anchor));
     }
 }
 
 /// Convert section heading with proper level
-fn convert_section(conv: &mut LatexConverter, cmd: &CmdItem, level: u8, output: &mut String) {
-    if let Some(title) = conv.get_required_arg(cmd, 0) {
+///
+/// `level` is zero-based and shifted by the heading options. Past
+/// `max_heading_level` the title becomes bold run-in text. An unnumbered
+/// heading says whether it is `outlined`.
+fn convert_section(
+    conv: &mut LatexConverter,
+    cmd: &CmdItem,
+    level: u8,
+    unnumbered: Option<bool>,
+    output: &mut String,
+) {
+    conv.state.pdf_strings.clear();
+    let prev_mode = conv.state.mode;
+    conv.state.mode = ConversionMode::Text;
+    conv.state.heading_labels = Some(Vec::new());
+    let title = conv.convert_required_arg(cmd, 0);
+    let labels = conv.state.heading_labels.take().unwrap_or_default();
+    conv.state.mode = prev_mode;
+    if let Some(title) = title {
+        // A heading is a single line: `\\` breaks it explicitly instead.
+        // Labels in the title follow it after a space, so that they label
+        // the heading rather than math or text that ends it.
+        let mut title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        let labels: String = labels
+            .iter()
+            .map(|label| format!(" {}", label_to_typst(label)))
+            .collect();
         output.push('\n');
-        for _ in 0..=level {
-            output.push('=');
+        if !conv.state.pdf_strings.is_empty() {
+            let _ = writeln!(output, "// Bookmark: {}", conv.state.pdf_strings.concat());
+        }
+        let level = heading_level(conv, level);
+        if level > conv.state.options.max_heading_level {
+            // Run-in: the paragraph continues on the title's line
+            if !title.is_empty() {
+                let _ = write!(output, "\n*{}*{}", title, labels);
+            }
+        } else if let Some(outlined) = unnumbered {
+            let _ = writeln!(
+                output,
+                "#heading(level: {}, numbering: none, outlined: {})[{}]{}",
+                level, outlined, title, labels
+            );
+        } else {
+            title.push_str(&labels);
+            output.push_str(&"=".repeat(level as usize));
+            output.push(' ');
+            output.push_str(&title);
+            output.push('\n');
         }
-        output.push(' ');
-        output.push_str(&title);
-        output.push('\n');
     }
+    conv.state.pdf_strings.clear();
 }
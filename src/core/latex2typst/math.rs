@@ -2,7 +2,7 @@
 //!
 //! This module handles math formulas, delimiters, and math-specific constructs.
 
-use mitex_parser::syntax::{FormulaItem, SyntaxElement, SyntaxKind, SyntaxNode};
+use mitex_parser::syntax::{CmdItem, FormulaItem, SyntaxElement, SyntaxKind, SyntaxNode};
 use rowan::ast::AstNode;
 use std::fmt::Write;
 
@@ -14,6 +14,20 @@ pub fn convert_formula(conv: &mut LatexConverter, elem: SyntaxElement, output: &
     if let SyntaxElement::Node(n) = elem {
         if let Some(formula) = FormulaItem::cast(n.clone()) {
             let is_inline = formula.is_inline();
+            if conv.state.options.math_passthrough {
+                let source = n.text().to_string();
+                let body = ["$$", "$", "\\(", "\\["]
+                    .iter()
+                    .find_map(|open| source.strip_prefix(open))
+                    .unwrap_or(&source);
+                let body = ["$$", "$", "\\)", "\\]"]
+                    .iter()
+                    .find_map(|close| body.strip_suffix(close))
+                    .unwrap_or(body);
+                conv.state.uses_mitex_package = true;
+                output.push_str(&mitex_call(!is_inline, false, None, body));
+                return;
+            }
             let prev_mode = conv.state.mode;
             conv.state.mode = ConversionMode::Math;
 
@@ -29,6 +43,7 @@ pub fn convert_formula(conv: &mut LatexConverter, elem: SyntaxElement, output: &
                 output.push_str(&cleaned);
                 output.push('$');
             } else {
+                separate_display_math(conv, output);
                 output.push_str("$ ");
                 output.push_str(&cleaned);
                 output.push_str(" $");
@@ -39,6 +54,57 @@ pub fn convert_formula(conv: &mut LatexConverter, elem: SyntaxElement, output: &
     }
 }
 
+/// Put a blank line between a display equation about to be written and one
+/// that directly precedes it, whatever the first one ends with, so the two
+/// stay separate blocks. Inside a list item the new line keeps the item's
+/// indentation.
+pub(crate) fn separate_display_math(conv: &LatexConverter, output: &mut String) {
+    if ends_with_display_math(output) {
+        output.truncate(output.trim_end().len());
+        output.push_str("\n\n");
+        output.extend(std::iter::repeat_n(' ', conv.state.line_indent()));
+    }
+}
+
+/// Whether `output` ends with a display equation: `$ .. $`, possibly
+/// labelled or wrapped in an unnumbered `#math.equation`
+fn ends_with_display_math(output: &str) -> bool {
+    let mut end = output.trim_end();
+    if let Some(rest) = end.strip_suffix(']') {
+        let rest = rest.trim_end();
+        if rest.ends_with(" $") {
+            end = rest;
+        }
+    }
+    if end.ends_with('>') {
+        if let Some((equation, _)) = end.rsplit_once(" <") {
+            end = equation;
+        }
+    }
+    end.ends_with(" $") && end.len() > 2
+}
+
+/// A call rendering raw LaTeX math with the `@preview/mitex` package, for
+/// `L2TOptions::math_passthrough`; the caller notes the package import
+pub(crate) fn mitex_call(block: bool, numbered: bool, label: Option<&str>, latex: &str) -> String {
+    let latex = latex.trim();
+    // A backtick would end the raw text, so such math goes in a string
+    let source = if latex.contains('`') {
+        format!("\"{}\"", latex.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("`{}`", latex)
+    };
+    let mut call = match (block, numbered) {
+        (false, _) => format!("#mi({})", source),
+        (true, false) => format!("#mitex({})", source),
+        (true, true) => format!("#mitex(numbering: \"(1)\", {})", source),
+    };
+    if let Some(label) = label {
+        let _ = write!(call, " <{}>", label);
+    }
+    call
+}
+
 /// Convert a curly group in math mode
 pub fn convert_curly(conv: &mut LatexConverter, elem: SyntaxElement, output: &mut String) {
     if conv.state.in_preamble {
@@ -51,7 +117,12 @@ pub fn convert_curly(conv: &mut LatexConverter, elem: SyntaxElement, output: &mu
     };
 
     if let Some(pending) = conv.state.pending_citation.take() {
-        super::markup::emit_pending_citation_from_curly(&node, pending, output);
+        super::markup::emit_pending_citation_from_curly(
+            &node,
+            pending,
+            &conv.state.manual_bib_keys,
+            output,
+        );
         return;
     }
 
@@ -103,6 +174,7 @@ pub fn convert_curly(conv: &mut LatexConverter, elem: SyntaxElement, output: &mu
 
     // Check if it's empty
     let mut has_content = false;
+    conv.enter_group();
     for child in node.children_with_tokens() {
         match child.kind() {
             SyntaxKind::TokenWhiteSpace
@@ -113,6 +185,7 @@ pub fn convert_curly(conv: &mut LatexConverter, elem: SyntaxElement, output: &mu
         }
         conv.visit_element(child, output);
     }
+    conv.leave_group(output);
     // Add zero-width space for empty groups in math mode
     if !has_content && matches!(conv.state.mode, ConversionMode::Math) {
         output.push_str("zws ");
@@ -201,6 +274,15 @@ pub fn convert_lr(conv: &mut LatexConverter, elem: SyntaxElement, output: &mut S
         }
     }
 
+    // A bare bracket without its partner would unbalance the Typst output,
+    // so one-sided pairs spell it by name
+    let has_left = left_delim.as_deref().is_some_and(|d| d != ".");
+    let has_right = right_delim.as_deref().is_some_and(|d| d != ".");
+    if has_left != has_right {
+        left_delim = left_delim.map(|d| one_sided_delimiter(&d));
+        right_delim = right_delim.map(|d| one_sided_delimiter(&d));
+    }
+
     // Check for common optimizations (matching pairs that don't need lr())
     // Also handle mismatched or missing delimiters gracefully
     let (use_lr, is_valid_pair) = match (left_delim.as_deref(), right_delim.as_deref()) {
@@ -430,10 +512,10 @@ fn classify_lr_matrix_like(
     let env_name = env.name_tok()?.text().to_string();
 
     let kind = match env_name.as_str() {
-        "array" | "matrix" | "smallmatrix" => LrMatrixKind::NoIntrinsicDelim,
-        "pmatrix" | "bmatrix" | "Bmatrix" | "vmatrix" | "Vmatrix" => {
-            LrMatrixKind::WithIntrinsicDelim
-        }
+        "array" | "matrix" | "matrix*" | "smallmatrix" => LrMatrixKind::NoIntrinsicDelim,
+        "pmatrix" | "bmatrix" | "Bmatrix" | "vmatrix" | "Vmatrix" | "pmatrix*" | "bmatrix*"
+        | "Bmatrix*" | "vmatrix*" | "Vmatrix*" | "psmallmatrix" | "bsmallmatrix"
+        | "Bsmallmatrix" | "vsmallmatrix" | "Vsmallmatrix" => LrMatrixKind::WithIntrinsicDelim,
         _ => return None,
     };
 
@@ -499,6 +581,240 @@ fn emit_nested_lr_matrix_like(
     output.push_str(") ");
 }
 
+/// Sizes of the `\big` delimiter family; `\bigl`/`\bigr` add the side
+const SIZED_DELIMITER_COMMANDS: &[&str] = &["big", "Big", "bigg", "Bigg"];
+
+/// The `mat(delim: ..)` of a `\bigl(`..`\bigr)` pair directly around a
+/// matrix environment without delimiters of its own
+pub(crate) fn sized_matrix_delimiter(node: &SyntaxNode) -> Option<&'static str> {
+    let env = mitex_parser::syntax::EnvItem::cast(node.clone())?;
+    if !matches!(env.name_tok()?.text(), "matrix" | "matrix*" | "smallmatrix") {
+        return None;
+    }
+    let open = sized_delimiter(significant_sibling(node, SiblingSide::Prev)?, 'l')?;
+    let close = sized_delimiter(significant_sibling(node, SiblingSide::Next)?, 'r')?;
+    match (open.as_str(), close.as_str()) {
+        ("(", ")") => Some("("),
+        ("[", "]") => Some("["),
+        ("{", "}") => Some("{"),
+        ("bar.v", "bar.v") => Some("|"),
+        ("bar.v.double", "bar.v.double") => Some("‖"),
+        _ => None,
+    }
+}
+
+/// Whether a `\bigl(`-style command is one of the pair that
+/// [`sized_matrix_delimiter`] folds into the matrix
+pub(crate) fn delimits_sized_matrix(cmd: &SyntaxNode) -> bool {
+    [SiblingSide::Prev, SiblingSide::Next]
+        .into_iter()
+        .any(|side| {
+            significant_sibling(cmd, side)
+                .and_then(|sibling| sibling.into_node())
+                .is_some_and(|env| sized_matrix_delimiter(&env).is_some())
+        })
+}
+
+#[derive(Clone, Copy)]
+enum SiblingSide {
+    Prev,
+    Next,
+}
+
+/// The nearest sibling that is not white space
+fn significant_sibling(node: &SyntaxNode, side: SiblingSide) -> Option<SyntaxElement> {
+    let step = |elem: &SyntaxElement| match side {
+        SiblingSide::Prev => elem.prev_sibling_or_token(),
+        SiblingSide::Next => elem.next_sibling_or_token(),
+    };
+    std::iter::successors(step(&SyntaxElement::Node(node.clone())), step).find(|elem| {
+        !matches!(
+            elem.kind(),
+            SyntaxKind::TokenWhiteSpace | SyntaxKind::TokenLineBreak
+        )
+    })
+}
+
+/// How a `\big`-family command places its delimiter
+#[derive(Clone, Copy, PartialEq)]
+enum SizedRole {
+    Open,
+    Close,
+    /// `\bigm|`, or `\big|` with a delimiter that has no side: a divider
+    /// with relation spacing
+    Middle,
+}
+
+/// `lr(size: ..)` percentage of each `\big` size
+fn sized_delimiter_percent(size: &str) -> u32 {
+    match size {
+        "Big" => 150,
+        "bigg" => 200,
+        "Bigg" => 250,
+        _ => 120,
+    }
+}
+
+/// Size, role and LaTeX delimiter of a `\big`-family command
+fn sized_delimiter_parts(cmd: &CmdItem) -> Option<(&'static str, SizedRole, String)> {
+    let name = cmd.name_tok()?;
+    let name = name.text().trim_start_matches('\\');
+    let (size, suffix) = SIZED_DELIMITER_COMMANDS.iter().find_map(|size| {
+        let suffix = name.strip_prefix(size)?;
+        matches!(suffix, "" | "l" | "r" | "m").then_some((*size, suffix))
+    })?;
+    let arg = cmd
+        .syntax()
+        .children()
+        .find(|child| child.kind() == SyntaxKind::ClauseArgument)
+        .map(|arg| arg.text().to_string())
+        .unwrap_or_default();
+    let delim = extract_delimiter_from_text(arg.trim()).to_string();
+    let role = match suffix {
+        "l" => SizedRole::Open,
+        "r" => SizedRole::Close,
+        "m" => SizedRole::Middle,
+        _ => match delim.as_str() {
+            "(" | "[" | "\\{" | "\\lbrace" | "\\langle" | "\\lfloor" | "\\lceil" | "\\lvert"
+            | "\\lVert" | "\\lgroup" => SizedRole::Open,
+            ")" | "]" | "\\}" | "\\rbrace" | "\\rangle" | "\\rfloor" | "\\rceil" | "\\rvert"
+            | "\\rVert" | "\\rgroup" => SizedRole::Close,
+            _ => SizedRole::Middle,
+        },
+    };
+    Some((size, role, delim))
+}
+
+/// The `\big`-family command an element starts with, looking through the
+/// base of a `\bigr)^2` attachment
+fn leading_sized_command(elem: &SyntaxElement) -> Option<CmdItem> {
+    let node = elem.as_node()?;
+    if node.kind() == SyntaxKind::ItemAttachComponent {
+        let base = node.first_child()?;
+        return if base.kind() == SyntaxKind::ClauseArgument {
+            base.first_child().and_then(CmdItem::cast)
+        } else {
+            None
+        };
+    }
+    CmdItem::cast(node.clone())
+}
+
+/// The command that closes the `\bigl(`-style opener `cmd` at the same
+/// level, skipping pairs nested between them
+fn sized_delimiter_closer(cmd: &SyntaxNode) -> Option<CmdItem> {
+    let mut depth = 0;
+    let siblings = std::iter::successors(cmd.next_sibling_or_token(), |elem| {
+        elem.next_sibling_or_token()
+    });
+    for sibling in siblings {
+        let Some(other) = leading_sized_command(&sibling) else {
+            continue;
+        };
+        match sized_delimiter_parts(&other).map(|(_, role, _)| role) {
+            Some(SizedRole::Open) => depth += 1,
+            Some(SizedRole::Close) if depth == 0 => return Some(other),
+            Some(SizedRole::Close) => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A delimiter standing alone in an `lr(..)` argument: brackets are escaped
+/// so that they do not open or close a group
+fn escaped_delimiter(delim: &str) -> String {
+    match delim {
+        "" | "." => String::new(),
+        "(" | ")" | "[" | "]" | "{" | "}" => format!("\\{}", delim),
+        other => other.to_string(),
+    }
+}
+
+/// Convert `\big(`, `\Bigl[`, `\biggr\}`, `\bigm|` and the rest of the
+/// family. A `\bigl`..`\bigr` pair of one size wraps what is between them in
+/// `lr(size: ..)`; any other sized delimiter, including either side of a pair
+/// whose sizes differ, is sized on its own so both sizes survive.
+pub(crate) fn convert_sized_delimiter(
+    conv: &mut LatexConverter,
+    cmd: &CmdItem,
+    output: &mut String,
+) {
+    let Some((size, role, delim)) = sized_delimiter_parts(cmd) else {
+        return;
+    };
+    let percent = sized_delimiter_percent(size);
+    let typst = convert_delimiter(&delim);
+    match role {
+        SizedRole::Open => {
+            let closer = sized_delimiter_closer(cmd.syntax()).and_then(|closer| {
+                let (close_size, _, close_delim) = sized_delimiter_parts(&closer)?;
+                (close_size == size).then_some((closer, close_delim))
+            });
+            if let Some((closer, close_delim)) = closer {
+                // Bare brackets group in Typst math only as an open/close pair
+                let grouped = matches!(typst.as_str(), "(" | "[" | "{")
+                    && matches!(convert_delimiter(&close_delim).as_str(), ")" | "]" | "}");
+                let open = if grouped {
+                    typst
+                } else {
+                    escaped_delimiter(&typst)
+                };
+                conv.state
+                    .sized_delimiter_closers
+                    .push((closer.syntax().text_range(), grouped));
+                let _ = write!(output, "lr(size: #{}%, {} ", percent, open);
+                return;
+            }
+        }
+        SizedRole::Close => {
+            let range = cmd.syntax().text_range();
+            let closers = &mut conv.state.sized_delimiter_closers;
+            if let Some(pos) = closers.iter().position(|(closer, _)| *closer == range) {
+                let (_, grouped) = closers.remove(pos);
+                let close = if grouped {
+                    typst
+                } else {
+                    escaped_delimiter(&typst)
+                };
+                let _ = write!(output, " {})", close);
+                return;
+            }
+        }
+        SizedRole::Middle => {}
+    }
+    let delim = escaped_delimiter(&typst);
+    if delim.is_empty() {
+        return;
+    }
+    if role == SizedRole::Middle {
+        let _ = write!(
+            output,
+            "class(\"relation\", lr(size: #{}%, {})) ",
+            percent, delim
+        );
+    } else {
+        let _ = write!(output, "lr(size: #{}%, {}) ", percent, delim);
+    }
+}
+
+/// The delimiter of a `\big(`/`\bigl(`-style command on the given side
+fn sized_delimiter(elem: SyntaxElement, side: char) -> Option<String> {
+    let cmd = CmdItem::cast(elem.into_node()?)?;
+    let name = cmd.name_tok()?;
+    let name = name.text().trim_start_matches('\\');
+    if !SIZED_DELIMITER_COMMANDS.contains(&name.strip_suffix(side).unwrap_or(name)) {
+        return None;
+    }
+    let arg = cmd
+        .syntax()
+        .children()
+        .find(|child| child.kind() == SyntaxKind::ClauseArgument)?
+        .text()
+        .to_string();
+    Some(convert_delimiter(extract_delimiter_from_text(arg.trim())))
+}
+
 /// Convert subscript/superscript attachment
 pub fn convert_attachment(conv: &mut LatexConverter, elem: SyntaxElement, output: &mut String) {
     let node = match elem {
@@ -587,6 +903,20 @@ fn extract_delimiter_from_text(text: &str) -> &str {
     }
 }
 
+/// Name of a bracket that appears without its partner
+fn one_sided_delimiter(delim: &str) -> String {
+    match delim {
+        "(" => "paren.l",
+        ")" => "paren.r",
+        "[" => "bracket.l",
+        "]" => "bracket.r",
+        "{" => "brace.l",
+        "}" => "brace.r",
+        other => other,
+    }
+    .to_string()
+}
+
 /// Convert a LaTeX delimiter to Typst equivalent
 fn convert_delimiter(delim: &str) -> String {
     match delim.trim() {
@@ -7,14 +7,21 @@
 pub mod context;
 pub mod engine;
 mod environment;
+mod framed;
+mod macros;
 mod markup;
 mod math;
 mod table;
-mod utils;
+mod typography;
+pub(crate) mod utils;
 
+use crate::features::images::ImageAsset;
+use crate::features::refs::Label;
+use crate::utils::CompanionFile;
 pub use context::{
-    ConversionMode, ConversionState, EnvironmentContext, L2TOptions, LatexConverter, PreambleMode,
-    MERGED_SPEC,
+    default_metadata_commands, ConversionMode, ConversionState, DataTableMode, DoubleRulePolicy,
+    EnvironmentContext, FragmentContext, L2TOptions, LatexConverter, MetadataCommand, PreambleMode,
+    SidewaysStyle, TodoStyle, UnknownEnvironmentPolicy, MERGED_SPEC,
 };
 
 // =============================================================================
@@ -40,6 +47,28 @@ pub enum WarningKind {
     MacroLoop,
     /// General parsing or conversion issue
     ParseError,
+    /// A value (e.g., a measured length) was approximated or replaced by a fallback
+    Approximation,
+    /// Two sources disagreed on a document metadata field
+    MetadataConflict,
+    /// An environment without a Typst mapping was converted by policy
+    UnknownEnvironment,
+    /// An image in a format Typst cannot embed (e.g. EPS)
+    UnsupportedImageFormat,
+    /// A page-flow hint (e.g. `\nopagebreak`) that Typst cannot express
+    PageFlow,
+    /// A file the document reads could not be found
+    FileNotFound,
+    /// A preamble command without a Typst equivalent was dropped
+    PreambleDropped,
+    /// The document was not delimited as expected, e.g. it has text after
+    /// `\end{document}` or no `\begin{document}`
+    DocumentStructure,
+    /// A macro kept as a `#let` definition was renamed to avoid a Typst name
+    MacroRenamed,
+    /// Global typography settings (`\widowpenalty`, microtype, ..) without a
+    /// Typst equivalent, reported together
+    Typography,
 }
 
 impl std::fmt::Display for WarningKind {
@@ -53,6 +82,16 @@ impl std::fmt::Display for WarningKind {
             WarningKind::RunawayArgument => write!(f, "runaway argument"),
             WarningKind::MacroLoop => write!(f, "macro loop"),
             WarningKind::ParseError => write!(f, "parse error"),
+            WarningKind::Approximation => write!(f, "approximation"),
+            WarningKind::MetadataConflict => write!(f, "metadata conflict"),
+            WarningKind::UnknownEnvironment => write!(f, "unknown environment"),
+            WarningKind::UnsupportedImageFormat => write!(f, "unsupported image format"),
+            WarningKind::PageFlow => write!(f, "page flow"),
+            WarningKind::FileNotFound => write!(f, "file not found"),
+            WarningKind::PreambleDropped => write!(f, "preamble command dropped"),
+            WarningKind::DocumentStructure => write!(f, "document structure"),
+            WarningKind::MacroRenamed => write!(f, "macro renamed"),
+            WarningKind::Typography => write!(f, "typography"),
         }
     }
 }
@@ -143,6 +182,52 @@ impl ConversionWarning {
         .with_location(macro_name.to_string())
     }
 
+    /// Create an approximation warning
+    pub fn approximation(location: &str, msg: impl Into<String>) -> Self {
+        ConversionWarning::new(WarningKind::Approximation, msg).with_location(location.to_string())
+    }
+
+    /// Create a metadata conflict note (`\hypersetup` value kept over `\<field>`)
+    pub fn metadata_conflict(field: &str, kept: &str, dropped: &str) -> Self {
+        ConversionWarning::new(
+            WarningKind::MetadataConflict,
+            format!(
+                "pdf{} '{}' from \\hypersetup overrides \\{}{{{}}}",
+                field, kept, field, dropped
+            ),
+        )
+        .with_location("\\hypersetup")
+    }
+
+    /// Create a note for an unknown preamble command left out of the output
+    pub fn preamble_dropped(name: &str) -> Self {
+        ConversionWarning::new(
+            WarningKind::PreambleDropped,
+            format!(
+                "Preamble command '{}' has no Typst equivalent and was dropped",
+                name
+            ),
+        )
+        .with_location(name.to_string())
+    }
+
+    /// Create a note on how the document body was delimited
+    pub fn document_structure(msg: impl Into<String>) -> Self {
+        ConversionWarning::new(WarningKind::DocumentStructure, msg)
+    }
+
+    /// Create a note for a macro whose `#let` name was changed
+    pub fn macro_renamed(name: &str, renamed: &str) -> Self {
+        ConversionWarning::new(
+            WarningKind::MacroRenamed,
+            format!(
+                "Macro '\\{}' is defined as '{}', as '{}' is already a Typst name",
+                name, renamed, name
+            ),
+        )
+        .with_location(format!("\\{}", name))
+    }
+
     /// Create a parse error warning
     pub fn parse_error(msg: impl Into<String>) -> Self {
         ConversionWarning::new(WarningKind::ParseError, msg)
@@ -168,10 +253,19 @@ impl From<ConversionWarning> for crate::utils::error::CliDiagnostic {
             WarningKind::UnsupportedMacro
             | WarningKind::PartialExpansion
             | WarningKind::PatternMismatch
-            | WarningKind::ParseError => DiagnosticSeverity::Warning,
-            WarningKind::UnsupportedPrimitive | WarningKind::LaTeX3Skipped => {
-                DiagnosticSeverity::Info
-            }
+            | WarningKind::ParseError
+            | WarningKind::Approximation
+            | WarningKind::UnknownEnvironment
+            | WarningKind::UnsupportedImageFormat
+            | WarningKind::FileNotFound => DiagnosticSeverity::Warning,
+            WarningKind::UnsupportedPrimitive
+            | WarningKind::LaTeX3Skipped
+            | WarningKind::MetadataConflict
+            | WarningKind::PageFlow
+            | WarningKind::PreambleDropped
+            | WarningKind::DocumentStructure
+            | WarningKind::MacroRenamed
+            | WarningKind::Typography => DiagnosticSeverity::Info,
         };
 
         let mut diag = CliDiagnostic::new(severity, warning.kind.to_string(), warning.message);
@@ -189,6 +283,12 @@ pub struct ConversionResult {
     pub output: String,
     /// Warnings generated during conversion
     pub warnings: Vec<ConversionWarning>,
+    /// Images referenced by the document
+    pub(crate) assets: Vec<ImageAsset>,
+    /// Labels defined by the document
+    pub(crate) labels: Vec<Label>,
+    /// Files produced for the output to use
+    pub(crate) companion_files: Vec<CompanionFile>,
 }
 
 impl ConversionResult {
@@ -197,12 +297,21 @@ impl ConversionResult {
         ConversionResult {
             output,
             warnings: Vec::new(),
+            assets: Vec::new(),
+            labels: Vec::new(),
+            companion_files: Vec::new(),
         }
     }
 
     /// Create a result with warnings
     pub fn with_warnings(output: String, warnings: Vec<ConversionWarning>) -> Self {
-        ConversionResult { output, warnings }
+        ConversionResult {
+            output,
+            warnings,
+            assets: Vec::new(),
+            labels: Vec::new(),
+            companion_files: Vec::new(),
+        }
     }
 
     /// Check if there are any warnings
@@ -210,6 +319,25 @@ impl ConversionResult {
         !self.warnings.is_empty()
     }
 
+    /// Every image the document references, with the path used in the
+    /// output, so callers can copy the files next to the converted document
+    pub fn asset_manifest(&self) -> &[ImageAsset] {
+        &self.assets
+    }
+
+    /// Files the output refers to that the conversion produced, such as a
+    /// bibliography embedded with `filecontents`; write them next to the
+    /// output along with the images of the asset manifest
+    pub fn companion_files(&self) -> &[CompanionFile] {
+        &self.companion_files
+    }
+
+    /// Every `\label` of the document in source order, with the kind of
+    /// element it points at and, for headings, the heading title
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
     /// Get warnings as formatted strings
     pub fn format_warnings(&self) -> Vec<String> {
         self.warnings.iter().map(|w| w.to_string()).collect()
@@ -1,5 +1,10 @@
 //! Cell types and alignment for table parsing
 
+/// Marks the Typst fill of `\cellcolor` in cell content, up to `___`
+pub const CELL_FILL_MARKER: &str = "___TYPST_FILL___:";
+/// Marks the Typst fill of `\rowcolor` in a row, up to `___`
+pub const ROW_FILL_MARKER: &str = "___TYPST_ROW_FILL___:";
+
 /// Cell alignment options
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CellAlign {
@@ -32,6 +37,8 @@ pub struct GridCell {
     pub colspan: usize,
     /// Optional cell-specific alignment (from \multicolumn)
     pub align: Option<CellAlign>,
+    /// Optional background color (Typst), from \cellcolor or \rowcolor
+    pub fill: Option<String>,
     /// Whether this cell has special properties (needs table.cell)
     pub is_special: bool,
 }
@@ -44,6 +51,7 @@ impl GridCell {
             rowspan: 1,
             colspan: 1,
             align: None,
+            fill: None,
             is_special: false,
         }
     }
@@ -55,8 +63,13 @@ impl GridCell {
 
     /// Parse a raw cell string and extract span/alignment info (recursively)
     pub fn parse(raw: &str) -> Self {
+        // A fill may sit anywhere in the cell, also inside \multicolumn
+        let (raw, fill) = take_fill_marker(raw, CELL_FILL_MARKER);
+        let raw = raw.as_str();
+
         // Base case: raw string is content
         let mut cell = GridCell::new(raw.to_string());
+        cell.fill = fill;
 
         // Check if it's a special cell marker
         if let Some(start_idx) = raw.find("___TYPST_CELL___:") {
@@ -102,15 +115,13 @@ impl GridCell {
             if let Some(content_start) = marker_content.find('[') {
                 let mut depth = 1;
                 let mut content_end = content_start;
-                let chars: Vec<char> = marker_content.chars().collect();
-
-                for (i, &ch) in chars.iter().enumerate().skip(content_start + 1) {
+                for (i, ch) in marker_content[content_start + 1..].char_indices() {
                     if ch == '[' {
                         depth += 1;
                     } else if ch == ']' {
                         depth -= 1;
                         if depth == 0 {
-                            content_end = i;
+                            content_end = content_start + 1 + i;
                             break;
                         }
                     }
@@ -163,7 +174,12 @@ impl GridCell {
             format!("[{}]", clean_content)
         };
 
-        if self.is_special || self.rowspan > 1 || self.colspan > 1 || self.align.is_some() {
+        if self.is_special
+            || self.rowspan > 1
+            || self.colspan > 1
+            || self.align.is_some()
+            || self.fill.is_some()
+        {
             let mut attrs = Vec::new();
 
             if self.rowspan > 1 {
@@ -175,6 +191,9 @@ impl GridCell {
             if let Some(align) = self.align {
                 attrs.push(format!("align: {}", align.to_typst()));
             }
+            if let Some(fill) = &self.fill {
+                attrs.push(format!("fill: {}", fill));
+            }
 
             if attrs.is_empty() && !self.is_special {
                 // Just normal content if no special attrs (and not marked special explicitly)
@@ -193,3 +212,19 @@ impl GridCell {
         }
     }
 }
+
+/// Remove every `marker` and its fill from `raw`, returning the last fill
+pub fn take_fill_marker(raw: &str, marker: &str) -> (String, Option<String>) {
+    let mut rest = raw;
+    let mut text = String::with_capacity(raw.len());
+    let mut fill = None;
+    while let Some(start) = rest.find(marker) {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + marker.len()..];
+        let end = after.find("___").unwrap_or(after.len());
+        fill = Some(after[..end].to_string());
+        rest = after[end..].strip_prefix("___").unwrap_or_default();
+    }
+    text.push_str(rest);
+    (text, fill)
+}
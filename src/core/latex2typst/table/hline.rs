@@ -1,5 +1,35 @@
 //! Horizontal line types and utilities for table parsing
 
+use crate::core::latex2typst::DoubleRulePolicy;
+
+/// Rule settings for one table
+#[derive(Debug, Clone, Default)]
+pub struct TableRules {
+    /// Rule thickness from `\arrayrulewidth`, as a Typst length
+    pub width: Option<String>,
+    /// How `\hline\hline` is drawn
+    pub double: DoubleRulePolicy,
+    /// Vertical rules from `!{\vrule width ..}`: column boundary and thickness
+    pub vlines: Vec<(usize, Option<String>)>,
+}
+
+impl TableRules {
+    /// Stroke of a rule drawn `factor` times as thick as a single one
+    fn stroke(&self, factor: f64) -> Option<String> {
+        if factor == 1.0 {
+            return self.width.clone();
+        }
+        let width = self.width.as_deref().unwrap_or("1pt");
+        let split = width
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(width.len());
+        match width[..split].parse::<f64>() {
+            Ok(value) if split > 0 => Some(format!("{}{}", value * factor, &width[split..])),
+            _ => Some(format!("{} * {}", factor, width)),
+        }
+    }
+}
+
 /// Represents a horizontal line (full or partial)
 #[derive(Debug, Clone)]
 pub struct HLine {
@@ -7,6 +37,8 @@ pub struct HLine {
     pub start_col: Option<usize>,
     /// Ending column (1-based, inclusive)
     pub end_col: Option<usize>,
+    /// Whether this is a double rule (`\hline\hline`)
+    pub double: bool,
 }
 
 impl HLine {
@@ -15,6 +47,7 @@ impl HLine {
         HLine {
             start_col: None,
             end_col: None,
+            double: false,
         }
     }
 
@@ -23,17 +56,32 @@ impl HLine {
         HLine {
             start_col: Some(start),
             end_col: Some(end),
+            double: false,
         }
     }
 
-    /// Generate Typst code for this hline
-    pub fn to_typst(&self) -> String {
-        match (self.start_col, self.end_col) {
-            (Some(s), Some(e)) => {
-                // Typst uses 0-based indexing for stroke positions
-                format!("table.hline(start: {}, end: {})", s - 1, e)
-            }
-            _ => "table.hline()".to_string(),
+    /// Generate Typst code for this hline with the table's rule settings.
+    /// Typst has no double stroke: a double rule is either one thicker line
+    /// or two lines around a thin spacer row spanning `col_count` columns.
+    pub fn to_typst_with_rules(&self, rules: &TableRules, col_count: usize) -> String {
+        let mut args = Vec::new();
+        if let (Some(s), Some(e)) = (self.start_col, self.end_col) {
+            // Typst uses 0-based indexing for stroke positions
+            args.push(format!("start: {}", s - 1));
+            args.push(format!("end: {}", e));
+        }
+        let thick = self.double && rules.double == DoubleRulePolicy::Thick;
+        if let Some(stroke) = rules.stroke(if thick { 2.0 } else { 1.0 }) {
+            args.push(format!("stroke: {}", stroke));
+        }
+        let line = format!("table.hline({})", args.join(", "));
+        if self.double && rules.double == DoubleRulePolicy::Spaced {
+            format!(
+                "{}, table.cell(colspan: {}, inset: 0pt, stroke: none)[#v(2pt)], {}",
+                line, col_count, line
+            )
+        } else {
+            line
         }
     }
 }
@@ -93,6 +141,19 @@ pub fn extract_hline_range(row_str: &str) -> Option<(usize, usize)> {
     Some((n1, n2))
 }
 
+/// Extract the range of every `|||HLINE|||` marker in a row string
+///
+/// The arguments of a cline/cmidrule follow its marker, so each segment after
+/// a marker is inspected separately. Returns one entry per marker, with `None`
+/// for full lines.
+pub fn extract_hline_ranges(row_str: &str) -> Vec<Option<(usize, usize)>> {
+    row_str
+        .split("|||HLINE|||")
+        .skip(1)
+        .map(extract_hline_range)
+        .collect()
+}
+
 /// Clean arguments left over from cline/cmidrule after |||HLINE|||
 pub fn clean_hline_args(s: &str) -> String {
     let mut result = s.trim_start().to_string();
@@ -200,7 +261,8 @@ pub fn find_cmd_args_end(s: &str) -> Option<usize> {
         }
     }
 
-    Some(pos)
+    // Byte offset of the char position
+    Some(s.char_indices().nth(pos).map_or(s.len(), |(i, _)| i))
 }
 
 /// Clean a cell string by removing booktabs commands
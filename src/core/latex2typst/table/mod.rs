@@ -19,10 +19,16 @@
 //! # Example
 //!
 //! ```ignore
-//! use table::{CellAlign, parse_with_grid_parser};
+//! use table::{CellAlign, parse_with_grid_parser, TableRules, TableSpacing};
 //!
 //! let alignments = vec![CellAlign::Left, CellAlign::Center, CellAlign::Right];
-//! let typst_code = parse_with_grid_parser(content, alignments);
+//! let typst_code = parse_with_grid_parser(
+//!     content,
+//!     alignments,
+//!     TableRules::default(),
+//!     Vec::new(),
+//!     &TableSpacing::default(),
+//! );
 //! ```
 
 mod cell;
@@ -33,5 +39,6 @@ mod parser;
 mod tests;
 
 // Re-export public API
-pub use cell::CellAlign;
-pub use parser::parse_with_grid_parser;
+pub use cell::{CellAlign, CELL_FILL_MARKER, ROW_FILL_MARKER};
+pub use hline::TableRules;
+pub use parser::{parse_with_grid_parser, RowColors, TableSpacing};
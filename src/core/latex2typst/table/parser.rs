@@ -1,7 +1,71 @@
 //! State-aware table grid parser
 
-use super::cell::{CellAlign, GridCell};
-use super::hline::{clean_cell_content, clean_hline_args, extract_hline_range, HLine};
+use super::cell::{take_fill_marker, CellAlign, GridCell, ROW_FILL_MARKER};
+use super::hline::{clean_cell_content, clean_hline_args, extract_hline_ranges, HLine, TableRules};
+
+/// Table spacing and shading parameters in effect for one table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableSpacing {
+    /// Row height factor from `\renewcommand{\arraystretch}{..}`
+    pub array_stretch: Option<f64>,
+    /// Padding on each side of a column from `\tabcolsep`, as a Typst length
+    pub column_sep: Option<String>,
+    /// Alternating row colors from `\rowcolors`
+    pub row_colors: Option<RowColors>,
+}
+
+/// Alternating row colors from `\rowcolors{start}{odd}{even}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowColors {
+    /// First shaded row, counted from one
+    pub start: usize,
+    /// Typst color of the odd rows, none for no shading
+    pub odd: Option<String>,
+    /// Typst color of the even rows, none for no shading
+    pub even: Option<String>,
+}
+
+impl RowColors {
+    /// The table `fill:` closure; Typst counts rows from zero, so LaTeX's
+    /// odd rows have even `y`
+    pub fn to_typst_fill(&self) -> String {
+        let color = |color: &Option<String>| color.clone().unwrap_or_else(|| "none".to_string());
+        let alternate = format!(
+            "if calc.even(y) {{ {} }} else {{ {} }}",
+            color(&self.odd),
+            color(&self.even)
+        );
+        if self.start > 1 {
+            format!(
+                "(_, y) => if y < {} {{ none }} else {}",
+                self.start - 1,
+                alternate
+            )
+        } else {
+            format!("(_, y) => {}", alternate)
+        }
+    }
+}
+
+impl TableSpacing {
+    /// The `inset:` of the generated table. Typst pads cells by 5pt, which is
+    /// scaled by the stretch factor for the vertical padding.
+    pub fn to_typst_inset(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(x) = &self.column_sep {
+            parts.push(format!("x: {}", x));
+        }
+        if let Some(stretch) = self.array_stretch.filter(|s| *s != 1.0) {
+            let y = (stretch * 5.0 * 100.0).round() / 100.0;
+            parts.push(format!("y: {}pt", y));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(format!("({})", parts.join(", ")))
+        }
+    }
+}
 
 /// Represents a parsed table row
 #[derive(Debug, Clone)]
@@ -41,6 +105,13 @@ pub struct TableGridParser {
     pub default_alignments: Vec<CellAlign>,
     /// Pending hlines to attach to the next row
     pending_hlines: Vec<HLine>,
+    /// Rule thickness and double-rule settings
+    pub rules: TableRules,
+    /// Typst placed before and after each cell of a column, from the
+    /// `>{..}` and `<{..}` of its spec
+    pub cell_formats: Vec<(String, String)>,
+    /// Cell padding from `\arraystretch` and `\tabcolsep`
+    pub spacing: TableSpacing,
 }
 
 impl TableGridParser {
@@ -51,12 +122,19 @@ impl TableGridParser {
             rows: Vec::new(),
             default_alignments: alignments,
             pending_hlines: Vec::new(),
+            rules: TableRules::default(),
+            cell_formats: Vec::new(),
+            spacing: TableSpacing::default(),
         }
     }
 
-    /// Add a full horizontal line
+    /// Add a full horizontal line; a second one right after the first makes
+    /// it a double rule
     pub fn add_hline(&mut self) {
-        self.pending_hlines.push(HLine::full());
+        match self.pending_hlines.last_mut() {
+            Some(last) if last.start_col.is_none() && !last.double => last.double = true,
+            _ => self.pending_hlines.push(HLine::full()),
+        }
     }
 
     /// Add a partial horizontal line (cline/cmidrule)
@@ -68,6 +146,17 @@ impl TableGridParser {
     pub fn process_row(&mut self, raw_cells: Vec<String>) {
         let mut row = GridRow::new();
 
+        // \rowcolor shades the cells of its row that have no \cellcolor
+        let mut row_fill = None;
+        let raw_cells: Vec<String> = raw_cells
+            .into_iter()
+            .map(|raw| {
+                let (raw, fill) = take_fill_marker(&raw, ROW_FILL_MARKER);
+                row_fill = fill.or(row_fill.take());
+                raw.trim().to_string()
+            })
+            .collect();
+
         // Attach pending hlines
         row.hlines_before.append(&mut self.pending_hlines);
 
@@ -107,7 +196,17 @@ impl TableGridParser {
             } else {
                 // Not covered, process the input cell
                 let raw = &raw_cells[input_idx];
-                let cell = GridCell::parse(raw);
+                let mut cell = GridCell::parse(raw);
+                if cell.fill.is_none() {
+                    cell.fill.clone_from(&row_fill);
+                }
+
+                // A \multicolumn brings its own spec
+                if let Some((before, after)) = self.cell_formats.get(current_col) {
+                    if cell.align.is_none() && raw != "\\" {
+                        cell.content = format!("{}{}{}", before, cell.content.trim(), after);
+                    }
+                }
 
                 // Update coverage for future rows
                 let rows_to_cover = cell.rowspan.saturating_sub(1);
@@ -134,6 +233,15 @@ impl TableGridParser {
             }
         }
 
+        // Columns past the last input cell that are still covered by a multirow
+        // (rows ending early) must also advance, otherwise their coverage leaks
+        // into the following rows and shifts every cell by the rowspan's width.
+        for coverage in self.col_coverage.iter_mut().skip(current_col) {
+            if *coverage > 0 {
+                *coverage -= 1;
+            }
+        }
+
         if !row.cells.is_empty() || !row.hlines_before.is_empty() {
             self.rows.push(row);
         }
@@ -158,12 +266,35 @@ impl TableGridParser {
                 .collect();
             let _ = writeln!(output, "    align: ({}),", aligns.join(", "));
         }
+        if let Some(inset) = self.spacing.to_typst_inset() {
+            let _ = writeln!(output, "    inset: {},", inset);
+        }
+        if let Some(colors) = &self.spacing.row_colors {
+            let _ = writeln!(output, "    fill: {},", colors.to_typst_fill());
+        }
+        if let Some(width) = &self.rules.width {
+            let _ = writeln!(output, "    stroke: {},", width);
+        }
+        for (x, width) in &self.rules.vlines {
+            match width {
+                Some(width) => {
+                    let _ = writeln!(output, "    table.vline(x: {}, stroke: {}),", x, width);
+                }
+                None => {
+                    let _ = writeln!(output, "    table.vline(x: {}),", x);
+                }
+            }
+        }
 
         // Generate rows
         for row in &self.rows {
             // Emit hlines before this row
             for hline in &row.hlines_before {
-                let _ = writeln!(output, "    {},", hline.to_typst());
+                let _ = writeln!(
+                    output,
+                    "    {},",
+                    hline.to_typst_with_rules(&self.rules, col_count)
+                );
             }
 
             // Emit cells
@@ -175,7 +306,11 @@ impl TableGridParser {
 
         // Emit any remaining pending hlines
         for hline in &self.pending_hlines {
-            let _ = writeln!(output, "    {},", hline.to_typst());
+            let _ = writeln!(
+                output,
+                "    {},",
+                hline.to_typst_with_rules(&self.rules, col_count)
+            );
         }
 
         output.push_str(")\n");
@@ -183,10 +318,21 @@ impl TableGridParser {
     }
 }
 
-/// Parse table content using the state-aware TableGridParser
-pub fn parse_with_grid_parser(content: &str, alignments: Vec<CellAlign>) -> String {
+/// Parse table content using the state-aware TableGridParser, drawing rules
+/// with the given settings, placing `cell_formats` around the cells of
+/// each column and padding cells by `spacing`
+pub fn parse_with_grid_parser(
+    content: &str,
+    alignments: Vec<CellAlign>,
+    rules: TableRules,
+    cell_formats: Vec<(String, String)>,
+    spacing: &TableSpacing,
+) -> String {
     let col_count = alignments.len().max(1);
     let mut parser = TableGridParser::new(alignments);
+    parser.rules = rules;
+    parser.cell_formats = cell_formats;
+    parser.spacing = spacing.clone();
 
     for row_str in content.split("|||ROW|||") {
         let row_str = row_str.trim();
@@ -194,9 +340,10 @@ pub fn parse_with_grid_parser(content: &str, alignments: Vec<CellAlign>) -> Stri
             continue;
         }
 
-        // Check for HLINE markers and extract partial line info
-        if row_str.contains("|||HLINE|||") {
-            let hline_info = extract_hline_range(row_str);
+        // Check for HLINE markers and extract partial line info.
+        // Several rules may share a line (`\cmidrule{2-3}\cmidrule{4-5}`),
+        // each one becomes its own hline.
+        for hline_info in extract_hline_ranges(row_str) {
             match hline_info {
                 Some((start, end)) => parser.add_partial_hline(start, end),
                 None => parser.add_hline(),
@@ -8,7 +8,13 @@ use super::*;
 fn test_basic_table() {
     let content = "A|||CELL|||B|||CELL|||C|||ROW|||1|||CELL|||2|||CELL|||3";
     let alignments = vec![CellAlign::Left, CellAlign::Center, CellAlign::Right];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     assert!(output.contains("[A], [B], [C]"));
     assert!(output.contains("[1], [2], [3]"));
@@ -20,7 +26,13 @@ fn test_multirow() {
     // The empty & becomes an empty string between markers
     let content = "___TYPST_CELL___:table.cell(rowspan: 2)[A]|||CELL|||B|||CELL|||C|||ROW||| |||CELL|||D|||CELL|||E";
     let alignments = vec![CellAlign::Center; 3];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     println!("Multirow output:\n{}", output);
 
@@ -36,7 +48,13 @@ fn test_multicolumn() {
     let content =
         "A|||CELL|||___TYPST_CELL___:table.cell(colspan: 2)[Wide]|||ROW|||1|||CELL|||2|||CELL|||3";
     let alignments = vec![CellAlign::Left; 3];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     assert!(output.contains("[A], table.cell(colspan: 2)[Wide]"));
     assert!(output.contains("[1], [2], [3]"));
@@ -48,7 +66,13 @@ fn test_sparse_data() {
     // Empty cells are represented as space between markers
     let content = "A|||CELL||| |||CELL|||B|||ROW|||C|||CELL|||D|||CELL||| ";
     let alignments = vec![CellAlign::Left; 3];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     println!("Sparse output:\n{}", output);
 
@@ -62,7 +86,13 @@ fn test_hline() {
     // Table with hlines
     let content = "|||HLINE|||A|||CELL|||B|||ROW|||||CELL|||C|||CELL|||D|||ROW|||||HLINE|||";
     let alignments = vec![CellAlign::Center; 2];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     println!("HLine output:\n{}", output);
 
@@ -74,7 +104,13 @@ fn test_cmidrule() {
     // Partial line with cmidrule info: (lr)2-4
     let content = "|||HLINE|||A|||CELL|||B|||CELL|||C|||CELL|||D|||ROW|||(lr)2-4|||HLINE|||E|||CELL|||F|||CELL|||G|||CELL|||H";
     let alignments = vec![CellAlign::Center; 4];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     println!("Cmidrule output:\n{}", output);
 
@@ -91,7 +127,13 @@ fn test_multirow_with_sparse() {
     // Row 3: & E & F
     let content = "___TYPST_CELL___:table.cell(rowspan: 3)[A]|||CELL|||B|||CELL|||C|||ROW||| |||CELL||| |||CELL|||D|||ROW||| |||CELL|||E|||CELL|||F";
     let alignments = vec![CellAlign::Center; 3];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     println!("Multirow with sparse:\n{}", output);
 
@@ -137,9 +179,164 @@ fn test_grid_cell_parse() {
 fn test_empty_table() {
     let content = "";
     let alignments = vec![CellAlign::Left];
-    let output = parse_with_grid_parser(content, alignments);
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
 
     // Should still produce valid table structure
     assert!(output.contains("table("));
     assert!(output.contains("columns:"));
 }
+
+#[test]
+fn test_multirow_with_multicolumn_header() {
+    // \multirow{2}{*}{Model} & \multicolumn{2}{c}{Dataset A} & \multicolumn{2}{c}{Dataset B} \\
+    // \cmidrule(lr){2-3}\cmidrule(lr){4-5}
+    //  & P & R & P & R \\
+    let content = " |||HLINE||| ___TYPST_CELL___:table.cell(rowspan: 2)[Model] |||CELL||| ___TYPST_CELL___:table.cell(colspan: 2)[Dataset A] |||CELL||| ___TYPST_CELL___:table.cell(colspan: 2)[Dataset B] |||ROW||| |||HLINE|||(lr)2-3|||HLINE|||(lr)4-5 |||CELL||| P |||CELL||| R |||CELL||| P |||CELL||| R |||ROW||| |||HLINE||| X |||CELL||| 1 |||CELL||| 2 |||CELL||| 3 |||CELL||| 4 |||ROW|||";
+    let alignments = vec![CellAlign::Center; 5];
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
+
+    println!("Multirow + multicolumn header:\n{}", output);
+
+    assert!(output.contains(
+        "table.cell(rowspan: 2)[Model], table.cell(colspan: 2)[Dataset A], table.cell(colspan: 2)[Dataset B]"
+    ));
+    // Second header row: the covered first column is consumed, not shifted into
+    assert!(output.contains("    [P], [R], [P], [R],"));
+    assert!(output.contains("[X], [1], [2], [3], [4]"));
+    // Both cmidrules are kept as separate partial lines
+    assert!(output.contains("table.hline(start: 1, end: 3)"));
+    assert!(output.contains("table.hline(start: 3, end: 5)"));
+}
+
+#[test]
+fn test_multirow_in_short_row() {
+    // A & \multirow{2}{*}{B} \\ C \\ D & E
+    // The second row ends before the covered column; coverage must still expire.
+    let content =
+        "A|||CELL|||___TYPST_CELL___:table.cell(rowspan: 2)[B]|||ROW|||C|||ROW|||D|||CELL|||E";
+    let alignments = vec![CellAlign::Left; 2];
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
+
+    assert!(output.contains("[A], table.cell(rowspan: 2)[B]"));
+    assert!(output.contains("[C],"));
+    assert!(output.contains("[D], [E]"));
+}
+
+#[test]
+fn test_double_hline_and_rule_width() {
+    let content = "|||HLINE|||A|||CELL|||B|||ROW||||||HLINE||||||HLINE|||1|||CELL|||2";
+    let alignments = vec![CellAlign::Left, CellAlign::Right];
+
+    let output = parse_with_grid_parser(
+        content,
+        alignments.clone(),
+        TableRules::default(),
+        Vec::new(),
+        &TableSpacing::default(),
+    );
+    assert_eq!(output.matches("table.hline()").count(), 1);
+    assert!(output.contains("table.hline(stroke: 2pt),\n    [1], [2]"));
+
+    let rules = TableRules {
+        width: Some("1.5pt".to_string()),
+        double: crate::core::latex2typst::DoubleRulePolicy::Spaced,
+        vlines: vec![(1, Some("2pt".to_string()))],
+    };
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        rules,
+        Vec::new(),
+        &TableSpacing::default(),
+    );
+    assert!(output.contains("    stroke: 1.5pt,\n    table.vline(x: 1, stroke: 2pt),\n"));
+    assert!(output.contains(
+        "table.hline(stroke: 1.5pt), table.cell(colspan: 2, inset: 0pt, stroke: none)[#v(2pt)], table.hline(stroke: 1.5pt),"
+    ));
+}
+
+#[test]
+fn test_table_spacing_becomes_inset() {
+    let content = "A|||CELL|||B";
+    let alignments = vec![CellAlign::Left, CellAlign::Left];
+    let spacing = TableSpacing {
+        array_stretch: Some(1.5),
+        column_sep: Some("10pt".to_string()),
+        ..TableSpacing::default()
+    };
+    let output = parse_with_grid_parser(
+        content,
+        alignments.clone(),
+        TableRules::default(),
+        Vec::new(),
+        &spacing,
+    );
+    assert!(output.contains("    inset: (x: 10pt, y: 7.5pt),\n"));
+
+    let spacing = TableSpacing {
+        array_stretch: Some(1.2),
+        ..TableSpacing::default()
+    };
+    let output = parse_with_grid_parser(
+        content,
+        alignments,
+        TableRules::default(),
+        Vec::new(),
+        &spacing,
+    );
+    assert!(output.contains("    inset: (y: 6pt),\n"));
+}
+
+#[test]
+fn test_cell_and_row_fills() {
+    // Simulate: \rowcolors{1}{gray}{} and \rowcolor{blue} A & \cellcolor{red} B \\ C & D
+    let content =
+        "___TYPST_ROW_FILL___:blue___ A|||CELL|||___TYPST_FILL___:red___ B|||ROW|||C|||CELL|||D";
+    let spacing = TableSpacing {
+        row_colors: Some(RowColors {
+            start: 1,
+            odd: Some("gray".to_string()),
+            even: None,
+        }),
+        ..TableSpacing::default()
+    };
+    let output = parse_with_grid_parser(
+        content,
+        vec![CellAlign::Left, CellAlign::Left],
+        TableRules::default(),
+        Vec::new(),
+        &spacing,
+    );
+    assert!(output.contains(
+        "    fill: (_, y) => if calc.even(y) { gray } else { none },
+"
+    ));
+    assert!(output.contains("table.cell(fill: blue)[A], table.cell(fill: red)[B],"));
+    assert!(output.contains(
+        "    [C], [D],
+"
+    ));
+
+    let cell =
+        GridCell::parse("___TYPST_FILL___:yellow______TYPST_CELL___:table.cell(colspan: 2)[W]");
+    assert_eq!(cell.fill.as_deref(), Some("yellow"));
+    assert_eq!(cell.to_typst(), "table.cell(colspan: 2, fill: yellow)[W]");
+}
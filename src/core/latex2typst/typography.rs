@@ -0,0 +1,265 @@
+//! Global typography toggles
+//!
+//! Commands such as `\sloppy` or `\frenchspacing` and packages such as
+//! microtype change how the whole document is set. [`TYPOGRAPHY_COMMANDS`]
+//! and [`TYPOGRAPHY_PACKAGES`] say what becomes of each: a Typst rule,
+//! nothing at all, or a note. A toggle is supported by adding a row there.
+//! The notes of a document are reported together, in one diagnostic.
+
+use mitex_parser::syntax::{CmdItem, SyntaxKind};
+use rowan::ast::AstNode;
+use rowan::TextSize;
+
+use super::context::LatexConverter;
+use super::{ConversionWarning, WarningKind};
+
+/// What a typography toggle converts to
+#[derive(Debug, Clone, Copy)]
+pub enum TypographyAction {
+    /// A Typst rule with about the same effect
+    Convert(fn(&mut LatexConverter, &CmdItem) -> String),
+    /// Nothing, as Typst sets text this way already
+    Ignore,
+    /// Nothing, with why, for the note of the document
+    Note(&'static str),
+}
+
+/// A global typography command
+#[derive(Debug, Clone, Copy)]
+pub struct TypographyCommand {
+    /// Name without the backslash
+    pub name: &'static str,
+    /// What it converts to
+    pub action: TypographyAction,
+    /// Set like a TeX parameter, `\tolerance=1000`; the value goes with it
+    pub parameter: bool,
+}
+
+const fn command(name: &'static str, action: TypographyAction) -> TypographyCommand {
+    TypographyCommand {
+        name,
+        action,
+        parameter: false,
+    }
+}
+
+const fn parameter(name: &'static str, action: TypographyAction) -> TypographyCommand {
+    TypographyCommand {
+        name,
+        action,
+        parameter: true,
+    }
+}
+
+const NO_PENALTIES: &str = "Typst has no penalties; set `text(costs: ..)` instead";
+const NO_TOLERANCE: &str = "Typst's line breaking has no tolerance to set";
+
+/// Global typography commands and what they convert to
+pub const TYPOGRAPHY_COMMANDS: &[TypographyCommand] = &[
+    command("frenchspacing", TypographyAction::Ignore),
+    command(
+        "nonfrenchspacing",
+        TypographyAction::Note("Typst puts no extra space after a sentence"),
+    ),
+    command("sloppy", TypographyAction::Convert(sloppy)),
+    command("fussy", TypographyAction::Ignore),
+    command(
+        "raggedbottom",
+        TypographyAction::Note("Typst pages are ragged at the bottom already"),
+    ),
+    command(
+        "flushbottom",
+        TypographyAction::Note("Typst does not stretch pages to the bottom"),
+    ),
+    command(
+        "microtypesetup",
+        TypographyAction::Note("Typst has no font expansion"),
+    ),
+    parameter("widowpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("clubpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("displaywidowpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("brokenpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("hyphenpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("exhyphenpenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("interlinepenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("linepenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("predisplaypenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("postdisplaypenalty", TypographyAction::Note(NO_PENALTIES)),
+    parameter("tolerance", TypographyAction::Note(NO_TOLERANCE)),
+    parameter("pretolerance", TypographyAction::Note(NO_TOLERANCE)),
+    parameter("emergencystretch", TypographyAction::Note(NO_TOLERANCE)),
+];
+
+/// Packages that only change the typography, and what they convert to
+pub const TYPOGRAPHY_PACKAGES: &[(&str, TypographyAction)] = &[
+    (
+        "microtype",
+        TypographyAction::Note("Typst hangs punctuation into the margin but has no font expansion"),
+    ),
+    ("nowidow", TypographyAction::Note(NO_PENALTIES)),
+];
+
+/// `\sloppy` lets lines stretch rather than run into the margin
+fn sloppy(_: &mut LatexConverter, _: &CmdItem) -> String {
+    "#set par(justify: true, linebreaks: \"simple\")".to_string()
+}
+
+/// The typography command `name`, if it is one
+pub fn typography_command(name: &str) -> Option<&'static TypographyCommand> {
+    TYPOGRAPHY_COMMANDS
+        .iter()
+        .find(|command| command.name == name)
+}
+
+/// What the typography package `name` converts to, if it is one
+pub fn typography_package(name: &str) -> Option<TypographyAction> {
+    TYPOGRAPHY_PACKAGES
+        .iter()
+        .find(|(package, _)| *package == name)
+        .map(|(_, action)| *action)
+}
+
+/// Convert a typography command: a rule goes with the settings of the
+/// document when in the preamble and where the command stood in the body,
+/// a note is kept for [`report_typography_notes`]
+pub(super) fn convert_typography_command(
+    conv: &mut LatexConverter,
+    cmd: &CmdItem,
+    command: &TypographyCommand,
+    output: &mut String,
+) {
+    if command.parameter {
+        if let Some(end) = parameter_value_end(cmd) {
+            conv.state.consumed_until = Some(end);
+        }
+    }
+    apply_typography_action(
+        conv,
+        &format!("\\{}", command.name),
+        command.action,
+        cmd,
+        output,
+    );
+}
+
+/// Apply the action of a typography toggle called `name` in notes
+pub(super) fn apply_typography_action(
+    conv: &mut LatexConverter,
+    name: &str,
+    action: TypographyAction,
+    cmd: &CmdItem,
+    output: &mut String,
+) {
+    match action {
+        TypographyAction::Convert(convert) => {
+            let rule = convert(conv, cmd);
+            if conv.state.in_preamble {
+                if !conv.state.typography_rules.contains(&rule) {
+                    conv.state.typography_rules.push(rule);
+                }
+            } else {
+                output.push('\n');
+                output.push_str(&rule);
+                output.push('\n');
+            }
+        }
+        TypographyAction::Ignore => {}
+        TypographyAction::Note(reason) => {
+            let notes = &mut conv.state.typography_notes;
+            if !notes.iter().any(|(noted, _)| noted == name) {
+                notes.push((name.to_string(), reason));
+            }
+        }
+    }
+}
+
+/// End of the value assigned to a TeX parameter, `=1000` or ` 3em`
+fn parameter_value_end(cmd: &CmdItem) -> Option<TextSize> {
+    let mut token = cmd.syntax().last_token()?.next_token();
+    while let Some(current) = token {
+        match current.kind() {
+            SyntaxKind::TokenWhiteSpace => {}
+            SyntaxKind::TokenWord => {
+                let value = current.text().trim_start_matches('=');
+                if value.is_empty() {
+                    // A lone `=`
+                } else if is_parameter_value(value) {
+                    return Some(current.text_range().end());
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+        token = current.next_token();
+    }
+    None
+}
+
+/// A number, with a unit if it is a length
+fn is_parameter_value(value: &str) -> bool {
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &value[number.len()..];
+    let number = number.strip_prefix('-').unwrap_or(number);
+    !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && matches!(
+            unit,
+            "" | "pt" | "em" | "ex" | "bp" | "cm" | "mm" | "in" | "pc" | "sp" | "dd" | "cc"
+        )
+}
+
+/// Report the typography toggles without a Typst equivalent in a single
+/// diagnostic, grouped by why
+pub(super) fn report_typography_notes(conv: &mut LatexConverter) {
+    let notes = std::mem::take(&mut conv.state.typography_notes);
+    if notes.is_empty() {
+        return;
+    }
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (name, reason) in &notes {
+        match groups.iter_mut().find(|(grouped, _)| grouped == reason) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((reason, vec![name])),
+        }
+    }
+    let groups: Vec<String> = groups
+        .iter()
+        .map(|(reason, names)| format!("{} ({})", names.join(", "), reason))
+        .collect();
+    conv.state.add_warning(ConversionWarning::new(
+        WarningKind::Typography,
+        format!(
+            "typography settings not carried over: {}",
+            groups.join("; ")
+        ),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_values() {
+        assert!(is_parameter_value("10000"));
+        assert!(is_parameter_value("3em"));
+        assert!(is_parameter_value("-1.5pt"));
+        assert!(!is_parameter_value("after"));
+        assert!(!is_parameter_value("3apples"));
+        assert!(!is_parameter_value("pt"));
+    }
+
+    #[test]
+    fn test_registry_names_are_unique() {
+        for (i, command) in TYPOGRAPHY_COMMANDS.iter().enumerate() {
+            assert!(
+                TYPOGRAPHY_COMMANDS[i + 1..]
+                    .iter()
+                    .all(|other| other.name != command.name),
+                "{}",
+                command.name
+            );
+        }
+    }
+}
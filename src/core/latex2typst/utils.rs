@@ -3,6 +3,14 @@
 //! This module contains pure utility functions that don't depend on converter state.
 
 use mitex_parser::syntax::{SyntaxElement, SyntaxKind, SyntaxNode};
+use std::fmt::Write;
+
+use crate::data::constants::stretch_to_leading_em;
+use crate::features::refs::{
+    citation_mode_from_latex_command, citation_to_typst, reference_to_typst,
+    reference_type_from_latex_command, Citation, CiteGroup, Reference,
+};
+use crate::utils::files::{find_latex_includes, IncludeCommand};
 
 // =============================================================================
 // Text Processing Utilities
@@ -49,35 +57,614 @@ pub fn to_roman_numeral(num: usize) -> String {
     result
 }
 
+/// Render a `\baselinestretch` factor as a Typst `#set par(leading: ..)` rule
+pub fn line_spacing_rule(stretch: f64) -> String {
+    format!("#set par(leading: {}em)", stretch_to_leading_em(stretch))
+}
+
+/// Typst for `\appendix` and the `appendices` environment: the headings
+/// after it restart at A, and a reference to one reads "Appendix A"
+pub const APPENDIX_RULES: &str = "\n// Appendix\n#counter(heading).update(0)\n\
+    #set heading(numbering: \"A.1\", supplement: [Appendix])\n\n";
+
+/// Typst opening a `subappendices` environment, closed by `]`: the headings
+/// below the current top-level one restart at A, as in "2.A"
+pub const SUBAPPENDIX_RULES: &str = "\n// Appendix\n#[\n\
+    #counter(heading).update((..n) => n.pos().first())\n\
+    #set heading(numbering: \"1.A\", supplement: [Appendix])\n\n";
+
+/// Typst for `\thepage`
+pub const PAGE_NUMBER: &str = "#context counter(page).display()";
+
+/// Typst for `\leftmark`: the last top-level heading up to the end of the page
+pub const LEFT_MARK: &str = "#context { \
+    let hs = query(heading.where(level: 1)).filter(h => h.location().page() <= here().page()); \
+    if hs.len() > 0 { hs.last().body } }";
+
+/// Typst for `\rightmark`: the first heading on the page, or else the last
+/// heading before it
+pub const RIGHT_MARK: &str = "#context { \
+    let hs = query(heading).filter(h => h.location().page() <= here().page()); \
+    let on-page = hs.filter(h => h.location().page() == here().page()); \
+    if on-page.len() > 0 { on-page.first().body } else if hs.len() > 0 { hs.last().body } }";
+
+/// Render `[left, center, right]` header or footer slots as a Typst value
+pub fn page_slots_expr(slots: &[String; 3]) -> String {
+    match slots {
+        [l, c, r] if l.is_empty() && c.is_empty() && r.is_empty() => "none".to_string(),
+        [l, c, r] if c.is_empty() && r.is_empty() => format!("[{}]", l),
+        [l, c, r] if l.is_empty() && r.is_empty() => format!("align(center)[{}]", c),
+        [l, c, r] => format!(
+            "grid(columns: (1fr, auto, 1fr), align(left)[{}], align(center)[{}], align(right)[{}])",
+            l, c, r
+        ),
+    }
+}
+
 // =============================================================================
 // Command Protection/Restoration
 // =============================================================================
 
+/// Placeholder that stands in for `\today` while the document is parsed
+pub const TODAY_PLACEHOLDER: &str = "\u{E000}TODAY\u{E001}";
+
+/// Zero-argument commands that MiTeX would lose, with their placeholders and
+/// final Typst text
+const PROTECTED_COMMANDS: &[(&str, &str, &str)] = &[
+    (
+        "today",
+        TODAY_PLACEHOLDER,
+        "#datetime.today().display(\"[month repr:long] [day padding:none], [year]\")",
+    ),
+    ("LaTeX", "\u{E000}LATEX\u{E001}", "LaTeX"),
+    ("TeX", "\u{E000}TEX\u{E001}", "TeX"),
+    ("XeTeX", "\u{E000}XETEX\u{E001}", "XeTeX"),
+    ("LuaTeX", "\u{E000}LUATEX\u{E001}", "LuaTeX"),
+    ("pdfTeX", "\u{E000}PDFTEX\u{E001}", "pdfTeX"),
+    ("BibTeX", "\u{E000}BIBTEX\u{E001}", "BibTeX"),
+    // amsmath's `\nobreakdash` with the dash it keeps from breaking; Typst
+    // breaks after an en or em dash but never before one
+    ("nobreakdash---", "\u{E000}NBEMDASH\u{E001}", "—"),
+    ("nobreakdash--", "\u{E000}NBENDASH\u{E001}", "–"),
+    ("nobreakdash-", "\u{E000}NBHYPHEN\u{E001}", "\u{2011}"),
+];
+
 /// Protect zero-argument commands from being lost during parsing.
 /// Replaces specific commands with Unicode private use area placeholders that survive the MiTeX parser.
 pub fn protect_zero_arg_commands(input: &str) -> String {
-    let mut result = input.to_string();
     // Use text placeholders wrapped in Private Use Area characters to avoid parser interference.
-    result = result.replace("\\today", "\u{E000}TODAY\u{E001}");
-    result = result.replace("\\LaTeX", "\u{E000}LATEX\u{E001}");
-    result = result.replace("\\TeX", "\u{E000}TEX\u{E001}");
-    result = result.replace("\\XeTeX", "\u{E000}XETEX\u{E001}");
-    result = result.replace("\\LuaTeX", "\u{E000}LUATEX\u{E001}");
-    result = result.replace("\\pdfTeX", "\u{E000}PDFTEX\u{E001}");
-    result = result.replace("\\BibTeX", "\u{E000}BIBTEX\u{E001}");
-    result
+    replace_in_one_pass(input, '\\', |rest| {
+        PROTECTED_COMMANDS
+            .iter()
+            .find_map(|(name, placeholder, _)| {
+                rest.starts_with(name).then_some((name.len(), *placeholder))
+            })
+    })
 }
 
-/// Restore protected commands after conversion
+/// Restore protected commands and verbatim characters after conversion
 pub fn restore_protected_commands(input: &str) -> String {
-    let mut result = input.to_string();
-    result = result.replace("\u{E000}TODAY\u{E001}", "#datetime.today().display()");
-    result = result.replace("\u{E000}LATEX\u{E001}", "LaTeX");
-    result = result.replace("\u{E000}TEX\u{E001}", "TeX");
-    result = result.replace("\u{E000}XETEX\u{E001}", "XeTeX");
-    result = result.replace("\u{E000}LUATEX\u{E001}", "LuaTeX");
-    result = result.replace("\u{E000}PDFTEX\u{E001}", "pdfTeX");
-    result = result.replace("\u{E000}BIBTEX\u{E001}", "BibTeX");
+    let input = &restore_verbatim_chars(input);
+    replace_in_one_pass(input, '\u{E000}', |rest| {
+        PROTECTED_COMMANDS
+            .iter()
+            .find_map(|(_, placeholder, text)| {
+                let tail = placeholder.strip_prefix('\u{E000}')?;
+                rest.starts_with(tail).then_some((tail.len(), *text))
+            })
+    })
+}
+
+/// Characters TeX treats specially, with the private use characters that
+/// stand in for them inside a verbatim argument while the document is parsed
+const VERBATIM_CHARS: &[(char, char)] = &[
+    ('%', '\u{E010}'),
+    ('&', '\u{E011}'),
+    ('#', '\u{E012}'),
+    ('~', '\u{E013}'),
+    ('_', '\u{E014}'),
+    ('^', '\u{E015}'),
+    ('$', '\u{E016}'),
+    ('\\', '\u{E017}'),
+    ('{', '\u{E018}'),
+    ('}', '\u{E019}'),
+];
+
+/// Commands with a verbatim argument
+const VERBATIM_ARG_COMMANDS: &[&str] = &["url", "path", "href", "verb", "lstinline"];
+
+/// Read the arguments of `\url`, `\path`, `\verb`, `\lstinline` and the URL of
+/// `\href` verbatim: a `%` in them does not start a comment, nor does `#`,
+/// `&`, `~` or `_` mean anything to the macro engine or the parser. Their
+/// special characters are replaced until [`restore_verbatim_chars`] puts
+/// them back, and a `|..|` argument is put in braces.
+pub fn protect_verbatim_arguments(input: &str) -> String {
+    if !VERBATIM_ARG_COMMANDS
+        .iter()
+        .any(|name| input.contains(&format!("\\{}", name)))
+    {
+        return input.to_string();
+    }
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('\\') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let name_len = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let name = &after[..name_len];
+        let tail = &after[name_len..];
+        // `\\`, `\%` and other control symbols are copied whole
+        let symbol_len = if name_len == 0 {
+            tail.chars().next().map_or(0, char::len_utf8)
+        } else {
+            0
+        };
+        // Listings that show these commands keep them as written
+        if name == "begin" {
+            if let Some(len) = verbatim_environment_len(&rest[pos..]) {
+                out.push_str(&rest[pos..pos + len]);
+                rest = &rest[pos + len..];
+                continue;
+            }
+        }
+        let skip = match name {
+            "verb" => tail.strip_prefix('*').map_or(0, |_| 1),
+            "lstinline" if tail.starts_with('[') => tail.find(']').map_or(0, |end| end + 1),
+            _ => 0,
+        };
+        let body = match name {
+            "url" | "path" | "href" => verbatim_body(&tail[skip..], true),
+            "verb" | "lstinline" => verbatim_body(&tail[skip..], false),
+            _ => None,
+        };
+        let Some(body) = body else {
+            let len = 1 + name_len + symbol_len;
+            out.push_str(&rest[pos..pos + len]);
+            rest = &rest[pos + len..];
+            continue;
+        };
+        // `\verb*` loses its star; the argument is always braced
+        out.push_str(&rest[pos..pos + 1 + name_len]);
+        if name != "verb" {
+            out.push_str(&tail[..skip]);
+        }
+        let arg = &tail[skip..];
+        out.push('{');
+        out.push_str(&protect_verbatim_chars(&arg[body.clone()]));
+        out.push('}');
+        rest = &arg[body.end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte range of the body of a verbatim argument at the start of `text`: in
+/// braces or, unless `braced_only`, between two copies of one character as
+/// in `\verb|..|`
+fn verbatim_body(text: &str, braced_only: bool) -> Option<std::ops::Range<usize>> {
+    let open = text.chars().next()?;
+    let start = open.len_utf8();
+    if open == '{' {
+        let mut depth = 0;
+        for (i, c) in text[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Some(start..start + i),
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        return None;
+    }
+    if braced_only || open.is_alphanumeric() || open.is_whitespace() {
+        return None;
+    }
+    let len = text[start..].find(open)?;
+    (!text[start..start + len].contains('\n')).then_some(start..start + len)
+}
+
+/// Replace the characters TeX treats specially by their placeholders, so
+/// that `text` reads as plain words until it is restored
+pub fn protect_verbatim_chars(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            VERBATIM_CHARS
+                .iter()
+                .find(|(special, _)| *special == c)
+                .map_or(c, |(_, placeholder)| *placeholder)
+        })
+        .collect()
+}
+
+/// Whether `text` holds characters [`protect_verbatim_chars`] replaced
+pub fn has_verbatim_chars(text: &str) -> bool {
+    text.chars().any(|c| {
+        VERBATIM_CHARS
+            .iter()
+            .any(|(_, placeholder)| *placeholder == c)
+    })
+}
+
+/// Put back protected characters in a word of markup, escaping those that
+/// mean something to Typst, so `\section` prints as written
+pub fn verbatim_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in restore_verbatim_chars(text).chars() {
+        if matches!(c, '\\' | '$' | '#' | '_' | '~' | '*' | '`' | '@' | '<') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Put back the characters [`protect_verbatim_arguments`] replaced
+pub fn restore_verbatim_chars(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            VERBATIM_CHARS
+                .iter()
+                .find(|(_, placeholder)| *placeholder == c)
+                .map_or(c, |(special, _)| *special)
+        })
+        .collect()
+}
+
+/// Text of a `\texttt` argument (with its inner braces) as it is printed:
+/// escaped characters such as `\{` become literal, a group that is not
+/// the argument of a command keeps its braces, and commands are dropped
+/// with their argument unwrapped, so `\texttt{\texttt{x}}` is just `x`
+pub fn monospace_text(raw: &str) -> String {
+    let mut text = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    // Whether each open group was the argument of a command
+    let mut groups: Vec<bool> = Vec::new();
+    let mut after_command = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let name: String =
+                    std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_alphabetic())).collect();
+                after_command = false;
+                if name.is_empty() {
+                    match chars.next() {
+                        Some('\\') => text.push('\n'),
+                        Some(c) => text.push(c),
+                        None => text.push('\\'),
+                    }
+                    continue;
+                }
+                while chars.next_if_eq(&' ').is_some() {}
+                let literal = match name.as_str() {
+                    "textbackslash" => Some('\\'),
+                    "textasciitilde" => Some('~'),
+                    "textasciicircum" => Some('^'),
+                    "textunderscore" => Some('_'),
+                    "textbraceleft" => Some('{'),
+                    "textbraceright" => Some('}'),
+                    _ => None,
+                };
+                match literal {
+                    Some(literal) => {
+                        text.push(literal);
+                        // `\textbackslash{}`
+                        if chars.peek() == Some(&'{') {
+                            let mut lookahead = chars.clone();
+                            lookahead.next();
+                            if lookahead.peek() == Some(&'}') {
+                                chars.next();
+                                chars.next();
+                            }
+                        }
+                    }
+                    None => after_command = true,
+                }
+            }
+            '{' => {
+                groups.push(after_command);
+                if !after_command {
+                    text.push('{');
+                }
+            }
+            '}' => {
+                let argument = groups.pop().unwrap_or(false);
+                if !argument {
+                    text.push('}');
+                }
+                // A further argument of the same command
+                after_command = argument && chars.peek() == Some(&'{');
+            }
+            '~' => {
+                after_command = false;
+                text.push(' ');
+            }
+            c => {
+                after_command = false;
+                text.push(c);
+            }
+        }
+    }
+    text
+}
+
+/// Inline raw text, as a `#raw(..)` call when backticks rule out the
+/// backtick form
+pub fn raw_inline(text: &str) -> String {
+    if text.contains('`') {
+        format!(
+            "#raw(\"{}\")",
+            text.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Split argument text into runs of text and of inline math, `$..$` or
+/// `\(..\)`; the math is given without its delimiters and flagged `true`.
+/// Math that is not closed is kept as text.
+pub fn split_inline_math(text: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    let bytes = text.as_bytes();
+    while pos < bytes.len() {
+        let (open, close) = match bytes[pos] {
+            b'$' => (1, "$"),
+            b'\\' if bytes.get(pos + 1) == Some(&b'(') => (2, "\\)"),
+            b'\\' => {
+                // An escaped character such as `\$` is text
+                pos += 2;
+                continue;
+            }
+            _ => {
+                pos += 1;
+                continue;
+            }
+        };
+        let Some(end) = text[pos + open..].find(close).map(|end| pos + open + end) else {
+            break;
+        };
+        if start < pos {
+            parts.push((&text[start..pos], false));
+        }
+        parts.push((&text[pos + open..end], true));
+        pos = end + close.len();
+        start = pos;
+    }
+    if start < text.len() {
+        parts.push((&text[start..], false));
+    }
+    parts
+}
+
+/// Replace text following each `marker` in a single forward pass.
+///
+/// `replacement` receives the input after the marker and returns how many
+/// bytes it covers together with the text that replaces the marker and them.
+fn replace_in_one_pass<'a>(
+    input: &str,
+    marker: char,
+    replacement: impl Fn(&str) -> Option<(usize, &'a str)>,
+) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut last = 0;
+    for (pos, _) in input.match_indices(marker) {
+        if pos < last {
+            continue;
+        }
+        let after = pos + marker.len_utf8();
+        if let Some((len, text)) = replacement(&input[after..]) {
+            result.push_str(&input[last..pos]);
+            result.push_str(text);
+            last = after + len;
+        }
+    }
+    result.push_str(&input[last..]);
+    result
+}
+
+/// Environments whose bodies are copied without looking inside
+const VERBATIM_ENVIRONMENTS: &[&str] = &["verbatim", "Verbatim", "lstlisting", "minted", "comment"];
+
+/// Length of the verbatim environment `text` starts with, from its
+/// `\begin{..}` to the end of its `\end{..}` or of the text
+fn verbatim_environment_len(text: &str) -> Option<usize> {
+    let (env, _) = text.strip_prefix("\\begin{")?.split_once('}')?;
+    if !VERBATIM_ENVIRONMENTS.contains(&env) {
+        return None;
+    }
+    let end = format!("\\end{{{}}}", env);
+    Some(text.find(&end).map_or(text.len(), |pos| pos + end.len()))
+}
+
+/// Pair every `\left` with a `\right` in the same group, as TeX requires.
+///
+/// A group ends at a brace, an environment, a math delimiter, a cell (`&`)
+/// or a row (`\\`); a `\left` still open there is closed with `\right.`, and a
+/// `\right` with no `\left` in its group gets a `\left.` where the group
+/// starts. Sources that split a sized pair across rows of an `align` then
+/// parse into pairs that each convert to a balanced `lr(..)`.
+pub fn balance_left_right(input: &str) -> String {
+    if !input.contains("\\left") && !input.contains("\\right") {
+        return input.to_string();
+    }
+
+    /// Where a group starts in the output, and how many `\left` are open in it
+    struct Group {
+        start: usize,
+        open: usize,
+    }
+    fn close(group: &mut Group, out: &mut String) {
+        for _ in 0..group.open {
+            out.push_str("\\right.");
+        }
+        group.open = 0;
+    }
+
+    let mut out = String::with_capacity(input.len() + 16);
+    let mut groups = vec![Group { start: 0, open: 0 }];
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        let group = groups
+            .last_mut()
+            .expect("the outermost group is never popped");
+        let len = match c {
+            '%' => rest.find('\n').unwrap_or(rest.len()),
+            '{' => {
+                out.push('{');
+                groups.push(Group {
+                    start: out.len(),
+                    open: 0,
+                });
+                rest = &rest[1..];
+                continue;
+            }
+            '}' => {
+                close(group, &mut out);
+                if groups.len() > 1 {
+                    groups.pop();
+                }
+                1
+            }
+            '$' | '&' => {
+                close(group, &mut out);
+                let len = if rest.starts_with("$$") { 2 } else { 1 };
+                out.push_str(&rest[..len]);
+                group.start = out.len();
+                rest = &rest[len..];
+                continue;
+            }
+            '\\' => {
+                let name_len = rest[1..]
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len() - 1);
+                let name = &rest[1..1 + name_len];
+                match name {
+                    "" => {
+                        let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+                        if matches!(&rest[1..1 + escaped], "\\" | "(" | ")" | "[" | "]") {
+                            close(group, &mut out);
+                            out.push_str(&rest[..1 + escaped]);
+                            group.start = out.len();
+                            rest = &rest[1 + escaped..];
+                            continue;
+                        }
+                        1 + escaped
+                    }
+                    "left" | "right" => {
+                        if name == "left" {
+                            group.open += 1;
+                        } else if group.open > 0 {
+                            group.open -= 1;
+                        } else {
+                            out.insert_str(group.start, "\\left.");
+                        }
+                        // The delimiter is copied as is, so `\left\{` opens no group
+                        let after = &rest[1 + name_len..];
+                        let spaces = after.len() - after.trim_start().len();
+                        let delim = &after[spaces..];
+                        let delim_len = match delim.strip_prefix('\\') {
+                            Some(cmd) => {
+                                1 + cmd
+                                    .find(|c: char| !c.is_ascii_alphabetic())
+                                    .filter(|&n| n > 0)
+                                    .unwrap_or_else(|| cmd.chars().next().map_or(0, char::len_utf8))
+                            }
+                            None => delim.chars().next().map_or(0, char::len_utf8),
+                        };
+                        1 + name_len + spaces + delim_len
+                    }
+                    "begin" => {
+                        let env = rest[6..]
+                            .strip_prefix('{')
+                            .and_then(|s| s.split_once('}'))
+                            .map(|(env, _)| env);
+                        match env {
+                            Some(env) if VERBATIM_ENVIRONMENTS.contains(&env) => {
+                                let end = format!("\\end{{{}}}", env);
+                                rest.find(&end).map_or(rest.len(), |pos| pos + end.len())
+                            }
+                            Some(env) => {
+                                out.push_str(&rest[..8 + env.len()]);
+                                groups.push(Group {
+                                    start: out.len(),
+                                    open: 0,
+                                });
+                                rest = &rest[8 + env.len()..];
+                                continue;
+                            }
+                            None => 6,
+                        }
+                    }
+                    "end" => {
+                        close(group, &mut out);
+                        if groups.len() > 1 {
+                            groups.pop();
+                        }
+                        4
+                    }
+                    "verb" => {
+                        let delim = rest[5..].chars().next();
+                        delim
+                            .and_then(|d| {
+                                let close = if d == '{' { '}' } else { d };
+                                rest[5 + d.len_utf8()..].find(close)
+                            })
+                            .map_or(rest.len(), |pos| {
+                                5 + 2 * delim.map_or(0, char::len_utf8) + pos
+                            })
+                    }
+                    _ => 1 + name_len,
+                }
+            }
+            _ => c.len_utf8(),
+        };
+        let len = len.min(rest.len());
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    while let Some(mut group) = groups.pop() {
+        close(&mut group, &mut out);
+    }
+    out
+}
+
+/// Marks where the text flowing around a `wrapfigure` starts: the paragraph
+/// after it becomes the body of the `#wrap-content(..)[..]` call
+pub const WRAP_BODY_MARKER: &str = "\u{E000}WRAPBODY\u{E001}";
+
+/// Close each `#wrap-content(..)[` opened at a [`WRAP_BODY_MARKER`] after the
+/// paragraph that follows it
+pub fn close_wrapped_paragraphs(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find(WRAP_BODY_MARKER) {
+        result.push_str(&rest[..pos]);
+        let body = &rest[pos + WRAP_BODY_MARKER.len()..];
+
+        // The paragraph ends at the first blank line after some text
+        let mut end = body.len();
+        let mut offset = 0;
+        let mut seen_text = false;
+        for line in body.split_inclusive('\n') {
+            if !line.trim().is_empty() {
+                seen_text = true;
+            } else if seen_text {
+                end = offset;
+                break;
+            }
+            offset += line.len();
+        }
+
+        result.push('\n');
+        result.push_str(body[..end].trim());
+        result.push_str("\n]\n");
+        rest = &body[end..];
+    }
+    result.push_str(rest);
     result
 }
 
@@ -179,6 +766,27 @@ pub fn extract_node_text_with_braces(node: &SyntaxNode) -> String {
             SyntaxElement::Token(t) => {
                 text.push_str(t.text());
             }
+            // The parser keeps only the name of `\begin{..}` and `\end{..}`
+            SyntaxElement::Node(n)
+                if matches!(n.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd) =>
+            {
+                let delimiter = if n.kind() == SyntaxKind::ItemBegin {
+                    "begin"
+                } else {
+                    "end"
+                };
+                for part in n.children_with_tokens() {
+                    match part {
+                        SyntaxElement::Token(t) if t.kind() == SyntaxKind::TokenCommandSym => {
+                            let _ = write!(text, "\\{}{{{}}}", delimiter, t.text());
+                        }
+                        SyntaxElement::Token(t) => text.push_str(t.text()),
+                        SyntaxElement::Node(n) => {
+                            text.push_str(&extract_node_text_with_braces(&n));
+                        }
+                    }
+                }
+            }
             SyntaxElement::Node(n) => {
                 text.push_str(&extract_node_text_with_braces(&n));
             }
@@ -245,13 +853,21 @@ pub fn extract_arg_content_with_braces(node: &SyntaxNode) -> String {
 /// Extract inner content of a curly/bracket node, skipping its braces
 pub fn extract_curly_inner_content(node: &SyntaxNode) -> String {
     let mut content = String::new();
+    let first = node.first_child_or_token();
+    let last = node.last_child_or_token();
     for child in node.children_with_tokens() {
         match child.kind() {
-            // Skip the braces of this curly node
-            SyntaxKind::TokenLBrace
-            | SyntaxKind::TokenRBrace
-            | SyntaxKind::TokenLBracket
-            | SyntaxKind::TokenRBracket => continue,
+            // Skip the braces of this curly node; a stray `]` inside is text
+            SyntaxKind::TokenLBrace | SyntaxKind::TokenLBracket
+                if Some(&child) == first.as_ref() =>
+            {
+                continue
+            }
+            SyntaxKind::TokenRBrace | SyntaxKind::TokenRBracket
+                if Some(&child) == last.as_ref() =>
+            {
+                continue
+            }
             _ => {
                 if let SyntaxElement::Token(t) = child {
                     content.push_str(t.text());
@@ -289,13 +905,373 @@ pub fn contains_top_level_separator(text: &str, separator: char) -> bool {
     false
 }
 
+/// Split `\thanks{..}` notes out of a title or author, returning the remaining
+/// text and the raw note bodies in order
+pub fn extract_thanks(text: &str) -> (String, Vec<String>) {
+    let mut rest = String::new();
+    let mut notes = Vec::new();
+    let mut remaining = text;
+
+    while let Some(pos) = remaining.find("\\thanks") {
+        rest.push_str(&remaining[..pos]);
+        let after = remaining[pos + "\\thanks".len()..].trim_start();
+        let Some(body) = after.strip_prefix('{') else {
+            remaining = after;
+            continue;
+        };
+        let mut depth = 1;
+        let end = body
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .map_or(body.len(), |(i, _)| i);
+        notes.push(body[..end].to_string());
+        remaining = body.get(end + 1..).unwrap_or("");
+    }
+    rest.push_str(remaining);
+
+    (rest, notes)
+}
+
+/// Display math environments, which an inline argument can only show inline
+const DISPLAY_MATH_ENVS: &[&str] = &[
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "flalign",
+    "flalign*",
+    "eqnarray",
+    "eqnarray*",
+    "displaymath",
+];
+
+/// Turn the display math of an inline argument such as a caption into
+/// inline `$..$` math, without its labels, tags and alignment points, and
+/// with its lines joined by a `\quad`. Also returns the opening delimiter of
+/// each display found, in order.
+pub fn inline_display_math(text: &str) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find(['\\', '$']) {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let display = if let Some(after) = rest.strip_prefix("$$") {
+            after
+                .find("$$")
+                .map(|end| ("$$".to_string(), &after[..end], end + 4))
+        } else if let Some(after) = rest.strip_prefix("\\[") {
+            after
+                .find("\\]")
+                .map(|end| ("\\[".to_string(), &after[..end], end + 4))
+        } else if let Some(after) = rest.strip_prefix("\\begin{") {
+            after.split_once('}').and_then(|(name, body)| {
+                let end = body.find(&format!("\\end{{{}}}", name))?;
+                DISPLAY_MATH_ENVS.contains(&name).then(|| {
+                    let delimiter = format!("\\begin{{{}}}", name);
+                    let len = delimiter.len() + end + name.len() + 6;
+                    (delimiter, &body[..end], len)
+                })
+            })
+        } else {
+            None
+        };
+        match display {
+            Some((delimiter, body, len)) => {
+                let _ = write!(result, "${}$", inline_math_body(body));
+                found.push(delimiter);
+                rest = &rest[len..];
+            }
+            None => {
+                // A control symbol such as `\\` or `\$` stays whole
+                let len = match rest.strip_prefix('\\') {
+                    Some(after) => after.chars().next().map_or(1, |c| 1 + c.len_utf8()),
+                    None => 1,
+                };
+                result.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    result.push_str(rest);
+    (result, found)
+}
+
+/// The body of a display equation as the body of an inline one
+fn inline_math_body(body: &str) -> String {
+    let mut body = body.to_string();
+    while let Some(pos) = body.find("\\label{") {
+        let end = body[pos..]
+            .find('}')
+            .map_or(body.len(), |end| pos + end + 1);
+        body.replace_range(pos..end, "");
+    }
+    let body = body
+        .replace("\\nonumber", "")
+        .replace("\\notag", "")
+        .replace("\\\\", " \\quad ")
+        .replace('&', "");
+    body.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Class of a LaTeX source from its `\documentclass`, if it has one
+pub fn source_document_class(source: &str) -> Option<&str> {
+    let pos = source.find("\\documentclass")?;
+    let class = source[pos..].split_once('{')?.1.split_once('}')?.0;
+    Some(class.trim())
+}
+
+/// Take the files a document embeds with `filecontents` or `filecontents*`
+/// out of it, as (name, contents) pairs. Each block leaves as many line
+/// endings as it spanned, so the lines after it keep their numbers.
+pub fn extract_filecontents(source: &str) -> (String, Vec<(String, String)>) {
+    const BEGIN: &str = "\\begin{filecontents";
+    let mut rest = source;
+    let mut text = String::with_capacity(source.len());
+    let mut files = Vec::new();
+    while let Some(pos) = rest.find(BEGIN) {
+        let after = &rest[pos + BEGIN.len()..];
+        let starred = after.starts_with('*');
+        let end_tag = if starred {
+            "\\end{filecontents*}"
+        } else {
+            "\\end{filecontents}"
+        };
+        // `}`, then `[overwrite]` or other options, then `{name}`
+        let header = after
+            .strip_prefix('*')
+            .unwrap_or(after)
+            .strip_prefix('}')
+            .map(|header| header.trim_start())
+            .map(|header| match header.strip_prefix('[') {
+                Some(options) => options.split_once(']').map_or("", |(_, rest)| rest),
+                None => header,
+            });
+        let Some((name, body)) = header
+            .and_then(|header| header.trim_start().strip_prefix('{'))
+            .and_then(|header| header.split_once('}'))
+        else {
+            text.push_str(&rest[..pos + BEGIN.len()]);
+            rest = after;
+            continue;
+        };
+        let Some(end) = body.find(end_tag) else {
+            text.push_str(&rest[..pos + BEGIN.len()]);
+            rest = after;
+            continue;
+        };
+        // The contents start on the line after `\begin{filecontents}`
+        let contents = &body[..end];
+        let contents = contents
+            .split_once('\n')
+            .map_or("", |(_, contents)| contents);
+        files.push((name.trim().to_string(), contents.to_string()));
+
+        let block_end = rest.len() - body.len() + end + end_tag.len();
+        text.push_str(&rest[..pos]);
+        text.extend(rest[pos..block_end].matches('\n').map(|_| '\n'));
+        rest = &rest[block_end..];
+    }
+    text.push_str(rest);
+    (text, files)
+}
+
+/// Includes deeper than this are left to the converter, which also stops
+/// a file that inputs itself
+const MAX_INPUT_DEPTH: usize = 16;
+
+/// Put the contents of the files `\\input` or `\\include` reads where
+/// they are read, before the document is parsed, so an included file may
+/// hold part of an environment, such as the rows of a tabular or its
+/// `\end{tabular}`. `read` gives the contents of an included path.
+/// Commented-out includes, figure files and unreadable files are left to
+/// the converter.
+pub fn inline_input_files(source: &str, read: &dyn Fn(&str) -> Option<String>) -> String {
+    inline_input_files_at(source, read, 0)
+}
+
+fn inline_input_files_at(
+    source: &str,
+    read: &dyn Fn(&str) -> Option<String>,
+    depth: usize,
+) -> String {
+    if depth >= MAX_INPUT_DEPTH {
+        return source.to_string();
+    }
+    let verbatim = verbatim_regions(source);
+    let mut text = String::with_capacity(source.len());
+    let mut last = 0;
+    for (start, end, command) in find_latex_includes(source) {
+        if !matches!(
+            command,
+            IncludeCommand::Input(_) | IncludeCommand::Include(_)
+        ) {
+            continue;
+        }
+        let line_start = source[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        if has_comment(&source[line_start..start])
+            || verbatim.iter().any(|region| region.contains(&start))
+        {
+            continue;
+        }
+        let Some(contents) = read(command.path().trim()) else {
+            continue;
+        };
+        if is_figure_file(&contents) {
+            continue;
+        }
+        let contents = inline_input_files_at(&contents, read, depth + 1);
+        let contents = contents.trim_end();
+        text.push_str(&source[last..start]);
+        match command {
+            IncludeCommand::Include(_) => {
+                let _ = write!(text, "\\clearpage\n{}\n\\clearpage", contents);
+            }
+            _ => {
+                text.push_str(contents);
+                // The file ends a line, which may be a comment
+                if contents.rsplit('\n').next().is_some_and(has_comment) {
+                    text.push('\n');
+                }
+            }
+        }
+        last = end;
+    }
+    text.push_str(&source[last..]);
+    text
+}
+
+/// Whether `line` holds a `%` that starts a comment, not an escaped `\%`
+fn has_comment(line: &str) -> bool {
+    line.match_indices('%').any(|(pos, _)| {
+        line[..pos]
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count()
+            % 2
+            == 0
+    })
+}
+
+/// Byte ranges of `source` shown as written: verbatim environments and the
+/// arguments of `\verb` and `\lstinline`
+fn verbatim_regions(source: &str) -> Vec<std::ops::Range<usize>> {
+    let mut regions = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = source[pos..].find('\\') {
+        let start = pos + found;
+        let text = &source[start..];
+        // `\\` is a control symbol, not the start of a command
+        if text.starts_with("\\\\") {
+            pos = start + 2;
+            continue;
+        }
+        let len = verbatim_environment_len(text).or_else(|| {
+            let arg = text
+                .strip_prefix("\\verb")
+                .map(|arg| arg.strip_prefix('*').unwrap_or(arg))
+                .or_else(|| {
+                    let arg = text.strip_prefix("\\lstinline")?;
+                    match arg.strip_prefix('[') {
+                        Some(options) => options.split_once(']').map(|(_, arg)| arg),
+                        None => Some(arg),
+                    }
+                })?;
+            let body = verbatim_body(arg, false)?;
+            Some(text.len() - arg.len() + body.end + 1)
+        });
+        match len {
+            Some(len) => {
+                regions.push(start..start + len);
+                pos = start + len;
+            }
+            None => pos = start + 1,
+        }
+    }
+    regions
+}
+
+/// Whether an included file is a figure that `\input` shows as a whole: a
+/// document of its own, such as a `standalone` picture, or a bare
+/// `tikzpicture`
+fn is_figure_file(source: &str) -> bool {
+    let body = source.trim();
+    source_document_class(source).is_some()
+        || (body.starts_with("\\begin{tikzpicture}") && body.ends_with("\\end{tikzpicture}"))
+}
+
+/// The picture of a TikZ figure file: a `standalone` document or a bare
+/// `tikzpicture` without a preamble. Documents of other classes and files
+/// without a picture give `None`.
+pub fn standalone_tikz_picture(source: &str) -> Option<&str> {
+    const BEGIN: &str = "\\begin{tikzpicture}";
+    const END: &str = "\\end{tikzpicture}";
+    let mut body = source;
+    if let Some(class) = source_document_class(source) {
+        if class != "standalone" {
+            return None;
+        }
+        if let Some(start) = source.find("\\begin{document}") {
+            let end = source.rfind("\\end{document}").unwrap_or(source.len());
+            body = source.get(start..end)?;
+        }
+    }
+    let start = body.find(BEGIN)?;
+    let end = body.rfind(END)? + END.len();
+    body.get(start..end)
+}
+
 // =============================================================================
 // Caption Text Conversion
 // =============================================================================
 
+/// Prenote and postnote of a citation from its `[..]` optional arguments:
+/// a single one is the postnote
+pub fn optional_args_to_prefix_suffix(
+    optional_args: &[String],
+) -> (Option<String>, Option<String>) {
+    let cleaned: Vec<String> = optional_args
+        .iter()
+        .map(|value| {
+            value
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .trim()
+                .to_string()
+        })
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    match cleaned.as_slice() {
+        [] => (None, None),
+        [only] => (None, Some(only.clone())),
+        [prefix, suffix, ..] => (Some(prefix.clone()), Some(suffix.clone())),
+    }
+}
+
 /// Convert caption/title/author text that may contain inline math and formatting commands
 /// Handles LaTeX math mode ($...$) and text formatting commands
 pub fn convert_caption_text(text: &str) -> String {
+    convert_caption_text_with(text, &|math| {
+        format!("${}$", super::latex_math_to_typst(math))
+    })
+}
+
+/// [`convert_caption_text`] with the inline math (without its `$`) converted by `convert_math`
+pub fn convert_caption_text_with(text: &str, convert_math: &dyn Fn(&str) -> String) -> String {
     let mut result = String::new();
     let mut chars = text.chars().peekable();
 
@@ -310,11 +1286,17 @@ pub fn convert_caption_text(text: &str) -> String {
                 }
                 math_content.push(chars.next().unwrap());
             }
-            // Convert the math content
-            let converted = super::latex_math_to_typst(&math_content);
-            result.push('$');
-            result.push_str(&converted);
-            result.push('$');
+            result.push_str(&convert_math(&math_content));
+        } else if ch == '\\' && chars.next_if_eq(&'(').is_some() {
+            // `\(..\)` is inline math too
+            let mut math_content = String::new();
+            while let Some(next) = chars.next() {
+                if next == '\\' && chars.next_if_eq(&')').is_some() {
+                    break;
+                }
+                math_content.push(next);
+            }
+            result.push_str(&convert_math(&math_content));
         } else if ch == '\\' {
             // Handle backslash commands in text mode
             let mut cmd = String::new();
@@ -325,9 +1307,28 @@ pub fn convert_caption_text(text: &str) -> String {
                     break;
                 }
             }
+            // Control symbols: `\\`, `\&`, `\_`, ..
+            if cmd.is_empty() {
+                if let Some(symbol) =
+                    chars.next_if(|c| matches!(c, '\\' | '&' | '%' | '_' | '#' | '$' | '{' | '}'))
+                {
+                    cmd.push(symbol);
+                }
+            }
+
+            let reference = reference_type_from_latex_command(&cmd);
+            let citation = citation_mode_from_latex_command(&cmd);
+            let mut optional_args = Vec::new();
+            while citation.is_some() && chars.peek() == Some(&'[') {
+                chars.next();
+                optional_args.push(chars.by_ref().take_while(|&c| c != ']').collect());
+            }
 
             // Check if this command takes a braced argument
-            let has_arg = crate::data::symbols::is_caption_text_command(&cmd);
+            let has_arg = crate::data::symbols::is_caption_text_command(&cmd)
+                || reference.is_some()
+                || citation.is_some()
+                || cmd == "label";
 
             // Extract argument content if present
             let arg_content = if has_arg {
@@ -362,58 +1363,88 @@ pub fn convert_caption_text(text: &str) -> String {
                 None
             };
 
+            // References and citations; a label belongs after the figure
+            if let (Some(ref_type), Some(keys)) = (reference, &arg_content) {
+                let references: Vec<String> = keys
+                    .split(',')
+                    .map(|key| {
+                        reference_to_typst(&Reference {
+                            target: sanitize_label(key.trim()),
+                            ref_type,
+                        })
+                    })
+                    .collect();
+                result.push_str(&references.join(", "));
+                continue;
+            }
+            if let (Some(mode), Some(keys)) = (citation, &arg_content) {
+                let (prefix, suffix) = optional_args_to_prefix_suffix(&optional_args);
+                let mut group = CiteGroup::new();
+                group.prefix = prefix;
+                group.suffix = suffix;
+                for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                    group.push(Citation::with_mode(key.to_string(), mode));
+                }
+                result.push_str(&citation_to_typst(&group));
+                continue;
+            }
+
             // Convert common text-mode commands
             match cmd.as_str() {
+                "label" => {}
                 "textbf" | "bf" => {
                     result.push('*');
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                     result.push('*');
                 }
                 "textit" | "it" | "emph" => {
-                    result.push('_');
-                    if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                    let content = arg_content
+                        .map(|content| convert_caption_text_with(&content, convert_math))
+                        .unwrap_or_default();
+                    // Underscores cannot nest
+                    if content.contains('_') {
+                        result.push_str(&format!("#emph[{}]", content));
+                    } else {
+                        result.push('_');
+                        result.push_str(&content);
+                        result.push('_');
                     }
-                    result.push('_');
                 }
                 "texttt" => {
-                    result.push('`');
-                    if let Some(content) = arg_content {
-                        result.push_str(&content); // Don't recurse for monospace
-                    }
-                    result.push('`');
+                    let content = arg_content.unwrap_or_default();
+                    result.push_str(&raw_inline(&monospace_text(&content)));
                 }
                 "textsc" => {
                     result.push_str("#smallcaps[");
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                     result.push(']');
                 }
                 "underline" => {
                     result.push_str("#underline[");
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                     result.push(']');
                 }
                 "textrm" | "text" | "mbox" | "hbox" => {
                     // Just include the content
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                 }
                 "textsf" => {
                     result.push_str("#text(font: \"sans-serif\")[");
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                     result.push(']');
                 }
                 // Date/time commands
-                "today" => result.push_str("#datetime.today().display()"),
+                "today" => result.push_str(TODAY_PLACEHOLDER),
 
                 // LaTeX logo commands
                 "LaTeX" => result.push_str("LaTeX"),
@@ -431,7 +1462,16 @@ pub fn convert_caption_text(text: &str) -> String {
                 "$" => result.push_str("\\$"), // $ needs escaping in Typst
                 "{" => result.push('{'),
                 "}" => result.push('}'),
-                "\\" => result.push_str("\\ "), // line break
+                "\\" => {
+                    // A line break within the same block; its `*` and
+                    // `[..]` spacing have no place in a single block
+                    chars.next_if_eq(&'*');
+                    if chars.next_if_eq(&'[').is_some() {
+                        chars.by_ref().take_while(|&c| c != ']').for_each(drop);
+                    }
+                    result.push_str("\\ ");
+                }
+                "newline" | "linebreak" => result.push_str("\\ "),
                 "" => {
                     // Just a backslash followed by non-alpha (like \\ or \&)
                     // Already consumed, do nothing
@@ -440,7 +1480,7 @@ pub fn convert_caption_text(text: &str) -> String {
                     // For unknown commands, skip the backslash (don't output raw LaTeX)
                     // If there's an argument, output its content
                     if let Some(content) = arg_content {
-                        result.push_str(&convert_caption_text(&content));
+                        result.push_str(&convert_caption_text_with(&content, convert_math));
                     }
                     // Otherwise, just skip the unknown command
                 }
@@ -4,6 +4,9 @@
 
 use std::collections::HashMap;
 
+use crate::core::latex2typst::UnknownEnvironmentPolicy;
+use crate::utils::{LineEnding, ProgressCallback};
+
 /// Controls how the document wrapper (`\documentclass` + `\usepackage`s +
 /// `\begin{document}` ... `\end{document}`) is emitted around the body
 /// when `T2LOptions::full_document` is `true`.
@@ -44,6 +47,73 @@ impl DocumentWrapperMode {
     }
 }
 
+/// LaTeX environment used for Typst code blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CodeBackend {
+    /// Plain `verbatim`, no highlighting
+    Verbatim,
+    /// `lstlisting` from the listings package; languages listings does not
+    /// define fall back to `verbatim`
+    #[default]
+    Listings,
+    /// `minted` (needs `-shell-escape` and Pygments)
+    Minted,
+}
+
+/// LaTeX construct that positions a `#place(..)`d element on the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaceBackend {
+    /// A `textblock*` environment from textpos in absolute mode
+    #[default]
+    Textpos,
+    /// A node in a `remember picture, overlay` tikzpicture
+    TikZ,
+}
+
+/// Delimiters of inline math
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InlineMathDelim {
+    /// `$..$`
+    #[default]
+    Dollar,
+    /// `\(..\)`
+    Paren,
+}
+
+impl InlineMathDelim {
+    /// Opening and closing delimiter
+    pub fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            InlineMathDelim::Dollar => ("$", "$"),
+            InlineMathDelim::Paren => ("\\(", "\\)"),
+        }
+    }
+}
+
+/// Delimiters of unnumbered display math; aligned and numbered equations
+/// keep their environments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayMathDelim {
+    /// `\[..\]`
+    #[default]
+    Brackets,
+    /// `$$..$$`
+    DoubleDollar,
+    /// An amsmath `equation*` environment
+    Equation,
+}
+
+impl DisplayMathDelim {
+    /// Opening and closing delimiter, each on a line of its own
+    pub fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            DisplayMathDelim::Brackets => ("\\[\n", "\n\\]"),
+            DisplayMathDelim::DoubleDollar => ("$$\n", "\n$$"),
+            DisplayMathDelim::Equation => ("\\begin{equation*}\n", "\n\\end{equation*}"),
+        }
+    }
+}
+
 /// Options for Typst to LaTeX conversion
 #[derive(Debug, Clone)]
 pub struct T2LOptions {
@@ -63,6 +133,72 @@ pub struct T2LOptions {
     /// Controls the LaTeX document wrapper when `full_document` is true.
     /// Default: [`DocumentWrapperMode::Default`].
     pub wrapper: DocumentWrapperMode,
+    /// Evaluate embedded code (`#{...}`, `#(...)`) with a scoped MiniEval
+    /// even outside the `with_eval` pipeline.
+    /// Default: true.
+    pub eval_inline_code: bool,
+    /// Emit `\autoref` instead of `\ref` for plain `@label` references.
+    /// Default: false.
+    pub use_autoref: bool,
+    /// Emit a custom `list(marker: ..)` as an enumitem `label=` option on the
+    /// environment instead of repeating it as `\item[..]` on every item.
+    /// Default: false.
+    pub use_enumitem: bool,
+    /// Spell accented letters with TeX accent commands (`é` → `\'e`) for
+    /// engines without UTF-8 input.
+    /// Default: false.
+    pub ascii_accents: bool,
+    /// Keep typographic quotes, dashes and the ellipsis as Unicode characters
+    /// instead of spelling them ``` ``..'' ```, `--`, `---` and `\ldots`.
+    /// Default: false.
+    pub keep_unicode: bool,
+    /// Write `abs(..)` and `norm(..)` as the physics package's `\abs{..}` and
+    /// `\norm{..}` instead of `\left\lvert .. \right\rvert` delimiters.
+    /// Default: false.
+    pub use_physics_package: bool,
+    /// Write a `block` with a fill or a stroke as a `tcolorbox` with its
+    /// colors, frame and corners, a bold first line becoming the title;
+    /// otherwise only the content of the block is kept.
+    /// Default: false.
+    pub tcolorbox_blocks: bool,
+    /// Environment for code blocks with a language.
+    /// Default: [`CodeBackend::Listings`].
+    pub code_backend: CodeBackend,
+    /// Top-level `#set`/`#show` rules with no LaTeX counterpart: dropped while
+    /// the content they style is converted (`ConvertBody`, `Drop`), or also
+    /// kept as `%` comments at the top of the output (`RawBlock`). Each one
+    /// is reported as a warning by the diagnostics API.
+    /// Default: [`UnknownEnvironmentPolicy::ConvertBody`].
+    pub unknown_rule_policy: UnknownEnvironmentPolicy,
+    /// Line ending of the output. Input is always read with `\n` endings.
+    /// Default: [`LineEnding::Lf`].
+    pub line_ending: LineEnding,
+    /// Write the cells of a table filled from `csv(..)` into the tabular,
+    /// reading the file through the resolver given to
+    /// [`typst_to_latex_with_file_resolver`]; otherwise the table becomes a
+    /// csvsimple `\csvautotabular` that loads the file.
+    /// Default: false.
+    ///
+    /// [`typst_to_latex_with_file_resolver`]: super::typst_to_latex_with_file_resolver
+    pub materialize_data: bool,
+    /// How `#place(..)` positions its element relative to the page.
+    /// Default: [`PlaceBackend::Textpos`].
+    pub place_backend: PlaceBackend,
+    /// Added to the level of every heading before it is mapped to a
+    /// sectioning command; `-1` turns `==` into `\section`.
+    /// Default: 0.
+    pub heading_offset: i8,
+    /// Delimiters of inline math, in text, captions, footnotes and table
+    /// cells alike.
+    /// Default: [`InlineMathDelim::Dollar`].
+    pub inline_math_delim: InlineMathDelim,
+    /// Delimiters of unnumbered display math.
+    /// Default: [`DisplayMathDelim::Brackets`].
+    pub display_math_delim: DisplayMathDelim,
+    /// Told the phase, bytes done and blocks converted while a document
+    /// is converted by the diagnostics API.
+    /// Default: None.
+    pub progress: Option<ProgressCallback>,
 }
 
 impl Default for T2LOptions {
@@ -75,6 +211,22 @@ impl Default for T2LOptions {
             math_only: false,
             block_math_mode: true,
             wrapper: DocumentWrapperMode::Default,
+            eval_inline_code: true,
+            use_autoref: false,
+            use_enumitem: false,
+            ascii_accents: false,
+            keep_unicode: false,
+            use_physics_package: false,
+            tcolorbox_blocks: false,
+            code_backend: CodeBackend::Listings,
+            unknown_rule_policy: UnknownEnvironmentPolicy::ConvertBody,
+            line_ending: LineEnding::Lf,
+            materialize_data: false,
+            place_backend: PlaceBackend::Textpos,
+            heading_offset: 0,
+            inline_math_delim: InlineMathDelim::Dollar,
+            display_math_delim: DisplayMathDelim::Brackets,
+            progress: None,
         }
     }
 }
@@ -180,6 +332,18 @@ pub struct ConvertContext {
     pub variables: HashMap<String, String>,
     /// Pending label to be attached to the next figure/table environment
     pub pending_label: Option<String>,
+    /// Whether links are underlined (`#show link: underline`)
+    pub underline_links: bool,
+    /// Pattern from `#set math.equation(numbering: ..)`; numbers every
+    /// display equation when set
+    pub equation_numbering: Option<String>,
+    /// Number of numbered display equations emitted so far
+    pub equation_count: usize,
+    /// Whether inline code may use `\verb`; false in contexts whose output
+    /// ends up inside a command argument
+    pub inline_verb: bool,
+    /// Number of emphasized or italic spans around the current text
+    pub italic_depth: usize,
 }
 
 /// Initial capacity for output buffer (reduces reallocations)
@@ -201,6 +365,11 @@ impl ConvertContext {
             warnings: Vec::new(),
             variables: HashMap::new(),
             pending_label: None,
+            underline_links: false,
+            equation_numbering: None,
+            equation_count: 0,
+            inline_verb: false,
+            italic_depth: 0,
         }
     }
 
@@ -219,6 +388,11 @@ impl ConvertContext {
             warnings: Vec::new(),
             variables: HashMap::new(),
             pending_label: None,
+            underline_links: false,
+            equation_numbering: None,
+            equation_count: 0,
+            inline_verb: false,
+            italic_depth: 0,
         }
     }
 
@@ -387,7 +387,7 @@ mod tests {
         assert_eq!(parse_json("true").unwrap(), Value::Bool(true));
         assert_eq!(parse_json("false").unwrap(), Value::Bool(false));
         assert_eq!(parse_json("42").unwrap(), Value::Int(42));
-        assert_eq!(parse_json("3.14").unwrap(), Value::Float(3.14));
+        assert_eq!(parse_json("2.5").unwrap(), Value::Float(2.5));
         assert_eq!(
             parse_json("\"hello\"").unwrap(),
             Value::Str("hello".to_string())
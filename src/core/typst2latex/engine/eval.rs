@@ -6,20 +6,24 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+use crate::core::typst2latex::utils::sets_flipped_page;
 use crate::features::refs::{citation_mode_from_typst_form, CitationMode, ReferenceType};
 use indexmap::IndexMap;
+use regex::Regex;
 
 use typst_syntax::ast::{self, AstNode};
 use typst_syntax::{parse, parse_code, SyntaxKind, SyntaxNode};
 
-use super::library::{call_builtin, call_calc, call_method, BuiltinResult};
+use super::library::{call_builtin, call_calc, call_method, text_symbol, BuiltinResult};
 use super::ops;
 use super::scope::Scopes;
+use super::show::{text_show_rule, TextShowRule};
+use super::value::value_to_typst_arg;
 use super::value::{
     bibliography_content_value, citation_content_value, label_content_value,
-    normalize_ref_target_text, reference_content_value, Alignment, Arguments, Closure, ContentNode,
-    Direction, EvalError, EvalErrorKind, EvalResult, HorizAlign, MathSegment, Selector, ShowRule,
-    SourceSpan, Value, VertAlign,
+    normalize_ref_target_text, reference_content_value, Alignment, Arg, Arguments, Closure,
+    ContentNode, Direction, EvalError, EvalErrorKind, EvalResult, HorizAlign, Length, LengthUnit,
+    MathSegment, Selector, ShowRule, SourceSpan, Value, VertAlign, WrappedRegex,
 };
 use super::vfs::{NoopVfs, VirtualFileSystem};
 
@@ -370,10 +374,27 @@ impl MiniEval {
 
     /// Evaluate markup (the top-level content).
     pub fn eval_markup(&mut self, markup: ast::Markup) -> EvalResult<Value> {
+        let exprs: Vec<ast::Expr> = markup.exprs().collect();
+        self.eval_markup_exprs(&exprs)
+    }
+
+    /// Evaluate a run of markup expressions; a show rule on text restyles
+    /// the content of the expressions after it.
+    fn eval_markup_exprs(&mut self, exprs: &[ast::Expr]) -> EvalResult<Value> {
         let mut output = Value::None;
 
-        for expr in markup.exprs() {
-            let value = self.eval_expr(expr)?;
+        for (i, expr) in exprs.iter().enumerate() {
+            if let ast::Expr::ShowRule(rule) = expr {
+                if let Some(rule) = self.eval_text_show_rule(*rule) {
+                    let rest = match self.eval_markup_exprs(&exprs[i + 1..])? {
+                        Value::Content(nodes) => Value::Content(rule.apply(nodes)),
+                        other => other,
+                    };
+                    return ops::join(output, rest);
+                }
+            }
+
+            let value = self.eval_expr(*expr)?;
             output = ops::join(output, value)?;
 
             if self.flow.is_some() {
@@ -384,6 +405,48 @@ impl MiniEval {
         Ok(output)
     }
 
+    /// The text show rule a `#show` stands for, if it is one. A rule whose
+    /// pattern or styling arguments do not evaluate is skipped with a warning.
+    fn eval_text_show_rule(&mut self, rule: ast::ShowRule) -> Option<TextShowRule> {
+        let syntax = text_show_rule(rule)?;
+        let pattern = match self.eval_expr(syntax.selector) {
+            Ok(Value::Str(text)) => Regex::new(&regex::escape(&text)).ok(),
+            Ok(Value::Regex(WrappedRegex(pattern))) => Some(pattern),
+            _ => None,
+        };
+        // Arguments such as `fill: red` the evaluator does not know stay as written
+        let args = syntax
+            .args
+            .into_iter()
+            .map(|(name, expr)| {
+                let value = match self.eval_expr(expr) {
+                    Ok(value) => value_to_typst_arg(&value),
+                    Err(_) => expr.to_untyped().clone().into_text().to_string(),
+                };
+                (name, value)
+            })
+            .collect();
+        match pattern {
+            Some(pattern) => Some(TextShowRule {
+                pattern,
+                func: syntax.func,
+                args,
+            }),
+            None => {
+                let source = rule.to_untyped().clone().into_text();
+                let message = format!(
+                    "show rule `#{}` skipped: its selector is not a string or valid regex",
+                    source.trim()
+                );
+                match SourceSpan::from_typst_span(rule.span()) {
+                    Some(span) => self.warn_at(message, span),
+                    None => self.warn(message),
+                }
+                None
+            }
+        }
+    }
+
     /// Evaluate an expression.
     /// Evaluate an expression.
     ///
@@ -399,6 +462,7 @@ impl MiniEval {
             | ast::Expr::Bool(_)
             | ast::Expr::Int(_)
             | ast::Expr::Float(_)
+            | ast::Expr::Numeric(_)
             | ast::Expr::Str(_) => self.eval_literal(expr),
 
             // ================================================================
@@ -415,6 +479,11 @@ impl MiniEval {
             // ================================================================
             // Blocks and Parenthesized Expressions
             // ================================================================
+            // Blocks that work out today's date are kept like `datetime.today()`
+            ast::Expr::CodeBlock(block) if uses_today(block.to_untyped()) => {
+                let source = format!("#{}", block.to_untyped().clone().into_text());
+                Ok(Value::Content(vec![ContentNode::RawSource(source)]))
+            }
             ast::Expr::CodeBlock(block) => self.eval_code_block(block),
             ast::Expr::ContentBlock(block) => self.eval_content_block(block),
             ast::Expr::Parenthesized(paren) => self.eval_expr(paren.expr()),
@@ -476,10 +545,10 @@ impl MiniEval {
             // ================================================================
             // Passthrough (unevaluated, preserved as raw source)
             // ================================================================
-            ast::Expr::SetRule(_)
-            | ast::Expr::ShowRule(_)
-            | ast::Expr::Contextual(_)
-            | ast::Expr::DestructAssignment(_) => self.passthrough_expr(&expr),
+            ast::Expr::SetRule(_) | ast::Expr::ShowRule(_) | ast::Expr::DestructAssignment(_) => {
+                self.passthrough_expr(&expr)
+            }
+            ast::Expr::Contextual(contextual) => self.eval_contextual(contextual),
 
             // Fallback for any unhandled expression types
             _ => self.passthrough_expr(&expr),
@@ -509,13 +578,33 @@ impl MiniEval {
         Ok(Value::None)
     }
 
+    /// Evaluate a `context` block. Counters, locations and measurements need
+    /// the laid-out document, so the body sees placeholders instead: counters
+    /// read 0, `here()` is page 1 at the origin, `measure` is zero-sized and
+    /// `query` finds nothing. A body that still fails is kept as source.
+    fn eval_contextual(&mut self, contextual: ast::Contextual) -> EvalResult<Value> {
+        let message = "`context` needs the laid-out document; its body was evaluated \
+                       with placeholder counters, locations and sizes";
+        match SourceSpan::from_typst_span(contextual.span()) {
+            Some(span) => self.warn_at(message, span),
+            None => self.warn(message),
+        }
+        match self.eval_expr(contextual.body()) {
+            Ok(value) => Ok(value),
+            Err(_) => {
+                let source = format!("#{}", contextual.to_untyped().clone().into_text());
+                Ok(Value::Content(vec![ContentNode::RawSource(source)]))
+            }
+        }
+    }
+
     /// Passthrough expression as raw source (for unevaluated expressions).
     fn passthrough_expr(&self, expr: &ast::Expr) -> EvalResult<Value> {
         let source = expr.to_untyped().text().to_string();
         Ok(Value::Content(vec![ContentNode::RawSource(source)]))
     }
 
-    /// Evaluate literal expressions (None, Auto, Bool, Int, Float, Str).
+    /// Evaluate literal expressions (None, Auto, Bool, Int, Float, Numeric, Str).
     fn eval_literal(&mut self, expr: ast::Expr) -> EvalResult<Value> {
         match expr {
             ast::Expr::None(_) => Ok(Value::None),
@@ -523,6 +612,7 @@ impl MiniEval {
             ast::Expr::Bool(b) => Ok(Value::Bool(b.get())),
             ast::Expr::Int(i) => Ok(Value::Int(i.get())),
             ast::Expr::Float(f) => Ok(Value::Float(f.get())),
+            ast::Expr::Numeric(n) => Ok(numeric_value(n.get())),
             ast::Expr::Str(s) => Ok(Value::Str(s.get().to_string())),
             _ => unreachable!("eval_literal called with non-literal"),
         }
@@ -616,6 +706,12 @@ impl MiniEval {
         let source_val = self.eval_expr(source_expr)?;
         let path = source_val.as_str()?;
 
+        // Packages cannot be fetched here; their functions stay unbound and
+        // reach the converter as calls, which maps some of them (wrap-it)
+        if path.starts_with('@') {
+            return Ok(Value::None);
+        }
+
         let current_dir = if let Some(cf) = &self.current_file {
             std::path::Path::new(cf)
                 .parent()
@@ -945,11 +1041,21 @@ impl MiniEval {
         Ok(output)
     }
 
-    /// Evaluate a content block.
+    /// Evaluate a content block. A block that flips its page is kept as a
+    /// `page(flipped: true)` call, since the set rule applies to it alone.
     fn eval_content_block(&mut self, block: ast::ContentBlock) -> EvalResult<Value> {
         self.scopes.enter();
         let result = self.eval_markup(block.body())?;
         self.scopes.exit();
+        if sets_flipped_page(block.body()) {
+            return Ok(Value::Content(vec![ContentNode::FuncCall {
+                name: "page".to_string(),
+                args: vec![
+                    Arg::Named("flipped".to_string(), Value::Bool(true)),
+                    Arg::Pos(result),
+                ],
+            }]));
+        }
         Ok(result)
     }
 
@@ -1525,6 +1631,20 @@ impl MiniEval {
         let callee = call.callee();
         let args = call.args();
 
+        // Today's date is only known when the LaTeX is compiled, so
+        // `datetime.today()` and calls on it are kept for the converter
+        if callee_starts_with_today(&callee) {
+            let source = format!("#{}", call.to_untyped().clone().into_text());
+            return Ok(Value::Content(vec![ContentNode::RawSource(source)]));
+        }
+
+        // Without file access the LaTeX loads `csv(..)` data itself, so
+        // calls that read a data file are kept for the converter
+        if !self.vfs.has_file_access() && reads_csv(call.to_untyped()) {
+            let source = format!("#{}", call.to_untyped().clone().into_text());
+            return Ok(Value::Content(vec![ContentNode::RawSource(source)]));
+        }
+
         // Check for field access (method call or module function)
         if let ast::Expr::FieldAccess(access) = &callee {
             return self.eval_method_call(*access, args);
@@ -1784,6 +1904,12 @@ impl MiniEval {
 
     /// Evaluate field access.
     fn eval_field_access(&mut self, access: ast::FieldAccess) -> EvalResult<Value> {
+        // Spaces and dashes of the sym module are text
+        let path = access.to_untyped().clone().into_text();
+        if let Some(symbol) = path.strip_prefix("sym.").and_then(text_symbol) {
+            return Ok(Value::Str(symbol.to_string()));
+        }
+
         let target = self.eval_expr(access.target())?;
         let field = access.field().get().as_str();
 
@@ -2059,6 +2185,12 @@ impl MiniEval {
                 | "assert"
                 | "measure"
                 | "layout"
+                | "here"
+                | "locate"
+                | "query"
+                | "page"
+                | "heading"
+                | "figure"
                 | "place"
                 | "box"
                 | "block"
@@ -2276,6 +2408,22 @@ pub fn expand_macros(source: &str) -> EvalResult<String> {
     Ok(result.output)
 }
 
+/// Convert a numeric literal with a unit (`1fr`, `2em`, `50%`) into its value.
+fn numeric_value((value, unit): (f64, ast::Unit)) -> Value {
+    let length = |unit| Value::Length(Length::exact(value, unit));
+    match unit {
+        ast::Unit::Pt => length(LengthUnit::Pt),
+        ast::Unit::Mm => length(LengthUnit::Mm),
+        ast::Unit::Cm => length(LengthUnit::Cm),
+        ast::Unit::In => length(LengthUnit::In),
+        ast::Unit::Em => length(LengthUnit::Em),
+        ast::Unit::Deg => Value::Angle(value),
+        ast::Unit::Rad => Value::Angle(value.to_degrees()),
+        ast::Unit::Fr => Value::Fraction(value),
+        ast::Unit::Percent => Value::Ratio(value / 100.0),
+    }
+}
+
 /// Normalize whitespace in content nodes.
 ///
 /// This function:
@@ -2322,10 +2470,45 @@ fn normalize_content_whitespace(nodes: Vec<ContentNode>) -> Vec<ContentNode> {
     result
 }
 
+/// Source text without whitespace, for matching call chains
+pub(crate) fn compact_source(node: &SyntaxNode) -> String {
+    node.clone()
+        .into_text()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+/// Whether a callee is `datetime.today` or a method on `datetime.today()`
+fn callee_starts_with_today(callee: &ast::Expr) -> bool {
+    compact_source(callee.to_untyped()).starts_with("datetime.today")
+}
+
+/// Whether code asks for `datetime.today()`
+pub(crate) fn uses_today(node: &SyntaxNode) -> bool {
+    compact_source(node).contains("datetime.today()")
+}
+
+/// Whether `node` calls `csv(..)`
+fn reads_csv(node: &SyntaxNode) -> bool {
+    node.cast::<ast::FuncCall>().is_some_and(
+        |call| matches!(call.callee(), ast::Expr::Ident(ident) if ident.get() == "csv"),
+    ) || node.children().any(reads_csv)
+}
+
 /// Expand macros in Typst source code, returning warnings as well.
 ///
 /// This is the full version that returns both the expanded output and any warnings.
 pub fn expand_macros_with_warnings(source: &str) -> EvalResult<ExpandResult> {
+    expand_macros_with_vfs(source, Arc::new(NoopVfs))
+}
+
+/// [`expand_macros_with_warnings`] with files such as `csv(..)` data read
+/// from `vfs`.
+pub fn expand_macros_with_vfs(
+    source: &str,
+    vfs: Arc<dyn VirtualFileSystem>,
+) -> EvalResult<ExpandResult> {
     let root = parse(source);
 
     if !root.errors().is_empty() {
@@ -2343,7 +2526,7 @@ pub fn expand_macros_with_warnings(source: &str) -> EvalResult<ExpandResult> {
         .cast::<ast::Markup>()
         .ok_or_else(|| EvalError::syntax("expected markup"))?;
 
-    let mut eval = MiniEval::new();
+    let mut eval = MiniEval::with_vfs(vfs);
     let result = eval.eval_markup(markup)?;
 
     // Convert result to content nodes
@@ -2423,6 +2606,15 @@ mod tests {
         assert!(result.contains("5"), "Expected 5 in: {}", result);
     }
 
+    #[test]
+    fn test_numeric_literals_keep_their_units() {
+        let result = expand_macros("#h(2fr) #rotate(90deg)[x] #box(width: 50%)[y]").unwrap();
+        assert!(result.contains("#h(2fr)"), "got: {}", result);
+        assert!(result.contains("90deg"), "got: {}", result);
+        assert!(result.contains("width: 50%"), "got: {}", result);
+        assert!(!result.contains("[2fr]"), "got: {}", result);
+    }
+
     #[test]
     fn test_for_loop() {
         // For loop with content block syntax [...]
@@ -3346,4 +3538,37 @@ sum(1..10)=#sum-to(10)
         assert!(result.contains("1"), "Should have 1: {}", result);
         assert!(result.contains("3"), "Should have 3: {}", result);
     }
+
+    #[test]
+    fn test_text_show_rules_wrap_matches() {
+        let code = r#"Before TODO.
+#show "TODO": strong
+#[
+  #show regex("\bAPI\b"): it => text(fill: red, it)
+  The API and APIs.
+]
+After TODO and API.
+#show regex("("): strong
+"#;
+        let result = expand_macros_with_warnings(code).unwrap();
+        assert!(
+            result.output.starts_with("Before TODO."),
+            "{}",
+            result.output
+        );
+        assert!(
+            result
+                .output
+                .contains("The #text(fill: red)[API] and APIs."),
+            "{}",
+            result.output
+        );
+        assert!(
+            result.output.contains("After *TODO* and API."),
+            "{}",
+            result.output
+        );
+        assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+        assert!(result.warnings[0].message.contains("regex(\"(\")"));
+    }
 }
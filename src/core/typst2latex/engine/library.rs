@@ -3,6 +3,7 @@
 //! This module implements built-in functions and methods that match typst-hs
 //! capabilities for full macro evaluation support.
 
+use crate::features::data_tables::parse_delimited;
 use crate::features::refs::{citation_mode_from_typst_form, CitationMode, ReferenceType};
 use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
 use indexmap::IndexMap;
@@ -16,7 +17,7 @@ use super::value::{
     Alignment, Arg, Arguments, Closure, Color, ContentNode, Counter, DateTime, EvalError,
     EvalResult, HorizAlign, Length, LengthUnit, Selector, State, Value, VertAlign, WrappedRegex,
 };
-use super::vfs::VirtualFileSystem;
+use super::vfs::{VfsError, VirtualFileSystem};
 
 /// Result of trying to call a built-in function.
 ///
@@ -130,6 +131,8 @@ pub fn call_builtin(
         // Return mock values with warnings during static evaluation
         "measure" => builtin_measure(args).into(),
         "layout" => builtin_layout(args).into(),
+        "here" | "locate" => Ok(location_placeholder()).into(),
+        "query" => Ok(Value::Array(Vec::new())).into(),
 
         // Alignment constants (handled as functions)
         "left" | "center" | "right" | "top" | "bottom" | "horizon" | "start" | "end" => {
@@ -600,6 +603,19 @@ fn builtin_counter(args: Vec<Value>) -> EvalResult<Value> {
     match args.as_slice() {
         [Value::Label(l)] => Ok(Value::Counter(Counter::Label(l.clone()))),
         [Value::Str(s)] => Ok(Value::Counter(Counter::Custom(s.clone()))),
+        [Value::Selector(s)] => Ok(Value::Counter(Counter::Selector(format!("{:?}", s)))),
+        // Element functions such as `page` or `heading` arrive as builtin wrappers
+        [Value::Func(f)] => match f.name.as_deref() {
+            Some("<builtin:page>") => Ok(Value::Counter(Counter::Page)),
+            Some(name) => Ok(Value::Counter(Counter::Selector(
+                name.trim_start_matches("<builtin:")
+                    .trim_end_matches('>')
+                    .to_string(),
+            ))),
+            None => Err(EvalError::argument(
+                "counter expects label, string key or element".to_string(),
+            )),
+        },
         _ => Err(EvalError::argument(
             "counter expects label or string key".to_string(),
         )),
@@ -770,24 +786,39 @@ fn builtin_csv(
         .first()
         .ok_or(EvalError::argument("csv expects path"))?
         .as_str()?;
-    let content = vfs
-        .read_text(path)
-        .map_err(|e| EvalError::other(e.to_string()))?;
+    let content = vfs.read_text(path).map_err(|e| match e {
+        VfsError::NotFound(path) => EvalError::file_not_found(path),
+        e => EvalError::other(e.to_string()),
+    })?;
 
     let delimiter = if let Some(d) = named.get("delimiter") {
         d.as_str()?
     } else {
         ","
     };
-    if delimiter != "," {
-        return Err(EvalError::other("Custom CSV delimiters not yet supported"));
-    }
+    let mut delimiter_chars = delimiter.chars();
+    let (Some(separator), None) = (delimiter_chars.next(), delimiter_chars.next()) else {
+        return Err(EvalError::argument(
+            "csv delimiter must be a single character",
+        ));
+    };
 
+    // Typst reads rows as arrays unless `row-type: dictionary` is given
     let has_header = if let Some(row_type) = named.get("row-type") {
         row_type.as_str()? == "dictionary"
     } else {
-        true // default is dictionary which implies header? Typst default is `dictionary`.
+        false
     };
+    if !has_header {
+        let rows = parse_delimited(&content, Some(separator))
+            .into_iter()
+            .map(|row| Value::Array(row.into_iter().map(Value::Str).collect()))
+            .collect();
+        return Ok(Value::Array(rows));
+    }
+    if separator != ',' {
+        return Err(EvalError::other("Custom CSV delimiters not yet supported"));
+    }
 
     data::parse_csv(&content, has_header)
 }
@@ -814,6 +845,26 @@ fn builtin_toml(args: Vec<Value>, vfs: &Arc<dyn VirtualFileSystem>) -> EvalResul
     data::parse_toml(&content)
 }
 
+// ============================================================================
+// sym module
+// ============================================================================
+
+/// The text of a `sym.*` space or dash that may appear in prose, such as
+/// `sym.space.nobreak`, by its path below `sym`
+pub fn text_symbol(path: &str) -> Option<&'static str> {
+    let symbol = match path {
+        "space" => " ",
+        "space.nobreak" | "space.nobreak.narrow" => "\u{a0}",
+        "space.thin" => "\u{2009}",
+        "hyph" => "\u{2010}",
+        "hyph.nobreak" => "\u{2011}",
+        "dash.en" => "–",
+        "dash.em" => "—",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
 // ============================================================================
 // calc module functions
 // ============================================================================
@@ -1255,6 +1306,10 @@ fn call_dict_method(
 ) -> EvalResult<Value> {
     match method {
         "len" => Ok(Value::Int(dict.len() as i64)),
+        // Methods of the location placeholder returned by `here()`
+        "page" if dict.contains_key("page") => Ok(dict["page"].clone()),
+        "position" if dict.contains_key("page") => Ok(Value::Dict(dict.clone())),
+        "page-numbering" if dict.contains_key("page") => Ok(Value::None),
         "at" => {
             let key = args
                 .first()
@@ -1781,6 +1836,11 @@ fn call_counter_method(c: &Counter, method: &str, args: Vec<Value>) -> EvalResul
                 .first()
                 .and_then(|v| v.as_str().ok().map(|s| s.to_string()))
                 .unwrap_or_else(|| "1".to_string());
+            // The converter knows the page counter as `\thepage`
+            if *c == Counter::Page {
+                let source = format!("#counter(page).display({:?})", numbering);
+                return Ok(Value::Content(vec![ContentNode::RawSource(source)]));
+            }
             // Get a string key for the counter
             let key = match c {
                 Counter::Custom(s) => s.clone(),
@@ -1812,17 +1872,11 @@ fn call_counter_method(c: &Counter, method: &str, args: Vec<Value>) -> EvalResul
     }
 }
 
-fn call_state_method(s: &State, method: &str, args: Vec<Value>) -> EvalResult<Value> {
+fn call_state_method(s: &State, method: &str, _args: Vec<Value>) -> EvalResult<Value> {
     match method {
-        // update() sets state to new value (or applies function)
-        "update" => {
-            let _new_value = args.first().cloned().unwrap_or(Value::None);
-            // Return content that represents a state update operation
-            Ok(Value::Content(vec![ContentNode::FuncCall {
-                name: format!("state({:?}).update", s.key),
-                args: args.into_iter().map(Arg::Pos).collect(),
-            }]))
-        }
+        // update() only takes effect during layout, and get() keeps returning
+        // the initial value, so the update is dropped
+        "update" => Ok(Value::Content(Vec::new())),
         // display() formats current state value
         "display" => {
             // If given a function, it formats the value
@@ -2005,6 +2059,26 @@ fn builtin_measure(args: Vec<Value>) -> EvalResult<Value> {
     Ok(Value::Dict(result))
 }
 
+/// here() and locate() - The location of an element.
+/// In static evaluation, every location is the top left corner of page 1.
+///
+/// # Typst signature: here() -> location
+/// Returns: { page: int, x: length, y: length }, which also answers `.page()`
+/// and `.position()`
+fn location_placeholder() -> Value {
+    let mut result = IndexMap::new();
+    result.insert("page".to_string(), Value::Int(1));
+    result.insert(
+        "x".to_string(),
+        Value::Length(Length::exact(0.0, LengthUnit::Pt)),
+    );
+    result.insert(
+        "y".to_string(),
+        Value::Length(Length::exact(0.0, LengthUnit::Pt)),
+    );
+    Value::Dict(result)
+}
+
 /// layout() - Access layout context information.
 /// In static evaluation, returns a mock context since actual layout requires document processing.
 ///
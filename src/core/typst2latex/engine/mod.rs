@@ -55,12 +55,19 @@ mod eval;
 mod library;
 mod ops;
 mod scope;
+mod show;
 mod value;
 mod vfs;
 
 pub use data::{parse_csv, parse_json, parse_toml, parse_yaml};
-pub use eval::{expand_macros, expand_macros_with_warnings, EvalWarning, ExpandResult, MiniEval};
+pub(crate) use eval::{compact_source, uses_today};
+pub use eval::{
+    expand_macros, expand_macros_with_vfs, expand_macros_with_warnings, EvalWarning, ExpandResult,
+    MiniEval,
+};
+pub(crate) use library::text_symbol;
 pub use scope::{Scope, Scopes};
+pub(crate) use show::text_show_rule;
 pub(crate) use value::render_math_segments_to_typst_source;
 pub use value::{
     Alignment, Arg, Arguments, Closure, Color, ContentNode, Counter, DateTime, Direction,
@@ -69,4 +76,4 @@ pub use value::{
 };
 #[cfg(not(target_arch = "wasm32"))]
 pub use vfs::RealVfs;
-pub use vfs::{MemoryVfs, NoopVfs, VfsError, VfsResult, VirtualFileSystem};
+pub use vfs::{MemoryVfs, NoopVfs, ResolverVfs, VfsError, VfsResult, VirtualFileSystem};
@@ -144,6 +144,16 @@ pub fn add(lhs: Value, rhs: Value) -> EvalResult<Value> {
             Dict(a)
         }
 
+        // 2D alignment from one alignment per axis: `top + right`
+        (Alignment(a), Alignment(b))
+            if a.horiz.is_none() != b.horiz.is_none() && a.vert.is_none() != b.vert.is_none() =>
+        {
+            Alignment(super::value::Alignment::new(
+                a.horiz.or(b.horiz),
+                a.vert.or(b.vert),
+            ))
+        }
+
         (a, b) => {
             return Err(EvalError::invalid_op(format!(
                 "cannot add {} and {}",
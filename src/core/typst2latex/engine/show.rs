@@ -0,0 +1,230 @@
+//! Show rules that restyle matched text.
+//!
+//! `#show "TODO": strong` and `#show regex("\bAPI\b"): it => smallcaps(it)`
+//! are applied while evaluating the markup that follows them: each match is
+//! wrapped in the styling call, so the converter sees ordinary `strong`,
+//! `emph`, `smallcaps` and `text` content.
+
+use regex::Regex;
+use typst_syntax::ast;
+
+use super::value::ContentNode;
+
+/// Styling functions a text show rule may apply, with the named arguments
+/// each one takes
+const TEXT_STYLES: &[(&str, &[&str])] = &[
+    ("strong", &[]),
+    ("emph", &[]),
+    ("smallcaps", &[]),
+    ("text", &["fill", "size"]),
+];
+
+/// A show rule on text as written: the selector expression (a string or a
+/// `regex(..)` call), the styling function and its named arguments
+pub(crate) struct TextShowRuleSyntax<'a> {
+    pub selector: ast::Expr<'a>,
+    pub func: &'static str,
+    pub args: Vec<(String, ast::Expr<'a>)>,
+}
+
+/// Recognize `#show "..": f`, `#show regex(..): f.with(..)` and
+/// `#show "..": it => f(.., it)` for the styling functions in
+/// [`TEXT_STYLES`]
+pub(crate) fn text_show_rule(rule: ast::ShowRule<'_>) -> Option<TextShowRuleSyntax<'_>> {
+    let selector = rule.selector()?;
+    let is_text_selector = match selector {
+        ast::Expr::Str(_) => true,
+        ast::Expr::FuncCall(call) => {
+            matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "regex")
+        }
+        _ => false,
+    };
+    if !is_text_selector {
+        return None;
+    }
+
+    let (func, args) = match rule.transform() {
+        ast::Expr::Ident(ident) => (style(ident.as_str())?, Vec::new()),
+        // `text.with(fill: red)`
+        ast::Expr::FuncCall(call) => {
+            let ast::Expr::FieldAccess(access) = call.callee() else {
+                return None;
+            };
+            let ast::Expr::Ident(target) = access.target() else {
+                return None;
+            };
+            if access.field().as_str() != "with" {
+                return None;
+            }
+            let func = style(target.as_str())?;
+            (func, named_args(func, call.args(), None)?)
+        }
+        // `it => text(fill: red, it)` or `it => smallcaps[#it]`
+        ast::Expr::Closure(closure) => {
+            let mut params = closure.params().children();
+            let Some(ast::Param::Pos(ast::Pattern::Normal(ast::Expr::Ident(param)))) =
+                params.next()
+            else {
+                return None;
+            };
+            if params.next().is_some() {
+                return None;
+            }
+            let ast::Expr::FuncCall(call) = closure.body() else {
+                return None;
+            };
+            let ast::Expr::Ident(callee) = call.callee() else {
+                return None;
+            };
+            let func = style(callee.as_str())?;
+            (func, named_args(func, call.args(), Some(param.as_str()))?)
+        }
+        _ => return None,
+    };
+
+    Some(TextShowRuleSyntax {
+        selector,
+        func,
+        args,
+    })
+}
+
+fn style(name: &str) -> Option<&'static str> {
+    TEXT_STYLES
+        .iter()
+        .find(|(func, _)| *func == name)
+        .map(|(func, _)| *func)
+}
+
+/// The named arguments of a styling call, which must take its body from the
+/// closure parameter `param` (a `with(..)` call has no body)
+fn named_args<'a>(
+    func: &str,
+    args: ast::Args<'a>,
+    param: Option<&str>,
+) -> Option<Vec<(String, ast::Expr<'a>)>> {
+    let allowed = TEXT_STYLES
+        .iter()
+        .find(|(name, _)| *name == func)
+        .map_or(&[][..], |(_, allowed)| *allowed);
+    let is_param = |expr: ast::Expr| match expr {
+        ast::Expr::Ident(ident) => Some(ident.as_str()) == param,
+        ast::Expr::ContentBlock(block) => {
+            let mut exprs = block.body().exprs();
+            matches!(
+                (exprs.next(), exprs.next()),
+                (Some(ast::Expr::Ident(ident)), None) if Some(ident.as_str()) == param
+            )
+        }
+        _ => false,
+    };
+
+    let mut named = Vec::new();
+    let mut has_body = false;
+    for arg in args.items() {
+        match arg {
+            ast::Arg::Named(arg) if allowed.contains(&arg.name().as_str()) => {
+                named.push((arg.name().as_str().to_string(), arg.expr()));
+            }
+            ast::Arg::Pos(expr) if !has_body && is_param(expr) => has_body = true,
+            _ => return None,
+        }
+    }
+    (has_body == param.is_some()).then_some(named)
+}
+
+/// A show rule on text, ready to apply
+#[derive(Debug, Clone)]
+pub(crate) struct TextShowRule {
+    pub pattern: Regex,
+    pub func: &'static str,
+    /// Named arguments as Typst source
+    pub args: Vec<(String, String)>,
+}
+
+impl TextShowRule {
+    /// Wrap every match in `nodes`, including in strong, emphasized,
+    /// heading and list item content
+    pub fn apply(&self, nodes: Vec<ContentNode>) -> Vec<ContentNode> {
+        let mut result = Vec::with_capacity(nodes.len());
+        let mut run = String::new();
+        for node in nodes {
+            match node {
+                ContentNode::Text(text) => run.push_str(&text),
+                ContentNode::Space => run.push(' '),
+                other => {
+                    self.flush_run(&mut run, &mut result);
+                    result.push(self.apply_children(other));
+                }
+            }
+        }
+        self.flush_run(&mut run, &mut result);
+        result
+    }
+
+    fn apply_children(&self, node: ContentNode) -> ContentNode {
+        match node {
+            ContentNode::Strong(children) => ContentNode::Strong(self.apply(children)),
+            ContentNode::Emph(children) => ContentNode::Emph(self.apply(children)),
+            ContentNode::Heading { level, content } => ContentNode::Heading {
+                level,
+                content: self.apply(content),
+            },
+            ContentNode::ListItem(children) => ContentNode::ListItem(self.apply(children)),
+            ContentNode::EnumItem { number, content } => ContentNode::EnumItem {
+                number,
+                content: self.apply(content),
+            },
+            other => other,
+        }
+    }
+
+    /// Split a run of text and spaces around the matches
+    fn flush_run(&self, run: &mut String, result: &mut Vec<ContentNode>) {
+        let text = std::mem::take(run);
+        let mut last = 0;
+        for found in self.pattern.find_iter(&text) {
+            if found.is_empty() {
+                continue;
+            }
+            push_text(&text[last..found.start()], result);
+            result.push(self.styled(found.as_str()));
+            last = found.end();
+        }
+        push_text(&text[last..], result);
+    }
+
+    fn styled(&self, text: &str) -> ContentNode {
+        let body = vec![ContentNode::Text(text.to_string())];
+        match self.func {
+            "strong" => ContentNode::Strong(body),
+            "emph" => ContentNode::Emph(body),
+            func => {
+                let body: String = body.iter().map(ContentNode::to_typst).collect();
+                let args: Vec<String> = self
+                    .args
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+                let source = if args.is_empty() {
+                    format!("#{}[{}]", func, body)
+                } else {
+                    format!("#{}({})[{}]", func, args.join(", "), body)
+                };
+                ContentNode::RawSource(source)
+            }
+        }
+    }
+}
+
+/// Text as text and space nodes, the way the markup is evaluated
+fn push_text(text: &str, result: &mut Vec<ContentNode>) {
+    for (i, word) in text.split(' ').enumerate() {
+        if i > 0 && !matches!(result.last(), Some(ContentNode::Space)) {
+            result.push(ContentNode::Space);
+        }
+        if !word.is_empty() {
+            result.push(ContentNode::Text(word.to_string()));
+        }
+    }
+}
@@ -1527,11 +1527,33 @@ fn value_to_typst_math_arg(value: &Value) -> String {
     }
 }
 
+/// Escape the characters of plain text that Typst markup would read as
+/// syntax, so text produced by evaluation (including escapes such as `\$`)
+/// reads back as the same text
+fn escape_markup_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        // The no-break space would be read back as a plain space
+        if c == '\u{a0}' {
+            escaped.push('~');
+            continue;
+        }
+        if matches!(
+            c,
+            '\\' | '$' | '#' | '_' | '*' | '[' | ']' | '<' | '>' | '@' | '`' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 impl ContentNode {
     /// Convert this content node back to Typst source code.
     pub fn to_typst(&self) -> String {
         match self {
-            ContentNode::Text(s) => s.clone(),
+            ContentNode::Text(s) => escape_markup_text(s),
             ContentNode::Space => " ".to_string(),
             ContentNode::Linebreak => "\\\n".to_string(),
             ContentNode::Parbreak => "\n\n".to_string(),
@@ -1541,7 +1563,12 @@ impl ContentNode {
             }
             ContentNode::Emph(children) => {
                 let inner: String = children.iter().map(|c| c.to_typst()).collect();
-                format!("_{}_", inner)
+                // Underscores cannot nest
+                if inner.contains('_') {
+                    format!("#emph[{}]", inner)
+                } else {
+                    format!("_{}_", inner)
+                }
             }
             ContentNode::Raw { text, lang, block } => {
                 if *block {
@@ -8,6 +8,9 @@ use std::collections::HashMap;
 use std::path::Path;
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::utils::{FileResolveError, FileResolver};
 
 /// Result type for VFS operations.
 pub type VfsResult<T> = Result<T, VfsError>;
@@ -57,6 +60,12 @@ pub trait VirtualFileSystem: Send + Sync {
 
     /// Get the current working directory.
     fn cwd(&self) -> VfsResult<String>;
+
+    /// Whether files can be read at all. Without file access, data files
+    /// are left for the LaTeX compiler to load.
+    fn has_file_access(&self) -> bool {
+        true
+    }
 }
 
 /// A no-op VFS that always returns errors.
@@ -84,6 +93,10 @@ impl VirtualFileSystem for NoopVfs {
     fn cwd(&self) -> VfsResult<String> {
         Ok(".".to_string())
     }
+
+    fn has_file_access(&self) -> bool {
+        false
+    }
 }
 
 /// An in-memory VFS for testing and bundled content.
@@ -253,6 +266,44 @@ impl VirtualFileSystem for RealVfs {
     }
 }
 
+/// A VFS reading through a crate [`FileResolver`], so that the evaluator
+/// sees the same files as the converters.
+pub struct ResolverVfs {
+    resolver: Arc<dyn FileResolver>,
+}
+
+impl ResolverVfs {
+    /// Create a VFS over `resolver`.
+    pub fn new(resolver: Arc<dyn FileResolver>) -> Self {
+        Self { resolver }
+    }
+}
+
+impl VirtualFileSystem for ResolverVfs {
+    fn read_text(&self, path: &str) -> VfsResult<String> {
+        self.resolver.read_file(path).map_err(|e| match e {
+            FileResolveError::NotFound(_) => VfsError::NotFound(path.to_string()),
+            e => VfsError::ReadError(e.to_string()),
+        })
+    }
+
+    fn read_bytes(&self, path: &str) -> VfsResult<Vec<u8>> {
+        self.read_text(path).map(String::into_bytes)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.resolver.file_exists(path)
+    }
+
+    fn resolve(&self, base: &str, relative: &str) -> VfsResult<String> {
+        Ok(self.resolver.resolve_path(base, relative))
+    }
+
+    fn cwd(&self) -> VfsResult<String> {
+        Ok(self.resolver.base_dir().unwrap_or(".").to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,160 +2,234 @@
 //!
 //! Handles document structure, text formatting, and non-math content.
 
-use super::context::{ConvertContext, EnvironmentContext, T2LOptions, TokenType};
-use super::engine::{render_math_segments_to_typst_source, ContentNode};
+use super::context::{
+    CodeBackend, ConvertContext, EnvironmentContext, PlaceBackend, T2LOptions, TokenType,
+};
+use super::engine::{
+    compact_source, render_math_segments_to_typst_source, text_show_rule, text_symbol, uses_today,
+    ContentNode, MiniEval, SourceSpan,
+};
 use super::math::convert_math_node;
 use super::table::{LatexCell, LatexCellAlign, LatexHLine, LatexTableGenerator};
 use super::utils::{
     count_heading_markers, escape_latex_text, extract_length_value, format_latex_color_command,
     get_raw_text_with_lang, get_simple_text, get_string_content, is_display_math,
-    is_string_or_content, normalize_typst_color_expr, parse_angle_value, parse_spacing_spec,
-    FuncArgs, SpacingSpec,
+    normalize_typst_color_expr, parse_angle_value, parse_spacing_spec, sets_flipped_page,
+    spell_typography, typst_color_to_latex_spec, FuncArgs, SpacingSpec,
 };
+use crate::core::latex2typst::utils::to_roman_numeral;
+use crate::data::constants::{listings_language, minted_language, nearest_font_size_command};
 use crate::data::typst_compat::{
     get_heading_command, is_math_func_in_markup, MarkupHandler, TYPST_MARKUP_HANDLERS,
 };
 use crate::features::refs::{
     citation_mode_from_typst_form, citation_to_latex, label_to_latex, reference_to_latex, Citation,
-    CiteGroup, Reference,
+    CiteGroup, Reference, ReferenceType,
 };
 use crate::tikz::{convert_cetz_to_tikz, is_cetz_code};
-use typst_syntax::{SyntaxKind, SyntaxNode};
-
-/// Languages supported by the listings package (case-insensitive check)
-/// This is a subset of commonly used languages that listings supports by default
-const LISTINGS_SUPPORTED_LANGUAGES: &[&str] = &[
-    "abap",
-    "acsl",
-    "ada",
-    "algol",
-    "ant",
-    "assembler",
-    "awk",
-    "bash",
-    "basic",
-    "c",
-    "caml",
-    "cil",
-    "clean",
-    "cobol",
-    "comsol",
-    "csh",
-    "delphi",
-    "eiffel",
-    "erlang",
-    "euphoria",
-    "fortran",
-    "gcl",
-    "gnuplot",
-    "haskell",
-    "html",
-    "idl",
-    "inform",
-    "java",
-    "jvmis",
-    "ksh",
-    "lisp",
-    "logo",
-    "lua",
-    "make",
-    "mathematica",
-    "matlab",
-    "mercury",
-    "metapost",
-    "miranda",
-    "mizar",
-    "ml",
-    "modula-2",
-    "mupad",
-    "nastran",
-    "oberon-2",
-    "ocl",
-    "octave",
-    "oz",
-    "pascal",
-    "perl",
-    "php",
-    "pl/i",
-    "plasm",
-    "postscript",
-    "pov",
-    "prolog",
-    "promela",
-    "pstricks",
-    "python",
-    "r",
-    "reduce",
-    "rexx",
-    "rsl",
-    "ruby",
-    "s",
-    "sas",
-    "scala",
-    "scilab",
-    "sh",
-    "shelxl",
-    "simula",
-    "sparql",
-    "sql",
-    "tcl",
-    "tex",
-    "vbscript",
-    "verilog",
-    "vhdl",
-    "vrml",
-    "xml",
-    "xslt",
-    // Additional common aliases
-    "c++",
-    "cpp",
-    "objective-c",
-    "objc",
-    "javascript",
-    "js",
-    "typescript",
-    "ts",
-];
+use typst_syntax::{ast, SyntaxKind, SyntaxNode};
 
 fn flush_typst_chunk(buffer: &mut String, ctx: &mut ConvertContext) {
     if buffer.trim().is_empty() {
         buffer.clear();
         return;
     }
+    // A chunk continuing a line must not start a list, enum or heading
+    if !ctx.output.is_empty() && !ctx.output.ends_with('\n') {
+        escape_line_start_marker(buffer);
+    }
     let root = typst_syntax::parse(buffer);
     convert_markup_node(&root, ctx);
     buffer.clear();
 }
 
-fn emit_rendered_math(ctx: &mut ConvertContext, math_content: &str, is_block: bool) {
+/// Escape a marker such as `0.`, `-` or `=` at the start of a chunk
+fn escape_line_start_marker(chunk: &mut String) {
+    let digits = chunk.len() - chunk.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &chunk[digits..];
+    let marker = if digits > 0 {
+        rest.starts_with('.').then_some(digits)
+    } else {
+        ["- ", "+ ", "= ", "/ "]
+            .iter()
+            .any(|m| rest.starts_with(m) || rest == m.trim_end())
+            .then_some(0)
+    };
+    if let Some(at) = marker {
+        chunk.insert(at, '\\');
+    }
+}
+
+fn emit_rendered_math(
+    ctx: &mut ConvertContext,
+    math_content: &str,
+    is_block: bool,
+    label: Option<&str>,
+) {
     let in_table = ctx.is_in_env(&EnvironmentContext::Table);
 
     if math_content.trim().is_empty() {
         return;
     }
 
-    let has_alignment = has_unescaped_alignment(math_content);
+    let (has_alignment, has_linebreak) = top_level_math_structure(math_content);
+    // Labelled equations must be numbered for \ref to resolve
+    let numbered = is_block && !in_table && (label.is_some() || ctx.equation_numbering.is_some());
+    let tag = if numbered {
+        ctx.equation_count += 1;
+        ctx.equation_numbering
+            .as_deref()
+            .filter(|pattern| !is_standard_equation_numbering(pattern))
+            .map(|pattern| {
+                format!(
+                    "\\tag*{{{}}}\n",
+                    format_numbering(pattern, ctx.equation_count)
+                )
+            })
+    } else {
+        None
+    };
+    let label = label.map(|label| format!("{}\n", label_to_latex(label)));
 
-    if !in_table && has_alignment {
+    if numbered && (has_alignment || has_linebreak) {
+        // A numbered multi-line equation keeps its single number
+        ctx.push("\\begin{equation}\n\\begin{split}\n");
+        ctx.push(math_content);
+        ctx.push("\n\\end{split}\n");
+        ctx.push(tag.as_deref().unwrap_or_default());
+        ctx.push(label.as_deref().unwrap_or_default());
+        ctx.push("\\end{equation}");
+    } else if !in_table && has_alignment {
         ctx.push("\\begin{align}\n");
         ctx.push(math_content);
+        if let Some(label) = label {
+            ctx.push(" ");
+            ctx.push(label.trim_end());
+        }
         ctx.push("\n\\end{align}");
+    } else if numbered {
+        ctx.push("\\begin{equation}\n");
+        ctx.push(math_content);
+        ctx.push("\n");
+        ctx.push(tag.as_deref().unwrap_or_default());
+        ctx.push(label.as_deref().unwrap_or_default());
+        ctx.push("\\end{equation}");
     } else if is_block && !in_table {
-        ctx.push("\\[\n");
+        let (open, close) = ctx.options.display_math_delim.delimiters();
+        ctx.push(open);
         ctx.push(math_content);
-        ctx.push("\n\\]");
+        ctx.push(close);
     } else {
-        ctx.push("$");
+        let (open, close) = ctx.options.inline_math_delim.delimiters();
+        ctx.push(open);
         ctx.push(math_content);
-        ctx.push("$");
+        ctx.push(close);
     }
     ctx.last_token = TokenType::Command;
 }
 
+/// Whether LaTeX's own equation numbers look like a Typst numbering pattern.
+/// A single-level equation counter ignores the deeper levels of `(1.1)`.
+fn is_standard_equation_numbering(pattern: &str) -> bool {
+    pattern == "(1)" || pattern.starts_with("(1.1") && pattern.ends_with(')')
+}
+
+/// Format a single number with a Typst numbering pattern such as `[1]`,
+/// `(a)` or `i.`: the prefix, the first counting symbol and the suffix.
+fn format_numbering(pattern: &str, number: usize) -> String {
+    let is_counting = |c: char| matches!(c, '1' | 'a' | 'A' | 'i' | 'I');
+    let Some(start) = pattern.find(is_counting) else {
+        return format!("{}{}", pattern, number);
+    };
+    let symbol = pattern[start..].chars().next().unwrap_or('1');
+    let end = pattern.rfind(is_counting).map_or(start, |end| end + 1);
+    let formatted = match symbol {
+        'a' | 'A' => {
+            let mut letters = String::new();
+            let mut n = number;
+            while n > 0 {
+                n -= 1;
+                letters.insert(0, (b'a' + (n % 26) as u8) as char);
+                n /= 26;
+            }
+            if symbol == 'A' {
+                letters.to_uppercase()
+            } else {
+                letters
+            }
+        }
+        'i' => to_roman_numeral(number).to_lowercase(),
+        'I' => to_roman_numeral(number),
+        _ => number.to_string(),
+    };
+    format!("{}{}{}", &pattern[..start], formatted, &pattern[end..])
+}
+
+/// The pattern of a top-level `#set math.equation(numbering: "..")`. A
+/// numbering function keeps LaTeX's numbers; `none` turns numbering off.
+pub fn find_equation_numbering(root: &SyntaxNode) -> Option<String> {
+    let mut numbering = None;
+    for rule in root
+        .children()
+        .filter_map(|node| node.cast::<ast::SetRule>())
+    {
+        if !is_equation_target(rule.target()) {
+            continue;
+        }
+        for arg in rule.args().items() {
+            if let ast::Arg::Named(named) = arg {
+                if named.name().as_str() == "numbering" {
+                    numbering = match named.expr() {
+                        ast::Expr::None(_) => None,
+                        ast::Expr::Str(pattern) => Some(pattern.get().to_string()),
+                        _ => Some("(1)".to_string()),
+                    };
+                }
+            }
+        }
+    }
+    numbering
+}
+
+fn is_equation_target(target: ast::Expr) -> bool {
+    matches!(target, ast::Expr::FieldAccess(access)
+        if access.field().as_str() == "equation"
+            && matches!(access.target(), ast::Expr::Ident(ident) if ident.as_str() == "math"))
+}
+
+/// Find a label definition following `nodes[start]`, skipping spaces.
+/// Returns the label and the index of the label node.
+fn trailing_content_label(nodes: &[ContentNode], start: usize) -> Option<(&str, usize)> {
+    for (j, node) in nodes.iter().enumerate().skip(start + 1) {
+        match node {
+            ContentNode::Space => continue,
+            ContentNode::Label(label) | ContentNode::LabelDef(label) => {
+                return Some((label.as_str(), j))
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
 pub fn convert_content_nodes_to_latex(nodes: &[ContentNode], ctx: &mut ConvertContext) {
+    convert_content_blocks_to_latex(nodes, ctx, |_, _| {});
+}
+
+/// [`convert_content_nodes_to_latex`] for the nodes of a whole document,
+/// calling `on_block` with the number of nodes converted after each
+/// paragraph break
+pub fn convert_content_blocks_to_latex(
+    nodes: &[ContentNode],
+    ctx: &mut ConvertContext,
+    mut on_block: impl FnMut(&mut ConvertContext, usize),
+) {
     let mut buffer = String::new();
 
-    for node in nodes {
+    let mut i = 0;
+    while i < nodes.len() {
+        let node = &nodes[i];
+        i += 1;
         match node {
             ContentNode::Space => {
                 flush_typst_chunk(&mut buffer, ctx);
@@ -170,11 +244,11 @@ pub fn convert_content_nodes_to_latex(nodes: &[ContentNode], ctx: &mut ConvertCo
                 flush_typst_chunk(&mut buffer, ctx);
                 ctx.ensure_paragraph_break();
                 ctx.last_token = TokenType::Newline;
+                on_block(ctx, i);
             }
             ContentNode::Linebreak => {
                 flush_typst_chunk(&mut buffer, ctx);
-                ctx.push("\\\n");
-                ctx.last_token = TokenType::Newline;
+                push_linebreak(ctx);
             }
             ContentNode::Citation {
                 keys,
@@ -192,9 +266,13 @@ pub fn convert_content_nodes_to_latex(nodes: &[ContentNode], ctx: &mut ConvertCo
             }
             ContentNode::Reference { target, ref_type } => {
                 flush_typst_chunk(&mut buffer, ctx);
+                let ref_type = match ref_type {
+                    ReferenceType::Basic if ctx.options.use_autoref => ReferenceType::Named,
+                    other => *other,
+                };
                 ctx.push(&reference_to_latex(&Reference {
                     target: target.clone(),
-                    ref_type: *ref_type,
+                    ref_type,
                 }));
                 ctx.last_token = TokenType::Command;
             }
@@ -221,9 +299,25 @@ pub fn convert_content_nodes_to_latex(nodes: &[ContentNode], ctx: &mut ConvertCo
                 flush_typst_chunk(&mut buffer, ctx);
                 let math_source = render_math_segments_to_typst_source(segments);
                 let math_content = convert_math_source_to_latex(&math_source, &ctx.options);
-                emit_rendered_math(ctx, &math_content, *block);
+                let label = if *block {
+                    trailing_content_label(nodes, i - 1)
+                } else {
+                    None
+                };
+                if let Some((_, j)) = label {
+                    i = j + 1;
+                }
+                emit_rendered_math(ctx, &math_content, *block, label.map(|(l, _)| l));
+            }
+            other => {
+                buffer.push_str(&other.to_typst());
+                // Keep a trailing label in the same chunk so that headings and
+                // figures can attach it where LaTeX expects it.
+                if let Some((label, j)) = trailing_content_label(nodes, i - 1) {
+                    buffer.push_str(&format!(" <{}>", label));
+                    i = j + 1;
+                }
             }
-            other => buffer.push_str(&other.to_typst()),
         }
     }
 
@@ -255,37 +349,39 @@ fn convert_first_math_child(node: &SyntaxNode, ctx: &mut ConvertContext) -> bool
     false
 }
 
-/// Check if a language is supported by the listings package
-fn is_listings_supported(lang: &str) -> bool {
-    let lang_lower = lang.to_lowercase();
-    LISTINGS_SUPPORTED_LANGUAGES
-        .iter()
-        .any(|&supported| supported == lang_lower)
-}
-
-/// Check if math content has alignment markers (&) that are not inside cases/matrix environments
-/// These need to be wrapped in an align environment
-fn has_unescaped_alignment(content: &str) -> bool {
-    // Count nesting level of cases/matrix environments
-    let mut depth = 0;
+/// Find top-level alignment markers (`&`) and line breaks (`\\`) in converted
+/// math, ignoring those inside groups and nested environments such as cases
+/// or matrices. Returns `(has_alignment, has_linebreak)`.
+fn top_level_math_structure(content: &str) -> (bool, bool) {
+    let mut env_depth = 0usize;
+    let mut group_depth = 0usize;
+    let (mut alignment, mut linebreak) = (false, false);
     let mut chars = content.chars().peekable();
 
     while let Some(ch) = chars.next() {
-        if ch == '\\' {
-            // Check for \begin or \end
-            let rest: String = chars.clone().take(5).collect();
-            if rest.starts_with("begin") {
-                depth += 1;
-            } else if rest.starts_with("end") && depth > 0 {
-                depth -= 1;
-            }
-        } else if ch == '&' && depth == 0 {
-            // Found an alignment marker outside of any environment
-            return true;
+        match ch {
+            '\\' => {
+                let rest: String = chars.clone().take(5).collect();
+                if rest.starts_with("begin") {
+                    env_depth += 1;
+                } else if rest.starts_with("end") {
+                    env_depth = env_depth.saturating_sub(1);
+                } else if rest.starts_with('\\') {
+                    chars.next();
+                    linebreak |= env_depth == 0 && group_depth == 0;
+                } else if rest.starts_with(['&', '{', '}']) {
+                    // Escaped character
+                    chars.next();
+                }
+            }
+            '{' => group_depth += 1,
+            '}' => group_depth = group_depth.saturating_sub(1),
+            '&' => alignment |= env_depth == 0,
+            _ => {}
         }
     }
 
-    false
+    (alignment, linebreak)
 }
 
 /// Convert a markup node to LaTeX
@@ -340,51 +436,27 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
                         ctx.indent_level -= 1;
                         ctx.push_line("\\end{enumerate}");
                     }
-                    SyntaxKind::FuncCall => {
-                        // Check if this is a figure/table that has a label following it
-                        let func_name = child
-                            .children()
-                            .next()
-                            .map(|n| n.text().to_string())
-                            .unwrap_or_default();
-
-                        if func_name == "figure" {
-                            // Look ahead for a Label node (skip Space nodes)
-                            let mut label_text: Option<String> = None;
-                            let mut label_idx: Option<usize> = None;
-
-                            for (j, sibling) in children.iter().enumerate().skip(i + 1) {
-                                match sibling.kind() {
-                                    SyntaxKind::Space | SyntaxKind::Linebreak => continue,
-                                    SyntaxKind::Label => {
-                                        let text = sibling.text().to_string();
-                                        label_text = Some(
-                                            text.trim_start_matches('<')
-                                                .trim_end_matches('>')
-                                                .to_string(),
-                                        );
-                                        label_idx = Some(j);
-                                        break;
-                                    }
-                                    _ => break, // Not a label, stop looking
-                                }
-                            }
-
-                            // Set the pending label in context
-                            ctx.pending_label = label_text;
-                            convert_markup_node(child, ctx);
-                            ctx.pending_label = None;
-
-                            // Skip the label node if we found one
-                            if let Some(j) = label_idx {
-                                i = j + 1;
-                            } else {
-                                i += 1;
+                    SyntaxKind::FuncCall | SyntaxKind::Heading | SyntaxKind::Equation
+                        if takes_trailing_label(child) =>
+                    {
+                        // Attach a following <label> to the heading, figure or
+                        // equation instead of emitting it after the fact
+                        let label = find_trailing_label(&children, i);
+                        let label_text = label.as_ref().map(|(text, _)| text.as_str());
+                        match child.kind() {
+                            SyntaxKind::Heading => convert_heading(child, ctx, label_text),
+                            SyntaxKind::Equation => convert_equation(child, ctx, label_text),
+                            _ => {
+                                ctx.pending_label = label_text.map(str::to_string);
+                                convert_markup_node(child, ctx);
+                                ctx.pending_label = None;
                             }
-                        } else {
-                            convert_markup_node(child, ctx);
-                            i += 1;
                         }
+
+                        i = match label {
+                            Some((_, j)) => j + 1,
+                            None => i + 1,
+                        };
                     }
                     _ => {
                         convert_markup_node(child, ctx);
@@ -403,13 +475,51 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
                 &text
             };
             if !cleaned.trim().is_empty() {
-                ctx.push(&escape_latex_text(cleaned));
+                push_text(ctx, &escape_latex_text(cleaned));
                 ctx.last_token = TokenType::Text;
             } else if !cleaned.is_empty() && ctx.last_token != TokenType::Newline {
                 ctx.push(" ");
             }
         }
 
+        // `--`, `---`, `...`, `~` and the other markup shorthands
+        // Spaces and dashes of the sym module, `#sym.space.nobreak`
+        SyntaxKind::FieldAccess => {
+            let path = node.clone().into_text();
+            if let Some(symbol) = path.strip_prefix("sym.").and_then(text_symbol) {
+                push_text(ctx, &escape_latex_text(symbol));
+                ctx.last_token = TokenType::Text;
+            } else {
+                for child in node.children() {
+                    convert_markup_node(child, ctx);
+                }
+            }
+        }
+
+        SyntaxKind::Shorthand => {
+            if let Some(c) = node.cast::<ast::Shorthand>().map(|sh| sh.get()) {
+                push_text(ctx, &escape_latex_text(&c.to_string()));
+                ctx.last_token = TokenType::Text;
+            }
+        }
+
+        // `"` and `'`, opening after a space or an opening bracket or quote
+        SyntaxKind::SmartQuote => {
+            let opening = ctx
+                .output
+                .chars()
+                .last()
+                .is_none_or(|c| c.is_whitespace() || "([{`‘“".contains(c));
+            let quote = match (node.text().as_str(), opening) {
+                ("\"", true) => '“',
+                ("\"", false) => '”',
+                (_, true) => '‘',
+                (_, false) => '’',
+            };
+            push_text(ctx, &quote.to_string());
+            ctx.last_token = TokenType::Text;
+        }
+
         SyntaxKind::Space => {
             if ctx.last_token != TokenType::Newline && !ctx.output.ends_with(' ') {
                 ctx.push(" ");
@@ -418,26 +528,25 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
 
         // Escape sequences: \$, \#, \%, etc.
         SyntaxKind::Escape => {
-            let text = node.text().to_string();
-            // Remove the leading backslash and escape for LaTeX
-            let escaped_char = text.trim_start_matches('\\');
+            let escaped = node
+                .cast::<ast::Escape>()
+                .map(|escape| escape.get())
+                .unwrap_or_default();
             // Map Typst escape to LaTeX escape
-            let latex = match escaped_char {
-                "$" => "\\$",
-                "#" => "\\#",
-                "%" => "\\%",
-                "&" => "\\&",
-                "_" => "\\_",
-                "{" => "\\{",
-                "}" => "\\}",
-                "\\" => "\\textbackslash{}",
-                "~" => "\\textasciitilde{}",
-                "^" => "\\textasciicircum{}",
-                "*" => "*",
-                "`" => "`",
-                _ => escaped_char,
+            let latex = match escaped {
+                '$' => "\\$".to_string(),
+                '#' => "\\#".to_string(),
+                '%' => "\\%".to_string(),
+                '&' => "\\&".to_string(),
+                '_' => "\\_".to_string(),
+                '{' => "\\{".to_string(),
+                '}' => "\\}".to_string(),
+                '\\' => "\\textbackslash{}".to_string(),
+                '~' => "\\textasciitilde{}".to_string(),
+                '^' => "\\textasciicircum{}".to_string(),
+                other => other.to_string(),
             };
-            ctx.push(latex);
+            ctx.push(&latex);
             ctx.last_token = TokenType::Text;
         }
 
@@ -445,35 +554,10 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
             ctx.ensure_paragraph_break();
         }
 
-        SyntaxKind::Linebreak => {
-            // In table cells, use \newline instead of \\ to avoid LR mode errors
-            if ctx.is_in_env(&EnvironmentContext::Table) {
-                ctx.push("\\newline ");
-            } else {
-                ctx.push("\\\\\n");
-            }
-            ctx.last_token = TokenType::Newline;
-        }
+        SyntaxKind::Linebreak => push_linebreak(ctx),
 
         // Headings
-        SyntaxKind::Heading => {
-            let level = count_heading_markers(node);
-            let section_cmd = get_heading_command(level);
-
-            ctx.ensure_paragraph_break();
-            ctx.push(section_cmd);
-            ctx.push("{");
-
-            // Get heading content
-            for child in node.children() {
-                if child.kind() != SyntaxKind::HeadingMarker {
-                    convert_markup_node(child, ctx);
-                }
-            }
-
-            ctx.push("}\n");
-            ctx.last_token = TokenType::Newline;
-        }
+        SyntaxKind::Heading => convert_heading(node, ctx, None),
 
         SyntaxKind::HeadingMarker => {
             // Skip, handled by Heading
@@ -491,14 +575,21 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
             ctx.last_token = TokenType::Command;
         }
 
-        // Emphasis (italic)
+        // Emphasis (italic). It toggles italics like `\emph`, so inside
+        // italic text it becomes `\emph` and turns upright.
         SyntaxKind::Emph => {
-            ctx.push("\\textit{");
+            ctx.push(if ctx.italic_depth > 0 {
+                "\\emph{"
+            } else {
+                "\\textit{"
+            });
+            ctx.italic_depth += 1;
             for child in node.children() {
                 if child.kind() != SyntaxKind::Underscore {
                     convert_markup_node(child, ctx);
                 }
             }
+            ctx.italic_depth -= 1;
             ctx.push("}");
             ctx.last_token = TokenType::Command;
         }
@@ -507,37 +598,14 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
         SyntaxKind::Raw => {
             let (text, lang) = get_raw_text_with_lang(node);
             // Determine if this is a block code or inline code
-            // Block code: starts with ```, or contains newlines, or has language tag
-            let raw_text = node.text().to_string();
-            let is_block = raw_text.starts_with("```") || text.contains('\n') || lang.is_some();
+            // Block code: fenced with ```, or contains newlines, or has language tag
+            let fenced = node.cast::<ast::Raw>().is_some_and(|raw| raw.block());
+            let is_block = fenced || text.contains('\n') || lang.is_some();
 
             if is_block {
-                ctx.ensure_paragraph_break();
-                // Only use lstlisting if the language is supported, otherwise use verbatim
-                if let Some(ref language) = lang {
-                    if is_listings_supported(language) {
-                        ctx.push_line(&format!("\\begin{{lstlisting}}[language={}]", language));
-                        ctx.push(&text);
-                        ctx.newline();
-                        ctx.push_line("\\end{lstlisting}");
-                    } else {
-                        // Unsupported language - use verbatim with a comment
-                        ctx.push_line(&format!("% Code block (language: {})", language));
-                        ctx.push_line("\\begin{verbatim}");
-                        ctx.push(&text);
-                        ctx.newline();
-                        ctx.push_line("\\end{verbatim}");
-                    }
-                } else {
-                    ctx.push_line("\\begin{verbatim}");
-                    ctx.push(&text);
-                    ctx.newline();
-                    ctx.push_line("\\end{verbatim}");
-                }
+                emit_code_block(&text, lang.as_deref(), ctx);
             } else {
-                ctx.push("\\texttt{");
-                ctx.push(&escape_latex_text(&text));
-                ctx.push("}");
+                emit_inline_code(&text, ctx);
             }
             ctx.last_token = TokenType::Command;
         }
@@ -578,22 +646,7 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
         }
 
         // Math (inline or display)
-        SyntaxKind::Equation => {
-            let in_table = ctx.is_in_env(&EnvironmentContext::Table);
-            let is_block = !in_table && is_display_math(node);
-
-            // Convert math content to a temporary buffer first
-            let mut math_ctx = ConvertContext::new();
-            math_ctx.in_math = true;
-            for child in node.children() {
-                if child.kind() == SyntaxKind::Math {
-                    convert_math_node(child, &mut math_ctx);
-                }
-            }
-            let math_content = math_ctx.finalize().trim().to_string();
-
-            emit_rendered_math(ctx, &math_content, is_block);
-        }
+        SyntaxKind::Equation => convert_equation(node, ctx, None),
 
         SyntaxKind::Math => {
             // Delegate to math converter
@@ -605,7 +658,18 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
             convert_func_call_markup(node, ctx);
         }
 
-        // Content blocks
+        // Content blocks; one scoping `#set page(flipped: true)` is a landscape page
+        SyntaxKind::ContentBlock
+            if node
+                .cast::<ast::ContentBlock>()
+                .is_some_and(|block| sets_flipped_page(block.body())) =>
+        {
+            convert_landscape(ctx, |ctx| {
+                for child in node.children() {
+                    convert_markup_node(child, ctx);
+                }
+            });
+        }
         SyntaxKind::ContentBlock => {
             for child in node.children() {
                 convert_markup_node(child, ctx);
@@ -621,6 +685,17 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
 
         // Code block {expr} - skip braces, process inner content
         SyntaxKind::CodeBlock => {
+            // Blocks that print today's date, e.g. in another language; the
+            // evaluator keeps other blocks using it as they are
+            let today = uses_today(node);
+            if let Some(date) = today.then(|| today_block(node)).flatten() {
+                ctx.push(&date);
+                ctx.last_token = TokenType::Command;
+                return;
+            }
+            if !today && ctx.options.eval_inline_code && eval_inline_code(node, ctx) {
+                return;
+            }
             for child in node.children() {
                 // Skip the braces themselves
                 if child.kind() != SyntaxKind::LeftBrace && child.kind() != SyntaxKind::RightBrace {
@@ -629,11 +704,30 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
             }
         }
 
-        // Ignore set/show rules in markup (to avoid outputting them as text)
-        SyntaxKind::SetRule | SyntaxKind::ShowRule => {
+        // Embedded expression #(expr)
+        SyntaxKind::Parenthesized if ctx.options.eval_inline_code => {
+            if !eval_inline_code(node, ctx) {
+                for child in node.children() {
+                    convert_markup_node(child, ctx);
+                }
+            }
+        }
+
+        // Ignore set/show rules and imports in markup (to avoid outputting them as text)
+        SyntaxKind::SetRule | SyntaxKind::ShowRule | SyntaxKind::ModuleImport => {
             // Do nothing
         }
 
+        // `context` expressions: only content-producing bodies are converted
+        SyntaxKind::Contextual => {
+            if let Some(body) = node.cast::<ast::Contextual>().map(|c| c.body()) {
+                let body = ast::AstNode::to_untyped(body);
+                if matches!(body.kind(), SyntaxKind::FuncCall | SyntaxKind::ContentBlock) {
+                    convert_markup_node(body, ctx);
+                }
+            }
+        }
+
         // Handle identifiers that might be content
         SyntaxKind::Ident => {
             let text = node.text().to_string();
@@ -649,7 +743,12 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
             let text = get_simple_text(node);
             let label = text.trim_start_matches('@').trim();
             if !label.is_empty() {
-                ctx.push(&reference_to_latex(&Reference::new(label.to_string())));
+                let reference = if ctx.options.use_autoref {
+                    Reference::named(label.to_string())
+                } else {
+                    Reference::new(label.to_string())
+                };
+                ctx.push(&reference_to_latex(&reference));
                 ctx.last_token = TokenType::Command;
             }
         }
@@ -721,6 +820,93 @@ pub fn convert_markup_node(node: &SyntaxNode, ctx: &mut ConvertContext) {
     }
 }
 
+/// A line break that keeps the paragraph going
+fn push_linebreak(ctx: &mut ConvertContext) {
+    // In table cells, use \newline instead of \\ to avoid LR mode errors
+    if ctx.is_in_env(&EnvironmentContext::Table) {
+        ctx.push("\\newline ");
+    } else {
+        ctx.push("\\\\\n");
+    }
+    ctx.last_token = TokenType::Newline;
+}
+
+/// Push escaped text, spelling its typography the LaTeX way unless
+/// `keep_unicode` is set
+fn push_text(ctx: &mut ConvertContext, text: &str) {
+    if ctx.options.keep_unicode {
+        ctx.push(text);
+    } else {
+        ctx.push(&spell_typography(text));
+    }
+}
+
+/// Convert a heading, attaching a trailing `<label>` right after the sectioning command.
+fn convert_heading(node: &SyntaxNode, ctx: &mut ConvertContext, label: Option<&str>) {
+    let level = count_heading_markers(node) as i64 + i64::from(ctx.options.heading_offset);
+    let section_cmd = get_heading_command(level.max(1) as usize);
+
+    ctx.ensure_paragraph_break();
+    ctx.push(section_cmd);
+    ctx.push("{");
+
+    // Get heading content
+    for child in node.children() {
+        if !matches!(child.kind(), SyntaxKind::HeadingMarker | SyntaxKind::Space) {
+            convert_markup_node(child, ctx);
+        }
+    }
+
+    ctx.push("}\n");
+    if let Some(label) = label {
+        ctx.push(&label_to_latex(label));
+        ctx.push("\n");
+    }
+    ctx.last_token = TokenType::Newline;
+}
+
+/// Convert an equation; a labelled display equation becomes a numbered environment.
+fn convert_equation(node: &SyntaxNode, ctx: &mut ConvertContext, label: Option<&str>) {
+    let in_table = ctx.is_in_env(&EnvironmentContext::Table);
+    let is_block = !in_table && is_display_math(node);
+
+    // Convert math content to a temporary buffer first
+    let mut math_ctx = ConvertContext::new();
+    math_ctx.options = ctx.options.clone();
+    math_ctx.in_math = true;
+    for child in node.children() {
+        if child.kind() == SyntaxKind::Math {
+            convert_math_node(child, &mut math_ctx);
+        }
+    }
+    let math_content = math_ctx.finalize().trim().to_string();
+
+    emit_rendered_math(ctx, &math_content, is_block, label);
+}
+
+/// Evaluate an embedded code expression (`#{...}`, `#(...)`) with a scoped MiniEval
+/// and convert the resulting content through the normal content path.
+///
+/// Returns `false` if evaluation failed, so the caller can fall back to the
+/// syntactic conversion.
+fn eval_inline_code(node: &SyntaxNode, ctx: &mut ConvertContext) -> bool {
+    let Some(expr) = node.cast::<ast::Expr>() else {
+        return false;
+    };
+
+    let mut eval = MiniEval::new();
+    match eval.eval_expr(expr) {
+        Ok(value) => {
+            for warning in eval.take_warnings() {
+                ctx.add_warning(warning.message);
+            }
+            convert_content_nodes_to_latex(&value.into_content(), ctx);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 /// Convert Typst function calls to LaTeX (in markup mode)
 pub fn convert_func_call_markup(node: &SyntaxNode, ctx: &mut ConvertContext) {
     let children: Vec<_> = node.children().collect();
@@ -730,12 +916,24 @@ pub fn convert_func_call_markup(node: &SyntaxNode, ctx: &mut ConvertContext) {
 
     let func_name = children[0].text().to_string();
 
+    if is_page_counter_display(node) {
+        ctx.push("\\thepage");
+        ctx.last_token = TokenType::Command;
+        return;
+    }
+    if let Some(date) = today_display(node) {
+        ctx.push(&date);
+        ctx.last_token = TokenType::Command;
+        return;
+    }
+
     // Check if this is a math function that needs $ wrapping
     if is_math_func_in_markup(&func_name) {
+        let (open, close) = ctx.options.inline_math_delim.delimiters();
         ctx.in_math = true;
-        ctx.push("$");
+        ctx.push(open);
         super::math::convert_func_call(node, ctx);
-        ctx.push("$");
+        ctx.push(close);
         ctx.in_math = false;
         ctx.last_token = TokenType::Command;
         return;
@@ -745,8 +943,15 @@ pub fn convert_func_call_markup(node: &SyntaxNode, ctx: &mut ConvertContext) {
     if let Some(handler) = TYPST_MARKUP_HANDLERS.get(func_name.as_str()) {
         match handler {
             MarkupHandler::Wrap { prefix, suffix } => {
-                ctx.push(prefix);
+                let emph = func_name == "emph";
+                ctx.push(if emph && ctx.italic_depth > 0 {
+                    "\\emph{"
+                } else {
+                    prefix
+                });
+                ctx.italic_depth += usize::from(emph);
                 convert_func_args_text(&children, ctx);
+                ctx.italic_depth -= usize::from(emph);
                 ctx.push(suffix);
             }
             MarkupHandler::Environment { name } => {
@@ -773,6 +978,209 @@ pub fn convert_func_call_markup(node: &SyntaxNode, ctx: &mut ConvertContext) {
     ctx.last_token = TokenType::Command;
 }
 
+/// Whether a call is `counter(page).display(..)`
+fn is_page_counter_display(node: &SyntaxNode) -> bool {
+    let Some(ast::Expr::FieldAccess(access)) = node.cast::<ast::FuncCall>().map(|c| c.callee())
+    else {
+        return false;
+    };
+    let target: String = ast::AstNode::to_untyped(access.target())
+        .clone()
+        .into_text()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    access.field().as_str() == "display" && target == "counter(page)"
+}
+
+/// LaTeX for a code block that prints today's date: a lone
+/// `datetime.today().display(..)`, or the month name lookup `\today`
+/// becomes in languages other than English
+fn today_block(node: &SyntaxNode) -> Option<String> {
+    let block = node.cast::<ast::CodeBlock>()?;
+    let mut exprs = block.body().exprs();
+    let expr = exprs.next().filter(|_| exprs.next().is_none())?;
+    let expr = ast::AstNode::to_untyped(expr);
+    if let Some(date) = today_display(expr) {
+        return Some(date);
+    }
+    let compact = compact_source(expr);
+    let localized = compact.starts_with("str(datetime.today().day())+")
+        && compact.contains(".at(datetime.today().month()-1)+")
+        && compact.ends_with("+str(datetime.today().year())");
+    localized.then(|| "\\today".to_string())
+}
+
+/// LaTeX for `datetime.today().display(..)`. Patterns that spell out the
+/// month read like `\today`; numeric ones are built from the `\year`,
+/// `\month` and `\day` registers.
+fn today_display(node: &SyntaxNode) -> Option<String> {
+    let call = node.cast::<ast::FuncCall>()?;
+    let ast::Expr::FieldAccess(access) = call.callee() else {
+        return None;
+    };
+    if access.field().as_str() != "display"
+        || compact_source(ast::AstNode::to_untyped(access.target())) != "datetime.today()"
+    {
+        return None;
+    }
+    let pattern = call.args().items().find_map(|arg| match arg {
+        ast::Arg::Pos(ast::Expr::Str(s)) => Some(s.get().to_string()),
+        _ => None,
+    });
+    let Some(pattern) = pattern.filter(|p| !p.contains("repr:long")) else {
+        return Some("\\today".to_string());
+    };
+    let mut out = String::new();
+    let mut rest = pattern.as_str();
+    while let Some(start) = rest.find('[') {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        out.push_str(&escape_latex_text(&rest[..start]));
+        match rest[start + 1..start + len].split_whitespace().next() {
+            Some("year") => out.push_str("\\the\\year"),
+            Some("month") => out.push_str("\\the\\month"),
+            Some("day") => out.push_str("\\the\\day"),
+            _ => return Some("\\today".to_string()),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(&escape_latex_text(rest));
+    Some(out)
+}
+
+/// Header and footer from a document-level `#set page(..)` rule, as
+/// `[left, center, right]` LaTeX slots. `None` means the field was not set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageHeaders {
+    pub header: Option<[String; 3]>,
+    pub footer: Option<[String; 3]>,
+}
+
+impl PageHeaders {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_none() && self.footer.is_none()
+    }
+
+    /// Render the fancyhdr setup for the LaTeX preamble
+    pub fn to_fancyhdr(&self) -> String {
+        let mut setup = String::from(
+            "\\usepackage{fancyhdr}\n\\pagestyle{fancy}\n\\fancyhf{}\n\\renewcommand{\\headrulewidth}{0pt}\n",
+        );
+        for (command, slots) in [("fancyhead", &self.header), ("fancyfoot", &self.footer)] {
+            let Some(slots) = slots else {
+                continue;
+            };
+            for (position, value) in ["L", "C", "R"].iter().zip(slots) {
+                if !value.is_empty() {
+                    setup.push_str(&format!("\\{}[{}]{{{}}}\n", command, position, value));
+                }
+            }
+        }
+        setup
+    }
+}
+
+/// Collect `header` and `footer` from top-level `#set page(..)` rules. Later
+/// rules override earlier ones field by field; a page `numbering` without a
+/// footer puts the page number in the center of the footer, as Typst does.
+pub fn find_page_headers(root: &SyntaxNode) -> PageHeaders {
+    let mut headers = PageHeaders::default();
+    let mut numbered = false;
+    for node in root.children() {
+        let Some(rule) = node.cast::<ast::SetRule>() else {
+            continue;
+        };
+        if !matches!(rule.target(), ast::Expr::Ident(ident) if ident.as_str() == "page") {
+            continue;
+        }
+        for arg in rule.args().items() {
+            let ast::Arg::Named(named) = arg else {
+                continue;
+            };
+            match named.name().as_str() {
+                "header" => headers.header = Some(page_slots(named.expr())),
+                "footer" => headers.footer = Some(page_slots(named.expr())),
+                "numbering" => numbered = !matches!(named.expr(), ast::Expr::None(_)),
+                _ => {}
+            }
+        }
+    }
+    if numbered && headers.header.is_some() && headers.footer.is_none() {
+        headers.footer = Some([String::new(), "\\thepage".to_string(), String::new()]);
+    }
+    headers
+}
+
+/// Split a header/footer value into `[left, center, right]` LaTeX slots.
+/// Content is left-aligned by default; `#h(1fr)` separates the slots.
+fn page_slots(expr: ast::Expr) -> [String; 3] {
+    let latex = |node: &SyntaxNode| {
+        let mut ctx = ConvertContext::new();
+        convert_markup_node(node, &mut ctx);
+        ctx.finalize()
+    };
+    let node = ast::AstNode::to_untyped(expr).clone();
+    match expr {
+        ast::Expr::None(_) => Default::default(),
+        ast::Expr::Contextual(contextual) => page_slots(contextual.body()),
+        ast::Expr::FuncCall(call) => {
+            let name = match call.callee() {
+                ast::Expr::Ident(ident) => ident.as_str().to_string(),
+                _ => String::new(),
+            };
+            let positional: Vec<ast::Expr> = call
+                .args()
+                .items()
+                .filter_map(|arg| match arg {
+                    ast::Arg::Pos(expr) => Some(expr),
+                    _ => None,
+                })
+                .collect();
+            match (name.as_str(), positional.as_slice()) {
+                ("align", [alignment, body]) => {
+                    let alignment = ast::AstNode::to_untyped(*alignment).clone().into_text();
+                    let text = latex(ast::AstNode::to_untyped(*body));
+                    let mut slots: [String; 3] = Default::default();
+                    let position = if alignment.contains("center") {
+                        1
+                    } else if alignment.contains("right") || alignment.contains("end") {
+                        2
+                    } else {
+                        0
+                    };
+                    slots[position] = text;
+                    slots
+                }
+                ("grid" | "stack", cells) if !cells.is_empty() && cells.len() <= 3 => {
+                    let mut cells: Vec<String> = cells
+                        .iter()
+                        .map(|cell| page_slots(*cell).concat())
+                        .collect();
+                    if cells.len() == 2 {
+                        cells.insert(1, String::new());
+                    }
+                    cells.resize(3, String::new());
+                    [cells[0].clone(), cells[1].clone(), cells[2].clone()]
+                }
+                _ => [latex(&node), String::new(), String::new()],
+            }
+        }
+        _ => {
+            let text = latex(&node);
+            let parts: Vec<&str> = text.split("\\hfill").map(str::trim).collect();
+            match parts.as_slice() {
+                [left, right] => [left.to_string(), String::new(), right.to_string()],
+                [left, middle @ .., right] if !middle.is_empty() => {
+                    [left.to_string(), middle.join(" "), right.to_string()]
+                }
+                _ => [text.trim().to_string(), String::new(), String::new()],
+            }
+        }
+    }
+}
+
 /// Handle special markup functions that need custom logic
 fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     match func_name {
@@ -788,10 +1196,33 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
             convert_figure_to_latex(children, ctx);
         }
 
+        "wrap-content" => {
+            convert_wrap_content_to_latex(children, ctx);
+        }
+
+        "pagebreak" => {
+            // Floats placed since the last page break are flushed first
+            let page = ["\\newpage", "\\clearpage"]
+                .iter()
+                .filter_map(|command| ctx.output.rfind(command))
+                .max()
+                .map_or(ctx.output.as_str(), |start| &ctx.output[start..]);
+            if page.contains("\\begin{figure") || page.contains("\\begin{table") {
+                ctx.push("\\clearpage");
+            } else {
+                ctx.push("\\newpage");
+            }
+            ctx.last_token = TokenType::Command;
+        }
+
         "link" => {
             convert_link_to_latex(children, ctx);
         }
 
+        "underline" | "strike" | "overline" | "highlight" => {
+            convert_text_decoration(func_name, children, ctx);
+        }
+
         "cite" => {
             convert_cite_to_latex(children, ctx);
         }
@@ -814,6 +1245,33 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
             ctx.push("}");
         }
 
+        // Notes from the drafting package
+        "margin-note" | "inline-note" => {
+            let color = FuncArgs::from_func_call(children)
+                .named_color(if func_name == "inline-note" {
+                    "fill"
+                } else {
+                    "stroke"
+                })
+                .and_then(normalize_typst_color_expr)
+                .map(|color| typst_color_to_latex_spec(&color))
+                .filter(|spec| spec.model.is_none());
+            let mut options = Vec::new();
+            if func_name == "inline-note" {
+                options.push("inline".to_string());
+            }
+            if let Some(spec) = color {
+                options.push(format!("color={}", spec.value));
+            }
+            if options.is_empty() {
+                ctx.push("\\todo{");
+            } else {
+                ctx.push(&format!("\\todo[{}]{{", options.join(", ")));
+            }
+            convert_func_args_text(children, ctx);
+            ctx.push("}");
+        }
+
         "caption" => {
             ctx.push("\\caption{");
             convert_func_args_text(children, ctx);
@@ -840,12 +1298,34 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
         }
 
         // Layout
+        "block" if ctx.options.tcolorbox_blocks && convert_block_to_tcolorbox(children, ctx) => {}
         "pad" | "block" => {
             // For now, just output content without the box/padding wrapper to avoid "width inset" text
             // In the future, this could map to \parbox or minipage
             convert_func_args_text(children, ctx);
         }
 
+        // Absolute positioning
+        "place" => {
+            convert_place_func(children, ctx);
+        }
+
+        // A page of its own, turned to landscape when flipped
+        "page" => {
+            let flipped = FuncArgs::from_func_call(children).named_bool("flipped");
+            if flipped == Some(true) {
+                let body = capture_output(ctx, |ctx| convert_func_args_text(children, ctx));
+                if let Some(float) = sideways_float(&body) {
+                    ctx.ensure_paragraph_break();
+                    ctx.push_line(&float);
+                } else {
+                    convert_landscape(ctx, |ctx| ctx.push(&body));
+                }
+            } else {
+                convert_func_args_text(children, ctx);
+            }
+        }
+
         // Rotation
         "rotate" => {
             convert_rotate_func(children, ctx);
@@ -908,6 +1388,10 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
 
         // Box/Frame
         "box" => {
+            if let Some(fill) = fill_box_command(children) {
+                ctx.push(fill);
+                return;
+            }
             ctx.push("\\fbox{");
             convert_func_args_text(children, ctx);
             ctx.push("}");
@@ -942,12 +1426,19 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
             convert_raw_to_latex(children, ctx);
         }
 
-        // Horizontal spacing: h(1fr) -> \hfill, h(1em) -> \hspace{1em}
+        // Horizontal spacing: h(1fr) -> \hfill, h(2fr) -> \hspace{\stretch{2}},
+        // h(1em) -> \hspace{1em}
         "h" => {
             if let Some(args) = children.get(1) {
-                let arg_text = args.text().to_string();
+                let arg_text = get_simple_text(args);
                 match parse_spacing_spec(&arg_text) {
-                    Some(SpacingSpec::Flex(_)) => ctx.push("\\hfill"),
+                    Some(SpacingSpec::Flex(fr)) if fr == "1fr" => ctx.push("\\hfill"),
+                    Some(SpacingSpec::Flex(fr)) => {
+                        ctx.push(&format!(
+                            "\\hspace{{\\stretch{{{}}}}}",
+                            fr.trim_end_matches("fr")
+                        ));
+                    }
                     Some(SpacingSpec::Fixed(value)) => {
                         ctx.push(&format!("\\hspace{{{}}}", value));
                     }
@@ -959,7 +1450,7 @@ fn handle_special_markup_func(func_name: &str, children: &[&SyntaxNode], ctx: &m
         // Vertical spacing: v(1em) -> \vspace{1em}
         "v" => {
             if let Some(args) = children.get(1) {
-                let arg_text = args.text().to_string();
+                let arg_text = get_simple_text(args);
                 if arg_text.contains("fr") {
                     ctx.push("\\vfill");
                 } else if let Some(value) = extract_length_value(&arg_text) {
@@ -1029,32 +1520,32 @@ fn convert_text_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
         }
     }
 
-    if let Some(s) = style {
-        if s == "\"italic\"" || s == "italic" {
-            ctx.push("\\textit{");
-            suffix_count += 1;
-        }
+    let italic = style
+        .as_deref()
+        .is_some_and(|s| s == "\"italic\"" || s == "italic");
+    if italic {
+        ctx.push("\\textit{");
+        suffix_count += 1;
     }
 
     if let Some(s) = size {
-        // Simple heuristic for size mapping
-        if s.contains("pt") {
-            if let Ok(pt) = s.trim_end_matches("pt").trim().parse::<f64>() {
-                if pt >= 20.0 {
-                    ctx.push("{\\Huge ");
-                } else if pt >= 17.0 {
-                    ctx.push("{\\huge ");
-                } else if pt >= 14.0 {
-                    ctx.push("{\\Large ");
-                } else if pt >= 12.0 {
-                    ctx.push("{\\large ");
-                } else if pt <= 8.0 {
-                    ctx.push("{\\small ");
-                } else {
-                    ctx.push("{");
-                }
-                suffix_count += 1;
-            }
+        // Nearest named size of the standard 10pt classes. Evaluated input
+        // carries the length as a content block (`size: [8pt]`).
+        let s = s
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim();
+        let pt = if let Some(pt) = s.strip_suffix("pt") {
+            pt.trim().parse::<f64>().ok()
+        } else if let Some(em) = s.strip_suffix("em") {
+            em.trim().parse::<f64>().ok().map(|em| em * 10.0)
+        } else {
+            None
+        };
+        if let Some(pt) = pt {
+            ctx.push(&format!("{{\\{} ", nearest_font_size_command(pt)));
+            suffix_count += 1;
         }
     }
 
@@ -1063,9 +1554,11 @@ fn convert_text_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
         // Might be just setting style without content, or content is in a later block
         // For now, do nothing
     } else {
+        ctx.italic_depth += usize::from(italic);
         for node in content_nodes {
             convert_markup_node(node, ctx);
         }
+        ctx.italic_depth -= usize::from(italic);
     }
 
     // Close wrappers
@@ -1097,18 +1590,326 @@ fn convert_rotate_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
         }
     }
 
-    // Output \rotatebox{angle}{content}
-    let angle_deg = angle.unwrap_or(0.0);
-    ctx.push(&format!("\\rotatebox{{{}}}", angle_deg));
-    ctx.push("{");
+    let body = capture_output(ctx, |ctx| {
+        for node in content_nodes {
+            convert_markup_node(node, ctx);
+        }
+    });
 
-    for node in content_nodes {
-        convert_markup_node(node, ctx);
+    // A figure turned a quarter is a rotating float
+    let angle = angle.unwrap_or(0.0);
+    if angle % 180.0 != 0.0 && angle % 90.0 == 0.0 {
+        if let Some(float) = sideways_float(&body) {
+            ctx.ensure_paragraph_break();
+            ctx.push_line(&float);
+            return;
+        }
     }
 
+    // Typst turns clockwise, \rotatebox counterclockwise
+    ctx.push(&format!("\\rotatebox{{{}}}", 0.0 - angle));
+    ctx.push("{");
+    ctx.push(&body);
     ctx.push("}");
 }
 
+/// The output of `body`, taken from the context instead of added to it
+fn capture_output(ctx: &mut ConvertContext, body: impl FnOnce(&mut ConvertContext)) -> String {
+    let outer = std::mem::take(&mut ctx.output);
+    body(ctx);
+    std::mem::replace(&mut ctx.output, outer)
+}
+
+/// Convert a `block` with a fill or a stroke to a `tcolorbox`, a bold first
+/// line becoming its title. False, with nothing written, for a plain block.
+fn convert_block_to_tcolorbox(children: &[&SyntaxNode], ctx: &mut ConvertContext) -> bool {
+    let args = FuncArgs::from_func_call(children);
+    let fill = args.named("fill");
+    let stroke = args.named("stroke");
+    if fill.is_none() && stroke.is_none() {
+        return false;
+    }
+    // Colors with a model cannot stand in tcolorbox options
+    let color = |color: &str| {
+        let spec = typst_color_to_latex_spec(color);
+        spec.model.is_none().then_some(spec.value)
+    };
+
+    let mut options = Vec::new();
+    if let Some(fill) = fill.and_then(color) {
+        options.push(format!("colback={}", fill));
+    }
+    match stroke.map(str::trim) {
+        Some("none") => options.push("boxrule=0pt".to_string()),
+        Some(stroke) => {
+            for part in stroke.split('+').map(str::trim) {
+                if let Some(width) = extract_length_value(part) {
+                    options.push(format!("boxrule={}", width));
+                } else if let Some(frame) = color(part) {
+                    options.push(format!("colframe={}", frame));
+                }
+            }
+        }
+        None => options.push("boxrule=0pt".to_string()),
+    }
+    match args.named_length("radius") {
+        Some(radius) => options.push(format!("arc={}", radius)),
+        None => options.push("sharp corners".to_string()),
+    }
+
+    let body = capture_output(ctx, |ctx| convert_func_args_text(children, ctx));
+    let mut body = body.trim();
+    if let Some(rest) = body.strip_prefix("\\textbf{") {
+        if let Some((title, rest)) = rest.split_once("} \\\\") {
+            if !title.contains('}') {
+                options.insert(0, format!("title={{{}}}", title));
+                body = rest.trim_start();
+            }
+        }
+    }
+
+    ctx.ensure_paragraph_break();
+    ctx.push_line(&format!("\\begin{{tcolorbox}}[{}]", options.join(", ")));
+    ctx.push_line(body);
+    ctx.push_line("\\end{tcolorbox}");
+    true
+}
+
+/// A lone converted `figure` or `table` float as rotating's `sidewaysfigure`
+/// or `sidewaystable`, which are always set on a page of their own
+fn sideways_float(body: &str) -> Option<String> {
+    let body = body.trim();
+    ["figure", "table"].iter().find_map(|env| {
+        let inner = body
+            .strip_prefix(&format!("\\begin{{{}}}[htbp]", env))?
+            .strip_suffix(&format!("\\end{{{}}}", env))?;
+        if inner.contains("\\begin{figure}") || inner.contains("\\begin{table}") {
+            return None;
+        }
+        Some(format!(
+            "\\begin{{sideways{env}}}{inner}\\end{{sideways{env}}}",
+            env = env,
+            inner = inner
+        ))
+    })
+}
+
+/// Wrap the output of `body` in a pdflscape `landscape` environment
+fn convert_landscape(ctx: &mut ConvertContext, body: impl FnOnce(&mut ConvertContext)) {
+    ctx.ensure_paragraph_break();
+    ctx.push_line("\\begin{landscape}");
+    body(ctx);
+    ctx.newline();
+    ctx.push_line("\\end{landscape}");
+}
+
+/// Convert `#place(alignment, dx: .., dy: .., body)` to a textpos
+/// `textblock*` or a TikZ overlay node, by [`PlaceBackend`]. The alignment
+/// and offsets are taken relative to the page.
+fn convert_place_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
+    let args = FuncArgs::from_func_call(children);
+    let mut horiz = "left";
+    let mut vert = "top";
+    let mut body = Vec::new();
+    for arg in args.iter().filter(|arg| arg.is_positional) {
+        let words: Vec<&str> = arg.value.split('+').map(str::trim).collect();
+        let is_alignment = words.iter().all(|word| {
+            matches!(
+                *word,
+                "left" | "start" | "center" | "right" | "end" | "top" | "horizon" | "bottom"
+            )
+        });
+        if !is_alignment {
+            body.push(arg.node);
+            continue;
+        }
+        for word in words {
+            match word {
+                "left" | "start" => horiz = "left",
+                "center" => horiz = "center",
+                "right" | "end" => horiz = "right",
+                other => vert = other,
+            }
+        }
+    }
+    let dx = args
+        .named("dx")
+        .and_then(|dx| place_offset(dx, "\\paperwidth"));
+    let dy = args
+        .named("dy")
+        .and_then(|dy| place_offset(dy, "\\paperheight"));
+
+    ctx.ensure_paragraph_break();
+    match ctx.options.place_backend {
+        PlaceBackend::Textpos => {
+            let (x, anchor_x, align) = match horiz {
+                "center" => ("0.5\\paperwidth", "0.5", "\\centering\n"),
+                "right" => ("\\paperwidth", "1", "\\raggedleft\n"),
+                _ => ("0pt", "0", ""),
+            };
+            let (y, anchor_y) = match vert {
+                "horizon" => ("0.5\\paperheight", "0.5"),
+                "bottom" => ("\\paperheight", "1"),
+                _ => ("0pt", "0"),
+            };
+            ctx.push(&format!(
+                "\\begin{{textblock*}}{{\\paperwidth}}[{},{}]({},{})\n{}",
+                anchor_x,
+                anchor_y,
+                shifted_position(x, dx.as_deref()),
+                shifted_position(y, dy.as_deref()),
+                align
+            ));
+            for node in body {
+                convert_markup_node(node, ctx);
+            }
+            ctx.push("\n\\end{textblock*}\n");
+        }
+        PlaceBackend::TikZ => {
+            let anchor = match (vert, horiz) {
+                ("horizon", "center") => "center".to_string(),
+                (vert, horiz) => {
+                    let vert = match vert {
+                        "horizon" => "",
+                        "bottom" => "south",
+                        _ => "north",
+                    };
+                    let horiz = match horiz {
+                        "center" => "",
+                        "right" => "east",
+                        _ => "west",
+                    };
+                    format!("{} {}", vert, horiz).trim().to_string()
+                }
+            };
+            let mut options = vec![format!("anchor={}", anchor)];
+            if let Some(dx) = dx {
+                options.push(format!("xshift={}", dx));
+            }
+            if let Some(dy) = dy {
+                // Typst measures dy downwards, TikZ upwards
+                let up = dy
+                    .strip_prefix('-')
+                    .map_or_else(|| format!("-{}", dy), str::to_string);
+                options.push(format!("yshift={}", up));
+            }
+            ctx.push(&format!(
+                "\\begin{{tikzpicture}}[remember picture, overlay]\n\\node[{}] at (current page.{}) {{",
+                options.join(", "),
+                anchor
+            ));
+            for node in body {
+                convert_markup_node(node, ctx);
+            }
+            ctx.push("};\n\\end{tikzpicture}\n");
+        }
+    }
+}
+
+/// A `dx`/`dy` of `#place(..)` as a LaTeX length; a ratio is a share of
+/// the page dimension `page`
+fn place_offset(value: &str, page: &str) -> Option<String> {
+    if let Some(percent) = value.trim().strip_suffix('%') {
+        let ratio = percent.trim().parse::<f64>().ok()? / 100.0;
+        return Some(format!("{}{}", ratio, page));
+    }
+    extract_length_value(value)
+}
+
+/// A page position moved by an optional offset
+fn shifted_position(base: &str, offset: Option<&str>) -> String {
+    match offset {
+        None => base.to_string(),
+        Some(offset) if base == "0pt" => offset.to_string(),
+        Some(offset) if offset.starts_with('-') => format!("\\dimexpr{}{}\\relax", base, offset),
+        Some(offset) => format!("\\dimexpr{}+{}\\relax", base, offset),
+    }
+}
+
+/// `#place(..)` calls whose `float: true` or `scope: "parent"` the LaTeX
+/// positioning cannot reproduce
+pub fn find_unreplicable_placements(root: &SyntaxNode) -> Vec<(String, SourceSpan)> {
+    fn visit(node: &SyntaxNode, offset: usize, found: &mut Vec<(String, SourceSpan)>) {
+        if let Some(call) = node.cast::<ast::FuncCall>() {
+            let is_place =
+                matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "place");
+            if is_place {
+                for arg in call.args().items() {
+                    let ast::Arg::Named(named) = arg else {
+                        continue;
+                    };
+                    let value = ast::AstNode::to_untyped(named.expr()).clone().into_text();
+                    let message = match (named.name().as_str(), value.as_str()) {
+                        ("float", "true") => {
+                            "`place(float: true)` keeps its page position in LaTeX instead of floating"
+                        }
+                        ("scope", "\"parent\"") => {
+                            "`place(scope: \"parent\")` is positioned on the page, not across columns"
+                        }
+                        _ => continue,
+                    };
+                    found.push((
+                        message.to_string(),
+                        SourceSpan::new(offset, offset + node.len()),
+                    ));
+                }
+            }
+        }
+        let mut child_offset = offset;
+        for child in node.children() {
+            visit(child, child_offset, found);
+            child_offset += child.len();
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(root, 0, &mut found);
+    found
+}
+
+/// `#table(..)` calls whose `fill:` the LaTeX table cannot shade
+pub fn find_unshaded_tables(root: &SyntaxNode) -> Vec<(String, SourceSpan)> {
+    fn visit(node: &SyntaxNode, offset: usize, found: &mut Vec<(String, SourceSpan)>) {
+        if let Some(call) = node.cast::<ast::FuncCall>() {
+            let is_table =
+                matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "table");
+            if is_table {
+                for arg in call.args().items() {
+                    let ast::Arg::Named(named) = arg else {
+                        continue;
+                    };
+                    if named.name().as_str() != "fill" {
+                        continue;
+                    }
+                    let value = ast::AstNode::to_untyped(named.expr()).clone().into_text();
+                    let message = match table_fill(&value) {
+                        None => format!("Table fill `{}` not recognized, shading dropped", value),
+                        Some(TableFill::Rows(odd, even))
+                            if rowcolors_command(odd.as_deref(), even.as_deref()).is_none() =>
+                        {
+                            format!(
+                                "Table fill `{}` uses a color model \\rowcolors cannot take, shading dropped",
+                                value
+                            )
+                        }
+                        Some(_) => continue,
+                    };
+                    found.push((message, SourceSpan::new(offset, offset + node.len())));
+                }
+            }
+        }
+        let mut child_offset = offset;
+        for child in node.children() {
+            visit(child, child_offset, found);
+            child_offset += child.len();
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(root, 0, &mut found);
+    found
+}
+
 /// Convert #rect(...)[content] to appropriate LaTeX
 /// - If has content with fill: use \colorbox (preserves content)
 /// - If no content with fill and height: use \rule (solid rectangle)
@@ -1280,6 +2081,12 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     let mut hlines: Vec<(usize, LatexHLine)> = Vec::new(); // (cell_index, hline)
     let mut in_header = false;
     let mut header_end_idx: Option<usize> = None;
+    let mut data_source: Option<(String, char)> = None;
+    let mut spacing = args
+        .named_text("inset")
+        .map(table_spacing_commands)
+        .unwrap_or_default();
+    let fill = args.named_text("fill").map(str::to_string);
 
     if let Some(args_node) = children.get(1) {
         for child in args_node.children() {
@@ -1307,6 +2114,7 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                 }
                 SyntaxKind::ContentBlock | SyntaxKind::Markup | SyntaxKind::Equation => {
                     let mut cell_ctx = ConvertContext::new();
+                    cell_ctx.options = ctx.options.clone();
                     cell_ctx.push_env(EnvironmentContext::Table);
                     convert_markup_node(child, &mut cell_ctx);
                     let content = cell_ctx.finalize();
@@ -1331,6 +2139,7 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                                         | SyntaxKind::Equation => {
                                             // Plain content like [Type A] or $x$
                                             let mut cell_ctx = ConvertContext::new();
+                                            cell_ctx.options = ctx.options.clone();
                                             cell_ctx.push_env(EnvironmentContext::Table);
                                             convert_markup_node(header_child, &mut cell_ctx);
                                             let content = cell_ctx.finalize();
@@ -1357,6 +2166,7 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                                                 } else {
                                                     // Other function call (e.g., text(...))
                                                     let mut cell_ctx = ConvertContext::new();
+                                                    cell_ctx.options = ctx.options.clone();
                                                     cell_ctx.push_env(EnvironmentContext::Table);
                                                     convert_markup_node(
                                                         header_child,
@@ -1415,6 +2225,7 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
 
                                 if let Some(content_arg) = content_node {
                                     let mut cell_ctx = ConvertContext::new();
+                                    cell_ctx.options = ctx.options.clone();
                                     cell_ctx.push_env(EnvironmentContext::Table);
                                     convert_markup_node(&content_arg, &mut cell_ctx);
                                     let content = cell_ctx.finalize();
@@ -1427,6 +2238,7 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                         } else {
                             // Regular cell (other function call)
                             let mut cell_ctx = ConvertContext::new();
+                            cell_ctx.options = ctx.options.clone();
                             cell_ctx.push_env(EnvironmentContext::Table);
                             convert_markup_node(child, &mut cell_ctx);
                             let content = cell_ctx.finalize();
@@ -1436,6 +2248,12 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                         }
                     }
                 }
+                // Cells spread from a data file: `..csv("data.csv").flatten()`
+                SyntaxKind::Spread => {
+                    if let Some(source) = csv_spread_source(child) {
+                        data_source = Some(source);
+                    }
+                }
                 // Handle string arguments as table cells (from MiniEval expansion)
                 SyntaxKind::Str => {
                     let text = child.text().to_string();
@@ -1459,6 +2277,31 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
         }
     }
 
+    // A data file the evaluator did not read into cells: csvsimple loads it
+    // when the LaTeX document is compiled, or a placeholder stands in for
+    // the cells that could not be materialized
+    if let Some((path, separator)) = data_source.filter(|_| cells.is_empty()) {
+        if !ctx.options.materialize_data {
+            let separator = match separator {
+                ';' => ", separator=semicolon",
+                '\t' => ", separator=tab",
+                '|' => ", separator=pipe",
+                _ => "",
+            };
+            ctx.ensure_paragraph_break();
+            ctx.push(&format!(
+                "\\csvautotabular[no head{}]{{{}}}",
+                separator, path
+            ));
+            return;
+        }
+        cells.push(LatexCell::new(format!(
+            "Missing data file \\texttt{{{}}}",
+            escape_latex_text(&path)
+        )));
+        columns = 1;
+    }
+
     // Infer columns if not specified
     if columns == 0 {
         columns = if cells.len() >= 4 {
@@ -1476,6 +2319,16 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     // Create the table generator
     let mut generator = LatexTableGenerator::new(columns, col_aligns);
 
+    // Shading: whole columns in the spec, alternating rows by \rowcolors
+    // (from xcolor's `table` option) kept local to this table
+    match fill.as_deref().and_then(table_fill) {
+        Some(TableFill::Columns(colors)) => generator.column_fills = colors,
+        Some(TableFill::Rows(odd, even)) => {
+            spacing.extend(rowcolors_command(odd.as_deref(), even.as_deref()))
+        }
+        None => {}
+    }
+
     // Process cells row by row
     let mut current_row: Vec<LatexCell> = Vec::new();
     let mut cell_idx = 0;
@@ -1532,7 +2385,162 @@ fn convert_table_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     }
 
     ctx.ensure_paragraph_break();
-    ctx.push(&generator.generate_latex());
+    if spacing.is_empty() {
+        ctx.push(&generator.generate_latex());
+    } else {
+        // Keep the spacing changes local to this table
+        ctx.push(&format!("{{{}\n", spacing.concat()));
+        ctx.push(&generator.generate_latex());
+        ctx.push("}");
+    }
+}
+
+/// Shading of a whole table from its `fill:`
+enum TableFill {
+    /// Colors of the columns, repeated across the table
+    Columns(Vec<Option<String>>),
+    /// Colors of the odd and even rows, as LaTeX counts them from one
+    Rows(Option<String>, Option<String>),
+}
+
+/// Recognize a table `fill:`: a color, an array of column colors, or a
+/// closure alternating on `calc.odd(y)`/`calc.even(y)` such as
+/// `(_, y) => if calc.odd(y) { gray.lighten(80%) }`
+fn table_fill(fill: &str) -> Option<TableFill> {
+    // `none` for no shading, otherwise a color
+    let color = |text: &str| match text.trim() {
+        "none" => Some(None),
+        text => normalize_typst_color_expr(text).map(Some),
+    };
+    let fill = fill.trim();
+
+    let Some((params, body)) = fill.split_once("=>") else {
+        let Some(inner) = fill.strip_prefix('(').and_then(|f| f.strip_suffix(')')) else {
+            return Some(TableFill::Columns(vec![color(fill)?]));
+        };
+        let mut colors = Vec::new();
+        let mut depth = 0usize;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    colors.push(color(&inner[start..i])?);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if !inner[start..].trim().is_empty() {
+            colors.push(color(&inner[start..])?);
+        }
+        return Some(TableFill::Columns(colors));
+    };
+
+    let params = params.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let (_, row) = params.split_once(',')?;
+    let row = row.trim();
+    let body = body.trim().strip_prefix("if ")?.trim_start();
+    let (odd, rest) = match body.strip_prefix("calc.odd(") {
+        Some(rest) => (true, rest),
+        None => (false, body.strip_prefix("calc.even(")?),
+    };
+    let rest = rest.trim_start().strip_prefix(row)?.trim_start();
+    let rest = rest.strip_prefix(')')?.trim_start().strip_prefix('{')?;
+    let (then, rest) = rest.split_once('}')?;
+    let otherwise = match rest.trim() {
+        "" => None,
+        rest => {
+            let branch = rest.strip_prefix("else")?.trim_start().strip_prefix('{')?;
+            color(branch.strip_suffix('}')?)?
+        }
+    };
+    let then = color(then)?;
+    // Typst counts rows from zero, so its odd rows are LaTeX's even ones
+    Some(if odd {
+        TableFill::Rows(otherwise, then)
+    } else {
+        TableFill::Rows(then, otherwise)
+    })
+}
+
+/// `\rowcolors` for the odd and even row colors, an empty color leaving
+/// its rows unshaded; xcolor takes no color model here
+fn rowcolors_command(odd: Option<&str>, even: Option<&str>) -> Option<String> {
+    let spec = |color: Option<&str>| match color {
+        None => Some(String::new()),
+        Some(color) => {
+            let spec = typst_color_to_latex_spec(color);
+            spec.model.is_none().then_some(spec.value)
+        }
+    };
+    Some(format!(
+        "\\rowcolors{{1}}{{{}}}{{{}}}",
+        spec(odd)?,
+        spec(even)?
+    ))
+}
+
+/// Path and delimiter of a table argument spread from `csv(..)`
+fn csv_spread_source(spread: &SyntaxNode) -> Option<(String, char)> {
+    let text = spread.clone().into_text();
+    let call = text
+        .trim_start_matches('.')
+        .trim_start()
+        .strip_prefix("csv(")?;
+    let path = call.split('"').nth(1)?;
+    let delimiter = call
+        .split_once("delimiter:")
+        .and_then(|(_, rest)| rest.split('"').nth(1))
+        .map_or(',', |delimiter| match delimiter {
+            "\\t" => '\t',
+            other => other.chars().next().unwrap_or(','),
+        });
+    Some((path.to_string(), delimiter))
+}
+
+/// LaTeX spacing parameters for a table `inset:`, padding Typst's 5pt
+/// default: `\tabcolsep` for the horizontal and `\arraystretch` for the
+/// vertical padding
+fn table_spacing_commands(inset: &str) -> Vec<String> {
+    let inset = inset.trim();
+    let (mut x, mut y) = (None, None);
+    if inset.starts_with('(') {
+        let inner = inset.trim_start_matches('(').trim_end_matches(')');
+        for entry in inner.split(',') {
+            let Some((key, value)) = entry.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "x" => x = Some(value),
+                "y" => y = Some(value),
+                "rest" => {
+                    x = x.or(Some(value));
+                    y = y.or(Some(value));
+                }
+                _ => {}
+            }
+        }
+    } else {
+        x = Some(inset);
+        y = Some(inset);
+    }
+
+    let mut commands = Vec::new();
+    if let Some(length) = y
+        .and_then(|y| y.strip_suffix("pt"))
+        .and_then(|pt| pt.trim().parse::<f64>().ok())
+        .filter(|pt| *pt != 5.0)
+    {
+        let stretch = (length / 5.0 * 100.0).round() / 100.0;
+        commands.push(format!("\\renewcommand{{\\arraystretch}}{{{}}}", stretch));
+    }
+    if let Some(length) = x.filter(|x| *x != "5pt").and_then(extract_length_value) {
+        commands.push(format!("\\setlength{{\\tabcolsep}}{{{}}}", length));
+    }
+    commands
 }
 
 /// Parse Typst align specification to LaTeX column alignments
@@ -1613,6 +2621,67 @@ fn infer_table_columns(value: &str) -> Option<usize> {
 // Enhanced Figure Conversion
 // ============================================================================
 
+/// Convert `wrap-content(fixed, body, align: ..)` from the wrap-it package to
+/// a `wrapfigure` followed by the text that flows around it. The width comes
+/// from a `block`/`box` around the figure.
+fn convert_wrap_content_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
+    let args = FuncArgs::from_func_call(children);
+    let side = match args.named("align") {
+        Some(align) if align.contains("left") || align.contains("start") => "l",
+        _ => "r",
+    };
+    let fixed = args.positional_node(0);
+    let width = fixed
+        .filter(|node| node.kind() == SyntaxKind::FuncCall)
+        .and_then(|node| {
+            let call: Vec<&SyntaxNode> = node.children().collect();
+            let callee = call.first()?.text().to_string();
+            if !matches!(callee.as_str(), "block" | "box") {
+                return None;
+            }
+            FuncArgs::from_func_call(&call)
+                .named("width")
+                .map(convert_dimension_to_latex)
+        })
+        .unwrap_or_else(|| "0.50\\textwidth".to_string());
+
+    let mut fixed_ctx = ConvertContext::new();
+    fixed_ctx.options = ctx.options.clone();
+    if let Some(node) = fixed {
+        convert_markup_node(node, &mut fixed_ctx);
+    }
+    // Widths inside the float are relative to the float, not the page
+    let figure = fixed_ctx.finalize().replace("\\textwidth", "\\linewidth");
+    let figure = figure.trim();
+    let begin = format!("\\begin{{wrapfigure}}{{{}}}{{{}}}", side, width);
+
+    ctx.ensure_paragraph_break();
+    match figure
+        .strip_prefix("\\begin{figure}")
+        .and_then(|rest| rest.strip_suffix("\\end{figure}"))
+    {
+        Some(inner) => {
+            // Float placement options do not apply to wrapfigure
+            let inner = match inner.strip_prefix('[') {
+                Some(rest) => rest.split_once(']').map_or(rest, |(_, rest)| rest),
+                None => inner,
+            };
+            ctx.push_line(&begin);
+            ctx.push_line(inner.trim());
+        }
+        None => {
+            ctx.push_line(&begin);
+            ctx.push_line("\\centering");
+            ctx.push_line(figure);
+        }
+    }
+    ctx.push_line("\\end{wrapfigure}");
+
+    if let Some(body) = args.positional_node(1) {
+        convert_markup_node(body, ctx);
+    }
+}
+
 fn convert_figure_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     let mut caption: Option<String> = None;
     let mut label: Option<String> = None;
@@ -1637,6 +2706,7 @@ fn convert_figure_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                                         && n.kind() != SyntaxKind::Space
                                 }) {
                                     let mut cap_ctx = ConvertContext::new();
+                                    cap_ctx.options = ctx.options.clone();
                                     convert_markup_node(value_node, &mut cap_ctx);
                                     caption = Some(cap_ctx.finalize());
                                 }
@@ -1666,12 +2736,20 @@ fn convert_figure_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                     }
 
                     let mut content_ctx = ConvertContext::new();
+                    content_ctx.options = ctx.options.clone();
                     convert_markup_node(child, &mut content_ctx);
                     content = content_ctx.finalize();
                 }
                 SyntaxKind::ContentBlock => {
                     // Peek inside content block to see if it contains a table
-                    for sub in child.children() {
+                    let subs = child.children().flat_map(|sub| {
+                        if sub.kind() == SyntaxKind::Markup {
+                            sub.children().collect()
+                        } else {
+                            vec![sub]
+                        }
+                    });
+                    for sub in subs {
                         if sub.kind() == SyntaxKind::FuncCall {
                             let func_name = sub
                                 .children()
@@ -1686,6 +2764,7 @@ fn convert_figure_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                     }
 
                     let mut content_ctx = ConvertContext::new();
+                    content_ctx.options = ctx.options.clone();
                     convert_markup_node(child, &mut content_ctx);
                     let c = content_ctx.finalize();
                     if !c.is_empty() {
@@ -1742,7 +2821,7 @@ fn convert_link_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     if let Some(args) = children.get(1) {
         let mut first_str = true;
         for child in args.children() {
-            if is_string_or_content(child.kind()) {
+            if child.kind() == SyntaxKind::Str {
                 let content = get_string_content(child);
                 if first_str {
                     url = content;
@@ -1752,12 +2831,16 @@ fn convert_link_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                 }
             } else if child.kind() == SyntaxKind::ContentBlock {
                 let mut text_ctx = ConvertContext::new();
+                text_ctx.options = ctx.options.clone();
                 convert_markup_node(child, &mut text_ctx);
                 text = text_ctx.finalize();
             }
         }
     }
 
+    if ctx.underline_links {
+        ctx.push("\\underline{");
+    }
     if text.is_empty() {
         ctx.push("\\url{");
         ctx.push(&url);
@@ -1769,12 +2852,154 @@ fn convert_link_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
         ctx.push(&text);
         ctx.push("}");
     }
+    if ctx.underline_links {
+        ctx.push("}");
+    }
+}
+
+/// Whether the document underlines its links with `#show link: underline`
+/// (or the equivalent `it => underline(it)`)
+pub fn links_are_underlined(root: &SyntaxNode) -> bool {
+    root.children()
+        .filter_map(|node| node.cast::<ast::ShowRule>())
+        .any(underlines_links)
+}
+
+fn underlines_links(rule: ast::ShowRule) -> bool {
+    let selector = rule
+        .selector()
+        .map(|selector| ast::AstNode::to_untyped(selector).clone().into_text());
+    let transform: String = ast::AstNode::to_untyped(rule.transform())
+        .clone()
+        .into_text()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    selector.as_deref() == Some("link")
+        && matches!(transform.as_str(), "underline" | "it=>underline(it)")
+}
+
+/// Top-level `#set`/`#show` rules that no document-level scan carries over,
+/// with their source text and byte span.
+///
+/// Carried over are `#set document(..)`, `#set par(leading: ..)`,
+/// `#set page(..)` with a header, footer or numbering,
+/// `#set text(lang: ..)`, `#set math.equation(numbering: ..)`, and
+/// `#show link: underline`.
+pub fn find_unhandled_rules(root: &SyntaxNode) -> Vec<(String, SourceSpan)> {
+    fn named_args(rule: &ast::SetRule) -> Vec<String> {
+        rule.args()
+            .items()
+            .filter_map(|arg| match arg {
+                ast::Arg::Named(named) => Some(named.name().as_str().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    let mut rules = Vec::new();
+    let mut offset = 0;
+    for node in root.children() {
+        let start = offset;
+        offset += node.len();
+        let handled = if let Some(rule) = node.cast::<ast::SetRule>() {
+            let args = named_args(&rule);
+            let has = |name: &str| args.iter().any(|arg| arg == name);
+            match rule.target() {
+                ast::Expr::Ident(target) => match target.as_str() {
+                    "document" => true,
+                    "par" => has("leading"),
+                    "page" => has("header") || has("footer") || has("numbering"),
+                    "text" => {
+                        has("lang") && args.iter().all(|arg| arg == "lang" || arg == "region")
+                    }
+                    _ => false,
+                },
+                target if is_equation_target(target) => has("numbering"),
+                _ => false,
+            }
+        } else if let Some(rule) = node.cast::<ast::ShowRule>() {
+            // Show rules on text are applied by the evaluator
+            underlines_links(rule) || text_show_rule(rule).is_some()
+        } else {
+            continue;
+        };
+        if !handled {
+            rules.push((
+                node.clone().into_text().to_string(),
+                SourceSpan::new(start, offset),
+            ));
+        }
+    }
+    rules
+}
+
+/// Convert `underline`, `strike`, `overline` and `highlight` in markup.
+/// `\underline` cannot break across lines, so content with a line break uses
+/// ulem's `\uline`; a highlight with a custom `fill` becomes a `\colorbox`.
+fn convert_text_decoration(func_name: &str, children: &[&SyntaxNode], ctx: &mut ConvertContext) {
+    let start = ctx.output_len();
+    convert_func_args_text(children, ctx);
+    let content = ctx.output.split_off(start);
+
+    match func_name {
+        "underline" if content.contains("\\\\") => {
+            ctx.push(&format!("\\uline{{{}}}", content));
+        }
+        "underline" => ctx.push(&format!("\\underline{{{}}}", content)),
+        "strike" => ctx.push(&format!("\\sout{{{}}}", content)),
+        "overline" => {
+            let (open, close) = ctx.options.inline_math_delim.delimiters();
+            ctx.push(&format!(
+                "{}\\overline{{\\text{{{}}}}}{}",
+                open, content, close
+            ))
+        }
+        _ => {
+            let fill = FuncArgs::from_func_call(children)
+                .named_color("fill")
+                .and_then(normalize_typst_color_expr);
+            match fill {
+                Some(color) => {
+                    ctx.push(&format_latex_color_command("colorbox", &color));
+                    ctx.push(&format!("{{{}}}", content));
+                }
+                None => ctx.push(&format!("\\hl{{{}}}", content)),
+            }
+        }
+    }
 }
 
 // ============================================================================
 // Citation and Reference Conversion
 // ============================================================================
 
+/// Whether a markup child can own a `<label>` that directly follows it.
+fn takes_trailing_label(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        SyntaxKind::FuncCall => node
+            .children()
+            .next()
+            .is_some_and(|callee| callee.text() == "figure"),
+        SyntaxKind::Heading => true,
+        SyntaxKind::Equation => is_display_math(node),
+        _ => false,
+    }
+}
+
+/// Look ahead from `children[start]` for a `<label>`, skipping spaces.
+/// Returns the label text and its index.
+fn find_trailing_label(children: &[&SyntaxNode], start: usize) -> Option<(String, usize)> {
+    for (j, sibling) in children.iter().enumerate().skip(start + 1) {
+        match sibling.kind() {
+            SyntaxKind::Space | SyntaxKind::Linebreak => continue,
+            SyntaxKind::Label => return Some((normalize_label_like_text(sibling.text()), j)),
+            _ => return None,
+        }
+    }
+    None
+}
+
 fn normalize_label_like_text(text: &str) -> String {
     text.trim()
         .trim_start_matches('<')
@@ -1916,6 +3141,27 @@ fn convert_grid_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     ctx.push_line("\\end{minipage}");
 }
 
+/// `box(width: 1fr, repeat[.])` and `box(width: 1fr, line(..))` are the Typst
+/// spellings of `\dotfill` and `\hrulefill`.
+fn fill_box_command(children: &[&SyntaxNode]) -> Option<&'static str> {
+    let args = FuncArgs::from_func_call(children);
+    if args.named_text("width").map(str::trim) != Some("1fr") || args.positional_count() != 1 {
+        return None;
+    }
+    let body: String = args
+        .positional(0)?
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '#' | '[' | ']' | '(' | ')' | '"'))
+        .collect();
+    if body == "repeat." {
+        Some("\\dotfill")
+    } else if body.starts_with("line") {
+        Some("\\hrulefill")
+    } else {
+        None
+    }
+}
+
 /// Convert function arguments as text content, ignoring named argument keys
 pub fn convert_func_args_text(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     let args = FuncArgs::from_func_call(children);
@@ -1994,9 +3240,25 @@ fn convert_list_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext, is_
         EnvironmentContext::Itemize
     };
 
+    // Custom marker: enumitem `label=` option or a `\item[..]` per item
+    let marker = FuncArgs::from_func_call(children)
+        .named_node("marker")
+        .map(|node| {
+            let mut marker_ctx = ConvertContext::new();
+            marker_ctx.options = ctx.options.clone();
+            convert_markup_node(node, &mut marker_ctx);
+            marker_ctx.finalize().trim().to_string()
+        })
+        .filter(|marker| !marker.is_empty());
+    let (env_opts, item_cmd) = match marker {
+        Some(m) if ctx.options.use_enumitem => (format!("[label={}]", m), "\\item".to_string()),
+        Some(m) => (String::new(), format!("\\item[{}]", m)),
+        None => (String::new(), "\\item".to_string()),
+    };
+
     ctx.ensure_paragraph_break();
     ctx.push(&ctx.list_indent());
-    ctx.push_line(&format!("\\begin{{{}}}", env_name));
+    ctx.push_line(&format!("\\begin{{{}}}{}", env_name, env_opts));
 
     ctx.push_env(env);
 
@@ -2006,10 +3268,11 @@ fn convert_list_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext, is_
             match child.kind() {
                 SyntaxKind::ContentBlock | SyntaxKind::Markup => {
                     ctx.push(&ctx.list_indent());
-                    ctx.push("  \\item ");
+                    ctx.push(&format!("  {} ", item_cmd));
 
                     let mut item_ctx = ConvertContext::new();
                     item_ctx.list_depth = ctx.list_depth;
+                    item_ctx.options = ctx.options.clone();
                     convert_markup_node(child, &mut item_ctx);
                     let item_text = item_ctx.finalize();
                     ctx.push(&item_text);
@@ -2026,17 +3289,22 @@ fn convert_list_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext, is_
                             convert_list_to_latex(&func_children, ctx, true);
                         } else {
                             ctx.push(&ctx.list_indent());
-                            ctx.push("  \\item ");
+                            ctx.push(&format!("  {} ", item_cmd));
                             convert_markup_node(child, ctx);
                             ctx.newline();
                         }
                     }
                 }
+                SyntaxKind::Named
+                | SyntaxKind::LeftParen
+                | SyntaxKind::RightParen
+                | SyntaxKind::Comma
+                | SyntaxKind::Space => {}
                 _ => {
                     let text = get_simple_text(child);
                     if !text.is_empty() && text != "," {
                         ctx.push(&ctx.list_indent());
-                        ctx.push("  \\item ");
+                        ctx.push(&format!("  {} ", item_cmd));
                         ctx.push(&escape_latex_text(&text));
                         ctx.newline();
                     }
@@ -2079,29 +3347,74 @@ fn convert_raw_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     }
 
     if is_block || content.contains('\n') {
-        ctx.ensure_paragraph_break();
-        if !lang.is_empty() && is_listings_supported(&lang) {
-            // Use listings package with language (only if supported)
-            ctx.push_line(&format!("\\begin{{lstlisting}}[language={}]", lang));
-            ctx.push(&content);
-            ctx.newline();
-            ctx.push_line("\\end{lstlisting}");
-        } else {
-            // Unsupported or no language - use verbatim
-            if !lang.is_empty() {
-                ctx.push_line(&format!("% Code block (language: {})", lang));
+        emit_code_block(&content, Some(lang.as_str()).filter(|l| !l.is_empty()), ctx);
+    } else {
+        emit_inline_code(&content, ctx);
+    }
+}
+
+/// Emit a code block in the environment chosen by `T2LOptions::code_backend`.
+/// Content is passed through untouched: none of the environments interpret
+/// it, and no `escapeinside` is set for listings.
+fn emit_code_block(text: &str, lang: Option<&str>, ctx: &mut ConvertContext) {
+    ctx.ensure_paragraph_break();
+    let backend = ctx.options.code_backend;
+    let listings = lang
+        .filter(|_| backend == CodeBackend::Listings)
+        .and_then(listings_language);
+    let (env, args) = if backend == CodeBackend::Minted {
+        let lexer = lang.map_or_else(|| "text".to_string(), minted_language);
+        ("minted", format!("{{{}}}", lexer))
+    } else if let Some(language) = listings {
+        ("lstlisting", format!("[language={}]", language))
+    } else {
+        if let Some(lang) = lang {
+            ctx.push_line(&format!("% Code block (language: {})", lang));
+        }
+        ("verbatim", String::new())
+    };
+    ctx.push_line(&format!("\\begin{{{}}}{}", env, args));
+    ctx.push(text);
+    ctx.newline();
+    ctx.push_line(&format!("\\end{{{}}}", env));
+}
+
+/// Emit inline code as `\verb` with a delimiter the code does not contain.
+/// Inside command arguments, where `\verb` is not allowed, or when no
+/// delimiter is free, fall back to an escaped `\texttt`.
+fn emit_inline_code(text: &str, ctx: &mut ConvertContext) {
+    let delimiter = ['|', '!', '+', '@', '=', '/', ';', ':', '"']
+        .into_iter()
+        .find(|d| !text.contains(*d));
+    match delimiter {
+        Some(d) if ctx.inline_verb && !in_command_argument(&ctx.output) => {
+            ctx.push(&format!("\\verb{}{}{}", d, text, d));
+        }
+        _ => {
+            ctx.push("\\texttt{");
+            ctx.push(&escape_latex_text(text));
+            ctx.push("}");
+        }
+    }
+}
+
+/// Whether the end of the output is inside an open `{` group of the
+/// current paragraph
+fn in_command_argument(output: &str) -> bool {
+    let paragraph = output.rsplit("\n\n").next().unwrap_or(output);
+    let mut depth = 0i32;
+    let mut chars = paragraph.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
             }
-            ctx.push_line("\\begin{verbatim}");
-            ctx.push(&content);
-            ctx.newline();
-            ctx.push_line("\\end{verbatim}");
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
         }
-    } else {
-        // Inline code
-        ctx.push("\\texttt{");
-        ctx.push(&escape_latex_text(&content));
-        ctx.push("}");
     }
+    depth > 0
 }
 
 // ============================================================================
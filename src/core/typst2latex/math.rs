@@ -293,6 +293,13 @@ fn convert_lr_content_nodes_fixed_size(
     let left_delim = find_lr_edge_delimiter(content, true);
     let right_delim = find_lr_edge_delimiter(content, false);
 
+    if let (Some(left), Some(right)) = (&left_delim, &right_delim) {
+        if left.start == right.start {
+            emit_single_sized_delimiter(ctx, &left.text, size);
+            return;
+        }
+    }
+
     let (left_text, right_text) = if left_delim.is_some() || right_delim.is_some() {
         (
             left_delim
@@ -327,33 +334,34 @@ fn emit_fixed_lr_delimiter(
     size: LrDelimiterSize,
 ) {
     let latex = get_latex_delimiter(delim_text, is_left);
+    push_sized_delimiter(ctx, latex, if is_left { "l" } else { "r" }, size);
+}
 
-    match size {
-        LrDelimiterSize::Plain => {
-            ctx.push(latex);
-            set_last_token_after_delimiter(ctx, latex);
-        }
-        LrDelimiterSize::Big => {
-            ctx.push("\\big");
-            ctx.push(latex);
-            set_last_token_after_delimiter(ctx, latex);
-        }
-        LrDelimiterSize::BigLarge => {
-            ctx.push("\\Big");
-            ctx.push(latex);
-            set_last_token_after_delimiter(ctx, latex);
-        }
-        LrDelimiterSize::BigGl => {
-            ctx.push("\\bigg");
-            ctx.push(latex);
-            set_last_token_after_delimiter(ctx, latex);
-        }
-        LrDelimiterSize::BigGLarge => {
-            ctx.push("\\Bigg");
-            ctx.push(latex);
-            set_last_token_after_delimiter(ctx, latex);
-        }
+/// A delimiter sized on its own, e.g. `lr(size: #150%, \[)`: `\Bigl[` or
+/// `\Bigr]` by the side it opens or closes, `\Big|` for one without a side
+fn emit_single_sized_delimiter(ctx: &mut ConvertContext, delim_text: &str, size: LrDelimiterSize) {
+    let latex = get_latex_delimiter(delim_text, true);
+    let side = match latex {
+        "(" | "[" | "\\{" | "\\langle" | "\\lfloor" | "\\lceil" | "\\lgroup" => "l",
+        ")" | "]" | "\\}" | "\\rangle" | "\\rfloor" | "\\rceil" | "\\rgroup" => "r",
+        _ => "",
+    };
+    push_sized_delimiter(ctx, latex, side, size);
+}
+
+fn push_sized_delimiter(ctx: &mut ConvertContext, latex: &str, side: &str, size: LrDelimiterSize) {
+    let command = match size {
+        LrDelimiterSize::Plain => "",
+        LrDelimiterSize::Big => "\\big",
+        LrDelimiterSize::BigLarge => "\\Big",
+        LrDelimiterSize::BigGl => "\\bigg",
+        LrDelimiterSize::BigGLarge => "\\Bigg",
+    };
+    if !command.is_empty() {
+        ctx.push(&format!("{}{}", command, side));
     }
+    ctx.push(latex);
+    set_last_token_after_delimiter(ctx, latex);
 }
 
 /// Represents a detected delimiter in lr() content.
@@ -577,6 +585,8 @@ fn get_node_delimiter_text(node: &SyntaxNode) -> String {
         SyntaxKind::RightBracket => "]".to_string(),
         SyntaxKind::LeftBrace => "{".to_string(),
         SyntaxKind::RightBrace => "}".to_string(),
+        // Escaped brackets such as `\(` stand alone without opening a group
+        SyntaxKind::Escape => node.text().trim_start_matches('\\').to_string(),
         // Default: use text content
         _ => node.text().to_string(),
     }
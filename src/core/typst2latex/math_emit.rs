@@ -24,7 +24,8 @@ pub fn emit_math_ir(ir: &MathIr, ctx: &mut ConvertContext) {
             close,
         } => emit_delimited(open, content, close, ctx),
         MathIr::Apply { callee, args } => emit_apply(callee, args, ctx),
-        MathIr::Limits(content) => emit_math_ir(content, ctx),
+        MathIr::Limits(content) => emit_limits_modifier(content, r"\limits", ctx),
+        MathIr::Scripts(content) => emit_limits_modifier(content, r"\nolimits", ctx),
         MathIr::Style { mode, content } => emit_style(mode, content, ctx),
         MathIr::Attachment { .. } => emit_attachment(ir, ctx),
         MathIr::Script {
@@ -69,6 +70,26 @@ fn emit_symbol(symbol: &str, ctx: &mut ConvertContext) {
     }
 }
 
+/// Emit the operator of `limits(..)`/`scripts(..)`; a large operator symbol
+/// keeps the placement with `\limits`/`\nolimits` (`\int\limits`).
+/// `scripts(..)` around other content renders it in display style.
+fn emit_limits_modifier(content: &MathIr, modifier: &str, ctx: &mut ConvertContext) {
+    let content = match content {
+        MathIr::Limits(inner) | MathIr::Scripts(inner) => inner.as_ref(),
+        other => other,
+    };
+    if matches!(content, MathIr::Symbol(symbol) if is_limits_like_symbol(symbol)) {
+        emit_math_ir(content, ctx);
+        ctx.trim_trailing_space();
+        ctx.push(modifier);
+        ctx.last_token = TokenType::Command;
+    } else if modifier == r"\nolimits" && !is_operator_name(content) {
+        emit_style(&MathStyleMode::Display, content, ctx);
+    } else {
+        emit_math_ir(content, ctx);
+    }
+}
+
 fn emit_punctuation(ch: char, ctx: &mut ConvertContext) {
     ctx.trim_trailing_space();
     match ch {
@@ -91,6 +112,10 @@ fn emit_spacing(spacing: &MathSpacing, ctx: &mut ConvertContext) {
             ctx.push("\\ ");
             ctx.last_token = TokenType::Command;
         }
+        MathSpacing::NoBreak => {
+            ctx.push("~");
+            ctx.last_token = TokenType::None;
+        }
         MathSpacing::Thin => {
             ctx.push("\\,");
             ctx.last_token = TokenType::Command;
@@ -123,6 +148,22 @@ fn emit_delimited(open: &str, content: &MathIr, close: &str, ctx: &mut ConvertCo
 }
 
 fn emit_style(mode: &MathStyleMode, content: &MathIr, ctx: &mut ConvertContext) {
+    // A styled fraction is `\dfrac` / `\tfrac`
+    if let MathIr::Command(command) = content {
+        if command.latex == "\\frac" {
+            let latex = match mode {
+                MathStyleMode::Display => "\\dfrac",
+                MathStyleMode::Inline => "\\tfrac",
+            };
+            let command = MathCommand {
+                latex: latex.to_string(),
+                ..command.clone()
+            };
+            emit_math_ir(&MathIr::Command(command), ctx);
+            return;
+        }
+    }
+
     match mode {
         MathStyleMode::Display => {
             ctx.push(r"\displaystyle ");
@@ -171,6 +212,14 @@ fn emit_attachment(attachment: &MathIr, ctx: &mut ConvertContext) {
         ctx.last_token = TokenType::None;
     }
 
+    if top_right.is_none() && bottom_right.is_none() && top_left.is_none() && bottom_left.is_none()
+    {
+        if let Some(stacked) = stacked_base(base, true) {
+            emit_stacked(stacked, top.as_deref(), bottom.as_deref(), ctx);
+            return;
+        }
+    }
+
     emit_math_ir(base, ctx);
 
     if let Some(bottom) = bottom.as_deref() {
@@ -204,6 +253,13 @@ fn emit_script(
     sup: Option<&MathIr>,
     ctx: &mut ConvertContext,
 ) {
+    if primes == 0 && (sub.is_some() || sup.is_some()) {
+        if let Some(stacked) = stacked_base(base, false) {
+            emit_stacked(stacked, sup, sub, ctx);
+            return;
+        }
+    }
+
     emit_math_ir(base, ctx);
 
     if primes > 0 {
@@ -224,6 +280,73 @@ fn emit_script(
     }
 }
 
+/// Relations that take a stacked annotation (`\\overset{def}{=}`) when Typst
+/// attaches something directly above or below them
+const STACKABLE_RELATIONS: &[&str] = &[
+    "=",
+    "<",
+    ">",
+    r"\le",
+    r"\ge",
+    r"\leq",
+    r"\geq",
+    r"\neq",
+    r"\equiv",
+    r"\sim",
+    r"\simeq",
+    r"\approx",
+    r"\cong",
+    r"\propto",
+    r"\to",
+    r"\rightarrow",
+    r"\leftarrow",
+    r"\leftrightarrow",
+    r"\Rightarrow",
+    r"\Leftarrow",
+    r"\Leftrightarrow",
+    r"\longrightarrow",
+    r"\longleftarrow",
+    r"\mapsto",
+];
+
+/// The base to stack annotations on, for attachments that sit directly above
+/// or below it in Typst: `limits(..)` around anything but a large operator,
+/// and (for `attach(..)`) a bare relation.
+fn stacked_base(base: &MathIr, is_attach: bool) -> Option<&MathIr> {
+    match base {
+        MathIr::Limits(inner) if !is_limits_like_base(inner) && !is_operator_name(inner) => {
+            Some(inner.as_ref())
+        }
+        MathIr::Operator(op) | MathIr::Symbol(op) | MathIr::Ident(op)
+            if is_attach && STACKABLE_RELATIONS.contains(&op.as_str()) =>
+        {
+            Some(base)
+        }
+        _ => None,
+    }
+}
+
+/// Emit `\\overset{top}{base}` / `\\underset{bottom}{base}`.
+///
+/// amsmath keeps the math class of the base, so relations keep their spacing.
+fn emit_stacked(
+    base: &MathIr,
+    top: Option<&MathIr>,
+    bottom: Option<&MathIr>,
+    ctx: &mut ConvertContext,
+) {
+    let mut stacked = emit_math_ir_to_string(base, ctx);
+    if let Some(top) = top {
+        let top = render_script_fragment(strip_grouping_parentheses_for_script(top), ctx);
+        stacked = format!(r"\overset{{{}}}{{{}}}", top, stacked);
+    }
+    if let Some(bottom) = bottom {
+        let bottom = render_script_fragment(strip_grouping_parentheses_for_script(bottom), ctx);
+        stacked = format!(r"\underset{{{}}}{{{}}}", bottom, stacked);
+    }
+    emit_symbol(&stacked, ctx);
+}
+
 fn strip_grouping_parentheses_for_script(ir: &MathIr) -> &MathIr {
     match ir {
         MathIr::Delimited {
@@ -369,6 +492,11 @@ fn is_limits_like_symbol(symbol: &str) -> bool {
     LIMITS_LIKE_SYMBOLS.contains(&symbol)
 }
 
+/// `op(..)` is already a math operator, so its scripts stay scripts.
+fn is_operator_name(base: &MathIr) -> bool {
+    matches!(base, MathIr::Command(command) if command.latex == r"\operatorname")
+}
+
 fn is_limits_like_base(base: &MathIr) -> bool {
     match base {
         MathIr::Limits(_) => true,
@@ -452,10 +580,19 @@ fn emit_command(command: &MathCommand, ctx: &mut ConvertContext) {
 
 fn emit_environment(environment: &MathEnvironment, ctx: &mut ConvertContext) {
     match environment {
-        MathEnvironment::Matrix { name, rows } => {
+        MathEnvironment::Matrix { name, align, rows } => {
+            // A non-default alignment needs the mathtools starred variant
+            let name = match align {
+                Some(_) => format!("{}*", name),
+                None => name.clone(),
+            };
             ctx.push("\\begin{");
-            ctx.push(name);
-            ctx.push("}\n");
+            ctx.push(&name);
+            ctx.push("}");
+            if let Some(align) = align {
+                ctx.push(&format!("[{}]", align));
+            }
+            ctx.push("\n");
             for (row_index, row) in rows.iter().enumerate() {
                 ctx.push("  ");
                 for (cell_index, cell) in row.iter().enumerate() {
@@ -471,7 +608,7 @@ fn emit_environment(environment: &MathEnvironment, ctx: &mut ConvertContext) {
                 }
             }
             ctx.push("\\end{");
-            ctx.push(name);
+            ctx.push(&name);
             ctx.push("}");
             ctx.last_token = TokenType::Command;
         }
@@ -501,7 +638,7 @@ fn emit_case_row(row: &MathCaseRow, ctx: &mut ConvertContext) {
 }
 
 fn emit_linebreak(ctx: &mut ConvertContext) {
-    emit_raw_literal(" \\\n", ctx);
+    emit_raw_literal(" \\\\\n", ctx);
 }
 
 fn emit_raw_literal(text: &str, ctx: &mut ConvertContext) {
@@ -552,7 +689,7 @@ mod tests {
         );
 
         assert!(
-            result.contains("i \\\n") && result.contains("j"),
+            result.contains("i \\\\\n") && result.contains("j"),
             "linebreak IR should still emit a raw LaTeX linebreak in plain math, got: {}",
             result
         );
@@ -13,6 +13,7 @@ use typst_syntax::{SyntaxKind, SyntaxNode};
 pub enum MathSpacing {
     Soft,
     Space,
+    NoBreak,
     Thin,
     Med,
     Thick,
@@ -44,6 +45,8 @@ pub enum MathStyleMode {
 pub enum MathEnvironment {
     Matrix {
         name: String,
+        /// Column alignment of the mathtools starred variant (`l` or `r`)
+        align: Option<char>,
         rows: Vec<Vec<MathIr>>,
     },
     Cases {
@@ -71,6 +74,8 @@ pub enum MathIr {
         args: Vec<MathIr>,
     },
     Limits(Box<MathIr>),
+    /// `scripts(..)`: attachments at the side, like `\nolimits`
+    Scripts(Box<MathIr>),
     Style {
         mode: MathStyleMode,
         content: Box<MathIr>,
@@ -206,6 +211,7 @@ pub fn normalize_math_ir(ir: MathIr) -> MathIr {
                 .collect(),
         },
         MathIr::Limits(content) => MathIr::Limits(Box::new(normalize_math_ir(*content))),
+        MathIr::Scripts(content) => MathIr::Scripts(Box::new(normalize_math_ir(*content))),
         MathIr::Style { mode, content } => MathIr::Style {
             mode,
             content: Box::new(normalize_math_ir(*content)),
@@ -251,9 +257,10 @@ pub fn normalize_math_ir(ir: MathIr) -> MathIr {
             MathIr::Command(command)
         }
         MathIr::Environment(environment) => match environment {
-            MathEnvironment::Matrix { name, rows } => {
+            MathEnvironment::Matrix { name, align, rows } => {
                 MathIr::Environment(MathEnvironment::Matrix {
                     name,
+                    align,
                     rows: rows
                         .into_iter()
                         .map(|row| {
@@ -306,9 +313,7 @@ fn build_math_ident(node: &SyntaxNode) -> MathIr {
     }
 
     if let Some(tex) = TYPST_TO_TEX.get(text_str) {
-        return MathIr::Symbol(with_leading_backslash(
-            normalize_package_sensitive_math_tex(tex),
-        ));
+        return tex_symbol(tex);
     }
 
     if text_str.len() == 1 {
@@ -326,10 +331,13 @@ fn build_field_access(node: &SyntaxNode) -> MathIr {
     let full_text = collect_field_access_text(node);
     let full_text_str = full_text.as_str();
 
+    // A tie is the same space in math
+    if full_text_str == "space.nobreak" {
+        return MathIr::Spacing(MathSpacing::NoBreak);
+    }
+
     if let Some(tex) = TYPST_TO_TEX.get(full_text_str) {
-        return MathIr::Symbol(with_leading_backslash(
-            normalize_package_sensitive_math_tex(tex),
-        ));
+        return tex_symbol(tex);
     }
 
     if full_text_str == "square.stroked" || full_text_str == "square.filled" {
@@ -354,6 +362,8 @@ fn build_escape(node: &SyntaxNode) -> MathIr {
 
     match escaped {
         ',' | ':' | ';' => MathIr::Punctuation(escaped),
+        // Characters special to LaTeX stay escaped
+        '#' | '$' | '%' | '&' | '_' | '{' | '}' => MathIr::RawLiteral(format!("\\{}", escaped)),
         '+' | '-' | '=' | '<' | '>' | '|' | '/' | '*' => MathIr::Operator(escaped.to_string()),
         ch if ch.is_ascii_digit() => MathIr::Number(ch.to_string()),
         ch if ch.is_alphabetic() => MathIr::Ident(ch.to_string()),
@@ -459,8 +469,14 @@ fn build_func_call(node: &SyntaxNode, options: &T2LOptions) -> MathIr {
         match handler {
             MathHandler::Command { latex_cmd } => {
                 let args = build_args(args_node, options);
+                // \mathbf only bolds Latin letters and digits
+                let latex_cmd = if *latex_cmd == "\\mathbf" && args.iter().any(contains_greek) {
+                    "\\boldsymbol"
+                } else {
+                    latex_cmd
+                };
                 return MathIr::Command(MathCommand {
-                    latex: (*latex_cmd).to_string(),
+                    latex: latex_cmd.to_string(),
                     args,
                     optional_arg: None,
                 });
@@ -519,9 +535,7 @@ fn build_func_call(node: &SyntaxNode, options: &T2LOptions) -> MathIr {
 
 fn build_callable_ir(func_str: &str, args: Vec<MathIr>) -> MathIr {
     let callee = if let Some(tex) = TYPST_TO_TEX.get(func_str) {
-        MathIr::Symbol(with_leading_backslash(
-            normalize_package_sensitive_math_tex(tex),
-        ))
+        tex_symbol(tex)
     } else {
         MathIr::Command(MathCommand {
             latex: r"\operatorname".to_string(),
@@ -556,12 +570,9 @@ fn build_special_func_call(
             args_node, options,
         ))),
         "attach" => Some(build_attach_ir(args_node, options)),
-        "scripts" => Some(MathIr::Style {
-            mode: MathStyleMode::Display,
-            content: Box::new(seq_or_single(
-                args.iter().map(|arg| build_math_ir(arg, options)).collect(),
-            )),
-        }),
+        "scripts" => args
+            .first()
+            .map(|arg| MathIr::Scripts(Box::new(build_math_ir(arg, options)))),
         "primes" => Some(build_primes_ir(args_node)),
         "stretch" => Some(build_stretch_ir(args_node, options)),
         "mid" => Some(MathIr::Symbol(r"\mid".to_string())),
@@ -622,6 +633,27 @@ fn build_special_func_call(
             }))
         }
         "class" => build_class_ir(&args, options),
+        "abs" | "math.abs" | "norm" | "math.norm" => {
+            let content = seq_or_single(build_args(args_node, options));
+            let norm = func_str.ends_with("norm");
+            if options.use_physics_package {
+                return Some(MathIr::Command(MathCommand {
+                    latex: if norm { r"\norm" } else { r"\abs" }.to_string(),
+                    args: vec![content],
+                    optional_arg: None,
+                }));
+            }
+            let (open, close) = if norm {
+                (r"\left\lVert ", r"\right\rVert")
+            } else {
+                (r"\left\lvert ", r"\right\rvert")
+            };
+            Some(MathIr::Delimited {
+                open: open.to_string(),
+                content: Box::new(content),
+                close: close.to_string(),
+            })
+        }
         "set" | "Set" => Some(MathIr::Delimited {
             open: r"\left\{".to_string(),
             content: Box::new(seq_or_single(
@@ -649,6 +681,7 @@ fn build_math_vec_ir(args_node: &SyntaxNode, options: &T2LOptions) -> MathIr {
 
     MathIr::Environment(MathEnvironment::Matrix {
         name: "pmatrix".to_string(),
+        align: None,
         rows,
     })
 }
@@ -941,9 +974,12 @@ fn build_generic(node: &SyntaxNode, options: &T2LOptions) -> MathIr {
     }
 
     if let Some(tex) = TYPST_TO_TEX.get(text_str) {
-        return MathIr::Symbol(with_leading_backslash(
-            normalize_package_sensitive_math_tex(tex),
-        ));
+        return tex_symbol(tex);
+    }
+
+    // A literal percent sign would start a LaTeX comment
+    if text_str == "%" {
+        return MathIr::RawLiteral("\\%".to_string());
     }
 
     MathIr::Ident(convert_unicode_in_text(text_str))
@@ -984,7 +1020,7 @@ fn build_environment(args_node: &SyntaxNode, name: &str, options: &T2LOptions) -
             "(" => "pmatrix",
             "{" => "Bmatrix",
             "|" => "vmatrix",
-            "||" => "Vmatrix",
+            "||" | "‖" => "Vmatrix",
             _ => name,
         }
     } else {
@@ -1034,8 +1070,17 @@ fn build_environment(args_node: &SyntaxNode, name: &str, options: &T2LOptions) -
         rows.push(current_row);
     }
 
+    let align = args
+        .named_text("align")
+        .and_then(|value| match value.trim() {
+            "left" => Some('l'),
+            "right" => Some('r'),
+            _ => None,
+        });
+
     MathIr::Environment(MathEnvironment::Matrix {
         name: actual_name.to_string(),
+        align,
         rows,
     })
 }
@@ -1058,6 +1103,82 @@ fn seq_or_single(items: Vec<MathIr>) -> MathIr {
     }
 }
 
+/// Symbol for a `TYPST_TO_TEX` entry. Single letters are the Latin look-alikes
+/// of Greek letters that have no LaTeX command (`Alpha` → `A`).
+fn tex_symbol(tex: &str) -> MathIr {
+    if tex.len() == 1 && tex.bytes().all(|b| b.is_ascii_alphabetic()) {
+        MathIr::Ident(tex.to_string())
+    } else {
+        MathIr::Symbol(with_leading_backslash(
+            normalize_package_sensitive_math_tex(tex),
+        ))
+    }
+}
+
+/// Greek letter commands, which `\mathbf` leaves unbolded
+const GREEK_COMMANDS: &[&str] = &[
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "varepsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "vartheta",
+    "iota",
+    "kappa",
+    "varkappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "pi",
+    "varpi",
+    "rho",
+    "varrho",
+    "sigma",
+    "varsigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "varphi",
+    "chi",
+    "psi",
+    "omega",
+    "digamma",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Pi",
+    "Sigma",
+    "Upsilon",
+    "Phi",
+    "Psi",
+    "Omega",
+    "Digamma",
+];
+
+fn contains_greek(ir: &MathIr) -> bool {
+    match ir {
+        MathIr::Symbol(tex) => tex
+            .strip_prefix('\\')
+            .is_some_and(|name| GREEK_COMMANDS.contains(&name)),
+        MathIr::Seq(items) => items.iter().any(contains_greek),
+        MathIr::Delimited { content, .. } => contains_greek(content),
+        MathIr::Script { base, sub, sup, .. } => {
+            contains_greek(base)
+                || sub.as_deref().is_some_and(contains_greek)
+                || sup.as_deref().is_some_and(contains_greek)
+        }
+        MathIr::Command(command) => command.args.iter().any(contains_greek),
+        _ => false,
+    }
+}
+
 fn with_leading_backslash(tex: &str) -> String {
     if tex.starts_with('\\') {
         tex.to_string()
@@ -13,9 +13,27 @@ mod preprocess;
 mod table;
 mod utils;
 
-pub use context::{ConvertContext, DocumentWrapperMode, EnvironmentContext, T2LOptions, TokenType};
+use std::sync::Arc;
+
+use crate::core::latex2typst::{FragmentContext, UnknownEnvironmentPolicy};
+use crate::data::constants::{
+    leading_em_to_stretch, DOUBLE_SPACING_STRETCH, ONEHALF_SPACING_STRETCH,
+};
+use crate::data::dates::DateLanguage;
+use crate::utils::files::FileResolver;
+use crate::utils::limits::{typst_tree_depth, MAX_NESTING_DEPTH};
+use crate::utils::line_endings::{finish_lines, normalize_line_endings};
+use crate::utils::progress::{Phase, Progress};
+pub use context::{
+    CodeBackend, ConvertContext, DisplayMathDelim, DocumentWrapperMode, EnvironmentContext,
+    InlineMathDelim, PlaceBackend, T2LOptions, TokenType,
+};
 use engine::ContentNode;
 use typst_syntax::{parse, parse_math};
+use utils::{
+    escape_latex_text, find_document_metadata, find_par_leading_em, spell_accents_outside_verbatim,
+    strip_math_delimiters, DocumentMetadata,
+};
 
 // Re-export specific items that were previously exposed by `eval` from core
 pub use engine::{expand_macros, EvalError, EvalResult, MiniEval, SourceSpan, Value};
@@ -61,6 +79,10 @@ pub enum WarningKind {
     RecursionLimitExceeded,
     /// General evaluation warning (from MiniEval)
     EvalWarning,
+    /// A set or show rule with no LaTeX counterpart
+    UnsupportedRule,
+    /// A construct converted to a LaTeX one that behaves differently
+    Approximation,
     /// Other/generic warning
     Other,
 }
@@ -82,6 +104,8 @@ impl std::fmt::Display for WarningKind {
             WarningKind::RegexError => write!(f, "regex error"),
             WarningKind::RecursionLimitExceeded => write!(f, "recursion limit exceeded"),
             WarningKind::EvalWarning => write!(f, "eval warning"),
+            WarningKind::UnsupportedRule => write!(f, "unsupported rule"),
+            WarningKind::Approximation => write!(f, "approximation"),
             WarningKind::Other => write!(f, "other"),
         }
     }
@@ -250,27 +274,56 @@ pub fn typst_to_latex(input: &str) -> String {
 
 /// Convert Typst code to LaTeX with options
 pub fn typst_to_latex_with_options(input: &str, options: &T2LOptions) -> String {
+    let original = input;
+    let input = &*normalize_line_endings(input);
+    if let Some((kept, _)) = keep_too_deep(input, options.math_only) {
+        return finish_lines(&kept, options.line_ending, original);
+    }
     let mut ctx = ConvertContext::new();
     ctx.options = options.clone();
+    ctx.inline_verb = true;
 
     // Preprocess: handle imports, etc.
     let processed_input = preprocess::preprocess_typst(input);
 
     if options.math_only {
-        let root = parse_math(&processed_input);
+        let root = parse_math(strip_math_delimiters(&processed_input));
         math::convert_math_node(&root, &mut ctx);
     } else {
         let root = parse(&processed_input);
+        ctx.underline_links = markup::links_are_underlined(&root);
+        ctx.equation_numbering = markup::find_equation_numbering(&root);
         markup::convert_markup_node(&root, &mut ctx);
     }
 
-    let mut result = ctx.finalize();
+    let result = apply_document_settings(ctx.finalize(), input, options);
+    finish_lines(&result, options.line_ending, original)
+}
 
-    if options.full_document {
-        result = wrap_in_document(&result, options);
+/// Source whose tree is deeper than [`MAX_NESTING_DEPTH`], kept unconverted:
+/// as text in math, otherwise verbatim
+fn keep_too_deep(input: &str, math: bool) -> Option<(String, ConversionWarning)> {
+    let depth = if math {
+        typst_tree_depth(&parse_math(strip_math_delimiters(input)))
+    } else {
+        typst_tree_depth(&parse(input))
+    };
+    if depth <= MAX_NESTING_DEPTH {
+        return None;
     }
-
-    result
+    let warning = ConversionWarning::new(
+        WarningKind::RecursionLimitExceeded,
+        format!(
+            "input nests {} levels deep, more than the {} that are converted; kept as is",
+            depth, MAX_NESTING_DEPTH
+        ),
+    );
+    let kept = if math {
+        format!("\\text{{{}}}", escape_latex_text(input.trim()))
+    } else {
+        format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}", input.trim())
+    };
+    Some((kept, warning))
 }
 
 /// Convert Typst document to LaTeX document
@@ -305,11 +358,54 @@ pub fn typst_document_to_latex(input: &str) -> String {
 /// }
 /// ```
 pub fn typst_to_latex_with_diagnostics(input: &str, options: &T2LOptions) -> ConversionResult {
+    convert_with_vfs(input, options, Arc::new(engine::NoopVfs))
+}
+
+/// [`typst_to_latex_with_diagnostics`] with files read through `resolver`.
+///
+/// With [`T2LOptions::materialize_data`] set, a table filled from
+/// `csv("data.csv")` gets the rows of the file as its cells; a file the
+/// resolver cannot read leaves a placeholder table and a `FileNotFound`
+/// warning.
+pub fn typst_to_latex_with_file_resolver(
+    input: &str,
+    options: &T2LOptions,
+    resolver: Arc<dyn FileResolver>,
+) -> ConversionResult {
+    if options.materialize_data {
+        convert_with_vfs(input, options, Arc::new(engine::ResolverVfs::new(resolver)))
+    } else {
+        typst_to_latex_with_diagnostics(input, options)
+    }
+}
+
+fn convert_with_vfs(
+    input: &str,
+    options: &T2LOptions,
+    vfs: Arc<dyn engine::VirtualFileSystem>,
+) -> ConversionResult {
+    let original = input;
+    let input = &*normalize_line_endings(input);
+    if let Some((kept, warning)) = keep_too_deep(input, options.math_only) {
+        let kept = finish_lines(&kept, options.line_ending, original);
+        return ConversionResult::with_warnings(kept, vec![warning]);
+    }
     let mut warnings = Vec::new();
+    let report = |phase, bytes_processed, bytes_total, current_block| {
+        if let Some(progress) = &options.progress {
+            progress.report(Progress {
+                phase,
+                bytes_processed,
+                bytes_total,
+                current_block,
+            });
+        }
+    };
 
     // Step 1: Expand macros using MiniEval (with show rules applied)
+    report(Phase::MacroExpansion, 0, input.len(), 0);
     let (expanded_input, expanded_nodes): (String, Option<Vec<ContentNode>>) =
-        match engine::expand_macros_with_warnings(input) {
+        match engine::expand_macros_with_vfs(input, vfs) {
             Ok(result) => {
                 warnings.extend(result.warnings.into_iter().map(ConversionWarning::from));
                 (result.output, Some(result.nodes))
@@ -319,28 +415,135 @@ pub fn typst_to_latex_with_diagnostics(input: &str, options: &T2LOptions) -> Con
                 (preprocess::preprocess_typst(input), None)
             }
         };
+    report(Phase::MacroExpansion, input.len(), input.len(), 0);
+    let size = expanded_input.len();
+    report(Phase::Parsing, 0, size, 0);
+
+    if !options.math_only {
+        for (rule, span) in markup::find_unhandled_rules(&parse(input)) {
+            let action = match options.unknown_rule_policy {
+                UnknownEnvironmentPolicy::RawBlock => "kept as a comment",
+                UnknownEnvironmentPolicy::ConvertBody | UnknownEnvironmentPolicy::Drop => "dropped",
+            };
+            warnings.push(ConversionWarning::with_span(
+                WarningKind::UnsupportedRule,
+                format!(
+                    "`#{}` has no LaTeX counterpart, {}",
+                    rule.lines().next().unwrap_or_default().trim(),
+                    action
+                ),
+                span,
+            ));
+        }
+    }
+
+    if !options.math_only {
+        let root = parse(input);
+        let approximations = markup::find_unreplicable_placements(&root)
+            .into_iter()
+            .chain(markup::find_unshaded_tables(&root));
+        for (message, span) in approximations {
+            warnings.push(ConversionWarning::with_span(
+                WarningKind::Approximation,
+                message,
+                span,
+            ));
+        }
+    }
 
     // Step 2: Convert the (possibly expanded) Typst to LaTeX
     let mut ctx = ConvertContext::new();
     ctx.options = options.clone();
+    ctx.inline_verb = true;
+    ctx.underline_links = !options.math_only && markup::links_are_underlined(&parse(input));
+    if !options.math_only {
+        ctx.equation_numbering = markup::find_equation_numbering(&parse(input));
+    }
+
+    report(Phase::Parsing, size, size, 0);
 
+    report(Phase::Conversion, 0, size, 0);
+    let mut blocks = 0;
     if options.math_only {
-        let root = parse_math(&expanded_input);
+        let root = parse_math(strip_math_delimiters(&expanded_input));
         math::convert_math_node(&root, &mut ctx);
     } else if let Some(nodes) = expanded_nodes.as_ref() {
-        markup::convert_content_nodes_to_latex(nodes, &mut ctx);
+        // Evaluated content keeps no source offsets; the bytes done are
+        // estimated from the share of nodes converted
+        markup::convert_content_blocks_to_latex(nodes, &mut ctx, |_, converted| {
+            blocks += 1;
+            report(
+                Phase::Conversion,
+                size * converted / nodes.len(),
+                size,
+                blocks,
+            );
+        });
     } else {
         let root = parse(&expanded_input);
         markup::convert_markup_node(&root, &mut ctx);
     }
+    report(Phase::Conversion, size, size, blocks);
 
-    let mut output = ctx.finalize();
+    let body = ctx.finalize();
+    let size = body.len();
+    report(Phase::PostProcessing, 0, size, blocks);
+    let output = apply_document_settings(body, input, options);
+    let output = finish_lines(&output, options.line_ending, original);
+    report(Phase::PostProcessing, size, size, blocks);
 
-    if options.full_document {
-        output = wrap_in_document(&output, options);
+    ConversionResult::with_warnings(output, warnings)
+}
+
+/// Convert a single Typst element, e.g. a `#table(..)` call or an equation,
+/// the way it would be converted inside a document, but without the
+/// document wrapper, package lines or carried-over set rules.
+///
+/// `context.macros` holds `#let` definitions the fragment relies on.
+pub fn convert_fragment(input: &str, options: &T2LOptions, context: &FragmentContext) -> String {
+    let original = input;
+    let input = &*normalize_line_endings(input);
+    if let Some((kept, _)) = keep_too_deep(input, context.math) {
+        return finish_lines(&kept, options.line_ending, original);
+    }
+    let mut ctx = ConvertContext::new();
+    ctx.options = options.clone();
+    ctx.inline_verb = true;
+    if context.in_float {
+        ctx.push_env(EnvironmentContext::Figure);
     }
 
-    ConversionResult::with_warnings(output, warnings)
+    if context.math && context.macros.is_empty() {
+        let root = parse_math(strip_math_delimiters(input));
+        math::convert_math_node(&root, &mut ctx);
+        return finish_lines(ctx.finalize().trim(), options.line_ending, original);
+    }
+
+    // Math goes through markup so definitions expand inside it
+    let body = if context.math {
+        format!("${}$", strip_math_delimiters(input).trim())
+    } else {
+        input.to_string()
+    };
+    let source = format!("{}\n{}", context.macros, body);
+    match engine::expand_macros_with_warnings(&source) {
+        Ok(result) => markup::convert_content_nodes_to_latex(&result.nodes, &mut ctx),
+        Err(_) => {
+            markup::convert_markup_node(&parse(&preprocess::preprocess_typst(&source)), &mut ctx)
+        }
+    }
+    let output = ctx.finalize();
+    let output = output.trim();
+    let output = if context.math {
+        output
+            .strip_prefix('$')
+            .and_then(|inner| inner.strip_suffix('$'))
+            .unwrap_or(output)
+            .trim()
+    } else {
+        output
+    };
+    finish_lines(output, options.line_ending, original)
 }
 
 /// Convert Typst code to LaTeX with MiniEval preprocessing.
@@ -378,9 +581,84 @@ pub fn typst_to_latex_with_eval(input: &str, options: &T2LOptions) -> String {
     result.output
 }
 
-fn wrap_in_document(content: &str, options: &T2LOptions) -> String {
+/// Carry document-level set rules over: `#set par(leading: ..)` becomes
+/// `\linespread`, `#set document(..)` fills in the title, author and PDF
+/// metadata, and `#set page(header: .., footer: ..)` becomes a fancyhdr setup
+/// when the body is wrapped in a document.
+fn apply_document_settings(body: String, input: &str, options: &T2LOptions) -> String {
+    let root = (!options.math_only).then(|| parse(input));
+    let stretch = root
+        .as_ref()
+        .and_then(find_par_leading_em)
+        .map(leading_em_to_stretch)
+        .filter(|stretch| *stretch != 1.0);
+
+    let body = match root.as_ref() {
+        Some(root) if options.unknown_rule_policy == UnknownEnvironmentPolicy::RawBlock => {
+            let rules = markup::find_unhandled_rules(root);
+            if rules.is_empty() {
+                body
+            } else {
+                let mut kept = String::from("% Unconverted Typst rules:\n");
+                for (rule, _) in rules {
+                    for line in format!("#{}", rule.trim()).lines() {
+                        kept.push_str("% ");
+                        kept.push_str(line);
+                        kept.push('\n');
+                    }
+                }
+                format!("{}\n{}", kept, body)
+            }
+        }
+        _ => body,
+    };
+
+    let output = if options.full_document {
+        let metadata = root
+            .as_ref()
+            .map(find_document_metadata)
+            .unwrap_or_default();
+        let mut options = options.clone();
+        if options.title.is_none() {
+            options.title = metadata.title.as_deref().map(escape_latex_text);
+        }
+        if options.author.is_none() && !metadata.authors.is_empty() {
+            let authors: Vec<String> = metadata
+                .authors
+                .iter()
+                .map(|author| escape_latex_text(author))
+                .collect();
+            options.author = Some(authors.join(" \\and "));
+        }
+        let headers = root
+            .as_ref()
+            .map(markup::find_page_headers)
+            .unwrap_or_default();
+        wrap_in_document(&body, &options, stretch, &metadata, &headers)
+    } else if let Some(stretch) = stretch {
+        format!("\\linespread{{{}}}\\selectfont\n\n{}", stretch, body)
+    } else {
+        body
+    };
+
+    if options.ascii_accents {
+        spell_accents_outside_verbatim(&output)
+    } else {
+        output
+    }
+}
+
+fn wrap_in_document(
+    content: &str,
+    options: &T2LOptions,
+    line_stretch: Option<f64>,
+    metadata: &DocumentMetadata,
+    headers: &markup::PageHeaders,
+) -> String {
     match &options.wrapper {
-        DocumentWrapperMode::Default => default_wrapper(content, options),
+        DocumentWrapperMode::Default => {
+            default_wrapper(content, options, line_stretch, metadata, headers)
+        }
         DocumentWrapperMode::BodyOnly => content.to_string(),
         DocumentWrapperMode::Custom {
             before_body,
@@ -396,7 +674,13 @@ fn wrap_in_document(content: &str, options: &T2LOptions) -> String {
     }
 }
 
-fn default_wrapper(content: &str, options: &T2LOptions) -> String {
+fn default_wrapper(
+    content: &str,
+    options: &T2LOptions,
+    line_stretch: Option<f64>,
+    metadata: &DocumentMetadata,
+    headers: &markup::PageHeaders,
+) -> String {
     let mut doc = String::new();
 
     // Document class
@@ -413,11 +697,118 @@ fn default_wrapper(content: &str, options: &T2LOptions) -> String {
     doc.push_str("\\usepackage{amssymb}\n");
     doc.push_str("\\usepackage{graphicx}\n");
     doc.push_str("\\usepackage{hyperref}\n");
-    doc.push_str("\\usepackage{xcolor}\n");
+    // Table shading needs xcolor's `table` option (colortbl)
+    if ["\\cellcolor", "\\columncolor", "\\rowcolors"]
+        .iter()
+        .any(|command| content.contains(command))
+    {
+        doc.push_str("\\usepackage[table]{xcolor}\n");
+    } else {
+        doc.push_str("\\usepackage{xcolor}\n");
+    }
     doc.push_str("\\usepackage{longtable}\n"); // For tables
     doc.push_str("\\usepackage{booktabs}\n"); // For better tables
     doc.push_str("\\usepackage{geometry}\n");
     doc.push_str("\\geometry{a4paper, margin=2cm}\n");
+    if options.use_enumitem {
+        doc.push_str("\\usepackage{enumitem}\n");
+    }
+    // Language from #set text(lang: ..)
+    if let Some(babel) = metadata
+        .language
+        .as_ref()
+        .filter(|language| **language != DateLanguage::default())
+        .and_then(DateLanguage::babel_name)
+    {
+        doc.push_str(&format!("\\usepackage[{}]{{babel}}\n", babel));
+    }
+    // Decorations outside the standard packages
+    if content.contains("\\sout{") || content.contains("\\uline{") {
+        doc.push_str("\\usepackage[normalem]{ulem}\n");
+    }
+    if content.contains("\\hl{") {
+        doc.push_str("\\usepackage{soul}\n");
+    }
+    if content.contains("\\todo{") || content.contains("\\todo[") {
+        doc.push_str("\\usepackage{todonotes}\n");
+    }
+    if content.contains("matrix*}") {
+        doc.push_str("\\usepackage{mathtools}\n");
+    }
+    if content.contains("\\csvautotabular") {
+        doc.push_str("\\usepackage{csvsimple}\n");
+    }
+    if content.contains("\\makecell") {
+        doc.push_str("\\usepackage{makecell}\n");
+    }
+    // Absolute positioning from #place(..)
+    if content.contains("\\begin{textblock*}") {
+        doc.push_str("\\usepackage[absolute,overlay]{textpos}\n");
+    }
+    if options.use_physics_package && (content.contains("\\abs{") || content.contains("\\norm{")) {
+        doc.push_str("\\usepackage{physics}\n");
+    }
+    if content.contains("\\begin{landscape}") {
+        doc.push_str("\\usepackage{pdflscape}\n");
+    }
+    if content.contains("\\begin{sideways") {
+        doc.push_str("\\usepackage{rotating}\n");
+    }
+    if content.contains("\\begin{tcolorbox}") {
+        doc.push_str("\\usepackage{tcolorbox}\n");
+    }
+    if content.contains("\\begin{tikzpicture}") {
+        doc.push_str("\\usepackage{tikz}\n");
+    }
+    // Code blocks
+    if content.contains("\\begin{wrapfigure}") {
+        doc.push_str("\\usepackage{wrapfig}\n");
+    }
+    if content.contains("\\begin{lstlisting}") {
+        doc.push_str("\\usepackage{listings}\n");
+    }
+    if content.contains("\\begin{minted}") {
+        doc.push_str("\\usepackage{minted}\n");
+    }
+
+    // Line spacing: setspace only for its named presets
+    match line_stretch {
+        Some(s) if s == ONEHALF_SPACING_STRETCH => {
+            doc.push_str("\\usepackage{setspace}\n\\onehalfspacing\n");
+        }
+        Some(s) if s == DOUBLE_SPACING_STRETCH => {
+            doc.push_str("\\usepackage{setspace}\n\\doublespacing\n");
+        }
+        Some(s) => doc.push_str(&format!("\\linespread{{{}}}\n", s)),
+        None => {}
+    }
+
+    // Headers and footers from #set page(..)
+    if !headers.is_empty() {
+        doc.push_str(&headers.to_fancyhdr());
+    }
+
+    // PDF metadata from #set document(..)
+    if !metadata.is_empty() {
+        let join = |values: &[String]| {
+            values
+                .iter()
+                .map(|value| escape_latex_text(value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut fields = Vec::new();
+        if let Some(ref title) = metadata.title {
+            fields.push(format!("pdftitle={{{}}}", escape_latex_text(title)));
+        }
+        if !metadata.authors.is_empty() {
+            fields.push(format!("pdfauthor={{{}}}", join(&metadata.authors)));
+        }
+        if !metadata.keywords.is_empty() {
+            fields.push(format!("pdfkeywords={{{}}}", join(&metadata.keywords)));
+        }
+        doc.push_str(&format!("\\hypersetup{{{}}}\n", fields.join(", ")));
+    }
 
     // Title and author
     if let Some(ref title) = options.title {
@@ -426,6 +817,9 @@ fn default_wrapper(content: &str, options: &T2LOptions) -> String {
     if let Some(ref author) = options.author {
         doc.push_str(&format!("\\author{{{}}}\n", author));
     }
+    if let Some(ref date) = metadata.date {
+        doc.push_str(&format!("\\date{{{}}}\n", date));
+    }
 
     doc.push('\n');
     doc.push_str("\\begin{document}\n\n");
@@ -435,9 +829,12 @@ fn default_wrapper(content: &str, options: &T2LOptions) -> String {
         doc.push_str("\\maketitle\n\n");
     }
 
-    doc.push_str(content);
-
-    doc.push_str("\n\n\\end{document}");
+    // An empty body leaves just the skeleton
+    if !content.trim().is_empty() {
+        doc.push_str(content);
+        doc.push_str("\n\n");
+    }
+    doc.push_str("\\end{document}");
 
     doc
 }
@@ -661,6 +1058,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_inline_code_evaluated_without_eval_pipeline() {
+        let input = "#{ let x = 3; [Value: #x] }\n\nSum #(1 + 2) and #(\"a\" + \"b\").";
+        let result = typst_to_latex_with_options(input, &T2LOptions::default());
+
+        assert!(result.contains("Value: 3"), "got: {}", result);
+        assert!(result.contains("Sum 3 and ab."), "got: {}", result);
+        assert!(!result.contains("\\{"), "got: {}", result);
+
+        // Content blocks inside code go through the normal markup path
+        let result = typst_to_latex_with_options("#{ [*bold*] }", &T2LOptions::default());
+        assert!(result.contains("\\textbf{bold}"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_inline_code_eval_disabled() {
+        let opts = T2LOptions {
+            eval_inline_code: false,
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_options("Sum #(1 + 2)", &opts);
+        assert!(!result.contains("Sum 3"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_math_only_strips_enclosing_delimiters() {
+        let opts = T2LOptions {
+            math_only: true,
+            ..Default::default()
+        };
+        for input in ["$ x + y $", "$x + y$", "x + y"] {
+            assert_eq!(typst_to_latex_with_options(input, &opts).trim(), "x + y");
+            assert_eq!(typst_to_latex_with_eval(input, &opts).trim(), "x + y");
+        }
+        assert!(!typst_to_latex_with_options("$a$ + $b$", &opts).contains("$$"));
+    }
+
+    #[test]
+    fn test_block_math_requires_padding_on_both_sides() {
+        let opts = T2LOptions::default();
+        for (input, block) in [
+            ("$ x $", true),
+            ("$x$", false),
+            ("$ x$", false),
+            ("$x $", false),
+        ] {
+            let result = typst_to_latex_with_options(input, &opts);
+            assert_eq!(result.contains("\\["), block, "{}: {}", input, result);
+            let result = typst_to_latex_with_eval(input, &opts);
+            assert_eq!(result.contains("\\["), block, "{}: {}", input, result);
+        }
+    }
+
+    #[test]
+    fn test_labels_attach_to_heading_figure_and_equation() {
+        let input = "= Intro <sec-intro>\nSee @sec-intro.\n\n#figure(image(\"a.png\"), caption: [A]) <fig-a>\n\n$ x = y $ <eq-c>\n";
+        let opts = T2LOptions::default();
+        for result in [
+            typst_to_latex_with_options(input, &opts),
+            typst_to_latex_with_eval(input, &opts),
+        ] {
+            assert!(
+                result.contains("\\section{Intro}\n\\label{sec-intro}"),
+                "got: {}",
+                result
+            );
+            assert!(result.contains("\\ref{sec-intro}"), "got: {}", result);
+            assert!(
+                result.contains("\\label{fig-a}\n\\end{figure}"),
+                "got: {}",
+                result
+            );
+            assert!(
+                result.contains("\\begin{equation}\nx = y\n\\label{eq-c}\n\\end{equation}"),
+                "got: {}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_use_autoref_option() {
+        let opts = T2LOptions {
+            use_autoref: true,
+            ..Default::default()
+        };
+        let input = "= Intro <intro>\nSee @intro.";
+        assert!(typst_to_latex_with_options(input, &opts).contains("\\autoref{intro}"));
+        assert!(typst_to_latex_with_eval(input, &opts).contains("\\autoref{intro}"));
+    }
+
     #[test]
     fn test_for_loop_list_not_nested() {
         // Test that for loop with list items produces flat list, not nested
@@ -378,6 +378,19 @@ impl<'a> MacroExpander<'a> {
                     i += 2; // Skip both Hash and FuncCall
                     continue;
                 }
+
+                // Embedded code #{...} / #(...) / #a.b - keep the # so the
+                // converter still sees code, but substitute known definitions
+                // inside it
+                if matches!(
+                    next.kind(),
+                    SyntaxKind::CodeBlock | SyntaxKind::Parenthesized | SyntaxKind::FieldAccess
+                ) {
+                    result.push('#');
+                    result.push_str(&self.expand_node(next));
+                    i += 2;
+                    continue;
+                }
             }
 
             result.push_str(&self.expand_node(child));
@@ -144,6 +144,7 @@ impl LatexCell {
                         SyntaxKind::ContentBlock => {
                             // Cell content [...]
                             let mut cell_ctx = ConvertContext::new();
+                            cell_ctx.options = ctx.options.clone();
                             cell_ctx.push_env(EnvironmentContext::Table);
                             convert_markup_node(arg, &mut cell_ctx);
                             content = cell_ctx.finalize();
@@ -151,6 +152,7 @@ impl LatexCell {
                         SyntaxKind::FuncCall => {
                             // Cell content can be a function call like text(...)[...]
                             let mut cell_ctx = ConvertContext::new();
+                            cell_ctx.options = ctx.options.clone();
                             cell_ctx.push_env(EnvironmentContext::Table);
                             convert_markup_node(arg, &mut cell_ctx);
                             let func_content = cell_ctx.finalize();
@@ -167,6 +169,7 @@ impl LatexCell {
         // If no ContentBlock was found, try to get content from trailing content
         if content.is_empty() {
             let mut cell_ctx = ConvertContext::new();
+            cell_ctx.options = ctx.options.clone();
             cell_ctx.push_env(EnvironmentContext::Table);
             for child in node.children() {
                 if child.kind() == SyntaxKind::ContentBlock {
@@ -196,7 +199,16 @@ impl LatexCell {
             return String::new();
         }
 
-        let content_str = self.content.trim();
+        let align_char = self.align.unwrap_or(default_align).to_char();
+        let mut content_str = self.content.trim().to_string();
+        // Line breaks only take effect in p-columns; \makecell stacks them anywhere
+        if content_str.contains("\\newline") {
+            content_str = format!(
+                "\\makecell[{}]{{{}}}",
+                align_char,
+                content_str.replace("\\newline ", "\\\\ ")
+            );
+        }
         let mut prefix = String::new();
 
         // Add cell color if present
@@ -216,9 +228,10 @@ impl LatexCell {
             content
         };
 
-        // Wrap in \multicolumn if needed
-        if self.colspan > 1 {
-            let align_char = self.align.unwrap_or(default_align).to_char();
+        // Wrap in \multicolumn if needed; a single column one overrides the
+        // alignment of its column
+        let realigned = self.align.is_some_and(|align| align != default_align);
+        if self.colspan > 1 || realigned {
             format!(
                 "\\multicolumn{{{}}}{{|{}|}}{{{}}}",
                 self.colspan, align_char, inner
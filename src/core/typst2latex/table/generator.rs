@@ -2,6 +2,7 @@
 
 use super::cell::{LatexCell, LatexCellAlign};
 use super::hline::LatexHLine;
+use crate::core::typst2latex::utils::format_latex_color_command;
 
 /// Represents a parsed table row
 #[derive(Debug, Clone)]
@@ -61,6 +62,8 @@ pub struct LatexTableGenerator {
     pub has_header: bool,
     /// Track if we're currently processing header rows
     in_header: bool,
+    /// Column background colors (Typst), repeated across the columns
+    pub column_fills: Vec<Option<String>>,
 }
 
 impl LatexTableGenerator {
@@ -84,6 +87,7 @@ impl LatexTableGenerator {
             use_booktabs: false,
             has_header: false,
             in_header: false,
+            column_fills: Vec::new(),
         }
     }
 
@@ -268,14 +272,23 @@ impl LatexTableGenerator {
     fn build_column_spec(&self) -> String {
         let mut spec = String::from("|");
 
-        for align in &self.col_aligns {
-            spec.push(align.to_char());
-            spec.push('|');
-        }
-
         // If col_aligns is shorter than col_count, fill with 'c'
-        for _ in self.col_aligns.len()..self.col_count {
-            spec.push('c');
+        let aligns = self
+            .col_aligns
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(LatexCellAlign::Center))
+            .take(self.col_aligns.len().max(self.col_count));
+        for (col, align) in aligns.enumerate() {
+            if !self.column_fills.is_empty() {
+                if let Some(color) = &self.column_fills[col % self.column_fills.len()] {
+                    spec.push_str(&format!(
+                        ">{{{}}}",
+                        format_latex_color_command("columncolor", color)
+                    ));
+                }
+            }
+            spec.push(align.to_char());
             spec.push('|');
         }
 
@@ -278,3 +278,18 @@ fn test_complex_rowspan_colspan() {
     // The output should contain lines with just " & C & D"
     assert!(latex.contains("C & D"));
 }
+
+#[test]
+fn test_column_fills() {
+    let mut gen = LatexTableGenerator::new(3, vec![LatexCellAlign::Left; 3]);
+    gen.column_fills = vec![Some("yellow".to_string()), None];
+    gen.process_row(vec![
+        LatexCell::new("a".to_string()),
+        LatexCell::new("b".to_string()),
+        LatexCell::new("c".to_string()),
+    ]);
+    let latex = gen.generate_latex();
+    assert!(
+        latex.contains("\\begin{tabular}{|>{\\columncolor{yellow}}l|l|>{\\columncolor{yellow}}l|}")
+    );
+}
@@ -2,10 +2,13 @@
 //!
 //! Helper functions for text escaping, content extraction, etc.
 
+use crate::data::accents::spell_accents;
 use crate::data::colors::TYPST_TO_LATEX_COLORS;
+use crate::data::dates::DateLanguage;
 use lazy_static::lazy_static;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use typst_syntax::{SyntaxKind, SyntaxNode};
+use typst_syntax::{ast, SyntaxKind, SyntaxNode};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SpacingSpec {
@@ -213,6 +216,101 @@ pub fn escape_latex_text(text: &str) -> String {
         .replace('^', "\\textasciicircum{}")
 }
 
+/// Spell typographic quotes, dashes, the ellipsis, the no-break space and the
+/// non-breaking hyphen of escaped text with the LaTeX input conventions
+pub fn spell_typography(text: &str) -> Cow<'_, str> {
+    if !text.contains(['“', '”', '‘', '’', '–', '—', '…', '\u{a0}', '\u{2011}']) {
+        return Cow::Borrowed(text);
+    }
+    let mut spelled = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '“' => spelled.push_str("``"),
+            '”' => spelled.push_str("''"),
+            '‘' => spelled.push('`'),
+            '’' => spelled.push('\''),
+            '–' => spelled.push_str("--"),
+            '—' => spelled.push_str("---"),
+            '…' => spelled.push_str("\\ldots{}"),
+            '\u{a0}' => spelled.push('~'),
+            '\u{2011}' => spelled.push_str("\\nobreakdash-"),
+            c => spelled.push(c),
+        }
+    }
+    Cow::Owned(spelled)
+}
+
+/// Spell accented letters with TeX accent commands (`é` → `\'e`), leaving
+/// `verbatim`, `lstlisting` and `minted` blocks, `\verb` text, URLs and
+/// label keys untouched
+pub fn spell_accents_outside_verbatim(latex: &str) -> String {
+    let mut out = String::with_capacity(latex.len());
+    let mut verbatim_end: Option<&str> = None;
+    for line in latex.split_inclusive('\n') {
+        match verbatim_end {
+            Some(end) => {
+                out.push_str(line);
+                if line.trim_start().starts_with(end) {
+                    verbatim_end = None;
+                }
+            }
+            None => {
+                out.push_str(&spell_accents_outside_literals(line));
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("\\begin{verbatim}") {
+                    verbatim_end = Some("\\end{verbatim}");
+                } else if trimmed.starts_with("\\begin{lstlisting}") {
+                    verbatim_end = Some("\\end{lstlisting}");
+                } else if trimmed.starts_with("\\begin{minted}") {
+                    verbatim_end = Some("\\end{minted}");
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Commands whose braced first argument is a URL or a key, which must stay
+/// as written
+const LITERAL_ARG_COMMANDS: &[&str] = &[
+    "url", "href", "label", "ref", "eqref", "pageref", "autoref", "cref", "Cref", "nameref",
+];
+
+/// [`spell_accents`] on a line, except in `\verb` text and in the arguments
+/// of [`LITERAL_ARG_COMMANDS`]
+fn spell_accents_outside_literals(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(pos) = rest.find('\\') {
+        out.push_str(&spell_accents(&rest[..pos]));
+        let after = &rest[pos + 1..];
+        let name_len = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let name = &after[..name_len];
+        let tail = &after[name_len..];
+        let literal = match name {
+            "verb" => {
+                let star = usize::from(tail.starts_with('*'));
+                let body = &tail[star..];
+                body.chars().next().and_then(|delim| {
+                    let open = delim.len_utf8();
+                    body[open..].find(delim).map(|end| star + open + end + open)
+                })
+            }
+            name if LITERAL_ARG_COMMANDS.contains(&name) && tail.starts_with('{') => {
+                tail.find('}').map(|end| end + 1)
+            }
+            _ => None,
+        };
+        let len = 1 + name_len + literal.unwrap_or(0);
+        out.push_str(&rest[pos..pos + len]);
+        rest = &rest[pos + len..];
+    }
+    out.push_str(&spell_accents(rest));
+    out
+}
+
 /// Check if a string is a known color name
 pub fn is_color_name(s: &str) -> bool {
     matches!(
@@ -270,6 +368,7 @@ pub fn normalize_typst_color_expr(color: &str) -> Option<String> {
 
     if is_color_name(color)
         || is_typst_color_method_chain(color)
+        || color_mix_to_latex(color).is_some()
         || parse_typst_rgb_spec(color).is_some()
         || parse_typst_cmyk_spec(color).is_some()
         || parse_typst_luma_spec(color).is_some()
@@ -304,6 +403,10 @@ pub fn typst_color_to_latex_spec(color: &str) -> LatexColorSpec {
         return LatexColorSpec::new(None, color);
     }
 
+    if let Some(color) = color_mix_to_latex(color) {
+        return LatexColorSpec::new(None, color);
+    }
+
     LatexColorSpec::new(None, simple_color_to_latex(color))
 }
 
@@ -327,14 +430,6 @@ fn simple_color_to_latex(color: &str) -> &'static str {
     "black" // Default fallback
 }
 
-/// Check if a node kind represents string or content
-pub fn is_string_or_content(kind: SyntaxKind) -> bool {
-    matches!(
-        kind,
-        SyntaxKind::Str | SyntaxKind::ContentBlock | SyntaxKind::Text | SyntaxKind::Markup
-    )
-}
-
 /// Get string content from a node (strips quotes)
 pub fn get_string_content(node: &SyntaxNode) -> String {
     let text = node.text().to_string();
@@ -357,40 +452,35 @@ pub fn count_heading_markers(node: &SyntaxNode) -> usize {
 }
 
 /// Check if an equation is display math (block math)
-/// Display math in Typst starts with `$ ` (space after $) or `$\n` (newline after $)
+/// Like Typst, an equation is a block when it has whitespace right after the
+/// opening `$` and right before the closing `$`: `$ x $` is block, `$x$` and
+/// `$ x$` are inline.
 pub fn is_display_math(node: &SyntaxNode) -> bool {
-    // Get full text recursively for newline detection
-    let text = get_simple_text(node);
-
-    // Check if there are newlines in the content (multi-line = display math)
-    if text.contains('\n') {
-        return true;
-    }
-
-    // Check the structure of the Equation node
-    // Display math: $ followed by Space, then Math, then Space (optional), then $
-    // Inline math: $ followed directly by Math content, then $
-    let children: Vec<_> = node.children().collect();
+    node.cast::<ast::Equation>()
+        .is_some_and(|equation| equation.block())
+}
 
-    // Look for: [Dollar] [Space] [Math] ...
-    // or: [Dollar] [Math] (no space = inline)
-    if children.len() >= 2 && children[0].kind() == SyntaxKind::Dollar {
-        // Check if the second child is a Space (display math)
-        if children[1].kind() == SyntaxKind::Space {
-            return true;
-        }
-    }
+/// Strip one pair of enclosing `$ ... $` from math-only input.
+/// Returns the input unchanged when it is not a single delimited equation.
+pub fn strip_math_delimiters(input: &str) -> &str {
+    let trimmed = input.trim();
+    let Some(inner) = trimmed
+        .strip_prefix('$')
+        .and_then(|rest| rest.strip_suffix('$'))
+    else {
+        return input;
+    };
 
-    // Fallback: check raw text
-    let raw_text = node.text().to_string();
-    if raw_text.len() >= 2 {
-        let after_dollar = raw_text.chars().nth(1);
-        if matches!(after_dollar, Some(' ') | Some('\n') | Some('\r')) {
-            return true;
+    // `$a$ + $b$` is two equations, not one
+    let mut escaped = false;
+    for c in inner.chars() {
+        match c {
+            '\\' => escaped = !escaped,
+            '$' if !escaped => return input,
+            _ => escaped = false,
         }
     }
-
-    false
+    inner
 }
 
 /// Get raw text content and language from a raw node
@@ -515,6 +605,147 @@ pub fn extract_length_value(text: &str) -> Option<String> {
     None
 }
 
+/// Find a document-level `#set par(leading: ..)` and return the leading in em.
+/// The last matching rule wins, as it would in Typst.
+pub fn find_par_leading_em(root: &SyntaxNode) -> Option<f64> {
+    let mut leading = None;
+    for node in root.children() {
+        let Some(rule) = node.cast::<ast::SetRule>() else {
+            continue;
+        };
+        if !matches!(rule.target(), ast::Expr::Ident(ident) if ident.as_str() == "par") {
+            continue;
+        }
+        for arg in rule.args().items() {
+            if let ast::Arg::Named(named) = arg {
+                if named.name().as_str() != "leading" {
+                    continue;
+                }
+                if let ast::Expr::Numeric(numeric) = named.expr() {
+                    if let (value, ast::Unit::Em) = numeric.get() {
+                        leading = Some(value);
+                    }
+                }
+            }
+        }
+    }
+    leading
+}
+
+/// Metadata from a document-level `#set document(..)` rule
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub keywords: Vec<String>,
+    /// Argument for `\date`: `\today` for `date: auto`, empty for `date: none`
+    pub date: Option<String>,
+    /// Language from `#set text(lang: ..)`
+    pub language: Option<DateLanguage>,
+}
+
+impl DocumentMetadata {
+    /// Whether there are no PDF fields to set
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.authors.is_empty() && self.keywords.is_empty()
+    }
+}
+
+/// Collect `title`, `author`, `keywords` and `date` from top-level
+/// `#set document(..)` rules, and the language from `#set text(lang: ..)`.
+/// Later rules override earlier ones field by field.
+pub fn find_document_metadata(root: &SyntaxNode) -> DocumentMetadata {
+    fn texts(expr: ast::Expr) -> Vec<String> {
+        match expr {
+            ast::Expr::Str(s) => vec![s.get().to_string()],
+            ast::Expr::ContentBlock(content) => {
+                vec![get_simple_text(ast::AstNode::to_untyped(content.body()))]
+            }
+            ast::Expr::Array(array) => array
+                .items()
+                .filter_map(|item| match item {
+                    ast::ArrayItem::Pos(expr) => texts(expr).into_iter().next(),
+                    ast::ArrayItem::Spread(_) => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    let mut metadata = DocumentMetadata::default();
+    let mut date = None;
+    for node in root.children() {
+        let Some(rule) = node.cast::<ast::SetRule>() else {
+            continue;
+        };
+        let ast::Expr::Ident(target) = rule.target() else {
+            continue;
+        };
+        if target.as_str() == "text" {
+            let field = |name: &str| {
+                rule.args().items().find_map(|arg| match arg {
+                    ast::Arg::Named(named) if named.name().as_str() == name => {
+                        texts(named.expr()).into_iter().next()
+                    }
+                    _ => None,
+                })
+            };
+            if let Some(lang) = field("lang") {
+                metadata.language = Some(DateLanguage {
+                    lang,
+                    region: field("region").map(|region| region.to_uppercase()),
+                });
+            }
+            continue;
+        }
+        if target.as_str() != "document" {
+            continue;
+        }
+        for arg in rule.args().items() {
+            let ast::Arg::Named(named) = arg else {
+                continue;
+            };
+            if named.name().as_str() == "date" {
+                date = Some(named.expr());
+                continue;
+            }
+            let values: Vec<String> = texts(named.expr())
+                .into_iter()
+                .filter(|value| !value.trim().is_empty())
+                .collect();
+            match named.name().as_str() {
+                "title" => metadata.title = values.into_iter().next(),
+                "author" => metadata.authors = values,
+                "keywords" => metadata.keywords = values,
+                _ => {}
+            }
+        }
+    }
+
+    // Written out once the language is known
+    let language = metadata.language.clone().unwrap_or_default();
+    metadata.date = date.and_then(|date| match date {
+        ast::Expr::Auto(_) => Some("\\today".to_string()),
+        ast::Expr::None(_) => Some(String::new()),
+        ast::Expr::FuncCall(call) => {
+            let field = |name: &str| {
+                call.args().items().find_map(|arg| match arg {
+                    ast::Arg::Named(named) if named.name().as_str() == name => match named.expr() {
+                        ast::Expr::Int(value) => Some(value.get()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+            };
+            let (year, month) = (field("year")?, field("month")?);
+            let day = field("day").map(|day| day as u32);
+            Some(language.format_date(year as i32, month as u32, day))
+        }
+        _ => None,
+    });
+    metadata
+}
+
 pub fn parse_spacing_spec(text: &str) -> Option<SpacingSpec> {
     let trimmed = text
         .trim()
@@ -575,6 +806,27 @@ fn color_method_chain_to_latex(color: &str) -> Option<String> {
     Some(current)
 }
 
+/// `color.mix((blue, 5%), (white, 95%))` as xcolor's `blue!5!white`
+fn color_mix_to_latex(color: &str) -> Option<String> {
+    let inner = color.strip_prefix("color.mix(")?.strip_suffix(')')?;
+    let mut parts = Vec::new();
+    for pair in inner.split("),") {
+        let pair = pair.trim().strip_prefix('(')?.trim_end_matches(')');
+        let (color, percent) = pair.rsplit_once(',')?;
+        let color = color.trim();
+        if !is_color_name(color) && !is_typst_color_method_chain(color) {
+            return None;
+        }
+        let spec = typst_color_to_latex_spec(color);
+        let percent: f64 = percent.trim().strip_suffix('%')?.trim().parse().ok()?;
+        parts.push((spec.value, percent));
+    }
+    match parts.as_slice() {
+        [(first, percent), (second, _)] => Some(format!("{}!{}!{}", first, percent, second)),
+        _ => None,
+    }
+}
+
 fn parse_typst_rgb_spec(color: &str) -> Option<(&'static str, String)> {
     let content = parse_typst_color_func_args(color, "rgb")?;
 
@@ -727,6 +979,22 @@ fn format_decimal(value: f64) -> String {
     formatted
 }
 
+/// Whether markup sets `#set page(flipped: true)` for itself, as the body
+/// of a content block does to put its content on a landscape page
+pub fn sets_flipped_page(markup: ast::Markup) -> bool {
+    markup.exprs().any(|expr| {
+        let ast::Expr::SetRule(rule) = expr else {
+            return false;
+        };
+        matches!(rule.target(), ast::Expr::Ident(ident) if ident.as_str() == "page")
+            && rule.args().items().any(|arg| {
+                matches!(arg, ast::Arg::Named(named)
+                    if named.name().as_str() == "flipped"
+                        && matches!(named.expr(), ast::Expr::Bool(b) if b.get()))
+            })
+    })
+}
+
 /// Parse angle string (e.g., "45deg", "-90deg", "1.57rad") to degrees
 pub fn parse_angle_value(text: &str) -> Option<f64> {
     let text = text.trim();
@@ -1155,7 +1423,7 @@ mod tests {
         }
 
         for child in node.children() {
-            if let Some(found) = find_first_func_call(&child) {
+            if let Some(found) = find_first_func_call(child) {
                 return Some(found);
             }
         }
@@ -1312,6 +1580,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typst_color_mix_to_latex_spec() {
+        assert_eq!(
+            typst_color_to_latex_spec("color.mix((blue, 5%), (white, 95%))"),
+            LatexColorSpec::new(None, "blue!5!white")
+        );
+        assert_eq!(
+            typst_color_to_latex_spec("color.mix((red, 75%), (black, 25%))"),
+            LatexColorSpec::new(None, "red!75!black")
+        );
+        assert_eq!(
+            normalize_typst_color_expr("color.mix((rgb(\"#ff0000\"), 50%), (white, 50%))"),
+            None
+        );
+    }
+
     #[test]
     fn test_normalize_typst_color_expr() {
         assert_eq!(normalize_typst_color_expr("red"), Some("red".to_string()));
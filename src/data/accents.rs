@@ -0,0 +1,201 @@
+//! Text accents (diacritics)
+//!
+//! Maps the TeX text accent commands (`\'e`, `\v{s}`, `\c{c}`, ...) and the
+//! letter commands (`\ss`, `\o`, ...) to composed Unicode characters, and back
+//! for LaTeX engines without UTF-8 input.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// TeX text accent commands with the combining character each one adds and
+/// the Typst math accent used when the command appears in math
+pub const TEXT_ACCENTS: &[(&str, char, Option<&str>)] = &[
+    ("`", '\u{300}', Some("grave")),
+    ("'", '\u{301}', Some("acute")),
+    ("^", '\u{302}', Some("hat")),
+    ("~", '\u{303}', Some("tilde")),
+    ("=", '\u{304}', Some("macron")),
+    ("u", '\u{306}', Some("breve")),
+    (".", '\u{307}', Some("dot")),
+    ("\"", '\u{308}', Some("dot.double")),
+    ("r", '\u{30A}', Some("circle")),
+    ("H", '\u{30B}', Some("acute.double")),
+    ("v", '\u{30C}', Some("caron")),
+    ("d", '\u{323}', None),
+    ("c", '\u{327}', None),
+    ("k", '\u{328}', None),
+    ("b", '\u{331}', None),
+    ("t", '\u{361}', None),
+];
+
+/// Base letters and their precomposed forms, per accent command
+const ACCENTED_LETTERS: &[(&str, &str, &str)] = &[
+    ("`", "aeinouwyAEINOUWY", "àèìǹòùẁỳÀÈÌǸÒÙẀỲ"),
+    (
+        "'",
+        "acegiklmnoprsuwyzACEGIKLMNOPRSUWYZ",
+        "áćéǵíḱĺḿńóṕŕśúẃýźÁĆÉǴÍḰĹḾŃÓṔŔŚÚẂÝŹ",
+    ),
+    (
+        "^",
+        "aceghijosuwyzACEGHIJOSUWYZ",
+        "âĉêĝĥîĵôŝûŵŷẑÂĈÊĜĤÎĴÔŜÛŴŶẐ",
+    ),
+    ("~", "aeinouvyAEINOUVY", "ãẽĩñõũṽỹÃẼĨÑÕŨṼỸ"),
+    ("=", "aegiouyAEGIOUY", "āēḡīōūȳĀĒḠĪŌŪȲ"),
+    ("u", "aegiouAEGIOU", "ăĕğĭŏŭĂĔĞĬŎŬ"),
+    (
+        ".",
+        "abcdefghmnoprstwxyzABCDEFGHIMNOPRSTWXYZ",
+        "ȧḃċḋėḟġḣṁṅȯṗṙṡṫẇẋẏżȦḂĊḊĖḞĠḢİṀṄȮṖṘṠṪẆẊẎŻ",
+    ),
+    ("\"", "aehiotuwxyAEHIOUWXY", "äëḧïöẗüẅẍÿÄËḦÏÖÜẄẌŸ"),
+    ("r", "auwyAU", "åůẘẙÅŮ"),
+    ("H", "ouOU", "őűŐŰ"),
+    (
+        "v",
+        "acdeghijklnorstuzACDEGHIKLNORSTUZ",
+        "ǎčďěǧȟǐǰǩľňǒřšťǔžǍČĎĚǦȞǏǨĽŇǑŘŠŤǓŽ",
+    ),
+    (
+        "d",
+        "abdehiklmnorstuvwyzABDEHIKLMNORSTUVWYZ",
+        "ạḅḍẹḥịḳḷṃṇọṛṣṭụṿẉỵẓẠḄḌẸḤỊḲḶṂṆỌṚṢṬỤṾẈỴẒ",
+    ),
+    ("c", "cdeghklnrstCDEGHKLNRST", "çḑȩģḩķļņŗşţÇḐȨĢḨĶĻŅŖŞŢ"),
+    ("k", "aeiouAEIOU", "ąęįǫųĄĘĮǪŲ"),
+    ("b", "bdhklnrtzBDKLNRTZ", "ḇḏẖḵḻṉṟṯẕḆḎḴḺṈṞṮẔ"),
+];
+
+/// Letters that LaTeX spells as commands
+pub const LETTER_COMMANDS: &[(&str, char)] = &[
+    ("ss", 'ß'),
+    ("o", 'ø'),
+    ("O", 'Ø'),
+    ("aa", 'å'),
+    ("AA", 'Å'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("oe", 'œ'),
+    ("OE", 'Œ'),
+    ("l", 'ł'),
+    ("L", 'Ł'),
+    ("i", 'ı'),
+    ("j", 'ȷ'),
+];
+
+lazy_static! {
+    static ref COMPOSED: HashMap<(&'static str, char), char> = ACCENTED_LETTERS
+        .iter()
+        .flat_map(|(accent, bases, composed)| {
+            bases
+                .chars()
+                .zip(composed.chars())
+                .map(move |(base, c)| ((*accent, base), c))
+        })
+        .collect();
+
+    /// LaTeX spelling of each composed letter
+    static ref SPELLED: HashMap<char, String> = {
+        let mut spelled = HashMap::new();
+        for (accent, bases, composed) in ACCENTED_LETTERS {
+            for (base, c) in bases.chars().zip(composed.chars()) {
+                let spelling = if accent.chars().all(char::is_alphabetic) {
+                    format!("\\{}{{{}}}", accent, base)
+                } else {
+                    format!("\\{}{}", accent, base)
+                };
+                spelled.insert(c, spelling);
+            }
+        }
+        // Letter commands win, e.g. `\aa{}` over `\r{a}`
+        for (name, c) in LETTER_COMMANDS {
+            spelled.insert(*c, format!("\\{}{{}}", name));
+        }
+        spelled
+    };
+}
+
+/// Check whether a command name is a text accent
+pub fn is_text_accent(name: &str) -> bool {
+    TEXT_ACCENTS.iter().any(|(accent, _, _)| *accent == name)
+}
+
+/// Typst math accent for a text accent command, if there is one
+pub fn math_accent(name: &str) -> Option<&'static str> {
+    TEXT_ACCENTS
+        .iter()
+        .find(|(accent, _, _)| *accent == name)
+        .and_then(|(_, _, math)| *math)
+}
+
+/// Apply a text accent command to its (already converted) argument:
+/// `("\"", "o")` → `ö`.
+///
+/// Dotless `ı`/`ȷ` take accents like `i`/`j`. Letters without a precomposed
+/// form get the combining character; the tie `\t{oo}` joins two letters.
+pub fn compose_accent(accent: &str, arg: &str) -> String {
+    let Some(mark) = TEXT_ACCENTS
+        .iter()
+        .find(|(name, _, _)| *name == accent)
+        .map(|(_, mark, _)| *mark)
+    else {
+        return arg.to_string();
+    };
+    let mut chars = arg.trim().chars();
+    let Some(first) = chars.next() else {
+        return String::new();
+    };
+    let rest: String = chars.collect();
+    let base = match first {
+        'ı' => 'i',
+        'ȷ' => 'j',
+        c => c,
+    };
+    match COMPOSED.get(&(accent, base)) {
+        Some(composed) if accent != "t" => format!("{}{}", composed, rest),
+        _ => format!("{}{}{}", first, mark, rest),
+    }
+}
+
+/// Spell composed letters with TeX accent and letter commands: `é` → `\'e`,
+/// `š` → `\v{s}`, `ß` → `\ss{}`. Other characters are kept.
+pub fn spell_accents(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match SPELLED.get(&c) {
+            Some(spelling) => out.push_str(spelling),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_accent() {
+        assert_eq!(compose_accent("\"", "o"), "ö");
+        assert_eq!(compose_accent("'", "ı"), "í");
+        assert_eq!(compose_accent("v", "ȷ"), "ǰ");
+        assert_eq!(compose_accent("H", "o"), "ő");
+        assert_eq!(compose_accent("c", "c"), "ç");
+        assert_eq!(compose_accent("t", "oo"), "o\u{361}o");
+        assert_eq!(compose_accent("k", "x"), "x\u{328}");
+    }
+
+    #[test]
+    fn test_spell_accents_round_trips() {
+        assert_eq!(spell_accents("café"), "caf\\'e");
+        assert_eq!(spell_accents("Straße Škoda"), "Stra\\ss{}e \\v{S}koda");
+        assert_eq!(spell_accents("Erdős på"), "Erd\\H{o}s p\\aa{}");
+        for (accent, bases, _) in ACCENTED_LETTERS {
+            for base in bases.chars() {
+                let composed = compose_accent(accent, &base.to_string());
+                assert!(SPELLED.contains_key(&composed.chars().next().unwrap()));
+            }
+        }
+    }
+}
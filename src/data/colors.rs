@@ -327,6 +327,14 @@ fn parse_color(color: &str, model: Option<&str>) -> String {
     color.to_string()
 }
 
+/// Parse a color given as `[model]{spec}`, as in `\cellcolor[RGB]{255,0,0}`
+pub fn parse_model_color(spec: &str, model: Option<&str>) -> String {
+    match model.map(str::trim) {
+        Some(model) if !model.is_empty() => parse_color(spec, Some(model)),
+        _ => parse_color_expression(spec),
+    }
+}
+
 /// Parse color with possible modifiers (e.g., "blue!50!white")
 pub fn parse_color_expression(expr: &str) -> String {
     let expr = expr.trim();
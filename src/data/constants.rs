@@ -351,6 +351,149 @@ lazy_static! {
     };
 }
 
+/// Languages the listings package defines, by their (case-insensitive) name
+const LISTINGS_LANGUAGES: &[&str] = &[
+    "abap",
+    "acsl",
+    "ada",
+    "algol",
+    "ant",
+    "assembler",
+    "awk",
+    "bash",
+    "basic",
+    "c",
+    "caml",
+    "cil",
+    "clean",
+    "cobol",
+    "comsol",
+    "csh",
+    "delphi",
+    "eiffel",
+    "erlang",
+    "euphoria",
+    "fortran",
+    "gcl",
+    "gnuplot",
+    "haskell",
+    "html",
+    "idl",
+    "inform",
+    "java",
+    "jvmis",
+    "ksh",
+    "lisp",
+    "logo",
+    "lua",
+    "make",
+    "mathematica",
+    "matlab",
+    "mercury",
+    "metapost",
+    "miranda",
+    "mizar",
+    "ml",
+    "modula-2",
+    "mupad",
+    "nastran",
+    "oberon-2",
+    "ocl",
+    "octave",
+    "oz",
+    "pascal",
+    "perl",
+    "php",
+    "pl/i",
+    "plasm",
+    "postscript",
+    "pov",
+    "prolog",
+    "promela",
+    "pstricks",
+    "python",
+    "r",
+    "reduce",
+    "rexx",
+    "rsl",
+    "ruby",
+    "s",
+    "sas",
+    "scala",
+    "scilab",
+    "sh",
+    "shelxl",
+    "simula",
+    "sparql",
+    "sql",
+    "tcl",
+    "tex",
+    "vbscript",
+    "verilog",
+    "vhdl",
+    "vrml",
+    "xml",
+    "xslt",
+];
+
+lazy_static! {
+    /// Typst `raw` languages (as normalized by [`LANGUAGE_MAP`]) to their
+    /// listings and minted names. Listings has no definition for many newer
+    /// languages; those map to `None`.
+    static ref CODE_LANGUAGE_NAMES: HashMap<&'static str, (Option<&'static str>, &'static str)> = {
+        let mut m = HashMap::new();
+        m.insert("python", (Some("Python"), "python"));
+        m.insert("rust", (None, "rust"));
+        m.insert("javascript", (None, "javascript"));
+        m.insert("typescript", (None, "typescript"));
+        m.insert("java", (Some("Java"), "java"));
+        m.insert("c", (Some("C"), "c"));
+        m.insert("cpp", (Some("C++"), "cpp"));
+        m.insert("csharp", (Some("[Sharp]C"), "csharp"));
+        m.insert("objc", (Some("[Objective]C"), "objective-c"));
+        m.insert("ocaml", (Some("[Objective]Caml"), "ocaml"));
+        m.insert("bash", (Some("bash"), "bash"));
+        m.insert("latex", (Some("[LaTeX]TeX"), "latex"));
+        m.insert("html", (Some("HTML"), "html"));
+        m.insert("xml", (Some("XML"), "xml"));
+        m.insert("sql", (Some("SQL"), "sql"));
+        m.insert("haskell", (Some("Haskell"), "haskell"));
+        m.insert("lisp", (Some("Lisp"), "common-lisp"));
+        m.insert("makefile", (Some("make"), "make"));
+        m.insert("asm", (Some("Assembler"), "nasm"));
+        m.insert("docker", (None, "docker"));
+        m.insert("text", (None, "text"));
+        m
+    };
+}
+
+/// Normalize a Typst `raw` language id through [`LANGUAGE_MAP`]
+fn normalize_code_language(lang: &str) -> String {
+    LANGUAGE_MAP
+        .get(lang)
+        .or_else(|| LANGUAGE_MAP.get(lang.to_lowercase().as_str()))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| lang.to_lowercase())
+}
+
+/// listings name for a Typst `raw` language, if listings defines the language
+pub fn listings_language(lang: &str) -> Option<String> {
+    let lang = normalize_code_language(lang);
+    match CODE_LANGUAGE_NAMES.get(lang.as_str()) {
+        Some((listings, _)) => listings.map(str::to_string),
+        None => LISTINGS_LANGUAGES.contains(&lang.as_str()).then_some(lang),
+    }
+}
+
+/// minted (Pygments) lexer name for a Typst `raw` language
+pub fn minted_language(lang: &str) -> String {
+    let lang = normalize_code_language(lang);
+    match CODE_LANGUAGE_NAMES.get(lang.as_str()) {
+        Some((_, minted)) => minted.to_string(),
+        None => lang,
+    }
+}
+
 // ============================================================================
 // Theorem Environment Mappings
 // ============================================================================
@@ -670,6 +813,72 @@ pub fn is_native_math_operator(name: &str) -> bool {
     NATIVE_MATH_OPERATORS.contains(name)
 }
 
+// ============================================================================
+// Line Spacing
+// ============================================================================
+
+/// Typst's default paragraph leading, in em.
+pub const TYPST_DEFAULT_LEADING_EM: f64 = 0.65;
+
+/// LaTeX's default `\baselineskip` relative to the font size (12pt at 10pt).
+pub const LATEX_BASELINESKIP_EM: f64 = 1.2;
+
+/// `\baselinestretch` used by setspace's `\onehalfspacing` (at 10pt).
+pub const ONEHALF_SPACING_STRETCH: f64 = 1.25;
+
+/// `\baselinestretch` used by setspace's `\doublespacing` (at 10pt).
+pub const DOUBLE_SPACING_STRETCH: f64 = 1.667;
+
+/// Convert a `\baselinestretch` factor to a Typst `par.leading` in em.
+///
+/// A stretch of 1 maps to Typst's default leading; every additional unit adds
+/// one LaTeX baselineskip, so `\onehalfspacing` becomes `0.95em` and
+/// `\doublespacing` becomes `1.45em`.
+pub fn stretch_to_leading_em(stretch: f64) -> f64 {
+    let leading = TYPST_DEFAULT_LEADING_EM + LATEX_BASELINESKIP_EM * (stretch - 1.0);
+    (leading.max(0.0) * 100.0).round() / 100.0
+}
+
+/// Inverse of [`stretch_to_leading_em`].
+pub fn leading_em_to_stretch(leading: f64) -> f64 {
+    let stretch = 1.0 + (leading - TYPST_DEFAULT_LEADING_EM) / LATEX_BASELINESKIP_EM;
+    (stretch * 1000.0).round() / 1000.0
+}
+
+// ============================================================================
+// Font Sizes
+// ============================================================================
+
+/// LaTeX size switches and their sizes in pt for the standard 10pt classes.
+pub const LATEX_FONT_SIZES: [(&str, f64); 10] = [
+    ("tiny", 5.0),
+    ("scriptsize", 7.0),
+    ("footnotesize", 8.0),
+    ("small", 9.0),
+    ("normalsize", 10.0),
+    ("large", 12.0),
+    ("Large", 14.4),
+    ("LARGE", 17.28),
+    ("huge", 20.74),
+    ("Huge", 24.88),
+];
+
+/// Size in pt of a LaTeX size switch such as `footnotesize`.
+pub fn font_size_pt(command: &str) -> Option<f64> {
+    LATEX_FONT_SIZES
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, pt)| *pt)
+}
+
+/// The LaTeX size switch closest to a size in pt.
+pub fn nearest_font_size_command(pt: f64) -> &'static str {
+    LATEX_FONT_SIZES
+        .iter()
+        .min_by(|a, b| (a.1 - pt).abs().total_cmp(&(b.1 - pt).abs()))
+        .map_or("normalsize", |(name, _)| name)
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -687,6 +896,16 @@ mod tests {
         assert_eq!(LANGUAGE_MAP.get("typescript"), Some(&"typescript"));
     }
 
+    #[test]
+    fn test_code_language_names() {
+        assert_eq!(listings_language("py").as_deref(), Some("Python"));
+        assert_eq!(listings_language("fortran").as_deref(), Some("fortran"));
+        assert_eq!(listings_language("rust"), None);
+        assert_eq!(minted_language("rs"), "rust");
+        assert_eq!(minted_language("C++"), "cpp");
+        assert_eq!(minted_language("zig"), "zig");
+    }
+
     #[test]
     fn test_theorem_types() {
         let thm = THEOREM_TYPES.get("theorem").unwrap();
@@ -725,4 +944,23 @@ mod tests {
         assert!(opts.line_numbers);
         assert_eq!(opts.caption, Some("Hello World".to_string()));
     }
+
+    #[test]
+    fn test_line_spacing_round_trip() {
+        assert_eq!(stretch_to_leading_em(1.0), 0.65);
+        assert_eq!(stretch_to_leading_em(ONEHALF_SPACING_STRETCH), 0.95);
+        assert_eq!(stretch_to_leading_em(DOUBLE_SPACING_STRETCH), 1.45);
+        assert_eq!(leading_em_to_stretch(0.95), ONEHALF_SPACING_STRETCH);
+        assert_eq!(leading_em_to_stretch(0.65), 1.0);
+    }
+
+    #[test]
+    fn test_font_sizes() {
+        assert_eq!(font_size_pt("footnotesize"), Some(8.0));
+        assert_eq!(font_size_pt("Large"), Some(14.4));
+        assert_eq!(font_size_pt("bfseries"), None);
+        assert_eq!(nearest_font_size_command(8.0), "footnotesize");
+        assert_eq!(nearest_font_size_command(11.0), "normalsize");
+        assert_eq!(nearest_font_size_command(30.0), "Huge");
+    }
 }
@@ -0,0 +1,309 @@
+//! Dates
+//!
+//! Month names and `\today` layouts for the languages babel and polyglossia
+//! commonly load, so that dates read the same in LaTeX and Typst.
+
+/// Babel and polyglossia language names with the Typst `lang` (and `region`)
+/// they correspond to
+const LANGUAGES: &[(&str, &str, Option<&str>)] = &[
+    ("english", "en", None),
+    ("american", "en", None),
+    ("USenglish", "en", None),
+    ("british", "en", Some("GB")),
+    ("UKenglish", "en", Some("GB")),
+    ("australian", "en", Some("AU")),
+    ("ngerman", "de", None),
+    ("german", "de", None),
+    ("austrian", "de", Some("AT")),
+    ("naustrian", "de", Some("AT")),
+    ("swissgerman", "de", Some("CH")),
+    ("nswissgerman", "de", Some("CH")),
+    ("french", "fr", None),
+    ("francais", "fr", None),
+    ("spanish", "es", None),
+    ("italian", "it", None),
+    ("dutch", "nl", None),
+    ("portuguese", "pt", None),
+    ("portuges", "pt", None),
+    ("brazil", "pt", Some("BR")),
+    ("brazilian", "pt", Some("BR")),
+];
+
+/// Month names per Typst language code
+const MONTHS: &[(&str, [&str; 12])] = &[
+    (
+        "en",
+        [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+    ),
+    (
+        "es",
+        [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+    ),
+];
+
+/// A document language as Typst spells it: `lang` plus an optional `region`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateLanguage {
+    pub lang: String,
+    pub region: Option<String>,
+}
+
+impl DateLanguage {
+    /// Language for a babel or polyglossia name: `ngerman` → `de`
+    pub fn from_babel(name: &str) -> Option<Self> {
+        let name = name.trim();
+        LANGUAGES
+            .iter()
+            .find(|(babel, _, _)| babel.eq_ignore_ascii_case(name))
+            .map(|(_, lang, region)| Self {
+                lang: lang.to_string(),
+                region: region.map(str::to_string),
+            })
+    }
+
+    /// Babel option for a Typst language: `de` → `ngerman`
+    pub fn babel_name(&self) -> Option<&'static str> {
+        let region = self.region.as_deref();
+        LANGUAGES
+            .iter()
+            .find(|(_, lang, r)| *lang == self.lang && *r == region)
+            .or_else(|| LANGUAGES.iter().find(|(_, lang, _)| *lang == self.lang))
+            .map(|(babel, _, _)| *babel)
+    }
+
+    fn is_us_english(&self) -> bool {
+        self.lang == "en" && self.region.as_deref().is_none_or(|r| r == "US")
+    }
+
+    fn months(&self) -> &'static [&'static str; 12] {
+        MONTHS
+            .iter()
+            .find(|(lang, _)| *lang == self.lang)
+            .map_or(&MONTHS[0].1, |(_, months)| months)
+    }
+
+    /// Write a date the way `\today` does in this language; without a day
+    /// only the month and year are given
+    pub fn format_date(&self, year: i32, month: u32, day: Option<u32>) -> String {
+        let name = self.months()[(month.clamp(1, 12) - 1) as usize];
+        match (self.lang.as_str(), day) {
+            (_, None) if self.lang == "es" || self.lang == "pt" => {
+                format!("{} de {}", name, year)
+            }
+            (_, None) => format!("{} {}", name, year),
+            ("en", Some(day)) if self.is_us_english() => format!("{} {}, {}", name, day, year),
+            ("de", Some(day)) => format!("{}. {} {}", day, name, year),
+            ("es" | "pt", Some(day)) => format!("{} de {} de {}", day, name, year),
+            (_, Some(day)) => format!("{} {} {}", day, name, year),
+        }
+    }
+
+    /// Typst code that prints today's date like `\today` in this language.
+    /// `display` only knows English month names, so other languages look
+    /// the name up themselves.
+    pub fn typst_today(&self) -> String {
+        if self.lang == "en" {
+            let pattern = if self.is_us_english() {
+                "[month repr:long] [day padding:none], [year]"
+            } else {
+                "[day padding:none] [month repr:long] [year]"
+            };
+            return format!("datetime.today().display(\"{}\")", pattern);
+        }
+        let months: Vec<String> = self.months().iter().map(|m| format!("\"{}\"", m)).collect();
+        let (after_day, after_month) = match self.lang.as_str() {
+            "de" => (". ", " "),
+            "es" | "pt" => (" de ", " de "),
+            _ => (" ", " "),
+        };
+        format!(
+            "{{ str(datetime.today().day()) + \"{}\" + ({}).at(datetime.today().month() - 1) + \"{}\" + str(datetime.today().year()) }}",
+            after_day,
+            months.join(", "),
+            after_month
+        )
+    }
+}
+
+impl Default for DateLanguage {
+    fn default() -> Self {
+        Self {
+            lang: "en".to_string(),
+            region: None,
+        }
+    }
+}
+
+/// Read a written date such as `March 2024`, `March 5, 2024`, `5 March 2024`
+/// or `5. März 2024` in any of the known languages, or an ISO `2024-03-05`.
+/// Returns the year, month and day, if one was given.
+pub fn parse_date(text: &str) -> Option<(i32, u32, Option<u32>)> {
+    let text = text.trim();
+    let mut parts = text.splitn(3, '-');
+    if let (Some(y), Some(m), Some(d)) = (parts.next(), parts.next(), parts.next()) {
+        if let (Ok(y), Ok(m), Ok(d)) = (y.parse(), m.parse(), d.parse()) {
+            return valid(y, m, Some(d));
+        }
+    }
+
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '.')
+        .filter(|w| !w.is_empty() && *w != "de")
+        .collect();
+    let mut year = None;
+    let mut month = None;
+    let mut day = None;
+    for word in words {
+        if let Ok(number) = word.parse::<i32>() {
+            if number > 31 {
+                year = Some(number);
+            } else {
+                day = Some(number as u32);
+            }
+        } else {
+            let lower = word.to_lowercase();
+            let index = MONTHS
+                .iter()
+                .find_map(|(_, months)| months.iter().position(|m| m.to_lowercase() == lower))?;
+            month = Some(index as u32 + 1);
+        }
+    }
+    valid(year?, month?, day)
+}
+
+fn valid(year: i32, month: u32, day: Option<u32>) -> Option<(i32, u32, Option<u32>)> {
+    ((1..=12).contains(&month) && day.is_none_or(|d| (1..=31).contains(&d)))
+        .then_some((year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_dates() {
+        assert_eq!(parse_date("March 2024"), Some((2024, 3, None)));
+        assert_eq!(parse_date("March 5, 2024"), Some((2024, 3, Some(5))));
+        assert_eq!(parse_date("5. März 2024"), Some((2024, 3, Some(5))));
+        assert_eq!(parse_date("2024-03-05"), Some((2024, 3, Some(5))));
+        assert_eq!(parse_date("Spring 2024"), None);
+
+        let german = DateLanguage::from_babel("ngerman").unwrap();
+        assert_eq!(german.format_date(2024, 3, Some(5)), "5. März 2024");
+        assert_eq!(german.babel_name(), Some("ngerman"));
+        let english = DateLanguage::default();
+        assert_eq!(english.format_date(2024, 3, Some(5)), "March 5, 2024");
+        assert_eq!(english.format_date(2024, 3, None), "March 2024");
+    }
+}
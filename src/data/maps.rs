@@ -45,7 +45,8 @@ lazy_static! {
             alias: Some("arrow.b.double".to_string()),
         }));
         m.insert("bibitem".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // \bibitem[label]{key}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("multicolumn".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1232,15 +1233,23 @@ lazy_static! {
             alias: None,
         }));
         m.insert("caption".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short form, then the full text
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
+        m.insert("captionof".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \captionof{kind}[short]{text}: a float caption outside a float
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("t{,b}t") } },
             alias: None,
         }));
         m.insert("cfrac".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 2 } },
+            // \cfrac[l]{a}{b}: optional numerator alignment, then two terms
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}tt") } },
             alias: None,
         }));
         m.insert("chapter".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("check".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1263,6 +1272,21 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
         }));
+        m.insert("cellcolor".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \cellcolor[model]{color}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
+        m.insert("rowcolor".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \rowcolor[model]{color}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
+        m.insert("rowcolors".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \rowcolors[commands]{start}{odd color}{even color}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}ttt") } },
+            alias: None,
+        }));
         m.insert("colorbox".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 2 } },
             alias: None,
@@ -1271,6 +1295,11 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 3 } },
             alias: None,
         }));
+        m.insert("rotatebox".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \rotatebox[origin=c]{angle}{content}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}tt") } },
+            alias: None,
+        }));
         m.insert("hl".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
@@ -1283,6 +1312,11 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
         }));
+        m.insert("csvautotabular".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \csvautotabular[options]{file}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
         m.insert("date".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
@@ -1347,10 +1381,19 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
+        m.insert("hypersetup".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            alias: None,
+        }));
         m.insert("label".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
         }));
+        m.insert("lstinline".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \lstinline[options]{code}; `|code|` is braced before parsing
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
         m.insert("mathbb".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
@@ -1448,7 +1491,8 @@ lazy_static! {
             alias: None,
         }));
         m.insert("paragraph".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("parencite".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1459,6 +1503,15 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
         }));
+        m.insert("path".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            alias: None,
+        }));
+        m.insert("pgfplotstabletypeset".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \pgfplotstabletypeset[options]{file}
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
         m.insert("phantom".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
@@ -1484,7 +1537,8 @@ lazy_static! {
             alias: None,
         }));
         m.insert("section".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("set".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1501,12 +1555,19 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 2 } },
             alias: None,
         }));
+        m.insert("subparagraph".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+            alias: None,
+        }));
         m.insert("subsection".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("subsubsection".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short (TOC) title, then the title
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("text".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1550,7 +1611,8 @@ lazy_static! {
             alias: None,
         }));
         m.insert("title".to_string(), CommandSpecItem::Cmd(CmdShape {
-            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            // Optional short form, then the full text
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
             alias: None,
         }));
         m.insert("underbrace".to_string(), CommandSpecItem::Cmd(CmdShape {
@@ -1573,6 +1635,11 @@ lazy_static! {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
         }));
+        m.insert("verb".to_string(), CommandSpecItem::Cmd(CmdShape {
+            // \verb|code|, braced before parsing
+            args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
+            alias: None,
+        }));
         m.insert("widehat".to_string(), CommandSpecItem::Cmd(CmdShape {
             args: ArgShape::Right { pattern: ArgPattern::FixedLenTerm { len: 1 } },
             alias: None,
@@ -1742,41 +1809,43 @@ lazy_static! {
         m.insert("vectorunit".to_string(), cmd1());
 
         // -- Derivatives + star variants --
+        // Arguments after the first are taken only when braced
         m.insert("dd".to_string(), cmd1_opt());
         m.insert("differential".to_string(), cmd1_opt());
-        m.insert("dv".to_string(), cmd2_opt());
-        m.insert("dv*".to_string(), cmd2_opt());
-        m.insert("derivative".to_string(), cmd2_opt());
-        m.insert("pdv".to_string(), cmd3_opt());
-        m.insert("pdv*".to_string(), cmd3_opt());
-        m.insert("pderivative".to_string(), cmd3_opt());
-        m.insert("partialderivative".to_string(), cmd3_opt());
-        m.insert("fdv".to_string(), cmd2_opt());
-        m.insert("fdv*".to_string(), cmd2_opt());
-        m.insert("fderivative".to_string(), cmd2_opt());
-        m.insert("functionalderivative".to_string(), cmd2_opt());
+        m.insert("dv".to_string(), cmd1_opt());
+        m.insert("dv*".to_string(), cmd1_opt());
+        m.insert("derivative".to_string(), cmd1_opt());
+        m.insert("pdv".to_string(), cmd1_opt());
+        m.insert("pdv*".to_string(), cmd1_opt());
+        m.insert("pderivative".to_string(), cmd1_opt());
+        m.insert("partialderivative".to_string(), cmd1_opt());
+        m.insert("fdv".to_string(), cmd1_opt());
+        m.insert("fdv*".to_string(), cmd1_opt());
+        m.insert("fderivative".to_string(), cmd1_opt());
+        m.insert("functionalderivative".to_string(), cmd1_opt());
         m.insert("var".to_string(), cmd1());
         m.insert("variation".to_string(), cmd1());
 
         // -- Dirac notation + star variants --
+        // Arguments after the first are taken only when braced
         m.insert("ket".to_string(), cmd1());
         m.insert("ket*".to_string(), cmd1());
         m.insert("bra".to_string(), cmd1());
         m.insert("bra*".to_string(), cmd1());
-        m.insert("braket".to_string(), cmd2());
-        m.insert("braket*".to_string(), cmd2());
-        m.insert("innerproduct".to_string(), cmd2());
-        m.insert("ip".to_string(), cmd2());
-        m.insert("dyad".to_string(), cmd2());
-        m.insert("dyad*".to_string(), cmd2());
-        m.insert("outerproduct".to_string(), cmd2());
-        m.insert("ketbra".to_string(), cmd2());
-        m.insert("op".to_string(), cmd2());
-        m.insert("expval".to_string(), cmd2());
-        m.insert("expval*".to_string(), cmd2());
-        m.insert("expectationvalue".to_string(), cmd2());
-        m.insert("ev".to_string(), cmd2());
-        m.insert("ev*".to_string(), cmd2());
+        m.insert("braket".to_string(), cmd1());
+        m.insert("braket*".to_string(), cmd1());
+        m.insert("innerproduct".to_string(), cmd1());
+        m.insert("ip".to_string(), cmd1());
+        m.insert("dyad".to_string(), cmd1());
+        m.insert("dyad*".to_string(), cmd1());
+        m.insert("outerproduct".to_string(), cmd1());
+        m.insert("ketbra".to_string(), cmd1());
+        m.insert("op".to_string(), cmd1());
+        m.insert("expval".to_string(), cmd1());
+        m.insert("expval*".to_string(), cmd1());
+        m.insert("expectationvalue".to_string(), cmd1());
+        m.insert("ev".to_string(), cmd1());
+        m.insert("ev*".to_string(), cmd1());
         m.insert("vev".to_string(), cmd1());
         m.insert("mel".to_string(), cmd3());
         m.insert("mel*".to_string(), cmd3());
@@ -1867,6 +1936,267 @@ lazy_static! {
         // -- flatfrac (2 args) --
         m.insert("flatfrac".to_string(), cmd2());
 
+        // =====================================================================
+        // Length registers (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("newlength".to_string(), cmd1());
+        m.insert("setlength".to_string(), cmd2());
+        m.insert("addtolength".to_string(), cmd2());
+        m.insert("settowidth".to_string(), cmd2());
+
+        // =====================================================================
+        // Line spacing (handled in markup.rs / environment.rs)
+        // =====================================================================
+
+        m.insert("linespread".to_string(), cmd1());
+        m.insert("setstretch".to_string(), cmd1());
+        m.insert("spacing".to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+            args: ArgPattern::FixedLenTerm { len: 1 },
+            ctx_feature: mitex_spec::ContextFeature::None,
+            alias: None,
+        }));
+
+        // =====================================================================
+        // siunitx commands (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("SI".to_string(), cmd2_opt());
+        m.insert("qty".to_string(), cmd2_opt());
+        m.insert("si".to_string(), cmd1_opt());
+        m.insert("unit".to_string(), cmd1_opt());
+        m.insert("num".to_string(), cmd1_opt());
+        m.insert("ang".to_string(), cmd1_opt());
+        m.insert("SIrange".to_string(), cmd3_opt());
+        m.insert("qtyrange".to_string(), cmd3_opt());
+        m.insert("numrange".to_string(), cmd2_opt());
+        m.insert("DeclareSIUnit".to_string(), cmd2_opt());
+        m.insert("sisetup".to_string(), cmd1());
+
+        // =====================================================================
+        // ulem/soul decorations: \uline, \sout, \st (handled in markup.rs)
+        // =====================================================================
+
+        for name in ["uline", "uuline", "uwave", "ul", "sout", "xout", "st"] {
+            m.insert(name.to_string(), cmd1());
+        }
+
+        // =====================================================================
+        // Text accents: \'e, \v{s}, \c{c} (handled in markup.rs)
+        // =====================================================================
+
+        for (accent, _, _) in super::accents::TEXT_ACCENTS {
+            m.insert(accent.to_string(), cmd1());
+        }
+
+        // =====================================================================
+        // hyperref: \texorpdfstring{tex}{pdf} (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("texorpdfstring".to_string(), cmd2());
+
+        // =====================================================================
+        // Page flow: \pagebreak[n], \nopagebreak[n] (handled in markup.rs)
+        // =====================================================================
+
+        for name in ["pagebreak", "nopagebreak"] {
+            m.insert(name.to_string(), CommandSpecItem::Cmd(CmdShape {
+                args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}") } },
+                alias: None,
+            }));
+        }
+
+        // =====================================================================
+        // Document language: \usepackage[lang]{babel}, \setmainlanguage{lang}
+        // (read in markup.rs)
+        // =====================================================================
+
+        for name in ["usepackage", "RequirePackage", "setmainlanguage", "setdefaultlanguage"] {
+            m.insert(name.to_string(), CommandSpecItem::Cmd(CmdShape {
+                args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}t") } },
+                alias: None,
+            }));
+        }
+
+        // =====================================================================
+        // array: \newcolumntype{R}[n]{spec} (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("newcolumntype".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("t{,b}t") } },
+            alias: None,
+        }));
+
+        // =====================================================================
+        // mathtools starred matrices: \begin{pmatrix*}[r] (environment.rs)
+        // =====================================================================
+
+        for name in ["matrix*", "pmatrix*", "bmatrix*", "Bmatrix*", "vmatrix*", "Vmatrix*"] {
+            m.insert(name.to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+                args: ArgPattern::Glob { pattern: GlobStr::from("{,b}") },
+                ctx_feature: mitex_spec::ContextFeature::IsMatrix,
+                alias: None,
+            }));
+        }
+
+        // =====================================================================
+        // wrapfig: \begin{wrapfigure}[lines]{r}[overhang]{width} (environment.rs)
+        // =====================================================================
+
+        m.insert("wrapfigure".to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+            args: ArgPattern::Glob { pattern: GlobStr::from("{,b}t{,b}t") },
+            ctx_feature: mitex_spec::ContextFeature::None,
+            alias: None,
+        }));
+
+        // =====================================================================
+        // Unnumbered headings and outline entries: \section*{title},
+        // \addcontentsline{toc}{section}{title}, \pdfbookmark[level]{title}{anchor}
+        // and \hypertarget{name}{text} (markup.rs)
+        // =====================================================================
+
+        for name in [
+            "chapter*", "section*", "subsection*", "subsubsection*", "paragraph*", "subparagraph*",
+        ] {
+            m.insert(name.to_string(), cmd1_opt());
+        }
+        m.insert("addcontentsline".to_string(), cmd3());
+        m.insert("pdfbookmark".to_string(), cmd2_opt());
+        m.insert("hypertarget".to_string(), cmd2());
+        m.insert("hyperlink".to_string(), cmd2());
+
+        // =====================================================================
+        // Boxes: \begin{minipage}[pos][height][inner]{width}, the same for
+        // subfigure (environment.rs)
+        // and \parbox[pos][height][inner]{width}{text}, \makebox (markup.rs)
+        // =====================================================================
+
+        for name in ["minipage", "subfigure"] {
+            m.insert(name.to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+                args: ArgPattern::Glob { pattern: GlobStr::from("{,b}{,b}{,b}t") },
+                ctx_feature: mitex_spec::ContextFeature::None,
+                alias: None,
+            }));
+        }
+        m.insert("parbox".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}{,b}{,b}tt") } },
+            alias: None,
+        }));
+        // \mbox{text}, \makebox[width][pos]{text}, \textsuperscript{text}
+        m.insert("makebox".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right { pattern: ArgPattern::Glob { pattern: GlobStr::from("{,b}{,b}t") } },
+            alias: None,
+        }));
+        for name in ["mbox", "hbox", "textsuperscript", "textsubscript"] {
+            m.insert(name.to_string(), cmd1());
+        }
+
+        // =====================================================================
+        // textpos: \begin{textblock*}{width}[ax,ay](x,y) (environment.rs)
+        // =====================================================================
+
+        for name in ["textblock", "textblock*"] {
+            m.insert(name.to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+                args: ArgPattern::Glob { pattern: GlobStr::from("t{,b}") },
+                ctx_feature: mitex_spec::ContextFeature::None,
+                alias: None,
+            }));
+        }
+
+        // =====================================================================
+        // Framed boxes: \begin{tcolorbox}[options], \begin{mdframed}[options]
+        // (framed.rs), \tcbset{options} and \mdfsetup{options} (markup.rs)
+        // =====================================================================
+
+        for name in ["tcolorbox", "mdframed"] {
+            m.insert(name.to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {
+                args: ArgPattern::Glob { pattern: GlobStr::from("{,b}") },
+                ctx_feature: mitex_spec::ContextFeature::None,
+                alias: None,
+            }));
+        }
+        for name in ["tcbset", "mdfsetup", "tcbuselibrary"] {
+            m.insert(name.to_string(), cmd1());
+        }
+
+        // =====================================================================
+        // Graphics search path: \graphicspath{{figs/}} (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("graphicspath".to_string(), cmd1());
+
+        // =====================================================================
+        // Standalone TikZ figures: \input{fig.tikz} (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("input".to_string(), cmd1());
+        m.insert("documentclass".to_string(), cmd1_opt());
+
+        // =====================================================================
+        // Bibliography files: \bibliography{refs} (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("bibliography".to_string(), cmd1());
+        m.insert("bibliographystyle".to_string(), cmd1());
+
+        // =====================================================================
+        // Page styles and fancyhdr (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("pagestyle".to_string(), cmd1());
+        m.insert("thispagestyle".to_string(), cmd1());
+        for name in [
+            "fancyhead", "fancyfoot", "fancyhf", "lhead", "chead", "rhead", "lfoot", "cfoot",
+            "rfoot",
+        ] {
+            m.insert(name.to_string(), cmd1_opt());
+        }
+        m.insert("thepage".to_string(), cmd0(None));
+        m.insert("leftmark".to_string(), cmd0(None));
+        m.insert("rightmark".to_string(), cmd0(None));
+
+        // =====================================================================
+        // todonotes and margin notes (handled in markup.rs)
+        // =====================================================================
+
+        for name in ["todo", "missingfigure", "marginnote", "marginpar"] {
+            m.insert(name.to_string(), cmd1_opt());
+        }
+        m.insert("listoftodos".to_string(), cmd0(None));
+
+        // =====================================================================
+        // Stacked cell content: \shortstack, makecell, \pbox (handled in markup.rs)
+        // =====================================================================
+
+        for name in ["shortstack", "makecell", "thead"] {
+            m.insert(name.to_string(), cmd1_opt());
+        }
+        m.insert("pbox".to_string(), cmd2_opt());
+
+        // =====================================================================
+        // List items: \item[label] (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("item".to_string(), CommandSpecItem::Cmd(CmdShape {
+            args: ArgShape::Right {
+                pattern: ArgPattern::Glob {
+                    pattern: GlobStr::from("{,b}"),
+                },
+            },
+            alias: None,
+        }));
+
+        // =====================================================================
+        // cleveref commands (handled in markup.rs)
+        // =====================================================================
+
+        m.insert("labelcref".to_string(), cmd1());
+        m.insert("nameref".to_string(), cmd1());
+        m.insert("namecref".to_string(), cmd1());
+        m.insert("nameCref".to_string(), cmd1());
+        m.insert("crefrange".to_string(), cmd2());
+        m.insert("Crefrange".to_string(), cmd2());
+
         CommandSpec::new(m)
     };
 }
@@ -2372,6 +2702,7 @@ pub static TYPST_TO_TEX: phf::Map<&'static str, &'static str> = phf_map! {
     "Rho" => "P",
     "Tau" => "T",
     "Zeta" => "Z",
+    "omicron" => "o",
     "digamma" => "\\digamma",
 
     // Blackboard bold (missing letters)
@@ -6,8 +6,10 @@
 //! - Language/theorem type constants
 //! - Shorthand symbol mappings
 
+pub mod accents;
 pub mod colors;
 pub mod constants;
+pub mod dates;
 pub mod extended_symbols;
 pub mod maps;
 pub mod physics;
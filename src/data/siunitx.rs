@@ -1,6 +1,7 @@
 //! Complete siunitx package support
 //! Surpasses Pandoc with full siunitx v3 compatibility
 
+use crate::data::symbols::longest_first;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashMap;
@@ -391,17 +392,17 @@ fn convert_unit(unit_str: &str) -> String {
     let mut result = unit_str.to_string();
 
     // Apply unit replacements
-    for (cmd, symbol) in SI_UNITS.iter() {
+    for (cmd, symbol) in longest_first(&SI_UNITS) {
         result = result.replace(cmd, symbol);
     }
 
     // Apply prefix replacements
-    for (cmd, symbol) in SI_PREFIXES.iter() {
+    for (cmd, symbol) in longest_first(&SI_PREFIXES) {
         result = result.replace(cmd, symbol);
     }
 
     // Apply modifiers
-    for (cmd, symbol) in UNIT_MODIFIERS.iter() {
+    for (cmd, symbol) in longest_first(&UNIT_MODIFIERS) {
         result = result.replace(cmd, symbol);
     }
 
@@ -542,6 +543,343 @@ fn format_list(values: &[String], unit: Option<&str>) -> String {
     format!("{}, and {}", init.join(", "), last_formatted)
 }
 
+// =============================================================================
+// Options and Typst output (used by the LaTeX -> Typst converter)
+// =============================================================================
+
+/// How units are printed in ranges (siunitx `range-units`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangeUnits {
+    /// Unit after both values: 10 °C – 20 °C
+    #[default]
+    Repeat,
+    /// Unit once after the range: 10 – 20 °C
+    Single,
+    /// Range in brackets followed by the unit: (10 – 20) °C
+    Brackets,
+}
+
+/// How `\per` is printed (siunitx `per-mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PerMode {
+    /// Slash: m/s
+    #[default]
+    Symbol,
+    /// Negative powers: m s⁻¹
+    Power,
+}
+
+/// Subset of siunitx options that affect conversion
+///
+/// Set globally with `\sisetup{...}` and overridden per command with `[...]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SiOptions {
+    /// `range-units`
+    pub range_units: RangeUnits,
+    /// `per-mode`
+    pub per_mode: PerMode,
+    /// `group-separator`; digits are only grouped when this is given
+    pub group_separator: Option<String>,
+}
+
+impl SiOptions {
+    /// Apply a `key=value, ...` option list, ignoring unknown keys
+    pub fn apply(&mut self, options: &str) {
+        for (key, value) in split_key_values(options) {
+            match key.as_str() {
+                "range-units" => {
+                    self.range_units = match value.as_str() {
+                        "single" => RangeUnits::Single,
+                        "brackets" => RangeUnits::Brackets,
+                        _ => RangeUnits::Repeat,
+                    }
+                }
+                "per-mode" => {
+                    self.per_mode = match value.as_str() {
+                        "power" | "reciprocal" => PerMode::Power,
+                        _ => PerMode::Symbol,
+                    }
+                }
+                "group-separator" => self.group_separator = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    /// Return a copy with per-command options applied on top
+    pub fn with_overrides(&self, options: Option<&str>) -> Self {
+        let mut merged = self.clone();
+        if let Some(opts) = options {
+            merged.apply(opts);
+        }
+        merged
+    }
+}
+
+/// Split `key=value` pairs at top-level commas (braced values keep their commas)
+pub(crate) fn split_key_values(options: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+
+    for c in options.chars().chain(std::iter::once(',')) {
+        match c {
+            '{' => {
+                depth += 1;
+                current.push(c);
+            }
+            '}' => {
+                depth -= 1;
+                current.push(c);
+            }
+            ',' if depth == 0 => {
+                if let Some((k, v)) = current.split_once('=') {
+                    let v = v.trim();
+                    let v = v
+                        .strip_prefix('{')
+                        .and_then(|v| v.strip_suffix('}'))
+                        .unwrap_or(v);
+                    pairs.push((k.trim().to_string(), v.to_string()));
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+
+    pairs
+}
+
+/// Render an integer as Unicode superscript digits
+fn superscript(n: i32) -> String {
+    n.to_string()
+        .chars()
+        .map(|c| match c {
+            '-' => '⁻',
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            _ => '⁹',
+        })
+        .collect()
+}
+
+/// Convert a siunitx unit expression to plain text (e.g. `\kilo\metre\per\second` -> `km/s`)
+///
+/// `custom` holds units registered with `\DeclareSIUnit` and takes precedence
+/// over the built-in tables.
+pub fn unit_to_text(unit: &str, custom: &HashMap<String, String>, options: &SiOptions) -> String {
+    // (symbol, power, in denominator)
+    let mut parts: Vec<(String, i32, bool)> = Vec::new();
+    let mut prefix = String::new();
+    let mut pending_power: Option<i32> = None;
+    let mut per = false;
+
+    let chars: Vec<char> = unit.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+
+            if let Some(symbol) = custom.get(&name) {
+                parts.push((
+                    format!("{}{}", prefix, symbol),
+                    pending_power.take().unwrap_or(1),
+                    per,
+                ));
+                prefix.clear();
+                per = false;
+                continue;
+            }
+
+            match name.as_str() {
+                "\\per" => per = true,
+                "\\square" => pending_power = Some(2),
+                "\\cubic" => pending_power = Some(3),
+                "\\squared" | "\\cubed" => {
+                    if let Some(last) = parts.last_mut() {
+                        last.1 = if name == "\\squared" { 2 } else { 3 };
+                    }
+                }
+                "\\tothe" | "\\raiseto" => {
+                    // Power argument in braces: \tothe{4}
+                    if i < chars.len() && chars[i] == '{' {
+                        let end = chars[i..].iter().position(|&c| c == '}').map(|p| i + p);
+                        if let Some(end) = end {
+                            let n: String = chars[i + 1..end].iter().collect();
+                            if let Ok(n) = n.trim().parse::<i32>() {
+                                if name == "\\tothe" {
+                                    if let Some(last) = parts.last_mut() {
+                                        last.1 = n;
+                                    }
+                                } else {
+                                    pending_power = Some(n);
+                                }
+                            }
+                            i = end + 1;
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(p) = SI_PREFIXES.get(name.as_str()) {
+                        prefix.push_str(p);
+                    } else {
+                        let symbol = SI_UNITS
+                            .get(name.as_str())
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| name.trim_start_matches('\\').to_string());
+                        parts.push((
+                            format!("{}{}", prefix, symbol),
+                            pending_power.take().unwrap_or(1),
+                            per,
+                        ));
+                        prefix.clear();
+                        per = false;
+                    }
+                }
+            }
+        } else if c.is_whitespace() || c == '{' || c == '}' || c == '~' || c == '.' {
+            i += 1;
+        } else {
+            // Literal unit text such as `m/s` or `kg^2`
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !"\\{}~.".contains(chars[i]) {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            parts.push((format!("{}{}", prefix, literal), 1, per));
+            prefix.clear();
+            per = false;
+        }
+    }
+
+    let render = |(symbol, power): (&str, i32)| {
+        if power == 1 {
+            symbol.to_string()
+        } else {
+            format!("{}{}", symbol, superscript(power))
+        }
+    };
+
+    match options.per_mode {
+        PerMode::Power => parts
+            .iter()
+            .map(|(s, p, d)| render((s, if *d { -p } else { *p })))
+            .collect::<Vec<_>>()
+            .join(" "),
+        PerMode::Symbol => {
+            let numerator: Vec<String> = parts
+                .iter()
+                .filter(|(_, _, d)| !d)
+                .map(|(s, p, _)| render((s, *p)))
+                .collect();
+            let denominator: Vec<String> = parts
+                .iter()
+                .filter(|(_, _, d)| *d)
+                .map(|(s, p, _)| render((s, *p)))
+                .collect();
+            let numerator = if numerator.is_empty() {
+                "1".to_string()
+            } else {
+                numerator.join(" ")
+            };
+            match denominator.len() {
+                0 => numerator,
+                1 => format!("{}/{}", numerator, denominator[0]),
+                _ => format!("{}/({})", numerator, denominator.join(" ")),
+            }
+        }
+    }
+}
+
+/// Convert a siunitx number to Typst math (e.g. `1.23e4` -> `1.23 times 10^4`)
+pub fn number_to_typst_math(num: &str, options: &SiOptions) -> String {
+    let num = num.trim();
+
+    let (mantissa, exponent) = match num.find(['e', 'E']) {
+        Some(pos) => (num[..pos].trim(), Some(num[pos + 1..].trim())),
+        None => (num, None),
+    };
+
+    let mantissa = mantissa
+        .replace("+-", " plus.minus ")
+        .replace("-+", " minus.plus ");
+    let mantissa = match &options.group_separator {
+        Some(sep) => group_digits(mantissa.trim(), sep),
+        None => mantissa.trim().to_string(),
+    };
+
+    match exponent {
+        Some(exp) => {
+            let exp = exp.trim_start_matches('+');
+            let power = if exp.len() == 1 {
+                exp.to_string()
+            } else {
+                format!("({})", exp)
+            };
+            if mantissa.is_empty() || mantissa == "1" && options.group_separator.is_none() {
+                format!("10^{}", power)
+            } else {
+                format!("{} times 10^{}", mantissa, power)
+            }
+        }
+        None => mantissa,
+    }
+}
+
+/// Group integer digits in threes (only for five or more digits, as siunitx does)
+fn group_digits(num: &str, separator: &str) -> String {
+    let (sign, rest) = match num.strip_prefix('-') {
+        Some(r) => ("-", r),
+        None => ("", num),
+    };
+    let (int_part, frac_part) = match rest.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (rest, None),
+    };
+
+    if int_part.len() < 5 || !int_part.chars().all(|c| c.is_ascii_digit()) {
+        return num.to_string();
+    }
+
+    let sep = match separator.trim() {
+        "" | "\\," | "\\ " | "\\:" | "\\;" => "\u{2009}",
+        s => s,
+    };
+
+    let mut grouped = String::new();
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push_str(sep);
+        }
+        grouped.push(c);
+    }
+    if let Some(f) = frac_part {
+        grouped.push('.');
+        grouped.push_str(f);
+    }
+
+    // Quote so Typst math does not space the separator as punctuation
+    format!("{}\"{}\"", sign, grouped)
+}
+
+/// Convert `\ang{d;m;s}` content to text with degree/minute/second marks
+pub fn angle_to_text(angle: &str) -> String {
+    convert_angle(angle)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -590,4 +928,57 @@ mod tests {
         assert_eq!(convert_unit(r"\MHz"), "MHz");
         assert_eq!(convert_unit(r"\celsius"), "°C");
     }
+
+    #[test]
+    fn test_si_options_apply() {
+        let mut opts = SiOptions::default();
+        opts.apply("range-units = single, group-separator = {,}");
+        assert_eq!(opts.range_units, RangeUnits::Single);
+        assert_eq!(opts.group_separator.as_deref(), Some(","));
+
+        let local = opts.with_overrides(Some("range-units=brackets,per-mode=power"));
+        assert_eq!(local.range_units, RangeUnits::Brackets);
+        assert_eq!(local.per_mode, PerMode::Power);
+        assert_eq!(opts.range_units, RangeUnits::Single);
+    }
+
+    #[test]
+    fn test_unit_to_text() {
+        let custom = HashMap::new();
+        let opts = SiOptions::default();
+        assert_eq!(
+            unit_to_text(r"\kilo\metre\per\second", &custom, &opts),
+            "km/s"
+        );
+        assert_eq!(
+            unit_to_text(r"\metre\per\second\squared", &custom, &opts),
+            "m/s²"
+        );
+        assert_eq!(unit_to_text(r"\celsius", &custom, &opts), "°C");
+
+        let power = SiOptions {
+            per_mode: PerMode::Power,
+            ..Default::default()
+        };
+        assert_eq!(unit_to_text(r"\metre\per\second", &custom, &power), "m s⁻¹");
+
+        let mut custom = HashMap::new();
+        custom.insert("\\clight".to_string(), "c".to_string());
+        assert_eq!(unit_to_text(r"\clight", &custom, &opts), "c");
+    }
+
+    #[test]
+    fn test_number_to_typst_math() {
+        let opts = SiOptions::default();
+        assert_eq!(number_to_typst_math("1.23e4", &opts), "1.23 times 10^4");
+        assert_eq!(number_to_typst_math("5e-10", &opts), "5 times 10^(-10)");
+        assert_eq!(number_to_typst_math("1234567", &opts), "1234567");
+
+        let grouped = SiOptions {
+            group_separator: Some(",".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(number_to_typst_math("1234567", &grouped), "\"1,234,567\"");
+        assert_eq!(number_to_typst_math("1234", &grouped), "1234");
+    }
 }
@@ -436,28 +436,37 @@ fn replace_command_safe(input: &str, cmd: &str, replacement: &str) -> String {
     result
 }
 
+/// Entries of a replacement table, longest key first. Replacing in this
+/// order never lets a command eat the start of a longer one, and the result
+/// does not depend on the order in which the table hashes its keys.
+pub fn longest_first<V: Copy>(table: &HashMap<&'static str, V>) -> Vec<(&'static str, V)> {
+    let mut entries: Vec<_> = table.iter().map(|(key, value)| (*key, *value)).collect();
+    entries.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+    entries
+}
+
 /// Apply simple symbol replacements
 pub fn apply_symbol_replacements(input: &str) -> String {
     let mut result = input.to_string();
 
     // Apply all symbol maps
-    for (latex, typst) in MISC_SYMBOLS.iter() {
+    for (latex, typst) in longest_first(&MISC_SYMBOLS) {
         result = result.replace(latex, typst);
     }
-    for (latex, typst) in CHAR_COMMANDS.iter() {
+    for (latex, typst) in longest_first(&CHAR_COMMANDS) {
         result = result.replace(latex, typst);
     }
     // Use safe replacement for LETTER_COMMANDS to avoid \th matching \theta
-    for (latex, typst) in LETTER_COMMANDS.iter() {
+    for (latex, typst) in longest_first(&LETTER_COMMANDS) {
         result = replace_command_safe(&result, latex, typst);
     }
-    for (latex, typst) in BIBLATEX_COMMANDS.iter() {
+    for (latex, typst) in longest_first(&BIBLATEX_COMMANDS) {
         result = result.replace(latex, typst);
     }
-    for (latex, typst) in NAME_COMMANDS.iter() {
+    for (latex, typst) in longest_first(&NAME_COMMANDS) {
         result = result.replace(latex, typst);
     }
-    for (latex, typst) in GREEK_LETTERS.iter() {
+    for (latex, typst) in longest_first(&GREEK_LETTERS) {
         result = result.replace(latex, typst);
     }
 
@@ -817,6 +826,81 @@ pub fn convert_delimiter(delim: &str) -> Option<&'static str> {
     DELIMITER_TO_TYPST.get(delim).copied()
 }
 
+// ============================================================================
+// Negated Relations
+// ============================================================================
+
+lazy_static! {
+    /// Negated Typst symbols for `\not <relation>`, keyed by the Typst form
+    /// of the relation (symbol name or shorthand)
+    pub static ref NEGATED_RELATIONS: HashMap<&'static str, &'static str> = {
+        let mut m = HashMap::new();
+        // Equality and order
+        m.insert("=", "eq.not");
+        m.insert("eq", "eq.not");
+        m.insert("<", "lt.not");
+        m.insert("lt", "lt.not");
+        m.insert(">", "gt.not");
+        m.insert("gt", "gt.not");
+        m.insert("<=", "lt.eq.not");
+        m.insert("lt.eq", "lt.eq.not");
+        m.insert(">=", "gt.eq.not");
+        m.insert("gt.eq", "gt.eq.not");
+        m.insert("prec", "prec.not");
+        m.insert("succ", "succ.not");
+        m.insert("prec.curly.eq", "prec.curly.eq.not");
+        m.insert("succ.curly.eq", "succ.curly.eq.not");
+        m.insert("lt.tri", "lt.tri.not");
+        m.insert("gt.tri", "gt.tri.not");
+        m.insert("lt.tri.eq", "lt.tri.eq.not");
+        m.insert("gt.tri.eq", "gt.tri.eq.not");
+        // Equivalence and similarity
+        m.insert("equiv", "equiv.not");
+        m.insert("approx", "approx.not");
+        m.insert("asymp", "asymp.not");
+        m.insert("~", "tilde.not");
+        m.insert("tilde.op", "tilde.not");
+        m.insert("tilde.eq", "tilde.eq.not");
+        m.insert("tilde.equiv", "tilde.equiv.not");
+        // Sets
+        m.insert("in", "in.not");
+        m.insert("in.rev", "in.rev.not");
+        m.insert("ni", "in.rev.not");
+        m.insert("subset", "subset.not");
+        m.insert("supset", "supset.not");
+        m.insert("subset.eq", "subset.eq.not");
+        m.insert("supset.eq", "supset.eq.not");
+        m.insert("subset.sq.eq", "subset.sq.eq.not");
+        m.insert("supset.sq.eq", "supset.sq.eq.not");
+        m.insert("exists", "exists.not");
+        // Divisibility and parallelism
+        m.insert("divides", "divides.not");
+        m.insert("parallel", "parallel.not");
+        // Arrows
+        m.insert("<-", "arrow.l.not");
+        m.insert("arrow.l", "arrow.l.not");
+        m.insert("->", "arrow.r.not");
+        m.insert("arrow.r", "arrow.r.not");
+        m.insert("<->", "arrow.l.r.not");
+        m.insert("arrow.l.r", "arrow.l.r.not");
+        m.insert("arrow.l.double", "arrow.l.double.not");
+        m.insert("=>", "arrow.r.double.not");
+        m.insert("arrow.r.double", "arrow.r.double.not");
+        m.insert("<=>", "arrow.l.r.double.not");
+        m.insert("arrow.l.r.double", "arrow.l.r.double.not");
+        // Turnstiles
+        m.insert("tack.r", "tack.r.not");
+        m.insert("tack.r.double", "tack.r.double.not");
+        m.insert("forces", "forces.not");
+        m
+    };
+}
+
+/// Look up the negated Typst symbol for a relation given in Typst notation
+pub fn negate_relation(relation: &str) -> Option<&'static str> {
+    NEGATED_RELATIONS.get(relation).copied()
+}
+
 // ============================================================================
 // Caption/Title Text Formatting Commands
 // ============================================================================
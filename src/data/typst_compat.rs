@@ -36,8 +36,10 @@ lazy_static! {
         m.insert("bold", MarkupHandler::Wrap { prefix: "\\textbf{", suffix: "}" });
         m.insert("emph", MarkupHandler::Wrap { prefix: "\\textit{", suffix: "}" });
         m.insert("italic", MarkupHandler::Wrap { prefix: "\\textit{", suffix: "}" });
-        m.insert("underline", MarkupHandler::Wrap { prefix: "\\underline{", suffix: "}" });
-        m.insert("strike", MarkupHandler::Wrap { prefix: "\\sout{", suffix: "}" });
+        m.insert("underline", MarkupHandler::Special);
+        m.insert("strike", MarkupHandler::Special);
+        m.insert("overline", MarkupHandler::Special);
+        m.insert("highlight", MarkupHandler::Special);
         m.insert("smallcaps", MarkupHandler::Wrap { prefix: "\\textsc{", suffix: "}" });
         m.insert("raw", MarkupHandler::Wrap { prefix: "\\texttt{", suffix: "}" });
         m.insert("sub", MarkupHandler::Wrap { prefix: "\\textsubscript{", suffix: "}" });
@@ -45,14 +47,17 @@ lazy_static! {
 
         // Document structure
         m.insert("heading", MarkupHandler::Wrap { prefix: "\\section{", suffix: "}\n" });
-        m.insert("list", MarkupHandler::Environment { name: "itemize" });
-        m.insert("enum", MarkupHandler::Environment { name: "enumerate" });
+        m.insert("list", MarkupHandler::Special);
+        m.insert("enum", MarkupHandler::Special);
         m.insert("quote", MarkupHandler::Environment { name: "quote" });
         m.insert("figure", MarkupHandler::Special);
+        m.insert("wrap-content", MarkupHandler::Special);
+        m.insert("margin-note", MarkupHandler::Special);
+        m.insert("inline-note", MarkupHandler::Special);
         m.insert("table", MarkupHandler::Special);
 
         // Page elements
-        m.insert("pagebreak", MarkupHandler::Wrap { prefix: "\\newpage", suffix: "" });
+        m.insert("pagebreak", MarkupHandler::Special);
         m.insert("linebreak", MarkupHandler::Wrap { prefix: "\\\\", suffix: "" });
         m.insert("line", MarkupHandler::Wrap { prefix: "\\hrule", suffix: "" });
 
@@ -72,6 +77,8 @@ lazy_static! {
         m.insert("footnote", MarkupHandler::Special);
         m.insert("caption", MarkupHandler::Special);
         m.insert("rotate", MarkupHandler::Special);
+        m.insert("place", MarkupHandler::Special);
+        m.insert("page", MarkupHandler::Special);
         m.insert("rect", MarkupHandler::Special);
         m.insert("box", MarkupHandler::Special);
         m.insert("block", MarkupHandler::Special);
@@ -173,8 +180,8 @@ lazy_static! {
         m.insert("caron", MathHandler::Command { latex_cmd: "\\check" });
 
         // Delimiters
-        m.insert("abs", MathHandler::Delimiters { open: "\\left|", close: "\\right|" });
-        m.insert("norm", MathHandler::Delimiters { open: "\\left\\|", close: "\\right\\|" });
+        m.insert("abs", MathHandler::Special);
+        m.insert("norm", MathHandler::Special);
         m.insert("floor", MathHandler::Delimiters { open: "\\left\\lfloor ", close: "\\right\\rfloor" });
         m.insert("ceil", MathHandler::Delimiters { open: "\\left\\lceil ", close: "\\right\\rceil" });
         m.insert("round", MathHandler::Delimiters { open: "\\left\\lfloor ", close: "\\right\\rceil" });
@@ -244,8 +251,8 @@ lazy_static! {
         m.insert("math.vec", MathHandler::Special);  // Column vector -> pmatrix
         m.insert("math.mat", MathHandler::Environment { name: "matrix" });
         m.insert("math.cases", MathHandler::Environment { name: "cases" });
-        m.insert("math.abs", MathHandler::Delimiters { open: "\\left|", close: "\\right|" });
-        m.insert("math.norm", MathHandler::Delimiters { open: "\\left\\|", close: "\\right\\|" });
+        m.insert("math.abs", MathHandler::Special);
+        m.insert("math.norm", MathHandler::Special);
         m.insert("math.floor", MathHandler::Delimiters { open: "\\left\\lfloor ", close: "\\right\\rfloor" });
         m.insert("math.ceil", MathHandler::Delimiters { open: "\\left\\lceil ", close: "\\right\\rceil" });
         m.insert("math.round", MathHandler::Delimiters { open: "\\left\\lfloor ", close: "\\right\\rceil" });
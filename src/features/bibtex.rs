@@ -28,9 +28,9 @@
 //! assert_eq!(entries[0].entry_type, "article");
 //! ```
 
+use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
 use std::fmt::Write;
 
 lazy_static! {
@@ -72,7 +72,7 @@ pub struct BibEntry {
     /// Citation key
     pub key: String,
     /// Fields and their values
-    pub fields: HashMap<String, String>,
+    pub fields: IndexMap<String, String>,
 }
 
 impl BibEntry {
@@ -81,7 +81,7 @@ impl BibEntry {
         Self {
             entry_type: entry_type.to_lowercase(),
             key: key.to_string(),
-            fields: HashMap::new(),
+            fields: IndexMap::new(),
         }
     }
 
@@ -217,10 +217,10 @@ fn escape_yaml_string(s: &str) -> String {
 /// A collection of BibTeX entries
 #[derive(Debug, Clone, Default)]
 pub struct Bibliography {
-    /// All entries keyed by citation key
-    pub entries: HashMap<String, BibEntry>,
+    /// All entries keyed by citation key, in the order of the file
+    pub entries: IndexMap<String, BibEntry>,
     /// String definitions (@string{...})
-    pub strings: HashMap<String, String>,
+    pub strings: IndexMap<String, String>,
     /// Preamble content
     pub preamble: Vec<String>,
 }
@@ -345,7 +345,7 @@ fn parse_single_entry(input: &str) -> Option<BibEntry> {
     parse_fields(fields_content, &mut entry);
 
     // Clean up field values (remove LaTeX encoding)
-    let cleaned_fields: HashMap<String, String> = entry
+    let cleaned_fields: IndexMap<String, String> = entry
         .fields
         .iter()
         .map(|(k, v)| (k.clone(), clean_latex_encoding(v)))
@@ -0,0 +1,193 @@
+//! CSV/TSV data tables
+//!
+//! `\csvautotabular` (csvsimple) and `\pgfplotstabletypeset` (pgfplotstable)
+//! typeset a whole data file as a table, as does a Typst `table(..)` spread
+//! from `csv(..)`. This module reads such files and the options of the LaTeX
+//! commands that describe them.
+
+/// How a data file is split into cells and typeset
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataTableOptions {
+    /// Cell separator; `None` splits at runs of whitespace
+    pub separator: Option<char>,
+    /// Whether the first row is a header
+    pub header: bool,
+    /// Alignment letter (`l`, `c`, `r`) of each column; the last one
+    /// repeats for further columns
+    pub alignments: Vec<char>,
+}
+
+impl DataTableOptions {
+    /// Options of `\csvautotabular[..]`: comma separated with a header row,
+    /// left-aligned columns
+    pub fn csvsimple(options: &str) -> Self {
+        let mut table = DataTableOptions {
+            separator: Some(','),
+            header: true,
+            alignments: vec!['l'],
+        };
+        for (key, value) in split_options(options) {
+            match key.as_str() {
+                "separator" => table.separator = separator_named(&value),
+                "no head" => table.header = false,
+                "head" => table.header = value != "false",
+                "tabular" | "centered tabular" | "longtable" => {
+                    table.alignments = column_letters(&value)
+                }
+                _ => {}
+            }
+        }
+        table
+    }
+
+    /// Options of `\pgfplotstabletypeset[..]`: whitespace separated with a
+    /// header row, centered columns
+    pub fn pgfplotstable(options: &str) -> Self {
+        let mut table = DataTableOptions {
+            separator: None,
+            header: true,
+            alignments: vec!['c'],
+        };
+        for (key, value) in split_options(options) {
+            match key.as_str() {
+                "col sep" => table.separator = separator_named(&value),
+                "header" => table.header = value != "false",
+                "column type" => table.alignments = column_letters(&value),
+                _ => {}
+            }
+        }
+        table
+    }
+
+    /// Alignment letter of column `index`
+    pub fn alignment(&self, index: usize) -> char {
+        self.alignments
+            .get(index)
+            .or(self.alignments.last())
+            .copied()
+            .unwrap_or('l')
+    }
+}
+
+/// Split `key=value` options at top-level commas; a bare key gets an empty
+/// value
+fn split_options(options: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in options.chars().chain(std::iter::once(',')) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                let (key, value) = current.split_once('=').unwrap_or((&current, ""));
+                let key = key.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !key.is_empty() {
+                    pairs.push((key, value.trim().to_string()));
+                }
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        if c != '{' && c != '}' {
+            current.push(c);
+        }
+    }
+    pairs
+}
+
+/// Separator character for a csvsimple `separator=` or pgfplotstable
+/// `col sep=` name
+fn separator_named(name: &str) -> Option<char> {
+    match name.trim() {
+        "comma" => Some(','),
+        "semicolon" => Some(';'),
+        "colon" => Some(':'),
+        "tab" => Some('\t'),
+        "pipe" => Some('|'),
+        "ampersand" | "&" => Some('&'),
+        "space" => None,
+        other => other.chars().next().filter(|_| other.chars().count() == 1),
+    }
+}
+
+/// Alignment letters of a column spec such as `|l|c|r|`
+fn column_letters(spec: &str) -> Vec<char> {
+    spec.chars()
+        .filter(|c| matches!(c, 'l' | 'c' | 'r'))
+        .collect()
+}
+
+/// Split the text of a data file into rows of cells. Blank lines are
+/// skipped; with a separator, a cell may be quoted (`"a, b"`, with `""` for
+/// a quote inside it).
+pub fn parse_delimited(content: &str, separator: Option<char>) -> Vec<Vec<String>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match separator {
+            None => line.split_whitespace().map(str::to_string).collect(),
+            Some(separator) => split_quoted(line, separator),
+        })
+        .collect()
+}
+
+fn split_quoted(line: &str, separator: char) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted || cell.trim().is_empty() => {
+                if !quoted {
+                    cell.clear();
+                }
+                quoted = !quoted;
+            }
+            c if c == separator && !quoted => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+            }
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delimited() {
+        assert_eq!(
+            parse_delimited("a,\"b, c\",\"say \"\"hi\"\"\"\n\n1,2,3\n", Some(',')),
+            vec![vec!["a", "b, c", "say \"hi\""], vec!["1", "2", "3"]]
+        );
+        assert_eq!(
+            parse_delimited("x  y\n1\t2\n", None),
+            vec![vec!["x", "y"], vec!["1", "2"]]
+        );
+    }
+
+    #[test]
+    fn test_command_options() {
+        let csv = DataTableOptions::csvsimple("separator=semicolon, no head, tabular=|l|r|");
+        assert_eq!(csv.separator, Some(';'));
+        assert!(!csv.header);
+        assert_eq!((csv.alignment(0), csv.alignment(5)), ('l', 'r'));
+
+        let pgf = DataTableOptions::pgfplotstable("col sep=tab, header=false");
+        assert_eq!(pgf.separator, Some('\t'));
+        assert!(!pgf.header);
+        assert_eq!(pgf.alignment(2), 'c');
+        assert_eq!(DataTableOptions::pgfplotstable("").separator, None);
+    }
+}
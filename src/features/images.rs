@@ -678,6 +678,78 @@ fn find_matching_paren(s: &str, open: char, close: char) -> Option<usize> {
     None
 }
 
+/// Default extension probing order for `\includegraphics{plot}`
+pub const DEFAULT_IMAGE_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "svg"];
+
+/// Image formats Typst cannot embed; they need converting first
+pub const UNSUPPORTED_IMAGE_EXTENSIONS: &[&str] = &["eps", "ps", "tif", "tiff"];
+
+/// An image referenced by a converted document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageAsset {
+    /// Path as written in the source, e.g. `plot` in `\includegraphics{plot}`
+    pub source: String,
+    /// Path written to the output
+    pub path: String,
+    /// Whether a file resolver found the file at `path`
+    pub found: bool,
+}
+
+/// Extension of the file name in `path`, if it has one
+pub fn image_extension(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    name.rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty())
+}
+
+/// Check whether Typst needs the image converted to another format first
+pub fn needs_format_conversion(path: &str) -> bool {
+    image_extension(path).is_some_and(|ext| {
+        UNSUPPORTED_IMAGE_EXTENSIONS
+            .iter()
+            .any(|unsupported| ext.eq_ignore_ascii_case(unsupported))
+    })
+}
+
+/// Split a `\graphicspath` argument like `{figs/}{imgs/}` into directories,
+/// each ending in `/`
+pub fn parse_graphics_path(arg: &str) -> Vec<String> {
+    arg.split(['{', '}'])
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| {
+            let dir = dir.strip_prefix("./").unwrap_or(dir);
+            if dir.ends_with('/') || dir.is_empty() {
+                dir.to_string()
+            } else {
+                format!("{}/", dir)
+            }
+        })
+        .collect()
+}
+
+/// Paths to try for an image, in the order graphicx does: the path itself,
+/// then under each `\graphicspath` directory, each with every extension when
+/// the path has none.
+pub fn image_path_candidates(path: &str, dirs: &[String], extensions: &[String]) -> Vec<String> {
+    let mut bases = vec![path.to_string()];
+    if !path.starts_with('/') {
+        bases.extend(dirs.iter().map(|dir| format!("{}{}", dir, path)));
+    }
+    if image_extension(path).is_some() {
+        return bases;
+    }
+    bases
+        .iter()
+        .flat_map(|base| {
+            extensions
+                .iter()
+                .map(move |ext| format!("{}.{}", base, ext))
+        })
+        .collect()
+}
+
 /// Convert LaTeX \includegraphics to Typst #image
 pub fn convert_includegraphics_to_typst(latex: &str) -> Option<String> {
     let latex = latex.trim();
@@ -728,6 +800,30 @@ pub fn convert_image_to_latex(typst: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_image_path_candidates() {
+        let dirs = parse_graphics_path("{figs/}{./imgs}");
+        assert_eq!(dirs, vec!["figs/", "imgs/"]);
+        let extensions = vec!["pdf".to_string(), "png".to_string()];
+        assert_eq!(
+            image_path_candidates("plot", &dirs, &extensions),
+            vec![
+                "plot.pdf",
+                "plot.png",
+                "figs/plot.pdf",
+                "figs/plot.png",
+                "imgs/plot.pdf",
+                "imgs/plot.png"
+            ]
+        );
+        assert_eq!(
+            image_path_candidates("a.v2/plot.png", &dirs, &extensions),
+            vec!["a.v2/plot.png", "figs/a.v2/plot.png", "imgs/a.v2/plot.png"]
+        );
+        assert!(needs_format_conversion("figs/diagram.EPS"));
+        assert!(!needs_format_conversion("plot.pdf"));
+    }
+
     #[test]
     fn test_dimension_parse() {
         assert_eq!(Dimension::parse("10cm"), Some(Dimension::Centimeter(10.0)));
@@ -2,6 +2,7 @@
 //!
 //! This module contains specialized handlers for complex LaTeX/Typst features:
 //! - Tables (tabular, multicolumn, multirow)
+//! - CSV/TSV data tables
 //! - Images and figures
 //! - Citations and cross-references
 //! - BibTeX parsing
@@ -9,6 +10,7 @@
 //! - Document templates
 
 pub mod bibtex;
+pub mod data_tables;
 pub mod images;
 pub mod refs;
 pub mod tables;
@@ -134,6 +134,11 @@ pub struct Label {
     pub label_type: LabelType,
     /// Display number (if assigned)
     pub number: Option<String>,
+    /// Title of the labeled heading, as Typst markup
+    pub title: Option<String>,
+    /// Name references to the element start with, e.g. `Theorem`, when
+    /// Typst cannot supply it
+    pub supplement: Option<String>,
 }
 
 /// Type of labeled element
@@ -159,6 +164,8 @@ impl Label {
             id,
             label_type,
             number: None,
+            title: None,
+            supplement: None,
         }
     }
 }
@@ -224,6 +231,8 @@ impl RefDatabase {
             id: id.clone(),
             label_type,
             number: Some(counter.to_string()),
+            title: None,
+            supplement: None,
         };
 
         self.labels.insert(id.clone(), label);
@@ -583,6 +592,63 @@ pub fn reference_to_latex(reference: &Reference) -> String {
     format!("{}{{{}}}", cmd, reference.target)
 }
 
+/// Supplement implied by a cleveref-style label prefix (`fig:`, `eq-`, `sec:` ...)
+pub fn supplement_for_label(label: &str) -> Option<&'static str> {
+    let (prefix, _) = label.split_once([':', '-'])?;
+    let supplement = match prefix.to_lowercase().as_str() {
+        "fig" | "figure" => "Figure",
+        "tab" | "table" => "Table",
+        "eq" | "eqn" | "equation" => "Equation",
+        "sec" | "subsec" | "section" => "Section",
+        "ch" | "chap" | "chapter" => "Chapter",
+        "app" | "appendix" => "Appendix",
+        "thm" | "theorem" => "Theorem",
+        "lem" | "lemma" => "Lemma",
+        "def" | "defn" | "definition" => "Definition",
+        "cor" | "corollary" => "Corollary",
+        "prop" | "proposition" => "Proposition",
+        "alg" | "algorithm" => "Algorithm",
+        "lst" | "listing" => "Listing",
+        _ => return None,
+    };
+    Some(supplement)
+}
+
+/// Join references the way cleveref lists them: `a`, `a and b`, `a, b and c`
+fn join_reference_list(parts: &[String]) -> String {
+    match parts {
+        [] => String::new(),
+        [only] => only.clone(),
+        [init @ .., last] => format!("{} and {}", init.join(", "), last),
+    }
+}
+
+/// Convert a cleveref command over already-sanitized keys to Typst
+///
+/// `\cref` relies on Typst's automatic supplement, `\Cref` pins a capitalized
+/// supplement derived from the label prefix, `\labelcref` drops the supplement
+/// and `\namecref` degrades to the bare supplement name.
+pub fn cleveref_to_typst(command: &str, keys: &[String]) -> String {
+    let parts: Vec<String> = keys
+        .iter()
+        .map(|key| match (command, supplement_for_label(key)) {
+            ("Cref" | "Crefrange", Some(supplement)) => {
+                format!("#ref(<{}>, supplement: [{}])", key, supplement)
+            }
+            ("labelcref", _) => format!("#ref(<{}>, supplement: none)", key),
+            ("namecref", Some(supplement)) => supplement.to_lowercase(),
+            ("nameCref", Some(supplement)) => supplement.to_string(),
+            _ => reference_to_typst(&Reference::named(key.clone())),
+        })
+        .collect();
+
+    if matches!(command, "crefrange" | "Crefrange") {
+        parts.join("–")
+    } else {
+        join_reference_list(&parts)
+    }
+}
+
 /// Convert label to LaTeX
 pub fn label_to_latex(id: &str) -> String {
     format!("\\label{{{}}}", id)
@@ -938,6 +1004,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cleveref_to_typst() {
+        let keys = |k: &[&str]| k.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(cleveref_to_typst("cref", &keys(&["eq-1"])), "@eq-1");
+        assert_eq!(
+            cleveref_to_typst("cref", &keys(&["eq-1", "fig-2", "sec-3"])),
+            "@eq-1, @fig-2 and @sec-3"
+        );
+        assert_eq!(
+            cleveref_to_typst("Cref", &keys(&["sec-intro"])),
+            "#ref(<sec-intro>, supplement: [Section])"
+        );
+        assert_eq!(cleveref_to_typst("Cref", &keys(&["intro"])), "@intro");
+        assert_eq!(
+            cleveref_to_typst("crefrange", &keys(&["eq-a", "eq-b"])),
+            "@eq-a–@eq-b"
+        );
+        assert_eq!(
+            cleveref_to_typst("labelcref", &keys(&["fig-x"])),
+            "#ref(<fig-x>, supplement: none)"
+        );
+        assert_eq!(cleveref_to_typst("namecref", &keys(&["fig-x"])), "figure");
+    }
+
     #[test]
     fn test_label_conversions() {
         assert_eq!(label_to_latex("sec:intro"), "\\label{sec:intro}");
@@ -533,79 +533,111 @@ fn extract_table_content(input: &str) -> String {
     input.to_string()
 }
 
+/// Rules drawn between rows
+const RULE_COMMANDS: &[&str] = &["hline", "toprule", "midrule", "bottomrule", "cmidrule"];
+
+/// longtable markers that end the head and foot rows
+const LONGTABLE_MARKERS: &[&str] = &["endhead", "endfirsthead", "endfoot", "endlastfoot"];
+
 /// Parse table rows from content
+///
+/// Math (`$..$`, `\(..\)`) is atomic: an `&` or `\\` inside it, e.g. in an
+/// `aligned` block, does not separate cells or rows.
 fn parse_rows(content: &str, _num_cols: usize) -> Vec<Row> {
     let mut rows: Vec<Row> = Vec::new();
     let mut current_row = Row::new();
     let mut current_cell = String::new();
     let mut in_brace = 0;
+    let mut in_math = false;
 
-    let lines: Vec<&str> = content.lines().collect();
-
-    for line in lines {
-        let line = line.trim();
-
-        // Skip hline/cline commands but track them
-        if line.starts_with("\\hline")
-            || line.starts_with("\\toprule")
-            || line.starts_with("\\midrule")
-            || line.starts_with("\\bottomrule")
-            || line.starts_with("\\cmidrule")
-        {
-            if !current_row.is_empty() {
-                current_row.has_bottom_border = true;
-            } else if let Some(last) = rows.last_mut() {
-                last.has_bottom_border = true;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        let outside = in_brace == 0 && !in_math;
+        match c {
+            '%' => {
+                // Comment up to the end of the line
+                while chars.next_if(|&c| c != '\n').is_some() {}
             }
-            continue;
-        }
-
-        // Skip endhead, endfirsthead for longtable
-        if line.starts_with("\\endhead") || line.starts_with("\\endfirsthead") {
-            continue;
-        }
-
-        // Parse row content
-        for c in line.chars() {
-            match c {
-                '{' => {
-                    in_brace += 1;
-                    current_cell.push(c);
+            '\\' => match chars.peek().copied() {
+                Some('\\') if outside => {
+                    chars.next();
+                    skip_bracketed_arg(&mut chars);
+                    let cell_content = current_cell.trim();
+                    if !cell_content.is_empty() || !current_row.cells.is_empty() {
+                        current_row.push(parse_cell(cell_content));
+                    }
+                    if !current_row.is_empty() {
+                        rows.push(std::mem::take(&mut current_row));
+                    }
+                    current_cell.clear();
                 }
-                '}' => {
-                    in_brace -= 1;
-                    current_cell.push(c);
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut name = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                        name.push(c);
+                    }
+                    let at_row_start = current_cell.trim().is_empty() && current_row.is_empty();
+                    if outside && RULE_COMMANDS.contains(&name.as_str()) {
+                        if !current_row.is_empty() {
+                            current_row.has_bottom_border = true;
+                        } else if let Some(last) = rows.last_mut() {
+                            last.has_bottom_border = true;
+                        }
+                        // \cmidrule(lr){2-3}
+                        skip_bracketed_arg(&mut chars);
+                        if chars.peek() == Some(&'(') {
+                            while chars.next().is_some_and(|c| c != ')') {}
+                        }
+                        if name == "cmidrule" {
+                            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                            skip_braced_content(&mut chars);
+                        }
+                    } else if !(outside
+                        && at_row_start
+                        && LONGTABLE_MARKERS.contains(&name.as_str()))
+                    {
+                        current_cell.push('\\');
+                        current_cell.push_str(&name);
+                    }
                 }
-                '&' if in_brace == 0 => {
-                    // Cell separator
-                    let cell = parse_cell(current_cell.trim());
-                    current_row.push(cell);
-                    current_cell.clear();
+                Some(delim @ ('(' | ')')) => {
+                    chars.next();
+                    in_math = delim == '(';
+                    current_cell.push('\\');
+                    current_cell.push(delim);
                 }
-                _ => {
-                    current_cell.push(c);
+                Some(escaped) => {
+                    // \&, \$, \{, \} and other control symbols
+                    chars.next();
+                    current_cell.push('\\');
+                    current_cell.push(escaped);
+                }
+                None => current_cell.push('\\'),
+            },
+            '$' => {
+                in_math = !in_math;
+                current_cell.push(c);
+                if chars.peek() == Some(&'$') {
+                    current_cell.push('$');
+                    chars.next();
                 }
             }
-        }
-
-        // Check for row end
-        if line.ends_with("\\\\") || line.contains("\\\\") {
-            // Remove trailing \\
-            let cell_content = current_cell
-                .trim()
-                .trim_end_matches("\\\\")
-                .trim_end_matches('\\')
-                .trim();
-            if !cell_content.is_empty() || !current_row.cells.is_empty() {
-                let cell = parse_cell(cell_content);
-                current_row.push(cell);
+            '{' => {
+                in_brace += 1;
+                current_cell.push(c);
             }
-
-            if !current_row.is_empty() {
-                rows.push(current_row);
+            '}' => {
+                in_brace -= 1;
+                current_cell.push(c);
+            }
+            '&' if outside => {
+                // Cell separator
+                current_row.push(parse_cell(current_cell.trim()));
+                current_cell.clear();
+            }
+            _ => {
+                current_cell.push(c);
             }
-            current_row = Row::new();
-            current_cell.clear();
         }
     }
 
@@ -621,6 +653,16 @@ fn parse_rows(content: &str, _num_cols: usize) -> Vec<Row> {
     rows
 }
 
+/// Skip an optional `[..]` argument, e.g. the spacing of `\\[2pt]`
+fn skip_bracketed_arg(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    let mut lookahead = chars.clone();
+    while lookahead.next_if(|c| c.is_whitespace()).is_some() {}
+    if lookahead.next() == Some('[') {
+        *chars = lookahead;
+        while chars.next().is_some_and(|c| c != ']') {}
+    }
+}
+
 /// Parse a single cell, handling multicolumn/multirow
 fn parse_cell(content: &str) -> Cell {
     let content = content.trim();
@@ -1115,6 +1157,34 @@ A & B & C \\
         assert!(!table.header.is_empty());
     }
 
+    #[test]
+    fn test_math_in_cells_is_atomic() {
+        let input = r#"
+\begin{tabular}{lll}
+\hline
+MSE$\downarrow$ & Acc.\(\uparrow\) & $\begin{aligned} a &= b \\ c &= d \end{aligned}$ \\
+\hline
+1.0 & 2.0 \& more & 3.0 \\
+\end{tabular}
+"#;
+        let table = parse_latex_table(input).unwrap();
+        let header: Vec<&str> = table.header[0]
+            .cells
+            .iter()
+            .map(|cell| cell.content.as_str())
+            .collect();
+        assert_eq!(header.len(), 3, "{:?}", header);
+        assert_eq!(header[0], r"MSE$\downarrow$");
+        assert_eq!(header[1], r"Acc.\(\uparrow\)");
+        assert!(header[2].contains(r"a &= b \\ c &= d"), "{:?}", header);
+        assert!(table.header[0].has_bottom_border);
+
+        assert_eq!(table.body.len(), 1);
+        let body = &table.body[0].cells;
+        assert_eq!(body.len(), 3);
+        assert_eq!(body[1].content, r"2.0 \& more");
+    }
+
     #[test]
     fn test_colspec_parsing() {
         let specs = extract_colspecs("\\begin{tabular}{|l|c|r|}").unwrap();
@@ -36,6 +36,42 @@ lazy_static! {
     static ref COORD_POLAR: Regex = Regex::new(r"\((-?[\d.]+):(-?[\d.]+)([a-zA-Z]*)\)").unwrap();
 }
 
+/// CeTZ release line the generated code targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CetzVersion {
+    /// 0.2.x, without the `calc` coordinate helpers: coordinate arithmetic is
+    /// computed when its inputs are absolute, otherwise left in a comment
+    V0_2,
+    /// 0.3.x
+    #[default]
+    V0_3,
+    /// 0.4.x
+    V0_4,
+}
+
+impl CetzVersion {
+    /// Package version of the `#import` line
+    pub fn package_version(self) -> &'static str {
+        match self {
+            CetzVersion::V0_2 => "0.2.2",
+            CetzVersion::V0_3 => "0.3.4",
+            CetzVersion::V0_4 => "0.4.2",
+        }
+    }
+
+    /// Whether the `calc.add`/`calc.lerp` coordinate helpers exist
+    fn has_calc_helpers(self) -> bool {
+        self != CetzVersion::V0_2
+    }
+}
+
+/// A number rounded to four decimals, without trailing zeros
+fn format_number(value: f64) -> String {
+    let rounded = (value * 10_000.0).round() / 10_000.0;
+    // No `-0`
+    format!("{}", rounded + 0.0)
+}
+
 /// A parsed coordinate
 #[derive(Debug, Clone)]
 pub enum Coordinate {
@@ -308,6 +344,11 @@ impl Coordinate {
 
     /// Convert to CeTZ coordinate string
     pub fn to_cetz(&self) -> String {
+        self.to_cetz_for(CetzVersion::default())
+    }
+
+    /// Convert to a CeTZ coordinate string for the given CeTZ version
+    pub fn to_cetz_for(&self, version: CetzVersion) -> String {
         match self {
             Coordinate::Absolute(x, y) => format!("({}, {})", x, y),
             Coordinate::Relative(dx, dy) => format!("(rel: ({}, {}))", dx, dy),
@@ -320,7 +361,29 @@ impl Coordinate {
             }
             Coordinate::Named(name) => format!("\"{}\"", name),
             Coordinate::Variable { x_expr, y_expr } => format!("({}, {})", x_expr, y_expr),
-            Coordinate::Calc(expr) => expr.to_cetz(),
+            Coordinate::Calc(expr) => expr.to_cetz_for(version),
+        }
+    }
+
+    /// The point this coordinate stands for, if it does not depend on named
+    /// coordinates, variables or the current position
+    pub fn point(&self) -> Option<(f64, f64)> {
+        match self {
+            Coordinate::Absolute(x, y) => Some((*x, *y)),
+            Coordinate::Polar(angle, radius) => {
+                let rad = angle.to_radians();
+                Some((radius * rad.cos(), radius * rad.sin()))
+            }
+            Coordinate::Calc(expr) => expr.point(),
+            Coordinate::Relative(..) | Coordinate::Named(_) | Coordinate::Variable { .. } => None,
+        }
+    }
+
+    /// The vector an offset in coordinate arithmetic adds
+    fn vector(&self) -> Option<(f64, f64)> {
+        match self {
+            Coordinate::Relative(dx, dy) => Some((*dx, *dy)),
+            _ => self.point(),
         }
     }
 }
@@ -328,15 +391,52 @@ impl Coordinate {
 impl CalcExpr {
     /// Convert calc expression to CeTZ code
     pub fn to_cetz(&self) -> String {
+        self.to_cetz_for(CetzVersion::default())
+    }
+
+    /// Convert calc expression to CeTZ code for the given CeTZ version.
+    /// Without the `calc` helpers, an expression over absolute points
+    /// becomes the point it computes; any other stays a comment after its
+    /// first coordinate.
+    pub fn to_cetz_for(&self, version: CetzVersion) -> String {
+        if version.has_calc_helpers() {
+            return self.helper_call(version);
+        }
+        if let Some((x, y)) = self.point() {
+            return format!("({}, {})", format_number(x), format_number(y));
+        }
+        let first = match self {
+            CalcExpr::Add { base, .. } | CalcExpr::Sub { base, .. } => base,
+            CalcExpr::Lerp { from, .. } => from,
+            CalcExpr::Scale { coord, .. } => coord,
+            CalcExpr::Projection { point, .. } => point,
+        };
+        format!(
+            "{} /* {} */",
+            first.to_cetz_for(version),
+            self.helper_call(CetzVersion::V0_3)
+        )
+    }
+
+    /// The expression written with the `calc` helpers
+    fn helper_call(&self, version: CetzVersion) -> String {
         match self {
             CalcExpr::Add { base, offset } => {
                 // CeTZ: vector addition using calc.add or tuple math
                 // We output as a comment with manual calculation suggestion
                 // For simple cases, we can emit vector addition
-                format!("calc.add({}, {})", base.to_cetz(), offset.to_cetz())
+                format!(
+                    "calc.add({}, {})",
+                    base.to_cetz_for(version),
+                    offset.to_cetz_for(version)
+                )
             }
             CalcExpr::Sub { base, offset } => {
-                format!("calc.sub({}, {})", base.to_cetz(), offset.to_cetz())
+                format!(
+                    "calc.sub({}, {})",
+                    base.to_cetz_for(version),
+                    offset.to_cetz_for(version)
+                )
             }
             CalcExpr::Lerp { from, to, factor } => {
                 // CeTZ doesn't have lerp built-in, but we can use vector math
@@ -344,13 +444,13 @@ impl CalcExpr {
                 // For named coordinates, we need to output a helper
                 format!(
                     "calc.lerp({}, {}, {})",
-                    from.to_cetz(),
-                    to.to_cetz(),
+                    from.to_cetz_for(version),
+                    to.to_cetz_for(version),
                     factor
                 )
             }
             CalcExpr::Scale { coord, factor } => {
-                format!("calc.scale({}, {})", coord.to_cetz(), factor)
+                format!("calc.scale({}, {})", coord.to_cetz_for(version), factor)
             }
             CalcExpr::Projection {
                 line_start,
@@ -360,13 +460,50 @@ impl CalcExpr {
                 // Projection is complex - output as a comment
                 format!(
                     "/* projection of {} onto line from {} to {} */",
-                    point.to_cetz(),
-                    line_start.to_cetz(),
-                    line_end.to_cetz()
+                    point.to_cetz_for(version),
+                    line_start.to_cetz_for(version),
+                    line_end.to_cetz_for(version)
                 )
             }
         }
     }
+
+    /// The point this expression computes, if all its inputs are absolute
+    pub fn point(&self) -> Option<(f64, f64)> {
+        match self {
+            CalcExpr::Add { base, offset } => {
+                let ((x, y), (dx, dy)) = (base.point()?, offset.vector()?);
+                Some((x + dx, y + dy))
+            }
+            CalcExpr::Sub { base, offset } => {
+                let ((x, y), (dx, dy)) = (base.point()?, offset.vector()?);
+                Some((x - dx, y - dy))
+            }
+            CalcExpr::Lerp { from, to, factor } => {
+                let ((x1, y1), (x2, y2)) = (from.point()?, to.point()?);
+                Some((x1 + factor * (x2 - x1), y1 + factor * (y2 - y1)))
+            }
+            CalcExpr::Scale { coord, factor } => {
+                let (x, y) = coord.point()?;
+                Some((x * factor, y * factor))
+            }
+            CalcExpr::Projection {
+                line_start,
+                point,
+                line_end,
+            } => {
+                let ((ax, ay), (px, py), (cx, cy)) =
+                    (line_start.point()?, point.point()?, line_end.point()?);
+                let (dx, dy) = (cx - ax, cy - ay);
+                let length = dx * dx + dy * dy;
+                if length == 0.0 {
+                    return Some((ax, ay));
+                }
+                let t = ((px - ax) * dx + (py - ay) * dy) / length;
+                Some((ax + t * dx, ay + t * dy))
+            }
+        }
+    }
 }
 
 /// A path segment
@@ -1798,16 +1935,24 @@ fn find_matching_bracket(input: &str) -> Option<usize> {
 
 /// Convert a TikZ picture to CeTZ code
 pub fn convert_tikz_to_cetz(input: &str) -> String {
+    convert_tikz_to_cetz_for(input, CetzVersion::default())
+}
+
+/// Convert a TikZ picture to CeTZ code for the given CeTZ version
+pub fn convert_tikz_to_cetz_for(input: &str, version: CetzVersion) -> String {
     let commands = parse_tikz_picture(input);
     let mut output = String::new();
 
-    // CeTZ preamble (using 0.3.4 for better coordinate handling)
-    output.push_str("#import \"@preview/cetz:0.3.4\": canvas, draw\n\n");
+    let _ = writeln!(
+        output,
+        "#import \"@preview/cetz:{}\": canvas, draw\n",
+        version.package_version()
+    );
     output.push_str("#canvas({\n");
     output.push_str("  import draw: *\n\n");
 
     for cmd in commands {
-        convert_command_to_cetz(&mut output, &cmd, 1);
+        convert_command_to_cetz(&mut output, &cmd, 1, version);
     }
 
     output.push_str("})\n");
@@ -1816,7 +1961,12 @@ pub fn convert_tikz_to_cetz(input: &str) -> String {
 
 /// Convert a single TikZ command to CeTZ code
 /// `indent_level` controls the indentation for nested structures (e.g., foreach)
-fn convert_command_to_cetz(output: &mut String, cmd: &TikZCommand, indent_level: usize) {
+fn convert_command_to_cetz(
+    output: &mut String,
+    cmd: &TikZCommand,
+    indent_level: usize,
+    version: CetzVersion,
+) {
     let indent = "  ".repeat(indent_level);
 
     match cmd {
@@ -1826,10 +1976,10 @@ fn convert_command_to_cetz(output: &mut String, cmd: &TikZCommand, indent_level:
                 output.push_str(&format!("{}// Clip region (partial support)\n", indent));
             }
             // Call the draw command converter with fill flag from options
-            convert_path_command(output, options, segments, indent_level);
+            convert_path_command(output, options, segments, indent_level, version);
         }
         TikZCommand::Node(node) => {
-            convert_node_command_with_indent(output, node, indent_level);
+            convert_node_command_with_indent(output, node, indent_level, version);
         }
         TikZCommand::Coordinate { name, position } => {
             let _ = writeln!(
@@ -1837,7 +1987,7 @@ fn convert_command_to_cetz(output: &mut String, cmd: &TikZCommand, indent_level:
                 "{}// Coordinate: {} at {}",
                 indent,
                 name,
-                position.to_cetz()
+                position.to_cetz_for(version)
             );
         }
         TikZCommand::Foreach {
@@ -1849,7 +1999,7 @@ fn convert_command_to_cetz(output: &mut String, cmd: &TikZCommand, indent_level:
             let values_str = values.join(", ");
             let _ = writeln!(output, "{}for {} in ({}) {{", indent, variable, values_str);
             for body_cmd in body {
-                convert_command_to_cetz(output, body_cmd, indent_level + 1);
+                convert_command_to_cetz(output, body_cmd, indent_level + 1, version);
             }
             let _ = writeln!(output, "{}}}", indent);
         }
@@ -1862,9 +2012,10 @@ fn convert_path_command(
     options: &DrawOptions,
     segments: &[PathSegment],
     indent_level: usize,
+    version: CetzVersion,
 ) {
     // Use the existing convert_draw_command logic
-    convert_draw_command_impl(output, options, segments, indent_level);
+    convert_draw_command_impl(output, options, segments, indent_level, version);
 }
 
 /// Map TikZ node position to CeTZ anchor
@@ -1895,17 +2046,22 @@ fn map_tikz_position_to_cetz_anchor(tikz_pos: &str) -> &'static str {
 }
 
 /// Convert a node command with specific indent level
-fn convert_node_command_with_indent(output: &mut String, node: &TikZNode, indent_level: usize) {
+fn convert_node_command_with_indent(
+    output: &mut String,
+    node: &TikZNode,
+    indent_level: usize,
+    version: CetzVersion,
+) {
     let indent = "  ".repeat(indent_level);
 
     // Handle positioning library relative position
     let pos = if let Some(ref rel_pos) = node.options.relative_pos {
         // Convert relative positioning to CeTZ
-        convert_relative_position_to_cetz(rel_pos)
+        convert_relative_position_to_cetz(rel_pos, version)
     } else {
         node.position
             .as_ref()
-            .map(|c| c.to_cetz())
+            .map(|c| c.to_cetz_for(version))
             .unwrap_or_else(|| "(0, 0)".to_string())
     };
 
@@ -1938,7 +2094,7 @@ fn convert_node_command_with_indent(output: &mut String, node: &TikZNode, indent
 }
 
 /// Convert relative positioning to CeTZ coordinate expression
-fn convert_relative_position_to_cetz(rel_pos: &RelativePosition) -> String {
+fn convert_relative_position_to_cetz(rel_pos: &RelativePosition, version: CetzVersion) -> String {
     // Map TikZ direction to offset vector
     let (dx, dy) = match rel_pos.direction.as_str() {
         "above" => (0.0, 1.0),
@@ -1978,10 +2134,15 @@ fn convert_relative_position_to_cetz(rel_pos: &RelativePosition) -> String {
     let offset_x = dx * distance;
     let offset_y = dy * distance;
 
-    format!(
+    let call = format!(
         "calc.add(\"{}\", ({:.2}, {:.2}))",
         rel_pos.of_node, offset_x, offset_y
-    )
+    );
+    if version.has_calc_helpers() {
+        call
+    } else {
+        format!("\"{}\" /* {} */", rel_pos.of_node, call)
+    }
 }
 
 /// Convert a draw/fill command to CeTZ with proper state tracking (internal implementation)
@@ -1990,6 +2151,7 @@ fn convert_draw_command_impl(
     options: &DrawOptions,
     segments: &[PathSegment],
     indent_level: usize,
+    version: CetzVersion,
 ) {
     let indent = "  ".repeat(indent_level);
 
@@ -2014,7 +2176,7 @@ fn convert_draw_command_impl(
                 format!(", {}", style)
             };
 
-            let coords_str: Vec<_> = polyline.iter().map(|c| c.to_cetz()).collect();
+            let coords_str: Vec<_> = polyline.iter().map(|c| c.to_cetz_for(version)).collect();
 
             if closed {
                 let _ = writeln!(
@@ -2054,7 +2216,7 @@ fn convert_draw_command_impl(
             }
 
             PathSegment::Node { text, anchor } => {
-                let pos = current_pos.to_cetz();
+                let pos = current_pos.to_cetz_for(version);
                 // In Typst content blocks [...], $...$ is math mode - don't escape it
                 let escaped_text = text.replace('#', "\\#");
 
@@ -2092,7 +2254,7 @@ fn convert_draw_command_impl(
                     output,
                     "{}circle({}, radius: {}{})",
                     indent,
-                    center.to_cetz(),
+                    center.to_cetz_for(version),
                     radius,
                     style_str
                 );
@@ -2112,8 +2274,8 @@ fn convert_draw_command_impl(
                     output,
                     "{}rect({}, {}{})",
                     indent,
-                    corner1.to_cetz(),
-                    corner2.to_cetz(),
+                    corner1.to_cetz_for(version),
+                    corner2.to_cetz_for(version),
                     style_str
                 );
                 current_pos = corner2.clone();
@@ -2130,7 +2292,7 @@ fn convert_draw_command_impl(
                     output,
                     "{}arc({}, start: {}deg, stop: {}deg, radius: {})",
                     indent,
-                    current_pos.to_cetz(),
+                    current_pos.to_cetz_for(version),
                     start_angle,
                     end_angle,
                     radius
@@ -2148,7 +2310,7 @@ fn convert_draw_command_impl(
                     output,
                     "{}ellipse({}, {}, {})",
                     indent,
-                    center.to_cetz(),
+                    center.to_cetz_for(version),
                     x_radius,
                     y_radius
                 );
@@ -2167,8 +2329,8 @@ fn convert_draw_command_impl(
                     output,
                     "{}grid({}, {}{})",
                     indent,
-                    corner1.to_cetz(),
-                    corner2.to_cetz(),
+                    corner1.to_cetz_for(version),
+                    corner2.to_cetz_for(version),
                     step_str
                 );
             }
@@ -2187,9 +2349,9 @@ fn convert_draw_command_impl(
                     format!(", {}", style)
                 };
 
-                let coords: Vec<String> = std::iter::once(start.to_cetz())
-                    .chain(controls.iter().map(|c| c.to_cetz()))
-                    .chain(std::iter::once(end.to_cetz()))
+                let coords: Vec<String> = std::iter::once(start.to_cetz_for(version))
+                    .chain(controls.iter().map(|c| c.to_cetz_for(version)))
+                    .chain(std::iter::once(end.to_cetz_for(version)))
                     .collect();
 
                 let _ = writeln!(
@@ -2217,14 +2379,14 @@ fn convert_draw_command_impl(
                 };
 
                 let start = current_pos.clone();
-                let mut coords = vec![start.to_cetz()];
+                let mut coords = vec![start.to_cetz_for(version)];
                 if let Some(c1) = control1 {
-                    coords.push(c1.to_cetz());
+                    coords.push(c1.to_cetz_for(version));
                 }
                 if let Some(c2) = control2 {
-                    coords.push(c2.to_cetz());
+                    coords.push(c2.to_cetz_for(version));
                 }
-                coords.push(end.to_cetz());
+                coords.push(end.to_cetz_for(version));
 
                 let _ = writeln!(
                     output,
@@ -3213,6 +3375,58 @@ mod tests {
         convert_cetz_command_to_tikz(&cmd)
     }
 
+    #[test]
+    fn test_cetz_version_matrix() {
+        let input = "\\begin{tikzpicture}\n\\coordinate (A) at (1,2);\n\\draw (0,0) -- ($(0,0)!0.5!(2,3)$);\n\\draw ($(A)+(1,0)$) -- ($(1,1)+(0.5,-1)$);\n\\node[right=1cm of A] {x};\n\\end{tikzpicture}";
+        let body_with_helpers = r#"#canvas({
+  import draw: *
+
+  // Coordinate: A at (1, 2)
+  line((0, 0), calc.lerp((0, 0), (2, 3), 0.5))
+  line(calc.add("A", (1, 0)), calc.add((1, 1), (0.5, -1)))
+  content(calc.add("A", (1.00, 0.00)), [x])
+})
+"#;
+        let expected = [
+            (
+                CetzVersion::V0_2,
+                r#"#import "@preview/cetz:0.2.2": canvas, draw
+
+#canvas({
+  import draw: *
+
+  // Coordinate: A at (1, 2)
+  line((0, 0), (1, 1.5))
+  line("A" /* calc.add("A", (1, 0)) */, (1.5, 0))
+  content("A" /* calc.add("A", (1.00, 0.00)) */, [x])
+})
+"#
+                .to_string(),
+            ),
+            (
+                CetzVersion::V0_3,
+                format!(
+                    "#import \"@preview/cetz:0.3.4\": canvas, draw\n\n{}",
+                    body_with_helpers
+                ),
+            ),
+            (
+                CetzVersion::V0_4,
+                format!(
+                    "#import \"@preview/cetz:0.4.2\": canvas, draw\n\n{}",
+                    body_with_helpers
+                ),
+            ),
+        ];
+        for (version, expected) in expected {
+            assert_eq!(convert_tikz_to_cetz_for(input, version), expected);
+        }
+        assert_eq!(
+            convert_tikz_to_cetz(input),
+            convert_tikz_to_cetz_for(input, CetzVersion::V0_3)
+        );
+    }
+
     #[test]
     fn test_cetz_line_to_tikz() {
         let cetz = "line((0, 0), (1, 1))";
@@ -13,6 +13,20 @@
 //! - **Reference System**: Complete citation and cross-reference support
 //! - **Macro Expansion**: Basic LaTeX macro definition and expansion
 //!
+//! ## Determinism
+//!
+//! Every conversion function is deterministic: the same input and options
+//! give byte-identical output on every run, thread and platform. Tables and
+//! collected definitions are never emitted in hash order, so converted output
+//! can be hashed and cached.
+//!
+//! ## Thread safety
+//!
+//! [`LatexConverter`], [`MiniEval`], the option types and the result types
+//! are `Send + Sync`, and the shared tables are initialized once behind a
+//! lock, so independent documents can be converted on any number of threads.
+//! [`convert_batch`] does so on a rayon pool when the `rayon` feature is on.
+//!
 //! ## Usage Examples
 //!
 //! ### Math Formula Conversion
@@ -74,9 +88,12 @@ pub mod wasm;
 pub use core::typst2latex;
 pub use core::typst2latex::{
     typst_document_to_latex, typst_to_latex, typst_to_latex_with_diagnostics,
-    typst_to_latex_with_eval, typst_to_latex_with_options, ConversionResult as T2LConversionResult,
+    typst_to_latex_with_eval, typst_to_latex_with_file_resolver, typst_to_latex_with_options,
+    ConversionResult as T2LConversionResult,
+};
+pub use core::typst2latex::{
+    CodeBackend, DisplayMathDelim, DocumentWrapperMode, InlineMathDelim, PlaceBackend, T2LOptions,
 };
-pub use core::typst2latex::{DocumentWrapperMode, T2LOptions};
 
 pub use core::latex2typst::{
     convert_document_with_ast, convert_document_with_ast_options, convert_math_with_ast,
@@ -84,7 +101,9 @@ pub use core::latex2typst::{
     latex_math_to_typst_with_diagnostics, latex_math_to_typst_with_eval,
     latex_to_typst_with_diagnostics, latex_to_typst_with_diagnostics_options,
     latex_to_typst_with_eval, ConversionMode, ConversionResult as L2TConversionResult,
-    ConversionState, EnvironmentContext, L2TOptions, LatexConverter, PreambleMode, WarningKind,
+    ConversionState, DataTableMode, DoubleRulePolicy, EnvironmentContext, FragmentContext,
+    L2TOptions, LatexConverter, MetadataCommand, PreambleMode, SidewaysStyle, TodoStyle,
+    UnknownEnvironmentPolicy, WarningKind,
 };
 
 // Re-export data modules
@@ -108,11 +127,14 @@ pub use data::symbols;
 
 // Re-export utilities
 pub use utils::diagnostics;
+pub use utils::diff::{diff_outputs, BlockKind, DiffHunk, OutputFormat};
 pub use utils::error::{
     CliDiagnostic, ConversionError, ConversionOutput, ConversionResult, ConversionWarning,
     DiagnosticSeverity,
 };
 pub use utils::files;
+pub use utils::line_endings::LineEnding;
+pub use utils::progress::{Phase, Progress, ProgressCallback};
 
 // Re-export main types and functions from eval (MiniEval) - now located in typst2latex
 pub use core::typst2latex::engine::{
@@ -152,6 +174,86 @@ pub fn latex_document_to_typst_with_options(input: &str, options: &L2TOptions) -
     convert_document_with_ast_options(input, options.clone())
 }
 
+/// Run a conversion, turning a panic into [`ConversionError::InternalError`]
+fn contain_panics(convert: impl FnOnce() -> String) -> Result<String, ConversionError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(convert)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        ConversionError::InternalError { message }
+    })
+}
+
+/// [`latex_to_typst`] that never panics.
+///
+/// The converters are meant not to panic on any input; should one slip
+/// through, it is caught and returned as [`ConversionError::InternalError`].
+/// Panics can only be caught when built with `panic = "unwind"`, the default.
+/// A stack overflow cannot be caught at all; input nested deeper than
+/// [`utils::limits::MAX_NESTING_DEPTH`] is therefore kept unconverted, with a
+/// warning, rather than converted.
+#[doc(alias = "catch_unwind")]
+pub fn try_latex_to_typst(input: &str) -> Result<String, ConversionError> {
+    contain_panics(|| latex_to_typst(input))
+}
+
+/// [`latex_document_to_typst`] that never panics; see [`try_latex_to_typst`]
+#[doc(alias = "catch_unwind")]
+pub fn try_latex_document_to_typst(input: &str) -> Result<String, ConversionError> {
+    contain_panics(|| latex_document_to_typst(input))
+}
+
+/// [`typst_to_latex`] that never panics; see [`try_latex_to_typst`]
+#[doc(alias = "catch_unwind")]
+pub fn try_typst_to_latex(input: &str) -> Result<String, ConversionError> {
+    contain_panics(|| typst_to_latex(input))
+}
+
+/// [`typst_document_to_latex`] that never panics; see [`try_latex_to_typst`]
+#[doc(alias = "catch_unwind")]
+pub fn try_typst_document_to_latex(input: &str) -> Result<String, ConversionError> {
+    contain_panics(|| typst_document_to_latex(input))
+}
+
+/// Convert independent in-memory sources, returning one result per input in
+/// input order.
+///
+/// `direction` picks the conversion; [`BatchDirection::Auto`](batch::BatchDirection::Auto)
+/// decides per source with [`detect_format`]. Of `options`, only
+/// `full_document`, `l2t_options` and `t2l_options` are read. Each source is
+/// converted as by the `try_*` functions, so one panicking input does not
+/// sink the rest. With the `rayon` feature the sources are spread over the
+/// global rayon pool.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn convert_batch(
+    inputs: &[&str],
+    direction: batch::BatchDirection,
+    options: &batch::BatchOptions,
+) -> Vec<ConversionResult<String>> {
+    use batch::BatchDirection;
+
+    let convert = |input: &&str| {
+        let direction = match direction {
+            BatchDirection::Auto if detect_format(input) == "typst" => BatchDirection::TypstToLatex,
+            BatchDirection::Auto => BatchDirection::LatexToTypst,
+            resolved => resolved,
+        };
+        contain_panics(|| batch::convert_source(input, direction, options))
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        inputs.par_iter().map(convert).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        inputs.iter().map(convert).collect()
+    }
+}
+
 /// Convert with automatic direction detection
 ///
 /// Detects whether the input is LaTeX or Typst and converts accordingly.
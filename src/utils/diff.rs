@@ -0,0 +1,804 @@
+//! Block-level diff of converted documents
+//!
+//! Comparing two tylax outputs line by line drowns real changes in
+//! reflowed paragraphs, indentation and comments. This module parses both
+//! outputs with the matching parser (typst-syntax for Typst, mitex for
+//! LaTeX), splits them into blocks (paragraphs, headings, equations,
+//! tables, ...) and compares the blocks by their significant tokens, so
+//! whitespace-only and comment-only edits never produce a hunk.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tylax::{diff_outputs, BlockKind, OutputFormat};
+//!
+//! let old = "= Intro\n\nHello   world.\n\n$ a + b $\n";
+//! let new = "= Intro\n// reflowed\nHello\nworld.\n\n$ a - b $\n";
+//!
+//! let hunks = diff_outputs(old, new, OutputFormat::Typst);
+//! assert_eq!(hunks.len(), 1);
+//! assert_eq!(hunks[0].kind, BlockKind::Equation);
+//! assert!(hunks[0].similarity < 1.0);
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use mitex_parser::syntax::{CmdItem, EnvItem, FormulaItem, SyntaxElement, SyntaxKind};
+use rowan::ast::AstNode;
+use typst_syntax::{ast, LinkedNode, SyntaxKind as TypstKind};
+
+use crate::core::latex2typst::context::MERGED_SPEC;
+
+/// Above this many token comparisons, similarity falls back to a bag-of-tokens
+/// overlap instead of a full LCS.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// Which parser to use for the compared outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// LaTeX output (T2L direction)
+    Latex,
+    /// Typst output (L2T direction)
+    Typst,
+}
+
+/// Structural kind of a compared block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    /// Running text, including inline math and inline commands
+    Paragraph,
+    /// Section headings
+    Heading,
+    /// Display math
+    Equation,
+    /// Itemize/enumerate/description lists and Typst list items
+    List,
+    /// Tables and grids
+    Table,
+    /// Figures and standalone images
+    Figure,
+    /// Verbatim blocks and raw code blocks
+    Code,
+    /// Preamble material and Typst set/show/let/import rules
+    Setup,
+}
+
+impl BlockKind {
+    /// Lower-case name used in CLI output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockKind::Paragraph => "paragraph",
+            BlockKind::Heading => "heading",
+            BlockKind::Equation => "equation",
+            BlockKind::List => "list",
+            BlockKind::Table => "table",
+            BlockKind::Figure => "figure",
+            BlockKind::Code => "code",
+            BlockKind::Setup => "setup",
+        }
+    }
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One changed block.
+///
+/// A block only present in the new output has an empty `old` span placed
+/// where it would have been inserted, and vice versa for removed blocks.
+/// Both cases have a similarity of `0.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    /// Kind of the block (taken from the new side when both exist)
+    pub kind: BlockKind,
+    /// Byte span of the block in the old output
+    pub old: Range<usize>,
+    /// Byte span of the block in the new output
+    pub new: Range<usize>,
+    /// Token similarity of the two sides, from `0.0` to `1.0`
+    pub similarity: f64,
+}
+
+impl DiffHunk {
+    /// The block exists only in the new output.
+    pub fn is_insertion(&self) -> bool {
+        self.old.is_empty() && !self.new.is_empty()
+    }
+
+    /// The block exists only in the old output.
+    pub fn is_deletion(&self) -> bool {
+        self.new.is_empty() && !self.old.is_empty()
+    }
+}
+
+/// Compare two converted outputs block by block.
+///
+/// Blocks are matched on their significant tokens; whitespace, line
+/// wrapping and comments are ignored. Unmatched blocks between two matches
+/// are paired in order and reported with their similarity, leftovers are
+/// reported as insertions or deletions.
+pub fn diff_outputs(old: &str, new: &str, format: OutputFormat) -> Vec<DiffHunk> {
+    let old_blocks = split_blocks(old, format);
+    let new_blocks = split_blocks(new, format);
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matching_blocks(&old_blocks, &new_blocks)
+        .into_iter()
+        .chain(std::iter::once((old_blocks.len(), new_blocks.len())))
+    {
+        pair_gap(
+            &old_blocks[i..next_i],
+            &new_blocks[j..next_j],
+            (anchor(&old_blocks, i), anchor(&new_blocks, j)),
+            &mut hunks,
+        );
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    hunks
+}
+
+/// A parsed block and its significant tokens.
+#[derive(Debug)]
+struct Block<'a> {
+    kind: BlockKind,
+    span: Range<usize>,
+    tokens: Vec<&'a str>,
+}
+
+fn split_blocks(input: &str, format: OutputFormat) -> Vec<Block<'_>> {
+    match format {
+        OutputFormat::Latex => latex_blocks(input),
+        OutputFormat::Typst => typst_blocks(input),
+    }
+}
+
+/// Offset at which a block missing from this side would sit.
+fn anchor(blocks: &[Block], index: usize) -> usize {
+    match index {
+        0 => blocks.first().map_or(0, |block| block.span.start),
+        _ => blocks[index - 1].span.end,
+    }
+}
+
+fn pair_gap(
+    old: &[Block],
+    new: &[Block],
+    (old_at, new_at): (usize, usize),
+    hunks: &mut Vec<DiffHunk>,
+) {
+    for (a, b) in old.iter().zip(new) {
+        hunks.push(DiffHunk {
+            kind: b.kind,
+            old: a.span.clone(),
+            new: b.span.clone(),
+            similarity: similarity(&a.tokens, &b.tokens),
+        });
+    }
+    let paired = old.len().min(new.len());
+    let old_end = old
+        .get(paired.wrapping_sub(1))
+        .map_or(old_at, |b| b.span.end);
+    let new_end = new
+        .get(paired.wrapping_sub(1))
+        .map_or(new_at, |b| b.span.end);
+    for a in &old[paired..] {
+        hunks.push(DiffHunk {
+            kind: a.kind,
+            old: a.span.clone(),
+            new: new_end..new_end,
+            similarity: 0.0,
+        });
+    }
+    for b in &new[paired..] {
+        hunks.push(DiffHunk {
+            kind: b.kind,
+            old: old_end..old_end,
+            new: b.span.clone(),
+            similarity: 0.0,
+        });
+    }
+}
+
+/// Index pairs of identical blocks, in order (longest common subsequence).
+fn matching_blocks(old: &[Block], new: &[Block]) -> Vec<(usize, usize)> {
+    let same = |a: &Block, b: &Block| a.kind == b.kind && a.tokens == b.tokens;
+
+    // Identical head and tail blocks are the common case; keep them out of
+    // the quadratic table.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|k| (k, k)).collect();
+    pairs.extend(
+        lcs_pairs(old_mid, new_mid, same)
+            .into_iter()
+            .map(|(a, b)| (a + prefix, b + prefix)),
+    );
+    pairs.extend((0..suffix).map(|k| (old.len() - suffix + k, new.len() - suffix + k)));
+    pairs
+}
+
+fn lcs_pairs<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> Vec<(usize, usize)> {
+    let width = b.len() + 1;
+    let mut table = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i * width + j] = if eq(&a[i], &b[j]) {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if eq(&a[i], &b[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Dice coefficient over the token LCS.
+fn similarity(a: &[&str], b: &[&str]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let common = if a.len() * b.len() <= MAX_LCS_CELLS {
+        lcs_pairs(a, b, |x, y| x == y).len()
+    } else {
+        let mut counts = std::collections::HashMap::new();
+        for token in a {
+            *counts.entry(*token).or_insert(0usize) += 1;
+        }
+        b.iter()
+            .filter(|token| match counts.get_mut(*token) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .count()
+    };
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+/// Split token text into word runs and single punctuation characters, so
+/// `x+y` and `x + y` compare equal.
+fn push_tokens<'a>(text: &'a str, tokens: &mut Vec<&'a str>) {
+    let mut word_start = None;
+    for (idx, ch) in text.char_indices() {
+        if ch.is_alphanumeric() {
+            word_start.get_or_insert(idx);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            tokens.push(&text[start..idx]);
+        }
+        if !ch.is_whitespace() {
+            tokens.push(&text[idx..idx + ch.len_utf8()]);
+        }
+    }
+    if let Some(start) = word_start {
+        tokens.push(&text[start..]);
+    }
+}
+
+/// Collects inline material into paragraphs and standalone blocks.
+struct BlockBuilder<'a> {
+    source: &'a str,
+    blocks: Vec<Block<'a>>,
+    paragraph: Option<Block<'a>>,
+    paragraph_kind: BlockKind,
+}
+
+impl<'a> BlockBuilder<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            blocks: Vec::new(),
+            paragraph: None,
+            paragraph_kind: BlockKind::Paragraph,
+        }
+    }
+
+    fn inline(&mut self, span: Range<usize>, tokens: Vec<&'a str>) {
+        if tokens.is_empty() {
+            return;
+        }
+        let kind = self.paragraph_kind;
+        let paragraph = self.paragraph.get_or_insert_with(|| Block {
+            kind,
+            span: span.clone(),
+            tokens: Vec::new(),
+        });
+        paragraph.span.end = span.end;
+        paragraph.tokens.extend(tokens);
+    }
+
+    fn parbreak(&mut self) {
+        if let Some(paragraph) = self.paragraph.take() {
+            self.blocks.push(paragraph);
+        }
+    }
+
+    fn block(&mut self, kind: BlockKind, span: Range<usize>, tokens: Vec<&'a str>) {
+        self.parbreak();
+        self.blocks.push(Block { kind, span, tokens });
+    }
+
+    /// Extend the previous block (consecutive Typst list items).
+    fn extend_last(&mut self, span: Range<usize>, tokens: Vec<&'a str>) {
+        if let Some(last) = self.blocks.last_mut() {
+            last.span.end = span.end;
+            last.tokens.extend(tokens);
+        }
+    }
+
+    fn finish(mut self) -> Vec<Block<'a>> {
+        self.parbreak();
+        self.blocks
+    }
+
+    fn slice(&self, span: &Range<usize>) -> &'a str {
+        self.source.get(span.clone()).unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// LaTeX
+// ============================================================================
+
+fn latex_blocks(input: &str) -> Vec<Block<'_>> {
+    let tree = mitex_parser::parse(input, MERGED_SPEC.clone());
+    let has_document = tree
+        .children()
+        .any(|node| latex_env_name(&node).as_deref() == Some("document"));
+
+    let mut builder = BlockBuilder::new(input);
+    if has_document {
+        builder.paragraph_kind = BlockKind::Setup;
+    }
+    let mut walker = LatexWalker {
+        map: SourceMap::new(&tree, input),
+        newlines: 0,
+        after_comment: false,
+    };
+    walker.walk(tree.children_with_tokens(), &mut builder);
+    builder.finish()
+}
+
+/// Where the tokens of a mitex tree are in its source. The tree leaves out
+/// the `\begin{`, `\end{` and closing braces around environment names, so
+/// its own offsets drift from the source after the first environment.
+struct SourceMap<'a> {
+    source: &'a str,
+    /// Tree offset and source offset of each token, in order
+    starts: Vec<(usize, usize)>,
+}
+
+impl<'a> SourceMap<'a> {
+    fn new(tree: &mitex_parser::syntax::SyntaxNode, source: &'a str) -> Self {
+        let mut starts = Vec::new();
+        let mut cursor = 0;
+        for token in tree
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+        {
+            let text = token.text();
+            let start = source
+                .get(cursor..)
+                .and_then(|rest| rest.find(text))
+                .map_or(cursor, |pos| cursor + pos);
+            starts.push((usize::from(token.text_range().start()), start));
+            cursor = start + text.len();
+        }
+        Self { source, starts }
+    }
+
+    /// Source range of a token
+    fn token(&self, token: &mitex_parser::syntax::SyntaxToken) -> Range<usize> {
+        let offset = usize::from(token.text_range().start());
+        let start = match self.starts.binary_search_by_key(&offset, |&(tree, _)| tree) {
+            Ok(index) => self.starts[index].1,
+            Err(index) => index
+                .checked_sub(1)
+                .map_or(offset, |index| self.starts[index].1),
+        };
+        start..start + token.text().len()
+    }
+
+    /// Source range of an element: a whole environment from its `\begin`
+    /// to the closing brace of its `\end{..}`
+    fn range(&self, element: &SyntaxElement) -> Range<usize> {
+        let (first, last) = match element {
+            SyntaxElement::Token(token) => (Some(token.clone()), Some(token.clone())),
+            SyntaxElement::Node(node) => (node.first_token(), node.last_token()),
+        };
+        let (Some(first), Some(last)) = (first, last) else {
+            return 0..0;
+        };
+        let in_item = |token: &mitex_parser::syntax::SyntaxToken, kind| {
+            token.parent_ancestors().any(|node| node.kind() == kind)
+        };
+        let mut start = self.token(&first).start;
+        let mut end = self.token(&last).end.max(start);
+        if in_item(&first, SyntaxKind::ItemBegin) {
+            if let Some(pos) = self.source.get(..start).and_then(|s| s.rfind("\\begin")) {
+                start = pos;
+            }
+        }
+        if in_item(&last, SyntaxKind::ItemEnd) {
+            if let Some(pos) = self.source.get(end..).and_then(|s| s.find('}')) {
+                end += pos + 1;
+            }
+        }
+        start..end
+    }
+}
+
+struct LatexWalker<'a> {
+    map: SourceMap<'a>,
+    /// Line breaks seen since the last significant element
+    newlines: usize,
+    /// A `%` comment swallows the line break that ends it
+    after_comment: bool,
+}
+
+impl<'a> LatexWalker<'a> {
+    fn walk(
+        &mut self,
+        elements: impl Iterator<Item = SyntaxElement>,
+        builder: &mut BlockBuilder<'a>,
+    ) {
+        for element in elements {
+            let span = self.map.range(&element);
+            match element.kind() {
+                SyntaxKind::TokenWhiteSpace => {}
+                SyntaxKind::TokenLineBreak => {
+                    let breaks = builder.slice(&span).matches('\n').count();
+                    let breaks = if self.after_comment {
+                        breaks.saturating_sub(1)
+                    } else {
+                        breaks
+                    };
+                    self.after_comment = false;
+                    self.newlines += breaks;
+                    if self.newlines >= 2 {
+                        builder.parbreak();
+                    }
+                }
+                SyntaxKind::TokenComment | SyntaxKind::ItemBlockComment => {
+                    self.after_comment = true;
+                    self.newlines = 0;
+                }
+                SyntaxKind::ItemText => {
+                    if let Some(node) = element.into_node() {
+                        self.walk(node.children_with_tokens(), builder);
+                    }
+                }
+                _ => {
+                    self.newlines = 0;
+                    self.after_comment = false;
+                    self.element(element, span, builder);
+                }
+            }
+        }
+    }
+
+    fn element(
+        &mut self,
+        element: SyntaxElement,
+        span: Range<usize>,
+        builder: &mut BlockBuilder<'a>,
+    ) {
+        let Some(node) = element.as_node().cloned() else {
+            builder.inline(span, latex_tokens(&element, &self.map));
+            return;
+        };
+
+        if let Some(name) = latex_env_name(&node) {
+            if name == "document" {
+                builder.parbreak();
+                builder.paragraph_kind = BlockKind::Paragraph;
+                let body = node.children_with_tokens().filter(|child| {
+                    !matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd)
+                });
+                self.walk(body, builder);
+                builder.parbreak();
+                builder.paragraph_kind = BlockKind::Setup;
+            } else {
+                let tokens = latex_tokens(&element, &self.map);
+                builder.block(latex_env_kind(&name), span, tokens);
+            }
+            return;
+        }
+
+        let tokens = latex_tokens(&element, &self.map);
+        if FormulaItem::cast(node.clone()).is_some_and(|formula| formula.is_display()) {
+            builder.block(BlockKind::Equation, span, tokens);
+            return;
+        }
+
+        let is_heading = CmdItem::cast(node)
+            .and_then(|cmd| cmd.name_tok())
+            .is_some_and(|name| LATEX_HEADINGS.contains(&name.text().trim_start_matches('\\')));
+        if is_heading {
+            builder.block(BlockKind::Heading, span, tokens);
+        } else {
+            builder.inline(span, tokens);
+        }
+    }
+}
+
+const LATEX_HEADINGS: &[&str] = &[
+    "part",
+    "chapter",
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+];
+
+fn latex_env_name(node: &mitex_parser::syntax::SyntaxNode) -> Option<String> {
+    EnvItem::cast(node.clone())
+        .and_then(|env| env.name_tok())
+        .map(|name| name.text().to_string())
+}
+
+fn latex_env_kind(name: &str) -> BlockKind {
+    match name.trim_end_matches('*') {
+        "equation" | "align" | "gather" | "multline" | "flalign" | "alignat" | "eqnarray"
+        | "displaymath" | "dmath" | "subequations" => BlockKind::Equation,
+        "table" | "tabular" | "tabularx" | "tabulary" | "longtable" | "tabu" | "sidewaystable" => {
+            BlockKind::Table
+        }
+        "figure" | "wrapfigure" | "subfigure" | "sidewaysfigure" => BlockKind::Figure,
+        "itemize" | "enumerate" | "description" => BlockKind::List,
+        "verbatim" | "Verbatim" | "lstlisting" | "minted" | "comment" => BlockKind::Code,
+        _ => BlockKind::Paragraph,
+    }
+}
+
+fn latex_tokens<'a>(element: &SyntaxElement, map: &SourceMap<'a>) -> Vec<&'a str> {
+    let mut tokens = Vec::new();
+    let mut visit = |token: mitex_parser::syntax::SyntaxToken| {
+        let skip = matches!(
+            token.kind(),
+            SyntaxKind::TokenWhiteSpace | SyntaxKind::TokenLineBreak | SyntaxKind::TokenComment
+        ) || token
+            .parent_ancestors()
+            .any(|node| node.kind() == SyntaxKind::ItemBlockComment);
+        if !skip {
+            let text = map.source.get(map.token(&token)).unwrap_or_default();
+            push_tokens(text, &mut tokens);
+        }
+    };
+    match element {
+        SyntaxElement::Token(token) => visit(token.clone()),
+        SyntaxElement::Node(node) => node
+            .descendants_with_tokens()
+            .filter_map(|child| child.into_token())
+            .for_each(&mut visit),
+    }
+    tokens
+}
+
+// ============================================================================
+// Typst
+// ============================================================================
+
+fn typst_blocks(input: &str) -> Vec<Block<'_>> {
+    let root = typst_syntax::parse(input);
+    let root = LinkedNode::new(&root);
+    let mut builder = BlockBuilder::new(input);
+
+    let children: Vec<LinkedNode> = root.children().collect();
+    let mut list_open = false;
+    let mut idx = 0;
+    while idx < children.len() {
+        let node = &children[idx];
+        idx += 1;
+        match node.kind() {
+            TypstKind::Parbreak => {
+                builder.parbreak();
+                list_open = false;
+                continue;
+            }
+            TypstKind::Space => continue,
+            kind if kind.is_trivia() => continue,
+            _ => {}
+        }
+
+        // `#expr` in markup: the hash and the expression form one element.
+        let (span, expr) = if node.kind() == TypstKind::Hash && idx < children.len() {
+            idx += 1;
+            (
+                node.offset()..children[idx - 1].range().end,
+                &children[idx - 1],
+            )
+        } else {
+            (node.range(), node)
+        };
+        let tokens = typst_tokens(expr, input);
+
+        match typst_block_kind(expr) {
+            Some(BlockKind::List) if list_open => builder.extend_last(span, tokens),
+            Some(kind) => builder.block(kind, span, tokens),
+            None => builder.inline(span, tokens),
+        }
+        list_open = matches!(
+            expr.kind(),
+            TypstKind::ListItem | TypstKind::EnumItem | TypstKind::TermItem
+        );
+    }
+    builder.finish()
+}
+
+fn typst_block_kind(node: &LinkedNode) -> Option<BlockKind> {
+    match node.kind() {
+        TypstKind::Heading => Some(BlockKind::Heading),
+        TypstKind::ListItem | TypstKind::EnumItem | TypstKind::TermItem => Some(BlockKind::List),
+        TypstKind::Equation => node
+            .cast::<ast::Equation>()
+            .filter(|equation| equation.block())
+            .map(|_| BlockKind::Equation),
+        TypstKind::Raw => node
+            .cast::<ast::Raw>()
+            .filter(|raw| raw.block())
+            .map(|_| BlockKind::Code),
+        TypstKind::SetRule
+        | TypstKind::ShowRule
+        | TypstKind::LetBinding
+        | TypstKind::ModuleImport
+        | TypstKind::ModuleInclude => Some(BlockKind::Setup),
+        TypstKind::FuncCall => {
+            let call = node.cast::<ast::FuncCall>()?;
+            let ast::Expr::Ident(name) = call.callee() else {
+                return None;
+            };
+            match name.as_str() {
+                "table" | "grid" => Some(BlockKind::Table),
+                "figure" | "image" => Some(BlockKind::Figure),
+                "heading" => Some(BlockKind::Heading),
+                "list" | "enum" | "terms" => Some(BlockKind::List),
+                "align" | "block" | "quote" | "pagebreak" | "colbreak" | "v" | "outline"
+                | "bibliography" | "place" | "columns" | "title" => Some(BlockKind::Paragraph),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn typst_tokens<'a>(node: &LinkedNode, source: &'a str) -> Vec<&'a str> {
+    fn visit<'a>(node: &LinkedNode, source: &'a str, tokens: &mut Vec<&'a str>) {
+        if node.kind().is_trivia() {
+            return;
+        }
+        if node.get().children().len() == 0 {
+            push_tokens(&source[node.range()], tokens);
+            return;
+        }
+        for child in node.children() {
+            visit(&child, source, tokens);
+        }
+    }
+
+    let mut tokens = Vec::new();
+    visit(node, source, &mut tokens);
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_outputs_have_no_hunks() {
+        let doc = "= Title\n\nSome text.\n\n$ x $\n";
+        assert!(diff_outputs(doc, doc, OutputFormat::Typst).is_empty());
+    }
+
+    #[test]
+    fn test_typst_whitespace_and_comments_are_ignored() {
+        let old = "Some text\nacross lines.\n\n$ x+y $\n";
+        let new = "// generated\nSome   text across\n  lines.\n\n\n$ x + y $ // sum\n";
+        assert!(diff_outputs(old, new, OutputFormat::Typst).is_empty());
+    }
+
+    #[test]
+    fn test_latex_whitespace_and_comments_are_ignored() {
+        let old = "\\section{Intro}\nHello world.\n\n\\[ a+b \\]\n";
+        let new = "\\section{Intro} % first\nHello\n  world.\n%\n\\[\n  a + b\n\\]\n";
+        assert!(diff_outputs(old, new, OutputFormat::Latex).is_empty());
+    }
+
+    #[test]
+    fn test_changed_block_reports_kind_spans_and_similarity() {
+        let old = "Intro.\n\n#table(columns: 2)[a][b]\n";
+        let new = "Intro.\n\n#table(columns: 2)[a][c]\n";
+        let hunks = diff_outputs(old, new, OutputFormat::Typst);
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!(hunk.kind, BlockKind::Table);
+        assert_eq!(&old[hunk.old.clone()], "#table(columns: 2)[a][b]");
+        assert_eq!(&new[hunk.new.clone()], "#table(columns: 2)[a][c]");
+        assert!(hunk.similarity > 0.8 && hunk.similarity < 1.0);
+    }
+
+    #[test]
+    fn test_inserted_and_removed_blocks() {
+        let old = "A.\n\nB.\n\nC.\n";
+        let new = "A.\n\nC.\n\nD.\n";
+        let hunks = diff_outputs(old, new, OutputFormat::Typst);
+        assert_eq!(hunks.len(), 2);
+        assert!(hunks[0].is_deletion());
+        assert_eq!(&old[hunks[0].old.clone()], "B.");
+        assert!(hunks[1].is_insertion());
+        assert_eq!(&new[hunks[1].new.clone()], "D.");
+        assert_eq!(hunks[1].old, old.len() - 1..old.len() - 1);
+    }
+
+    #[test]
+    fn test_latex_blocks_inside_document() {
+        let input = "\\documentclass{article}\n\\begin{document}\n\\section{A}\nText $x$.\n\\begin{equation}\nx\n\\end{equation}\n\\begin{itemize}\\item a\\end{itemize}\n\\end{document}\n";
+        let kinds: Vec<_> = latex_blocks(input).iter().map(|b| b.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                BlockKind::Setup,
+                BlockKind::Heading,
+                BlockKind::Paragraph,
+                BlockKind::Equation,
+                BlockKind::List,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_latex_spans_follow_environments_and_non_ascii_text() {
+        let old = "Héllo wörld.\n\n\\begin{itemize}\n\\item é\n\\end{itemize}\n\nAfter ü.\n";
+        let new = "Héllo wörld.\n\n\\begin{itemize}\n\\item è\n\\end{itemize}\n\nAfter ü!\n";
+        let hunks = diff_outputs(old, new, OutputFormat::Latex);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].kind, BlockKind::List);
+        assert_eq!(
+            &old[hunks[0].old.clone()],
+            "\\begin{itemize}\n\\item é\n\\end{itemize}"
+        );
+        assert_eq!(&new[hunks[1].new.clone()], "After ü!");
+    }
+
+    #[test]
+    fn test_typst_list_items_form_one_block() {
+        let input = "#set page(a4)\n- a\n- b\n\n+ c\n";
+        let kinds: Vec<_> = typst_blocks(input).iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, [BlockKind::Setup, BlockKind::List, BlockKind::List]);
+    }
+}
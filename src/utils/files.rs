@@ -244,6 +244,16 @@ impl FileResolver for NoopFileResolver {
     }
 }
 
+/// A file a converted document needs next to it, produced by the
+/// conversion rather than copied from the source tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompanionFile {
+    /// Path the output refers to, relative to the output
+    pub path: String,
+    /// Contents to write there
+    pub contents: String,
+}
+
 /// Represents a parsed include/input command
 #[derive(Debug, Clone)]
 pub enum IncludeCommand {
@@ -276,41 +286,26 @@ impl IncludeCommand {
 pub fn find_latex_includes(content: &str) -> Vec<(usize, usize, IncludeCommand)> {
     let mut includes = Vec::new();
 
-    // Helper to find includes for a specific command
-    fn find_cmd_includes(
-        content: &str,
-        cmd: &str,
-        includes: &mut Vec<(usize, usize, IncludeCommand)>,
-        cmd_type: &str,
-    ) {
-        let mut search_start = 0;
-        while let Some(pos) = content[search_start..].find(cmd) {
-            let abs_pos = search_start + pos;
-            let after_cmd = &content[abs_pos + cmd.len()..];
-
-            if let Some(end) = after_cmd.find('}') {
-                let path = after_cmd[..end].to_string();
-                let full_end = abs_pos + cmd.len() + end + 1;
-                let inc_cmd = match cmd_type {
-                    "input" => IncludeCommand::Input(path),
-                    "include" => IncludeCommand::Include(path),
-                    "subfile" => IncludeCommand::Subfile(path),
-                    _ => IncludeCommand::Input(path),
-                };
-                includes.push((abs_pos, full_end, inc_cmd));
-            }
+    // Single forward pass over the backslashes, so results come out sorted
+    for (pos, _) in content.match_indices('\\') {
+        let after = &content[pos + 1..];
+        let (name, make): (&str, fn(String) -> IncludeCommand) = if after.starts_with("input{") {
+            ("input{", IncludeCommand::Input)
+        } else if after.starts_with("include{") {
+            ("include{", IncludeCommand::Include)
+        } else if after.starts_with("subfile{") {
+            ("subfile{", IncludeCommand::Subfile)
+        } else {
+            continue;
+        };
 
-            search_start = abs_pos + cmd.len();
+        let arg = &after[name.len()..];
+        if let Some(end) = arg.find('}') {
+            let full_end = pos + 1 + name.len() + end + 1;
+            includes.push((pos, full_end, make(arg[..end].to_string())));
         }
     }
 
-    find_cmd_includes(content, "\\input{", &mut includes, "input");
-    find_cmd_includes(content, "\\include{", &mut includes, "include");
-    find_cmd_includes(content, "\\subfile{", &mut includes, "subfile");
-
-    // Sort by position
-    includes.sort_by_key(|(pos, _, _)| *pos);
-
     includes
 }
 
@@ -0,0 +1,69 @@
+//! Input limits
+//!
+//! The converters recurse once per level of the syntax tree, so input nested
+//! hundreds of levels deep would overflow the stack, which no panic handler
+//! can catch. Trees deeper than [`MAX_NESTING_DEPTH`] are kept unconverted
+//! instead. The depth is measured without recursing.
+
+use mitex_parser::syntax::{SyntaxKind, SyntaxNode as LatexNode};
+use rowan::WalkEvent;
+use typst_syntax::SyntaxNode as TypstNode;
+
+/// Deepest syntax tree that is converted. Real documents stay far below it;
+/// the limit leaves room for the 2 MiB stack of a spawned thread in a debug
+/// build.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+/// Stack a LaTeX command takes while its arguments convert, in tree levels
+const LATEX_COMMAND_WEIGHT: usize = 4;
+
+/// Depth of a LaTeX syntax tree. Commands count as several levels, as
+/// converting one takes far more stack than a group does.
+pub fn latex_tree_depth(root: &LatexNode) -> usize {
+    let weight = |node: &LatexNode| match node.kind() {
+        SyntaxKind::ItemCmd => LATEX_COMMAND_WEIGHT,
+        _ => 1,
+    };
+    let mut depth = 0usize;
+    let mut max = 0;
+    for event in root.preorder() {
+        match event {
+            WalkEvent::Enter(node) => {
+                depth += weight(&node);
+                max = max.max(depth);
+            }
+            WalkEvent::Leave(node) => depth -= weight(&node),
+        }
+    }
+    max
+}
+
+/// Depth of a Typst syntax tree
+pub fn typst_tree_depth(root: &TypstNode) -> usize {
+    let mut max = 0;
+    let mut stack = vec![(root, 1usize)];
+    while let Some((node, depth)) = stack.pop() {
+        max = max.max(depth);
+        stack.extend(node.children().map(|child| (child, depth + 1)));
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::maps::TEX_COMMAND_SPEC;
+
+    #[test]
+    fn test_tree_depth() {
+        let latex = |s: &str| latex_tree_depth(&mitex_parser::parse(s, TEX_COMMAND_SPEC.clone()));
+        assert!(latex(r"\frac{a}{\sqrt{b}}") > latex(r"\frac{a}{b}"));
+        assert!(latex(&r"\sqrt".repeat(200)) > MAX_NESTING_DEPTH);
+        assert!(latex(&"a + ".repeat(200)) < 10);
+
+        let typst = |s: &str| typst_tree_depth(&typst_syntax::parse(s));
+        assert!(typst("#box[#text(red)[x]]") > typst("#box[x]"));
+        assert!(typst(&format!("${}x{}$", "(".repeat(200), ")".repeat(200))) > MAX_NESTING_DEPTH);
+        assert!(typst(&"a + ".repeat(200)) < 10);
+    }
+}
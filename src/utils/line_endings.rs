@@ -0,0 +1,97 @@
+//! Line endings
+//!
+//! Input is read with `\n` line endings whatever the source file uses, so
+//! text passed through verbatim cannot carry a stray `\r` into the output.
+//! The output then gets one line ending throughout and no trailing
+//! whitespace at the ends of its lines.
+
+use std::borrow::Cow;
+
+/// Line ending of converted output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`, whatever the input uses
+    #[default]
+    Lf,
+    /// `\r\n`
+    Crlf,
+    /// The ending of the first line break of the input: `\r\n` for a CRLF
+    /// file, `\n` for anything else
+    Preserve,
+}
+
+impl LineEnding {
+    /// The ending used for output converted from `input`
+    pub fn for_input(self, input: &str) -> LineEnding {
+        match self {
+            LineEnding::Preserve => match input.find(['\r', '\n']) {
+                Some(pos) if input[pos..].starts_with("\r\n") => LineEnding::Crlf,
+                _ => LineEnding::Lf,
+            },
+            ending => ending,
+        }
+    }
+
+    /// The characters of the ending
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Lf | LineEnding::Preserve => "\n",
+        }
+    }
+}
+
+/// Turn CRLF and lone CR line breaks into `\n`
+pub fn normalize_line_endings(input: &str) -> Cow<'_, str> {
+    if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Strip trailing whitespace from every line of `output` and end its lines
+/// with `ending`, resolved against the original `input`
+pub fn finish_lines(output: &str, ending: LineEnding, input: &str) -> String {
+    let newline = ending.for_input(input).as_str();
+    let output = normalize_line_endings(output);
+    let mut finished = String::with_capacity(output.len());
+    let mut lines = output.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        finished.push_str(line.trim_end_matches([' ', '\t']));
+        if lines.peek().is_some() {
+            finished.push_str(newline);
+        }
+    }
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert!(matches!(normalize_line_endings("a\nb"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_finish_lines_strips_trailing_whitespace() {
+        assert_eq!(
+            finish_lines("a \\\\ \nb\t\n\n", LineEnding::Lf, ""),
+            "a \\\\\nb\n\n"
+        );
+    }
+
+    #[test]
+    fn test_preserve_follows_first_line_break() {
+        assert_eq!(LineEnding::Preserve.for_input("a\r\nb\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::Preserve.for_input("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::Preserve.for_input("a\rb"), LineEnding::Lf);
+        assert_eq!(
+            finish_lines("x\ny\n", LineEnding::Preserve, "a\r\n"),
+            "x\r\ny\r\n"
+        );
+    }
+}
@@ -2,17 +2,30 @@
 //!
 //! This module contains utilities and helpers:
 //! - Diagnostics and error reporting
+//! - Block-level diffs of converted output
 //! - File resolution for multi-file documents
 //! - Error types and result types
+//! - Limits that keep deeply nested input from overflowing the stack
+//! - Consistent line endings in converted output
+//! - Progress reporting for long conversions
 
 pub mod diagnostics;
+pub mod diff;
 pub mod error;
 pub mod files;
+pub mod limits;
+pub mod line_endings;
+pub mod progress;
 
 // Re-export commonly used items
 pub use diagnostics::{check_latex, format_diagnostics, Diagnostic, DiagnosticLevel};
+pub use diff::{diff_outputs, BlockKind, DiffHunk, OutputFormat};
 pub use error::{ConversionError, ConversionOutput, ConversionResult, ConversionWarning};
-pub use files::{FileResolveError, FileResolver, MemoryFileResolver, NoopFileResolver};
+pub use files::{
+    CompanionFile, FileResolveError, FileResolver, MemoryFileResolver, NoopFileResolver,
+};
+pub use line_endings::LineEnding;
+pub use progress::{Phase, Progress, ProgressCallback};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use files::StdFileResolver;
@@ -0,0 +1,68 @@
+//! Progress of a document conversion
+//!
+//! A long document takes a while to convert. A [`ProgressCallback`] set on
+//! the conversion options is told where the converter is at the start and
+//! end of each phase, and after each top-level block while converting.
+
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// Phase of a document conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Expanding user macros (LaTeX) or evaluating code (Typst)
+    MacroExpansion,
+    /// Parsing the expanded source
+    Parsing,
+    /// Converting the parsed document, block by block
+    Conversion,
+    /// Assembling the preamble and finishing the output
+    PostProcessing,
+}
+
+/// Where a conversion is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Phase being worked through
+    pub phase: Phase,
+    /// Bytes of the phase's input done with
+    pub bytes_processed: usize,
+    /// Size in bytes of the text the phase works through: the source for
+    /// macro expansion, the expanded source for parsing and conversion, and
+    /// the converted body for post-processing
+    pub bytes_total: usize,
+    /// Number of top-level blocks converted so far
+    pub current_block: usize,
+}
+
+/// Callback told about the progress of a conversion.
+///
+/// Reporting is cheap when no callback is set. A callback that panics is
+/// ignored: the conversion carries on as if it had returned.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Wrap a closure
+    pub fn new(callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Call the callback, swallowing a panic
+    pub fn report(&self, progress: Progress) {
+        let _ = catch_unwind(AssertUnwindSafe(|| (self.0)(progress)));
+    }
+}
+
+impl From<Arc<dyn Fn(Progress) + Send + Sync>> for ProgressCallback {
+    fn from(callback: Arc<dyn Fn(Progress) + Send + Sync>) -> Self {
+        Self(callback)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
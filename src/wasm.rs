@@ -482,11 +482,14 @@ fn format_typst_output(input: &str) -> String {
 #[cfg(feature = "wasm")]
 #[wasm_bindgen(js_name = "previewTable")]
 pub fn preview_table_wasm(input: &str, format: &str) -> JsValue {
+    use crate::core::latex2typst::engine::expand_latex;
     use crate::features::tables::{parse_latex_table, parse_typst_table};
 
     let result = match format {
         "latex" => {
-            if let Some(table) = parse_latex_table(input) {
+            // Expand macros first, so a header cell such as `\mse` defined
+            // as `MSE$\downarrow$` is split like the text it stands for
+            if let Some(table) = parse_latex_table(&expand_latex(input)) {
                 table_to_preview_data(&table)
             } else {
                 return to_js_value(&TablePreviewError {
@@ -0,0 +1,18 @@
+#set document(title: "A Short Note", author: ("Ann Lee", "Bob Roe"))
+#set page(paper: "a4", numbering: "1")
+#set heading(numbering: "1.")
+
+= Introduction <sec:intro>
+This is _emphasised_, *bold* and `raw` text. See @sec:method.
+
+== Background
+A footnote#footnote[A note.] and a #link("https://typst.app")[link].
+
+= Method <sec:method>
+- First item
+- Second item with $x^2$
+
++ One
++ Two
+
+/ Term: Description
@@ -0,0 +1,4 @@
+Knuth wrote about this @knuth84, see also @lamport94[p. 3]
+and #cite(<knuth84>, form: "prose").
+
+#bibliography("refs.bib", style: "ieee")
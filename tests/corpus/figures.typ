@@ -0,0 +1,6 @@
+#figure(
+  image("plot.pdf", width: 50%),
+  caption: [A plot of $sin x$.],
+) <fig:plot>
+
+As @fig:plot shows, the curve is periodic.
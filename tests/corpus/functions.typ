@@ -0,0 +1,10 @@
+#let R = $bb(R)$
+#let norm(x) = $lr(|| #x ||)$
+#let greet(name) = [Hello, #name!]
+
+#greet("World") For $x in #R^n$ we have $norm(x)_2 <= 1$.
+
+#for i in range(3) [Item #i. ]
+
+#let total = 1 + 2 * 3
+The total is #total.
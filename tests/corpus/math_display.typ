@@ -0,0 +1,10 @@
+#set math.equation(numbering: "(1)")
+
+$ integral_(-oo)^oo e^(-x^2) dif x = sqrt(pi) $ <eq:gauss>
+
+$ a &= b + c \
+  d &<= sum_(i=1)^n binom(n, i) x_i^2 $
+
+$ f(x) = cases(0 & x < 0, 1/2 & x = 0, 1 & "otherwise") $
+
+By @eq:gauss we are done.
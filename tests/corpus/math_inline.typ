@@ -0,0 +1,3 @@
+Let $alpha, beta in RR$ with $alpha != beta$ and $lim_(n -> oo) 1/n = 0$.
+Vectors $bold(v)$, $arrow(u)$ and $hat(x)$ satisfy $norm(bold(v)) <= 1$.
+The set ${x | x > 0}$ is open, and $(a/b)^2 approx 1$ with $f'(x)$.
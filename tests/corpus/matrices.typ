@@ -0,0 +1,3 @@
+$ A = mat(1, 2; 3, 4), quad B = mat(delim: "[", align: #right, -1, 2; 3, -4), quad det mat(delim: "|", a, b; c, d) = a d - b c $
+
+$ vec(1, 2, 3) $
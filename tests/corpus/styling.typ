@@ -0,0 +1,12 @@
+#set text(font: "New Computer Modern", size: 11pt)
+#set par(justify: true)
+
+#text(fill: red)[Red text] and #text(size: 14pt)[large text].
+#underline[Underlined], #strike[struck] and #highlight[highlighted].
+
+#align(center)[Centered paragraph.]
+
+#quote(block: true, attribution: [Someone])[A block quote.]
+
+#pagebreak()
+After the break, #smallcaps[small caps] and #sub[sub]/#super[super].
@@ -0,0 +1,12 @@
+#figure(
+  table(
+    columns: 3,
+    align: (left, center, right),
+    table.header([Name], [Value], [Unit]),
+    [Length], [10], [m],
+    [Mass], [2.5], [kg],
+  ),
+  caption: [Basic measurements],
+) <tab:basic>
+
+See @tab:basic.
@@ -0,0 +1,9 @@
+#table(
+  columns: 4,
+  stroke: 0.5pt,
+  inset: (x: 10pt, y: 7.5pt),
+  table.header(table.cell(rowspan: 2)[Model], table.cell(colspan: 2)[Score], [Time]),
+  [A], [B], [(s)],
+  [Base], [0.81], [0.77], [12],
+  [Ours], [*0.90*], [*0.85*], [9],
+)
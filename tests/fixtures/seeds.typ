@@ -0,0 +1,46 @@
+#set document(title: "A Short Note", author: ("Ann Lee", "Bob Roe"))
+#set page(paper: "a4", numbering: "1")
+#set text(lang: "en")
+#set math.equation(numbering: "(1)")
+#let R = $bb(R)$
+#let norm(x) = $lr(|| #x ||)$
+
+= Introduction <sec:intro>
+We study $f: #R^n -> #R$ with $norm(x)_2 <= 1$, see @knuth84.
+
+$ integral_0^oo e^(-x^2) dif x = sqrt(pi) / 2 $ <eq:main>
+
+$ a &= b + c \
+  &<= sum_(i=1)^n binom(n, i) x_i^2 $
+
+#table(
+  columns: 3,
+  stroke: 0.5pt,
+  table.header(table.cell(colspan: 2)[Head], [X]),
+  [a], [b], [c],
+  table.cell(rowspan: 2)[m], [1], [2],
+  [3], [4],
+)
+
+#figure(
+  image("plot.pdf", width: 50%),
+  caption: [A plot of $sin x$.],
+) <fig:plot>
+
+- First _point_
+- Second with *bold* and `code`
+  + nested
+
+#for i in range(3) [Item #i. ]
+#let total = (1, 2, 3).map(x => x * 2).sum()
+Total: #total
+
+$mat(1, 0; 0, 1)$ and $cal(O)(n log n)$, $underbrace(a + b, c)$, $attach(=, t: !)$.
+
+```python
+def f(x): return x ** 2
+```
+
+#footnote[A note with #link("https://example.com")] @fig:plot
+#align(center)[#block(fill: luma(230), inset: 8pt)[Boxed]]
+#bibliography("refs.bib")
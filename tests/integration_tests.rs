@@ -4,8 +4,10 @@ use std::io::Write;
 use std::process::{Command, Stdio};
 
 use tylax::{
-    convert_auto, convert_auto_document, detect_format, latex_document_to_typst, latex_to_typst,
-    typst_to_latex, typst_to_latex_with_diagnostics, typst_to_latex_with_options, T2LOptions,
+    convert_auto, convert_auto_document, detect_format, latex_document_to_typst,
+    latex_document_to_typst_with_options, latex_to_typst, typst_to_latex,
+    typst_to_latex_with_diagnostics, typst_to_latex_with_options, CodeBackend, FragmentContext,
+    L2TOptions, LatexConverter, LineEnding, SidewaysStyle, T2LOptions, TodoStyle,
 };
 
 fn run_t2l_cli(input: &str) -> String {
@@ -335,6 +337,168 @@ fn assert_t2l_paths_match(input: &str) -> String {
 mod l2t_math {
     use super::*;
 
+    #[test]
+    fn test_big_delimiter_sizes() {
+        let result = latex_to_typst(r"\bigl( \sum_i x_i \bigr)^2");
+        assert_eq!(result.trim(), "lr(size: #120%, (sum_(i) x_(i)))^(2)");
+
+        let result = latex_to_typst(r"\Bigl\langle x \Bigr\rangle + \bigg\{ y \bigg\}");
+        assert!(
+            result.contains("lr(size: #150%, chevron.l x chevron.r)"),
+            "Got: {}",
+            result
+        );
+        assert!(result.contains("lr(size: #200%, { y })"), "Got: {}", result);
+
+        // Each side of a mismatched pair keeps its own size
+        let result = latex_to_typst(r"\Bigl[ x \bigr]");
+        assert_eq!(result.trim(), r"lr(size: #150%, \[) x lr(size: #120%, \])");
+
+        let result = latex_to_typst(r"a \big| b \Biggm| c");
+        assert_eq!(
+            result.trim(),
+            r#"a class("relation", lr(size: #120%, bar.v)) b class("relation", lr(size: #250%, bar.v)) c"#
+        );
+    }
+
+    #[test]
+    fn test_starred_and_small_matrices() {
+        let result = latex_to_typst(r"\begin{pmatrix*}[r] -1 & 2 \\ 3 & -4 \end{pmatrix*}");
+        assert!(
+            result.contains(r#"mat(delim: "(", align: right, "#),
+            "Got: {}",
+            result
+        );
+        assert!(!result.contains("[r"), "Got: {}", result);
+
+        let result = latex_to_typst(r"\begin{matrix*}[l] a & b \end{matrix*}");
+        assert!(
+            result.contains("mat(delim: #none, align: left, a, b)"),
+            "Got: {}",
+            result
+        );
+
+        let result = latex_to_typst(r"\begin{smallmatrix} a & b \\ c & d \end{smallmatrix}");
+        assert!(
+            result.contains("inline(mat(delim: #none, a, b ; c, d))"),
+            "Got: {}",
+            result
+        );
+
+        let result = latex_to_typst(
+            r"\bigl(\begin{smallmatrix} a & b \\ c & d \end{smallmatrix}\bigr) + \big|\begin{smallmatrix} 1 \end{smallmatrix}\big|",
+        );
+        assert_eq!(
+            result.trim(),
+            r#"inline(mat(delim: "(", a, b ; c, d)) + inline(mat(delim: "|", 1))"#
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_left_right() {
+        // A sized pair split across the rows of an align
+        let input = "\\documentclass{article}\n\\begin{document}\n\\begin{align}\nf &= \\left( a + b \\right. \\\\\n  &\\quad \\left. + c \\right)\n\\end{align}\n\\end{document}";
+        let result = latex_document_to_typst(input);
+        assert!(
+            result.contains("lr(paren.l a + b) \\ & quad lr(+ c paren.r)"),
+            "Got: {}",
+            result
+        );
+
+        // Unmatched halves are closed within their cell, row or formula
+        let result = latex_to_typst(r"\left( a \\ b \right]");
+        assert_eq!(result.trim(), r"lr(paren.l a) \ lr(b bracket.r)");
+        let result = latex_to_typst(r"\frac{a \right)}{\left\{ b}");
+        assert_eq!(result.trim(), "frac(lr(a paren.r), lr(brace.l b))");
+
+        let input = "\\documentclass{article}\n\\begin{document}\n$\\left( a$ and $b \\right)$ and $c$\n\\end{document}";
+        let result = latex_document_to_typst(input);
+        assert!(
+            result.contains("$lr(paren.l a)$ and $lr(b paren.r)$ and $c$"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_limits_modifiers() {
+        let result = latex_to_typst(r"\int\limits_0^1 f + \sum\nolimits_{i} x_i");
+        assert!(
+            result.contains("limits(integral)_(0)^(1) f"),
+            "Got: {}",
+            result
+        );
+        assert!(result.contains("scripts(sum)_(i) x_(i)"), "Got: {}", result);
+        assert!(!result.contains("/*"), "Got: {}", result);
+
+        // The last modifier wins, also on starred operators
+        let result = latex_to_typst(r"\int\limits\nolimits_0 + \argmax\nolimits_x f");
+        assert!(result.contains("scripts(integral)_(0)"), "Got: {}", result);
+        assert!(
+            result.contains("scripts(op(\"argmax\"))_(x)"),
+            "Got: {}",
+            result
+        );
+
+        let input = "\\documentclass{article}\n\\DeclareMathOperator*{\\esssup}{esssup}\n\\begin{document}\n$\\esssup_x f + \\esssup\\nolimits_x f$\n\\end{document}";
+        let result = latex_document_to_typst(input);
+        assert!(
+            result.contains("limits(op(\"esssup\"))_(x) f + scripts(op(\"esssup\"))_(x) f"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_fraction_styles() {
+        let result = latex_to_typst(r"\dfrac{a}{b} + \tfrac{1}{2}");
+        assert!(
+            result.contains("display(frac(a, b)) + inline(frac(1, 2))"),
+            "Got: {}",
+            result
+        );
+        let result = latex_to_typst(r"\cfrac{1}{1 + \cfrac[l]{1}{x}}");
+        assert!(
+            result.contains("display(frac(1, 1 + display(frac(1, x))))"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_blackboard_bold_without_shorthand() {
+        let result = latex_to_typst(r"\mathbb{q} + \mathbb{R}");
+        assert!(result.contains("bb(q)"), "Got: {}", result);
+        assert!(result.contains("RR"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_not_negated_relations() {
+        let cases = [
+            (r"a \not= b", "eq.not"),
+            (r"x \not\in S", "in.not"),
+            (r"A \not\subset B", "subset.not"),
+            (r"a \not\equiv b", "equiv.not"),
+            (r"a \not\prec b", "prec.not"),
+            (r"a \not\le b", "lt.eq.not"),
+            (r"a \not\to b", "arrow.r.not"),
+        ];
+        for (latex, expected) in cases {
+            let result = latex_to_typst(latex);
+            assert!(result.contains(expected), "{} -> {}", latex, result);
+            assert!(
+                !result.split_whitespace().any(|word| word == "not"),
+                "{} -> {}",
+                latex,
+                result
+            );
+        }
+
+        // Relations without a negated symbol are struck through
+        let result = latex_to_typst(r"a \not\star b");
+        assert!(result.contains("cancel(star)"), "Got: {}", result);
+    }
+
     #[test]
     fn test_greek_letters() {
         // AST converter may output Unicode Greek letters (α, β, etc.) or text names
@@ -425,12 +589,44 @@ mod l2t_math {
     fn test_overset_with_unbraced_symbol_base() {
         assert_eq!(
             latex_to_typst(r"\overset{p}\sim").trim(),
-            "limits(tilde)^(p)"
+            "attach(limits(tilde), t: p)"
         );
         assert_eq!(
             latex_to_typst(r"\overset{p}{\sim}").trim(),
-            "limits(tilde)^(p)"
+            "attach(limits(tilde), t: p)"
+        );
+    }
+
+    #[test]
+    fn test_stacked_annotations() {
+        assert_eq!(
+            latex_to_typst(r"a \stackrel{!}{=} b").trim(),
+            "a attach(limits(=), t: !) b"
+        );
+        assert_eq!(
+            latex_to_typst(r"\underset{x}{\operatorname{argmin}}").trim(),
+            "attach(limits(op(\"argmin\")), b: x)"
+        );
+        let result = latex_to_typst(r"\underset{i \to \infty}{\lim} x_i");
+        assert!(
+            result.contains("attach(limits(lim), b: i -> infinity)"),
+            "Got: {}",
+            result
+        );
+        assert!(latex_to_typst(r"\stackrel{\text{def}}{=}").contains("eq.def"));
+    }
+
+    #[test]
+    fn test_split_inside_equation_keeps_one_number() {
+        let result = latex_document_to_typst(
+            "\\begin{document}\n\\begin{equation}\\label{eq:s}\n\\begin{split} a &= b + c \\\\ &\\quad + d \\end{split}\n\\end{equation}\n\\end{document}",
+        );
+        assert!(
+            result.contains("$ a & = b + c \\ & quad + d $ <eq-s>"),
+            "Got: {}",
+            result
         );
+        assert!(!result.contains("split"), "Got: {}", result);
     }
 
     #[test]
@@ -892,6 +1088,122 @@ mod l2t_math {
 mod t2l_math {
     use super::*;
 
+    #[test]
+    fn test_sized_lr_becomes_big_pairs() {
+        let result = typst_to_latex("$lr(size: #150%, (sum_i x_i))^2$");
+        assert!(
+            result.contains(r"\Bigl(\sum_i x_i\Bigr)^2"),
+            "Got: {}",
+            result
+        );
+
+        let result = typst_to_latex(r"$lr(size: #150%, \[) x lr(size: #120%, \])$");
+        assert!(result.contains(r"\Bigl[ x \bigr]"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_aligned_matrices_use_starred_environments() {
+        let result = typst_to_latex(r#"$mat(delim: "(", align: right, -1, 2; 3, -4)$"#);
+        assert!(result.contains(r"\begin{pmatrix*}[r]"), "Got: {}", result);
+        assert!(result.contains(r"\end{pmatrix*}"), "Got: {}", result);
+
+        let result = typst_to_latex(r#"$mat(delim: "[", align: center, a, b)$"#);
+        assert!(result.contains(r"\begin{bmatrix}"), "Got: {}", result);
+
+        let result = typst_to_latex_with_options(
+            r#"$mat(delim: "‖", align: left, a)$"#,
+            &T2LOptions::full_document(),
+        );
+        assert!(result.contains(r"\begin{Vmatrix*}[l]"), "Got: {}", result);
+        assert!(
+            result.contains(r"\usepackage{mathtools}"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_greek_capitals_without_commands_become_latin() {
+        let result = typst_to_latex("$Alpha + Beta + Rho + Chi + omicron + Gamma + upright(Eta)$");
+        assert!(
+            result.contains(r"A + B + P + X + o + \Gamma + \mathrm{H}"),
+            "Got: {}",
+            result
+        );
+
+        let result = typst_to_latex("$bold(Gamma) + bold(x) + bold(Delta x)$");
+        assert!(
+            result.contains(r"\boldsymbol{\Gamma} + \mathbf{x} + \boldsymbol{\Delta x}"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_limits_and_scripts_restore_modifiers() {
+        let result = typst_to_latex("$limits(integral)_0^1 f + scripts(sum)_(i) x$");
+        assert!(
+            result.contains(r"\int\limits_0^1 f + \sum\nolimits_i x"),
+            "Got: {}",
+            result
+        );
+        assert!(!result.contains(r"\displaystyle"), "Got: {}", result);
+
+        let result = typst_to_latex("$scripts(limits(integral))_0$");
+        assert!(result.contains(r"\int\nolimits_0"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_styled_fractions_restore_dfrac_and_tfrac() {
+        let result = typst_to_latex("$display(frac(a, b)) + inline(frac(1, 2))$");
+        assert!(
+            result.contains(r"\dfrac{a}{b} + \tfrac{1}{2}"),
+            "Got: {}",
+            result
+        );
+        assert!(!result.contains(r"\displaystyle"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_negated_relations_prefer_dedicated_commands() {
+        let result = typst_to_latex("$a != b, a eq.not b, x in.not S$");
+        assert_eq!(result.matches(r"\neq").count(), 2, "Got: {}", result);
+        assert!(result.contains(r"\notin"), "Got: {}", result);
+        assert!(!result.contains(r"\not\in"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_attach_above_relation_uses_overset() {
+        let result = typst_to_latex(r#"$a attach(=, t: "def") b$"#);
+        assert!(
+            result.contains(r"\overset{\text{def}}{=}"),
+            "Got: {}",
+            result
+        );
+        let result = typst_to_latex("$attach(limits(=), t: !)$");
+        assert!(result.contains(r"\overset{!}{=}"), "Got: {}", result);
+        let result = typst_to_latex("$lim_(x -> 0) f$");
+        assert!(result.contains(r"\lim_{"), "Got: {}", result);
+        let result = typst_to_latex("$attach(x, t: 2)$");
+        assert!(result.contains("x^2"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_labelled_multiline_equation_uses_split() {
+        let result = typst_to_latex("$ a &= b + c \\\n  &quad + d $ <eq:s>");
+        assert!(
+            result.contains(
+                "\\begin{equation}\n\\begin{split}\na & = b + c \\\\\n &\\quad + d\n\\end{split}\n\\label{eq:s}\n\\end{equation}"
+            ),
+            "Got: {}",
+            result
+        );
+        // Without a label the lines stay individually aligned
+        let result = typst_to_latex("$ a &= b \\\n  &= c $");
+        assert!(result.contains("\\begin{align}"), "Got: {}", result);
+        assert!(result.contains(r"b \\"), "Got: {}", result);
+    }
+
     #[test]
     fn test_greek_letters() {
         let result = typst_to_latex("alpha + beta = gamma");
@@ -996,107 +1308,3240 @@ mod l2t_document {
     use super::*;
 
     #[test]
-    fn test_simple_document() {
-        let latex = r#"
-\documentclass{article}
-\title{My Document}
-\author{John Doe}
-\begin{document}
-\maketitle
-Hello, world!
-\end{document}
-"#;
-
-        let result = latex_document_to_typst(latex);
-
-        // Should contain document content (AST converter may handle metadata differently)
-        assert!(!result.is_empty(), "Result should not be empty");
-        // The document should contain the body content
-        assert!(
-            result.contains("Hello") || result.contains("world"),
-            "Missing body content: {}",
-            result
-        );
+    fn test_table_shading_becomes_fill() {
+        let input = "\\documentclass{article}\n\\usepackage[table]{xcolor}\n\\begin{document}\n{\\rowcolors{2}{gray!20}{}\n\\begin{tabular}{lc}\n\\rowcolor{blue} A & B \\\\\n\\cellcolor[RGB]{255,0,0} x & \\cellcolor{yellow} y \\\\\n\\end{tabular}}\n\\begin{tabular}{l}\nz \\\\\n\\end{tabular}\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "fill: (_, y) => if y < 1 { none } else if calc.even(y) { color.mix((gray, 20%), (white, 80%)) } else { none },",
+            "table.cell(fill: blue)[A], table.cell(fill: blue)[B],",
+            "table.cell(fill: rgb(255, 0, 0))[x], table.cell(fill: yellow)[y],",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        // \rowcolors ends with its group
+        assert_eq!(output.matches("fill: (_, y)").count(), 1, "{}", output);
+    }
+
+    #[test]
+    fn test_monospace_keeps_inner_braces() {
+        let input = "\\documentclass{article}\n\\begin{document}\nUse \\texttt{std::vector<int>{}} and \\texttt{\\{key: value\\}}, or \\texttt{{\"a\": {\"b\": 1}}}. Nested \\texttt{\\texttt{x}}, tick \\texttt{a`b}, sans \\textsf{\\{k\\}}.\n\\begin{figure}\n\\caption{The \\texttt{map<K, V>{}} type}\n\\end{figure}\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "Use `std::vector<int>{}` and `{key: value}`",
+            "or `{\"a\": {\"b\": 1}}`.",
+            "Nested `x`,",
+            "tick #raw(\"a`b\"),",
+            "sans {k}.",
+            "[The `map<K, V>{}` type]",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
     }
 
     #[test]
-    fn test_document_with_sections() {
-        let latex = r#"
-\documentclass{article}
-\begin{document}
-\section{Introduction}
-This is the intro.
-\section{Methods}
-This is methods.
-\end{document}
-"#;
-
-        let result = latex_document_to_typst(latex);
-        assert!(!result.contains("Error"));
+    fn test_line_breaks_and_paragraphs() {
+        let input = "\\documentclass{article}\n\\title{On Roses\\\\ A Poem}\n\\begin{document}\n\\maketitle\nRoses are red,\\\\\nviolets are blue,\\newline sugar is sweet\\\\[6pt]\nand so are you.\\par Next paragraph.\n\\begin{flushright}\nJohn Doe\\\\ 1 Main St\n\\end{flushright}\n\\begin{figure}\n\\caption{Left\\\\[2pt] right}\n\\end{figure}\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "title: \"On Roses A Poem\",",
+            "[On Roses\\  A Poem]",
+            "Roses are red,\\  violets are blue,\\  sugar is sweet\\ #v(6pt, weak: true)  and so are you.\n\n Next paragraph.",
+            "#align(right)[\n John Doe\\  1 Main St\n]",
+            "caption: [Left\\  right],",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
     }
 
     #[test]
-    fn test_document_class_detection() {
-        let article =
-            latex_document_to_typst(r"\documentclass{article}\begin{document}Test\end{document}");
-        assert!(article.contains("a4") || !article.contains("Error"));
-
-        let book =
-            latex_document_to_typst(r"\documentclass{book}\begin{document}Test\end{document}");
-        assert!(book.contains("heading") || !book.contains("Error"));
+    fn test_xspace_macro_spacing() {
+        let input = "\\documentclass{article}\n\\usepackage{xspace}\n\\newcommand{\\tool}{TyLaX\\xspace}\n\\begin{document}\nWe use \\tool. Also \\tool, and (see \\tool) here. Then \\tool\nis great, \\tool \\emph{really}. A \\tool\\footnote{About \\tool.} works.\n\nEnd with \\tool\n\n\\begin{figure}\n\\caption{The \\tool pipeline and \\tool.}\n\\end{figure}\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "We use TyLaX. Also TyLaX, and (see TyLaX) here.",
+            "Then TyLaX is great, TyLaX _really_.",
+            "A TyLaX#footnote[About TyLaX.] works.",
+            "End with TyLaX\n",
+            "caption: [The TyLaX pipeline and TyLaX.],",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
     }
 
     #[test]
-    fn test_document_with_math() {
-        let latex = r#"
-\documentclass{article}
-\begin{document}
-The formula $E = mc^2$ is famous.
-\end{document}
-"#;
-
-        let result = latex_document_to_typst(latex);
-        assert!(!result.contains("Error"));
+    fn test_short_caption_forms() {
+        let input = "\\documentclass{article}\n\\title[Running]{Full Title}\n\\begin{document}\n\\maketitle\n\\section[Short]{Long section}\n\\begin{figure}\n\\includegraphics{a.png}\n\\caption[Perf.]{Performance on all benchmarks}\n\\end{figure}\n\\begin{figure}\n\\includegraphics{b.png}\n\\caption{Results [subset A]}\n\\end{figure}\n\\begin{table}\n\\caption[Short]{Timings [s]}\n\\begin{tabular}{l}a\\end{tabular}\n\\end{table}\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("title: \"Full Title\""), "{}", output);
+        assert!(output.contains("\n= Long section\n"), "{}", output);
+        assert!(
+            output.contains("caption: [Performance on all benchmarks],"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("caption: [Results [subset A]],"),
+            "{}",
+            output
+        );
+        assert!(output.contains("caption: [Timings [s]],"), "{}", output);
+        assert!(!output.contains("Perf."), "{}", output);
+        assert!(!output.contains("Running"), "{}", output);
     }
-}
-
-// ============================================================================
-// Document Mode Tests - Typst to LaTeX
-// ============================================================================
-
-mod t2l_document {
-    use super::*;
 
     #[test]
-    fn test_heading_conversion() {
-        let typst = "= Main Title";
-        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+    fn test_multicolumn_alignment() {
+        let input = "\\begin{tabular}{|l|l|}\n\\multicolumn{1}{c|}{Head} & b \\\\\n\\multicolumn{2}{r}{wide} \\\\\n\\end{tabular}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        assert!(
+            result
+                .output
+                .contains("table.cell(align: center)[Head], [b],"),
+            "{}",
+            result.output
+        );
         assert!(
-            result.contains("\\section"),
-            "Expected section, got: {}",
             result
+                .output
+                .contains("table.cell(colspan: 2, align: right)[wide],"),
+            "{}",
+            result.output
         );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == tylax::WarningKind::Approximation
+                && w.message.contains("Vertical rules")));
     }
 
     #[test]
-    fn test_subsection_conversion() {
-        let typst = "== Subsection";
-        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+    fn test_heading_levels() {
+        let convert = |input: &str, options: L2TOptions| {
+            LatexConverter::with_options(options).convert_document(input)
+        };
+        let book = "\\documentclass{book}\n\\begin{document}\n\\chapter{C}\n\\section{S}\n\\paragraph{Para}\nBody.\n\\end{document}";
+        let output = convert(book, L2TOptions::default());
+        assert!(output.contains("\n= C\n"), "{}", output);
+        assert!(output.contains("\n== S\n"), "{}", output);
+        assert!(output.contains("\n*Para* Body."), "{}", output);
+
+        let output = convert(
+            book,
+            L2TOptions {
+                heading_offset: 1,
+                max_heading_level: 6,
+                ..L2TOptions::default()
+            },
+        );
+        assert!(output.contains("\n== C\n"), "{}", output);
+        assert!(output.contains("\n====== Para\n"), "{}", output);
+
+        let fragment = "\\subsection{A}\nx\n\\subsubsection{B}\ny";
+        let output = convert(
+            fragment,
+            L2TOptions {
+                auto_normalize_headings: true,
+                ..L2TOptions::default()
+            },
+        );
+        assert!(output.contains("\n= A\n"), "{}", output);
+        assert!(output.contains("\n== B\n"), "{}", output);
+
+        let output = convert(
+            "\\section{S}",
+            L2TOptions {
+                heading_offset: -5,
+                ..L2TOptions::default()
+            },
+        );
+        assert!(output.contains("\n= S\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_figure_label_placement() {
+        let figure = |body: &str| {
+            let input = format!(
+                "\\documentclass{{article}}\n\\begin{{document}}\n\\begin{{figure}}\n{}\n\\end{{figure}}\n\\end{{document}}",
+                body
+            );
+            latex_document_to_typst(&input)
+        };
+        for body in [
+            r"\label{fig:x}\includegraphics{a.png}\caption{Plot}",
+            r"\includegraphics{a.png}\caption{Plot\label{fig:x}}",
+            r"\includegraphics{a.png}\label{fig:x}\caption{Plot}",
+        ] {
+            let output = figure(body);
+            assert_eq!(output.matches("<fig-x>").count(), 1, "{}", output);
+            assert!(output.contains(") <fig-x>"), "{}", output);
+            assert!(output.contains("caption: [Plot],"), "{}", output);
+        }
+
+        let output = figure(
+            r"\includegraphics{a.png}\caption{Unlike \ref{tab:base} and \cite[p.~2]{key} \label{fig:ours}}",
+        );
         assert!(
-            result.contains("\\subsection"),
-            "Expected subsection, got: {}",
-            result
+            output.contains("caption: [Unlike @tab-base and #cite(<key>, supplement: [p.~2])"),
+            "{}",
+            output
         );
+        assert_eq!(output.matches("<fig-ours>").count(), 1, "{}", output);
+        assert!(output.contains(") <fig-ours>"), "{}", output);
     }
 
     #[test]
-    fn test_bold_conversion() {
-        let typst = "*bold text*";
-        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+    fn test_quotes_and_dashes() {
+        let input = r"\documentclass{article}
+\begin{document}
+He said ``a `b' c'' and `d'. Pages 1--5 --- done\ldots{} It's $f''(x)$ \verb|``v''|.
+\end{document}";
+        let output = latex_document_to_typst(input);
         assert!(
-            result.contains("\\textbf"),
-            "Expected textbf, got: {}",
-            result
+            output.contains(r#"He said "a 'b' c" and 'd'. Pages 1–5 — done... It's $f ''(x)$"#),
+            "{}",
+            output
+        );
+        assert!(output.contains("``v''"), "{}", output);
+    }
+
+    #[test]
+    fn test_ties_and_nobreak_dashes() {
+        let input = r"\documentclass{article}
+\title{On~Ties}
+\begin{document}
+See Figure~\ref{fig:x} and Dr.~Smith~\cite{k}.
+Pages 1\nobreakdash-5, 3\nobreakdash--4 and non\nobreakdash-trivial.
+Math $a~b$ and $\text{Dr.~X}$.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("See Figure~@fig-x and Dr.~Smith~#cite(<k>)."),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("Pages 1\u{2011}5, 3–4 and non\u{2011}trivial."),
+            "{}",
+            output
+        );
+        assert!(output.contains("$a space.nobreak b$"), "{}", output);
+        assert!(output.contains(r#"$"Dr.\u{a0}X"$"#), "{}", output);
+        assert!(output.contains("title: \"On Ties\""), "{}", output);
+    }
+
+    #[test]
+    fn test_typography_toggles_are_noted_together() {
+        let input = r"\documentclass{article}
+\usepackage{microtype}
+\usepackage[all]{nowidow}
+\frenchspacing
+\sloppy
+\widowpenalty=10000
+\clubpenalty 10000
+\begin{document}
+Text \tolerance=1000 \emergencystretch=3em after \raggedbottom here.
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        assert!(
+            output.contains("#set par(justify: true, linebreaks: \"simple\")"),
+            "{}",
+            output
+        );
+        assert!(output.contains("Text"), "{}", output);
+        for leaked in ["=1000", "3em", "10000", "frenchspacing", "microtype"] {
+            assert!(!output.contains(leaked), "{}: {}", leaked, output);
+        }
+
+        let notes: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == tylax::WarningKind::Typography)
+            .collect();
+        assert_eq!(notes.len(), 1, "{:?}", result.warnings);
+        for name in [
+            "microtype",
+            "nowidow",
+            "\\widowpenalty",
+            "\\clubpenalty",
+            "\\tolerance",
+            "\\emergencystretch",
+            "\\raggedbottom",
+        ] {
+            assert!(notes[0].message.contains(name), "{}", notes[0].message);
+        }
+        assert!(!notes[0].message.contains("frenchspacing"));
+    }
+
+    #[test]
+    fn test_inline_math_in_command_arguments() {
+        let input = r"\documentclass{article}
+\begin{document}
+\textbf{the bound \(O(n)\)} and \emph{both $x$ and \(y\)}.
+\begin{description}
+\item[\(\alpha\)-case] first
+\item[$\beta$-case] second
+\end{description}
+\begin{itemize}
+\item[\(\gamma\)] shared
+\item[\(\gamma\)] marker
+\end{itemize}
+\begin{itemize}
+\item[\(\delta\)-point] own
+\item plain
+\end{itemize}
+\begin{enumerate}
+\item[\(\epsilon\))] numbered
+\end{enumerate}
+Text\footnote{A note with \(z^2\) and $w$.}.
+\begin{tabular}{cc}
+\multicolumn{1}{c}{\(p\)-value} & $q$ \\
+\(a\) & \textit{\(b\) and $c$} \\
+\end{tabular}
+\href{https://example.com}{see \(h\)}, \mbox{m \(d\)}, \texttt{code \(k\)}, \textrm{r \(r\)} and $\text{for \(t>0\) and $s$}$.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "*the bound $O(n)$* and _both $x$ and $y$_.",
+            "/ $alpha$-case:  first",
+            "/ $beta$-case:  second",
+            "#set list(marker: [$gamma$])",
+            "/ $delta$-point:  own",
+            "/ $epsilon.alt$):  numbered",
+            "#footnote[A note with $z^(2)$ and $w$.]",
+            "table.cell(align: center)[$p$-value], [$q$],",
+            "[$a$], [_$b$ and $c$_],",
+            "#link(\"https://example.com\")[see $h$]",
+            "#box[m $d$]",
+            "`code `$k$",
+            "r $r$ and",
+            "$\"for \" t > 0 \" and \" s$",
+        ] {
+            assert!(output.contains(expected), "{}\n---\n{}", expected, output);
+        }
+        assert!(!output.contains("\\("), "{}", output);
+    }
+
+    #[test]
+    fn test_framed_boxes_become_blocks() {
+        let input = r"\documentclass{article}
+\usepackage{tcolorbox,mdframed}
+\tcbset{colframe=green}
+\newtcolorbox{mybox}[1]{colback=red!5!white, fonttitle=\bfseries, title=#1}
+\newmdenv[linecolor=blue, skipabove=1em]{note}
+\begin{document}
+\begin{tcolorbox}[title=Theorem 1, colback=blue!5, arc=2mm, boxrule=1pt, coltitle=white]
+Body with \textbf{bold} text.
+\end{tcolorbox}
+\begin{mybox}{My Title}
+Inside.
+\end{mybox}
+\begin{note}
+Framed.
+\end{note}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        for expected in [
+            "#block(width: 100%, fill: color.mix((blue, 5%), (white, 95%)), stroke: 1pt + green, inset: 8pt, radius: 2mm)[\n*Theorem 1* \\\nBody with *bold* text.\n]",
+            "#block(width: 100%, fill: color.mix((red, 5%), (white, 95%)), stroke: 0.5mm + green, inset: 8pt, radius: 4pt)[\n*My Title* \\\nInside.\n]",
+            "#block(width: 100%, stroke: 0.4pt + blue, inset: 8pt)[\nFramed.\n]",
+        ] {
+            assert!(output.contains(expected), "{}\n---\n{}", expected, output);
+        }
+        assert!(!output.contains("colback"), "{}", output);
+
+        let dropped: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.message.contains("framed box options"))
+            .collect();
+        assert_eq!(dropped.len(), 1, "{:?}", result.warnings);
+        assert!(dropped[0].message.ends_with("coltitle, skipabove"));
+    }
+
+    #[test]
+    fn test_sideways_floats_turn_with_their_captions() {
+        let input = r"\documentclass{article}
+\usepackage{rotating}
+\begin{document}
+See \ref{tab:wide}.
+\begin{sidewaystable}
+\centering
+\caption{Wide}
+\label{tab:wide}
+\begin{tabular}{ll}
+a & b \\
+\end{tabular}
+\end{sidewaystable}
+\begin{sidewaysfigure}
+\includegraphics{plot.png}
+\caption{Plot}
+\end{sidewaysfigure}
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert_eq!(
+            output
+                .matches("#rotate(90deg, reflow: true)[\n#figure(")
+                .count(),
+            2,
+            "{}",
+            output
+        );
+        let table = output.find("#rotate").expect("rotated table");
+        let figure = output.rfind("#rotate").expect("rotated figure");
+        let table = &output[table..figure];
+        assert!(table.contains("caption: [Wide]"), "{}", output);
+        assert!(table.contains("] <tab-wide>\n]"), "{}", output);
+        assert!(output[figure..].contains("caption: [Plot]"), "{}", output);
+        assert!(!output.contains("sideways"), "{}", output);
+
+        let options = L2TOptions {
+            sideways_style: SidewaysStyle::FlippedPage,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert_eq!(
+            output.matches("#page(flipped: true)[\n#figure(").count(),
+            2,
+            "{}",
+            output
+        );
+        assert!(!output.contains("#rotate"), "{}", output);
+    }
+
+    #[test]
+    fn test_landscape_pages() {
+        let input = r"\documentclass{article}
+\usepackage{pdflscape}
+\begin{document}
+\begin{landscape}
+\begin{table}
+\begin{tabular}{ll}
+a & b \\
+\end{tabular}
+\caption{Wide}
+\end{table}
+\end{landscape}
+\begin{table}
+\rotatebox{90}{\begin{tabular}{ll} c & d \\ \end{tabular}}
+\caption{Turned}
+\end{table}
+Inline \rotatebox{45}{x}.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert_eq!(
+            output.matches("#page(flipped: true)[").count(),
+            2,
+            "{}",
+            output
+        );
+        assert!(output.contains("caption: [Wide]"), "{}", output);
+        assert!(output.contains("[c], [d]"), "{}", output);
+        assert!(
+            output.contains("#rotate(-45deg, reflow: true)[x]"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_escapes_survive_macro_expansion() {
+        let input = r"\documentclass{article}
+\newcommand{\pct}[1]{#1\%}
+\newcommand{\specials}{\$ \% \& \_ \{ \}}
+\begin{document}
+Text: \pct{20} and \specials
+
+Math: $\newcommand{\set}[1]{\{#1\} \% \_ \$ \&}\set{x}$
+
+\begin{tabular}{ll}
+\pct{5} & \specials \\
+\end{tabular}
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains(r"Text: 20% and \$ % & \_ { }"),
+            "{}",
+            output
+        );
+        assert!(output.contains(r"Math: ${x } % \_ \$ \&$"), "{}", output);
+        assert!(output.contains(r"[5%], [\$ % & \_ { }],"), "{}", output);
+    }
+
+    #[test]
+    fn test_nameref_and_autoref() {
+        use tylax::core::latex2typst::WarningKind;
+        use tylax::features::refs::LabelType;
+
+        let input = r"\documentclass{article}
+\begin{document}
+See \nameref{sec:method}, \autoref{thm:main} and \nameref{fig:plot}.
+\section{The \emph{Method}}\label{sec:method}
+\begin{figure}\caption{Plot}\label{fig:plot}\end{figure}
+\begin{theorem}\label{thm:main} True. \end{theorem}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        assert!(
+            result
+                .output
+                .contains("See #link(<sec-method>)[The _Method_], #link(<thm-main>)[Theorem 1] and @fig-plot."),
+            "{}",
+            result.output
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Approximation && w.message.contains("fig:plot")));
+
+        let labels: Vec<_> = result
+            .labels()
+            .iter()
+            .map(|label| (label.id.as_str(), label.label_type, label.title.as_deref()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("sec:method", LabelType::Section, Some("The _Method_")),
+                ("fig:plot", LabelType::Figure, None),
+                ("thm:main", LabelType::Theorem, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_textblock_to_place() {
+        let input = r"\begin{document}
+\begin{textblock*}{\paperwidth}[1,0](\dimexpr\paperwidth-1cm\relax,1cm)
+\raggedleft
+Logo
+\end{textblock*}
+\begin{textblock*}{3cm}(2cm,0pt)
+Note
+\end{textblock*}
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("#place(top + right, dx: -1cm, dy: 1cm)[\nLogo\n]"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#place(top + left, dx: 2cm)[\nNote\n]"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_verbatim_url_and_code_arguments() {
+        let input = r"\documentclass{article}
+\begin{document}
+See \url{https://example.com/a%20b&c=1#top~x_y} here.\footnote{Mirror: \url{http://x.org/%7Euser/a_b&q=#1}.}
+Also \href{https://e.com/p%20q#s}{the site}, \path{C:/a%b_c}, \verb|50%&#| and \lstinline{x%y}.
+Still here.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains(r#"See #link("https://example.com/a%20b&c=1#top~x_y") here."#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"#footnote[Mirror: #link("http://x.org/%7Euser/a_b&q=#1").]"#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                r#"#link("https://e.com/p%20q#s")[the site], `C:/a%b_c`, `50%&#` and `x%y`."#
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains("Still here."), "{}", output);
+    }
+
+    #[test]
+    fn test_verbatim_bodies_keep_verb_as_written() {
+        let input = r"\documentclass{article}
+\begin{document}
+\begin{verbatim}
+Write \verb|x| or \url{a/b}.
+\end{verbatim}
+\begin{lstlisting}
+\verb+y+
+\end{lstlisting}
+But \verb|z| here.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains(r"Write \verb|x| or \url{a/b}."),
+            "{}",
+            output
+        );
+        assert!(output.contains(r"\verb+y+"), "{}", output);
+        assert!(output.contains("But `z` here."), "{}", output);
+    }
+
+    #[test]
+    fn test_csv_data_tables() {
+        use std::sync::Arc;
+        use tylax::core::latex2typst::{DataTableMode, LatexConverter, WarningKind};
+        use tylax::utils::MemoryFileResolver;
+
+        let input = r"\documentclass{article}
+\begin{document}
+\csvautotabular[separator=semicolon]{data.csv}
+\pgfplotstabletypeset{missing.dat}
+\end{document}";
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("data.csv", "name;value\n\"a; b\";$x^2$\n");
+        let resolver = Arc::new(resolver);
+
+        let mut converter = LatexConverter::new().with_file_resolver(resolver.clone());
+        let result = converter.convert_document_with_diagnostics(input);
+        assert!(
+            result.output.contains(
+                "#table(\n    columns: (auto, auto),\n    align: (left, left),\n    \
+                 table.header([name], [value]),\n    [a; b], [$x^(2)$],\n)"
+            ),
+            "{}",
+            result.output
+        );
+        assert!(
+            result
+                .output
+                .contains("#table(columns: 1, [Missing data file `missing.dat`])"),
+            "{}",
+            result.output
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::FileNotFound && w.message.contains("missing.dat")));
+
+        let mut converter = LatexConverter::with_options(L2TOptions {
+            data_tables: DataTableMode::Load,
+            ..L2TOptions::default()
+        })
+        .with_file_resolver(resolver);
+        let output = converter.convert_document(input);
+        assert!(
+            output.contains("let data = csv(\"data.csv\", delimiter: \";\")"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("table.header(..data.first()),"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_appendix_sections_and_references() {
+        let input = r"\documentclass{article}
+\usepackage{appendix}
+\begin{document}
+\section{Intro}\label{sec:intro}
+See \ref{app:data} and \autoref{app:data}.
+\begin{subappendices}
+\subsection{Proofs}\label{app:proofs}
+\end{subappendices}
+\appendix
+\section{Data}\label{app:data}
+Back to \ref{sec:intro} and \ref{app:proofs}.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("See @app-data and @app-data."),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                "#[\n#counter(heading).update((..n) => n.pos().first())\n\
+                 #set heading(numbering: \"1.A\", supplement: [Appendix])\n\n== Proofs\n<app-proofs>\n]"
+            ),
+            "{}",
+            output
+        );
+        let appendix = output
+            .find("#counter(heading).update(0)\n#set heading(numbering: \"A.1\", supplement: [Appendix])")
+            .expect(&output);
+        let data = output.find("= Data\n<app-data>").expect(&output);
+        assert!(appendix < data, "{}", output);
+        assert!(
+            output.contains("Back to @sec-intro and @app-proofs."),
+            "{}",
+            output
+        );
+
+        let output = latex_document_to_typst(
+            r"\documentclass{article}
+\begin{document}
+\begin{appendices}
+\section{Extra}\label{app:extra}
+\end{appendices}
+\end{document}",
+        );
+        assert!(
+            output.contains("#set heading(numbering: \"A.1\", supplement: [Appendix])\n\n= Extra"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_adjacent_display_equations_stay_separate() {
+        let input = r"\documentclass{article}
+\newcommand{\e}{\epsilon}
+\begin{document}
+\[ a = \e\]\[ b = c \]
+$$ d = \frac12$$$$ e $$
+\[ f = \cdots \]
+\begin{equation} g \end{equation}
+\begin{equation*} h \end{equation*}
+\begin{itemize}
+\item First \[ m = \alpha\]
+\[ n \]
+\end{itemize}
+Text\footnote{See \[ u = \beta\] \[ v \]}.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        for pair in [
+            "$ a = epsilon.alt $\n\n$ b = c $",
+            "$ d = 1/2 $\n\n$ e $",
+            "$ e $\n\n$ f = dots.h.c $",
+            "$ f = dots.h.c $\n\n$ g $",
+            "$ g $\n\n#math.equation(block: true, numbering: none)[\n$ h $\n]",
+            "-  First $ m = alpha $\n\n    $ n $",
+            "#footnote[See $ u = beta $\n\n$ v $]",
+        ] {
+            assert!(output.contains(pair), "missing {:?} in\n{}", pair, output);
+        }
+        assert!(!output.contains("$$"), "{}", output);
+    }
+
+    #[test]
+    fn test_float_specifiers_and_captionof() {
+        let input = r"\documentclass{article}
+\begin{document}
+\begin{figure}[H]
+\includegraphics{a.png}
+\caption{Pinned}
+\end{figure}
+\begin{table}[!t]
+\caption{Top}
+\begin{tabular}{cc}
+a & b \\
+\end{tabular}
+\end{table}
+\begin{figure}[p]
+\includegraphics{c.png}
+\end{figure}
+\begin{center}
+\includegraphics{d.png}
+\captionof{figure}{Side by side}
+\label{fig:d}
+\end{center}
+\begin{center}
+\captionof{table}{Numbers}\label{tab:n}
+\begin{tabular}{cc}
+1 & 2 \\
+\end{tabular}
+\end{center}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        assert!(
+            output.contains("image(\"a.png\"),\n  placement: none,\n  caption: [Pinned],"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#figure(\n  placement: top,\n  caption: [Top],"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("image(\"c.png\"),\n  placement: auto,"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                "#figure(\n  kind: image,\n  caption: [Side by side],\n)[\n#image(\"d.png\")\n] <fig-d>"
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#figure(\n  kind: table,\n  caption: [Numbers],\n)[\n#table("),
+            "{}",
+            output
+        );
+        assert!(output.contains("] <tab-n>"), "{}", output);
+        assert!(!output.contains("captionof"), "{}", output);
+        let placement_notes: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.message.contains("Float specifier"))
+            .collect();
+        assert_eq!(placement_notes.len(), 1, "{:?}", result.warnings);
+        assert!(placement_notes[0].message.contains("`p`"));
+    }
+
+    #[test]
+    fn test_emit_metadata_for_semantic_commands() {
+        use tylax::MetadataCommand;
+
+        let input = r"\documentclass{article}
+\keywords{\emph{deep} learning \sep graphs; proofs}
+\begin{document}
+Body.
+\acks{We thank \textbf{Bob}.}
+\begin{IEEEkeywords}
+alpha
+\end{IEEEkeywords}
+\reviewernote{Check the proof.}
+\end{document}";
+        let mut options = L2TOptions {
+            emit_metadata: true,
+            ..Default::default()
+        };
+        options
+            .metadata_commands
+            .push(MetadataCommand::new("reviewernote", "review"));
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert!(
+            output.contains(
+                "#metadata((keywords: ([_deep_ learning], [graphs], [proofs]))) <keywords>"
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#metadata((acks: [We thank *Bob*.])) <acks>"),
+            "{}",
+            output
+        );
+        assert!(!output.contains(r"/* \acks */"), "{}", output);
+        assert!(
+            output.contains("#metadata((keywords: ([alpha],))) <keywords>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#metadata((review: [Check the proof.])) <review>"),
+            "{}",
+            output
+        );
+
+        // Off by default
+        let output = latex_document_to_typst(input);
+        assert!(!output.contains("#metadata"), "{}", output);
+    }
+
+    #[test]
+    fn test_math_macros_in_tabular_header() {
+        let input = r"\documentclass{article}
+\newcommand{\mse}{MSE$\downarrow$}
+\newcommand{\acc}{Acc.$\uparrow$}
+\newcommand{\rel}{$\begin{aligned} a &= b \\ c &= d \end{aligned}$}
+\begin{document}
+\begin{tabular}{lll}
+\mse & \acc & \rel \\
+1.0 & 2.0 & 3.0 \\
+\end{tabular}
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("[MSE$arrow.b$], [Acc.$arrow.t$], [$"),
+            "{}",
+            output
+        );
+        // The `&` and `\\` of the aligned block stay inside the third cell
+        assert!(output.contains(r"a & = b \ c & = d$],"), "{}", output);
+        assert!(output.contains("[1.0], [2.0], [3.0],"), "{}", output);
+    }
+
+    #[test]
+    fn test_crlf_cr_and_mixed_line_endings() {
+        let lines = [
+            r"\documentclass{article}",
+            r"\begin{document}",
+            r"\begin{tabular}{cc}",
+            r"a & b \\",
+            r"\end{tabular}",
+            r"\begin{lstlisting}",
+            r"x = 1",
+            r"\end{lstlisting}",
+            r"End",
+            r"\end{document}",
+        ];
+        let expected = latex_document_to_typst(&lines.join("\n"));
+        let mixed: String = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}", line, ["\r\n", "\r", "\n"][i % 3]))
+            .collect();
+        for input in [lines.join("\r\n"), lines.join("\r"), mixed] {
+            let output = latex_document_to_typst(&input);
+            assert!(!output.contains('\r'), "{:?}", output);
+            assert_eq!(output, expected);
+        }
+        assert!(
+            expected.lines().all(|line| line == line.trim_end()),
+            "{:?}",
+            expected
+        );
+
+        let options = L2TOptions {
+            line_ending: LineEnding::Preserve,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(&lines.join("\r\n"), &options);
+        assert_eq!(output.replace("\r\n", "\n"), expected);
+        assert_eq!(output.matches('\n').count(), output.matches("\r\n").count());
+    }
+
+    #[test]
+    fn test_preamble_definition_guards() {
+        let input = r"\documentclass{article}
+\makeatletter
+\@ifundefined{chapter}{\newcommand{\partname}{Section}}{\newcommand{\partname}{Chapter}}
+\makeatother
+\ifdefined\pdfoutput
+  \ifnum\pdfoutput>0 \usepackage[pdftex]{graphicx}\fi
+\else
+  \usepackage{graphicx}
+\fi
+\ifcsname R\endcsname\else\newcommand{\R}{\mathbb{R}}\fi
+\begin{document}
+See the \partname{} on $\R$.
+
+Closing paragraph.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("See the Section on $RR$."), "{}", output);
+        // The paragraph break after the guarded preamble survives
+        assert!(output.contains("$RR$.\n\n"), "{}", output);
+        assert!(!output.contains("ifdefined"), "{}", output);
+        assert!(!output.contains("pdfoutput"), "{}", output);
+    }
+
+    #[test]
+    fn test_standalone_tikz_inputs_become_cetz_figures() {
+        use std::sync::Arc;
+        use tylax::core::latex2typst::WarningKind;
+        use tylax::utils::MemoryFileResolver;
+
+        let picture = r"\begin{tikzpicture}
+\draw (0,0) -- (1,1);
+\end{tikzpicture}";
+        let standalone = format!(
+            "\\documentclass[tikz]{{standalone}}\n\\begin{{document}}\n{}\n\\end{{document}}",
+            picture
+        );
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("fig.tikz", &standalone);
+        resolver.add_file("diagram.tikz", picture);
+        resolver.add_file(
+            "plot.tex",
+            r"\documentclass{standalone}\begin{document}$x^2$\end{document}",
+        );
+        resolver.add_file("built.tex", &standalone);
+
+        let input = r"\documentclass{article}
+\begin{document}
+\input{fig.tikz}
+\begin{figure}
+\input{diagram}
+\caption{Inline}
+\end{figure}
+\input{plot}
+\input{chapter}
+\includegraphics{built.pdf}
+\end{document}";
+        let mut converter = LatexConverter::new().with_file_resolver(Arc::new(resolver));
+        let result = converter.convert_document_with_diagnostics(input);
+        let output = &result.output;
+        assert!(
+            output.contains("#figure[\n#canvas({\n  import draw: *\n\n  line((0, 0), (1, 1))"),
+            "{}",
+            output
+        );
+        // Inside a figure environment the canvas is the figure body
+        assert_eq!(
+            output.matches("line((0, 0), (1, 1))").count(),
+            3,
+            "{}",
+            output
+        );
+        assert_eq!(output.matches("#figure[").count(), 1, "{}", output);
+        assert!(output.contains("#image(\"plot.pdf\")"), "{}", output);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| { w.kind == WarningKind::Approximation && w.message.contains("plot.pdf") }));
+        assert!(output.contains("/* \\input{chapter} */"), "{}", output);
+        assert!(!output.contains("built.pdf"), "{}", output);
+
+        // Without a resolver a .tikz input keeps its pre-built PDF
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("#image(\"fig.pdf\")"), "{}", output);
+
+        // A standalone main document is converted without page setup
+        let output = latex_document_to_typst(&standalone);
+        assert!(!output.contains("#set page"), "{}", output);
+        assert!(!output.contains("#set heading"), "{}", output);
+        assert!(output.contains("line((0, 0), (1, 1))"), "{}", output);
+    }
+
+    #[test]
+    fn test_table_spacing_parameters_become_inset() {
+        let input = r"\documentclass{article}
+\renewcommand{\arraystretch}{1.2}
+\begin{document}
+\begin{tabular}{cc}
+a & b \\
+\end{tabular}
+
+{\renewcommand{\arraystretch}{1.5}
+\setlength{\tabcolsep}{10pt}
+\begin{tabular}{cc}
+c & d \\
+\end{tabular}}
+
+\begin{table}
+\def\arraystretch{2}
+\begin{tabular}{cc}
+e & f \\
+\end{tabular}
+\end{table}
+
+\begin{tabular}{cc}
+g & h \\
+\end{tabular}
+\end{document}";
+        let output = latex_document_to_typst(input);
+        let insets: Vec<&str> = output
+            .lines()
+            .filter(|line| line.trim_start().starts_with("inset:"))
+            .map(str::trim)
+            .collect();
+        // Group-local changes end with their group, the preamble value stays
+        assert_eq!(
+            insets,
+            vec![
+                "inset: (y: 6pt),",
+                "inset: (x: 10pt, y: 7.5pt),",
+                "inset: (y: 10pt),",
+                "inset: (y: 6pt),",
+            ],
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_math_passthrough_keeps_latex_for_mitex() {
+        let input = r"\documentclass{article}
+\newcommand{\R}{\mathbb{R}}
+\begin{document}
+Let $x \in \R$ and \(y`\) be given.
+\begin{equation}\label{eq:one}
+  E = mc^2
+\end{equation}
+\begin{align*}
+  a &= b \\ c &= d \nonumber
+\end{align*}
+See \eqref{eq:one}.\footnote{Note with $f(x)$.}
+\begin{figure}
+\caption{Plot of $\sin x$}
+\end{figure}
+\begin{tabular}{cc}
+$\alpha$ & b \\
+\end{tabular}
+\end{document}";
+        let options = L2TOptions {
+            math_passthrough: true,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert_eq!(
+            output
+                .matches("#import \"@preview/mitex:0.2.5\": mi, mitex")
+                .count(),
+            1,
+            "{}",
+            output
+        );
+        // Macros are expanded before the math is embedded
+        assert!(output.contains("#mi(`x \\in \\mathbb{R}`)"), "{}", output);
+        assert!(output.contains("#mi(\"y`\")"), "{}", output);
+        assert!(
+            output.contains("#mitex(numbering: \"(1)\", `E = mc^2`) <eq-one>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#mitex(`\\begin{aligned}\na &= b \\\\ c &= d\n\\end{aligned}`)"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#footnote[Note with #mi(`f(x)`).]"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("caption: [Plot of #mi(`\\sin x`)]"),
+            "{}",
+            output
+        );
+        assert!(output.contains("[#mi(`\\alpha`)], [b],"), "{}", output);
+
+        let output = latex_document_to_typst(input);
+        assert!(!output.contains("mitex"), "{}", output);
+    }
+
+    #[test]
+    fn test_stacked_cells_keep_their_line_breaks() {
+        let input = "\\begin{tabular}{lp{3cm}c}\n\\shortstack{line1 \\\\ line2} & a\\newline b & \\makecell[l]{x \\\\ y} \\\\\n\\makecell{p \\\\ q} & \\pbox{3cm}{u \\\\ v} & z \\\\\n\\end{tabular}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("[line1 \\ line2], [a\\  b], table.cell(align: left)[x \\ y],"),
+            "{}",
+            output
+        );
+        assert!(output.contains("[p \\ q], [u \\ v], [z],"), "{}", output);
+        assert!(!output.contains("3cmu"), "{}", output);
+    }
+
+    #[test]
+    fn test_todo_notes_and_margin_notes() {
+        let input = "\\documentclass{article}\n\\usepackage{todonotes}\n\\begin{document}\nText\\todo{fix this} more.\\todo[color=red!20]{check}\n\\todo[inline]{Rewrite}\nHere\\marginnote{side}.\n\\missingfigure[figwidth=6cm]{A plot}\n\\listoftodos\n\\end{document}";
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("Text#highlight(fill: orange)[TODO: fix this] more."),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#highlight(fill: color.mix((red, 20%), (white, 80%)))[TODO: check]"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#block(fill: orange, inset: 6pt, width: 100%)[TODO: Rewrite]"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#margin-note(stroke: none)[side]"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#figure(rect(width: 6cm, height: 4cm, fill: luma(230))"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("listoftodos"), "{}", output);
+
+        let options = L2TOptions {
+            todo_style: TodoStyle::Margin,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert!(
+            output.contains("#import \"@preview/drafting:0.2.2\": inline-note, margin-note"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("Text#margin-note[fix this] more."),
+            "{}",
+            output
+        );
+        assert!(output.contains("#inline-note[Rewrite]"), "{}", output);
+    }
+
+    #[test]
+    fn test_list_items_stay_on_consecutive_lines() {
+        let input = "\\begin{enumerate}\n\\item one\n% comment\n\n\\item two\n\\par\n\\item three\n\\vspace{1em}\n\\item four\n\nmore on four\n\\item five\n\\begin{itemize}\n\n\\item inner\n\\end{itemize}\n\\end{enumerate}";
+        let result = latex_document_to_typst(input);
+        // One list; the second paragraph of an item is indented past its marker
+        assert!(
+            result.contains("one\n  +  two\n  +  three #v(1em)\n  +  four\n\n     more on four\n  +  five\n    -  inner"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_array_column_material_and_newcolumntype() {
+        let input = r"\documentclass{article}
+\usepackage{array}
+\newcolumntype{R}{>{\raggedleft\arraybackslash}p{2cm}}
+\newcolumntype{P}[1]{>{\centering\arraybackslash}p{#1}}
+\begin{document}
+\begin{tabular}{R c <{\,\%} >{\bfseries}l *{2}{P{1cm}}}
+a & 5 & x & p & q \\
+\end{tabular}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        assert!(!output.contains("newcolumntype"), "Got: {}", output);
+        assert!(
+            output.contains("align: (right, center, left, center, center)"),
+            "Got: {}",
+            output
+        );
+        assert!(
+            output.contains("[a], [5 %], [#strong[x]], [p], [q]"),
+            "Got: {}",
+            output
+        );
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        // Unknown declarations are skipped with a warning, leaving the spec intact
+        let input = "\\begin{tabular}{>{\\color{red}\\bfseries}c l}\na & b \\\\\n\\end{tabular}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        assert!(
+            result.output.contains("[#strong[a]], [b]"),
+            "Got: {}",
+            result.output
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.message.contains("\\color")));
+    }
+
+    #[test]
+    fn test_preamble_setup_stays_out_of_body() {
+        let input = r"\documentclass[11pt,a4paper]{article}
+\usepackage[utf8]{inputenc}
+\usepackage[T1]{fontenc}
+\usepackage{lmodern}
+\usepackage{amsmath,amssymb,amsthm}
+\usepackage{graphicx}
+\usepackage{xcolor}
+\usepackage[margin=2.5cm]{geometry}
+\usepackage{booktabs}
+\usepackage{microtype}
+\usepackage{xspace}
+\usepackage{caption}
+\usepackage{tikz}
+\usepackage{pgfplots}
+\usepackage{hyperref}
+\hypersetup{
+  colorlinks=true,
+  linkcolor=blue!50!black,
+  citecolor=green!40!black,
+  urlcolor=magenta,
+  pdftitle={A Study of Things},
+  pdfauthor={Jane Doe},
+  pdfkeywords={alpha, beta}
+}
+\DeclareUnicodeCharacter{00A0}{~}
+\DeclareUnicodeCharacter{2212}{-}
+\pdfstringdefDisableCommands{\def\eqref#1{(\ref{#1})}}
+\hbadness=10000
+\vbadness=10000
+\hfuzz=2pt
+\tolerance=1000
+\emergencystretch=3em
+\widowpenalty=10000
+\clubpenalty=10000
+\setlength{\parindent}{0pt}
+\setlength{\parskip}{6pt}
+\renewcommand{\baselinestretch}{1.1}
+\newcommand{\R}{\mathbb{R}}
+\DeclareMathOperator{\tr}{tr}
+\newtheorem{theorem}{Theorem}
+\theoremstyle{definition}
+\newtheorem{definition}{Definition}
+\graphicspath{{figures/}}
+\numberwithin{equation}{section}
+\allowdisplaybreaks
+\frenchspacing
+\sloppy
+\makeatletter
+\def\@seccntformat#1{\csname the#1\endcsname.\quad}
+\makeatother
+\urlstyle{same}
+\captionsetup{font=small}
+\definecolor{myblue}{RGB}{0,80,160}
+\pgfplotsset{compat=1.18}
+\usetikzlibrary{arrows.meta}
+\AtBeginDocument{\renewcommand{\abstractname}{Summary}}
+\title{A Study of Things}
+\author{Jane Doe}
+\date{\today}
+\begin{document}
+\section{Intro}
+Hello $\R$.
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        let (head, body) = output.split_once("= Intro").expect("heading converted");
+        assert!(body.contains("Hello $RR$."), "Got: {}", output);
+        // Only document settings come before the first heading
+        let stray: Vec<_> = head
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter(|line| !line.ends_with(',') && *line != ")")
+            .collect();
+        assert!(stray.is_empty(), "{:?} in:\n{}", stray, output);
+        assert!(!head.contains("/*"), "Got: {}", output);
+        assert!(
+            output.contains("title: \"A Study of Things\""),
+            "Got: {}",
+            output
+        );
+
+        let dropped: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == tylax::WarningKind::PreambleDropped)
+            .filter_map(|w| w.location.as_deref())
+            .collect();
+        for name in [
+            "\\DeclareUnicodeCharacter",
+            "\\pdfstringdefDisableCommands",
+            "\\hbadness",
+        ] {
+            assert!(dropped.contains(&name), "{} not in {:?}", name, dropped);
+        }
+    }
+
+    #[test]
+    fn test_command_names_print_as_written() {
+        let input = r"\documentclass{article}
+\newcommand{\cs}[1]{\texttt{\string#1}}
+\begin{document}
+\section{Structure}
+Start a section with \cs\section{} and a list with \verb|\begin{itemize}|;
+each entry is an \texttt{\string\item}. Write \string\emph{} for emphasis
+and \detokenize{\textbf{bold} $x$} for bold text.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "= Structure",
+            "Start a section with `\\section` and a list with `\\begin{itemize}`;",
+            "each entry is an `\\item`.",
+            "Write \\\\emph for emphasis",
+            "and \\\\textbf{bold} \\$x\\$ for bold text.",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+    }
+
+    #[test]
+    fn test_content_after_end_document() {
+        let input = r"\documentclass{article}
+\begin{document}
+Body text.
+\end{document}
+Scratch notes with \textbf{todo} */ items.
+\section{Old draft}
+";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        assert!(result.output.contains("Body text."), "{}", result.output);
+        assert!(!result.output.contains("Scratch"), "{}", result.output);
+        assert!(!result.output.contains("Old draft"), "{}", result.output);
+        assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+        assert_eq!(
+            result.warnings[0].kind,
+            tylax::WarningKind::DocumentStructure
+        );
+        assert!(result.warnings[0].message.contains("dropped"));
+
+        let options = L2TOptions {
+            keep_trailing_content: true,
+            ..Default::default()
+        };
+        let result = tylax::latex_to_typst_with_diagnostics_options(input, options);
+        assert!(
+            result.output.ends_with(
+                "Body text.\n\n/* After \\end{document}:\nScratch notes with \\textbf{todo} * / items.\n\\section{Old draft}\n*/\n"
+            ),
+            "{}",
+            result.output
+        );
+        assert!(result.warnings[0].message.contains("kept as a comment"));
+
+        // Blank lines and comments after the end are not worth a note
+        let result = tylax::latex_to_typst_with_diagnostics(
+            "\\documentclass{article}\n\\begin{document}\nBody.\n\\end{document}\n\n% done\n",
+        );
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_missing_begin_document_converts_body() {
+        let input = r"\documentclass{article}
+\usepackage{amsmath}
+\hypersetup{
+  pdftitle={Notes},
+}
+\newcommand{\R}{\mathbb{R}}
+\title{Notes}
+\section{Intro}
+Let $x \in \R$.
+";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        let (head, body) = output.split_once("= Intro").expect("heading converted");
+        assert!(body.contains("Let $x in RR$."), "{}", output);
+        assert!(!head.contains("/*"), "{}", output);
+        assert!(!head.contains("amsmath"), "{}", output);
+        assert!(output.contains("title: \"Notes\""), "{}", output);
+        let notes: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == tylax::WarningKind::DocumentStructure)
+            .collect();
+        assert_eq!(notes.len(), 1, "{:?}", result.warnings);
+        assert!(notes[0].message.contains("line 8"), "{:?}", notes);
+    }
+
+    #[test]
+    fn test_emphasis_toggles_italics() {
+        let convert = |body: &str| {
+            latex_document_to_typst(&format!(
+                "\\documentclass{{article}}\n\\begin{{document}}\n{}\n\\end{{document}}",
+                body
+            ))
+        };
+
+        // Emphasis inside italic text turns upright, as in LaTeX
+        let result = convert(r"\textit{foo \emph{bar} baz}");
+        assert!(result.contains("_foo #emph[bar] baz_"), "Got: {}", result);
+
+        // Three levels: italic, upright, italic again
+        let result = convert(r"\emph{a \emph{b \emph{c} d} e}");
+        assert!(
+            result.contains("_a #emph[b #emph[c] d] e_"),
+            "Got: {}",
+            result
+        );
+
+        // `\textit` never toggles, and math stays inside the emphasis
+        let result = convert(r"\emph{x \textit{y} $z^2$}");
+        assert!(result.contains("_x y $z^(2)$_"), "Got: {}", result);
+    }
+
+    #[test]
+    fn test_dates_follow_babel_language() {
+        let result = latex_document_to_typst(
+            r"\documentclass{article}
+\usepackage[english,ngerman]{babel}
+\title{T}
+\date{\today}
+\begin{document}
+\maketitle
+Stand: \today.
+\end{document}",
+        );
+        assert!(
+            result.contains("#set text(lang: \"de\")"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("  date: auto,\n"), "got: {}", result);
+        assert!(
+            result.contains("Stand: #{ str(datetime.today().day()) + \". \" + (\"Januar\""),
+            "got: {}",
+            result
+        );
+
+        let result = latex_document_to_typst(
+            "\\documentclass{article}\n\\title{T}\n\\date{March 2024}\n\\begin{document}\n\\maketitle\nOn \\today.\n\\end{document}",
+        );
+        assert!(
+            result.contains("  date: datetime(year: 2024, month: 3, day: 1),\n"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("\n  March 2024\n"), "got: {}", result);
+        assert!(
+            result.contains(
+                "On #datetime.today().display(\"[month repr:long] [day padding:none], [year]\")."
+            ),
+            "got: {}",
+            result
+        );
+
+        let result = latex_document_to_typst(
+            "\\documentclass{article}\n\\title{T}\n\\author{A}\n\\date{}\n\\begin{document}\n\\maketitle\n\\end{document}",
+        );
+        assert!(result.contains("  date: none,\n"), "got: {}", result);
+        assert!(result.contains("[A]\n]"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_picture_environments_become_placeholders() {
+        use tylax::core::latex2typst::latex_to_typst_with_diagnostics_options;
+
+        let input = r"\documentclass{article}
+\begin{document}
+\setlength{\unitlength}{1mm}
+\begin{figure}[h]
+\begin{picture}(60,40)(0,0)
+\put(10,10){\line(1,0){40}}
+\end{picture}
+\caption{A picture}
+\label{fig:pic}
+\end{figure}
+\begin{pspicture}(-1,-1)(3,2)
+\psline(0,0)(2,1)
+\end{pspicture}
+\end{document}";
+
+        let result = latex_to_typst_with_diagnostics_options(input, L2TOptions::default());
+        let output = &result.output;
+        assert!(
+            output.contains(
+                "#rect(width: 60mm, height: 40mm, stroke: (dash: \"dashed\"))[Unconverted picture]\n```latex\n\\begin{picture}(60,40)(0,0)\n\\put(10,10){\\line(1,0){40}}\n\\end{picture}\n```"
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output
+                .contains("kind: image,\n  placement: auto,\n  caption: [A picture],\n) <fig-pic>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#rect(width: 4cm, height: 3cm"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("psline */"), "{}", output);
+
+        let warnings: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == tylax::WarningKind::UnknownEnvironment)
+            .collect();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].message.contains("picture (60mm x 40mm)"));
+        assert_eq!(
+            warnings[1].location.as_deref(),
+            Some("\\begin{pspicture} (line 11)")
+        );
+    }
+
+    #[test]
+    fn test_double_rules_and_rule_widths_in_tables() {
+        let input = r"\documentclass{article}
+\begin{document}
+\setlength{\arrayrulewidth}{1.5pt}
+\begin{tabular}{l!{\vrule width 2pt}c|r}
+\hline
+A & B & C \\
+\hline\hline
+1 & 2 & 3 \\
+\hline
+\end{tabular}
+\end{document}";
+
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("    stroke: 1.5pt,\n"), "{}", output);
+        assert!(
+            output.contains("table.vline(x: 1, stroke: 2pt),"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("table.hline(stroke: 3pt),\n    [1], [2], [3],"),
+            "{}",
+            output
+        );
+        assert_eq!(output.matches("table.hline(").count(), 3, "{}", output);
+    }
+
+    #[test]
+    fn test_page_flow_commands() {
+        use tylax::core::latex2typst::latex_to_typst_with_diagnostics_options;
+
+        let input = r"\documentclass{article}
+\begin{document}
+A \newpage B \clearpage C \pagebreak[2] D \pagebreak E
+\nopagebreak
+\FloatBarrier
+\begin{samepage}
+Kept together
+\end{samepage}
+\end{document}";
+
+        let result = latex_to_typst_with_diagnostics_options(input, L2TOptions::default());
+        let output = &result.output;
+        assert_eq!(output.matches("#pagebreak()\n").count(), 3, "{}", output);
+        assert!(output.contains("#pagebreak(weak: true)\n D"), "{}", output);
+        assert!(output.contains("// \\FloatBarrier\n"), "{}", output);
+        assert!(
+            output.contains("#block(breakable: false)[\n Kept together\n]"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("nopagebreak"), "{}", output);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == tylax::WarningKind::PageFlow));
+
+        let options = L2TOptions {
+            float_barrier_pagebreak: true,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert_eq!(
+            output.matches("#pagebreak(weak: true)\n").count(),
+            2,
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_convert_fragment_matches_document_output() {
+        let tabular = "\\begin{tabular}{cc}\na & b \\\\\n\\R & d\n\\end{tabular}";
+        let macros = "\\newcommand{\\R}{\\mathbb{R}}";
+        let mut converter = LatexConverter::new();
+        let fragment =
+            converter.convert_fragment(tabular, FragmentContext::text().with_macros(macros));
+        assert!(fragment.starts_with("#table("), "{}", fragment);
+        assert!(fragment.contains("[RR], [d],"), "{}", fragment);
+        assert!(!fragment.contains("#set"), "{}", fragment);
+
+        let document = latex_document_to_typst(&format!(
+            "\\documentclass{{article}}\n{}\n\\begin{{document}}\n{}\n\\end{{document}}",
+            macros, tabular
+        ));
+        assert!(
+            document.contains(&fragment),
+            "{}\n---\n{}",
+            fragment,
+            document
+        );
+
+        let mut converter = LatexConverter::new();
+        let math = converter.convert_fragment(
+            "\\R^n + \\frac12",
+            FragmentContext::math().with_macros(macros),
+        );
+        assert_eq!(math, "RR^(n) + 1/2");
+    }
+
+    #[test]
+    fn test_section_titles_with_fragile_commands() {
+        let input = r"\documentclass{article}
+\begin{document}
+\section{\texorpdfstring{$\alpha$-decay}{alpha-decay}}
+\subsection{A \protect\footnote{x} title}
+\section[Short]{Long\\ title}
+\end{document}";
+
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("// Bookmark: alpha-decay\n"), "{}", output);
+        assert!(output.contains(" $alpha$-decay\n"), "{}", output);
+        assert!(!output.contains("texorpdfstring"), "{}", output);
+        assert!(output.contains(" A #footnote[x] title\n"), "{}", output);
+        assert!(!output.contains("protect"), "{}", output);
+        assert!(output.contains(" Long\\ title\n"), "{}", output);
+        assert!(!output.contains("Short"), "{}", output);
+    }
+
+    #[test]
+    fn test_wrapfigure_conversion() {
+        let input = r"\documentclass{article}
+\begin{document}
+\begin{wrapfigure}{l}{0.4\textwidth}
+\includegraphics{plot.png}
+\caption{A plot}
+\label{fig:plot}
+\end{wrapfigure}
+First paragraph wraps
+around the figure.
+
+Second paragraph.
+\end{document}";
+
+        let output = latex_document_to_typst(input);
+        assert!(
+            output.contains("#align(left, block(width: 40%)[#figure("),
+            "{}",
+            output
+        );
+        assert!(output.contains("caption: [A plot]"), "{}", output);
+        assert!(output.contains(") <fig-plot>])"), "{}", output);
+
+        let options = L2TOptions {
+            enable_wrap_package: true,
+            ..Default::default()
+        };
+        let output = latex_document_to_typst_with_options(input, &options);
+        assert!(
+            output.contains("#import \"@preview/wrap-it:0.1.1\": wrap-content"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("#wrap-content(\n  block(width: 40%)[#figure("),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("  align: left,\n)[\nFirst paragraph wraps around the figure.\n]\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("Second paragraph."), "{}", output);
+    }
+
+    #[test]
+    fn test_graphicspath_and_asset_manifest() {
+        use std::sync::Arc;
+        use tylax::core::latex2typst::{LatexConverter, WarningKind};
+        use tylax::utils::MemoryFileResolver;
+
+        let input = r"\documentclass{article}
+\graphicspath{{figs/}{imgs/}}
+\begin{document}
+\includegraphics[width=3cm]{plot}
+\includegraphics{diagram.eps}
+\includegraphics{plot}
+\end{document}";
+
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("imgs/plot.png", "");
+        let mut converter = LatexConverter::new().with_file_resolver(Arc::new(resolver));
+        let result = converter.convert_document_with_diagnostics(input);
+        assert!(
+            result
+                .output
+                .contains("#image(\"imgs/plot.png\", width: 3cm)"),
+            "{}",
+            result.output
+        );
+
+        let manifest = result.asset_manifest();
+        assert_eq!(manifest.len(), 2, "{:?}", manifest);
+        assert_eq!(manifest[0].source, "plot");
+        assert_eq!(manifest[0].path, "imgs/plot.png");
+        assert!(manifest[0].found);
+        assert_eq!(manifest[1].path, "diagram.eps");
+        assert!(!manifest[1].found);
+        assert!(result.warnings.iter().any(|w| {
+            w.kind == WarningKind::UnsupportedImageFormat && w.message.contains("diagram.eps")
+        }));
+
+        // Without a resolver nothing can be checked, so the path is kept
+        let output = latex_document_to_typst(input);
+        assert!(output.contains("#image(\"plot\""), "{}", output);
+        assert!(!output.contains("plot.pdf"), "{}", output);
+    }
+
+    #[test]
+    fn test_input_files_join_the_enclosing_environment() {
+        use std::sync::Arc;
+        use tylax::utils::MemoryFileResolver;
+
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("rows.tex", "1 & 2 \\\\\n3 & 4 \\\\ % last row\n");
+        resolver.add_file("close.tex", "5 & 6 \\\\\n\\end{tabular}\n");
+        resolver.add_file("title.tex", "Waves on \\emph{water}\n");
+        resolver.add_file("eqs.tex", "a &= b \\\\\nc &= d\n");
+        let input = r"\documentclass{article}
+\title{\input{title}}
+\begin{document}
+\maketitle
+\begin{tabular}{cc}
+\input{rows}
+\end{tabular}
+
+\begin{tabular}{ll}
+x & y \\
+\input{close}
+
+\begin{align*}
+\input{eqs}
+\end{align*}
+
+About \input{title}.
+% \input{rows}
+\end{document}";
+        let mut converter = LatexConverter::new().with_file_resolver(Arc::new(resolver));
+        let output = converter.convert_document(input);
+        for expected in [
+            "[1], [2],\n    [3], [4],\n)",
+            "[x], [y],\n    [5], [6],\n)",
+            "$ a & = b \\ c & = d $",
+            "weight: \"bold\")[Waves on _water_]",
+            "About Waves on _water_.",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        assert!(!output.contains("input"), "{}", output);
+        assert_eq!(output.matches("[1]").count(), 1, "{}", output);
+    }
+
+    #[test]
+    fn test_input_files_outside_verbatim_and_after_escaped_percent() {
+        use std::sync::Arc;
+        use tylax::utils::MemoryFileResolver;
+
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("rows.tex", "Inlined rows\n");
+        let input = r"\documentclass{article}
+\begin{document}
+50\% done \input{rows}
+
+\begin{verbatim}
+\input{rows}
+\end{verbatim}
+
+Type \verb|\input{rows}| to include it.
+\end{document}";
+        let mut converter = LatexConverter::new().with_file_resolver(Arc::new(resolver));
+        let output = converter.convert_document(input);
+        assert!(output.contains("50% done Inlined rows"), "{}", output);
+        assert_eq!(output.matches("Inlined rows").count(), 1, "{}", output);
+        assert!(output.contains("```\n\\input{rows}\n```"), "{}", output);
+        assert!(output.contains("`\\input{rows}`"), "{}", output);
+    }
+
+    #[test]
+    fn test_filecontents_become_embedded_files() {
+        let input = r"\begin{filecontents*}{refs.bib}
+@article{knuth84,
+  author = {Donald E. Knuth},
+  title = {Literate Programming},
+  journal = {The Computer Journal},
+  year = {1984}
+}
+\end{filecontents*}
+\begin{filecontents}[overwrite]{intro.tex}
+\section{Intro}
+As shown by \cite{knuth84}.
+\end{filecontents}
+\begin{filecontents}{data.csv}
+a,b
+1,2
+\end{filecontents}
+\documentclass{article}
+\usepackage{csvsimple}
+\begin{document}
+\input{intro}
+\csvautotabular{data.csv}
+\bibliographystyle{plain}
+\bibliography{refs}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        for expected in [
+            "= Intro\n As shown by #cite(<knuth84>).",
+            "table.header([a], [b]),\n    [1], [2],",
+            "#bibliography(\"refs.bib\")",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        for leaked in ["@article", "Literate", "overwrite", "plain"] {
+            assert!(!output.contains(leaked), "{:?} in:\n{}", leaked, output);
+        }
+        let files = result.companion_files();
+        assert_eq!(files.len(), 1, "{:?}", files);
+        assert_eq!(files[0].path, "refs.bib");
+        assert!(files[0].contents.starts_with("@article{knuth84,\n"));
+
+        let options = L2TOptions {
+            hayagriva_bibliography: true,
+            ..Default::default()
+        };
+        let result = tylax::latex_to_typst_with_diagnostics_options(input, options);
+        assert!(
+            result.output.contains("#bibliography(\"refs.yml\")"),
+            "{}",
+            result.output
+        );
+        let files = result.companion_files();
+        assert_eq!(files[0].path, "refs.yml");
+        assert!(
+            files[0].contents.starts_with("knuth84:\n  type: article\n"),
+            "{}",
+            files[0].contents
+        );
+        assert!(files[0].contents.contains("Literate Programming"));
+
+        // A bibliography that is not embedded is left to the file system
+        let output = latex_document_to_typst(
+            "\\documentclass{article}\n\\begin{document}\nText.\n\\bibliography{a,b}\n\\end{document}",
+        );
+        assert!(
+            output.contains("#bibliography((\"a.bib\", \"b.bib\"))"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_labels_follow_headings_with_math() {
+        let input = r"\begin{document}
+\section{The $O(n \log n)$ algorithm}\label{sec:nlogn}
+\section{Ends with \(x^2\)\label{sec:end}}
+\section{$\alpha + \beta$\label{sec:all}}
+\section*{Star $x$\label{sec:star}}
+See \nameref{sec:nlogn}, \nameref{sec:end} and \nameref{sec:all}.
+\end{document}";
+        let output = latex_document_to_typst(input);
+        for expected in [
+            "= The $O(n log n)$ algorithm\n<sec-nlogn>",
+            "= Ends with $x^(2)$ <sec-end>\n",
+            "= $alpha + beta$ <sec-all>\n",
+            "[Star $x$] <sec-star>\n",
+            "#link(<sec-end>)[Ends with $x^(2)$]",
+            "#link(<sec-all>)[$alpha + beta$]",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        assert!(!output.contains("$<"), "{}", output);
+    }
+
+    #[test]
+    fn test_unnumbered_headings_and_outline_entries() {
+        let input = r"\documentclass{article}
+\usepackage{hyperref}
+\begin{document}
+\section{Intro}
+See \hyperlink{start}{the start}.
+\section*{Acknowledgments}
+\addcontentsline{toc}{section}{Acknowledgments}
+Thanks.
+\section*{Notation}
+Symbols.
+\phantomsection\label{sec:refs}
+\addcontentsline{toc}{section}{References}
+\addcontentsline{lof}{figure}{Extra}
+\pdfbookmark[2]{Details}{details}
+\hypertarget{start}{Start}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        for expected in [
+            "= Intro\n",
+            "See #link(<start>)[the start].",
+            "#heading(level: 1, numbering: none, outlined: true)[Acknowledgments]\n",
+            "#heading(level: 1, numbering: none, outlined: false)[Notation]\n",
+            "#metadata(none) <sec-refs>",
+            "#place(hide[#heading(level: 1, numbering: none)[References]])",
+            "#place(hide[#heading(level: 2, numbering: none, outlined: false, bookmarked: true)[Details]]) <details>",
+            "#metadata(none) <start>Start",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        for leaked in ["\\*", "toc", "Extra", "addcontentsline"] {
+            assert!(!output.contains(leaked), "{:?} in:\n{}", leaked, output);
+        }
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.kind == tylax::WarningKind::Approximation
+                    && w.message.contains("'lof'")),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_display_math_stays_in_its_container() {
+        let input = r"\documentclass{article}
+\usepackage{amsmath}
+\begin{document}
+\begin{minipage}[t]{0.5\textwidth}
+Before.
+\begin{align}
+a &= b \label{eq:ab}
+\end{align}
+After.
+\end{minipage}
+
+\begin{figure}
+\begin{minipage}{0.45\linewidth}
+\begin{equation*} x = 1 \end{equation*}
+\caption{Left}
+\end{minipage}
+\caption{Panels \begin{equation}\label{eq:p} p = q \end{equation} shown.}
+\end{figure}
+
+\begin{tabular}{p{3cm}c}
+Cell \begin{equation} e = mc^2 \end{equation} & b \\
+\end{tabular}
+\end{document}";
+        let result = tylax::latex_to_typst_with_diagnostics(input);
+        let output = &result.output;
+        for expected in [
+            "#block(width: 50%)[\n Before. $ a & = b $ <eq-ab>\n After.\n]",
+            "  [\n#block(width: 45%)[\n #math.equation(block: true, numbering: none)[\n$ x = 1 $\n]\n\n#align(center)[Left]\n]\n  ],",
+            "caption: [Panels $p = q$ shown.],",
+            "[Cell $ e = m c^(2) $], [b],",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        assert!(!output.contains("textwidth"), "{}", output);
+        assert!(
+            result.warnings.iter().any(|w| {
+                w.kind == tylax::WarningKind::Approximation
+                    && w.location.as_deref() == Some("\\begin{equation}")
+            }),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_math_macros_kept_as_let() {
+        let input = r"\documentclass{article}
+\newcommand{\E}{\mathbb{E}}
+\newcommand{\Var}{\operatorname{Var}}
+\newcommand{\inner}[2]{\langle #1, #2 \rangle}
+\newcommand{\norm}[1]{\lvert #1 \rvert}
+\newcommand{\R}{\ifmmode\mathbb{R}\else reals\fi}
+\begin{document}
+For $X$ in \R, $\E[X] = \inner{x}{y_1}$ and $\Var(X) \le \norm{x, y}$.
+Also \E.
+\end{document}";
+        let options = L2TOptions {
+            preserve_macros_as_let: true,
+            ..Default::default()
+        };
+        let result = tylax::latex_to_typst_with_diagnostics_options(input, options);
+        let output = &result.output;
+        for expected in [
+            "#let E = $EE$\n",
+            "#let Var = $op(\"Var\")$\n",
+            "#let inner(a, b) = $chevron.l #a, #b chevron.r$\n",
+            "#let normx(a) = $bar.v #a bar.v$\n",
+            "$#E;[X ] = inner(x, y_(1))$",
+            "$Var(X) <= normx(x \\, y)$",
+            "Also $#E$.",
+            "reals",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        assert!(!output.contains("#let R"), "{}", output);
+        assert!(
+            result.warnings.iter().any(
+                |w| w.kind == tylax::WarningKind::MacroRenamed && w.message.contains("'normx'")
+            ),
+            "{:?}",
+            result.warnings
+        );
+
+        // Expanded as before without the option
+        let output = latex_document_to_typst(input);
+        assert!(!output.contains("#let"), "{}", output);
+        assert!(output.contains("$bb(E)[X ] = chevron.l"), "{}", output);
+    }
+
+    #[test]
+    fn test_text_macros_kept_as_content() {
+        let input = r"\documentclass{article}
+\newcommand{\tool}{TyLaX}
+\newcommand{\greet}[1]{Hello, #1!}
+\newcommand{\dx}{\,dx}
+\begin{document}
+We use \tool. \tool{}s and \greet{\emph{you}} with $\int f \dx$ and $\tool^2$.
+\end{document}";
+        let options = L2TOptions {
+            preserve_macros_as_let: true,
+            ..Default::default()
+        };
+        let output = tylax::latex_to_typst_with_diagnostics_options(input, options).output;
+        for expected in [
+            "#let tool = [TyLaX]\n",
+            "#let greet(a) = [Hello, #a!]\n",
+            "We use #tool. #tool;s and #greet[_you_] with",
+            "$integral f thin d x$",
+            "$#tool;^(2)$",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        // Text mixed with math is expanded where it is used
+        assert!(!output.contains("#let dx"), "{}", output);
+        assert!(!output.contains("T y L a X"), "{}", output);
+    }
+
+    #[test]
+    fn test_horizontal_and_vertical_fills() {
+        let result = latex_document_to_typst(
+            "\\begin{document}\nName \\hfill Date\n\nSigned: \\hrulefill\n\nIntro \\dotfill 5\n\\vfill\n\\end{document}",
+        );
+        assert!(result.contains("Name #h(1fr) Date"), "got: {}", result);
+        assert!(
+            result.contains("#box(width: 1fr, line(length: 100%))"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("#box(width: 1fr, repeat[.])"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("#v(1fr)"), "got: {}", result);
+        assert!(!result.contains("hfill"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_fancyhdr_headers_and_footers() {
+        let result = latex_document_to_typst(
+            "\\documentclass{article}\n\\usepackage{fancyhdr}\n\\pagestyle{fancy}\n\\fancyhf{}\n\\fancyhead[L]{\\leftmark}\n\\fancyhead[R]{Draft}\n\\fancyfoot[C]{\\thepage}\n\\begin{document}\n\\maketitle\n\\thispagestyle{empty}\nText\n\\end{document}",
+        );
+        assert!(result.contains("#set page(\n  header: "), "got: {}", result);
+        assert!(result.contains("align(right)[Draft]"), "got: {}", result);
+        assert!(
+            result.contains("query(heading.where(level: 1))"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains(
+                "footer: context if here().page() == 1 { none } else { align(center)[#context counter(page).display()] }"
+            ),
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("fancy"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_text_accents_become_unicode() {
+        let result = latex_document_to_typst(
+            "Sch\\\"{o}n caf\\'e gar\\c{c}on Stra\\ss{} Espa\\~na Erd\\H{o}s na\\\"\\i ve $\\\"{o}$",
+        );
+        assert!(
+            result.contains("Schön café garçon Straß España Erdős naïve"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("dot.double(o)"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_simple_document() {
+        let latex = r#"
+\documentclass{article}
+\title{My Document}
+\author{John Doe}
+\begin{document}
+\maketitle
+Hello, world!
+\end{document}
+"#;
+
+        let result = latex_document_to_typst(latex);
+
+        // Should contain document content (AST converter may handle metadata differently)
+        assert!(!result.is_empty(), "Result should not be empty");
+        // The document should contain the body content
+        assert!(
+            result.contains("Hello") || result.contains("world"),
+            "Missing body content: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_document_with_sections() {
+        let latex = r#"
+\documentclass{article}
+\begin{document}
+\section{Introduction}
+This is the intro.
+\section{Methods}
+This is methods.
+\end{document}
+"#;
+
+        let result = latex_document_to_typst(latex);
+        assert!(!result.contains("Error"));
+    }
+
+    #[test]
+    fn test_document_class_detection() {
+        let article =
+            latex_document_to_typst(r"\documentclass{article}\begin{document}Test\end{document}");
+        assert!(article.contains("a4") || !article.contains("Error"));
+
+        let book =
+            latex_document_to_typst(r"\documentclass{book}\begin{document}Test\end{document}");
+        assert!(book.contains("heading") || !book.contains("Error"));
+    }
+
+    #[test]
+    fn test_document_with_math() {
+        let latex = r#"
+\documentclass{article}
+\begin{document}
+The formula $E = mc^2$ is famous.
+\end{document}
+"#;
+
+        let result = latex_document_to_typst(latex);
+        assert!(!result.contains("Error"));
+    }
+
+    #[test]
+    fn test_paragraph_segmentation_fixture() {
+        let latex = include_str!("fixtures/paragraphs.tex");
+        for options in [L2TOptions::default(), L2TOptions::no_expand()] {
+            let result = latex_document_to_typst_with_options(latex, &options);
+            let body = &result[result.find("Paragraph one").expect("body missing")..];
+            let paragraphs: Vec<&str> = body
+                .split("\n\n")
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .collect();
+            assert_eq!(paragraphs.len(), 5, "got: {:#?}", paragraphs);
+            assert!(paragraphs[0].ends_with("continues here."), "got: {}", body);
+            assert!(paragraphs[1].contains("joinswords"), "got: {}", body);
+            assert!(
+                paragraphs[2].contains("without a blank line."),
+                "got: {}",
+                body
+            );
+            assert!(!body.contains("\n\n\n"), "got: {:?}", body);
+        }
+    }
+
+    #[test]
+    fn test_line_spacing_commands() {
+        let latex = r#"
+\documentclass{article}
+\usepackage{setspace}
+\onehalfspacing
+\begin{document}
+Body.
+\begin{spacing}{2}
+Wide.
+\end{spacing}
+\singlespacing
+\end{document}
+"#;
+
+        let result = latex_document_to_typst(latex);
+        assert!(
+            result.contains("#set par(leading: 0.95em)"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("#[\n#set par(leading: 1.85em)"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("#set par(leading: 0.65em)"),
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("\\onehalfspacing"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_nested_tabular_in_cell() {
+        let latex = r"\begin{document}
+\begin{tabular}{cc}
+\begin{tabular}{c} top \\ \begin{tabular}{cc} x & y \\ z & w \end{tabular} \end{tabular} & B \\
+C & D \\
+\end{tabular}
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        // One outer table and two nested ones
+        assert_eq!(result.matches("#table(").count(), 3, "got: {}", result);
+        assert!(result.contains("[x], [y],"), "got: {}", result);
+        assert!(result.contains("[z], [w],"), "got: {}", result);
+        // The outer grid keeps its two rows of two cells
+        assert!(result.contains(")], [B],"), "got: {}", result);
+        assert!(result.contains("[C], [D],"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_substack_line_break_in_cell() {
+        let latex = r"\begin{document}
+\begin{tabular}{cc}
+A & $\sum_{\substack{i<n \\ j<m}} x_{ij}$ \\
+C & \textbf{D} \\
+\end{tabular}
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        assert!(
+            result.contains("[$sum_(i < n \\ j < m) x_(i j)$],"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("[C], [*D*],"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_font_size_switches() {
+        let latex = r"\begin{document}
+Normal {\footnotesize small words} back. \textbf{\large Big} end.
+
+{\small
+\begin{tabular}{cc}
+a & b \\
+\end{tabular}
+}
+
+\begin{center}
+\Huge Centered
+\end{center}
+After.
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        assert!(
+            result.contains("Normal #text(size: 8pt)[small words] back."),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("*#text(size: 12pt)[Big]*"),
+            "got: {}",
+            result
+        );
+        // A switch covering a whole table sets the table's text size
+        assert!(
+            result.contains("#text(size: 9pt)[#table("),
+            "got: {}",
+            result
+        );
+        // Environment bodies are groups too
+        assert!(
+            result.contains("#text(size: 24.88pt)[Centered]"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_maketitle_position() {
+        let latex = r"\documentclass{article}
+\title{On Things\thanks{Funded by X.}}
+\author{Ann Lee\thanks{Univ. A} \and Bob Roe}
+\begin{document}
+Opening text.
+
+\maketitle
+
+Body.
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        let opening = result.find("Opening text.").expect("opening text");
+        let block = result.find("#align(center)[").expect("title block");
+        let body = result.find("Body.").expect("body");
+        assert!(opening < block && block < body, "got: {}", result);
+        assert!(
+            result.contains("[On Things#footnote[Funded by X.]]"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("[Ann Lee#footnote[Univ. A], Bob Roe]"),
+            "got: {}",
+            result
+        );
+        // Metadata stays free of the notes
+        assert!(
+            result.contains("  title: \"On Things\",\n"),
+            "got: {}",
+            result
+        );
+
+        // Without \maketitle only the metadata is emitted
+        let result = latex_document_to_typst(
+            "\\documentclass{article}\n\\title{Quiet}\n\\begin{document}\nBody.\n\\end{document}",
+        );
+        assert!(result.contains("#set document("), "got: {}", result);
+        assert!(!result.contains("#align(center)"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_document_metadata() {
+        let latex = r"\documentclass{article}
+\title{Notes}
+\author{Ann Lee \and Bob Roe}
+\date{\today}
+\begin{document}
+\maketitle
+Body.
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        assert!(
+            result.starts_with(
+                "#set document(\n  title: \"Notes\",\n  author: (\"Ann Lee\", \"Bob Roe\"),\n  date: auto,\n)"
+            ),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("#text(size: 1.2em)[Ann Lee, Bob Roe]"),
+            "got: {}",
+            result
+        );
+
+        let result = latex_document_to_typst(
+            "\\documentclass{article}\n\\author{Solo}\n\\date{2024-03-05}\n\\begin{document}\nBody.\n\\end{document}",
+        );
+        assert!(result.contains("  author: \"Solo\",\n"), "got: {}", result);
+        assert!(
+            result.contains("  date: datetime(year: 2024, month: 3, day: 5),\n"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_item_optional_labels() {
+        // A marker shared by every item becomes a list marker set rule
+        let result = latex_document_to_typst(
+            "\\begin{itemize}\n\\item[$\\star$] A\n\\item[$\\star$] B\n\\end{itemize}",
+        );
+        assert!(
+            result.contains("#set list(marker: [$star$])"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("- A") || result.contains("-  A"),
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("#box"), "got: {}", result);
+
+        // Differing markers become term items, one per line, with
+        // formatting and math converted
+        let result = latex_document_to_typst(
+            "\\begin{itemize}\n\\item[$\\star$] starred point\n\\item[\\textbf{!}] loud\n\\item[\\(\\alpha\\)-case] first\n\\item plain\n\\end{itemize}",
+        );
+        let items: Vec<&str> = result
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("#set"))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                "/ $star$:  starred point",
+                "/ *!*:  loud",
+                "/ $alpha$-case:  first",
+                "-  plain"
+            ],
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("#box"), "got: {}", result);
+
+        // Description terms and enumerate labels go through conversion too
+        let result = latex_document_to_typst(
+            "\\begin{description}\n\\item[$\\alpha$] first\n\\end{description}\n\\begin{enumerate}\n\\item[a)] x\n\\end{enumerate}",
+        );
+        assert!(result.contains("/ $alpha$:"), "got: {}", result);
+        assert!(result.contains("/ a):  x"), "got: {}", result);
+    }
+}
+
+// ============================================================================
+// Document Mode Tests - Typst to LaTeX
+// ============================================================================
+
+mod t2l_document {
+    use super::*;
+
+    #[test]
+    fn test_text_show_rules_restyle_matches() {
+        let input = "#show \"TODO\": strong\n#show regex(\"\\bAPI\\b\"): it => smallcaps(it)\n#show \"Note\": text.with(fill: red)\n#show heading: it => block(it)\n\n= The API\n\nA TODO about the API and APIs. Note this.\n";
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::default());
+        let output = &result.output;
+        for expected in [
+            "\\section{The \\textsc{API}}",
+            "A \\textbf{TODO} about the \\textsc{API} and APIs. \\textcolor{red}{Note} this.",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        let messages: Vec<_> = result.warnings.iter().map(|w| &w.message).collect();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].contains("#show heading"), "{:?}", messages);
+    }
+
+    #[test]
+    fn test_table_fills_become_xcolor_shading() {
+        use tylax::core::typst2latex::WarningKind;
+
+        let input = "#table(columns: 2, fill: (_, y) => if calc.odd(y) { gray.lighten(80%) }, [A], [B], [C], [D])\n\n\
+                     #table(columns: 2, fill: (teal, none), table.cell(fill: red.lighten(60%))[x], [y])\n\n\
+                     #table(columns: 2, fill: (x, _) => if x == 0 { blue }, [a], [b])\n";
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document());
+        let output = &result.output;
+        for expected in [
+            "\\usepackage[table]{xcolor}",
+            "{\\rowcolors{1}{}{gray!20!white}\n\\begin{tabular}{|c|c|}",
+            "\\begin{tabular}{|>{\\columncolor{teal}}c|c|}",
+            "\\cellcolor{red!40!white} x & y",
+            "\\begin{tabular}{|c|c|}\n\\hline\n a & b",
+        ] {
+            assert!(output.contains(expected), "{:?} in:\n{}", expected, output);
+        }
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Approximation && w.message.contains("if x == 0")));
+    }
+
+    #[test]
+    fn test_line_breaks_stay_in_paragraph() {
+        let input = "Roses are red, \\\nviolets are blue.\n\nNew stanza.\n\n#align(right)[John Doe \\ 1 Main St]";
+        let output = typst_to_latex(input);
+        assert!(
+            output.contains("Roses are red, \\\\\nviolets are blue.\n\nNew stanza."),
+            "{}",
+            output
+        );
+        assert!(output.contains("John Doe \\\\\n1 Main St"), "{}", output);
+    }
+
+    #[test]
+    fn test_math_delimiter_options() {
+        use tylax::{DisplayMathDelim, InlineMathDelim};
+
+        let input = "Inline $x^2$ here.\n\n$ a + b $\n\n$ a &= b \\\n  c &= d $\n\n#figure(table(columns: 1, [$y$]), caption: [Cap $z$])\n\nNote#footnote[With $w$.]";
+        for (inline, open, close) in [
+            (InlineMathDelim::Dollar, "$", "$"),
+            (InlineMathDelim::Paren, "\\(", "\\)"),
+        ] {
+            for (display, block) in [
+                (DisplayMathDelim::Brackets, "\\[\na + b\n\\]"),
+                (DisplayMathDelim::DoubleDollar, "$$\na + b\n$$"),
+                (
+                    DisplayMathDelim::Equation,
+                    "\\begin{equation*}\na + b\n\\end{equation*}",
+                ),
+            ] {
+                let options = T2LOptions {
+                    inline_math_delim: inline,
+                    display_math_delim: display,
+                    ..T2LOptions::default()
+                };
+                let output = typst_to_latex_with_options(input, &options);
+                for expected in [
+                    format!("Inline {}x^2{} here.", open, close),
+                    block.to_string(),
+                    "\\begin{align}\na & = b".to_string(),
+                    format!("{}y{} \\\\", open, close),
+                    format!("\\caption{{Cap {}z{}}}", open, close),
+                    format!("\\footnote{{With {}w{}.}}", open, close),
+                ] {
+                    assert!(output.contains(&expected), "{:?} in:\n{}", expected, output);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_alignment_becomes_multicolumn() {
+        let input = "#table(columns: 2, align: (left, left), table.cell(align: center)[H], [b], table.cell(align: left)[x], [y])";
+        let output = typst_to_latex(input);
+        assert!(
+            output.contains("\\multicolumn{1}{|c|}{H} & b"),
+            "{}",
+            output
+        );
+        assert!(output.contains(" x & y"), "{}", output);
+    }
+
+    #[test]
+    fn test_heading_offset() {
+        let options = T2LOptions {
+            heading_offset: -1,
+            ..T2LOptions::default()
+        };
+        let output = typst_to_latex_with_options("= Title\n\n== Part\n\n=== Detail", &options);
+        assert!(output.contains("\\section{Title}"), "{}", output);
+        assert!(output.contains("\\section{Part}"), "{}", output);
+        assert!(output.contains("\\subsection{Detail}"), "{}", output);
+    }
+
+    #[test]
+    fn test_flipped_pages_become_landscape() {
+        let input = r"#page(flipped: true)[
+  #figure(table(columns: 2, [a], [b]), caption: [Wide])
+
+  Notes on the table.
+]
+
+#[
+#set page(flipped: true)
+Rotated text.
+]
+
+Upright text.
+";
+        let output = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document()).output;
+        assert!(output.contains("\\usepackage{pdflscape}"), "{}", output);
+        assert_eq!(
+            output.matches("\\begin{landscape}").count(),
+            2,
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("\\begin{landscape}\nRotated text.\n\\end{landscape}"),
+            "{}",
+            output
+        );
+        let table = output.find("\\begin{table}").expect("table");
+        assert!(output[..table].contains("\\begin{landscape}"), "{}", output);
+        assert!(
+            !output.contains("\\begin{landscape}\nUpright"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_ties_stay_ties() {
+        let input = "See Figure~@fig and Dr.~Smith~@key, pages 1#sym.hyph.nobreak;5.\n\n$a space.nobreak b$";
+        let output = typst_to_latex(input);
+        assert!(
+            output.contains(
+                "See Figure~\\ref{fig} and Dr.~Smith~\\ref{key}, pages 1\\nobreakdash-5."
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains("$a ~ b$"), "{}", output);
+
+        // Evaluated documents keep them too
+        let output = typst_to_latex(&format!("#let x = 1\n{}", input));
+        assert!(output.contains("Figure~\\ref{fig}"), "{}", output);
+        assert!(output.contains("Smith~\\ref{key}"), "{}", output);
+        assert!(output.contains("1\\nobreakdash-5"), "{}", output);
+    }
+
+    #[test]
+    fn test_turned_figures_become_sideways_floats() {
+        let input = r#"See @wide.
+
+#rotate(90deg, reflow: true)[
+  #figure(table(columns: 2, [a], [b]), caption: [Wide]) <wide>
+]
+
+#page(flipped: true)[
+  #figure(image("plot.png"), caption: [Plot])
+]
+
+#rotate(90deg)[Turned text]
+"#;
+        let output = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document()).output;
+        assert!(output.contains("\\usepackage{rotating}"), "{}", output);
+        assert!(!output.contains("\\begin{landscape}"), "{}", output);
+        let table = output.find("\\begin{sidewaystable}").expect("table");
+        let end = output.find("\\end{sidewaystable}").expect("table end");
+        assert!(output[table..end].contains("\\caption{Wide}"), "{}", output);
+        assert!(output[table..end].contains("\\label{wide}"), "{}", output);
+        assert!(
+            output.contains("\\begin{sidewaysfigure}\n\\centering"),
+            "{}",
+            output
+        );
+        assert!(output.contains("\\end{sidewaysfigure}"), "{}", output);
+        assert!(
+            output.contains("\\rotatebox{-90}{Turned text}"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_styled_blocks_become_tcolorboxes() {
+        let input = r#"#block(fill: color.mix((blue, 5%), (white, 95%)), stroke: 1pt + red, radius: 4pt)[
+*Theorem 1* \
+Boxed text.
+]
+
+#block(width: 50%)[Plain]
+"#;
+        let options = T2LOptions {
+            tcolorbox_blocks: true,
+            ..T2LOptions::full_document()
+        };
+        let output = typst_to_latex_with_options(input, &options);
+        assert!(output.contains("\\usepackage{tcolorbox}"), "{}", output);
+        assert!(
+            output.contains(
+                "\\begin{tcolorbox}[title={Theorem 1}, colback=blue!5!white, boxrule=1pt, colframe=red, arc=4pt]\nBoxed text.\n\\end{tcolorbox}"
+            ),
+            "{}",
+            output
+        );
+        assert_eq!(
+            output.matches("\\begin{tcolorbox}").count(),
+            1,
+            "{}",
+            output
+        );
+        assert!(output.contains("Plain"), "{}", output);
+
+        let output = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(!output.contains("tcolorbox"), "{}", output);
+        assert!(output.contains("Boxed text."), "{}", output);
+    }
+
+    #[test]
+    fn test_escapes_survive_evaluation() {
+        let input = r"#let pct(x) = [#x%]
+#let specials = [\$ \% \& \_ \{ \} \#]
+Text: #pct[20] and #specials and \\
+
+Math: $\{x\} \% \_ \$ \& \#$ and $#pct[5]$
+
+#table(columns: 2, [#pct[5]], [\$ % & \_ { }])
+";
+        let output = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document()).output;
+        assert!(
+            output.contains(r"Text: 20\% and \$ \% \& \_ \{ \} \# and \textbackslash{}"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r"Math: $\{x\} \% \_ \$ \& \#$ and $5\%$"),
+            "{}",
+            output
+        );
+        assert!(output.contains(r"5\% & \$ \% \& \_ \{ \} \\"), "{}", output);
+    }
+
+    #[test]
+    fn test_place_positions() {
+        use tylax::core::typst2latex::WarningKind;
+        use tylax::PlaceBackend;
+
+        let input = "#place(top + right, dx: -1cm, dy: 1cm, image(\"logo.png\", width: 2cm))\n\
+                     #place(bottom + center, dy: -5%)[Footer]\n\
+                     #place(top, float: true)[Floaty]\n";
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document());
+        let output = &result.output;
+        assert!(
+            output.contains(
+                "\\begin{textblock*}{\\paperwidth}[1,0](\\dimexpr\\paperwidth-1cm\\relax,1cm)"
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                "[0.5,1](0.5\\paperwidth,\\dimexpr\\paperheight-0.05\\paperheight\\relax)"
+            ),
+            "{}",
+            output
+        );
+        assert_eq!(
+            output
+                .matches("\\usepackage[absolute,overlay]{textpos}")
+                .count(),
+            1,
+            "{}",
+            output
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Approximation && w.message.contains("float")));
+
+        let options = T2LOptions {
+            place_backend: PlaceBackend::TikZ,
+            ..T2LOptions::full_document()
+        };
+        let output = typst_to_latex_with_options(input, &options);
+        assert!(
+            output.contains(
+                "\\node[anchor=north east, xshift=-1cm, yshift=-1cm] at (current page.north east)"
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains("\\usepackage{tikz}"), "{}", output);
+    }
+
+    #[test]
+    fn test_csv_tables() {
+        use std::sync::Arc;
+        use tylax::typst_to_latex_with_file_resolver;
+        use tylax::utils::MemoryFileResolver;
+
+        let input = "#table(columns: 2, ..csv(\"data.csv\", delimiter: \";\").flatten())\n";
+        let output = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(
+            output.contains("\\csvautotabular[no head, separator=semicolon]{data.csv}"),
+            "{}",
+            output
+        );
+        assert!(output.contains("\\usepackage{csvsimple}"), "{}", output);
+
+        // The data file does not stop the rest of the document expanding
+        let source = format!(
+            "#let greet(name) = [Hello, #name!]\n#greet(\"World\")\n\n{}",
+            input
+        );
+        let result = typst_to_latex_with_diagnostics(&source, &T2LOptions::default());
+        assert!(result.output.contains("Hello, World!"), "{}", result.output);
+        assert!(
+            result
+                .output
+                .contains("\\csvautotabular[no head, separator=semicolon]{data.csv}"),
+            "{}",
+            result.output
+        );
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        let options = T2LOptions {
+            materialize_data: true,
+            ..T2LOptions::default()
+        };
+        let mut resolver = MemoryFileResolver::new();
+        resolver.add_file("data.csv", "a;b\n1;2\n");
+        let result = typst_to_latex_with_file_resolver(input, &options, Arc::new(resolver));
+        assert!(result.output.contains(" a & b \\\\"), "{}", result.output);
+        assert!(result.output.contains(" 1 & 2 \\\\"), "{}", result.output);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        let result =
+            typst_to_latex_with_file_resolver(input, &options, Arc::new(MemoryFileResolver::new()));
+        assert!(
+            result
+                .output
+                .contains("Missing data file \\texttt{data.csv}"),
+            "{}",
+            result.output
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.to_string().contains("file not found: data.csv")));
+    }
+
+    #[test]
+    fn test_crlf_cr_and_mixed_line_endings() {
+        let lines = [
+            "= Title",
+            "```rust",
+            "fn main() {",
+            "  run()",
+            "}",
+            "```",
+            "#table(columns: 2,",
+            "  [a], [b])",
+            "$ x \\",
+            "  y $",
+        ];
+        let options = T2LOptions::full_document();
+        let expected = typst_to_latex_with_options(&lines.join("\n"), &options);
+        let mixed: String = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}", line, ["\r\n", "\r", "\n"][i % 3]))
+            .collect();
+        for input in [lines.join("\r\n"), lines.join("\r"), mixed] {
+            let output = typst_to_latex_with_options(&input, &options);
+            assert!(!output.contains('\r'), "{:?}", output);
+            assert_eq!(output, expected);
+            let result = typst_to_latex_with_diagnostics(&input, &options);
+            assert!(!result.output.contains('\r'), "{:?}", result.output);
+        }
+        assert!(
+            expected.contains("\\begin{verbatim}\nfn main() {\n"),
+            "{}",
+            expected
+        );
+        assert!(
+            expected.lines().all(|line| line == line.trim_end()),
+            "{:?}",
+            expected
+        );
+
+        let options = T2LOptions {
+            line_ending: LineEnding::Crlf,
+            ..T2LOptions::full_document()
+        };
+        let output = typst_to_latex_with_options(&lines.join("\n"), &options);
+        assert_eq!(output.replace("\r\n", "\n"), expected);
+        assert_eq!(output.matches('\n').count(), output.matches("\r\n").count());
+    }
+
+    #[test]
+    fn test_table_inset_becomes_scoped_spacing() {
+        let input = "#table(columns: 2, inset: (x: 10pt, y: 7.5pt), [a], [b])\n\n#table(columns: 2, [c], [d])";
+        let output = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(
+            output.contains(
+                "{\\renewcommand{\\arraystretch}{1.5}\\setlength{\\tabcolsep}{10pt}\n\\begin{tabular}"
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains("\\end{tabular}}"), "{}", output);
+        assert_eq!(output.matches("arraystretch").count(), 1, "{}", output);
+    }
+
+    #[test]
+    fn test_cell_line_breaks_become_makecell() {
+        let input = "#table(columns: 2, align: (left, center), [a \\ b], [c],\n  table.cell(colspan: 2)[x \\ y])\n";
+        let output = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(output.contains("\\makecell[l]{a \\\\ b} & c"), "{}", output);
+        assert!(
+            output.contains("\\multicolumn{2}{|l|}{\\makecell[l]{x \\\\ y}}"),
+            "{}",
+            output
+        );
+        assert!(output.contains("\\usepackage{makecell}"), "{}", output);
+    }
+
+    #[test]
+    fn test_drafting_notes_become_todos() {
+        let input = "#import \"@preview/drafting:0.2.2\": margin-note, inline-note\nText#margin-note[fix this] and #inline-note(fill: blue)[Rewrite]\n";
+        let output = typst_to_latex(input);
+        assert!(output.contains("Text\\todo{fix this}"), "{}", output);
+        assert!(
+            output.contains("\\todo[inline, color=blue]{Rewrite}"),
+            "{}",
+            output
+        );
+        let output = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(output.contains("\\usepackage{todonotes}"), "{}", output);
+    }
+
+    #[test]
+    fn test_context_and_state_use_placeholders() {
+        let input = "#let s = state(\"theme\", \"dark\")\n#s.update(\"light\")\nTheme: #context s.get() on page #context counter(page).get().first().\n#context {\n  let size = measure[Hello]\n  [Width: #size.width]\n}\nStatic *text* stays.\n";
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::default());
+        assert!(
+            result
+                .output
+                .contains(r"Theme: dark on page 0. Width: 0pt Static \textbf{text} stays."),
+            "Got: {}",
+            result.output
+        );
+        assert!(!result.output.contains("light"), "Got: {}", result.output);
+        assert_eq!(result.warnings.len(), 3, "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_nested_emphasis_becomes_emph() {
+        let result = typst_to_latex("_a #emph[b #emph[c] d] e_");
+        assert!(
+            result.contains(r"\textit{a \emph{b \emph{c} d} e}"),
+            "Got: {}",
+            result
+        );
+
+        let result = tylax::typst_to_latex_with_eval(
+            "#text(style: \"italic\")[x _y $z^2$_]",
+            &T2LOptions::default(),
+        );
+        assert!(
+            result.contains(r"\textit{x \emph{y $z^2$}}"),
+            "Got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_dates_become_today_and_date() {
+        let input = "#set document(title: \"T\", date: datetime(year: 2024, month: 3, day: 5))\n#set text(lang: \"de\")\n\nOn #datetime.today().display(\"[month repr:long] [day], [year]\"), #datetime.today().display(\"[year]-[month]-[day]\").\n";
+        let options = T2LOptions::full_document();
+        for result in [
+            typst_to_latex_with_options(input, &options),
+            tylax::typst_to_latex_with_eval(input, &options),
+        ] {
+            assert!(
+                result.contains("\\usepackage[ngerman]{babel}"),
+                "got: {}",
+                result
+            );
+            assert!(result.contains("\\date{5. März 2024}"), "got: {}", result);
+            assert!(
+                result.contains("On \\today, \\the\\year-\\the\\month-\\the\\day."),
+                "got: {}",
+                result
+            );
+            assert!(!result.contains("lang"), "got: {}", result);
+        }
+
+        // The month lookup written for `\\today` reads back as it; other
+        // code using today's date is not a date
+        let input = "Stand: #{ str(datetime.today().day()) + \". \" + (\"Januar\", \"Februar\").at(datetime.today().month() - 1) + \" \" + str(datetime.today().year()) }.\n\nEra: #{ if datetime.today().year() > 2020 [new] else [old] }.\n";
+        let result = typst_to_latex_with_options(input, &T2LOptions::default());
+        assert!(result.contains("Stand: \\today."), "got: {}", result);
+        assert!(!result.contains("Era: \\today"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_pagebreak_flushes_preceding_floats() {
+        let input = "A\n#pagebreak()\n#figure(image(\"a.png\"), caption: [x])\n#pagebreak()\nB\n";
+        for result in [
+            typst_to_latex_with_options(input, &T2LOptions::default()),
+            tylax::typst_to_latex_with_eval(input, &T2LOptions::default()),
+        ] {
+            let newpage = result.find("\\newpage").expect("newpage");
+            let clearpage = result.find("\\clearpage").expect("clearpage");
+            assert!(
+                newpage < result.find("\\begin{figure}").unwrap(),
+                "got: {}",
+                result
+            );
+            assert!(
+                clearpage > result.find("\\end{figure}").unwrap(),
+                "got: {}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_fragment_without_wrapper() {
+        let options = T2LOptions::full_document();
+        let table = tylax::typst2latex::convert_fragment(
+            "#hl[a] and #table(columns: 2, [a], [b])",
+            &options,
+            &FragmentContext::text().with_macros("#let hl(x) = strong(x)"),
+        );
+        assert!(table.starts_with("\\textbf{a} and"), "got: {}", table);
+        assert!(table.contains("\\begin{tabular}"), "got: {}", table);
+        assert!(!table.contains("\\documentclass"), "got: {}", table);
+        assert!(!table.contains("\\usepackage"), "got: {}", table);
+
+        let math = tylax::typst2latex::convert_fragment("x^2", &options, &FragmentContext::math());
+        assert_eq!(math, "x^2");
+    }
+
+    #[test]
+    fn test_equation_numbering_is_left_to_latex() {
+        let input = "#set math.equation(numbering: \"(1)\")\n$ a + b $ <eq:x>\n\n$ c $\n";
+        for result in [
+            typst_to_latex_with_options(input, &T2LOptions::default()),
+            tylax::typst_to_latex_with_eval(input, &T2LOptions::default()),
+        ] {
+            assert!(
+                result.contains("\\begin{equation}\na + b\n\\label{eq:x}\n\\end{equation}"),
+                "got: {}",
+                result
+            );
+            assert!(
+                result.contains("\\begin{equation}\nc\n\\end{equation}"),
+                "got: {}",
+                result
+            );
+            assert!(!result.contains("\\tag"), "got: {}", result);
+        }
+
+        let custom = "#set math.equation(numbering: \"[a]\")\n$ a $\n\n$ b $ <eq:b>\n";
+        let result = typst_to_latex_with_options(custom, &T2LOptions::default());
+        assert!(
+            result.contains("a\n\\tag*{[a]}\n\\end{equation}"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("b\n\\tag*{[b]}\n\\label{eq:b}\n\\end{equation}"),
+            "got: {}",
+            result
+        );
+
+        let unnumbered = typst_to_latex_with_options("$ c $\n", &T2LOptions::default());
+        assert!(unnumbered.contains("\\[\nc\n\\]"), "got: {}", unnumbered);
+    }
+
+    #[test]
+    fn test_wrap_content_to_wrapfigure() {
+        let input = r#"#import "@preview/wrap-it:0.1.1": wrap-content
+
+#wrap-content(
+  block(width: 40%)[#figure(
+    image("plot.png", width: 100%),
+    caption: [A plot],
+  ) <fig:plot>],
+  align: left,
+)[Text flowing around the figure.]
+"#;
+        for result in [
+            typst_to_latex_with_options(input, &T2LOptions::full_document()),
+            tylax::typst_to_latex_with_eval(input, &T2LOptions::full_document()),
+        ] {
+            assert!(result.contains("\\usepackage{wrapfig}"), "got: {}", result);
+            assert!(
+                result.contains("\\begin{wrapfigure}{l}{0.40\\textwidth}\n\\centering"),
+                "got: {}",
+                result
+            );
+            assert!(
+                result.contains("\\includegraphics[width=1.00\\linewidth]{plot.png}"),
+                "got: {}",
+                result
+            );
+            assert!(result.contains("\\label{fig:plot}"), "got: {}", result);
+            assert!(
+                result.contains("\\end{wrapfigure}\nText flowing around the figure."),
+                "got: {}",
+                result
+            );
+            assert!(!result.contains("@preview"), "got: {}", result);
+        }
+    }
+
+    #[test]
+    fn test_fills_and_spacing_in_markup() {
+        let input = "Name #h(1fr) Date #h(2fr) x #h(1cm) y\n\n#box(width: 1fr, repeat[.]) #box(width: 1fr, line(length: 100%))\n\n#v(1fr)";
+        for result in [
+            typst_to_latex_with_options(input, &T2LOptions::default()),
+            tylax::typst_to_latex_with_eval(input, &T2LOptions::default()),
+        ] {
+            assert!(result.contains(r"Name \hfill Date"), "got: {}", result);
+            assert!(result.contains(r"\hspace{\stretch{2}}"), "got: {}", result);
+            assert!(result.contains(r"\hspace{1cm}"), "got: {}", result);
+            assert!(result.contains(r"\dotfill"), "got: {}", result);
+            assert!(result.contains(r"\hrulefill"), "got: {}", result);
+            assert!(result.contains(r"\vfill"), "got: {}", result);
+        }
+    }
+
+    #[test]
+    fn test_page_header_and_footer_become_fancyhdr() {
+        let input = "#set page(header: [My Paper #h(1fr) Draft], footer: context align(center)[#counter(page).display()])\n\nPage #context counter(page).display() here.";
+        let result = tylax::typst_to_latex_with_eval(input, &T2LOptions::full_document());
+        assert!(
+            result.contains("\\usepackage{fancyhdr}\n\\pagestyle{fancy}\n\\fancyhf{}"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains(r"\fancyhead[L]{My Paper}"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains(r"\fancyhead[R]{Draft}"), "got: {}", result);
+        assert!(
+            result.contains(r"\fancyfoot[C]{\thepage}"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains(r"Page \thepage here"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_typography_spelled_for_latex() {
+        let input =
+            "He said \"a 'b' c\" and “d”. Pages 1--5 --- 1–5 — x... y… It's `\"q\" --` $\"s\"$.";
+        let result = typst_to_latex_with_options(input, &T2LOptions::default());
+        assert!(
+            result.contains(
+                r"He said ``a `b' c'' and ``d''. Pages 1--5 --- 1--5 --- x\ldots{} y\ldots{} It's"
+            ),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("\"q\" --"), "got: {}", result);
+
+        let options = T2LOptions {
+            keep_unicode: true,
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_options(input, &options);
+        assert!(
+            result.contains("He said “a ‘b’ c” and “d”. Pages 1–5 — 1–5 — x… y… It’s"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_ascii_accents_option_spells_accents() {
+        let options = T2LOptions {
+            ascii_accents: true,
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_options(
+            "Erdős wrote a café review.\n\n```python\nprint(\"café\")\n```",
+            &options,
+        );
+        assert!(
+            result.contains(r"Erd\H{o}s wrote a caf\'e review."),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("print(\"café\")"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_ascii_accents_keep_verbatim_text_urls_and_keys() {
+        let options = T2LOptions {
+            ascii_accents: true,
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_options(
+            "= Café <sec:café>\n\nSee @sec:café, `naïve`, #link(\"https://example.com/café\")[the café] and #link(\"https://é.example\").",
+            &options,
+        );
+        for expected in [
+            r"\section{Caf\'e}",
+            r"\label{sec:café}",
+            r"\ref{sec:café}",
+            r"\verb|naïve|",
+            r"\href{https://example.com/café}{the caf\'e}",
+            r"\url{https://é.example}",
+        ] {
+            assert!(result.contains(expected), "{} in: {}", expected, result);
+        }
+    }
+
+    #[test]
+    fn test_heading_conversion() {
+        let typst = "= Main Title";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(
+            result.contains("\\section"),
+            "Expected section, got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_subsection_conversion() {
+        let typst = "== Subsection";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(
+            result.contains("\\subsection"),
+            "Expected subsection, got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_bold_conversion() {
+        let typst = "*bold text*";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(
+            result.contains("\\textbf"),
+            "Expected textbf, got: {}",
+            result
         );
     }
 
@@ -1126,13 +4571,154 @@ mod t2l_document {
 
     #[test]
     fn test_inline_code() {
-        let typst = "`code`";
+        let typst = "`code` and `a|b`, *`bold`*";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(result.contains("\\verb|code|"), "got: {}", result);
+        assert!(result.contains("\\verb!a|b!"), "got: {}", result);
+        // \verb is not allowed inside a command argument
+        assert!(
+            result.contains("\\textbf{\\texttt{bold}}"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_code_block_backends() {
+        let typst = "```rust\nfn main() {}\n```\n\n```py\nprint(1)\n```\n\n```\nplain\n```";
+        let listings = typst_to_latex_with_options(typst, &T2LOptions::full_document());
+        assert!(
+            listings.contains("\\begin{lstlisting}[language=Python]\nprint(1)\n\\end{lstlisting}"),
+            "got: {}",
+            listings
+        );
+        assert!(
+            listings.contains("% Code block (language: rust)\n\\begin{verbatim}"),
+            "got: {}",
+            listings
+        );
+        assert!(
+            listings.contains("\\begin{verbatim}\nplain\n\\end{verbatim}"),
+            "got: {}",
+            listings
+        );
+        assert!(
+            listings.contains("\\usepackage{listings}"),
+            "got: {}",
+            listings
+        );
+        assert!(!listings.contains("minted"), "got: {}", listings);
+
+        let options = T2LOptions {
+            code_backend: CodeBackend::Minted,
+            ..T2LOptions::full_document()
+        };
+        let minted = typst_to_latex_with_options(typst, &options);
+        assert!(
+            minted.contains("\\begin{minted}{rust}\nfn main() {}\n\\end{minted}"),
+            "got: {}",
+            minted
+        );
+        assert!(
+            minted.contains("\\begin{minted}{python}"),
+            "got: {}",
+            minted
+        );
+        assert!(minted.contains("\\begin{minted}{text}"), "got: {}", minted);
+        assert!(minted.contains("\\usepackage{minted}"), "got: {}", minted);
+        assert!(!minted.contains("{listings}"), "got: {}", minted);
+    }
+
+    #[test]
+    fn test_par_leading_to_linespread() {
+        let typst = "#set par(leading: 1.45em)\n\nBody.";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::full_document());
+        assert!(
+            result.contains("\\usepackage{setspace}\n\\doublespacing"),
+            "got: {}",
+            result
+        );
+
+        let result = typst_to_latex_with_options(
+            "#set par(leading: 0.77em)\n\nBody.",
+            &T2LOptions::full_document(),
+        );
+        assert!(result.contains("\\linespread{1.1}"), "got: {}", result);
+        assert!(!result.contains("setspace"), "got: {}", result);
+
+        // Default leading leaves the preamble untouched
+        let result = typst_to_latex_with_options(
+            "#set par(leading: 0.65em)\n\nBody.",
+            &T2LOptions::full_document(),
+        );
+        assert!(!result.contains("linespread"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_text_size_to_size_switch() {
+        let typst =
+            "#text(size: 8pt)[small] and #text(size: 14pt)[big] and #text(size: 1.2em)[rel]";
+        let expected = "{\\footnotesize small} and {\\Large big} and {\\large rel}";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(result.contains(expected), "got: {}", result);
+        let result = typst_to_latex_with_diagnostics(typst, &T2LOptions::default());
+        assert!(result.output.contains(expected), "got: {}", result.output);
+    }
+
+    #[test]
+    fn test_set_document_metadata() {
+        let typst = "#set document(title: [My Paper], author: (\"Ann\", \"Bob\"), keywords: (\"a\", \"b\"))\n\nBody.";
+        let result = typst_to_latex_with_options(typst, &T2LOptions::full_document());
+        assert!(result.contains("\\title{My Paper}"), "got: {}", result);
+        assert!(
+            result.contains("\\author{Ann \\and Bob}"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains("\\maketitle"), "got: {}", result);
+        assert!(
+            result.contains(
+                "\\hypersetup{pdftitle={My Paper}, pdfauthor={Ann, Bob}, pdfkeywords={a, b}}"
+            ),
+            "got: {}",
+            result
+        );
+
+        // Explicit options win over the document's own metadata
+        let options = T2LOptions {
+            title: Some("Override".to_string()),
+            ..T2LOptions::full_document()
+        };
+        let result = typst_to_latex_with_options(typst, &options);
+        assert!(result.contains("\\title{Override}"), "got: {}", result);
+        assert!(!result.contains("\\title{My Paper}"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_list_marker() {
+        let typst = "#list(marker: [—], [One], [Two])";
         let result = typst_to_latex_with_options(typst, &T2LOptions::default());
+        assert!(result.contains("\\begin{itemize}\n"), "got: {}", result);
+        assert!(result.contains("\\item[---] One"), "got: {}", result);
+        assert!(result.contains("\\item[---] Two"), "got: {}", result);
+
+        let options = T2LOptions {
+            use_enumitem: true,
+            ..T2LOptions::full_document()
+        };
+        let result = typst_to_latex_with_options(typst, &options);
         assert!(
-            result.contains("\\texttt"),
-            "Expected texttt, got: {}",
+            result.contains("\\begin{itemize}[label=---]"),
+            "got: {}",
             result
         );
+        assert!(result.contains("\\item One"), "got: {}", result);
+        assert!(result.contains("\\usepackage{enumitem}"), "got: {}", result);
+
+        // Math markers are converted
+        let result =
+            typst_to_latex_with_options("#list(marker: [$star$], [A])", &T2LOptions::default());
+        assert!(result.contains("\\item[$\\star$] A"), "got: {}", result);
     }
 
     #[test]
@@ -1283,25 +4869,392 @@ mod edge_cases {
         assert!(!result.contains("Error"));
     }
 
-    #[test]
-    fn test_nested_structures() {
-        let result = latex_to_typst(r"\frac{\frac{1}{2}}{\frac{3}{4}}");
-        assert!(!result.contains("Error"));
-        assert!(result.contains("frac"));
-    }
+    #[test]
+    fn test_nested_structures() {
+        let result = latex_to_typst(r"\frac{\frac{1}{2}}{\frac{3}{4}}");
+        assert!(!result.contains("Error"));
+        assert!(result.contains("frac"));
+    }
+
+    #[test]
+    fn test_unicode() {
+        let result = typst_to_latex_with_options("α + β = γ", &T2LOptions::default());
+        // Should handle unicode gracefully
+        assert!(!result.is_empty());
+    }
+
+    #[test]
+    fn test_long_expression() {
+        let long_expr = r"\sum_{i=1}^{100} \frac{1}{i^2} = \frac{\pi^2}{6}";
+        let result = latex_to_typst(long_expr);
+        assert!(!result.contains("Error"));
+    }
+}
+
+// ============================================================================
+// Robustness Tests
+// ============================================================================
+
+mod robustness {
+    use tylax::{
+        latex_document_to_typst, try_latex_document_to_typst, try_latex_to_typst,
+        try_typst_document_to_latex, try_typst_to_latex, typst_to_latex_with_options, L2TOptions,
+        LatexConverter, T2LOptions,
+    };
+
+    #[test]
+    fn test_converters_and_results_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LatexConverter>();
+        assert_send_sync::<tylax::MiniEval>();
+        assert_send_sync::<L2TOptions>();
+        assert_send_sync::<T2LOptions>();
+        assert_send_sync::<tylax::L2TConversionResult>();
+        assert_send_sync::<tylax::T2LConversionResult>();
+        assert_send_sync::<tylax::ConversionResult<String>>();
+        assert_send_sync::<tylax::batch::BatchOptions>();
+    }
+
+    #[test]
+    fn test_convert_batch_matches_single_conversions() {
+        use tylax::batch::{BatchDirection, BatchOptions};
+
+        let documents: Vec<String> = (0..1000)
+            .map(|i| match i % 2 {
+                0 => format!(
+                    "\\documentclass{{article}}\n\\begin{{document}}\n\\section{{Part {i}}}\n$\\frac{{{i}}}{{2}}$\n\\end{{document}}"
+                ),
+                _ => format!("#set page(width: auto)\n= Part {i}\n$frac({i}, 2)$\n"),
+            })
+            .collect();
+        let inputs: Vec<&str> = documents.iter().map(String::as_str).collect();
+        let options = BatchOptions {
+            full_document: true,
+            ..Default::default()
+        };
+        let expected: Vec<String> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| match i % 2 {
+                0 => latex_document_to_typst(input),
+                _ => typst_to_latex_with_options(input, &T2LOptions::full_document()),
+            })
+            .collect();
+
+        let check = |results: Vec<tylax::ConversionResult<String>>| {
+            assert_eq!(results.len(), expected.len());
+            for (result, expected) in results.into_iter().zip(&expected) {
+                assert_eq!(&result.unwrap(), expected);
+            }
+        };
+        check(tylax::convert_batch(
+            &inputs,
+            BatchDirection::Auto,
+            &options,
+        ));
+
+        // Batches on 16 threads at once
+        let threads: Vec<_> = inputs
+            .chunks(inputs.len() / 16 + 1)
+            .map(|chunk| {
+                let chunk: Vec<String> = chunk.iter().map(|s| s.to_string()).collect();
+                let options = options.clone();
+                std::thread::spawn(move || {
+                    let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+                    tylax::convert_batch(&chunk, BatchDirection::Auto, &options)
+                })
+            })
+            .collect();
+        check(
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().unwrap())
+                .collect(),
+        );
+
+        #[cfg(feature = "rayon")]
+        {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(16)
+                .build()
+                .unwrap();
+            check(pool.install(|| tylax::convert_batch(&inputs, BatchDirection::Auto, &options)));
+        }
+    }
+
+    #[test]
+    fn test_output_is_deterministic() {
+        let latex = r"\documentclass{article}
+\usepackage{siunitx,xcolor,acronym}
+\newcommand{\R}{\mathbb{R}}
+\newcommand{\norm}[1]{\left\| #1 \right\|}
+\renewcommand{\vec}[1]{\mathbf{#1}}
+\DeclareMathOperator{\tr}{tr}
+\definecolor{brand}{RGB}{10,20,30}
+\newacronym{cnn}{CNN}{convolutional neural network}
+\newacronym{gpu}{GPU}{graphics processing unit}
+\begin{document}
+A \gls{cnn} on a \gls{gpu} at \SI{3.5}{\giga\hertz\per\meter} costs \pounds 5 \ldots
+$\norm{\vec{x}} \in \R^n$, $\tr A = \sum_i \alpha_i$, \textcolor{brand}{x}.
+See \cite{knuth84,lamport94} and \citep[p.~3]{turing36}, \cite{bib1}.
+\begin{thebibliography}{9}
+\bibitem{bib1} A. Author. \emph{Title}.
+\end{thebibliography}
+\end{document}";
+        let typst = "#let R = $bb(R)$\n#let norm(x) = $lr(|| #x ||)$\n#let items = (\"b\": 2, \"a\": 1, \"c\": 3)\n#for (k, v) in items [#k = #v, ]\n$norm(x) in #R$ @knuth84 @lamport94 #cite(<turing36>, supplement: [p. 3])\n";
+
+        let convert = || {
+            (
+                try_latex_document_to_typst(latex).unwrap(),
+                tylax::typst_to_latex_with_diagnostics(typst, &T2LOptions::full_document()).output,
+            )
+        };
+        let expected = convert();
+        for _ in 0..50 {
+            assert_eq!(convert(), expected);
+        }
+        let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(convert)).collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    }
+
+    /// Tokens that tend to unbalance or confuse the parsers
+    const LATEX_TOKENS: &[&str] = &[
+        "{",
+        "}",
+        "\\",
+        "$",
+        "&",
+        "#",
+        "%",
+        "[",
+        "]",
+        "_",
+        "^",
+        "\\begin{",
+        "\\end{",
+        "\\\\",
+        "\\left(",
+        "\\right",
+        "\\multicolumn{",
+        "\\hline",
+        "\\item",
+        "é",
+    ];
+    const TYPST_TOKENS: &[&str] = &[
+        "(", ")", "[", "]", "{", "}", "$", "#", "\"", ",", ":", "*", "_", "`", "<", ">", "@", "\\",
+        "=", "é",
+    ];
+
+    /// Deterministic xorshift generator, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize
+        }
+
+        /// A char boundary in `text`
+        fn boundary(&mut self, text: &str) -> usize {
+            let mut at = self.next() % (text.len() + 1);
+            while !text.is_char_boundary(at) {
+                at -= 1;
+            }
+            at
+        }
+    }
+
+    /// Truncate, delete, duplicate and insert at random places
+    fn mutate(seed: &str, tokens: &[&str], rng: &mut Rng) -> String {
+        let mut text = seed.to_string();
+        for _ in 0..1 + rng.next() % 3 {
+            let (a, b) = (rng.boundary(&text), rng.boundary(&text));
+            let (start, end) = (a.min(b), a.max(b));
+            match rng.next() % 4 {
+                0 => text.truncate(end),
+                1 => text.replace_range(start..end, ""),
+                2 => {
+                    let copy = text[start..end].to_string();
+                    text.insert_str(end, &copy);
+                }
+                _ => text.insert_str(start, tokens[rng.next() % tokens.len()]),
+            }
+        }
+        text
+    }
+
+    type Converter = fn(&str) -> Result<String, tylax::ConversionError>;
+
+    fn assert_no_panics(source: &str, tokens: &[&str], converters: &[Converter]) {
+        let mut rng = Rng(0x5eed_1234_abcd_9876);
+        let mut failures = Vec::new();
+        for chunk in source.split("\n\n") {
+            for _ in 0..100 {
+                let input = mutate(chunk, tokens, &mut rng);
+                for convert in converters {
+                    if let Err(err) = convert(&input) {
+                        failures.push(format!("{:?}: {}", input, err));
+                    }
+                }
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "{} panics, e.g.\n{}",
+            failures.len(),
+            failures[..failures.len().min(5)].join("\n")
+        );
+    }
+
+    #[test]
+    fn test_mutated_latex_does_not_panic() {
+        assert_no_panics(
+            include_str!("fixtures/seeds.tex"),
+            LATEX_TOKENS,
+            &[try_latex_to_typst, try_latex_document_to_typst],
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_is_kept_as_is() {
+        let latex = format!("{}x", r"\sqrt".repeat(1000));
+        let result = tylax::latex_to_typst_with_diagnostics(&latex);
+        assert!(result.output.contains("```latex"));
+        assert!(!result.warnings.is_empty());
+
+        let typst = format!("{}x{}", "#box[".repeat(1000), "]".repeat(1000));
+        let result = tylax::typst_to_latex_with_diagnostics(&typst, &T2LOptions::default());
+        assert!(result.output.contains(r"\begin{verbatim}"));
+        assert!(!result.warnings.is_empty());
+    }
+
+    /// Empty, blank, comment-only and settings-only sources
+    const DEGENERATE_TYPST: &[&str] = &[
+        "",
+        " ",
+        "\n\n",
+        "\r\n\t\r\n",
+        "// only a comment",
+        "/* only */ // comments\n",
+        "/* unterminated",
+        "#set text(lang: \"de\")\n#set par(justify: true)\n",
+        "#let x = 1\n#show heading: set text(blue)\n",
+        "#set page(",
+    ];
+
+    const DEGENERATE_LATEX: &[&str] = &[
+        "",
+        " ",
+        "\n\n",
+        "\r\n\t\r\n",
+        "% only a comment",
+        "% one\n%two\n",
+        "\\documentclass{article}\n\\usepackage[ngerman]{babel}\n",
+        "\\documentclass{article}\n\\begin{document}\n% nothing\n\\end{document}",
+        "\\begin{document}\\end{document}",
+        "\\documentclass{article}\n\\begin{document}",
+    ];
+
+    fn no_panic<T>(convert: impl FnOnce() -> T) -> Option<T> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(convert)).ok()
+    }
+
+    #[test]
+    fn test_degenerate_typst_through_every_entry_point() {
+        for input in DEGENERATE_TYPST {
+            let documents = [
+                no_panic(|| typst_to_latex_with_options(input, &T2LOptions::full_document())),
+                no_panic(|| tylax::typst_document_to_latex(input)),
+                no_panic(|| {
+                    tylax::typst_to_latex_with_diagnostics(input, &T2LOptions::full_document())
+                        .output
+                }),
+                no_panic(|| tylax::typst_to_latex_with_eval(input, &T2LOptions::full_document())),
+                try_typst_document_to_latex(input).ok(),
+            ];
+            for document in documents {
+                let document = document.unwrap_or_else(|| panic!("panicked on {:?}", input));
+                let body = document
+                    .split_once("\\begin{document}")
+                    .and_then(|(_, rest)| rest.strip_suffix("\\end{document}"))
+                    .unwrap_or_else(|| panic!("no document for {:?}:\n{}", input, document));
+                assert!(body.trim().is_empty(), "{:?}:\n{}", input, document);
+            }
+
+            let fragments = [
+                no_panic(|| tylax::typst_to_latex(input)),
+                no_panic(|| typst_to_latex_with_options(input, &T2LOptions::default())),
+                no_panic(|| tylax::typst_to_latex_with_eval(input, &T2LOptions::default())),
+                try_typst_to_latex(input).ok(),
+                no_panic(|| tylax::convert_auto(input).0),
+                no_panic(|| tylax::convert_auto_document(input).0),
+            ];
+            for fragment in fragments {
+                assert!(fragment.is_some(), "panicked on {:?}", input);
+            }
+        }
 
-    #[test]
-    fn test_unicode() {
-        let result = typst_to_latex_with_options("α + β = γ", &T2LOptions::default());
-        // Should handle unicode gracefully
-        assert!(!result.is_empty());
+        // Settings still reach the preamble
+        let document = tylax::typst_document_to_latex(DEGENERATE_TYPST[7]);
+        assert!(
+            document.contains("\\usepackage[ngerman]{babel}"),
+            "{}",
+            document
+        );
+    }
+
+    #[test]
+    fn test_degenerate_latex_through_every_entry_point() {
+        for input in DEGENERATE_LATEX {
+            let documents = [
+                no_panic(|| latex_document_to_typst(input)),
+                no_panic(|| {
+                    tylax::latex_document_to_typst_with_options(input, &L2TOptions::default())
+                }),
+                try_latex_document_to_typst(input).ok(),
+            ];
+            for document in documents {
+                let document = document.unwrap_or_else(|| panic!("panicked on {:?}", input));
+                // Only settings, no stray text of the source
+                assert!(
+                    document
+                        .lines()
+                        .all(|line| line.is_empty() || line.starts_with(['#', ' ', ')'])),
+                    "{:?}:\n{}",
+                    input,
+                    document
+                );
+                assert!(!document.contains('\\'), "{:?}:\n{}", input, document);
+            }
+
+            let fragments = [
+                no_panic(|| tylax::latex_to_typst(input)),
+                no_panic(|| tylax::latex_to_typst_with_diagnostics(input).output),
+                no_panic(|| tylax::latex_to_typst_with_eval(input)),
+                no_panic(|| tylax::latex_math_to_typst_with_eval(input)),
+                try_latex_to_typst(input).ok(),
+            ];
+            for fragment in fragments {
+                assert!(fragment.is_some(), "panicked on {:?}", input);
+            }
+        }
+
+        // A preamble without a body keeps its settings
+        let document = latex_document_to_typst(DEGENERATE_LATEX[6]);
+        assert!(document.contains("#set text(lang: \"de\")"), "{}", document);
     }
 
     #[test]
-    fn test_long_expression() {
-        let long_expr = r"\sum_{i=1}^{100} \frac{1}{i^2} = \frac{\pi^2}{6}";
-        let result = latex_to_typst(long_expr);
-        assert!(!result.contains("Error"));
+    fn test_mutated_typst_does_not_panic() {
+        assert_no_panics(
+            include_str!("fixtures/seeds.typ"),
+            TYPST_TOKENS,
+            &[try_typst_to_latex, try_typst_document_to_latex],
+        );
     }
 }
 
@@ -1361,6 +5314,94 @@ mod options {
         assert!(result.contains("\\author{Author Name}"));
         assert!(result.contains("\\maketitle"));
     }
+
+    fn collect_progress() -> (
+        tylax::ProgressCallback,
+        std::sync::Arc<std::sync::Mutex<Vec<tylax::Progress>>>,
+    ) {
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let callback =
+            tylax::ProgressCallback::new(move |progress| sink.lock().unwrap().push(progress));
+        (callback, reports)
+    }
+
+    #[test]
+    fn test_l2t_progress_reports_phases_and_blocks() {
+        use tylax::Phase;
+
+        let (callback, reports) = collect_progress();
+        let options = L2TOptions {
+            progress: Some(callback),
+            ..Default::default()
+        };
+        let latex = "\\documentclass{article}\n\\begin{document}\n\\section{One}\nFirst.\n\n\\section{Two}\nSecond.\n\\end{document}\n";
+        let plain = latex_document_to_typst(latex);
+        assert_eq!(latex_document_to_typst_with_options(latex, &options), plain);
+
+        let reports = reports.lock().unwrap();
+        let phases: Vec<Phase> = reports.iter().map(|p| p.phase).collect();
+        let first = |phase| phases.iter().position(|p| *p == phase).unwrap();
+        assert!(first(Phase::MacroExpansion) < first(Phase::Parsing));
+        assert!(first(Phase::Parsing) < first(Phase::Conversion));
+        assert!(first(Phase::Conversion) < first(Phase::PostProcessing));
+
+        let conversion: Vec<_> = reports
+            .iter()
+            .filter(|p| p.phase == Phase::Conversion)
+            .collect();
+        assert!(conversion.last().unwrap().current_block >= 2);
+        assert!(conversion
+            .windows(2)
+            .all(|w| w[0].bytes_processed <= w[1].bytes_processed));
+        assert!(reports.iter().all(|p| p.bytes_processed <= p.bytes_total));
+        let last = reports.last().unwrap();
+        assert_eq!(last.phase, Phase::PostProcessing);
+        assert_eq!(last.bytes_processed, last.bytes_total);
+    }
+
+    #[test]
+    fn test_t2l_progress_reports_blocks() {
+        use tylax::Phase;
+
+        let (callback, reports) = collect_progress();
+        let mut options = T2LOptions::full_document();
+        options.progress = Some(callback);
+        let result =
+            typst_to_latex_with_diagnostics("= One\n\nFirst.\n\n= Two\n\nSecond.\n", &options);
+        assert!(result.output.contains("\\section{Two}"));
+
+        let reports = reports.lock().unwrap();
+        let blocks = reports
+            .iter()
+            .filter(|p| p.phase == Phase::Conversion)
+            .map(|p| p.current_block)
+            .max();
+        assert!(blocks >= Some(3));
+        assert!(reports.iter().all(|p| p.bytes_processed <= p.bytes_total));
+        assert_eq!(reports.last().unwrap().phase, Phase::PostProcessing);
+    }
+
+    #[test]
+    fn test_panicking_progress_callback_is_ignored() {
+        let callback = tylax::ProgressCallback::new(|_| panic!("progress bar went away"));
+        let options = L2TOptions {
+            progress: Some(callback.clone()),
+            ..Default::default()
+        };
+        let latex = "\\begin{document}\nSome \\textbf{text}.\n\\end{document}";
+        assert_eq!(
+            latex_document_to_typst_with_options(latex, &options),
+            latex_document_to_typst(latex)
+        );
+
+        let options = T2LOptions {
+            progress: Some(callback),
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_diagnostics("Some *text*.", &options);
+        assert!(result.output.contains("\\textbf{text}"));
+    }
 }
 
 // ============================================================================
@@ -1368,7 +5409,20 @@ mod options {
 // ============================================================================
 
 mod tikz_cetz {
-    use tylax::tikz::{convert_cetz_to_tikz, convert_tikz_to_cetz, is_cetz_code};
+    use tylax::tikz::{convert_cetz_to_tikz, convert_tikz_to_cetz, is_cetz_code, CetzVersion};
+    use tylax::{L2TOptions, LatexConverter};
+
+    #[test]
+    fn test_cetz_version_option() {
+        let input = "\\begin{document}\n\\begin{tikzpicture}\n\\draw (0,0) -- ($(0,0)!0.5!(2,2)$);\n\\end{tikzpicture}\n\\end{document}";
+        let output = LatexConverter::with_options(L2TOptions {
+            cetz_version: CetzVersion::V0_2,
+            ..L2TOptions::default()
+        })
+        .convert_document(input);
+        assert!(output.contains("@preview/cetz:0.2.2"), "{}", output);
+        assert!(output.contains("line((0, 0), (1, 1))"), "{}", output);
+    }
 
     #[test]
     fn test_tikz_line_to_cetz() {
@@ -1639,7 +5693,7 @@ mod regression {
 // ============================================================================
 
 mod color_tests {
-    use tylax::latex_document_to_typst;
+    use tylax::{latex_document_to_typst, typst_to_latex_with_options, T2LOptions};
 
     // Helper: wrap content in document environment for proper parsing
     fn wrap_doc(content: &str) -> String {
@@ -1739,6 +5793,44 @@ mod color_tests {
             "Should contain the text content"
         );
     }
+
+    #[test]
+    fn test_ulem_decorations_to_typst() {
+        let input = wrap_doc(r"\uline{a} \sout{b} \st{c} \overline{d} $\underline{x}$");
+        let result = latex_document_to_typst(&input);
+        assert!(result.contains("#underline[a]"), "got: {}", result);
+        assert!(result.contains("#strike[b] #strike[c]"), "got: {}", result);
+        assert!(result.contains("#overline[d]"), "got: {}", result);
+        assert!(result.contains("$underline(x)$"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_text_decorations_to_latex() {
+        let input = "#show link: underline\nA #strike[#underline[both]] and #highlight[$x^2$ key] and #overline[top].\n#underline[one \\ two] #link(\"https://x.org\")[site]";
+        let result = typst_to_latex_with_options(input, &T2LOptions::full_document());
+        assert!(
+            result.contains(r"\sout{\underline{both}}"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains(r"\hl{$x^2$ key}"), "got: {}", result);
+        assert!(
+            result.contains(r"$\overline{\text{top}}$"),
+            "got: {}",
+            result
+        );
+        assert!(result.contains(r"\uline{one \\"), "got: {}", result);
+        assert!(
+            result.contains(r"\underline{\href{https://x.org}{site}}"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains("\\usepackage[normalem]{ulem}\n\\usepackage{soul}"),
+            "got: {}",
+            result
+        );
+    }
 }
 
 // ============================================================================
@@ -1810,6 +5902,20 @@ This is a test.
         );
     }
 
+    /// Test package-style delimited definitions next to ordinary ones
+    #[test]
+    fn test_delimited_args_package_style() {
+        let input = r"\def\ie{i.e.\xspace}\def\pair(#1,#2){\langle #1, #2\rangle}\def\until#1\stop{[#1]}\ie $\pair(\frac{a}{b},{x,y})$ \until one {two}\stop";
+        let result = expand_latex(input);
+        assert!(
+            result.contains(r"\langle \frac{a}{b}, x,y\rangle"),
+            "Got: {}",
+            result
+        );
+        assert!(result.contains("[one {two}]"), "Got: {}", result);
+        assert!(result.contains("i.e."), "Got: {}", result);
+    }
+
     /// Test DeferredParam (##) for nested macro definitions
     #[test]
     fn test_deferred_param() {
@@ -2089,6 +6195,22 @@ $$\pair{a^2}{\frac{\pi}{2}}$$
             result
         );
     }
+
+    #[test]
+    fn test_preamble_hooks_do_not_leak() {
+        let input = r"\documentclass{article}
+\newcommand{\greeting}{Hello}
+\AtBeginDocument{\renewcommand{\greeting}{Welcome}}
+\appto\greeting{ there}
+\begin{document}
+\greeting.
+\end{document}
+";
+        let result = latex_document_to_typst(input);
+        assert!(!result.contains("AtBeginDocument"), "Got: {}", result);
+        assert!(!result.contains("appto"), "Got: {}", result);
+        assert!(result.contains("Welcome."), "Got: {}", result);
+    }
 }
 
 // ============================================================================
@@ -2303,6 +6425,17 @@ mod warning_system {
         assert!(result.output.contains("Hello"));
     }
 
+    #[test]
+    fn test_thispagestyle_after_page_break_warns() {
+        // Typst cannot scope a page style to a single later page
+        let result = latex_to_typst_with_diagnostics(
+            r"\documentclass{article}\begin{document}A\newpage\thispagestyle{empty}B\end{document}",
+        );
+        assert!(result.warnings.iter().any(|w| {
+            w.kind == WarningKind::UnsupportedMacro && w.message.contains("thispagestyle")
+        }));
+    }
+
     #[test]
     fn test_explsyntax_block_skipped() {
         // ExplSyntaxOn block should be skipped with warning
@@ -2397,6 +6530,173 @@ Some text
             assert!(s.is_ascii() || !s.is_empty());
         }
     }
+
+    #[test]
+    fn test_length_registers_resolve_in_spacing() {
+        let input = r"
+\documentclass{article}
+\newlength{\mylen}
+\newlength\other
+\setlength{\mylen}{2cm}
+\settowidth{\other}{Hello}
+\begin{document}
+A\hspace{\mylen}B\hspace{0.5\mylen}C\vspace{\other}D
+\end{document}
+";
+        let result = latex_to_typst_with_diagnostics(input);
+
+        assert!(result.output.contains("#h(2cm)"), "{}", result.output);
+        assert!(result.output.contains("#h(0.5 * 2cm)"), "{}", result.output);
+        assert!(result.output.contains("#v(2.5em)"), "{}", result.output);
+        assert!(!result.output.contains("newlength"), "{}", result.output);
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.kind == WarningKind::Approximation),
+            "settowidth should warn about the approximation: {:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_unresolved_length_falls_back_to_em() {
+        let result = latex_to_typst_with_diagnostics(
+            r"\documentclass{article}\begin{document}A\hspace{\unknownlen}B\end{document}",
+        );
+
+        assert!(result.output.contains("#h(1em)"), "{}", result.output);
+        assert!(!result.output.contains("\\unknownlen"), "{}", result.output);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Approximation
+                && w.location.as_deref() == Some("\\unknownlen")));
+    }
+
+    #[test]
+    fn test_document_metadata_prefers_hypersetup() {
+        let result = latex_to_typst_with_diagnostics(
+            r"\documentclass{article}
+\hypersetup{pdftitle={PDF Title}, pdfauthor={Ann Lee, Bob Roe}, pdfkeywords={typst, latex}}
+\title{Visible Title}
+\author{Ann Lee \and Bob Roe}
+\begin{document}
+\maketitle
+Body.
+\end{document}",
+        );
+
+        assert!(
+            result.output.contains("  title: \"PDF Title\",\n"),
+            "{}",
+            result.output
+        );
+        assert!(
+            result
+                .output
+                .contains("  author: (\"Ann Lee\", \"Bob Roe\"),\n"),
+            "{}",
+            result.output
+        );
+        assert!(
+            result
+                .output
+                .contains("  keywords: (\"typst\", \"latex\"),\n"),
+            "{}",
+            result.output
+        );
+        // The visible title block keeps \title
+        assert!(
+            result.output.contains("[Visible Title]"),
+            "{}",
+            result.output
+        );
+        let conflicts: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == WarningKind::MetadataConflict)
+            .collect();
+        assert_eq!(conflicts.len(), 1, "{:?}", result.warnings);
+        assert!(conflicts[0].message.contains("pdftitle"));
+    }
+
+    #[test]
+    fn test_unknown_environment_policies() {
+        use tylax::core::latex2typst::{
+            latex_to_typst_with_diagnostics_options, L2TOptions, UnknownEnvironmentPolicy,
+        };
+
+        let input = "\\documentclass{article}\n\\begin{document}\nBefore\n\n\\begin{myenv}\nInside \\textbf{bold}\n\\end{myenv}\nAfter\n\\end{document}\n";
+
+        let result = latex_to_typst_with_diagnostics(input);
+        assert!(result.output.contains("*bold*"), "{}", result.output);
+        assert!(!result.output.contains("myenv"), "{}", result.output);
+        let warning = result
+            .warnings
+            .iter()
+            .find(|w| w.kind == WarningKind::UnknownEnvironment)
+            .expect("unknown environment warning");
+        assert!(warning.message.contains("myenv"));
+        assert_eq!(warning.location.as_deref(), Some("\\begin{myenv} (line 5)"));
+
+        let options = L2TOptions {
+            unknown_environment_policy: UnknownEnvironmentPolicy::RawBlock,
+            ..Default::default()
+        };
+        let result = latex_to_typst_with_diagnostics_options(input, options);
+        assert!(
+            result
+                .output
+                .contains("```latex\n\\begin{myenv}\nInside \\textbf{bold}\n\\end{myenv}\n```"),
+            "{}",
+            result.output
+        );
+
+        let options = L2TOptions {
+            unknown_environment_policy: UnknownEnvironmentPolicy::Drop,
+            ..Default::default()
+        };
+        let result = latex_to_typst_with_diagnostics_options(input, options);
+        assert!(!result.output.contains("bold"), "{}", result.output);
+        assert!(result.output.contains("After"), "{}", result.output);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::UnknownEnvironment));
+    }
+
+    #[test]
+    fn test_unsupported_typst_rules_warn() {
+        use tylax::core::latex2typst::UnknownEnvironmentPolicy;
+        use tylax::core::typst2latex::{typst_to_latex_with_diagnostics, T2LOptions};
+
+        let input = "#set text(font: \"Libertinus\")\n#set par(leading: 1em)\n#show link: underline\nText\n";
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::default());
+        let rules: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.kind == tylax::core::typst2latex::WarningKind::UnsupportedRule)
+            .collect();
+        assert_eq!(rules.len(), 1, "{:?}", result.warnings);
+        assert!(rules[0].message.contains("#set text"));
+        assert_eq!(rules[0].span.map(|s| (s.start, s.end)), Some((1, 29)));
+        assert!(!result.output.contains("Libertinus"), "{}", result.output);
+
+        let options = T2LOptions {
+            unknown_rule_policy: UnknownEnvironmentPolicy::RawBlock,
+            ..Default::default()
+        };
+        let result = typst_to_latex_with_diagnostics(input, &options);
+        assert!(
+            result
+                .output
+                .contains("% #set text(font: \"Libertinus\")\n"),
+            "{}",
+            result.output
+        );
+        assert!(result.output.contains("Text"), "{}", result.output);
+    }
 }
 
 // ============================================================================
@@ -2626,7 +6926,7 @@ mod t2l_lr_delimiters {
     fn test_lr_size_percent_uses_fixed_delimiters() {
         let result = typst_to_latex("$lr({a_n}, size: #200%)$");
         assert!(
-            result.contains("\\bigg\\{") && result.contains("\\bigg\\}"),
+            result.contains("\\biggl\\{") && result.contains("\\biggr\\}"),
             "size: #200% should map to fixed-size braces, got: {}",
             result
         );
@@ -2646,8 +6946,8 @@ mod t2l_lr_delimiters {
     fn test_lr_size_small_percent_uses_bigl() {
         let result = typst_to_latex("$lr((x+y), size: #120%)$");
         assert!(
-            result.contains("\\big(") && result.contains("\\big)"),
-            "size: #120% should map to \\big...\\big, got: {}",
+            result.contains("\\bigl(") && result.contains("\\bigr)"),
+            "size: #120% should map to \\bigl...\\bigr, got: {}",
             result
         );
         assert!(
@@ -2689,7 +6989,7 @@ mod t2l_lr_delimiters {
     fn test_lr_size_with_empty_delimiter_dot() {
         let result = typst_to_latex("$lr(., x, size: #200%)$");
         assert!(
-            result.contains("\\bigg."),
+            result.contains("\\biggl."),
             "Dot delimiter should remain valid in fixed-size mode, got: {}",
             result
         );
@@ -2753,7 +7053,7 @@ mod t2l_named_args {
         let result =
             typst_to_latex_with_options("#rotate(angle: 90deg)[Hi]", &T2LOptions::default());
         assert!(
-            result.contains("\\rotatebox{90}"),
+            result.contains("\\rotatebox{-90}"),
             "rotate angle should be preserved, got: {}",
             result
         );
@@ -2987,6 +7287,48 @@ mod t2l_citation_refs {
 mod l2t_citation_refs {
     use super::*;
 
+    #[test]
+    fn test_manual_bibliography_entries_are_citable() {
+        let result = latex_document_to_typst(
+            r#"\documentclass{article}
+\begin{document}
+See \cite{knuth}, \cite[p.~5]{knuth,lamport94} and \cite{external}.
+\begin{thebibliography}{99}
+\bibitem{knuth} Knuth, D. \newblock \emph{The TeXbook}. \newblock Addison-Wesley, 1984.
+\bibitem[Lam94]{lamport94} Lamport, L. \newblock LaTeX.
+\end{thebibliography}
+\end{document}"#,
+        );
+        assert!(
+            result.contains("#heading(numbering: none)[References]"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains(r#"numbering: "1")[Knuth, D."#)
+                && result.contains("Addison-Wesley, 1984.] <knuth>"),
+            "got: {}",
+            result
+        );
+        assert!(
+            result.contains(r#"numbering: _ => "Lam94")[Lamport, L."#)
+                && result.contains("<lamport94>"),
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("newblock"), "got: {}", result);
+        assert!(!result.contains("Lam94lamport94"), "got: {}", result);
+        assert!(result.contains(r"\[#ref(<knuth>)\]"), "got: {}", result);
+        assert!(
+            result.contains(r"\[#ref(<knuth>), #ref(<lamport94>), p.~5\]")
+                || result.contains(r"\[#ref(<knuth>), #ref(<lamport94>), p. 5\]"),
+            "got: {}",
+            result
+        );
+        // Keys without a \bibitem still go to the bibliography file
+        assert!(result.contains("#cite(<external>)"), "got: {}", result);
+    }
+
     #[test]
     fn test_l2t_citation_variants() {
         assert_eq!(
@@ -3044,6 +7386,27 @@ mod l2t_citation_refs {
             pageref
         );
     }
+
+    #[test]
+    fn test_l2t_cleveref_variants() {
+        assert_eq!(
+            latex_to_typst(r#"\cref{eq:1,fig:2}"#).trim(),
+            "@eq-1 and @fig-2"
+        );
+        assert_eq!(
+            latex_to_typst(r#"\Cref{sec:intro}"#).trim(),
+            "#ref(<sec-intro>, supplement: [Section])"
+        );
+        assert_eq!(
+            latex_to_typst(r#"\crefrange{eq:a}{eq:c}"#).trim(),
+            "@eq-a–@eq-c"
+        );
+        assert_eq!(
+            latex_to_typst(r#"\labelcref{fig:x}"#).trim(),
+            "#ref(<fig-x>, supplement: none)"
+        );
+        assert_eq!(latex_to_typst(r#"\nameCref{fig:x}"#).trim(), "Figure");
+    }
 }
 
 // ============================================================================
@@ -3472,7 +7835,7 @@ mod t2l_escaped_punctuation {
             result
         );
         assert!(
-            result.contains("A_{i \\\n") && result.contains("j}"),
+            result.contains("A_{i \\\\\n") && result.contains("j}"),
             "non-limits multiline subscript should still emit a plain multiline brace group, got: {}",
             result
         );
@@ -4048,6 +8411,71 @@ mod t2l_math_structured_ir {
     }
 }
 
+// ============================================================================
+// siunitx Tests - LaTeX to Typst
+// ============================================================================
+
+mod siunitx_package {
+    use super::*;
+
+    #[test]
+    fn test_si_and_num() {
+        let result = latex_to_typst(r"\SI{10}{\metre\per\second} and \num{1.23e4}");
+        assert!(result.contains(r#"$10 space "m/s"$"#), "got: {}", result);
+        assert!(result.contains("$1.23 times 10^4$"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_declare_si_unit() {
+        let latex = r"\documentclass{article}
+\DeclareSIUnit{\clight}{c}
+\DeclareSIUnit\rpm{rpm}
+\begin{document}
+\SI{3}{\clight} at \SI{2}{\kilo\rpm}
+\end{document}";
+        let result = latex_document_to_typst(latex);
+        assert!(result.contains(r#"$3 space "c"$"#), "got: {}", result);
+        assert!(result.contains(r#"$2 space "krpm"$"#), "got: {}", result);
+        assert!(!result.contains("DeclareSIUnit"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_ranges_follow_range_units() {
+        let result = latex_to_typst(r"\SIrange{10}{20}{\celsius}");
+        assert!(
+            result.contains(r#"$10 space "°C" dash.en 20 space "°C"$"#),
+            "got: {}",
+            result
+        );
+
+        let result = latex_to_typst(r"\SIrange[range-units=single]{10}{20}{\celsius}");
+        assert!(
+            result.contains(r#"$10 dash.en 20 space "°C"$"#),
+            "got: {}",
+            result
+        );
+
+        let result = latex_to_typst(r"\sisetup{range-units=brackets}\qtyrange{1}{2}{\metre}");
+        assert!(
+            result.contains(r#"$(1 dash.en 2) space "m"$"#),
+            "got: {}",
+            result
+        );
+
+        let result = latex_to_typst(r"\numrange{1}{5}");
+        assert!(result.contains("$1 dash.en 5$"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_ang_and_grouping() {
+        let result = latex_to_typst(r"\ang{30;15;}");
+        assert!(result.contains("$30°15′$"), "got: {}", result);
+
+        let result = latex_to_typst(r"\num[group-separator={,}]{1234567}");
+        assert!(result.contains(r#"$"1,234,567"$"#), "got: {}", result);
+    }
+}
+
 // ============================================================================
 // Physics Package Tests - LaTeX to Typst
 // ============================================================================
@@ -4055,6 +8483,56 @@ mod t2l_math_structured_ir {
 mod physics_package {
     use super::*;
 
+    #[test]
+    fn test_braket_with_bar() {
+        let result = latex_to_typst(r"\braket{\phi|\psi}");
+        assert!(
+            result.contains("lr(angle.l phi.alt | psi angle.r)"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_trailing_tokens_are_not_arguments() {
+        for (input, expected) in [
+            (
+                r"\braket{\phi|\psi} + 1",
+                "lr(angle.l phi.alt | psi angle.r) + 1",
+            ),
+            (r"\braket{a}{b} + 1", "lr(angle.l a | b angle.r) + 1"),
+            (r"\braket{a} + 1", "lr(angle.l a | a angle.r) + 1"),
+            (r"\pdv[2]{f}{x} + 1", "frac(diff^2 f, diff x^2) + 1"),
+            (r"\pdv{f}{x}{y} - 2", "frac(diff^2 f, diff x diff y) - 2"),
+            (r"\dv{f}{x} + 1", "frac(dif f, dif x) + 1"),
+            (r"\ev{X} + 1", "lr(angle.l X angle.r) + 1"),
+            (r"\ev{X}{\psi} = 0", "lr(angle.l psi | X | psi angle.r) = 0"),
+            (r"\dyad{a} + b", "lr(| a angle.r) lr(angle.l a |) + b"),
+        ] {
+            let result = latex_to_typst(input);
+            assert!(result.contains(expected), "{}: got {}", input, result);
+        }
+    }
+
+    #[test]
+    fn test_abs_norm_to_latex() {
+        let input = "$abs(x) + norm(v)$";
+        let result = typst_to_latex_with_options(input, &T2LOptions::default());
+        assert!(
+            result.contains(r"\left\lvert x\right\rvert + \left\lVert v\right\rVert"),
+            "got: {}",
+            result
+        );
+
+        let options = T2LOptions {
+            use_physics_package: true,
+            ..T2LOptions::full_document()
+        };
+        let result = typst_to_latex_with_options(input, &options);
+        assert!(result.contains(r"\abs{x} + \norm{v}"), "got: {}", result);
+        assert!(result.contains(r"\usepackage{physics}"), "got: {}", result);
+    }
+
     // --- Automatic bracing ---
 
     #[test]
@@ -4900,6 +9378,7 @@ mod preamble_customization {
 \title{Sample}
 \author{Alice}
 \begin{document}
+\maketitle
 \section{Intro}
 Hello.
 \end{document}"
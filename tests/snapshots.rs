@@ -0,0 +1,85 @@
+//! Snapshot tests over the golden corpus in `tests/corpus`
+//!
+//! Each `.tex` document is converted to Typst and each `.typ` document to
+//! LaTeX; the output and warnings are compared with `tests/corpus/snapshots`.
+//! Set `TYLAX_UPDATE_SNAPSHOTS=1` to accept the current output.
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tylax::{latex_to_typst_with_diagnostics, typst_to_latex_with_diagnostics, T2LOptions};
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// Corpus documents with the given extension, sorted by name
+fn corpus_files(extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(corpus_dir())
+        .expect("tests/corpus is missing")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Snapshot every document of the corpus with one extension, reporting all
+/// mismatches at once
+fn check_corpus(extension: &str, direction: &str, convert: fn(&str) -> (String, Vec<String>)) {
+    let files = corpus_files(extension);
+    assert!(
+        !files.is_empty(),
+        "no .{} documents in the corpus",
+        extension
+    );
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let input = fs::read_to_string(path).expect("unreadable corpus document");
+        let (output, warnings) = convert(&input);
+        let snapshot = support::render_snapshot(&name, direction, &output, &warnings);
+        let snapshot_path = corpus_dir()
+            .join("snapshots")
+            .join(format!("{}.snap", name));
+        if let Err(message) = support::check_snapshot(&snapshot_path, &snapshot) {
+            failures.push(message);
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} snapshots failed (set {}=1 to update them):\n\n{}",
+        failures.len(),
+        files.len(),
+        support::UPDATE_ENV,
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn latex_corpus_snapshots() {
+    check_corpus("tex", "latex -> typst", |input| {
+        let result = latex_to_typst_with_diagnostics(input);
+        let warnings = result.warnings.iter().map(|w| w.to_string()).collect();
+        (result.output, warnings)
+    });
+}
+
+#[test]
+fn typst_corpus_snapshots() {
+    check_corpus("typ", "typst -> latex", |input| {
+        let result = typst_to_latex_with_diagnostics(input, &T2LOptions::full_document());
+        let warnings = result.warnings.iter().map(|w| w.to_string()).collect();
+        (result.output, warnings)
+    });
+}
+
+#[test]
+fn normalize_hides_version_and_date() {
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d");
+    let output = format!("% tylax {} on {}\r\n", env!("CARGO_PKG_VERSION"), today);
+    assert_eq!(support::normalize(&output), "% tylax [VERSION] on [DATE]\n");
+}
@@ -0,0 +1,77 @@
+//! Shared helpers for the snapshot tests
+//!
+//! Snapshots hold the converted output of a corpus document followed by the
+//! warnings of the conversion. Run the tests with `TYLAX_UPDATE_SNAPSHOTS=1`
+//! to write the current output instead of comparing against it.
+
+use std::fs;
+use std::path::Path;
+
+use pretty_assertions::StrComparison;
+
+/// Environment variable that switches the snapshot tests to update mode
+pub const UPDATE_ENV: &str = "TYLAX_UPDATE_SNAPSHOTS";
+
+/// Whether snapshots should be rewritten instead of compared
+pub fn update_mode() -> bool {
+    std::env::var(UPDATE_ENV).is_ok_and(|value| value != "0" && !value.is_empty())
+}
+
+/// Replace the parts of an output that change between runs: the crate
+/// version and today's date
+pub fn normalize(output: &str) -> String {
+    let today = chrono::Local::now().date_naive();
+    let mut normalized = output
+        .replace("\r\n", "\n")
+        .replace(env!("CARGO_PKG_VERSION"), "[VERSION]");
+    for format in ["%Y-%m-%d", "%B %-d, %Y", "%-d %B %Y"] {
+        normalized = normalized.replace(&today.format(format).to_string(), "[DATE]");
+    }
+    normalized
+}
+
+/// Lay out a snapshot: a header naming the source, the output, then one
+/// line per warning
+pub fn render_snapshot(source: &str, direction: &str, output: &str, warnings: &[String]) -> String {
+    let mut snapshot = format!("source: {}\ndirection: {}\n---\n", source, direction);
+    snapshot.push_str(normalize(output).trim_end());
+    snapshot.push_str("\n--- warnings ---\n");
+    if warnings.is_empty() {
+        snapshot.push_str("(none)\n");
+    }
+    for warning in warnings {
+        snapshot.push_str(&normalize(warning));
+        snapshot.push('\n');
+    }
+    snapshot
+}
+
+/// Compare `actual` with the snapshot stored at `path`, or store it in update
+/// mode. Returns a readable diff when they differ.
+pub fn check_snapshot(path: &Path, actual: &str) -> Result<(), String> {
+    if update_mode() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        return fs::write(path, actual).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected.replace("\r\n", "\n"),
+        Err(_) => {
+            return Err(format!(
+                "missing snapshot {} (run with {}=1 to create it)",
+                path.display(),
+                UPDATE_ENV
+            ))
+        }
+    };
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "snapshot {} differs (< stored, > current):\n{}",
+            path.display(),
+            StrComparison::new(&expected, actual)
+        ))
+    }
+}
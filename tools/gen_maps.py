@@ -196,10 +196,14 @@ COMMANDS_WITH_ARGS = {
     "textcolor": 2, "colorbox": 2, "color": 1,
     
     # Links (1-2 args)
-    "url": 1, "href": 2,
+    "url": 1, "href": 2, "hypersetup": 1,
     
-    # References (1 arg)
+    # References
     "ref": 1, "eqref": 1, "autoref": 1, "pageref": 1, "cref": 1, "Cref": 1,
+    "labelcref": 1, "namecref": 1, "nameCref": 1, "crefrange": 2, "Crefrange": 2,
+    
+    # \item[label] takes only an optional argument; its spec is written by
+    # hand in maps.rs
     
     # Footnote (1 arg)
     "footnote": 1,
@@ -213,6 +217,12 @@ COMMANDS_WITH_ARGS = {
     "Acs": 1, "Acl": 1, "Acf": 1,
     "newacronym": 3, "newglossaryentry": 2,
     
+    # Length registers
+    "newlength": 1, "setlength": 2, "addtolength": 2, "settowidth": 2,
+
+    # Line spacing
+    "linespread": 1, "setstretch": 1,
+    
     # Special cite command
     "typstcite": 1,
 }
@@ -321,6 +331,13 @@ def generate_rust_code():
     lines.append('            ctx_feature: mitex_spec::ContextFeature::None,')
     lines.append('            alias: None,')
     lines.append('        }));')
+
+    # Add setspace spacing environment (one argument)
+    lines.append('        m.insert("spacing".to_string(), CommandSpecItem::Env(mitex_spec::EnvShape {')
+    lines.append('            args: ArgPattern::FixedLenTerm { len: 1 },')
+    lines.append('            ctx_feature: mitex_spec::ContextFeature::None,')
+    lines.append('            alias: None,')
+    lines.append('        }));')
     
     lines.append('')
     lines.append('        // Commands with required arguments')